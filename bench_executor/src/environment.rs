@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use bollard::service::InspectServiceOptions;
+use bollard::Docker;
+use log::warn;
+use serde::Serialize;
+use sysinfo::{CpuExt, System, SystemExt};
+
+pub const SERVICES: [&str; 3] = [
+    "bench_system_monitor",
+    "bench_system_cloud_server",
+    "bench_system_sensor",
+];
+
+/// Environment the runs of a sweep are executed in, to tie their results to it
+#[derive(Serialize)]
+pub struct Environment {
+    /// Images of the docker services, by service name
+    pub images: BTreeMap<String, Image>,
+    pub rustc_version: Option<String>,
+    pub host: Host,
+}
+
+#[derive(Serialize)]
+pub struct Image {
+    pub name: String,
+    pub id: Option<String>,
+    pub repo_digests: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Host {
+    pub host_name: Option<String>,
+    pub kernel_version: Option<String>,
+    pub cpu_brand: String,
+    pub cpu_count: usize,
+    pub physical_core_count: Option<usize>,
+    pub total_memory_bytes: u64,
+}
+
+pub async fn capture_environment(docker: &Docker) -> Environment {
+    let mut images = BTreeMap::new();
+    for service in SERVICES {
+        match get_image(docker, service).await {
+            Some(image) => {
+                images.insert(service.to_string(), image);
+            }
+            None => warn!("Could not capture the image of {service}"),
+        }
+    }
+    Environment {
+        images,
+        rustc_version: get_rustc_version(),
+        host: get_host(),
+    }
+}
+
+async fn get_image(docker: &Docker, service: &str) -> Option<Image> {
+    let name = docker
+        .inspect_service(service, None::<InspectServiceOptions>)
+        .await
+        .ok()?
+        .spec?
+        .task_template?
+        .container_spec?
+        .image?;
+    let image = docker.inspect_image(&name).await.ok()?;
+    Some(Image {
+        name,
+        id: image.id,
+        repo_digests: image.repo_digests.unwrap_or_default(),
+    })
+}
+
+/// Version of the compiler the test driver is built with, as it is run through cargo
+fn get_rustc_version() -> Option<String> {
+    let output = Command::new("rustc").arg("--version").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn get_host() -> Host {
+    let system = System::new_all();
+    Host {
+        host_name: system.host_name(),
+        kernel_version: system.kernel_version(),
+        cpu_brand: system
+            .cpus()
+            .first()
+            .map(|cpu| cpu.brand().to_string())
+            .unwrap_or_default(),
+        cpu_count: system.cpus().len(),
+        physical_core_count: system.physical_core_count(),
+        total_memory_bytes: system.total_memory(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    /// Keys of the JSON object, sorted
+    fn get_keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .expect("Value should be an object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn environment_serializes_with_the_expected_keys() {
+        let environment = Environment {
+            images: BTreeMap::from([(
+                SERVICES[0].to_string(),
+                Image {
+                    name: "motor_monitor:latest".to_string(),
+                    id: Some("sha256:0123".to_string()),
+                    repo_digests: vec!["motor_monitor@sha256:4567".to_string()],
+                },
+            )]),
+            rustc_version: Some("rustc 1.72.0".to_string()),
+            host: get_host(),
+        };
+        let value = serde_json::to_value(&environment).expect("Could not serialize environment");
+        assert_eq!(get_keys(&value), ["host", "images", "rustc_version"]);
+        assert_eq!(get_keys(&value["images"]), [SERVICES[0]]);
+        assert_eq!(
+            get_keys(&value["images"][SERVICES[0]]),
+            ["id", "name", "repo_digests"]
+        );
+        assert_eq!(
+            get_keys(&value["host"]),
+            [
+                "cpu_brand",
+                "cpu_count",
+                "host_name",
+                "kernel_version",
+                "physical_core_count",
+                "total_memory_bytes"
+            ]
+        );
+        assert!(value["host"]["cpu_count"].as_u64().unwrap() > 0);
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fmt::Formatter;
+use std::thread;
+use std::time::Duration;
+
+use bollard::service::{InspectServiceOptions, UpdateServiceOptions};
+use bollard::task::ListTasksOptions;
+use bollard::Docker;
+use log::info;
+
+use crate::environment::SERVICES;
+
+const DIGEST_SEPARATOR: char = '@';
+
+/// Service whose running image differs from the digest it was pinned to at the start of the sweep
+pub struct ImageDrift {
+    pub service: String,
+    pub pinned_image: String,
+    pub running_images: BTreeSet<String>,
+}
+
+impl fmt::Display for ImageDrift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} was pinned to {}, but runs {:?}",
+            self.service, self.pinned_image, self.running_images
+        )
+    }
+}
+
+/// Resolves the images of the docker services to their digests, and updates the services which
+/// reference their image by tag only to reference it by digest, so that the swarm cannot pull a
+/// newer image during the sweep.
+/// Waits until the tasks of the services were updated as well, and returns the pinned images, by service name.
+pub async fn pin_image_digests(docker: &Docker) -> BTreeMap<String, String> {
+    let mut pinned_images = BTreeMap::new();
+    for service_name in SERVICES {
+        let mut service = docker
+            .inspect_service(service_name, None::<InspectServiceOptions>)
+            .await
+            .unwrap_or_else(|e| panic!("Could not inspect service {service_name}: {e}"));
+        let container_spec = service
+            .spec
+            .as_mut()
+            .and_then(|spec| spec.task_template.as_mut())
+            .and_then(|task_template| task_template.container_spec.as_mut())
+            .unwrap_or_else(|| panic!("Could not get container spec of {service_name}"));
+        let image = container_spec
+            .image
+            .clone()
+            .unwrap_or_else(|| panic!("Could not get image of {service_name}"));
+        if image.contains(DIGEST_SEPARATOR) {
+            pinned_images.insert(service_name.to_string(), image);
+            continue;
+        }
+        let digest = resolve_digest(docker, &image).await.unwrap_or_else(|| {
+            panic!("Could not resolve digest of image {image} of {service_name}")
+        });
+        let pinned_image = format!("{image}{DIGEST_SEPARATOR}{digest}");
+        info!("Pinning {service_name} to {pinned_image}");
+        container_spec.image = Some(pinned_image.clone());
+        let options = UpdateServiceOptions {
+            version: service
+                .version
+                .as_ref()
+                .and_then(|version| version.index)
+                .expect("Could not get service version"),
+            ..Default::default()
+        };
+        docker
+            .update_service(service_name, service.spec.unwrap(), options, None)
+            .await
+            .unwrap_or_else(|e| panic!("Could not pin image of {service_name}: {e}"));
+        pinned_images.insert(service_name.to_string(), pinned_image);
+    }
+    while !find_image_drifts(&pinned_images, &get_running_images(docker).await).is_empty() {
+        info!("Waiting for the services to run the pinned images");
+        thread::sleep(Duration::from_secs(1));
+    }
+    pinned_images
+}
+
+/// Digest of the image in the repository it was pulled from, as the digest of the local image
+/// itself (its id) differs from the one a node pulling it by reference would get
+async fn resolve_digest(docker: &Docker, image: &str) -> Option<String> {
+    let repository = get_repository(image);
+    docker
+        .inspect_image(image)
+        .await
+        .ok()?
+        .repo_digests?
+        .iter()
+        .filter_map(|repo_digest| repo_digest.split_once(DIGEST_SEPARATOR))
+        .find(|(digest_repository, _)| *digest_repository == repository)
+        .map(|(_, digest)| digest.to_string())
+}
+
+/// Strips the tag of an image reference, keeping the port of a registry
+fn get_repository(image: &str) -> &str {
+    match image.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => image,
+    }
+}
+
+/// Images the docker services currently run, by service name: the one of the service spec and
+/// those of the running tasks, which may still differ from it during an update
+pub async fn get_running_images(docker: &Docker) -> BTreeMap<String, BTreeSet<String>> {
+    let mut running_images = BTreeMap::new();
+    for service_name in SERVICES {
+        let mut images = BTreeSet::new();
+        if let Some(image) = docker
+            .inspect_service(service_name, None::<InspectServiceOptions>)
+            .await
+            .ok()
+            .and_then(|service| service.spec?.task_template?.container_spec?.image)
+        {
+            images.insert(image);
+        }
+        let options = ListTasksOptions {
+            filters: HashMap::from([
+                ("service", vec![service_name]),
+                ("desired-state", vec!["running"]),
+            ]),
+        };
+        for task in docker.list_tasks(Some(options)).await.unwrap_or_default() {
+            if let Some(image) = task
+                .spec
+                .and_then(|spec| spec.container_spec)
+                .and_then(|container_spec| container_spec.image)
+            {
+                images.insert(image);
+            }
+        }
+        running_images.insert(service_name.to_string(), images);
+    }
+    running_images
+}
+
+pub fn find_image_drifts(
+    pinned_images: &BTreeMap<String, String>,
+    running_images: &BTreeMap<String, BTreeSet<String>>,
+) -> Vec<ImageDrift> {
+    pinned_images
+        .iter()
+        .filter_map(|(service, pinned_image)| {
+            let running_images = running_images.get(service).cloned().unwrap_or_default();
+            (running_images.len() != 1 || !running_images.contains(pinned_image)).then(|| {
+                ImageDrift {
+                    service: service.clone(),
+                    pinned_image: pinned_image.clone(),
+                    running_images,
+                }
+            })
+        })
+        .collect()
+}
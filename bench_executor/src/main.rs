@@ -1,9 +1,10 @@
 extern crate core;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::net::IpAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str::FromStr;
 use std::time::Duration;
@@ -15,10 +16,22 @@ use bollard::network::InspectNetworkOptions;
 use bollard::service::{InspectServiceOptions, UpdateServiceOptions};
 use bollard::{ClientVersion, Docker};
 use futures::FutureExt;
-use log::{info, warn};
-use serde::Deserialize;
+use log::{error, info, warn};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
 
-use data_transfer_objects::{NetworkConfig, RequestProcessingModel};
+use data_transfer_objects::{NetworkConfig, RequestProcessingModel, RetryPolicy};
+use utils::retry;
+use utils::retry::{Backoff, Retryable};
+
+use crate::environment::Environment;
+use crate::metrics::{MetricsPush, RunMetrics};
+
+mod environment;
+mod images;
+mod metrics;
 
 #[derive(Deserialize)]
 struct Config {
@@ -28,7 +41,61 @@ struct Config {
     durations: Vec<u64>,
     request_processing_models: Vec<RequestProcessingModel>,
     window_size_ms: Vec<u64>,
+    /// Numbers of readings per sensor of the count-based windows swept in addition to the
+    /// time-based ones
+    #[serde(default)]
+    window_counts: Vec<usize>,
     sensor_sampling_interval_ms: Vec<u32>,
+    #[serde(default)]
+    capture_environment: bool,
+    #[serde(default)]
+    pin_images: bool,
+    #[serde(default = "get_default_abort_on_image_drift")]
+    abort_on_image_drift: bool,
+    /// Checks whether the test driver exited once the duration of a run passed
+    #[serde(default = "get_default_test_driver_exit_retry")]
+    test_driver_exit_retry: RetryPolicy,
+    /// Checks whether the scaled sensor containers joined the network
+    #[serde(default = "RetryPolicy::patient_network")]
+    sensor_scaling_retry: RetryPolicy,
+    /// Pushes the summary of each run to InfluxDB or a Prometheus Pushgateway, if configured
+    #[serde(default)]
+    metrics_push: Option<MetricsPush>,
+}
+
+fn get_default_abort_on_image_drift() -> bool {
+    true
+}
+
+/// Once per second for 30 seconds
+fn get_default_test_driver_exit_retry() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 31,
+        initial_delay_ms: 1000,
+        multiplier: 1.0,
+        max_delay_ms: 1000,
+        jitter: 0.0,
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct ParameterSet {
+    duration: u64,
+    no_motor_groups: u16,
+    window_size_ms: u64,
+    window_sampling_interval: u64,
+    sensor_sampling_interval: u32,
+    /// Number of readings per sensor of a count-based window, which spans the window size if the
+    /// sensors miss no samples
+    window_count: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct SweepSummary {
+    seed: Option<u64>,
+    runs: Vec<String>,
+    environment: Option<Environment>,
+    pinned_images: Option<BTreeMap<String, String>>,
 }
 
 trait RAIIConfig {
@@ -38,7 +105,7 @@ trait RAIIConfig {
         sensor_addresses: Vec<IpAddr>,
     ) -> Self;
     fn update_sensor_addresses(&mut self, sensor_addresses: Vec<IpAddr>);
-    fn persist(&self);
+    fn persist(&self, run_directory: &Path);
 }
 
 impl RAIIConfig for NetworkConfig {
@@ -47,24 +114,21 @@ impl RAIIConfig for NetworkConfig {
         motor_monitor_socket_address: IpAddr,
         sensor_addresses: Vec<IpAddr>,
     ) -> NetworkConfig {
-        let network_config = NetworkConfig {
+        NetworkConfig {
             cloud_server_address: cloud_server_socket_address,
             motor_monitor_address: motor_monitor_socket_address,
             sensor_addresses,
-        };
-        network_config.persist();
-        network_config
+        }
     }
 
     fn update_sensor_addresses(&mut self, sensor_addresses: Vec<IpAddr>) {
         self.sensor_addresses = sensor_addresses;
-        self.persist();
     }
 
-    fn persist(&self) {
+    fn persist(&self, run_directory: &Path) {
         let config =
             toml::to_string(&self).expect("Could not create toml string from network config");
-        fs::write(Path::new("../network_config.toml"), config)
+        fs::write(run_directory.join(NETWORK_CONFIG_FILE_NAME), config)
             .expect("Could not write network config to file");
     }
 }
@@ -73,14 +137,23 @@ impl RAIIConfig for NetworkConfig {
 const CONFIG_PATH: &str = "resources/config-debug.toml";
 #[cfg(not(debug_assertions))]
 const CONFIG_PATH: &str = "resources/config-production.toml";
+/// Directory the test runs are executed in, one subdirectory per run
+const RUNS_DIRECTORY: &str = "runs";
+const NETWORK_CONFIG_FILE_NAME: &str = "network_config.toml";
 
 /// expects a running swarm w/ the stack deployed
 #[tokio::main]
 async fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::init();
     let config: Config =
         toml::from_str(&fs::read_to_string(CONFIG_PATH).expect("Could not read config file"))
             .expect("Could not parse config file");
+    for retry_policy in [&config.test_driver_exit_retry, &config.sensor_scaling_retry] {
+        retry_policy
+            .validate()
+            .unwrap_or_else(|e| panic!("Invalid retry policy in config file: {e}"));
+    }
     let docker = Docker::connect_with_unix(
         "/var/run/docker.sock",
         120,
@@ -90,85 +163,280 @@ async fn main() {
         },
     )
     .unwrap();
+    let seed = get_seed(&mut std::env::args());
+    let sweep = plan_sweep(&config, seed);
+    let pinned_images = match config.pin_images {
+        true => Some(images::pin_image_digests(&docker).await),
+        false => None,
+    };
+    let environment = match config.capture_environment {
+        true => Some(environment::capture_environment(&docker).await),
+        false => None,
+    };
+    persist_sweep_summary(seed, &sweep, environment, pinned_images.clone());
     let mut network_config = restart_system(&docker).await;
-    for outer_repetition in 1..=config.outer_repetitions {
-        for duration in &config.durations {
-            for no_motor_groups in &config.motor_groups_tcp {
-                for window_size_ms in &config.window_size_ms {
-                    // for window_sampling_interval in &config.window_sampling_interval_ms {
-                    let window_sampling_interval = window_size_ms;
-                    for sensor_sampling_interval in &config.sensor_sampling_interval_ms {
-                        // let window_sampling_interval = sensor_sampling_interval;
-                        // let window_size_ms = sensor_sampling_interval * 5;
-                        // for thread_pool_size in &config.thread_pool_sizes {
-                        if *sensor_sampling_interval as u64 > *window_size_ms
-                            || *window_sampling_interval > *window_size_ms
-                        {
-                            continue;
-                        }
-                        scale_service(*no_motor_groups, &docker, &mut network_config).await;
-                        for request_processing_model in &config.request_processing_models {
-                            let thread_pool_size = match request_processing_model {
-                                RequestProcessingModel::ReactiveStreaming => 10 * 40,
-                                RequestProcessingModel::ClientServer => no_motor_groups * 4 + 1,
-                                RequestProcessingModel::SpringQL => no_motor_groups * 12,
-                                RequestProcessingModel::ObjectOriented => no_motor_groups * 5,
-                            } as usize;
-                            let file_name_base = format!("{no_motor_groups}_{duration}_{window_size_ms}_{window_sampling_interval}_{sensor_sampling_interval}_{thread_pool_size}_{}", request_processing_model.to_string());
-                            let resource_usage_file_name = format!("{file_name_base}_ru.csv");
-                            let mut resource_usage_file = OpenOptions::new()
-                                .create(true)
-                                .append(true)
-                                .open(resource_usage_file_name.clone())
-                                .unwrap();
-                            let mut lines = fs::read_to_string(resource_usage_file_name)
-                                .unwrap()
-                                .lines()
-                                .count();
-                            if lines == 0 {
-                                writeln!(
-                                    resource_usage_file,
-                                    "id,utime,stime,cutime,cstime,vmhwm,vmpeak,load_average"
-                                )
-                                .unwrap();
-                                lines += 1;
-                            }
-                            if (lines - 1) >= (config.inner_repetitions * outer_repetition) as usize
-                            {
-                                continue;
-                            }
-                            for inner_repetition in
-                                (lines - 1)..(config.inner_repetitions * outer_repetition) as usize
-                            {
-                                info!("{inner_repetition} {no_motor_groups} {duration} {window_size_ms} {window_sampling_interval} {sensor_sampling_interval} {thread_pool_size} {}", request_processing_model.to_string());
-                                let results = execute_test_run(
-                                    *no_motor_groups,
-                                    *duration,
-                                    *window_size_ms,
-                                    *window_sampling_interval as u32,
-                                    *sensor_sampling_interval,
-                                    thread_pool_size,
-                                    *request_processing_model,
+    for (outer_repetition, parameter_sets) in (1..=config.outer_repetitions).zip(sweep) {
+        for (parameter_set, request_processing_models) in parameter_sets {
+            let ParameterSet {
+                duration,
+                no_motor_groups,
+                window_sampling_interval,
+                sensor_sampling_interval,
+                ..
+            } = parameter_set;
+            let window_size = get_window_size_token(&parameter_set);
+            scale_service(
+                no_motor_groups,
+                &docker,
+                &mut network_config,
+                config.sensor_scaling_retry,
+            )
+            .await;
+            for request_processing_model in request_processing_models {
+                let thread_pool_size =
+                    get_thread_pool_size(request_processing_model, no_motor_groups);
+                let file_name_base = get_file_name_base(&parameter_set, request_processing_model);
+                let resource_usage_file_name = format!("{file_name_base}_ru.csv");
+                let mut resource_usage_file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(resource_usage_file_name.clone())
+                    .unwrap();
+                let mut lines = fs::read_to_string(resource_usage_file_name)
+                    .unwrap()
+                    .lines()
+                    .count();
+                if lines == 0 {
+                    writeln!(
+                        resource_usage_file,
+                        "id,utime,stime,cutime,cstime,vmhwm,vmpeak,load_average,cpu_utilization"
+                    )
+                    .unwrap();
+                    lines += 1;
+                }
+                if (lines - 1) >= (config.inner_repetitions * outer_repetition) as usize {
+                    continue;
+                }
+                for inner_repetition in
+                    (lines - 1)..(config.inner_repetitions * outer_repetition) as usize
+                {
+                    check_image_drifts(&docker, &pinned_images, config.abort_on_image_drift).await;
+                    info!("{inner_repetition} {no_motor_groups} {duration} {window_size} {window_sampling_interval} {sensor_sampling_interval} {thread_pool_size} {}", request_processing_model.to_string());
+                    let results = execute_test_run(
+                        &parameter_set,
+                        thread_pool_size,
+                        request_processing_model,
+                        &config.test_driver_exit_retry,
+                        &network_config,
+                        &get_run_directory(&file_name_base, inner_repetition),
+                    );
+                    match results {
+                        Ok(results) => {
+                            let running_images = check_image_drifts(
+                                &docker,
+                                &pinned_images,
+                                config.abort_on_image_drift,
+                            )
+                            .await;
+                            if let Some(metrics_push) = &config.metrics_push {
+                                metrics::push_run_metrics(
+                                    metrics_push,
+                                    &parameter_set,
+                                    request_processing_model,
+                                    &RunMetrics::from_results(&results.0, &results.1, &results.2),
                                 );
-                                match results {
-                                    Ok(results) => {
-                                        write!(resource_usage_file, "{}", results.0).unwrap();
-                                        persist_alert_delays(&file_name_base, results.1);
-                                        persist_alert_failures(&file_name_base, results.2);
-                                    }
-                                    Err(_) => {
-                                        network_config = restart_system(&docker).await;
-                                    }
-                                }
                             }
+                            write!(resource_usage_file, "{}", results.0).unwrap();
+                            persist_alert_delays(&file_name_base, results.1);
+                            persist_alert_failures(&file_name_base, results.2);
+                            persist_alert_bytes(&file_name_base, results.3);
+                            persist_network_usage(&file_name_base, inner_repetition, &results.4);
+                            persist_images(&file_name_base, inner_repetition, &running_images);
+                        }
+                        Err(_) => {
+                            network_config = restart_system(&docker).await;
                         }
                     }
                 }
+            }
+        }
+    }
+}
+
+fn get_seed(args: &mut std::env::Args) -> Option<u64> {
+    args.skip_while(|arg| arg != "--seed")
+        .nth(1)
+        .map(|seed| seed.parse().expect("Could not parse seed"))
+}
+
+/// Creates the order in which the parameter sets (and the processing models per parameter set)
+/// are executed in each outer repetition.
+/// Without a seed, the order of the config file is kept, otherwise it is shuffled deterministically.
+fn plan_sweep(
+    config: &Config,
+    seed: Option<u64>,
+) -> Vec<Vec<(ParameterSet, Vec<RequestProcessingModel>)>> {
+    let mut rng = seed.map(SmallRng::seed_from_u64);
+    let mut parameter_sets = vec![];
+    for duration in &config.durations {
+        for no_motor_groups in &config.motor_groups_tcp {
+            for window_size_ms in &config.window_size_ms {
+                // for window_sampling_interval in &config.window_sampling_interval_ms {
+                let window_sampling_interval = window_size_ms;
+                for sensor_sampling_interval in &config.sensor_sampling_interval_ms {
+                    // let window_sampling_interval = sensor_sampling_interval;
+                    // let window_size_ms = sensor_sampling_interval * 5;
+                    // for thread_pool_size in &config.thread_pool_sizes {
+                    if *sensor_sampling_interval as u64 > *window_size_ms
+                        || *window_sampling_interval > *window_size_ms
+                    {
+                        continue;
+                    }
+                    parameter_sets.push(ParameterSet {
+                        duration: *duration,
+                        no_motor_groups: *no_motor_groups,
+                        window_size_ms: *window_size_ms,
+                        window_sampling_interval: *window_sampling_interval,
+                        sensor_sampling_interval: *sensor_sampling_interval,
+                        window_count: None,
+                    });
+                }
                 // }
                 // }
             }
+            // Sampled once per the time their readings take to arrive, like the time-based windows
+            // are once per their size
+            for window_count in &config.window_counts {
+                for sensor_sampling_interval in &config.sensor_sampling_interval_ms {
+                    let window_size_ms = *window_count as u64 * *sensor_sampling_interval as u64;
+                    parameter_sets.push(ParameterSet {
+                        duration: *duration,
+                        no_motor_groups: *no_motor_groups,
+                        window_size_ms,
+                        window_sampling_interval: window_size_ms,
+                        sensor_sampling_interval: *sensor_sampling_interval,
+                        window_count: Some(*window_count),
+                    });
+                }
+            }
         }
     }
+    (1..=config.outer_repetitions)
+        .map(|_| {
+            let mut repetition: Vec<(ParameterSet, Vec<RequestProcessingModel>)> = parameter_sets
+                .iter()
+                .map(|parameter_set| {
+                    let mut request_processing_models = config.request_processing_models.clone();
+                    // SpringQL only frames its windows by time
+                    if parameter_set.window_count.is_some() {
+                        request_processing_models.retain(|request_processing_model| {
+                            *request_processing_model != RequestProcessingModel::SpringQL
+                        });
+                    }
+                    (*parameter_set, request_processing_models)
+                })
+                .collect();
+            if let Some(rng) = rng.as_mut() {
+                repetition.shuffle(rng);
+                for (_, request_processing_models) in repetition.iter_mut() {
+                    request_processing_models.shuffle(rng);
+                }
+            }
+            repetition
+        })
+        .collect()
+}
+
+fn persist_sweep_summary(
+    seed: Option<u64>,
+    sweep: &[Vec<(ParameterSet, Vec<RequestProcessingModel>)>],
+    environment: Option<Environment>,
+    pinned_images: Option<BTreeMap<String, String>>,
+) {
+    let sweep_summary = SweepSummary {
+        seed,
+        runs: get_run_order(sweep),
+        environment,
+        pinned_images,
+    };
+    fs::write(
+        "sweep_summary.json",
+        serde_json::to_string_pretty(&sweep_summary).expect("Could not serialize sweep summary"),
+    )
+    .expect("Could not write sweep summary");
+}
+
+/// File name bases of the runs of the sweep, in the order they are executed
+fn get_run_order(sweep: &[Vec<(ParameterSet, Vec<RequestProcessingModel>)>]) -> Vec<String> {
+    sweep
+        .iter()
+        .flatten()
+        .flat_map(|(parameter_set, request_processing_models)| {
+            request_processing_models
+                .iter()
+                .map(|request_processing_model| {
+                    get_file_name_base(parameter_set, *request_processing_model)
+                })
+        })
+        .collect()
+}
+
+/// Reports the services whose images drifted from the pinned ones since the start of the sweep,
+/// e.g. because the stack was redeployed, aborting the sweep if configured to.
+/// Returns the images the services run.
+async fn check_image_drifts(
+    docker: &Docker,
+    pinned_images: &Option<BTreeMap<String, String>>,
+    abort_on_image_drift: bool,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let running_images = images::get_running_images(docker).await;
+    if let Some(pinned_images) = pinned_images {
+        let image_drifts = images::find_image_drifts(pinned_images, &running_images);
+        for image_drift in &image_drifts {
+            error!("Image drift: {image_drift}");
+        }
+        if !image_drifts.is_empty() && abort_on_image_drift {
+            panic!("Images of the services drifted from the pinned digests, aborting the sweep");
+        }
+    }
+    running_images
+}
+
+fn get_thread_pool_size(
+    request_processing_model: RequestProcessingModel,
+    no_motor_groups: u16,
+) -> usize {
+    (match request_processing_model {
+        RequestProcessingModel::ReactiveStreaming => 10 * 40,
+        RequestProcessingModel::ClientServer => no_motor_groups * 4 + 1,
+        RequestProcessingModel::SpringQL => no_motor_groups * 12,
+        RequestProcessingModel::ObjectOriented => no_motor_groups * 5,
+    }) as usize
+}
+
+fn get_file_name_base(
+    parameter_set: &ParameterSet,
+    request_processing_model: RequestProcessingModel,
+) -> String {
+    let ParameterSet {
+        duration,
+        no_motor_groups,
+        window_sampling_interval,
+        sensor_sampling_interval,
+        ..
+    } = parameter_set;
+    let thread_pool_size = get_thread_pool_size(request_processing_model, *no_motor_groups);
+    let window_size = get_window_size_token(parameter_set);
+    format!("{no_motor_groups}_{duration}_{window_size}_{window_sampling_interval}_{sensor_sampling_interval}_{thread_pool_size}_{}", request_processing_model.to_string())
+}
+
+/// Window size in the result file names, the number of readings prefixed by `c` for a
+/// count-based window, so that the two kinds are not mistaken for each other
+fn get_window_size_token(parameter_set: &ParameterSet) -> String {
+    match parameter_set.window_count {
+        Some(window_count) => format!("c{window_count}"),
+        None => parameter_set.window_size_ms.to_string(),
+    }
 }
 
 async fn setup_network_config(docker: &Docker) -> NetworkConfig {
@@ -216,7 +484,12 @@ async fn setup_network_config(docker: &Docker) -> NetworkConfig {
     )
 }
 
-async fn scale_service(no_motor_groups: u16, docker: &Docker, network_config: &mut NetworkConfig) {
+async fn scale_service(
+    no_motor_groups: u16,
+    docker: &Docker,
+    network_config: &mut NetworkConfig,
+    sensor_scaling_retry: RetryPolicy,
+) {
     let execution_chain = docker
         .inspect_service("bench_system_sensor", None::<InspectServiceOptions>)
         .then(|current| {
@@ -230,9 +503,8 @@ async fn scale_service(no_motor_groups: u16, docker: &Docker, network_config: &m
                 .update_service("bench_system_sensor", current.spec.unwrap(), options, None)
                 .then(|d| async move {
                     info!("{d:?}");
-                    let mut sensor_ips = Vec::new();
-                    while sensor_ips.len() != (no_motor_groups as usize) * 4 {
-                        thread::sleep(Duration::from_secs(1));
+                    let mut backoff = Backoff::new(&sensor_scaling_retry);
+                    loop {
                         let service_result = docker
                             .inspect_network(
                                 "bench_system_default",
@@ -240,9 +512,19 @@ async fn scale_service(no_motor_groups: u16, docker: &Docker, network_config: &m
                             )
                             .await
                             .unwrap();
-                        sensor_ips = get_sensor_ips(service_result);
+                        let sensor_ips = get_sensor_ips(service_result);
+                        if sensor_ips.len() == (no_motor_groups as usize) * 4 {
+                            break sensor_ips;
+                        }
+                        let delay = backoff.next().unwrap_or_else(|| {
+                            panic!(
+                                "Only {} of {} sensors joined the network",
+                                sensor_ips.len(),
+                                no_motor_groups * 4
+                            )
+                        });
+                        thread::sleep(delay);
                     }
-                    sensor_ips
                 })
         });
     let ips: Vec<IpAddr> = execution_chain.await;
@@ -283,21 +565,45 @@ fn update_spec(no_replicas: u16, current: &mut Service) {
         .replicas = Some(no_replicas.into());
 }
 
+fn get_run_directory(file_name_base: &str, inner_repetition: usize) -> PathBuf {
+    Path::new(RUNS_DIRECTORY).join(format!("{file_name_base}_{inner_repetition}"))
+}
+
+/// Executes the test driver with the network config and the results in the run directory, which is
+/// removed once the results were read, and kept for inspection if the run failed
 fn execute_test_run(
-    no_motor_groups: u16,
-    duration: u64,
-    window_size_ms: u64,
-    window_sampling_interval_ms: u32,
-    sensor_sampling_interval_ms: u32,
+    parameter_set: &ParameterSet,
     thread_pool_size: usize,
     request_processing_model: RequestProcessingModel,
-) -> Result<(String, String, String), ()> {
+    test_driver_exit_retry: &RetryPolicy,
+    network_config: &NetworkConfig,
+    run_directory: &Path,
+) -> Result<(String, String, String, String, String), ()> {
+    let ParameterSet {
+        duration,
+        no_motor_groups,
+        window_size_ms,
+        window_sampling_interval: window_sampling_interval_ms,
+        sensor_sampling_interval: sensor_sampling_interval_ms,
+        window_count,
+    } = *parameter_set;
+    // A directory left by a failed run of an earlier sweep
+    let _ = fs::remove_dir_all(run_directory);
+    fs::create_dir_all(run_directory).expect("Could not create run directory");
+    // The test driver runs in its own working directory
+    let run_directory =
+        fs::canonicalize(run_directory).expect("Could not canonicalize run directory");
+    network_config.persist(&run_directory);
     let mut command = Command::new("cargo");
-    let mut child = command
+    command
         .current_dir("../test_driver")
         .arg("run")
         .arg("--release")
         .arg("--")
+        .arg("--results-dir")
+        .arg(&run_directory)
+        .arg("--network-config")
+        .arg(run_directory.join(NETWORK_CONFIG_FILE_NAME))
         .arg("--motor-groups-tcp")
         .arg(no_motor_groups.to_string())
         .arg("--duration")
@@ -309,7 +615,11 @@ fn execute_test_run(
         .arg("--sensor-sampling-interval-ms")
         .arg(sensor_sampling_interval_ms.to_string())
         .arg("--thread-pool-size")
-        .arg(thread_pool_size.to_string())
+        .arg(thread_pool_size.to_string());
+    if let Some(window_count) = window_count {
+        command.arg("--window-count").arg(window_count.to_string());
+    }
+    let mut child = command
         .arg(request_processing_model.to_string())
         .stderr(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -322,30 +632,38 @@ fn execute_test_run(
         RequestProcessingModel::ObjectOriented => duration,
     };
     thread::sleep(Duration::from_secs(duration));
-    let mut process_finished = child.try_wait();
-    for _ in 0..30 {
-        if process_finished.is_ok() && process_finished.as_ref().unwrap().is_some() {
-            break;
-        }
-        thread::sleep(Duration::from_secs(1));
-        process_finished = child.try_wait();
-    }
-    if process_finished.is_err()
-        || process_finished.as_ref().unwrap().is_none()
-        || !process_finished.unwrap().unwrap().success()
-    {
+    // A test driver which is still running is checked again, one whose status cannot be read is not
+    let exit_status = retry::retry(
+        test_driver_exit_retry,
+        || match child.try_wait() {
+            Ok(Some(exit_status)) => Ok(exit_status),
+            Ok(None) => Err(None),
+            Err(e) => Err(Some(e)),
+        },
+        |e| match e {
+            None => Retryable::Retry,
+            Some(_) => Retryable::GiveUp,
+        },
+    );
+    if !matches!(exit_status, Ok(exit_status) if exit_status.success()) {
         Err(())
     } else {
-        let resource_usage = fs::read_to_string("../test_driver/motor_monitor_results.csv")
-            .unwrap_or("".to_string());
-        let _ = fs::remove_file("../test_driver/motor_monitor_results.csv");
-        let alert_delays =
-            fs::read_to_string("../test_driver/alert_delays.csv").unwrap_or("".to_string());
-        let _ = fs::remove_file("../test_driver/alert_delays.csv");
-        let alert_failures =
-            fs::read_to_string("../test_driver/alert_failures.csv").unwrap_or("".to_string());
-        let _ = fs::remove_file("../test_driver/alert_failures.csv");
-        Ok((resource_usage, alert_delays, alert_failures))
+        let read_result = |file_name: &str| {
+            fs::read_to_string(run_directory.join(file_name)).unwrap_or("".to_string())
+        };
+        let resource_usage = read_result("motor_monitor_results.csv");
+        let alert_delays = read_result("alert_delays.csv");
+        let alert_failures = read_result("alert_failures.csv");
+        let alert_bytes = read_result("alert_bytes.csv");
+        let network_usage = read_result("network_usage.csv");
+        let _ = fs::remove_dir_all(&run_directory);
+        Ok((
+            resource_usage,
+            alert_delays,
+            alert_failures,
+            alert_bytes,
+            network_usage,
+        ))
     }
 }
 
@@ -405,6 +723,42 @@ fn persist_alert_failures(file_name_base: &String, alert_failures: String) {
     persist_to_file(alert_failures_file_name, alert_failures);
 }
 
+fn persist_alert_bytes(file_name_base: &String, alert_bytes: String) {
+    let alert_bytes_file_name = format!("{file_name_base}_ab.csv");
+    persist_to_file(alert_bytes_file_name, alert_bytes);
+}
+
+/// Appends the bytes each component exchanged with its peers during a run,
+/// one line per component and peer, dropping the header of the test driver file
+fn persist_network_usage(file_name_base: &String, inner_repetition: usize, network_usage: &str) {
+    let network_usage_file_name = format!("{file_name_base}_nu.csv");
+    let network_usage = network_usage
+        .lines()
+        .skip(1)
+        .map(|line| format!("{inner_repetition},{line}\n"))
+        .collect::<String>();
+    persist_to_file(network_usage_file_name, network_usage);
+}
+
+/// Appends the images the services ran during a run, one line per service
+fn persist_images(
+    file_name_base: &String,
+    inner_repetition: usize,
+    running_images: &BTreeMap<String, BTreeSet<String>>,
+) {
+    let images_file_name = format!("{file_name_base}_im.csv");
+    let images = running_images
+        .iter()
+        .map(|(service, images)| {
+            format!(
+                "{inner_repetition},{service},{}\n",
+                images.iter().cloned().collect::<Vec<String>>().join(" ")
+            )
+        })
+        .collect();
+    persist_to_file(images_file_name, images);
+}
+
 fn persist_to_file(file_name: String, data: String) {
     let mut file = OpenOptions::new()
         .create(true)
@@ -413,3 +767,44 @@ fn persist_to_file(file_name: String, data: String) {
         .unwrap();
     write!(file, "{}", data).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_config() -> Config {
+        toml::from_str(
+            r#"
+            outer_repetitions = 3
+            inner_repetitions = 1
+            motor_groups_tcp = [1, 2, 4]
+            durations = [60]
+            window_size_ms = [100, 1000]
+            sensor_sampling_interval_ms = [10]
+            request_processing_models = ["ClientServer", "ReactiveStreaming", "SpringQL", "ObjectOriented"]
+            "#,
+        )
+        .expect("Could not parse test config")
+    }
+
+    #[test]
+    fn same_seed_yields_same_run_order() {
+        let config = get_test_config();
+        let run_order = get_run_order(&plan_sweep(&config, Some(42)));
+        assert_eq!(run_order.len(), 3 * 3 * 2 * 4);
+        assert_eq!(run_order, get_run_order(&plan_sweep(&config, Some(42))));
+        assert_ne!(run_order, get_run_order(&plan_sweep(&config, Some(43))));
+    }
+
+    #[test]
+    fn run_order_without_seed_follows_config() {
+        let config = get_test_config();
+        let run_order = get_run_order(&plan_sweep(&config, None));
+        let repetitions: Vec<&[String]> = run_order.chunks(run_order.len() / 3).collect();
+        assert!(repetitions
+            .iter()
+            .all(|repetition| *repetition == repetitions[0]));
+        assert!(run_order[0].starts_with("1_60_100_100_10_"));
+        assert!(run_order[0].ends_with("ClientServer"));
+    }
+}
@@ -0,0 +1,379 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use data_transfer_objects::RequestProcessingModel;
+
+use crate::{get_thread_pool_size, ParameterSet};
+
+const MEASUREMENT: &str = "bench_run";
+const PUSHGATEWAY_JOB: &str = "bench_executor";
+
+/// Backend the summary metrics of the runs are pushed to
+#[derive(Deserialize, Debug, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsFormat {
+    /// InfluxDB line protocol, posted to the write endpoint of the URL
+    Influx,
+    /// Prometheus text format, posted to the Pushgateway at the URL,
+    /// grouped by the parameters of the run
+    Pushgateway,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MetricsPush {
+    pub format: MetricsFormat,
+    pub url: String,
+    /// Value of the `Authorization` header, e.g. `Token <token>` for InfluxDB
+    pub authorization: Option<String>,
+}
+
+/// Summary of a run, derived from the results the test driver persisted
+#[derive(Debug, PartialEq)]
+pub struct RunMetrics {
+    /// CPU time (in clock ticks) the monitor spent in user and kernel mode,
+    /// absent if its benchmark data did not arrive
+    pub processing_time: Option<u64>,
+    /// Mean delay (in s) of the alerts, absent if there were none
+    pub mean_delay: Option<f64>,
+    pub alert_count: usize,
+    pub alert_failures: Option<u64>,
+}
+
+impl RunMetrics {
+    pub fn from_results(
+        resource_usage: &str,
+        alert_delays: &str,
+        alert_failures: &str,
+    ) -> RunMetrics {
+        let processing_time = resource_usage.lines().next().and_then(|line| {
+            let columns: Vec<&str> = line.split(',').collect();
+            Some(columns.get(1)?.parse::<u64>().ok()? + columns.get(2)?.parse::<u64>().ok()?)
+        });
+        let delays: Vec<f64> = alert_delays
+            .split(',')
+            .filter_map(|delay| delay.trim().parse().ok())
+            .collect();
+        RunMetrics {
+            processing_time,
+            mean_delay: (!delays.is_empty())
+                .then(|| delays.iter().sum::<f64>() / delays.len() as f64),
+            alert_count: delays.len(),
+            alert_failures: alert_failures
+                .split(',')
+                .next()
+                .and_then(|alert_failures| alert_failures.trim().parse().ok()),
+        }
+    }
+
+    fn get_fields(&self) -> Vec<(&'static str, Field)> {
+        let mut fields = vec![("alert_count", Field::Int(self.alert_count as u64))];
+        if let Some(processing_time) = self.processing_time {
+            fields.push(("processing_time", Field::Int(processing_time)));
+        }
+        if let Some(mean_delay) = self.mean_delay {
+            fields.push(("mean_delay", Field::Float(mean_delay)));
+        }
+        if let Some(alert_failures) = self.alert_failures {
+            fields.push(("alert_failures", Field::Int(alert_failures)));
+        }
+        fields
+    }
+}
+
+enum Field {
+    Int(u64),
+    Float(f64),
+}
+
+/// Pushes the summary of the run, tagged with its parameters, to the configured backend.
+/// A failed push is only logged, so that an unavailable backend does not abort the sweep.
+pub fn push_run_metrics(
+    metrics_push: &MetricsPush,
+    parameter_set: &ParameterSet,
+    request_processing_model: RequestProcessingModel,
+    run_metrics: &RunMetrics,
+) {
+    let tags = get_tags(parameter_set, request_processing_model);
+    let (url, body) = match metrics_push.format {
+        MetricsFormat::Influx => (
+            metrics_push.url.clone(),
+            get_line_protocol(&tags, run_metrics, get_timestamp_ns()),
+        ),
+        MetricsFormat::Pushgateway => (
+            get_pushgateway_url(&metrics_push.url, &tags),
+            get_text_format(run_metrics),
+        ),
+    };
+    let mut request = ureq::post(&url).set("Content-Type", "text/plain; charset=utf-8");
+    if let Some(authorization) = &metrics_push.authorization {
+        request = request.set("Authorization", authorization);
+    }
+    match request.send_string(&body) {
+        Ok(_) => info!("Pushed metrics of the run to {url}"),
+        Err(e) => warn!("Could not push metrics of the run to {url}: {e}"),
+    }
+}
+
+/// Parameters of the run, as also encoded in the names of its result files
+fn get_tags(
+    parameter_set: &ParameterSet,
+    request_processing_model: RequestProcessingModel,
+) -> Vec<(&'static str, String)> {
+    let mut tags = vec![
+        (
+            "request_processing_model",
+            request_processing_model.to_string(),
+        ),
+        ("motor_groups", parameter_set.no_motor_groups.to_string()),
+        ("duration", parameter_set.duration.to_string()),
+        ("window_size_ms", parameter_set.window_size_ms.to_string()),
+        (
+            "window_sampling_interval_ms",
+            parameter_set.window_sampling_interval.to_string(),
+        ),
+        (
+            "sensor_sampling_interval_ms",
+            parameter_set.sensor_sampling_interval.to_string(),
+        ),
+        (
+            "thread_pool_size",
+            get_thread_pool_size(request_processing_model, parameter_set.no_motor_groups)
+                .to_string(),
+        ),
+    ];
+    // Only the runs of count-based windows are tagged, keeping the series of the others as they were
+    if let Some(window_count) = parameter_set.window_count {
+        tags.push(("window_count", window_count.to_string()));
+    }
+    tags
+}
+
+/// A single line of the InfluxDB line protocol, e.g.
+/// `bench_run,request_processing_model=ClientServer,... alert_count=3i,mean_delay=0.02 <timestamp>`
+fn get_line_protocol(
+    tags: &[(&'static str, String)],
+    run_metrics: &RunMetrics,
+    timestamp_ns: u128,
+) -> String {
+    let tags: String = tags
+        .iter()
+        .map(|(key, value)| format!(",{key}={}", escape_tag_value(value)))
+        .collect();
+    let fields = run_metrics
+        .get_fields()
+        .into_iter()
+        .map(|(key, field)| match field {
+            Field::Int(value) => format!("{key}={value}i"),
+            Field::Float(value) => format!("{key}={value}"),
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    format!("{MEASUREMENT}{tags} {fields} {timestamp_ns}\n")
+}
+
+fn escape_tag_value(value: &str) -> String {
+    value
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// The metrics as gauges in the Prometheus text format, e.g. `bench_run_alert_count 3`;
+/// the tags are part of the grouping key in the URL instead
+fn get_text_format(run_metrics: &RunMetrics) -> String {
+    run_metrics
+        .get_fields()
+        .into_iter()
+        .map(|(key, field)| {
+            let value = match field {
+                Field::Int(value) => value.to_string(),
+                Field::Float(value) => value.to_string(),
+            };
+            format!("# TYPE {MEASUREMENT}_{key} gauge\n{MEASUREMENT}_{key} {value}\n")
+        })
+        .collect()
+}
+
+/// `<url>/metrics/job/bench_executor/<tag>/<value>/...`, so that each parameter tuple
+/// keeps its own group instead of replacing the metrics of the others
+fn get_pushgateway_url(url: &str, tags: &[(&'static str, String)]) -> String {
+    let grouping_key: String = tags
+        .iter()
+        .map(|(key, value)| format!("/{key}/{value}"))
+        .collect();
+    format!(
+        "{}/metrics/job/{PUSHGATEWAY_JOB}{grouping_key}",
+        url.trim_end_matches('/')
+    )
+}
+
+fn get_timestamp_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time is before the unix epoch")
+        .as_nanos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const TAGS: &str = "request_processing_model=ClientServer,motor_groups=2,duration=60,\
+        window_size_ms=1000,window_sampling_interval_ms=100,sensor_sampling_interval_ms=10,\
+        thread_pool_size=9";
+
+    /// Request the stub endpoint received
+    struct Request {
+        request_line: String,
+        headers: Vec<String>,
+        body: String,
+    }
+
+    impl Request {
+        fn get_header(&self, name: &str) -> Option<&str> {
+            self.headers.iter().find_map(|header| {
+                let (header_name, value) = header.split_once(':')?;
+                header_name
+                    .eq_ignore_ascii_case(name)
+                    .then_some(value.trim())
+            })
+        }
+    }
+
+    /// Stub HTTP endpoint on a local port, which accepts a single request and returns it
+    fn spawn_endpoint() -> (String, thread::JoinHandle<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut stream = BufReader::new(listener.accept().unwrap().0);
+            let mut lines = vec![];
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                lines.push(line);
+            }
+            let request_line = lines.remove(0);
+            let mut request = Request {
+                request_line,
+                headers: lines,
+                body: String::new(),
+            };
+            let content_length: usize = request
+                .get_header("Content-Length")
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; content_length];
+            stream.read_exact(&mut body).unwrap();
+            request.body = String::from_utf8(body).unwrap();
+            stream
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    fn get_test_parameter_set() -> ParameterSet {
+        ParameterSet {
+            duration: 60,
+            no_motor_groups: 2,
+            window_size_ms: 1000,
+            window_sampling_interval: 100,
+            sensor_sampling_interval: 10,
+            window_count: None,
+        }
+    }
+
+    fn get_test_run_metrics() -> RunMetrics {
+        RunMetrics::from_results("1,70,50,4096\n", "0.2,0.3,0.25,0.25", "1,4")
+    }
+
+    #[test]
+    fn run_metrics_are_derived_from_results() {
+        assert_eq!(
+            get_test_run_metrics(),
+            RunMetrics {
+                processing_time: Some(120),
+                mean_delay: Some(0.25),
+                alert_count: 4,
+                alert_failures: Some(1),
+            }
+        );
+    }
+
+    #[test]
+    fn line_protocol_is_posted_to_influx() {
+        let (url, endpoint) = spawn_endpoint();
+        let metrics_push = MetricsPush {
+            format: MetricsFormat::Influx,
+            url: format!("{url}/api/v2/write?bucket=bench"),
+            authorization: Some("Token secret".to_string()),
+        };
+
+        push_run_metrics(
+            &metrics_push,
+            &get_test_parameter_set(),
+            RequestProcessingModel::ClientServer,
+            &get_test_run_metrics(),
+        );
+
+        let request = endpoint.join().unwrap();
+        assert_eq!(
+            request.request_line,
+            "POST /api/v2/write?bucket=bench HTTP/1.1"
+        );
+        assert_eq!(request.get_header("Authorization"), Some("Token secret"));
+        let (line, timestamp) = request.body.trim_end().rsplit_once(' ').unwrap();
+        assert_eq!(
+            line,
+            format!(
+                "bench_run,{TAGS} alert_count=4i,processing_time=120i,mean_delay=0.25,alert_failures=1i"
+            )
+        );
+        assert!(timestamp.parse::<u128>().is_ok());
+        assert!(request.body.ends_with('\n'));
+    }
+
+    #[test]
+    fn gauges_are_posted_to_the_group_of_the_run() {
+        let (url, endpoint) = spawn_endpoint();
+        let metrics_push = MetricsPush {
+            format: MetricsFormat::Pushgateway,
+            url: format!("{url}/"),
+            authorization: None,
+        };
+
+        push_run_metrics(
+            &metrics_push,
+            &get_test_parameter_set(),
+            RequestProcessingModel::ClientServer,
+            &get_test_run_metrics(),
+        );
+
+        let request = endpoint.join().unwrap();
+        let grouping_key = TAGS.replace(['=', ','], "/");
+        assert_eq!(
+            request.request_line,
+            format!("POST /metrics/job/bench_executor/{grouping_key} HTTP/1.1")
+        );
+        assert_eq!(request.get_header("Authorization"), None);
+        assert_eq!(
+            request.body,
+            "# TYPE bench_run_alert_count gauge\nbench_run_alert_count 4\n\
+             # TYPE bench_run_processing_time gauge\nbench_run_processing_time 120\n\
+             # TYPE bench_run_mean_delay gauge\nbench_run_mean_delay 0.25\n\
+             # TYPE bench_run_alert_failures gauge\nbench_run_alert_failures 1\n"
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs, process};
+
+use clap::Parser;
+use env_logger::Env;
+use log::{error, info, warn};
+use serde::Serialize;
+
+use data_aggregator::stats::{get_mean, t_test, SIGNIFICANCE_LEVEL};
+use smoke_test::{build_packages, get_binary_dir, LocalRun};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about)]
+struct Args {
+    /// Git revision the baseline is built from, in a temporary worktree
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with = "baseline_dir",
+        required_unless_present = "baseline_dir"
+    )]
+    baseline_rev: Option<String>,
+
+    /// Checkout of the workspace the baseline is built in, e.g. a copy made before a change
+    #[clap(long, value_parser)]
+    baseline_dir: Option<PathBuf>,
+
+    /// Git revision the current build is built from, instead of the workspace
+    #[clap(long, value_parser)]
+    current_rev: Option<String>,
+
+    /// Runs of the smoke test scenario per build
+    #[clap(long, value_parser = clap::value_parser!(u32).range(2..), default_value_t = 5)]
+    runs: u32,
+
+    /// Relative increase of a metric over the baseline which is tolerated, e.g. 0.05 for 5%
+    #[clap(long, value_parser, default_value_t = 0.05)]
+    threshold: f64,
+
+    /// File the verdict is written to as JSON
+    #[clap(long, value_parser, default_value = "bench_regress_verdict.json")]
+    verdict: PathBuf,
+}
+
+/// Metric of the benchmark data of the motor monitor, where greater values are worse
+struct Metric {
+    name: &'static str,
+    get: fn(&[f64]) -> f64,
+}
+
+/// Metrics which are compared, named like the ones of the data aggregator
+const METRICS: [Metric; 3] = [
+    Metric {
+        name: "processing_time",
+        get: get_processing_time,
+    },
+    Metric {
+        name: "memory_usage",
+        get: get_memory_usage,
+    },
+    Metric {
+        name: "cpu_utilization",
+        get: get_cpu_utilization,
+    },
+];
+
+/// Worktree of a git revision in the temporary directory, which is removed when dropped
+struct Worktree {
+    workspace: PathBuf,
+    path: PathBuf,
+}
+
+impl Worktree {
+    fn add(workspace: &Path, revision: &str, name: &str) -> Result<Worktree, String> {
+        let path = env::temp_dir().join(format!("bench_regress_{}_{name}", process::id()));
+        run_git(
+            workspace,
+            &[
+                "worktree",
+                "add",
+                "--detach",
+                &path.to_string_lossy(),
+                revision,
+            ],
+        )?;
+        let worktree = Worktree {
+            workspace: workspace.to_path_buf(),
+            path,
+        };
+        // The lock file is not versioned, so it is copied to build with the same dependencies
+        let lock_file = workspace.join("Cargo.lock");
+        if lock_file.exists() {
+            fs::copy(&lock_file, worktree.path.join("Cargo.lock"))
+                .map_err(|e| format!("Could not copy Cargo.lock to the worktree: {e}"))?;
+        }
+        Ok(worktree)
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        if let Err(reason) = run_git(
+            &self.workspace,
+            &[
+                "worktree",
+                "remove",
+                "--force",
+                &self.path.to_string_lossy(),
+            ],
+        ) {
+            warn!(
+                "Could not remove worktree {}: {reason}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Build of the components whose runs are compared
+struct Build {
+    name: &'static str,
+    description: String,
+    workspace: PathBuf,
+    binary_dir: PathBuf,
+    _worktree: Option<Worktree>,
+    /// Fields of the benchmark data of the motor monitor per run
+    runs: Vec<Vec<f64>>,
+}
+
+impl Build {
+    fn get_values(&self, metric: &Metric) -> Vec<f64> {
+        self.runs
+            .iter()
+            .map(|fields| (metric.get)(fields))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Regression,
+    NoDifference,
+}
+
+#[derive(Serialize, Debug)]
+struct MetricVerdict {
+    name: &'static str,
+    verdict: Outcome,
+    baseline_mean: f64,
+    current_mean: f64,
+    relative_change: f64,
+    /// Of the current build exceeding the baseline by more than the threshold, absent if the
+    /// differences of all runs are equal, e.g. zero
+    p_value: Option<f64>,
+    baseline_values: Vec<f64>,
+    current_values: Vec<f64>,
+}
+
+#[derive(Serialize, Debug)]
+struct Verdict {
+    verdict: Outcome,
+    baseline: String,
+    current: String,
+    runs: u32,
+    threshold: f64,
+    significance_level: f64,
+    metrics: Vec<MetricVerdict>,
+}
+
+fn main() {
+    if env::args().any(|argument| argument == "--version-full") {
+        println!("{}", data_transfer_objects::build_info!());
+        return;
+    }
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("Could not get workspace directory")
+        .to_path_buf();
+    let verdict = match compare_builds(&workspace, &args) {
+        Ok(verdict) => verdict,
+        Err(reason) => {
+            error!("{reason}");
+            process::exit(2);
+        }
+    };
+    print!("{}", get_table(&verdict));
+    fs::write(
+        &args.verdict,
+        serde_json::to_string_pretty(&verdict).expect("Could not serialize verdict"),
+    )
+    .expect("Could not write verdict file");
+    if verdict.verdict == Outcome::Regression {
+        process::exit(1);
+    }
+}
+
+fn compare_builds(workspace: &Path, args: &Args) -> Result<Verdict, String> {
+    let current = prepare_build(workspace, "current", args.current_rev.as_deref(), None)?;
+    let baseline = prepare_build(
+        workspace,
+        "baseline",
+        args.baseline_rev.as_deref(),
+        args.baseline_dir.as_deref(),
+    )?;
+    let mut builds = [current, baseline];
+    for run in 0..args.runs {
+        // Alternating the build run first keeps a drift of the machine (e.g. its temperature)
+        // from favouring one of them
+        let order = if run % 2 == 0 { [0, 1] } else { [1, 0] };
+        for index in order {
+            let build = &mut builds[index];
+            info!(
+                "Run {} of {} of the {} build",
+                run + 1,
+                args.runs,
+                build.name
+            );
+            let fields = execute_run(build, run)?;
+            build.runs.push(fields);
+        }
+    }
+    let [current, baseline] = builds;
+    let metrics: Vec<MetricVerdict> = METRICS
+        .iter()
+        .map(|metric| compare_metric(metric, &current, &baseline, args))
+        .collect();
+    let verdict = if metrics
+        .iter()
+        .any(|metric| metric.verdict == Outcome::Regression)
+    {
+        Outcome::Regression
+    } else {
+        Outcome::NoDifference
+    };
+    Ok(Verdict {
+        verdict,
+        baseline: baseline.description,
+        current: current.description,
+        runs: args.runs,
+        threshold: args.threshold,
+        significance_level: SIGNIFICANCE_LEVEL,
+        metrics,
+    })
+}
+
+/// Builds the components of a revision in a worktree, of a checkout of the workspace,
+/// or of the workspace itself
+fn prepare_build(
+    workspace: &Path,
+    name: &'static str,
+    revision: Option<&str>,
+    checkout: Option<&Path>,
+) -> Result<Build, String> {
+    let (description, worktree) = match (revision, checkout) {
+        (Some(revision), _) => (
+            format!("revision {revision}"),
+            Some(Worktree::add(workspace, revision, name)?),
+        ),
+        (None, Some(checkout)) => (checkout.display().to_string(), None),
+        (None, None) => ("workspace".to_string(), None),
+    };
+    let (build_workspace, binary_dir) = match (&worktree, checkout) {
+        (Some(worktree), _) => (worktree.path.clone(), worktree.path.join("target/debug")),
+        (None, Some(checkout)) => (checkout.to_path_buf(), checkout.join("target/debug")),
+        (None, None) => (workspace.to_path_buf(), get_binary_dir()),
+    };
+    info!("Building the {name} build ({description})");
+    build_packages(&build_workspace).map_err(|failure| {
+        format!(
+            "Could not build the {name} build ({description}): {}",
+            failure.reason
+        )
+    })?;
+    Ok(Build {
+        name,
+        description,
+        workspace: build_workspace,
+        binary_dir,
+        _worktree: worktree,
+        runs: vec![],
+    })
+}
+
+/// Runs the smoke test scenario, returning the fields of the benchmark data of the motor monitor
+fn execute_run(build: &Build, run: u32) -> Result<Vec<f64>, String> {
+    let run_dir = env::temp_dir().join(format!(
+        "bench_regress_{}_{}_run_{run}",
+        process::id(),
+        build.name
+    ));
+    let run_failed = |reason: String| {
+        format!(
+            "Run {run} of the {} build failed: {reason}, the logs of the components are kept in {}",
+            build.name,
+            run_dir.display()
+        )
+    };
+    let mut local_run = LocalRun::execute(&build.workspace, &build.binary_dir, &run_dir)
+        .map_err(|failure| run_failed(format!("{:?}: {}", failure.stage, failure.reason)))?;
+    local_run
+        .check_components()
+        .map_err(|failure| run_failed(failure.reason))?;
+    let test_run_dir = local_run.get_test_run_dir().to_path_buf();
+    drop(local_run);
+    let fields = read_benchmark_data(&test_run_dir).map_err(run_failed)?;
+    let _ = fs::remove_dir_all(&run_dir);
+    Ok(fields)
+}
+
+/// Reads the line `id,utime,stime,cutime,cstime,vmhwm,vmpeak,load_average,cpu_utilization`
+/// the test driver persisted for the motor monitor
+fn read_benchmark_data(test_run_dir: &Path) -> Result<Vec<f64>, String> {
+    let results = fs::read_to_string(test_run_dir.join("motor_monitor_results.csv"))
+        .map_err(|e| format!("Could not read the benchmark data of the motor monitor: {e}"))?;
+    let line = results
+        .lines()
+        .find(|line| !line.is_empty())
+        .ok_or("The test driver did not persist any benchmark data of the motor monitor")?;
+    let fields: Vec<&str> = line.split(',').collect();
+    if fields.len() < 9 || fields[9..].iter().any(|flag| !flag.contains('=')) {
+        return Err(format!("Unusable benchmark data {line}"));
+    }
+    fields[..9]
+        .iter()
+        .map(|field| {
+            field
+                .parse::<f64>()
+                .map_err(|e| format!("{line}: {field}: {e}"))
+        })
+        .collect()
+}
+
+/// Tests whether the current build exceeds the baseline by more than the threshold,
+/// pairing the runs by their number
+fn compare_metric(
+    metric: &Metric,
+    current: &Build,
+    baseline: &Build,
+    args: &Args,
+) -> MetricVerdict {
+    let current_values = current.get_values(metric);
+    let baseline_values = baseline.get_values(metric);
+    let tolerated_values: Vec<f64> = baseline_values
+        .iter()
+        .map(|value| value * (1f64 + args.threshold))
+        .collect();
+    let p_value = t_test(&current_values, &tolerated_values, args.runs as usize)
+        .filter(|p_value| p_value.is_finite());
+    let baseline_mean = get_mean(&baseline_values);
+    let current_mean = get_mean(&current_values);
+    MetricVerdict {
+        name: metric.name,
+        verdict: match p_value {
+            Some(p_value) if p_value <= SIGNIFICANCE_LEVEL => Outcome::Regression,
+            _ => Outcome::NoDifference,
+        },
+        baseline_mean,
+        current_mean,
+        relative_change: current_mean / baseline_mean - 1f64,
+        p_value,
+        baseline_values,
+        current_values,
+    }
+}
+
+fn get_table(verdict: &Verdict) -> String {
+    let mut table = format!(
+        "{} against {} ({} runs each, threshold {:.1}%)\n{:<16} {:>14} {:>14} {:>9} {:>8}  verdict\n",
+        verdict.current,
+        verdict.baseline,
+        verdict.runs,
+        verdict.threshold * 100f64,
+        "metric",
+        "baseline",
+        "current",
+        "change",
+        "p-value"
+    );
+    for metric in &verdict.metrics {
+        let p_value = metric
+            .p_value
+            .map(|p_value| format!("{p_value:.4}"))
+            .unwrap_or_else(|| "-".to_string());
+        writeln!(
+            table,
+            "{:<16} {:>14.3} {:>14.3} {:>+8.1}% {:>8}  {:?}",
+            metric.name,
+            metric.baseline_mean,
+            metric.current_mean,
+            metric.relative_change * 100f64,
+            p_value,
+            metric.verdict
+        )
+        .unwrap();
+    }
+    writeln!(table, "Verdict: {:?}", verdict.verdict).unwrap();
+    table
+}
+
+fn run_git(workspace: &Path, arguments: &[&str]) -> Result<(), String> {
+    let output = Command::new("git")
+        .current_dir(workspace)
+        .args(arguments)
+        .output()
+        .map_err(|e| format!("Could not run git: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} exited with {}: {}",
+            arguments.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// CPU time of the motor monitor and its children in clock ticks
+fn get_processing_time(fields: &[f64]) -> f64 {
+    fields[1] + fields[2] + fields[3] + fields[4]
+}
+
+/// Peak resident set size of the motor monitor in kB
+fn get_memory_usage(fields: &[f64]) -> f64 {
+    fields[5]
+}
+
+fn get_cpu_utilization(fields: &[f64]) -> f64 {
+    fields[8]
+}
@@ -0,0 +1,72 @@
+//! Build script helper embedding the metadata of a build into the binary of a workspace crate,
+//! to be read with `data_transfer_objects::build_info!`
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Used instead of the git hash if the sources are not in a git repository, e.g. in a container build
+const UNKNOWN: &str = "unknown";
+
+/// Emits the git hash and dirty flag of the sources, the build timestamp and the cargo profile
+/// as the `BUILD_GIT_HASH`, `BUILD_GIT_DIRTY`, `BUILD_TIMESTAMP` and `BUILD_PROFILE` environment
+/// variables of the crate. A `BUILD_GIT_HASH` set in the environment of the build is taken as is,
+/// for builds without access to the repository.
+pub fn emit() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("Not called from a build script");
+    println!("cargo:rerun-if-env-changed=BUILD_GIT_HASH");
+    // Rerun on changes of the crate itself as well as on commits and checkouts
+    println!("cargo:rerun-if-changed={manifest_dir}");
+    let (git_hash, dirty) = match env::var("BUILD_GIT_HASH") {
+        Ok(git_hash) => (git_hash, false),
+        Err(_) => match run_git(&manifest_dir, &["rev-parse", "HEAD"]) {
+            Some(git_hash) => {
+                watch_git_dir(&manifest_dir);
+                let dirty = run_git(&manifest_dir, &["status", "--porcelain"])
+                    .is_some_and(|status| !status.is_empty());
+                (git_hash, dirty)
+            }
+            None => (UNKNOWN.to_string(), false),
+        },
+    };
+    let build_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Build time is before the epoch")
+        .as_secs();
+    println!("cargo:rustc-env=BUILD_GIT_HASH={git_hash}");
+    println!("cargo:rustc-env=BUILD_GIT_DIRTY={dirty}");
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={build_time}");
+    println!(
+        "cargo:rustc-env=BUILD_PROFILE={}",
+        env::var("PROFILE").unwrap_or_else(|_| UNKNOWN.to_string())
+    );
+}
+
+/// Reruns the build script once the checked out commit or the staged changes change
+fn watch_git_dir(manifest_dir: &str) {
+    let Some(git_dir) = run_git(manifest_dir, &["rev-parse", "--absolute-git-dir"]) else {
+        return;
+    };
+    let git_dir = Path::new(&git_dir);
+    println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+    println!("cargo:rerun-if-changed={}", git_dir.join("index").display());
+    if let Some(head_ref) = run_git(manifest_dir, &["symbolic-ref", "-q", "HEAD"]) {
+        println!(
+            "cargo:rerun-if-changed={}",
+            git_dir.join(head_ref).display()
+        );
+    }
+}
+
+fn run_git(manifest_dir: &str, arguments: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(manifest_dir)
+        .args(arguments)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use data_transfer_objects::DISK_FULL_GAP_LABEL;
+use utils::disk_writer;
+use utils::disk_writer::DiskWriter;
+
+const MANIFEST_PATH: &str = "alert_protocol.manifest";
+/// The manifest is renamed into place, so that a full disk cannot leave it truncated
+const MANIFEST_TMP_PATH: &str = "alert_protocol.manifest.tmp";
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct AlertProtocolLimits {
+    /// Size (in bytes) after which the protocol is continued in a new segment
+    pub max_segment_size: u64,
+    /// Total size (in bytes) of all segments after which alerts are no longer persisted
+    pub disk_budget: u64,
+}
+
+/// Protocol of the received alerts, split into segments `alert_protocol.csv`,
+/// `alert_protocol.1.csv`, ..., which are listed in a manifest
+pub struct AlertProtocol {
+    /// Directory of the segments and the manifest, the working directory if empty
+    directory: PathBuf,
+    limits: Option<AlertProtocolLimits>,
+    segments: Vec<PathBuf>,
+    segment: DiskWriter,
+    segment_size: u64,
+    total_size: u64,
+    unpersisted_alerts: u64,
+    /// Alerts which were not written because the disk is full, shared by the writers of all segments
+    unwritten_alerts: Arc<AtomicU64>,
+}
+
+impl AlertProtocol {
+    /// Removes the segments of the previous run in the directory and starts a new protocol
+    pub fn create(directory: PathBuf, limits: Option<AlertProtocolLimits>) -> AlertProtocol {
+        for segment in get_segments(&directory) {
+            let _ = fs::remove_file(segment);
+        }
+        let segment_path = get_segment_path(&directory, 0);
+        let unwritten_alerts = Arc::new(AtomicU64::new(0));
+        let alert_protocol = AlertProtocol {
+            directory,
+            limits,
+            segment: open_segment(&segment_path, Arc::clone(&unwritten_alerts))
+                .expect("Could not open alert protocol for writing"),
+            segments: vec![segment_path],
+            segment_size: 0,
+            total_size: 0,
+            unpersisted_alerts: 0,
+            unwritten_alerts,
+        };
+        alert_protocol
+            .write_manifest()
+            .expect("Could not write alert protocol manifest");
+        alert_protocol
+    }
+
+    /// Appends the line to the protocol, returns false if it was not persisted
+    /// because the disk budget is exhausted.
+    /// If the disk is full, the line is dropped and counted in the unwritten alerts instead.
+    pub fn append(&mut self, line: &str) -> bool {
+        let line_size = line.len() as u64 + 1;
+        if let Some(limits) = self.limits {
+            if self.total_size + line_size > limits.disk_budget {
+                if self.unpersisted_alerts == 0 {
+                    warn!(
+                        "Alert protocol reached the disk budget of {} bytes, no longer persisting alerts",
+                        limits.disk_budget
+                    );
+                }
+                self.unpersisted_alerts += 1;
+                return false;
+            }
+            if self.segment_size > 0 && self.segment_size + line_size > limits.max_segment_size {
+                self.rotate();
+            }
+        }
+        if writeln!(self.segment, "{line}").expect("Could not write to alert protocol") {
+            self.segment_size += line_size;
+            self.total_size += line_size;
+        }
+        true
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Alerts which were not written because the disk is full
+    pub fn get_unwritten_alerts(&self) -> u64 {
+        self.segment.get_dropped_records()
+    }
+
+    /// Continues the protocol in a new segment, unless the disk is too full to add it,
+    /// in which case the current segment is continued
+    fn rotate(&mut self) {
+        let segment_path = get_segment_path(&self.directory, self.segments.len());
+        info!("Rotating alert protocol to {}", segment_path.display());
+        let segment = match open_segment(&segment_path, Arc::clone(&self.unwritten_alerts)) {
+            Ok(segment) => segment,
+            Err(e) if disk_writer::is_disk_full(&e) => {
+                warn!("Could not rotate alert protocol, continuing the current segment: {e}");
+                return;
+            }
+            Err(e) => panic!("Could not open alert protocol for writing: {e}"),
+        };
+        self.segments.push(segment_path);
+        match self.write_manifest() {
+            Ok(()) => {
+                self.segment = segment;
+                self.segment_size = 0;
+            }
+            Err(e) if disk_writer::is_disk_full(&e) => {
+                warn!("Could not rotate alert protocol, continuing the current segment: {e}");
+                let _ = fs::remove_file(self.segments.pop().expect("Segment was just added"));
+            }
+            Err(e) => panic!("Could not write alert protocol manifest: {e}"),
+        }
+    }
+
+    fn write_manifest(&self) -> io::Result<()> {
+        let manifest_tmp_path = self.directory.join(MANIFEST_TMP_PATH);
+        let written = fs::write(
+            &manifest_tmp_path,
+            self.segments
+                .iter()
+                .map(|segment| format!("{}\n", segment.display()))
+                .collect::<String>(),
+        )
+        .and_then(|_| fs::rename(&manifest_tmp_path, self.directory.join(MANIFEST_PATH)));
+        if written.is_err() {
+            let _ = fs::remove_file(manifest_tmp_path);
+        }
+        written
+    }
+}
+
+/// Returns the segments of the current run in the directory, as listed in its manifest
+pub fn get_segments(directory: &Path) -> Vec<PathBuf> {
+    fs::read_to_string(directory.join(MANIFEST_PATH))
+        .map(|manifest| manifest.lines().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
+
+fn get_segment_path(directory: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => directory.join("alert_protocol.csv"),
+        index => directory.join(format!("alert_protocol.{index}.csv")),
+    }
+}
+
+/// Opens the segment for writing, marking the gaps of alerts not written due to a full disk
+fn open_segment(segment_path: &Path, unwritten_alerts: Arc<AtomicU64>) -> io::Result<DiskWriter> {
+    let segment = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(segment_path)?;
+    Ok(DiskWriter::new(segment_path, segment, unwritten_alerts)
+        .with_gap_marker(|unwritten_alerts| format!("{DISK_FULL_GAP_LABEL},{unwritten_alerts}\n")))
+}
@@ -1,29 +1,89 @@
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
 use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::{fs, thread};
 
-use log::{error, info};
+use log::{debug, error, info, warn};
 use serde::Deserialize;
 
-use data_transfer_objects::{Alert, CloudServerRunParameters};
+use data_transfer_objects::{
+    AbortRun, Alert, AlertEncoding, AlertSource, CloudServerRunParameters, DetailedAlert,
+    ParameterAck, RouteRule, RunStart, ABORTED_LABEL, ALERT_BYTES_LABEL, DROPPED_ALERTS_LABEL,
+    NETWORK_USAGE_LABEL, ROUTE_LABEL, SAMPLED_MESSAGES_LABEL, UNPERSISTED_ALERTS_LABEL,
+    UNWRITTEN_ALERTS_LABEL, WINDOW_AVERAGES_LABEL,
+};
+use utils::cloud_connection::AlertCodec;
+use utils::network_usage::{ByteCounter, CountingStream};
+use utils::telemetry::Telemetry;
+use utils::ReadError;
+
+use crate::alert_protocol::{AlertProtocol, AlertProtocolLimits};
+use crate::persistent_listener::PersistentMonitorListener;
+use crate::rate_limiter::{RateLimit, RateLimiter};
+
+mod alert_protocol;
+mod persistent_listener;
+mod rate_limiter;
 
 #[cfg(debug_assertions)]
 const CONFIG_PATH: &str = "resources/config-debug.toml";
 #[cfg(not(debug_assertions))]
 const CONFIG_PATH: &str = "/etc/config-production.toml";
 
+/// State of a run, shared by the connections of the monitors sending its alerts
+struct Run {
+    run_id: u64,
+    start_time: f64,
+    alert_rate_limit: Option<RateLimit>,
+    /// Alert routing of the monitors, whose labels the alerts are tagged with
+    routing: Vec<RouteRule>,
+    alert_protocol: Mutex<AlertProtocol>,
+    dropped_alerts: AtomicU64,
+    unpersisted_alerts: AtomicU64,
+    alert_bytes: AlertBytes,
+    /// Bytes exchanged with the monitors once they identified their run
+    monitor_bytes: Arc<ByteCounter>,
+    /// Exporter of the spans of the receipts of the alerts
+    telemetry: Telemetry,
+}
+
+/// Bytes of alerts received during a run, and the bytes they would have taken in plain encoding
+#[derive(Default)]
+struct AlertBytes {
+    received: AtomicU64,
+    uncompressed: AtomicU64,
+}
+
 #[derive(Deserialize)]
 struct CloudServerParameters {
     test_driver_listen_address: SocketAddr,
+    /// Grace period for the alerts still in flight at the end of a run, none if absent
+    #[serde(default)]
+    alert_drain_timeout_ms: u64,
+    /// Limit of alerts accepted per monitor connection, no limit if absent
+    alert_rate_limit: Option<RateLimit>,
+    /// Limits of the size of the alert protocol on disk, unlimited if absent
+    alert_protocol_limits: Option<AlertProtocolLimits>,
+    /// Keep the monitor listener bound across runs, assigning connections to runs by their run id
+    #[serde(default)]
+    persistent_monitor_listener: bool,
+    /// Number of threads accepting monitor connections on the persistent listener, each on its
+    /// own socket bound to the address with SO_REUSEPORT; a single plain listener if absent
+    monitor_accept_threads: Option<usize>,
 }
 
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::init();
     let cloud_server_parameters: CloudServerParameters =
         toml::from_str(&fs::read_to_string(CONFIG_PATH).expect("Could not read config file"))
             .expect("Could not parse config file");
+    let arguments: Vec<String> = std::env::args().collect();
+    let telemetry = Telemetry::new("cloud_server", get_otel_endpoint(&arguments).as_deref());
     let listener = TcpListener::bind(cloud_server_parameters.test_driver_listen_address)
         .unwrap_or_else(|_| {
             panic!(
@@ -35,23 +95,115 @@ fn main() {
         "Listening on {}",
         cloud_server_parameters.test_driver_listen_address
     );
+    if let Some(monitor_accept_threads) = cloud_server_parameters.monitor_accept_threads {
+        assert!(
+            monitor_accept_threads > 0,
+            "monitor_accept_threads has to be at least 1"
+        );
+    }
+    let mut persistent_monitor_listener: Option<PersistentMonitorListener> = None;
     for control_stream in listener.incoming() {
         match control_stream {
             Ok(mut control_stream) => {
                 info!("New run");
+                // The test driver aborts without sending parameters if the clocks disagree
                 let run_parameters =
-                    utils::read_object::<CloudServerRunParameters>(&mut control_stream)
-                        .expect("Could not get run parameters");
-                let thread_handle = thread::spawn(move || {
-                    execute_new_run(run_parameters.motor_monitor_listen_address);
+                    match utils::answer_clock_probe(&mut control_stream).and_then(|_| {
+                        utils::read_object::<CloudServerRunParameters>(&mut control_stream)
+                    }) {
+                        Ok(run_parameters) => run_parameters,
+                        Err(e) => {
+                            error!("Could not get run parameters: {e}");
+                            continue;
+                        }
+                    };
+                utils::resolved_config::log_resolved_config("cloud_server", &run_parameters);
+                let alert_protocol_directory = match utils::create_run_directory(
+                    run_parameters.run_directory.as_deref(),
+                    "cloud_server",
+                ) {
+                    Ok(alert_protocol_directory) => alert_protocol_directory.unwrap_or_default(),
+                    Err(e) => {
+                        error!("Could not create run directory: {e}");
+                        continue;
+                    }
+                };
+                // The listener is bound before acknowledging the parameters,
+                // so a monitor of the run cannot connect before it exists
+                let monitor_listener = match cloud_server_parameters.persistent_monitor_listener {
+                    true => {
+                        get_persistent_monitor_listener(
+                            &mut persistent_monitor_listener,
+                            run_parameters.motor_monitor_listen_address,
+                            cloud_server_parameters.monitor_accept_threads,
+                        );
+                        None
+                    }
+                    false => {
+                        info!("Binding to {}", run_parameters.motor_monitor_listen_address);
+                        match TcpListener::bind(run_parameters.motor_monitor_listen_address) {
+                            Ok(monitor_listener) => Some(monitor_listener),
+                            Err(e) => {
+                                error!(
+                                    "Could not bind to {}: {e}",
+                                    run_parameters.motor_monitor_listen_address
+                                );
+                                continue;
+                            }
+                        }
+                    }
+                };
+                let run_start = match acknowledge_parameters(&mut control_stream, &run_parameters) {
+                    Ok(run_start) => run_start,
+                    Err(e) => {
+                        error!("Could not get run start: {e}");
+                        continue;
+                    }
+                };
+                let run = Arc::new(Run {
+                    run_id: run_parameters.run_id,
+                    start_time: run_start.start_time,
+                    alert_rate_limit: cloud_server_parameters.alert_rate_limit,
+                    routing: run_parameters.routing.clone(),
+                    alert_protocol: Mutex::new(AlertProtocol::create(
+                        alert_protocol_directory,
+                        cloud_server_parameters.alert_protocol_limits,
+                    )),
+                    dropped_alerts: AtomicU64::new(0),
+                    unpersisted_alerts: AtomicU64::new(0),
+                    alert_bytes: AlertBytes::default(),
+                    monitor_bytes: Arc::new(ByteCounter::new()),
+                    telemetry: telemetry.clone(),
                 });
-                thread::sleep(utils::get_duration_to_end(
-                    Duration::from_secs_f64(run_parameters.start_time),
-                    Duration::from_secs_f64(run_parameters.duration),
-                ));
-                info!("Dropping handle");
-                drop(thread_handle);
-                send_alerts_to_driver(&mut control_stream);
+                match monitor_listener {
+                    None => persistent_monitor_listener
+                        .as_ref()
+                        .expect("Persistent monitor listener was not bound")
+                        .register(Arc::clone(&run)),
+                    Some(monitor_listener) => {
+                        let run = Arc::clone(&run);
+                        let number_of_monitors =
+                            1 + run_parameters.secondary_processing_model.is_some() as usize;
+                        thread::spawn(move || {
+                            execute_new_run(monitor_listener, number_of_monitors, run);
+                        });
+                    }
+                }
+                let abort_reason = await_end_of_run(
+                    &mut control_stream,
+                    &run,
+                    Duration::from_secs_f64(run_parameters.duration)
+                        + Duration::from_millis(cloud_server_parameters.alert_drain_timeout_ms),
+                );
+                if let Some(persistent_monitor_listener) = &persistent_monitor_listener {
+                    persistent_monitor_listener.unregister(run.run_id);
+                }
+                // The test driver may be gone after aborting the run
+                if let Err(e) =
+                    send_alerts_to_driver(&mut control_stream, &run, abort_reason.as_deref())
+                {
+                    error!("Could not send alerts to test driver: {e}");
+                }
             }
             Err(e) => {
                 error!("Error: {}", e);
@@ -61,34 +213,476 @@ fn main() {
     }
 }
 
-fn send_alerts_to_driver(control_stream: &mut TcpStream) {
+/// Reads the optional `--otel-endpoint <address>` argument, the collector the receipts of the
+/// alerts are exported to as spans
+fn get_otel_endpoint(arguments: &[String]) -> Option<String> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--otel-endpoint")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive an endpoint after --otel-endpoint")
+                .clone()
+        })
+}
+
+/// Returns the persistent listener, binding it first if there is none for the address yet
+fn get_persistent_monitor_listener(
+    persistent_monitor_listener: &mut Option<PersistentMonitorListener>,
+    monitor_listen_address: SocketAddr,
+    monitor_accept_threads: Option<usize>,
+) -> &PersistentMonitorListener {
+    if persistent_monitor_listener
+        .as_ref()
+        .is_some_and(|listener| listener.address != monitor_listen_address)
+    {
+        warn!(
+            "Monitor listen address changed to {monitor_listen_address}, the previous listener stays bound"
+        );
+        *persistent_monitor_listener = None;
+    }
+    persistent_monitor_listener.get_or_insert_with(|| {
+        PersistentMonitorListener::bind(monitor_listen_address, monitor_accept_threads)
+    })
+}
+
+/// Acknowledges the run parameters to the test driver, and waits for the start of the run
+fn acknowledge_parameters(
+    control_stream: &mut TcpStream,
+    run_parameters: &CloudServerRunParameters,
+) -> Result<RunStart, ReadError> {
+    let parameter_ack = ParameterAck {
+        run_id: run_parameters.run_id,
+        build_info: utils::build_info::get().cloned(),
+    };
     control_stream
-        .write_all(&fs::read("alert_protocol.csv").expect("Could not get alert file bytes"))
-        .expect("Could not send alert file to test driver");
-}
-
-fn execute_new_run(monitor_listen_address: SocketAddr) {
-    let mut alert_protocol = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open("alert_protocol.csv")
-        .expect("Could not open alert protocol for writing");
-    info!("Binding to {monitor_listen_address}");
-    let monitor_listener = TcpListener::bind(monitor_listen_address).unwrap();
-    let alarm_stream = monitor_listener.accept();
-    match alarm_stream {
-        Ok((mut alarm_stream, _)) => {
-            while let Some(alert) = utils::read_object::<Alert>(&mut alarm_stream) {
-                let delay = utils::get_now_duration() - Duration::from_secs_f64(alert.time);
-                info!("Received monitor message, delay: {delay:?}");
-                writeln!(alert_protocol, "{},{}", alert.to_csv(), delay.as_secs_f64())
-                    .expect("Could not write to alert protocol");
+        .write_all(
+            &utils::encode_object(&parameter_ack)
+                .expect("Could not write parameter ack to Vec<u8>"),
+        )
+        .map_err(ReadError::Io)?;
+    utils::read_object::<RunStart>(control_stream)
+}
+
+/// Waits until the run and the drain of its alerts are over, or until the test driver aborts it,
+/// returning the reason of the abort.
+/// Aborts of other runs are ignored, and so is the control connection once it is closed.
+fn await_end_of_run(
+    control_stream: &mut TcpStream,
+    run: &Run,
+    duration_with_drain: Duration,
+) -> Option<String> {
+    let end_time = Duration::from_secs_f64(run.start_time) + duration_with_drain;
+    loop {
+        let remaining = end_time.saturating_sub(utils::get_now_duration());
+        if remaining.is_zero() {
+            return None;
+        }
+        control_stream
+            .set_read_timeout(Some(remaining))
+            .expect("Could not set control connection read timeout");
+        match utils::read_object::<AbortRun>(control_stream) {
+            Ok(abort_run) if abort_run.run_id == run.run_id => {
+                warn!("Run {} was aborted: {}", run.run_id, abort_run.reason);
+                return Some(abort_run.reason);
+            }
+            Ok(abort_run) => info!(
+                "Ignoring abort of run {}, the current run is {}",
+                abort_run.run_id, run.run_id
+            ),
+            Err(ReadError::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => {
+                debug!("Control connection of run {} closed: {e}", run.run_id);
+                thread::sleep(end_time.saturating_sub(utils::get_now_duration()));
+                return None;
+            }
+        }
+    }
+}
+
+/// Streams the segments of the alert protocol to the test driver, each prefixed with its
+/// length (as little endian u64), followed by a segment holding the drop counters and,
+/// if the run was aborted, the reason of the abort
+fn send_alerts_to_driver(
+    control_stream: &mut TcpStream,
+    run: &Run,
+    abort_reason: Option<&str>,
+) -> io::Result<()> {
+    let alert_protocol_directory = run
+        .alert_protocol
+        .lock()
+        .expect("Alert protocol lock was poisoned")
+        .get_directory()
+        .to_path_buf();
+    for segment in alert_protocol::get_segments(&alert_protocol_directory) {
+        let mut segment_file = File::open(&segment).expect("Could not open alert protocol segment");
+        let segment_length = segment_file
+            .metadata()
+            .expect("Could not get alert protocol segment size")
+            .len();
+        control_stream.write_all(&segment_length.to_le_bytes())?;
+        let sent = io::copy(
+            &mut (&mut segment_file).take(segment_length),
+            control_stream,
+        )?;
+        assert_eq!(
+            sent,
+            segment_length,
+            "Alert protocol segment {} was truncated while sending",
+            segment.display()
+        );
+    }
+    let dropped_alerts = run.dropped_alerts.load(Ordering::Relaxed);
+    let unpersisted_alerts = run.unpersisted_alerts.load(Ordering::Relaxed);
+    let unwritten_alerts = run
+        .alert_protocol
+        .lock()
+        .expect("Alert protocol lock was poisoned")
+        .get_unwritten_alerts();
+    if dropped_alerts > 0 {
+        warn!("Dropped {dropped_alerts} alerts due to the rate limit");
+    }
+    if unpersisted_alerts > 0 {
+        warn!("Did not persist {unpersisted_alerts} alerts due to the disk budget");
+    }
+    if unwritten_alerts > 0 {
+        warn!("Did not persist {unwritten_alerts} alerts as the disk was full");
+    }
+    let monitor_usage = run.monitor_bytes.get_usage();
+    let mut counters = format!(
+        "{DROPPED_ALERTS_LABEL},{dropped_alerts}\n{UNPERSISTED_ALERTS_LABEL},{unpersisted_alerts}\n{UNWRITTEN_ALERTS_LABEL},{unwritten_alerts}\n{ALERT_BYTES_LABEL},{},{}\n{NETWORK_USAGE_LABEL},{},{}\n",
+        run.alert_bytes.received.load(Ordering::Relaxed),
+        run.alert_bytes.uncompressed.load(Ordering::Relaxed),
+        monitor_usage.bytes_written,
+        monitor_usage.bytes_read
+    );
+    if let Some(abort_reason) = abort_reason {
+        counters.push_str(&format!("{ABORTED_LABEL},{abort_reason}\n"));
+    }
+    control_stream.write_all(&(counters.len() as u64).to_le_bytes())?;
+    control_stream.write_all(counters.as_bytes())
+}
+
+/// Binds the monitor listener for this run only, and records the alerts of the first
+/// `number_of_monitors` monitors connecting
+fn execute_new_run(monitor_listener: TcpListener, number_of_monitors: usize, run: Arc<Run>) {
+    for _ in 0..number_of_monitors {
+        match monitor_listener.accept() {
+            Ok((mut alarm_stream, _)) => {
+                let alert_source = match utils::read_object::<AlertSource>(&mut alarm_stream) {
+                    Ok(alert_source) => alert_source,
+                    Err(e) => {
+                        error!("Could not get alert source from monitor: {e}");
+                        continue;
+                    }
+                };
+                if alert_source.run_id != run.run_id {
+                    warn!(
+                        "Monitor sends alerts of run {}, recording them for run {}",
+                        alert_source.run_id, run.run_id
+                    );
+                }
+                let run = Arc::clone(&run);
+                thread::spawn(move || receive_alerts(alarm_stream, &run, alert_source));
+            }
+            Err(e) => {
+                error!("Error: {}", e);
+                /* connection failed */
+            }
+        }
+    }
+}
+
+/// Records the alerts arriving over a monitor connection in the protocol of the run,
+/// tagged with the model of the monitor
+fn receive_alerts(alarm_stream: TcpStream, run: &Run, alert_source: AlertSource) {
+    let mut alarm_stream = CountingStream::new(alarm_stream, run.monitor_bytes.clone());
+    let alert_codec = negotiate_alert_encoding(&mut alarm_stream, run.start_time);
+    let plain_codec = AlertCodec::new(AlertEncoding::Plain, run.start_time);
+    let mut rate_limiter = run
+        .alert_rate_limit
+        .map(|rate_limit| RateLimiter::new(rate_limit, utils::get_now_duration()));
+    while let Ok(detailed_alert) = alert_codec.read_detailed_alert(&mut alarm_stream) {
+        let now = utils::get_now_duration();
+        // The encodings are deterministic, so re-encoding yields the size on the wire
+        run.alert_bytes.received.fetch_add(
+            alert_codec.encode_detailed(&detailed_alert).len() as u64,
+            Ordering::Relaxed,
+        );
+        let alert = &detailed_alert.alert;
+        run.alert_bytes
+            .uncompressed
+            .fetch_add(plain_codec.encode(alert).len() as u64, Ordering::Relaxed);
+        // A recovery is no failure detection, so it is neither rate limited nor has a delay to benchmark
+        if alert.cleared {
+            info!("Motor {} recovered from {}", alert.motor_id, alert.failure);
+            continue;
+        }
+        if let Some(rate_limiter) = rate_limiter.as_mut() {
+            if !rate_limiter.try_acquire(now) {
+                run.dropped_alerts.fetch_add(1, Ordering::Relaxed);
+                continue;
             }
         }
-        Err(e) => {
-            error!("Error: {}", e);
-            /* connection failed */
+        let delay = now - Duration::from_secs_f64(alert.time);
+        info!("Received monitor message, delay: {delay:?}");
+        let _span = run.telemetry.start_span("cloud_receipt", || {
+            vec![
+                ("motor_id", alert.motor_id.into()),
+                ("failure", alert.failure.to_string().into()),
+                ("alert_time", alert.time.into()),
+                ("delay", delay.as_secs_f64().into()),
+                (
+                    "request_processing_model",
+                    alert_source.request_processing_model.to_string().into(),
+                ),
+            ]
+        });
+        if !run
+            .alert_protocol
+            .lock()
+            .expect("Alert protocol lock was poisoned")
+            .append(&format!(
+                "{},{},{}{}{}{}{}",
+                alert.to_csv(),
+                delay.as_secs_f64(),
+                alert_source.request_processing_model.to_string(),
+                get_fingerprint_columns(alert),
+                get_sampled_messages_column(alert),
+                get_window_averages_column(&detailed_alert),
+                get_route_column(&run.routing, alert)
+            ))
+        {
+            run.unpersisted_alerts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    if let Some(rate_limiter) = rate_limiter {
+        debug!(
+            "Monitor connection of run {} closed, {} alerts dropped",
+            run.run_id,
+            rate_limiter.get_dropped()
+        );
+    }
+}
+
+/// Columns of the window fingerprints of a forensic alert, none for other alerts
+fn get_fingerprint_columns(alert: &Alert) -> String {
+    alert
+        .fingerprint
+        .iter()
+        .flatten()
+        .map(|channel_fingerprint| format!(",{channel_fingerprint}"))
+        .collect()
+}
+
+/// Column of the sampled messages an alert was evaluated on, none if it lists none
+fn get_sampled_messages_column(alert: &Alert) -> String {
+    match alert.sampled_messages.is_empty() {
+        true => String::new(),
+        false => format!(
+            ",{SAMPLED_MESSAGES_LABEL}{}",
+            alert
+                .sampled_messages
+                .iter()
+                .map(|sampled_message| sampled_message.to_string())
+                .collect::<Vec<String>>()
+                .join(";")
+        ),
+    }
+}
+
+/// Column of the averages of the windows a detailed alert was evaluated on, none for other alerts
+fn get_window_averages_column(detailed_alert: &DetailedAlert) -> String {
+    match &detailed_alert.averages {
+        Some(averages) => format!(",{WINDOW_AVERAGES_LABEL}{averages}"),
+        None => String::new(),
+    }
+}
+
+/// Column of the label of the route rule the monitor sent the alert by, none if no rule matched it
+fn get_route_column(routing: &[RouteRule], alert: &Alert) -> String {
+    match RouteRule::find(routing, alert.failure) {
+        Some(route_rule) => format!(",{ROUTE_LABEL}{}", route_rule.label),
+        None => String::new(),
+    }
+}
+
+/// Answers the alert encoding requested by the monitor with the one this build supports
+fn negotiate_alert_encoding(
+    alarm_stream: &mut CountingStream<TcpStream>,
+    start_time: f64,
+) -> AlertCodec {
+    let requested_encoding = utils::read_object::<AlertEncoding>(alarm_stream)
+        .expect("Could not get requested alert encoding");
+    let encoding = utils::cloud_connection::get_supported_alert_encoding(requested_encoding);
+    info!("Monitor requested {requested_encoding:?} alerts, receiving {encoding:?} alerts");
+    alarm_stream
+        .write_all(&utils::encode_object(&encoding).expect("Could not encode alert encoding"))
+        .expect("Could not send alert encoding to monitor");
+    AlertCodec::new(encoding, start_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_transfer_objects::{MotorFailure, RequestProcessingModel};
+    use std::env;
+    use std::path::Path;
+
+    const RUN_DURATION: Duration = Duration::from_millis(200);
+    const ALERT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+    pub(crate) fn get_test_run(run_id: u64, start_time: Duration, directory: &Path) -> Arc<Run> {
+        Arc::new(Run {
+            run_id,
+            start_time: start_time.as_secs_f64(),
+            alert_rate_limit: None,
+            routing: vec![],
+            alert_protocol: Mutex::new(AlertProtocol::create(directory.to_path_buf(), None)),
+            dropped_alerts: AtomicU64::new(0),
+            unpersisted_alerts: AtomicU64::new(0),
+            alert_bytes: AlertBytes::default(),
+            monitor_bytes: Arc::new(ByteCounter::new()),
+            telemetry: Telemetry::default(),
+        })
+    }
+
+    /// Connects like a monitor of the run, returning the connection and the negotiated encoding
+    pub(crate) fn connect_monitor(
+        monitor_address: SocketAddr,
+        run_id: u64,
+    ) -> (TcpStream, AlertEncoding) {
+        let mut alarm_stream =
+            TcpStream::connect(monitor_address).expect("Could not connect to monitor listener");
+        let alert_source = AlertSource {
+            run_id,
+            request_processing_model: RequestProcessingModel::ClientServer,
+        };
+        alarm_stream
+            .write_all(&utils::encode_object(&alert_source).unwrap())
+            .unwrap();
+        alarm_stream
+            .write_all(&utils::encode_object(&AlertEncoding::Plain).unwrap())
+            .unwrap();
+        let encoding = utils::read_object::<AlertEncoding>(&mut alarm_stream)
+            .expect("Could not get alert encoding");
+        (alarm_stream, encoding)
+    }
+
+    pub(crate) fn get_test_alert(time: Duration, motor_id: u16) -> Alert {
+        Alert {
+            time: time.as_secs_f64(),
+            motor_id,
+            failure: MotorFailure::PowerFailure,
+            fingerprint: None,
+            cleared: false,
+            sampled_messages: vec![],
+        }
+    }
+
+    /// Sends one alert over a monitor connection, 100 ms after the nominal end of the run
+    fn send_late_alert(monitor_address: SocketAddr, run_start: Duration) {
+        let (mut alarm_stream, encoding) = connect_monitor(monitor_address, 1);
+        let alert_time = run_start + RUN_DURATION + Duration::from_millis(100);
+        thread::sleep(alert_time.saturating_sub(utils::get_now_duration()));
+        alarm_stream
+            .write_all(
+                &AlertCodec::new(encoding, run_start.as_secs_f64())
+                    .encode(&get_test_alert(alert_time, 7)),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn alert_after_end_of_run_is_recorded_during_drain() {
+        let directory = env::temp_dir().join(format!("cloud_server_drain_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let run_start = utils::get_now_duration();
+        let run = get_test_run(1, run_start, &directory);
+        let monitor_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let monitor_address = monitor_listener.local_addr().unwrap();
+        let receiving_run = Arc::clone(&run);
+        thread::spawn(move || execute_new_run(monitor_listener, 1, receiving_run));
+        let monitor = thread::spawn(move || send_late_alert(monitor_address, run_start));
+        // The test driver stays connected without aborting the run
+        let control_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _test_driver = TcpStream::connect(control_listener.local_addr().unwrap()).unwrap();
+        let (mut control_stream, _) = control_listener.accept().unwrap();
+
+        let abort_reason = await_end_of_run(
+            &mut control_stream,
+            &run,
+            RUN_DURATION + ALERT_DRAIN_TIMEOUT,
+        );
+
+        // Read like the alerts sent to the test driver, right at the end of the drain
+        let alert_protocol = fs::read_to_string(directory.join("alert_protocol.csv")).unwrap();
+        monitor.join().expect("Monitor thread panicked");
+        assert_eq!(abort_reason, None);
+        let _ = fs::remove_dir_all(&directory);
+        let recorded_alerts: Vec<&str> = alert_protocol.lines().collect();
+        assert_eq!(recorded_alerts.len(), 1, "{alert_protocol}");
+        assert!(recorded_alerts[0].contains("PowerFailure"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn alerts_unwritten_due_to_a_full_disk_are_reported_to_the_driver() {
+        let directory =
+            env::temp_dir().join(format!("cloud_server_disk_full_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        // Every write to the protocol fails like on a full disk
+        std::os::unix::fs::symlink("/dev/full", directory.join("alert_protocol.csv")).unwrap();
+        let run = get_test_run(1, utils::get_now_duration(), &directory);
+        let monitor_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let monitor_address = monitor_listener.local_addr().unwrap();
+        let receiving_run = Arc::clone(&run);
+        thread::spawn(move || execute_new_run(monitor_listener, 1, receiving_run));
+        let (mut alarm_stream, encoding) = connect_monitor(monitor_address, 1);
+        let alert_codec = AlertCodec::new(encoding, run.start_time);
+        for motor_id in 0..3 {
+            alarm_stream
+                .write_all(
+                    &alert_codec.encode(&get_test_alert(utils::get_now_duration(), motor_id)),
+                )
+                .unwrap();
         }
+        drop(alarm_stream);
+        let get_unwritten_alerts = || run.alert_protocol.lock().unwrap().get_unwritten_alerts();
+        let deadline = utils::get_now_duration() + Duration::from_secs(5);
+        while get_unwritten_alerts() < 3 && utils::get_now_duration() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let control_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut test_driver = TcpStream::connect(control_listener.local_addr().unwrap()).unwrap();
+        let (mut control_stream, _) = control_listener.accept().unwrap();
+
+        send_alerts_to_driver(&mut control_stream, &run, None).unwrap();
+
+        drop(control_stream);
+        let mut received = vec![];
+        test_driver.read_to_end(&mut received).unwrap();
+        let _ = fs::remove_dir_all(&directory);
+        // The empty protocol segment, followed by the counters
+        assert_eq!(received[..8], 0u64.to_le_bytes());
+        let counters = String::from_utf8_lossy(&received[16..]);
+        assert!(
+            counters.contains(&format!("{UNWRITTEN_ALERTS_LABEL},3\n")),
+            "{counters}"
+        );
+    }
+
+    #[test]
+    fn config_without_the_optional_settings_is_accepted() {
+        let cloud_server_parameters: CloudServerParameters =
+            toml::from_str(r#"test_driver_listen_address = "0.0.0.0:8001""#).unwrap();
+        assert_eq!(cloud_server_parameters.alert_drain_timeout_ms, 0);
+        assert!(!cloud_server_parameters.persistent_monitor_listener);
     }
 }
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::{error, info, warn};
+use socket2::{Domain, Socket, Type};
+
+use data_transfer_objects::AlertSource;
+
+use crate::{receive_alerts, Run};
+
+type Runs = Arc<Mutex<HashMap<u64, Arc<Run>>>>;
+
+/// Monitor listener which stays bound across runs.
+/// Each monitor connection starts with the id of its run, and its alerts are recorded
+/// in the protocol of that run; connections of unknown runs are closed.
+/// With multiple accepting threads, each accepts on its own socket bound with SO_REUSEPORT,
+/// leaving the kernel to balance the connections across them.
+pub struct PersistentMonitorListener {
+    pub address: SocketAddr,
+    runs: Runs,
+}
+
+impl PersistentMonitorListener {
+    pub fn bind(address: SocketAddr, accept_threads: Option<usize>) -> PersistentMonitorListener {
+        let monitor_listeners = match accept_threads {
+            None => {
+                info!("Binding persistently to {address}");
+                vec![TcpListener::bind(address).unwrap()]
+            }
+            Some(accept_threads) => {
+                info!("Binding persistently to {address} with {accept_threads} accepting threads");
+                (0..accept_threads)
+                    .map(|_| {
+                        bind_reusing_port(address).unwrap_or_else(|e| {
+                            panic!("Could not bind to {address} with SO_REUSEPORT: {e}")
+                        })
+                    })
+                    .collect()
+            }
+        };
+        let runs: Runs = Arc::new(Mutex::new(HashMap::new()));
+        for monitor_listener in monitor_listeners {
+            let acceptor_runs = Arc::clone(&runs);
+            thread::spawn(move || {
+                for alarm_stream in monitor_listener.incoming() {
+                    match alarm_stream {
+                        Ok(alarm_stream) => {
+                            let runs = Arc::clone(&acceptor_runs);
+                            thread::spawn(move || handle_connection(alarm_stream, &runs));
+                        }
+                        Err(e) => error!("Error: {}", e),
+                    }
+                }
+            });
+        }
+        PersistentMonitorListener { address, runs }
+    }
+
+    pub fn register(&self, run: Arc<Run>) {
+        self.runs
+            .lock()
+            .expect("Run registry lock was poisoned")
+            .insert(run.run_id, run);
+    }
+
+    pub fn unregister(&self, run_id: u64) {
+        self.runs
+            .lock()
+            .expect("Run registry lock was poisoned")
+            .remove(&run_id);
+    }
+}
+
+fn bind_reusing_port(address: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(address), Type::STREAM, None)?;
+    socket.set_reuse_port(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(128)?;
+    Ok(socket.into())
+}
+
+fn handle_connection(mut alarm_stream: TcpStream, runs: &Runs) {
+    let alert_source = match utils::read_object::<AlertSource>(&mut alarm_stream) {
+        Ok(alert_source) => alert_source,
+        Err(e) => {
+            error!("Could not get alert source from monitor: {e}");
+            return;
+        }
+    };
+    let run = runs
+        .lock()
+        .expect("Run registry lock was poisoned")
+        .get(&alert_source.run_id)
+        .cloned();
+    match run {
+        Some(run) => receive_alerts(alarm_stream, &run, alert_source),
+        None => warn!(
+            "Monitor connected for unknown run {}, closing connection",
+            alert_source.run_id
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+    use std::{env, fs, process};
+
+    use data_transfer_objects::{AlertEncoding, RequestProcessingModel};
+    use utils::cloud_connection::AlertCodec;
+
+    use crate::tests::{connect_monitor, get_test_alert, get_test_run};
+
+    use super::*;
+
+    fn get_free_address() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    fn send_alert(monitor_address: SocketAddr, run: &Run, motor_id: u16) {
+        let (mut alarm_stream, encoding) = connect_monitor(monitor_address, run.run_id);
+        alarm_stream
+            .write_all(
+                &AlertCodec::new(encoding, run.start_time)
+                    .encode(&get_test_alert(utils::get_now_duration(), motor_id)),
+            )
+            .unwrap();
+    }
+
+    /// Connects like a monitor of the run, returning whether the listener closed the connection
+    /// instead of negotiating the alert encoding
+    fn is_turned_away(monitor_address: SocketAddr, run_id: u64) -> bool {
+        let mut alarm_stream = TcpStream::connect(monitor_address).unwrap();
+        let alert_source = AlertSource {
+            run_id,
+            request_processing_model: RequestProcessingModel::ClientServer,
+        };
+        alarm_stream
+            .write_all(&utils::encode_object(&alert_source).unwrap())
+            .unwrap();
+        // The listener may already have closed the connection
+        let _ = alarm_stream.write_all(&utils::encode_object(&AlertEncoding::Plain).unwrap());
+        alarm_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        !matches!(alarm_stream.read(&mut [0; 1]), Ok(1..))
+    }
+
+    /// Waits for the alert protocol in the directory to hold the number of alerts
+    fn await_recorded_alerts(directory: &Path, alerts: usize) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let recorded_alerts: Vec<String> =
+                fs::read_to_string(directory.join("alert_protocol.csv"))
+                    .unwrap_or_default()
+                    .lines()
+                    .map(str::to_string)
+                    .collect();
+            if recorded_alerts.len() >= alerts || Instant::now() > deadline {
+                return recorded_alerts;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn back_to_back_runs_record_their_alerts_under_their_run_id() {
+        let directory = env::temp_dir().join(format!("cloud_server_persistent_{}", process::id()));
+        let first_directory = directory.join("1");
+        let second_directory = directory.join("2");
+        fs::create_dir_all(&first_directory).unwrap();
+        fs::create_dir_all(&second_directory).unwrap();
+        let listener = PersistentMonitorListener::bind(get_free_address(), None);
+
+        let first_run = get_test_run(1, utils::get_now_duration(), &first_directory);
+        listener.register(Arc::clone(&first_run));
+        send_alert(listener.address, &first_run, 1);
+        let first_alerts = await_recorded_alerts(&first_directory, 1);
+        listener.unregister(1);
+
+        let second_run = get_test_run(2, utils::get_now_duration(), &second_directory);
+        listener.register(Arc::clone(&second_run));
+        // A late monitor of the first run is turned away instead of recording into the second run
+        let late_monitor_turned_away = is_turned_away(listener.address, 1);
+        send_alert(listener.address, &second_run, 2);
+        let second_alerts = await_recorded_alerts(&second_directory, 1);
+        listener.unregister(2);
+        let _ = fs::remove_dir_all(&directory);
+
+        assert_eq!(first_alerts.len(), 1, "{first_alerts:?}");
+        assert!(first_alerts[0].starts_with("1,"), "{first_alerts:?}");
+        assert_eq!(second_alerts.len(), 1, "{second_alerts:?}");
+        assert!(second_alerts[0].starts_with("2,"), "{second_alerts:?}");
+        assert!(late_monitor_turned_away);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn both_reusing_listeners_accept_connections() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        const CONNECTIONS: usize = 64;
+        let address = get_free_address();
+        let accepted: Vec<Arc<AtomicUsize>> = (0..2)
+            .map(|_| {
+                let monitor_listener = bind_reusing_port(address).unwrap();
+                let accepted = Arc::new(AtomicUsize::new(0));
+                let acceptor_accepted = Arc::clone(&accepted);
+                thread::spawn(move || {
+                    for alarm_stream in monitor_listener.incoming() {
+                        drop(alarm_stream.unwrap());
+                        acceptor_accepted.fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+                accepted
+            })
+            .collect();
+        // The kernel balances the connections by their source port
+        let _alarm_streams: Vec<TcpStream> = (0..CONNECTIONS)
+            .map(|_| TcpStream::connect(address).unwrap())
+            .collect();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let get_accepted = || -> Vec<usize> {
+            accepted
+                .iter()
+                .map(|accepted| accepted.load(Ordering::SeqCst))
+                .collect()
+        };
+        while get_accepted().iter().sum::<usize>() < CONNECTIONS && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let accepted = get_accepted();
+        assert_eq!(accepted.iter().sum::<usize>(), CONNECTIONS, "{accepted:?}");
+        assert!(
+            accepted.iter().all(|accepted| *accepted > 0),
+            "{accepted:?}"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use log::warn;
+use serde::Deserialize;
+
+/// Interval in which the number of dropped alerts is logged at most once
+const WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct RateLimit {
+    pub alerts_per_second: f64,
+    pub burst: u32,
+}
+
+/// Token bucket limiting the alerts accepted from one monitor connection.
+/// The bucket is refilled lazily on every alert, so no background thread is needed.
+pub struct RateLimiter {
+    rate_limit: RateLimit,
+    tokens: f64,
+    last_refill: Duration,
+    last_warning: Duration,
+    dropped_since_warning: u64,
+    dropped: u64,
+}
+
+impl RateLimiter {
+    pub fn new(rate_limit: RateLimit, now: Duration) -> RateLimiter {
+        RateLimiter {
+            rate_limit,
+            tokens: rate_limit.burst as f64,
+            last_refill: now,
+            last_warning: now,
+            dropped_since_warning: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Returns whether an alert arriving at `now` may be recorded, counting it as dropped otherwise
+    pub fn try_acquire(&mut self, now: Duration) -> bool {
+        let elapsed = now.saturating_sub(self.last_refill);
+        self.tokens = f64::min(
+            self.tokens + elapsed.as_secs_f64() * self.rate_limit.alerts_per_second,
+            self.rate_limit.burst as f64,
+        );
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return true;
+        }
+        self.dropped += 1;
+        self.dropped_since_warning += 1;
+        if now.saturating_sub(self.last_warning) >= WARNING_INTERVAL {
+            warn!(
+                "Dropped {} alerts exceeding {} alerts/s (burst {})",
+                self.dropped_since_warning,
+                self.rate_limit.alerts_per_second,
+                self.rate_limit.burst
+            );
+            self.dropped_since_warning = 0;
+            self.last_warning = now;
+        }
+        false
+    }
+
+    pub fn get_dropped(&self) -> u64 {
+        self.dropped
+    }
+}
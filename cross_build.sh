@@ -1,4 +1,6 @@
 CROSS_CONTAINER_ENGINE=podman
+# The build containers may lack git, so the commit is passed in for the build metadata
+export BUILD_GIT_HASH=$(git rev-parse HEAD)
 
 cd motor_driver
 cross build --target aarch64-unknown-linux-gnu --release
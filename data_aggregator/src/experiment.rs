@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::{read_dir, DirEntry};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use polars::datatypes::DataType;
+use polars::frame::DataFrame;
+use polars::prelude::{CsvReader, Schema, SerReader, Series};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+
+use data_transfer_objects::RequestProcessingModel;
+
+use crate::OutlierRun;
+
+/// Parameters of a run, in the order they appear in the names of its result files
+pub const PARAMETERS: [&str; 6] = [
+    "motor_groups",
+    "duration",
+    "window_size",
+    "window_sampling_interval",
+    "sensor_sampling_interval",
+    "thread_pool_size",
+];
+
+/// Unit of the window size of the runs, which the result file names mark by prefixing the number
+/// of readings of a count-based window with `c`
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum WindowUnit {
+    Milliseconds,
+    /// Readings per sensor
+    Readings,
+}
+
+/// Values of a metric in a result file, e.g. one per run for the resource usage,
+/// or one per alert for the alert delays
+pub type MetricData = Series;
+
+/// Result files a metric is read from
+pub enum Source {
+    /// The resource usage files (`ru`), with one line per run, the metric is derived from
+    ResourceUsage(fn(&DataFrame) -> Series),
+    /// Files of comma separated values containing the marker, e.g. `ad` for the alert delays
+    Values(&'static str),
+    /// Files containing the marker, e.g. `nu` for the network usage, which the values are parsed from
+    Parsed(&'static str, fn(&str) -> Result<MetricData, String>),
+}
+
+pub struct Metric {
+    pub name: &'static str,
+    pub source: Source,
+    /// Name of the diagram of the number of values per result file, if it is plotted
+    pub count_name: Option<&'static str>,
+}
+
+/// Values of a metric in a single result file, with the parameters of the run they belong to
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub params: BTreeMap<String, usize>,
+    /// The benchmarked system, i.e. the directory the result file was read from
+    pub system: String,
+    pub model: RequestProcessingModel,
+    pub metric: String,
+    pub values: MetricData,
+    /// Name of the result file the values were read from
+    pub file_name: String,
+    /// Reason for excluding the run, if it is a known outlier
+    pub outlier: Option<String>,
+    pub window_unit: WindowUnit,
+}
+
+/// All observations of a benchmark suite execution, independent of how they are plotted
+pub struct Experiment {
+    observations: Vec<Observation>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ResultFrame<T> {
+    pub independent_variable: usize,
+    pub processing_model: RequestProcessingModel,
+    /// Name of the result file the data was read from
+    pub file_name: String,
+    /// Reason for excluding the run, if it is a known outlier
+    pub outlier: Option<String>,
+    pub data: T,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ResultDiagram<T> {
+    pub independent_variable: usize,
+    pub frames: Vec<ResultFrame<T>>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ResultRow<T> {
+    pub independent_variable: usize,
+    pub results: Vec<ResultDiagram<T>>,
+}
+
+pub type ResultMatrix<T> = Vec<ResultRow<T>>;
+
+impl Experiment {
+    /// Reads the result files of the metrics from the directory, each file once,
+    /// returning the result files which could not be read.
+    /// Only the runs whose window size is of the unit are kept, as the window sizes of the
+    /// time-based and count-based windows cannot share an axis.
+    pub fn load(
+        system: &str,
+        directory: &str,
+        metrics: &[Metric],
+        outlier_runs: &[OutlierRun],
+        window_unit: WindowUnit,
+    ) -> (Experiment, Vec<String>) {
+        let mut observations = vec![];
+        let mut read_errors = vec![];
+        if metrics
+            .iter()
+            .any(|metric| matches!(metric.source, Source::ResourceUsage(_)))
+        {
+            let (mut resource_usage, mut errors) =
+                load_resource_usage(system, directory, metrics, outlier_runs);
+            observations.append(&mut resource_usage);
+            read_errors.append(&mut errors);
+        }
+        for metric in metrics {
+            let (file_name_marker, parse_values) = match metric.source {
+                Source::ResourceUsage(_) => continue,
+                Source::Values(file_name_marker) => (file_name_marker, parse_csv_values as _),
+                Source::Parsed(file_name_marker, parse_values) => (file_name_marker, parse_values),
+            };
+            let (mut values, mut errors) = load_values(
+                system,
+                directory,
+                metric.name,
+                file_name_marker,
+                parse_values,
+                outlier_runs,
+            );
+            observations.append(&mut values);
+            read_errors.append(&mut errors);
+        }
+        observations.retain(|observation| observation.window_unit == window_unit);
+        (Experiment { observations }, read_errors)
+    }
+
+    /// Pivots the observations of the metric into rows of the `y_outer` parameter, diagrams of the
+    /// `x_outer` parameter and frames of the `x_inner` parameter, each sorted by their parameter.
+    /// Frames of the same parameters stay in the order of their file names.
+    pub fn group_by(
+        &self,
+        metric: &str,
+        x_inner: &str,
+        x_outer: Option<&str>,
+        y_outer: Option<&str>,
+    ) -> Result<ResultMatrix<MetricData>, String> {
+        let observations: Vec<&Observation> = self
+            .observations
+            .iter()
+            .filter(|observation| observation.metric == metric)
+            .collect();
+        let Some(first) = observations.first() else {
+            return Err(format!("No observations of {metric}"));
+        };
+        if let Some(other) = observations
+            .iter()
+            .find(|observation| observation.system != first.system)
+        {
+            return Err(format!(
+                "Observations of {metric} come from the systems {} and {}, which the diagrams cannot tell apart",
+                first.system, other.system
+            ));
+        }
+        let get_param = |observation: &Observation, param: Option<&str>| {
+            param.map_or(0, |param| observation.params[param])
+        };
+        let mut rows: BTreeMap<usize, BTreeMap<usize, Vec<ResultFrame<MetricData>>>> =
+            BTreeMap::new();
+        for observation in observations {
+            rows.entry(get_param(observation, y_outer))
+                .or_default()
+                .entry(get_param(observation, x_outer))
+                .or_default()
+                .push(ResultFrame {
+                    independent_variable: get_param(observation, Some(x_inner)),
+                    processing_model: observation.model,
+                    file_name: observation.file_name.clone(),
+                    outlier: observation.outlier.clone(),
+                    data: observation.values.clone(),
+                });
+        }
+        Ok(rows
+            .into_iter()
+            .map(|(row_variable, diagrams)| ResultRow {
+                independent_variable: row_variable,
+                results: diagrams
+                    .into_iter()
+                    .map(|(diagram_variable, mut frames)| {
+                        frames.sort_by_key(|frame| frame.independent_variable);
+                        ResultDiagram {
+                            independent_variable: diagram_variable,
+                            frames,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+}
+
+/// Reads every resource usage file once, deriving an observation of each of the metrics from it
+fn load_resource_usage(
+    system: &str,
+    directory: &str,
+    metrics: &[Metric],
+    outlier_runs: &[OutlierRun],
+) -> (Vec<Observation>, Vec<String>) {
+    let mut schema = Schema::new();
+    schema.with_column("id".parse().unwrap(), DataType::Int64);
+    schema.with_column("utime".parse().unwrap(), DataType::Int64);
+    schema.with_column("stime".parse().unwrap(), DataType::Int64);
+    schema.with_column("cutime".parse().unwrap(), DataType::Int64);
+    schema.with_column("cstime".parse().unwrap(), DataType::Int64);
+    schema.with_column("vmhwm".parse().unwrap(), DataType::Int64);
+    schema.with_column("vmpeak".parse().unwrap(), DataType::Int64);
+    schema.with_column("load_average".parse().unwrap(), DataType::Float32);
+    schema.with_column("cpu_utilization".parse().unwrap(), DataType::Float32);
+
+    let schema = Arc::new(schema);
+
+    let read_results = get_relevant_files(directory, "ru")
+        .par_iter()
+        .map(|dir_entry| {
+            let schema = Arc::clone(&schema);
+            let file_name = get_file_name(dir_entry);
+            let (params, model, window_unit) = parse_file_name(&file_name)?;
+            let data_frame = CsvReader::from_path(dir_entry.path())
+                .and_then(move |csv_reader| {
+                    csv_reader
+                        .has_header(true)
+                        .with_dtypes(Some(schema))
+                        .finish()
+                })
+                .map_err(|error| format!("{file_name}: {error}"))?;
+            let outlier = get_outlier_reason(&file_name, outlier_runs);
+            Ok(metrics
+                .iter()
+                .filter_map(|metric| match metric.source {
+                    Source::ResourceUsage(extract_data) => Some(Observation {
+                        params: params.clone(),
+                        system: system.to_string(),
+                        model,
+                        metric: metric.name.to_string(),
+                        values: extract_data(&data_frame),
+                        file_name: file_name.clone(),
+                        outlier: outlier.clone(),
+                        window_unit,
+                    }),
+                    Source::Values(_) | Source::Parsed(..) => None,
+                })
+                .collect::<Vec<Observation>>())
+        })
+        .collect::<Vec<Result<Vec<Observation>, String>>>();
+    let (observations, read_errors) = partition_read_results(read_results);
+    (observations.into_iter().flatten().collect(), read_errors)
+}
+
+fn load_values(
+    system: &str,
+    directory: &str,
+    metric: &str,
+    file_name_marker: &str,
+    parse_values: fn(&str) -> Result<MetricData, String>,
+    outlier_runs: &[OutlierRun],
+) -> (Vec<Observation>, Vec<String>) {
+    let read_results = get_relevant_files(directory, file_name_marker)
+        .par_iter()
+        .map(|dir_entry| {
+            let file_name = get_file_name(dir_entry);
+            let (params, model, window_unit) = parse_file_name(&file_name)?;
+            let values = fs::read_to_string(dir_entry.path())
+                .map_err(|error| error.to_string())
+                .and_then(|content| parse_values(&content))
+                .map_err(|error| format!("{file_name}: {error}"))?;
+            Ok(Observation {
+                params,
+                system: system.to_string(),
+                model,
+                metric: metric.to_string(),
+                values,
+                outlier: get_outlier_reason(&file_name, outlier_runs),
+                file_name,
+                window_unit,
+            })
+        })
+        .collect::<Vec<Result<Observation, String>>>();
+    partition_read_results(read_results)
+}
+
+fn get_file_name(dir_entry: &DirEntry) -> String {
+    dir_entry
+        .file_name()
+        .into_string()
+        .expect("Result file should have UTF-8 name")
+}
+
+/// Parameters and processing model of a result file named
+/// `{motor_groups}_{duration}_..._{thread_pool_size}_{request_processing_model}_{dataset}.csv`
+fn parse_file_name(
+    file_name: &str,
+) -> Result<(BTreeMap<String, usize>, RequestProcessingModel, WindowUnit), String> {
+    let mut window_unit = WindowUnit::Milliseconds;
+    let values: Vec<usize> = file_name
+        .split('_')
+        .filter_map(|token| {
+            if let Some(window_count) = token
+                .strip_prefix('c')
+                .and_then(|window_count| window_count.parse().ok())
+            {
+                window_unit = WindowUnit::Readings;
+                return Some(window_count);
+            }
+            token.parse().ok()
+        })
+        .collect();
+    if values.len() < PARAMETERS.len() {
+        return Err(format!(
+            "{file_name}: expected {} parameters in the file name, found {}",
+            PARAMETERS.len(),
+            values.len()
+        ));
+    }
+    let params = PARAMETERS
+        .iter()
+        .zip(values)
+        .map(|(param, value)| (param.to_string(), value))
+        .collect();
+    let model = file_name
+        .split('_')
+        .nth(PARAMETERS.len())
+        .and_then(|model| RequestProcessingModel::from_str(model).ok())
+        .ok_or_else(|| format!("{file_name}: no request processing model in the file name"))?;
+    Ok((params, model, window_unit))
+}
+
+fn get_outlier_reason(file_name: &str, outlier_runs: &[OutlierRun]) -> Option<String> {
+    outlier_runs
+        .iter()
+        .find(|outlier_run| file_name.starts_with(&outlier_run.filename_prefix))
+        .map(|outlier_run| outlier_run.reason.clone())
+}
+
+/// Separates the successfully read result files from the errors of the unreadable ones
+fn partition_read_results<T>(read_results: Vec<Result<T, String>>) -> (Vec<T>, Vec<String>) {
+    let mut result_set = vec![];
+    let mut read_errors = vec![];
+    for read_result in read_results {
+        match read_result {
+            Ok(result) => result_set.push(result),
+            Err(read_error) => read_errors.push(read_error),
+        }
+    }
+    (result_set, read_errors)
+}
+
+/// Lists the result files containing the marker, sorted by name so the aggregation is deterministic
+fn get_relevant_files(directory: &str, file_name_marker: &str) -> Vec<DirEntry> {
+    let mut relevant_files: Vec<DirEntry> = read_dir(directory)
+        .expect("Raw data directory should exist and be readable")
+        .filter_map(|dir_entry| dir_entry.ok())
+        .filter_map(|dir_entry| {
+            if let Ok(file_name) = dir_entry.file_name().into_string() {
+                if file_name.contains(file_name_marker) && file_name.ends_with(".csv") {
+                    return Some(dir_entry);
+                }
+            }
+            None
+        })
+        .collect();
+    relevant_files.sort_by_key(|dir_entry| dir_entry.file_name());
+    relevant_files
+}
+
+fn parse_csv_values(content: &str) -> Result<Series, String> {
+    content
+        .split(',')
+        .filter(|token| !token.is_empty())
+        .map(|token| f64::from_str(token).map_err(|error| error.to_string()))
+        .collect()
+}
@@ -1,184 +1,632 @@
-use std::cmp::Ordering;
-use std::env::Args;
+use std::collections::BTreeMap;
 use std::fs;
-use std::fs::{read_dir, DirEntry, OpenOptions};
+use std::fs::OpenOptions;
 use std::io::Write;
+use std::num::NonZeroUsize;
 use std::ops::Range;
 use std::str::FromStr;
-use std::sync::Arc;
 
+use plotters::coord::Shift;
 use plotters::prelude::{
-    Boxplot, ChartBuilder, Circle, IntoDrawingArea, IntoLogRange, Quartiles, SVGBackend, BLACK,
-    BLUE, GREEN, RED, WHITE,
+    BitMapBackend, Boxplot, ChartBuilder, Circle, Color, DrawingArea, DrawingBackend, ErrorBar,
+    IntoDrawingArea, IntoLogRange, Quartiles, SVGBackend, TriangleMarker, BLACK, BLUE, GREEN, RED,
+    WHITE,
 };
 use polars::datatypes::DataType;
 use polars::export::ahash::{HashMap, HashMapExt};
 use polars::frame::DataFrame;
 use polars::prelude::Series;
-use polars::prelude::{ChunkVar, SerReader};
-use polars::prelude::{CsvReader, Schema};
-use statrs::distribution::{ContinuousCDF, StudentsT};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use serde::Deserialize;
 
+use data_aggregator::stats::{
+    decimate, get_confidence_interval, get_quartiles, t_test, ConfidenceInterval,
+    SIGNIFICANCE_LEVEL,
+};
 use data_transfer_objects::RequestProcessingModel;
 
+use crate::experiment::{
+    Experiment, Metric, MetricData, ResultDiagram, ResultFrame, ResultMatrix, ResultRow, Source,
+    WindowUnit, PARAMETERS,
+};
+
+mod experiment;
+
 const RAW_DATA_PATH: &str = "../bench_executor/";
-const X_LABEL: &str = "Window Size";
 
-const SIGNIFICANCE_LEVEL: f64 = 0.05;
+/// Name of the benchmarked system, i.e. of the directory its results are read from
+const SYSTEM: &str = "bench_executor";
+
+/// Metrics which are aggregated, in the order they are aggregated in
+const METRICS: [Metric; 7] = [
+    Metric {
+        name: "processing_time",
+        source: Source::ResourceUsage(get_processing_time),
+        count_name: None,
+    },
+    Metric {
+        name: "memory_usage",
+        source: Source::ResourceUsage(get_memory_usage),
+        count_name: None,
+    },
+    Metric {
+        name: "load_average",
+        source: Source::ResourceUsage(get_load_average),
+        count_name: None,
+    },
+    Metric {
+        name: "cpu_utilization",
+        source: Source::ResourceUsage(get_cpu_utilization),
+        count_name: None,
+    },
+    Metric {
+        name: "alert_delays",
+        source: Source::Values("ad"),
+        count_name: Some("number of alerts"),
+    },
+    Metric {
+        name: "alert_failures",
+        source: Source::Values("af"),
+        count_name: None,
+    },
+    Metric {
+        name: "bytes_transferred",
+        source: Source::Parsed("nu", get_bytes_transferred),
+        count_name: None,
+    },
+];
 
+/// Parameters of the file names the axes of the diagrams are made up of
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
-struct ResultFrame<T> {
-    independent_variable: usize,
-    processing_model: RequestProcessingModel,
-    data: T,
+struct Axes {
+    x_inner: &'static str,
+    x_outer: Option<&'static str>,
+    y_outer: Option<&'static str>,
+    /// Unit of the window size, the runs of the other unit being left out
+    window_unit: WindowUnit,
+}
+
+#[derive(Deserialize, Debug)]
+struct OutlierRun {
+    filename_prefix: String,
+    reason: String,
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
-struct ResultDiagram<T> {
-    independent_variable: usize,
-    frames: Vec<ResultFrame<T>>,
+/// Format the diagrams are rendered in, with none being rendered for `DataOnly`
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum OutputFormat {
+    Svg,
+    Png,
+    DataOnly,
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
-struct ResultRow<T> {
-    independent_variable: usize,
-    results: Vec<ResultDiagram<T>>,
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(OutputFormat::Svg),
+            "png" => Ok(OutputFormat::Png),
+            "data-only" => Ok(OutputFormat::DataOnly),
+            _ => Err(format!("Unknown output format {s}")),
+        }
+    }
 }
 
-type ResultMatrix<T> = Vec<ResultRow<T>>;
+impl OutputFormat {
+    fn get_figure_path(self, figure_name: &str) -> String {
+        match self {
+            OutputFormat::Png => format!("figures/{figure_name}.png"),
+            _ => format!("figures/{figure_name}.svg"),
+        }
+    }
+}
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
-struct Axes {
-    x_inner: usize,
-    x_outer: Option<usize>,
-    y_outer: Option<usize>,
+enum Comparison {
+    Equal,
+    DeclarativeBetter,
+    ImperativeBetter,
 }
 
 fn main() {
-    let axis_indices = get_axes_indices(&mut std::env::args());
-    aggregate_data("processing_time", &axis_indices, |data_frame| {
-        &(&(&data_frame["utime"] + &data_frame["stime"]) + &data_frame["cutime"])
-            + &data_frame["cstime"]
-    });
-    aggregate_data("memory_usage", &axis_indices, |data_frame| {
-        data_frame["vmhwm"].clone()
-    });
-    aggregate_data("load_average", &axis_indices, |data_frame| {
-        data_frame["load_average"].clone()
-    });
-    aggregate_series("ad", "alert_delays", &axis_indices);
-}
-
-fn get_axes_indices(args: &mut Args) -> Axes {
+    let mut args: Vec<String> = std::env::args().collect();
+    if get_flag(&mut args, "--version-full") {
+        println!("{}", data_transfer_objects::build_info!());
+        return;
+    }
+    let outlier_runs = get_outlier_runs(&mut args);
+    let with_confidence_intervals = get_flag(&mut args, "--confidence-intervals");
+    let output_format = get_output_format(&mut args);
+    let jobs = get_jobs(&mut args);
+    let min_samples = get_min_samples(&mut args);
+    let decimation = get_decimation(&mut args);
+    let window_unit = match get_flag(&mut args, "--count-windows") {
+        true => WindowUnit::Readings,
+        false => WindowUnit::Milliseconds,
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build_global()
+        .expect("Could not build thread pool");
+    let axes = get_axes(&mut args.into_iter(), window_unit);
+    let (experiment, read_errors) = Experiment::load(
+        SYSTEM,
+        RAW_DATA_PATH,
+        &METRICS,
+        &outlier_runs,
+        axes.window_unit,
+    );
+    let aggregation_errors: Vec<String> = METRICS
+        .iter()
+        .filter_map(|metric| {
+            aggregate_metric(
+                &experiment,
+                metric,
+                &axes,
+                with_confidence_intervals,
+                output_format,
+                min_samples,
+                decimation,
+            )
+            .err()
+        })
+        .collect();
+    for aggregation_error in &aggregation_errors {
+        eprintln!("{aggregation_error}");
+    }
+    if !read_errors.is_empty() {
+        eprintln!("{} result files could not be read:", read_errors.len());
+        for read_error in &read_errors {
+            eprintln!("{read_error}");
+        }
+    }
+    if !aggregation_errors.is_empty() || !read_errors.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn get_processing_time(data_frame: &DataFrame) -> Series {
+    &(&(&data_frame["utime"] + &data_frame["stime"]) + &data_frame["cutime"])
+        + &data_frame["cstime"]
+}
+
+fn get_memory_usage(data_frame: &DataFrame) -> Series {
+    data_frame["vmhwm"].clone()
+}
+
+fn get_load_average(data_frame: &DataFrame) -> Series {
+    data_frame["load_average"].clone()
+}
+
+/// Resource usage files of sweeps before the CPU utilization was recorded lack it, and yield no values
+fn get_cpu_utilization(data_frame: &DataFrame) -> Series {
+    data_frame
+        .column("cpu_utilization")
+        .cloned()
+        .unwrap_or_else(|_| Series::new_empty("cpu_utilization", &DataType::Float32))
+}
+
+/// Bytes all components wrote to their peers per run, from the lines
+/// `{repetition},{component},{peer},{bytes_written},{bytes_read}` of a network usage file
+fn get_bytes_transferred(content: &str) -> Result<Series, String> {
+    let mut bytes_transferred: BTreeMap<usize, u64> = BTreeMap::new();
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let (Some(repetition), Some(bytes_written)) = (fields.first(), fields.get(3)) else {
+            return Err(format!("Malformed network usage line {line}"));
+        };
+        *bytes_transferred
+            .entry(
+                repetition
+                    .parse()
+                    .map_err(|error| format!("{line}: {error}"))?,
+            )
+            .or_default() += bytes_written
+            .parse::<u64>()
+            .map_err(|error| format!("{line}: {error}"))?;
+    }
+    Ok(bytes_transferred
+        .into_values()
+        .map(|bytes| bytes as f64)
+        .collect())
+}
+
+/// Removes the flag from the arguments, returning whether it was present
+fn get_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|arg| arg == flag) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Removes `--jobs <n>` from the arguments, defaulting to the number of available cores
+fn get_jobs(args: &mut Vec<String>) -> usize {
+    match args.iter().position(|arg| arg == "--jobs") {
+        Some(index) => {
+            args.remove(index);
+            args.remove(index)
+                .parse()
+                .expect("Number of jobs should be a positive integer")
+        }
+        None => std::thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1),
+    }
+}
+
+/// Removes `--min-samples <n>` from the arguments, defaulting to the two samples a t-test needs
+fn get_min_samples(args: &mut Vec<String>) -> usize {
+    match args.iter().position(|arg| arg == "--min-samples") {
+        Some(index) => {
+            args.remove(index);
+            let min_samples = args
+                .remove(index)
+                .parse()
+                .expect("Minimum sample count should be a positive integer");
+            assert!(
+                min_samples >= 2,
+                "Minimum sample count should be at least 2"
+            );
+            min_samples
+        }
+        None => 2,
+    }
+}
+
+/// Removes `--decimate <n>` from the arguments, the number of values the quartiles of a result
+/// file are computed from, defaulting to all of them
+fn get_decimation(args: &mut Vec<String>) -> Option<usize> {
+    let index = args.iter().position(|arg| arg == "--decimate")?;
+    args.remove(index);
+    let target_size = args
+        .remove(index)
+        .parse()
+        .expect("Decimation target size should be a positive integer");
+    assert!(
+        target_size >= 1,
+        "Decimation target size should be at least 1"
+    );
+    Some(target_size)
+}
+
+/// Removes `--output-format <svg|png|data-only>` from the arguments, defaulting to SVG
+fn get_output_format(args: &mut Vec<String>) -> OutputFormat {
+    match args.iter().position(|arg| arg == "--output-format") {
+        Some(index) => {
+            args.remove(index);
+            args.remove(index)
+                .parse()
+                .expect("Output format should be one of svg, png or data-only")
+        }
+        None => OutputFormat::Svg,
+    }
+}
+
+/// Removes `--outlier-runs <file>` from the arguments and reads the outlier runs listed in the file
+fn get_outlier_runs(args: &mut Vec<String>) -> Vec<OutlierRun> {
+    match args.iter().position(|arg| arg == "--outlier-runs") {
+        Some(index) => {
+            args.remove(index);
+            let path = args.remove(index);
+            serde_json::from_str(
+                &fs::read_to_string(path).expect("Outlier runs file should be readable"),
+            )
+            .expect("Outlier runs file should be a JSON list of outlier runs")
+        }
+        None => vec![],
+    }
+}
+
+/// Parses the indices of the parameters of the axes, the outer ones being optional
+fn get_axes(args: &mut impl Iterator<Item = String>, window_unit: WindowUnit) -> Axes {
     Axes {
         x_inner: args
             .nth(1)
-            .map(|token| token.parse::<usize>().unwrap())
+            .map(|token| get_parameter(token.parse::<usize>().unwrap()))
             .unwrap(),
-        x_outer: args.next().and_then(|token| token.parse::<usize>().ok()),
-        y_outer: args.next().and_then(|token| token.parse::<usize>().ok()),
+        x_outer: args
+            .next()
+            .and_then(|token| token.parse::<usize>().ok())
+            .map(get_parameter),
+        y_outer: args
+            .next()
+            .and_then(|token| token.parse::<usize>().ok())
+            .map(get_parameter),
+        window_unit,
     }
 }
 
-fn aggregate_data(data_name: &str, axis_indices: &Axes, extract_data: fn(&DataFrame) -> Series) {
+/// Parameter of the file name token at the given index
+fn get_parameter(index: usize) -> &'static str {
+    PARAMETERS
+        .get(index)
+        .unwrap_or_else(|| panic!("No file name token at index {index}"))
+}
+
+/// Label of the parameter, used for the x-axis of the diagrams
+fn get_axis_label(parameter: &str, window_unit: WindowUnit) -> &'static str {
+    match parameter {
+        "motor_groups" => "Motor Groups",
+        "duration" => "Run Duration (in s)",
+        "window_size" => match window_unit {
+            WindowUnit::Milliseconds => "Window Size (in ms)",
+            WindowUnit::Readings => "Window Size (in readings per sensor)",
+        },
+        "window_sampling_interval" => "Window Sampling Interval (in ms)",
+        "sensor_sampling_interval" => "Sensor Sampling Interval (in ms)",
+        "thread_pool_size" => "Thread Pool Size",
+        _ => panic!("No parameter {parameter}"),
+    }
+}
+
+/// Aggregates the observations of the metric for all parameter sets, plotting them along the axes
+fn aggregate_metric(
+    experiment: &Experiment,
+    metric: &Metric,
+    axes: &Axes,
+    with_confidence_intervals: bool,
+    output_format: OutputFormat,
+    min_samples: usize,
+    decimation: Option<usize>,
+) -> Result<(), String> {
+    let result_matrix =
+        experiment.group_by(metric.name, axes.x_inner, axes.x_outer, axes.y_outer)?;
+    let data_name = get_data_name(metric.name, axes.window_unit);
     let mut aggregates: ResultMatrix<Quartiles> = vec![];
-    let result_matrix = get_data_frames(axis_indices, "ru");
+    let mut lengths: ResultMatrix<usize> = vec![];
+    let mut confidence_intervals: ResultMatrix<ConfidenceInterval> = vec![];
     for row in result_matrix {
+        let row_variable = row.independent_variable;
         let mut aggregates_row = ResultRow {
-            independent_variable: row.independent_variable,
+            independent_variable: row_variable,
             results: vec![],
         };
-        for diagram in row.results {
-            let mut aggregate_diagram = ResultDiagram {
-                independent_variable: diagram.independent_variable,
-                frames: vec![],
-            };
-            for frame in diagram.frames.clone() {
-                let data_frame = frame.data;
-                let data_series = extract_data(&data_frame);
-                let aggregate = get_aggregates(&data_series);
-                save_as_csv(
-                    data_name,
-                    row.independent_variable,
-                    diagram.independent_variable,
-                    frame.independent_variable,
-                    frame.processing_model,
-                    &aggregate,
-                );
-                let aggregate_frame = ResultFrame {
-                    independent_variable: frame.independent_variable,
-                    processing_model: frame.processing_model,
-                    data: aggregate,
-                };
-                aggregate_diagram.frames.push(aggregate_frame);
-            }
-            diagram
-                .frames
-                .iter()
-                .fold(HashMap::new(), |mut acc, frame| {
-                    let entry = acc
-                        .entry(frame.independent_variable)
-                        .or_insert((None, None));
-                    if frame.processing_model == RequestProcessingModel::ReactiveStreaming {
-                        entry.0 = Some(&frame.data)
-                    } else {
-                        entry.1 = Some(&frame.data)
-                    }
-                    acc
-                })
-                .iter()
-                .filter(|(_, (rx_frame, oo_frame))| rx_frame.is_some() && oo_frame.is_some())
-                .for_each(|(key, (rx_frame, oo_frame))| {
-                    let rx_series = extract_data(rx_frame.unwrap());
-                    let oo_series = extract_data(oo_frame.unwrap());
-                    let p_value = t_test(&rx_series, &oo_series); //rx > oo
-                    if p_value > SIGNIFICANCE_LEVEL {
-                        let p_value_c = t_test(&oo_series, &rx_series); // oo > rx
-                        if p_value_c > SIGNIFICANCE_LEVEL {
-                            println!(
-                                "Equal performance: {data_name} {} {} {key} {p_value}",
-                                row.independent_variable, diagram.independent_variable
-                            )
-                        } else {
-                            println!(
-                                "Declarative better performance: {data_name} {} {} {key} {p_value}",
-                                row.independent_variable, diagram.independent_variable
-                            )
-                        }
-                    }
-                });
+        let mut lengths_row = ResultRow {
+            independent_variable: row_variable,
+            results: vec![],
+        };
+        let mut confidence_intervals_row = ResultRow {
+            independent_variable: row_variable,
+            results: vec![],
+        };
+        // The diagrams write to distinct CSV files, so they can be aggregated independently
+        let diagrams = row
+            .results
+            .into_par_iter()
+            .map(|diagram| {
+                aggregate_diagram(&data_name, row_variable, diagram, min_samples, decimation)
+            })
+            .collect::<Vec<_>>();
+        for (aggregate_diagram, length_diagram, confidence_interval_diagram) in diagrams {
             aggregates_row.results.push(aggregate_diagram);
+            lengths_row.results.push(length_diagram);
+            confidence_intervals_row
+                .results
+                .push(confidence_interval_diagram);
         }
         aggregates.push(aggregates_row);
+        lengths.push(lengths_row);
+        confidence_intervals.push(confidence_intervals_row);
+    }
+    let x_label = get_axis_label(axes.x_inner, axes.window_unit);
+    plot_aggregate_data(&data_name, x_label, aggregates, output_format);
+    if let Some(count_name) = metric.count_name {
+        let count_name = get_data_name(count_name, axes.window_unit);
+        plot_simple_data(&count_name, x_label, lengths, output_format);
+    }
+    if with_confidence_intervals {
+        plot_confidence_intervals(&data_name, x_label, confidence_intervals, output_format);
     }
-    plot_aggregate_data(data_name, aggregates);
+    Ok(())
 }
 
-fn t_test(series1: &Series, series2: &Series) -> f64 {
-    let min_length = std::cmp::min(series1.len(), series2.len());
-    if min_length < 2 {
-        return 0f64;
+/// Name the CSV files and diagrams of a metric are written to, the ones of count-based windows
+/// being suffixed so that they do not overwrite the ones of time-based windows
+fn get_data_name(name: &str, window_unit: WindowUnit) -> String {
+    match window_unit {
+        WindowUnit::Milliseconds => name.to_string(),
+        WindowUnit::Readings => format!("{name}_count_windows"),
     }
-    let difference = series1.head(Some(min_length)) - series2.head(Some(min_length));
-    let diff_mean = difference.mean().unwrap();
-    let diff_std = match difference.i64() {
-        Ok(i) => i.std(1).unwrap(),
-        Err(_) => match difference.f32() {
-            Ok(i) => i.std(1).unwrap() as f64,
-            Err(_) => difference.f64().unwrap().std(1).unwrap(),
-        },
+}
+
+fn aggregate_diagram(
+    data_name: &str,
+    row_variable: usize,
+    diagram: ResultDiagram<MetricData>,
+    min_samples: usize,
+    decimation: Option<usize>,
+) -> (
+    ResultDiagram<Quartiles>,
+    ResultDiagram<usize>,
+    ResultDiagram<ConfidenceInterval>,
+) {
+    let mut aggregate_diagram = ResultDiagram {
+        independent_variable: diagram.independent_variable,
+        frames: vec![],
     };
-    let sample_size = difference.len() as f64;
-    // println!("diff_mean: {diff_mean}, diff_std: {diff_std}, sample_size: {sample_size}");
-    let t = diff_mean / (diff_std / sample_size.sqrt());
-    let degrees_of_freedom = if sample_size <= 1f64 {
-        1f64
-    } else {
-        sample_size - 1f64
+    let mut length_diagram = ResultDiagram {
+        independent_variable: diagram.independent_variable,
+        frames: vec![],
+    };
+    let mut confidence_interval_diagram = ResultDiagram {
+        independent_variable: diagram.independent_variable,
+        frames: vec![],
     };
-    let t_dist = StudentsT::new(0.0, 1.0, degrees_of_freedom).unwrap();
-    // println!("t: {t} dof: {degrees_of_freedom}");
-    1_f64 - t_dist.cdf(t)
+    for frame in diagram.frames.clone() {
+        let values = get_values(&frame.data);
+        let quartiles = match decimation {
+            Some(target_size) => get_quartiles(&decimate(&values, target_size)),
+            None => get_quartiles(&values),
+        };
+        if frame.outlier.is_none() {
+            save_as_csv(
+                data_name,
+                row_variable,
+                diagram.independent_variable,
+                frame.independent_variable,
+                frame.processing_model,
+                &quartiles,
+            );
+            confidence_interval_diagram.frames.push(ResultFrame {
+                independent_variable: frame.independent_variable,
+                processing_model: frame.processing_model,
+                file_name: frame.file_name.clone(),
+                outlier: None,
+                data: get_confidence_interval(&values),
+            });
+        }
+        let aggregate_frame = ResultFrame {
+            independent_variable: frame.independent_variable,
+            processing_model: frame.processing_model,
+            file_name: frame.file_name.clone(),
+            outlier: frame.outlier.clone(),
+            data: quartiles,
+        };
+        aggregate_diagram.frames.push(aggregate_frame);
+        let length_frame = ResultFrame {
+            independent_variable: frame.independent_variable,
+            processing_model: frame.processing_model,
+            file_name: frame.file_name,
+            outlier: frame.outlier,
+            data: frame.data.len(),
+        };
+        length_diagram.frames.push(length_frame);
+    }
+    compare_processing_models(data_name, row_variable, &diagram, min_samples);
+    (
+        aggregate_diagram,
+        length_diagram,
+        confidence_interval_diagram,
+    )
+}
+
+/// Runs the t-tests between the reactive streaming and the other processing model for
+/// every parameter set of the diagram.
+/// If the diagram contains outlier runs, the results of all parameter sets are additionally
+/// pooled and compared with and without the outliers, reporting whether the significance changes.
+/// Parameter sets with less than `min_samples` samples per model are skipped instead of tested.
+fn compare_processing_models(
+    data_name: &str,
+    row_variable: usize,
+    diagram: &ResultDiagram<MetricData>,
+    min_samples: usize,
+) {
+    let mut pairs = diagram
+        .frames
+        .iter()
+        .fold(HashMap::new(), |mut acc, frame| {
+            let entry = acc
+                .entry(frame.independent_variable)
+                .or_insert((None, None));
+            if frame.processing_model == RequestProcessingModel::ReactiveStreaming {
+                entry.0 = Some(frame)
+            } else {
+                entry.1 = Some(frame)
+            }
+            acc
+        })
+        .into_iter()
+        .filter_map(|(key, (rx_frame, oo_frame))| Some((key, rx_frame?, oo_frame?)))
+        .collect::<Vec<(usize, &ResultFrame<MetricData>, &ResultFrame<MetricData>)>>();
+    pairs.sort_by_key(|(key, _, _)| *key);
+    let mut compared_series = vec![];
+    for (key, rx_frame, oo_frame) in pairs {
+        let rx_series = &rx_frame.data;
+        let oo_series = &oo_frame.data;
+        let outlier = rx_frame.outlier.as_ref().or(oo_frame.outlier.as_ref());
+        let min_length = std::cmp::min(rx_series.len(), oo_series.len());
+        compared_series.push((
+            outlier.is_some(),
+            rx_series.head(Some(min_length)),
+            oo_series.head(Some(min_length)),
+        ));
+        let Some((comparison, p_value)) = compare_performance(rx_series, oo_series, min_samples)
+        else {
+            println!(
+                "Skipped: insufficient data ({} has {} and {} has {} samples, at least {min_samples} required): {data_name} {row_variable} {} {key}",
+                rx_frame.file_name,
+                rx_series.len(),
+                oo_frame.file_name,
+                oo_series.len(),
+                diagram.independent_variable
+            );
+            continue;
+        };
+        match (outlier, comparison) {
+            (Some(reason), _) => println!(
+                "Excluded outlier ({reason}): {data_name} {row_variable} {} {key} {comparison:?} {p_value}",
+                diagram.independent_variable
+            ),
+            (None, Comparison::Equal) => println!(
+                "Equal performance: {data_name} {row_variable} {} {key} {p_value}",
+                diagram.independent_variable
+            ),
+            (None, Comparison::DeclarativeBetter) => println!(
+                "Declarative better performance: {data_name} {row_variable} {} {key} {p_value}",
+                diagram.independent_variable
+            ),
+            (None, Comparison::ImperativeBetter) => {}
+        }
+    }
+    if compared_series.iter().any(|(outlier, _, _)| *outlier) {
+        let with_outliers = pool_series(compared_series.iter())
+            .and_then(|(rx_series, oo_series)| {
+                compare_performance(&rx_series, &oo_series, min_samples)
+            })
+            .map(|(comparison, _)| comparison);
+        let without_outliers =
+            pool_series(compared_series.iter().filter(|(outlier, _, _)| !outlier))
+                .and_then(|(rx_series, oo_series)| {
+                    compare_performance(&rx_series, &oo_series, min_samples)
+                })
+                .map(|(comparison, _)| comparison);
+        let change = if with_outliers == without_outliers {
+            "unchanged"
+        } else {
+            "changed"
+        };
+        println!(
+            "Significance {change} by excluding outliers: {data_name} {row_variable} {} {with_outliers:?} {without_outliers:?}",
+            diagram.independent_variable
+        );
+    }
+}
+
+/// Compares the performance of the two models, or returns `None` if either series has less
+/// than `min_samples` samples
+fn compare_performance(
+    rx_series: &Series,
+    oo_series: &Series,
+    min_samples: usize,
+) -> Option<(Comparison, f64)> {
+    let rx_values = get_values(rx_series);
+    let oo_values = get_values(oo_series);
+    let p_value = t_test(&rx_values, &oo_values, min_samples)?; // rx > oo
+    if p_value <= SIGNIFICANCE_LEVEL {
+        Some((Comparison::ImperativeBetter, p_value))
+    } else if t_test(&oo_values, &rx_values, min_samples)? > SIGNIFICANCE_LEVEL {
+        // oo > rx
+        Some((Comparison::Equal, p_value))
+    } else {
+        Some((Comparison::DeclarativeBetter, p_value))
+    }
+}
+
+fn pool_series<'a>(
+    mut compared_series: impl Iterator<Item = &'a (bool, Series, Series)>,
+) -> Option<(Series, Series)> {
+    let (_, rx_series, oo_series) = compared_series.next()?;
+    let mut rx_pool = rx_series.clone();
+    let mut oo_pool = oo_series.clone();
+    for (_, rx_series, oo_series) in compared_series {
+        rx_pool.append(rx_series).unwrap();
+        oo_pool.append(oo_series).unwrap();
+    }
+    Some((rx_pool, oo_pool))
 }
 
 fn save_as_csv(
@@ -211,276 +659,49 @@ fn save_as_csv(
     .unwrap();
 }
 
-fn aggregate_series(file_name_marker: &str, data_name: &str, axis_indices: &Axes) {
-    let mut aggregates: ResultMatrix<Quartiles> = vec![];
-    let mut lengths: ResultMatrix<usize> = vec![];
-    let result_matrix = get_series(axis_indices, file_name_marker);
-    for row in result_matrix {
-        let mut aggregates_row = ResultRow {
-            independent_variable: row.independent_variable,
-            results: vec![],
-        };
-        let mut lengths_row = ResultRow {
-            independent_variable: row.independent_variable,
-            results: vec![],
-        };
-        for diagram in row.results {
-            let mut aggregate_diagram = ResultDiagram {
-                independent_variable: diagram.independent_variable,
-                frames: vec![],
-            };
-            let mut length_diagram = ResultDiagram {
-                independent_variable: diagram.independent_variable,
-                frames: vec![],
-            };
-            for frame in diagram.frames.clone() {
-                let quartiles = get_aggregates(&frame.data);
-                save_as_csv(
-                    data_name,
-                    row.independent_variable,
-                    diagram.independent_variable,
-                    frame.independent_variable,
-                    frame.processing_model,
-                    &quartiles,
-                );
-                let aggregate_frame = ResultFrame {
-                    independent_variable: frame.independent_variable,
-                    processing_model: frame.processing_model,
-                    data: quartiles,
-                };
-                aggregate_diagram.frames.push(aggregate_frame);
-                let length_frame = ResultFrame {
-                    independent_variable: frame.independent_variable,
-                    processing_model: frame.processing_model,
-                    data: frame.data.len(),
-                };
-                length_diagram.frames.push(length_frame);
-            }
-            diagram
-                .frames
-                .iter()
-                .fold(HashMap::new(), |mut acc, frame| {
-                    let entry = acc
-                        .entry(frame.independent_variable)
-                        .or_insert((None, None));
-                    if frame.processing_model == RequestProcessingModel::ReactiveStreaming {
-                        entry.0 = Some(&frame.data)
-                    } else {
-                        entry.1 = Some(&frame.data)
-                    }
-                    acc
-                })
-                .iter()
-                .for_each(|(key, (rx_series, oo_series))| {
-                    let p_value = t_test(rx_series.unwrap(), oo_series.unwrap()); // rx > oo
-                    if p_value > SIGNIFICANCE_LEVEL {
-                        let p_value_c = t_test(oo_series.unwrap(), rx_series.unwrap()); // oo > rx
-                        if p_value_c > SIGNIFICANCE_LEVEL {
-                            println!(
-                                "Equal performance: {data_name} {} {} {key} {p_value}",
-                                row.independent_variable, diagram.independent_variable
-                            )
-                        } else {
-                            println!(
-                                "Declarative better performance: {data_name} {} {} {key} {p_value}",
-                                row.independent_variable, diagram.independent_variable
-                            )
-                        }
-                    }
-                });
-            aggregates_row.results.push(aggregate_diagram);
-            lengths_row.results.push(length_diagram);
-        }
-        aggregates.push(aggregates_row);
-        lengths.push(lengths_row);
-    }
-    plot_aggregate_data(data_name, aggregates);
-    plot_simple_data("number of alerts", lengths);
-}
-
-fn get_axis_variables(axes: &Axes, file_name: &str) -> Axes {
-    let independent_variables = get_independent_variables(file_name);
-    Axes {
-        x_inner: independent_variables[axes.x_inner],
-        x_outer: axes.x_outer.map(|idx| independent_variables[idx]),
-        y_outer: axes.y_outer.map(|idx| independent_variables[idx]),
-    }
-}
-
-fn get_request_processing_model(file_name: &str) -> RequestProcessingModel {
-    let request_processing_model = file_name.split('_').nth(6).unwrap();
-    RequestProcessingModel::from_str(request_processing_model).unwrap()
-}
-
-fn get_independent_variables(file_name: &str) -> Vec<usize> {
-    file_name
-        .split('_')
-        .filter_map(|token| token.parse().ok())
-        .collect::<Vec<usize>>()
-}
-
-fn get_aggregates(series: &Series) -> Quartiles {
-    if series.is_empty() {
-        Quartiles::new(&[0])
-    } else {
-        match series.f64() {
-            Ok(chunked) => Quartiles::new(&chunked.into_no_null_iter().collect::<Vec<f64>>()),
-            Err(_) => match series.f32() {
-                Ok(chunked) => Quartiles::new(&chunked.into_no_null_iter().collect::<Vec<f32>>()),
-                Err(_) => Quartiles::new(
-                    &series
-                        .i64()
-                        .unwrap()
-                        .into_no_null_iter()
-                        .map(|i| i as i32)
-                        .collect::<Vec<i32>>(),
-                ),
-            },
-        }
-    }
-}
-
-fn get_data_frames(axis_indices: &Axes, file_name_marker: &str) -> ResultMatrix<DataFrame> {
-    let mut schema = Schema::new();
-    schema.with_column("id".parse().unwrap(), DataType::Int64);
-    schema.with_column("utime".parse().unwrap(), DataType::Int64);
-    schema.with_column("stime".parse().unwrap(), DataType::Int64);
-    schema.with_column("cutime".parse().unwrap(), DataType::Int64);
-    schema.with_column("cstime".parse().unwrap(), DataType::Int64);
-    schema.with_column("vmhwm".parse().unwrap(), DataType::Int64);
-    schema.with_column("vmpeak".parse().unwrap(), DataType::Int64);
-    schema.with_column("load_average".parse().unwrap(), DataType::Float32);
-
-    let schema = Arc::new(schema);
-
-    let result_set = get_relevant_files(file_name_marker)
-        .iter()
-        .map(|dir_entry| {
-            let schema = Arc::clone(&schema);
-            let file_name = dir_entry
-                .file_name()
-                .into_string()
-                .expect("Result file should have UTF-8 name");
-            (
-                get_axis_variables(axis_indices, &file_name),
-                get_request_processing_model(&file_name),
-                CsvReader::from_path(dir_entry.path())
-                    .map(move |csv_reader| {
-                        csv_reader
-                            .has_header(true)
-                            .with_dtypes(Some(schema))
-                            .finish()
-                            .expect("Result file should be readable as csv")
-                    })
-                    .expect("Result file should be readable as data frame"),
-            )
-        })
-        .collect::<Vec<(Axes, RequestProcessingModel, DataFrame)>>();
-    data_to_matrix(result_set)
-}
-
-fn get_relevant_files(file_name_marker: &str) -> Vec<DirEntry> {
-    read_dir(RAW_DATA_PATH)
-        .expect("Raw data directory should exist and be readable")
-        .filter_map(|dir_entry| dir_entry.ok())
-        .filter_map(|dir_entry| {
-            if let Ok(file_name) = dir_entry.file_name().into_string() {
-                if file_name.contains(file_name_marker) && file_name.ends_with(".csv") {
-                    return Some(dir_entry);
-                }
-            }
-            None
-        })
+fn get_values(series: &Series) -> Vec<f64> {
+    series
+        .cast(&DataType::Float64)
+        .expect("Series should be castable to f64")
+        .f64()
+        .unwrap()
+        .into_no_null_iter()
         .collect()
 }
 
-fn data_to_matrix<T>(mut result_set: Vec<(Axes, RequestProcessingModel, T)>) -> ResultMatrix<T> {
-    result_set.sort_by(|(axes_1, _, _), (axes_2, _, _)| {
-        if axes_1.y_outer.cmp(&axes_2.y_outer) == Ordering::Equal {
-            if axes_1.x_outer.cmp(&axes_2.x_outer) == Ordering::Equal {
-                axes_1.x_inner.cmp(&axes_2.x_inner)
-            } else {
-                axes_1.x_outer.cmp(&axes_2.x_outer)
-            }
-        } else {
-            axes_1.y_outer.cmp(&axes_2.y_outer)
-        }
-    });
-    let mut result_matrix: ResultMatrix<T> = vec![];
-    let mut last_axes = result_set[0].0;
-    for (axes, request_processing_model, data_frame) in result_set {
-        let frame = ResultFrame {
-            independent_variable: axes.x_inner,
-            processing_model: request_processing_model,
-            data: data_frame,
-        };
-        if result_matrix.is_empty()
-            || (axes.y_outer.is_some() && axes.y_outer.cmp(&last_axes.y_outer) != Ordering::Equal)
-        {
-            let diagram = ResultDiagram {
-                independent_variable: axes.x_outer.unwrap_or(0),
-                frames: vec![frame],
-            };
-            let new_row = ResultRow {
-                independent_variable: axes.y_outer.unwrap_or(0),
-                results: vec![diagram],
-            };
-            result_matrix.push(new_row);
-        } else if axes.x_outer.is_some() && axes.x_outer.cmp(&last_axes.x_outer) != Ordering::Equal
-        {
-            let diagram = ResultDiagram {
-                independent_variable: axes.x_outer.unwrap_or(0),
-                frames: vec![frame],
-            };
-            let test = result_matrix.iter_mut().last().unwrap();
-            let row = &mut test.results;
-            row.push(diagram);
-        } else {
-            let row = &mut result_matrix.iter_mut().last().unwrap().results;
-            let diagram = &mut row.iter_mut().last().unwrap().frames;
-            diagram.push(frame);
-        }
-        last_axes = axes;
+fn plot_aggregate_data(
+    data_name: &str,
+    x_label: &str,
+    aggregate_matrix: ResultMatrix<Quartiles>,
+    output_format: OutputFormat,
+) {
+    let size = get_figure_size(&aggregate_matrix);
+    let figure_path = output_format.get_figure_path(data_name);
+    match output_format {
+        OutputFormat::Svg => draw_aggregate_data(
+            SVGBackend::new(&figure_path, size).into_drawing_area(),
+            data_name,
+            x_label,
+            aggregate_matrix,
+        ),
+        OutputFormat::Png => draw_aggregate_data(
+            BitMapBackend::new(&figure_path, size).into_drawing_area(),
+            data_name,
+            x_label,
+            aggregate_matrix,
+        ),
+        OutputFormat::DataOnly => {}
     }
-    result_matrix
-}
-
-fn get_series(axis_indices: &Axes, file_name_marker: &str) -> ResultMatrix<Series> {
-    let result_set = get_relevant_files(file_name_marker)
-        .iter()
-        .map(|dir_entry| {
-            let file_name = dir_entry
-                .file_name()
-                .into_string()
-                .expect("Result file should have UTF-8 name");
-            (
-                get_axis_variables(axis_indices, &file_name),
-                get_request_processing_model(&file_name),
-                read_csv_to_series(dir_entry),
-            )
-        })
-        .collect::<Vec<(Axes, RequestProcessingModel, Series)>>();
-    data_to_matrix(result_set)
 }
 
-fn read_csv_to_series(dir_entry: &DirEntry) -> Series {
-    let series: Series = fs::read_to_string(dir_entry.path())
-        .expect("Series file should be readable to string")
-        .split(',')
-        .filter(|token| !token.is_empty())
-        .map(f64::from_str)
-        .map(Result::unwrap)
-        .collect();
-    series
-}
-
-fn plot_aggregate_data(data_name: &str, aggregate_matrix: ResultMatrix<Quartiles>) {
+fn draw_aggregate_data<DB: DrawingBackend>(
+    root_drawing_area: DrawingArea<DB, Shift>,
+    data_name: &str,
+    x_label: &str,
+    aggregate_matrix: ResultMatrix<Quartiles>,
+) {
     let rows = aggregate_matrix.len();
     let columns = aggregate_matrix.first().unwrap().results.len();
-    let file_name = format!("figures/{data_name}.svg");
-    let root_drawing_area =
-        SVGBackend::new(&file_name, ((columns * 512) as u32, (rows * 512) as u32))
-            .into_drawing_area();
     root_drawing_area.fill(&WHITE).unwrap();
     root_drawing_area
         .titled(data_name, ("sans-serif", 40))
@@ -498,11 +719,15 @@ fn plot_aggregate_data(data_name: &str, aggregate_matrix: ResultMatrix<Quartiles
                 .unwrap();
             chart
                 .configure_mesh()
-                .x_desc(X_LABEL)
+                .x_desc(x_label)
                 .y_desc(data_name)
                 .draw()
                 .unwrap();
-            for frame in diagram.frames.iter() {
+            for frame in diagram
+                .frames
+                .iter()
+                .filter(|frame| frame.outlier.is_none())
+            {
                 let style = match frame.processing_model {
                     RequestProcessingModel::ReactiveStreaming => RED,
                     RequestProcessingModel::ClientServer => BLUE,
@@ -517,16 +742,71 @@ fn plot_aggregate_data(data_name: &str, aggregate_matrix: ResultMatrix<Quartiles
                     )
                     .unwrap();
             }
+            let dependent_range = get_dependent_range(diagram);
+            let outliers: Vec<(i32, f32)> = diagram
+                .frames
+                .iter()
+                .filter(|frame| frame.outlier.is_some())
+                .map(|frame| {
+                    (
+                        frame.independent_variable as i32,
+                        frame.data.values()[2].min(dependent_range.end),
+                    )
+                })
+                .collect();
+            if !outliers.is_empty() {
+                chart
+                    .draw_series(
+                        outliers
+                            .iter()
+                            .map(|point| TriangleMarker::new(*point, 5, BLACK.filled())),
+                    )
+                    .unwrap()
+                    .label(format!("{} runs excluded as outliers", outliers.len()))
+                    .legend(|point| TriangleMarker::new(point, 5, BLACK.filled()));
+                chart
+                    .configure_series_labels()
+                    .background_style(WHITE)
+                    .border_style(BLACK)
+                    .draw()
+                    .unwrap();
+            }
         }
     }
 }
-fn plot_simple_data(data_name: &str, aggregate_matrix: ResultMatrix<usize>) {
+fn plot_simple_data(
+    data_name: &str,
+    x_label: &str,
+    aggregate_matrix: ResultMatrix<usize>,
+    output_format: OutputFormat,
+) {
+    let size = get_figure_size(&aggregate_matrix);
+    let figure_path = output_format.get_figure_path(data_name);
+    match output_format {
+        OutputFormat::Svg => draw_simple_data(
+            SVGBackend::new(&figure_path, size).into_drawing_area(),
+            data_name,
+            x_label,
+            aggregate_matrix,
+        ),
+        OutputFormat::Png => draw_simple_data(
+            BitMapBackend::new(&figure_path, size).into_drawing_area(),
+            data_name,
+            x_label,
+            aggregate_matrix,
+        ),
+        OutputFormat::DataOnly => {}
+    }
+}
+
+fn draw_simple_data<DB: DrawingBackend>(
+    root_drawing_area: DrawingArea<DB, Shift>,
+    data_name: &str,
+    x_label: &str,
+    aggregate_matrix: ResultMatrix<usize>,
+) {
     let rows = aggregate_matrix.len();
     let columns = aggregate_matrix.first().unwrap().results.len();
-    let file_name = format!("figures/{data_name}.svg");
-    let root_drawing_area =
-        SVGBackend::new(&file_name, ((columns * 512) as u32, (rows * 512) as u32))
-            .into_drawing_area();
     root_drawing_area.fill(&WHITE).unwrap();
     root_drawing_area
         .titled(data_name, ("sans-serif", 40))
@@ -539,16 +819,20 @@ fn plot_simple_data(data_name: &str, aggregate_matrix: ResultMatrix<usize>) {
                 .set_left_and_bottom_label_area_size(20)
                 .build_cartesian_2d(
                     get_independent_range(diagram).log_scale(),
-                    0f32..diagram.frames.iter().map(|d| d.data).max().unwrap_or(0) as f32,
+                    0f32..get_simple_dependent_end(diagram),
                 )
                 .unwrap();
             chart
                 .configure_mesh()
-                .x_desc(X_LABEL)
+                .x_desc(x_label)
                 .y_desc(data_name)
                 .draw()
                 .unwrap();
-            for frame in diagram.frames.iter() {
+            for frame in diagram
+                .frames
+                .iter()
+                .filter(|frame| frame.outlier.is_none())
+            {
                 let style = match frame.processing_model {
                     RequestProcessingModel::ReactiveStreaming => RED,
                     RequestProcessingModel::ClientServer => BLUE,
@@ -564,9 +848,120 @@ fn plot_simple_data(data_name: &str, aggregate_matrix: ResultMatrix<usize>) {
                     ))
                     .unwrap();
             }
+            let dependent_end = get_simple_dependent_end(diagram);
+            let outliers: Vec<(i32, f32)> = diagram
+                .frames
+                .iter()
+                .filter(|frame| frame.outlier.is_some())
+                .map(|frame| {
+                    (
+                        frame.independent_variable as i32,
+                        (frame.data as f32).min(dependent_end),
+                    )
+                })
+                .collect();
+            if !outliers.is_empty() {
+                chart
+                    .draw_series(
+                        outliers
+                            .iter()
+                            .map(|point| TriangleMarker::new(*point, 5, BLACK.filled())),
+                    )
+                    .unwrap()
+                    .label(format!("{} runs excluded as outliers", outliers.len()))
+                    .legend(|point| TriangleMarker::new(point, 5, BLACK.filled()));
+                chart
+                    .configure_series_labels()
+                    .background_style(WHITE)
+                    .border_style(BLACK)
+                    .draw()
+                    .unwrap();
+            }
         }
     }
 }
+fn plot_confidence_intervals(
+    data_name: &str,
+    x_label: &str,
+    confidence_interval_matrix: ResultMatrix<ConfidenceInterval>,
+    output_format: OutputFormat,
+) {
+    let size = get_figure_size(&confidence_interval_matrix);
+    let figure_path = output_format.get_figure_path(&format!("{data_name}_ci"));
+    match output_format {
+        OutputFormat::Svg => draw_confidence_intervals(
+            SVGBackend::new(&figure_path, size).into_drawing_area(),
+            data_name,
+            x_label,
+            confidence_interval_matrix,
+        ),
+        OutputFormat::Png => draw_confidence_intervals(
+            BitMapBackend::new(&figure_path, size).into_drawing_area(),
+            data_name,
+            x_label,
+            confidence_interval_matrix,
+        ),
+        OutputFormat::DataOnly => {}
+    }
+}
+
+fn draw_confidence_intervals<DB: DrawingBackend>(
+    root_drawing_area: DrawingArea<DB, Shift>,
+    data_name: &str,
+    x_label: &str,
+    confidence_interval_matrix: ResultMatrix<ConfidenceInterval>,
+) {
+    let rows = confidence_interval_matrix.len();
+    let columns = confidence_interval_matrix.first().unwrap().results.len();
+    root_drawing_area.fill(&WHITE).unwrap();
+    root_drawing_area
+        .titled(data_name, ("sans-serif", 40))
+        .unwrap();
+    let panels = root_drawing_area.split_evenly((rows, columns));
+    for (y_index, row) in confidence_interval_matrix.iter().enumerate() {
+        for (x_index, diagram) in row.results.iter().enumerate() {
+            let mut chart = ChartBuilder::on(&panels[y_index * columns + x_index])
+                .margin(25)
+                .set_left_and_bottom_label_area_size(20)
+                .build_cartesian_2d(
+                    get_independent_range(diagram).log_scale(),
+                    0f32..diagram
+                        .frames
+                        .iter()
+                        .map(|frame| frame.data.upper as f32)
+                        .reduce(f32::max)
+                        .unwrap_or(1f32),
+                )
+                .unwrap();
+            chart
+                .configure_mesh()
+                .x_desc(x_label)
+                .y_desc(data_name)
+                .draw()
+                .unwrap();
+            for frame in diagram.frames.iter() {
+                let style = match frame.processing_model {
+                    RequestProcessingModel::ReactiveStreaming => RED,
+                    RequestProcessingModel::ClientServer => BLUE,
+                    RequestProcessingModel::SpringQL => GREEN,
+                    RequestProcessingModel::ObjectOriented => BLACK,
+                };
+                chart
+                    .plotting_area()
+                    .draw(&ErrorBar::new_vertical(
+                        frame.independent_variable as i32,
+                        frame.data.lower as f32,
+                        frame.data.mean as f32,
+                        frame.data.upper as f32,
+                        style.filled(),
+                        10,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+}
+
 fn get_independent_range<T>(diagram: &ResultDiagram<T>) -> Range<i32> {
     let independent_values = diagram
         .frames
@@ -578,9 +973,60 @@ fn get_independent_range<T>(diagram: &ResultDiagram<T>) -> Range<i32> {
 }
 
 fn get_dependent_range(diagram: &ResultDiagram<Quartiles>) -> Range<f32> {
-    let dependent_values = diagram.frames.iter().map(|frame| &frame.data);
+    let dependent_values = diagram
+        .frames
+        .iter()
+        .filter(|frame| frame.outlier.is_none())
+        .map(|frame| &frame.data);
     0f32..dependent_values
         .map(|result| result.values()[4])
         .reduce(f32::max)
-        .expect("At least one measurement should be present")
+        .unwrap_or(1f32)
+}
+
+fn get_simple_dependent_end(diagram: &ResultDiagram<usize>) -> f32 {
+    diagram
+        .frames
+        .iter()
+        .filter(|frame| frame.outlier.is_none())
+        .map(|frame| frame.data)
+        .max()
+        .unwrap_or(0) as f32
+}
+
+/// Size of a figure with a 512x512 panel per diagram of the matrix
+fn get_figure_size<T>(result_matrix: &ResultMatrix<T>) -> (u32, u32) {
+    let rows = result_matrix.len();
+    let columns = result_matrix.first().unwrap().results.len();
+    ((columns * 512) as u32, (rows * 512) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Label of the inner x-axis selected by the arguments
+    fn get_x_label(args: &[&str]) -> &'static str {
+        let axes = get_axes(
+            &mut args.iter().map(|arg| arg.to_string()),
+            WindowUnit::Milliseconds,
+        );
+        get_axis_label(axes.x_inner, axes.window_unit)
+    }
+
+    #[test]
+    fn sampling_interval_axes_are_labelled_by_their_parameter() {
+        assert_eq!(
+            get_x_label(&["data_aggregator", "3"]),
+            "Window Sampling Interval (in ms)"
+        );
+        assert_eq!(
+            get_x_label(&["data_aggregator", "4", "0"]),
+            "Sensor Sampling Interval (in ms)"
+        );
+        assert_eq!(
+            get_x_label(&["data_aggregator", "2"]),
+            "Window Size (in ms)"
+        );
+    }
 }
@@ -0,0 +1,168 @@
+use plotters::prelude::Quartiles;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use statrs::distribution::{ContinuousCDF, StudentsT};
+
+pub const SIGNIFICANCE_LEVEL: f64 = 0.05;
+/// Seed of the decimation, so that aggregating the same result files twice yields the same quartiles
+const DECIMATION_SEED: u64 = 0;
+
+/// Mean of a sample with the bounds of its confidence interval at `1 - SIGNIFICANCE_LEVEL`
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct ConfidenceInterval {
+    pub mean: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// One-sided paired t-test of the first sample having the greater mean, returning its p-value,
+/// or `None` if either sample has less than `min_samples` values.
+/// The values are paired by their index, the longer sample being cut to the length of the shorter one.
+pub fn t_test(sample1: &[f64], sample2: &[f64], min_samples: usize) -> Option<f64> {
+    let min_length = std::cmp::min(sample1.len(), sample2.len());
+    if min_length < min_samples {
+        return None;
+    }
+    let difference: Vec<f64> = sample1
+        .iter()
+        .zip(sample2)
+        .map(|(value1, value2)| value1 - value2)
+        .collect();
+    let sample_size = difference.len() as f64;
+    let diff_mean = difference.iter().sum::<f64>() / sample_size;
+    let diff_std = get_standard_deviation(&difference, diff_mean);
+    let t = diff_mean / (diff_std / sample_size.sqrt());
+    let degrees_of_freedom = if sample_size <= 1f64 {
+        1f64
+    } else {
+        sample_size - 1f64
+    };
+    let t_dist = StudentsT::new(0.0, 1.0, degrees_of_freedom).unwrap();
+    Some(1_f64 - t_dist.cdf(t))
+}
+
+/// Quartiles of the values, the ones of a single zero if there are none
+pub fn get_quartiles(values: &[f64]) -> Quartiles {
+    if values.is_empty() {
+        Quartiles::new(&[0])
+    } else {
+        Quartiles::new(values)
+    }
+}
+
+/// Uniform random sample of `target_size` of the values, drawn by reservoir sampling,
+/// so that the quartiles of very large series can be estimated without sorting all their values.
+/// Values which are no more than `target_size` are returned as they are.
+pub fn decimate(values: &[f64], target_size: usize) -> Vec<f64> {
+    if values.len() <= target_size {
+        return values.to_vec();
+    }
+    let mut rng = SmallRng::seed_from_u64(DECIMATION_SEED);
+    let mut reservoir = values[..target_size].to_vec();
+    for (index, value) in values.iter().enumerate().skip(target_size) {
+        let slot = rng.gen_range(0..=index);
+        if slot < target_size {
+            reservoir[slot] = *value;
+        }
+    }
+    reservoir
+}
+
+/// Calculates the mean of the values and its two-sided confidence interval using the t-distribution
+pub fn get_confidence_interval(values: &[f64]) -> ConfidenceInterval {
+    let sample_size = values.len() as f64;
+    if values.is_empty() {
+        return ConfidenceInterval {
+            mean: 0f64,
+            lower: 0f64,
+            upper: 0f64,
+        };
+    }
+    let mean = get_mean(values);
+    if values.len() < 2 {
+        return ConfidenceInterval {
+            mean,
+            lower: mean,
+            upper: mean,
+        };
+    }
+    let standard_deviation = get_standard_deviation(values, mean);
+    let t_dist = StudentsT::new(0.0, 1.0, sample_size - 1f64).unwrap();
+    let margin = t_dist.inverse_cdf(1f64 - SIGNIFICANCE_LEVEL / 2f64) * standard_deviation
+        / sample_size.sqrt();
+    ConfidenceInterval {
+        mean,
+        lower: mean - margin,
+        upper: mean + margin,
+    }
+}
+
+pub fn get_mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Sample standard deviation of the values around their mean
+fn get_standard_deviation(values: &[f64], mean: f64) -> f64 {
+    (values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (values.len() as f64 - 1f64))
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidence_interval_matches_hand_computed_one() {
+        // Sample standard deviation sqrt(32 / 7), t-quantile 0.975 for 7 degrees of freedom 2.3646
+        let confidence_interval =
+            get_confidence_interval(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        let margin = 2.3646 * (32f64 / 7f64).sqrt() / 8f64.sqrt();
+        assert_eq!(confidence_interval.mean, 5.0);
+        assert!((confidence_interval.lower - (5.0 - margin)).abs() < 1e-3);
+        assert!((confidence_interval.upper - (5.0 + margin)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn confidence_interval_of_single_value_is_the_value() {
+        assert_eq!(
+            get_confidence_interval(&[3.0]),
+            ConfidenceInterval {
+                mean: 3.0,
+                lower: 3.0,
+                upper: 3.0
+            }
+        );
+    }
+
+    #[test]
+    fn t_test_of_single_samples_has_no_p_value() {
+        assert_eq!(t_test(&[3.0], &[1.0, 2.0], 2), None);
+        assert!(t_test(&[3.0, 4.0], &[1.0, 2.0], 2).is_some());
+    }
+
+    #[test]
+    fn decimated_uniform_series_keeps_its_quartiles() {
+        let size = 1_000_000;
+        let values: Vec<f64> = (0..size).map(|value| value as f64 / size as f64).collect();
+        let decimated = decimate(&values, 10_000);
+        assert_eq!(decimated.len(), 10_000);
+        let quartiles = get_quartiles(&decimated).values();
+        for (quartile, expected_quartile) in quartiles[1..4].iter().zip([0.25, 0.5, 0.75]) {
+            assert!(
+                (*quartile as f64 - expected_quartile).abs() < 0.01,
+                "Quartile {quartile} instead of {expected_quartile}"
+            );
+        }
+    }
+
+    #[test]
+    fn short_series_is_not_decimated() {
+        let values = [3.0, 1.0, 2.0];
+        assert_eq!(decimate(&values, 3), values);
+        assert_eq!(decimate(&values, 10), values);
+    }
+}
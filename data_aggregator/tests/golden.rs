@@ -0,0 +1,282 @@
+//! Aggregates the results in `fixtures/bench_executor` and compares the outputs with the goldens
+//! in `fixtures/golden`, which were generated by the aggregator before the results were loaded
+//! into an `Experiment`.
+//! The fixture holds the resource usage, alert delays and alert failures of three motor group
+//! counts, two window sizes and two processing models, with one run listed as an outlier.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output};
+
+const FIXTURE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// The p-values of the t-tests may differ in the last digits, as they are computed in f64 from
+/// the f32 columns of the resource usage
+const P_VALUE_TOLERANCE: f64 = 1e-6;
+
+/// Copies the results of the fixture to a directory of the case, returning the directory the
+/// aggregator is run in.
+/// The aggregator reads the results from `../bench_executor`, so the copy is placed next to it.
+fn copy_fixture(case: &str) -> PathBuf {
+    let root =
+        std::env::temp_dir().join(format!("data_aggregator_golden_{}_{case}", process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let results = root.join("bench_executor");
+    fs::create_dir_all(&results).unwrap();
+    for entry in fs::read_dir(Path::new(FIXTURE_PATH).join("bench_executor")).unwrap() {
+        let entry = entry.unwrap();
+        fs::copy(entry.path(), results.join(entry.file_name())).unwrap();
+    }
+    let output_path = root.join(case);
+    fs::create_dir_all(output_path.join("figures")).unwrap();
+    output_path
+}
+
+fn execute_aggregator(output_path: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_data_aggregator"))
+        .args(args)
+        .args(["--jobs", "1"])
+        .current_dir(output_path)
+        .output()
+        .unwrap()
+}
+
+/// Runs the aggregator on a copy of the fixture, returning the directory it wrote its outputs to
+fn run_aggregator(case: &str, args: &[&str]) -> (PathBuf, Output) {
+    let output_path = copy_fixture(case);
+    let output = execute_aggregator(&output_path, args);
+    (output_path, output)
+}
+
+/// Files of the golden directory, relative to it
+fn get_golden_files(golden_path: &Path, directory: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for entry in fs::read_dir(golden_path.join(directory)).unwrap() {
+        let path = directory.join(entry.unwrap().file_name());
+        match golden_path.join(&path).is_dir() {
+            true => files.extend(get_golden_files(golden_path, &path)),
+            false => files.push(path),
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Splits a t-test result line into its verdict and its p-value
+fn split_p_value(line: &str) -> (&str, Option<f64>) {
+    match line.rsplit_once(' ') {
+        Some((verdict, p_value)) => match p_value.parse() {
+            Ok(p_value) => (verdict, Some(p_value)),
+            Err(_) => (line, None),
+        },
+        None => (line, None),
+    }
+}
+
+/// Checks that the outputs of the case match its goldens: the aggregated CSV files and figures
+/// byte for byte, and every line of the t-tests up to the tolerance of its p-value.
+/// Outputs of metrics added since are not compared.
+fn assert_matches_goldens(case: &str, args: &[&str]) {
+    let (output_path, output) = run_aggregator(case, args);
+    // The fixture predates the network usage files, which is the only error
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "No observations of bytes_transferred\n"
+    );
+    let golden_path = Path::new(FIXTURE_PATH).join("golden").join(case);
+    let golden_files = get_golden_files(&golden_path, Path::new(""));
+    assert!(golden_files.len() > 1);
+    for file in golden_files {
+        if file == Path::new("stdout.txt") {
+            continue;
+        }
+        let output_file = fs::read(output_path.join(&file))
+            .unwrap_or_else(|error| panic!("{case}: {} was not written: {error}", file.display()));
+        assert!(
+            output_file == fs::read(golden_path.join(&file)).unwrap(),
+            "{case}: {} differs from its golden",
+            file.display()
+        );
+    }
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines().map(split_p_value);
+    for golden_line in fs::read_to_string(golden_path.join("stdout.txt"))
+        .unwrap()
+        .lines()
+    {
+        let (golden_verdict, golden_p_value) = split_p_value(golden_line);
+        let (_, p_value) = lines
+            .find(|(verdict, _)| *verdict == golden_verdict)
+            .unwrap_or_else(|| panic!("{case}: {golden_line} is missing or out of order"));
+        match (golden_p_value, p_value) {
+            (Some(golden_p_value), Some(p_value)) => assert!(
+                (golden_p_value - p_value).abs() < P_VALUE_TOLERANCE,
+                "{case}: {golden_verdict} has the p-value {p_value} instead of {golden_p_value}"
+            ),
+            (golden_p_value, p_value) => assert_eq!(golden_p_value, p_value),
+        }
+    }
+    fs::remove_dir_all(output_path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn window_sizes_per_motor_groups_with_outliers_match_goldens() {
+    let outlier_runs = format!("{FIXTURE_PATH}/outlier_runs.json");
+    assert_matches_goldens(
+        "motor_groups_with_outliers",
+        &[
+            "2",
+            "0",
+            "--confidence-intervals",
+            "--outlier-runs",
+            &outlier_runs,
+            "--output-format",
+            "data-only",
+        ],
+    );
+}
+
+#[test]
+fn three_axes_match_goldens() {
+    assert_matches_goldens(
+        "three_axes",
+        &[
+            "2",
+            "0",
+            "1",
+            "--min-samples",
+            "3",
+            "--output-format",
+            "data-only",
+        ],
+    );
+}
+
+#[test]
+fn single_axis_figures_match_goldens() {
+    assert_matches_goldens("single_axis", &["0"]);
+}
+
+#[test]
+fn new_metric_is_aggregated_per_motor_groups() {
+    let (output_path, _) = run_aggregator("alert_failures", &["0", "--output-format", "data-only"]);
+    for model in ["ClientServer", "ReactiveStreaming"] {
+        let alert_failures =
+            fs::read_to_string(output_path.join(format!("alert_failures_0_0_{model}.csv")))
+                .unwrap();
+        // The header and one line per motor group count and window size
+        assert_eq!(alert_failures.lines().count(), 1 + 3 * 2);
+    }
+    fs::remove_dir_all(output_path.parent().unwrap()).unwrap();
+}
+
+/// Window sizes of the frames an aggregated CSV file holds
+fn get_window_sizes(output_path: &Path, file_name: &str) -> Vec<String> {
+    fs::read_to_string(output_path.join(file_name))
+        .unwrap_or_else(|error| panic!("{file_name} was not written: {error}"))
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn count_windows_do_not_share_the_axis_of_time_windows() {
+    let output_path = copy_fixture("count_windows");
+    let results = output_path.parent().unwrap().join("bench_executor");
+    // Runs of windows of 20 readings sampled every 100 ms, next to the ones of 1000 ms
+    for entry in fs::read_dir(&results).unwrap() {
+        let file_name = entry.unwrap().file_name().into_string().unwrap();
+        if file_name.contains("_1000_500_100_") {
+            fs::copy(
+                results.join(&file_name),
+                results.join(file_name.replace("_1000_500_100_", "_c20_2000_100_")),
+            )
+            .unwrap();
+        }
+    }
+    let args = ["2", "0", "--output-format", "data-only"];
+    execute_aggregator(&output_path, &args);
+    execute_aggregator(&output_path, &[&args[..], &["--count-windows"]].concat());
+    for model in ["ClientServer", "ReactiveStreaming"] {
+        assert_eq!(
+            get_window_sizes(&output_path, &format!("processing_time_0_1_{model}.csv")),
+            ["1000", "5000"]
+        );
+        assert_eq!(
+            get_window_sizes(
+                &output_path,
+                &format!("processing_time_count_windows_0_1_{model}.csv")
+            ),
+            ["20"]
+        );
+    }
+    fs::remove_dir_all(output_path.parent().unwrap()).unwrap();
+}
+
+/// Names of the files in the directory
+fn get_file_names(directory: &Path) -> Vec<String> {
+    fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect()
+}
+
+#[test]
+fn data_only_writes_the_csv_files_without_figures() {
+    let (output_path, _) = run_aggregator(
+        "data_only",
+        &[
+            "2",
+            "0",
+            "--confidence-intervals",
+            "--output-format",
+            "data-only",
+        ],
+    );
+    let csv_files: Vec<String> = get_file_names(&output_path)
+        .into_iter()
+        .filter(|file_name| file_name.ends_with(".csv"))
+        .collect();
+    assert!(
+        csv_files.contains(&"processing_time_0_1_ReactiveStreaming.csv".to_string()),
+        "{csv_files:?}"
+    );
+    assert!(
+        csv_files.contains(&"processing_time_0_1_ClientServer.csv".to_string()),
+        "{csv_files:?}"
+    );
+    assert_eq!(
+        get_file_names(&output_path.join("figures")),
+        Vec::<String>::new()
+    );
+    fs::remove_dir_all(output_path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn single_sample_series_are_skipped_instead_of_tested() {
+    let output_path = copy_fixture("single_sample");
+    let results = output_path.parent().unwrap().join("bench_executor");
+    for model in ["400_ReactiveStreaming", "5_ClientServer"] {
+        fs::write(
+            results.join(format!("1_60_1000_500_100_{model}_ad.csv")),
+            "0.05,",
+        )
+        .unwrap();
+    }
+    let output = execute_aggregator(&output_path, &["2", "0", "--output-format", "data-only"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let verdicts: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.contains("alert_delays 0 1 1000"))
+        .collect();
+    assert_eq!(verdicts.len(), 1, "{stdout}");
+    assert!(
+        verdicts[0].starts_with("Skipped: insufficient data")
+            && verdicts[0].contains("has 1 and")
+            && verdicts[0].contains("has 1 samples, at least 2 required"),
+        "{}",
+        verdicts[0]
+    );
+    fs::remove_dir_all(output_path.parent().unwrap()).unwrap();
+}
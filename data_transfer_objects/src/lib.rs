@@ -7,9 +7,12 @@ use std::net::IpAddr;
 #[cfg(feature = "std")]
 use std::net::SocketAddr;
 use std::ops::Index;
+use std::ops::RangeInclusive;
 #[cfg(feature = "std")]
 use std::str::FromStr;
 #[cfg(feature = "std")]
+use std::time::Duration;
+#[cfg(feature = "std")]
 use std::{f32, f64};
 
 use serde::{Deserialize, Serialize};
@@ -82,7 +85,46 @@ impl FromStr for MotorFailure {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+pub enum SensorKind {
+    AirTemperature,
+    ProcessTemperature,
+    RotationalSpeed,
+    Torque,
+}
+
+impl SensorKind {
+    /// The kind of a sensor is encoded in the lower two bits of its id
+    pub fn from_sensor_id(sensor_id: u32) -> SensorKind {
+        match sensor_id & 0x0003 {
+            0 => SensorKind::AirTemperature,
+            1 => SensorKind::ProcessTemperature,
+            2 => SensorKind::RotationalSpeed,
+            _ => SensorKind::Torque,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SensorKind::AirTemperature => "air_temperature",
+            SensorKind::ProcessTemperature => "process_temperature",
+            SensorKind::RotationalSpeed => "rotational_speed",
+            SensorKind::Torque => "torque",
+        }
+    }
+
+    /// Readings outside of this range cannot stem from a working sensor of this kind,
+    /// but from corrupted data, so the monitors quarantine them instead of evaluating them
+    pub fn plausible_range(&self) -> RangeInclusive<f32> {
+        match self {
+            SensorKind::AirTemperature | SensorKind::ProcessTemperature => 250.0..=400.0,
+            SensorKind::RotationalSpeed => 0.0..=5000.0,
+            SensorKind::Torque => 0.0..=100.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct SensorParameters {
     pub id: u32,
     pub start_time: f64,
@@ -90,6 +132,37 @@ pub struct SensorParameters {
     pub sampling_interval: u32,
     pub request_processing_model: RequestProcessingModel,
     pub motor_monitor_listen_address: SocketAddr,
+    pub warmup_samples: u32,
+    /// Probability with which the sensor emits a random failure reading instead of a measured one
+    pub random_failure_prob: f32,
+    /// Send the readings as `CompactSensorMessage`s
+    pub compact_wire: bool,
+    /// Monitor every reading is additionally sent to, for running two models side by side
+    pub secondary_motor_monitor_listen_address: Option<SocketAddr>,
+    pub transport: Transport,
+    /// Id of the run, which the paths of the Unix domain sockets of the monitors are derived from
+    pub run_id: u64,
+    /// Column of the data file lines the readings are read from
+    pub data_column: DataColumn,
+    /// Time after connecting to the monitor at which all sensors take their first sample,
+    /// if nonzero, instead of each sensor sampling as soon as it connected
+    pub startup_barrier_ms: u64,
+    /// Every this many sent messages are sampled for provenance tracking, none if zero
+    pub provenance_sample_interval: u32,
+    /// Retries of connecting to the monitors, e.g. while they are still binding their listeners
+    pub connect_retry: RetryPolicy,
+    /// Upper bound (in ms) of the delay each sensor adds before connecting, derived from its id,
+    /// so that the sensors of a run do not all connect to the monitor at the same time
+    pub connect_jitter_ms: u64,
+    /// Retries of reconnecting right away to a monitor which dropped the connection mid-run,
+    /// the sensor failing on a dropped connection without them
+    pub reconnect_retry: Option<RetryPolicy>,
+    /// OpenTelemetry collector the spans of the sent messages are exported to, if any
+    pub otel_endpoint: Option<String>,
+    /// Signal the sensor emits instead of the readings of its data file, if any
+    pub synthetic_signal: Option<SyntheticSignal>,
+    /// Readings the sensor emits instead of the ones of its data file if failures are scheduled
+    pub reading_schedule: Option<ReadingSchedule>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -102,7 +175,152 @@ pub struct BenchmarkData {
     pub peak_resident_set_size: u64,
     pub peak_virtual_memory_size: u64,
     pub load_average: f32,
+    /// CPU time of the process over its wall time times the cores it may run on,
+    /// e.g. 0.5 for one of two cores kept busy since the process started
+    pub cpu_utilization: f32,
     pub benchmark_data_type: BenchmarkDataType,
+    /// Written by the panic hook of a crashed monitor, covering the run up to the crash
+    pub crashed: bool,
+    /// Written by the memory watchdog of a monitor exceeding its memory limit
+    pub exceeded_memory_limit: bool,
+    /// Motors of the SpringQL monitor whose source readers stopped producing rows during the run
+    pub degraded_motors: u32,
+    /// Bytes exchanged with the sensors, absent if none were counted, as for SpringQL,
+    /// whose engine accepts the sensors itself
+    pub sensor_network_usage: Option<NetworkUsage>,
+    pub cloud_server_network_usage: NetworkUsage,
+    /// Alerts the monitor raised, counted whether or not they were sent to a cloud server
+    pub sent_alerts: u64,
+    pub build_info: Option<BuildInfo>,
+    /// Time spent in the hot paths of a monitor built with the `profiling` feature
+    pub hot_path_profile: Option<HotPathProfile>,
+    /// Alerts routed per rule of the alert routing, empty without any rules
+    pub route_counts: Vec<RouteCount>,
+}
+
+/// Bytes a component wrote to and read from the connections to one class of its peers during a run,
+/// e.g. those of a monitor to all of its sensors
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct NetworkUsage {
+    pub bytes_written: u64,
+    pub bytes_read: u64,
+}
+
+impl core::ops::Add for NetworkUsage {
+    type Output = NetworkUsage;
+
+    fn add(self, other: NetworkUsage) -> NetworkUsage {
+        NetworkUsage {
+            bytes_written: self.bytes_written + other.bytes_written,
+            bytes_read: self.bytes_read + other.bytes_read,
+        }
+    }
+}
+
+/// Written by a sensor to its standard output once it finished, and extended by the sensor driver
+/// with its own build before forwarding it to the motor driver, which forwards the usage of all
+/// sensors with their distinct builds to the test driver
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct SensorReport {
+    pub network_usage: NetworkUsage,
+    pub builds: Vec<BuildInfo>,
+}
+
+/// Version and build of a component, embedded at compile time by its build script
+/// and read with [build_info!]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub component: String,
+    pub version: String,
+    pub git_hash: String,
+    /// Whether the working tree had uncommitted changes when the component was built
+    pub dirty: bool,
+    /// Seconds since the epoch
+    pub build_time: u64,
+    pub profile: String,
+    /// Absent for components which exchange no messages with the others
+    pub protocol_version: Option<u8>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", self.component, self.version)?;
+        writeln!(
+            f,
+            "git hash: {}{}",
+            self.git_hash,
+            if self.dirty { " (dirty)" } else { "" }
+        )?;
+        writeln!(f, "build time: {}", self.build_time)?;
+        write!(f, "profile: {}", self.profile)?;
+        if let Some(protocol_version) = self.protocol_version {
+            write!(f, "\nprotocol version: {protocol_version}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Build of the crate this is expanded in, which needs a build script calling `build_info::emit`
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::BuildInfo {
+            component: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: env!("BUILD_GIT_HASH").to_string(),
+            dirty: env!("BUILD_GIT_DIRTY") == "true",
+            build_time: env!("BUILD_TIMESTAMP")
+                .parse()
+                .expect("Could not parse build timestamp"),
+            profile: env!("BUILD_PROFILE").to_string(),
+            protocol_version: None,
+        }
+    };
+}
+
+/// Category of the work a monitor does per message, whose time the `profiling` feature accounts for
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+pub enum HotPath {
+    Decode,
+    /// Inserting readings into the windows, evicting expired ones and averaging them
+    Window,
+    Rules,
+    /// Encoding the alerts and sending them to the cloud server
+    Alert,
+}
+
+impl HotPath {
+    pub const ALL: [HotPath; 4] = [
+        HotPath::Decode,
+        HotPath::Window,
+        HotPath::Rules,
+        HotPath::Alert,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HotPath::Decode => "decode",
+            HotPath::Window => "window",
+            HotPath::Rules => "rules",
+            HotPath::Alert => "alert",
+        }
+    }
+}
+
+/// Calls of a hot path during a run, summed over all threads of the monitor
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+pub struct HotPathTime {
+    pub hot_path: HotPath,
+    pub calls: u64,
+    pub nanoseconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct HotPathProfile {
+    /// Sensor messages the monitor decoded, none for SpringQL, whose engine decodes them itself
+    pub processed_messages: u64,
+    pub hot_paths: Vec<HotPathTime>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
@@ -115,7 +333,7 @@ pub enum BenchmarkDataType {
 impl BenchmarkData {
     pub fn to_csv_string(&self) -> String {
         format!(
-            "{},{},{},{},{},{},{},{}\n",
+            "{},{},{},{},{},{},{},{},{}{}{}{}\n",
             self.id,
             self.time_spent_in_user_mode,
             self.time_spent_in_kernel_mode,
@@ -123,20 +341,118 @@ impl BenchmarkData {
             self.children_time_spent_in_kernel_mode,
             self.peak_resident_set_size,
             self.peak_virtual_memory_size,
-            self.load_average
+            self.load_average,
+            self.cpu_utilization,
+            if self.crashed { ",crashed" } else { "" },
+            if self.exceeded_memory_limit {
+                ",exceeded_memory_limit"
+            } else {
+                ""
+            },
+            if self.degraded_motors > 0 {
+                format!(",degraded_motors={}", self.degraded_motors)
+            } else {
+                String::new()
+            }
+        )
+    }
+}
+
+/// Memory of a monitor sampled during the run, as far as it can be attributed to the threads of
+/// its processing engine. Sizes are in kB, like the peak resident set size of the benchmark data.
+/// Written by the monitor after its `BenchmarkData`, in the same output.
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct MemorySample {
+    /// Seconds since the start of the run
+    pub time: f64,
+    pub resident_set_size: u64,
+    pub anonymous: u64,
+    pub engine_threads: u32,
+    pub other_threads: u32,
+    /// Resident stacks of the threads of the engine and of the other threads, only counting
+    /// threads blocked in a system call, as the stack pointer of running threads is unknown
+    pub engine_stacks: u64,
+    pub other_stacks: u64,
+    /// Bytes buffered in the queues of the engine, as last reported by the engine itself
+    pub engine_queue_bytes: Option<u64>,
+}
+
+#[cfg(feature = "std")]
+impl MemorySample {
+    pub fn to_csv_string(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{}\n",
+            self.time,
+            self.resident_set_size,
+            self.anonymous,
+            self.engine_threads,
+            self.other_threads,
+            self.engine_stacks,
+            self.other_stacks,
+            self.engine_queue_bytes
+                .map(|engine_queue_bytes| engine_queue_bytes.to_string())
+                .unwrap_or_default()
         )
     }
 }
 
+/// Reading a sensor emits to signal a random failure, it lies outside the range of every sensor kind
+pub const RANDOM_FAILURE_READING: f32 = f32::MIN;
+
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct SensorMessage {
     pub reading: f32,
     pub sensor_id: u32,
     pub timestamp: f64,
+    /// Number of the message among the ones its sensor sent,
+    /// only set on the messages sampled for provenance tracking
+    pub sample_id: Option<u32>,
 }
 
-#[cfg(feature = "std")]
+/// Fixed-point wire form of a `SensorMessage`, with the reading in hundredths
+/// and the timestamp in milliseconds, which takes fewer bytes as varints
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct CompactSensorMessage {
+    pub reading_centi: i32,
+    pub sensor_id: u32,
+    pub timestamp_ms: u64,
+    pub sample_id: Option<u32>,
+}
+
+#[cfg(feature = "std")]
+impl From<SensorMessage> for CompactSensorMessage {
+    fn from(message: SensorMessage) -> Self {
+        CompactSensorMessage {
+            // The random failure reading does not fit into hundredths, so it is mapped to the minimum
+            reading_centi: match message.reading == RANDOM_FAILURE_READING {
+                true => i32::MIN,
+                false => (message.reading as f64 * 100.0).round() as i32,
+            },
+            sensor_id: message.sensor_id,
+            timestamp_ms: (message.timestamp * 1000.0).round() as u64,
+            sample_id: message.sample_id,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<CompactSensorMessage> for SensorMessage {
+    fn from(message: CompactSensorMessage) -> Self {
+        SensorMessage {
+            reading: match message.reading_centi {
+                i32::MIN => RANDOM_FAILURE_READING,
+                reading_centi => (reading_centi as f64 / 100.0) as f32,
+            },
+            sensor_id: message.sensor_id,
+            timestamp: message.timestamp_ms as f64 / 1000.0,
+            sample_id: message.sample_id,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MotorMonitorParameters {
     pub start_time: f64,
     pub duration: f64,
@@ -144,151 +460,1762 @@ pub struct MotorMonitorParameters {
     pub number_of_tcp_motor_groups: usize,
     pub number_of_i2c_motor_groups: u8,
     pub window_size_ms: u64,
+    /// Address advertised to the sensors, which may differ from the bound one behind NAT
     pub sensor_listen_address: SocketAddr,
     pub motor_monitor_listen_address: SocketAddr,
     pub sensor_sampling_interval: u32,
     pub window_sampling_interval: u32,
     pub thread_pool_size: usize,
+    pub wear_model: WearModel,
+    pub use_cumulative_age: bool,
+    /// Port the monitor binds to (on all interfaces) to accept sensor connections
+    pub sensor_bind_port: u16,
+    /// Only every `log_sample_rate`th message is logged on the hot paths
+    pub log_sample_rate: u64,
+    /// Leave Nagle's algorithm enabled on the connection to the cloud server
+    pub buffer_alerts: bool,
+    /// Request the compact alert encoding from the cloud server
+    pub compress_alerts: bool,
+    /// Send the alerts with the averages of the windows they were evaluated on
+    pub detailed_alerts: bool,
+    /// Exit on the first malformed sensor message instead of skipping it
+    pub strict_deserialization: bool,
+    /// Endpoints the alerts of single motors are posted to, in addition to the cloud server
+    pub alert_webhooks: Vec<AlertWebhook>,
+    /// Rules selecting the destination of each alert, the first matching one applying
+    pub routing: Vec<RouteRule>,
+    /// Channel the benchmark data is written to once the run completed
+    pub benchmark_output: BenchmarkOutput,
+    /// Id of the run, announced to the cloud server so it can assign the alerts to it
+    pub run_id: u64,
+    /// Expect the sensors to send `CompactSensorMessage`s
+    pub compact_wire: bool,
+    /// Unless it is `Tcp`, a Unix domain socket is bound for the sensors alongside the TCP listener
+    pub transport: Transport,
+    /// Attach the fingerprints of the evaluated windows to the alerts
+    pub forensic_alerts: bool,
+    /// File the panic hook writes the `CrashReport` to if the monitor panics
+    pub crash_file: Option<String>,
+    /// Let SpringQL post its monitoring reports to its web console
+    pub report_post: bool,
+    /// Resident set size the memory watchdog lets the monitor exit at
+    pub memory_limit_mb: Option<u64>,
+    /// Share of implausible readings of a sensor the monitor fails the run at,
+    /// instead of only quarantining them
+    pub max_quarantine_rate: Option<f32>,
+    /// Slide of the SpringQL windows, if it should differ from the `window_sampling_interval`
+    pub window_slide_ms: Option<u64>,
+    /// Time the SpringQL windows wait for late readings before they are closed
+    pub window_allowed_delay_ms: u64,
+    /// CPU cores the threads of the monitor are pinned to, none if empty
+    pub cpu_cores: Vec<usize>,
+    /// Time after connecting at which the sensors take their first sample, if nonzero
+    pub startup_barrier_ms: u64,
+    pub averaging: AveragingStrategy,
+    /// Attach the ids of the sampled messages in the evaluated windows to the alerts
+    pub track_provenance: bool,
+    /// Number of sensor connections handled at the same time, beyond which further ones are
+    /// queued in the accept backlog, or rejected with `reject_excess_connections`
+    pub max_sensor_connections: Option<usize>,
+    pub reject_excess_connections: bool,
+    /// Expect the sensors to connect in the order of their ids, closing a connection whose first
+    /// message is from another sensor than the one expected in its place
+    pub expect_sensor_order: bool,
+    /// Interpolate the readings of a window at the sensor sampling interval before averaging them,
+    /// so that missed samples do not bias the average
+    pub interpolate_gaps: bool,
+    /// OpenTelemetry collector the spans of the ingested messages, window evaluations and alerts
+    /// are exported to, if any
+    pub otel_endpoint: Option<String>,
+    pub rule_thresholds: RuleThresholds,
+    /// Count the alerts instead of sending them to the cloud server, without connecting to it
+    pub no_cloud: bool,
+    /// File the aggregates of every evaluated window are exported to as CSV, if any
+    pub window_export: Option<String>,
+    /// Whether the windows hold the readings of the last `window_size_ms` or the last readings
+    pub window_kind: WindowKind,
 }
 
+/// Channel a process writes its COBS encoded `BenchmarkData` to
 #[cfg(feature = "std")]
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct MotorDriverRunParameters {
-    pub start_time: f64,
-    pub duration: f64,
-    pub number_of_tcp_motor_groups: usize,
-    pub number_of_i2c_motor_groups: u8,
-    pub window_size_ms: u64,
-    pub sensor_listen_address: SocketAddr,
-    pub sensor_sampling_interval: u32,
-    pub window_sampling_interval: u32,
-    pub request_processing_model: RequestProcessingModel,
-    pub motor_monitor_listen_address: SocketAddr,
-    pub sensor_socket_addresses: Vec<SocketAddr>,
-    pub thread_pool_size: usize,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BenchmarkOutput {
+    /// Standard output, shared with anything else the process prints
+    Stdout,
+    /// File (created or truncated) at the path
+    Path(String),
+    /// Already open file descriptor, e.g. a pipe set up by the parent process
+    Fd(i32),
+    /// Connection to the address, opened for writing the data
+    Tcp(SocketAddr),
 }
 
 #[cfg(feature = "std")]
-#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
-pub struct Alert {
-    pub time: f64,
-    pub motor_id: u16,
-    pub failure: MotorFailure,
+impl FromStr for BenchmarkOutput {
+    type Err = String;
+
+    /// Parses `stdout`, `fd:<n>`, `tcp:<address>`, or a path
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "stdout" {
+            Ok(BenchmarkOutput::Stdout)
+        } else if let Some(fd) = s.strip_prefix("fd:") {
+            fd.parse()
+                .map(BenchmarkOutput::Fd)
+                .map_err(|e| format!("Could not parse file descriptor {fd}: {e}"))
+        } else if let Some(address) = s.strip_prefix("tcp:") {
+            address
+                .parse()
+                .map(BenchmarkOutput::Tcp)
+                .map_err(|e| format!("Could not parse address {address}: {e}"))
+        } else {
+            Ok(BenchmarkOutput::Path(s.to_string()))
+        }
+    }
 }
 
 #[cfg(feature = "std")]
-impl Alert {
-    pub fn to_csv(&self) -> String {
-        format!("{},{},{}", self.motor_id, self.time, self.failure)
+impl fmt::Display for BenchmarkOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchmarkOutput::Stdout => write!(f, "stdout"),
+            BenchmarkOutput::Path(path) => write!(f, "{path}"),
+            BenchmarkOutput::Fd(fd) => write!(f, "fd:{fd}"),
+            BenchmarkOutput::Tcp(address) => write!(f, "tcp:{address}"),
+        }
     }
+}
 
-    pub fn from_csv(csv_line: String) -> Alert {
-        let values: Vec<&str> = csv_line.split(',').collect();
-        Alert {
-            motor_id: u16::from_str(values[0]).expect("Could not parse motor id"),
-            time: f64::from_str(values[1]).expect("Could not parse time"),
-            failure: MotorFailure::from_str(values[2]).expect("Could not parse MotorFailure"),
+/// How the sensors connect to a monitor running on the same host
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Transport {
+    /// TCP, regardless of where the monitor runs
+    Tcp,
+    /// A Unix domain socket if the address of the monitor is a loopback address, TCP otherwise
+    Local,
+    /// A Unix domain socket, regardless of the address of the monitor
+    Uds,
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Transport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(Transport::Tcp),
+            "local" => Ok(Transport::Local),
+            "uds" => Ok(Transport::Uds),
+            _ => Err(format!("Unknown transport {s}, expected tcp, local or uds")),
         }
     }
+}
 
-    pub fn from_alert_with_delay(alert_with_delay: AlertWithDelay) -> Alert {
-        Alert {
-            time: alert_with_delay.time,
-            motor_id: alert_with_delay.motor_id,
-            failure: alert_with_delay.failure,
+#[cfg(feature = "std")]
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Tcp => write!(f, "tcp"),
+            Transport::Local => write!(f, "local"),
+            Transport::Uds => write!(f, "uds"),
         }
     }
 }
 
+/// How the readings of a sensor are averaged before the rules are evaluated on them
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub enum AveragingStrategy {
+    /// The average of the readings within the window
+    Window,
+    /// The exponentially weighted moving average, weighting each new reading by `alpha`,
+    /// which needs constant state per sensor instead of storing the readings of a window
+    Ewma { alpha: f64 },
+}
+
 #[cfg(feature = "std")]
-#[derive(Serialize, Deserialize, Debug)]
-pub struct AlertWithDelay {
-    pub time: f64,
-    pub motor_id: u16,
-    pub failure: MotorFailure,
-    pub delay: f64,
+impl FromStr for AveragingStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "window" => Ok(AveragingStrategy::Window),
+            Some(("ewma", alpha)) => match alpha.parse() {
+                Ok(alpha) if 0.0 < alpha && alpha <= 1.0 => Ok(AveragingStrategy::Ewma { alpha }),
+                _ => Err(format!(
+                    "Invalid EWMA weight {alpha}, expected a number in (0, 1]"
+                )),
+            },
+            _ => Err(format!(
+                "Unknown averaging strategy {s}, expected window or ewma:<alpha>"
+            )),
+        }
+    }
 }
 
 #[cfg(feature = "std")]
-impl AlertWithDelay {
-    pub fn from_csv(csv_line: String) -> AlertWithDelay {
-        let values: Vec<&str> = csv_line.split(',').collect();
-        AlertWithDelay {
-            motor_id: u16::from_str(values[0]).expect("Could not parse motor id"),
-            time: f64::from_str(values[1]).expect("Could not parse time"),
-            failure: MotorFailure::from_str(values[2]).expect("Could not parse MotorFailure"),
-            delay: f64::from_str(values[3]).expect("Could not parse delay"),
+impl fmt::Display for AveragingStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AveragingStrategy::Window => write!(f, "window"),
+            AveragingStrategy::Ewma { alpha } => write!(f, "ewma:{alpha}"),
         }
     }
 }
 
+/// Extent of the window the readings of a sensor are averaged over
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+pub enum WindowKind {
+    /// The readings of the last milliseconds (relative to the latest reading)
+    TimeMs(u64),
+    /// The last readings, however long ago they were taken
+    Count(usize),
+}
+
 #[cfg(feature = "std")]
-#[derive(Serialize, Deserialize, Debug)]
-pub struct CloudServerRunParameters {
-    pub start_time: f64,
-    pub duration: f64,
-    pub motor_monitor_listen_address: SocketAddr,
-    pub request_processing_model: RequestProcessingModel,
+impl fmt::Display for WindowKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowKind::TimeMs(window_size_ms) => write!(f, "time:{window_size_ms}"),
+            WindowKind::Count(count) => write!(f, "count:{count}"),
+        }
+    }
+}
+
+/// How often, and how far apart, a failed operation is attempted again.
+/// The delays grow by the multiplier up to the maximum delay, each randomly shortened or lengthened
+/// by up to the jitter fraction, so that components failing together do not retry in lockstep.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub struct RetryPolicy {
+    /// Attempts including the first one, at least 1
+    pub max_attempts: u32,
+    pub initial_delay_ms: u64,
+    pub multiplier: f64,
+    pub max_delay_ms: u64,
+    /// Fraction in [0, 1] of a delay it is randomly varied by
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// For peers on the same host or network, which are either up within a few seconds or not at all
+    pub const fn fast_local() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 10,
+            initial_delay_ms: 100,
+            multiplier: 2.0,
+            max_delay_ms: 1000,
+            jitter: 0.1,
+        }
+    }
+
+    /// For peers which may take minutes to become available, e.g. rescheduled containers
+    pub const fn patient_network() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 30,
+            initial_delay_ms: 1000,
+            multiplier: 1.5,
+            max_delay_ms: 10000,
+            jitter: 0.2,
+        }
+    }
 }
 
 #[cfg(feature = "std")]
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct MotorSensorGroup {
-    at_sensor: SocketAddr,
-    pt_sensor: SocketAddr,
-    rs_sensor: SocketAddr,
-    tq_sensor: SocketAddr,
+impl FromStr for RetryPolicy {
+    type Err = String;
+
+    /// Parses `fast_local`, `patient_network`, or
+    /// `<max_attempts>:<initial_delay_ms>:<multiplier>:<max_delay_ms>:<jitter>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fast_local" => return Ok(RetryPolicy::fast_local()),
+            "patient_network" => return Ok(RetryPolicy::patient_network()),
+            _ => {}
+        }
+        let fields: Vec<&str> = s.split(':').collect();
+        let [max_attempts, initial_delay_ms, multiplier, max_delay_ms, jitter] = fields[..] else {
+            return Err(format!(
+                "Unknown retry policy {s}, expected fast_local, patient_network or \
+                 <max_attempts>:<initial_delay_ms>:<multiplier>:<max_delay_ms>:<jitter>"
+            ));
+        };
+        let policy = RetryPolicy {
+            max_attempts: max_attempts
+                .parse()
+                .map_err(|e| format!("Could not parse max attempts {max_attempts}: {e}"))?,
+            initial_delay_ms: initial_delay_ms
+                .parse()
+                .map_err(|e| format!("Could not parse initial delay {initial_delay_ms}: {e}"))?,
+            multiplier: multiplier
+                .parse()
+                .map_err(|e| format!("Could not parse multiplier {multiplier}: {e}"))?,
+            max_delay_ms: max_delay_ms
+                .parse()
+                .map_err(|e| format!("Could not parse max delay {max_delay_ms}: {e}"))?,
+            jitter: jitter
+                .parse()
+                .map_err(|e| format!("Could not parse jitter {jitter}: {e}"))?,
+        };
+        policy.validate().map(|_| policy)
+    }
 }
 
 #[cfg(feature = "std")]
-impl Index<usize> for MotorSensorGroup {
-    type Output = SocketAddr;
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            Err("A retry policy needs at least 1 attempt".to_string())
+        } else if self.multiplier.is_nan() || self.multiplier < 1.0 {
+            Err(format!(
+                "Invalid retry multiplier {}, expected at least 1",
+                self.multiplier
+            ))
+        } else if !(0.0..=1.0).contains(&self.jitter) {
+            Err(format!(
+                "Invalid retry jitter {}, expected a fraction in [0, 1]",
+                self.jitter
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
 
-    fn index(&self, index: usize) -> &Self::Output {
-        match index {
-            0 => &self.at_sensor,
-            1 => &self.pt_sensor,
-            2 => &self.rs_sensor,
-            3 => &self.tq_sensor,
-            _ => panic!("Invalid MotorData index"),
+#[cfg(feature = "std")]
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}",
+            self.max_attempts,
+            self.initial_delay_ms,
+            self.multiplier,
+            self.max_delay_ms,
+            self.jitter
+        )
+    }
+}
+
+/// Bounds of the rules the monitors flag a motor failure by, so that they can be varied per run.
+/// The heat dissipation and power bounds apply to the rules of the client server and SpringQL
+/// monitors, the overstrain limit to those of all monitors.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub struct RuleThresholds {
+    /// Difference (in K) between air and process temperature below which the heat dissipation
+    /// fails, if the rotational speed is also below its threshold
+    pub heat_dissipation_temperature_difference: f64,
+    /// Rotational speed (in rpm) below which the heat dissipation fails,
+    /// if the temperature difference is also below its threshold
+    pub heat_dissipation_rotational_speed: f64,
+    /// Lower bound (in W) of the power the process does not fail at
+    pub min_power: f64,
+    /// Upper bound (in W) of the power the process does not fail at
+    pub max_power: f64,
+    /// Product of tool wear and torque (in Nms) above which the tool overstrains.
+    /// The wear is tracked in seconds, where the dataset the default stems from uses minutes.
+    pub overstrain_limit: f64,
+}
+
+impl Default for RuleThresholds {
+    /// The thresholds of the AI4I 2020 predictive maintenance dataset, for the L product variant
+    fn default() -> Self {
+        RuleThresholds {
+            heat_dissipation_temperature_difference: 8.6,
+            heat_dissipation_rotational_speed: 1380.0,
+            min_power: 3500.0,
+            max_power: 9000.0,
+            overstrain_limit: 11000.0,
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl IntoIterator for MotorSensorGroup {
-    type Item = SocketAddr;
-    type IntoIter = std::array::IntoIter<Self::Item, 4>;
+impl FromStr for RuleThresholds {
+    type Err = String;
 
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIterator::into_iter([
-            self.at_sensor,
-            self.pt_sensor,
-            self.rs_sensor,
-            self.tq_sensor,
-        ])
+    /// Parses `default`, or `<temperature_difference>:<rotational_speed>:<min_power>:<max_power>:<overstrain_limit>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "default" {
+            return Ok(RuleThresholds::default());
+        }
+        let fields: Vec<&str> = s.split(':').collect();
+        let [temperature_difference, rotational_speed, min_power, max_power, overstrain_limit] =
+            fields[..]
+        else {
+            return Err(format!(
+                "Unknown rule thresholds {s}, expected default or \
+                 <temperature_difference>:<rotational_speed>:<min_power>:<max_power>:<overstrain_limit>"
+            ));
+        };
+        let thresholds = RuleThresholds {
+            heat_dissipation_temperature_difference: temperature_difference.parse().map_err(
+                |e| format!("Could not parse temperature difference {temperature_difference}: {e}"),
+            )?,
+            heat_dissipation_rotational_speed: rotational_speed
+                .parse()
+                .map_err(|e| format!("Could not parse rotational speed {rotational_speed}: {e}"))?,
+            min_power: min_power
+                .parse()
+                .map_err(|e| format!("Could not parse min power {min_power}: {e}"))?,
+            max_power: max_power
+                .parse()
+                .map_err(|e| format!("Could not parse max power {max_power}: {e}"))?,
+            overstrain_limit: overstrain_limit
+                .parse()
+                .map_err(|e| format!("Could not parse overstrain limit {overstrain_limit}: {e}"))?,
+        };
+        thresholds.validate().map(|_| thresholds)
     }
 }
 
 #[cfg(feature = "std")]
-impl<'a> IntoIterator for &'a MotorSensorGroup {
-    type Item = &'a SocketAddr;
-    type IntoIter = std::array::IntoIter<Self::Item, 4>;
+impl RuleThresholds {
+    pub fn validate(&self) -> Result<(), String> {
+        let thresholds = [
+            self.heat_dissipation_temperature_difference,
+            self.heat_dissipation_rotational_speed,
+            self.min_power,
+            self.max_power,
+            self.overstrain_limit,
+        ];
+        if thresholds.iter().any(|threshold| !threshold.is_finite()) {
+            Err(format!(
+                "Invalid rule thresholds {self}, expected finite values"
+            ))
+        } else if self.min_power > self.max_power {
+            Err(format!(
+                "Invalid power band {}..={}, expected the min power to not exceed the max power",
+                self.min_power, self.max_power
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
 
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIterator::into_iter([
-            &self.at_sensor,
-            &self.pt_sensor,
-            &self.rs_sensor,
-            &self.tq_sensor,
-        ])
+#[cfg(feature = "std")]
+impl fmt::Display for RuleThresholds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}",
+            self.heat_dissipation_temperature_difference,
+            self.heat_dissipation_rotational_speed,
+            self.min_power,
+            self.max_power,
+            self.overstrain_limit
+        )
+    }
+}
+
+/// Column of the lines of a sensor data file a reading is read from,
+/// if the lines consist of comma separated columns
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+pub enum DataColumn {
+    /// The last column, being the reading of bare readings and of `timestamp,value` lines
+    Last,
+    /// The column at this index, starting at 0
+    Index(usize),
+}
+
+impl DataColumn {
+    /// The field of the line holding the reading, if the line has that column
+    pub fn get_field<'a>(&self, line: &'a str) -> Option<&'a str> {
+        let mut columns = line.split(',');
+        match self {
+            DataColumn::Last => columns.next_back(),
+            DataColumn::Index(index) => columns.nth(*index),
+        }
     }
 }
 
 #[cfg(feature = "std")]
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct NetworkConfig {
-    pub cloud_server_address: IpAddr,
-    pub motor_monitor_address: IpAddr,
-    pub sensor_addresses: Vec<IpAddr>,
+impl FromStr for DataColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last" => Ok(DataColumn::Last),
+            _ => s
+                .parse()
+                .map(DataColumn::Index)
+                .map_err(|_| format!("Unknown data column {s}, expected last or a column index")),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for DataColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DataColumn::Last => write!(f, "last"),
+            DataColumn::Index(index) => write!(f, "{index}"),
+        }
+    }
+}
+
+/// Shape of a synthetic signal over one period
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Waveform {
+    Sine,
+    /// Rising linearly from the bottom to the top of the signal, then dropping back
+    Sawtooth,
+}
+
+/// Deterministic signal the sensors of a kind emit instead of the readings of their data file,
+/// so that the readings, and with them the alerts, of a run are exactly predictable
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub struct SyntheticSignal {
+    pub sensor_kind: SensorKind,
+    pub waveform: Waveform,
+    pub offset: f32,
+    pub amplitude: f32,
+    pub period_ms: u64,
+}
+
+#[cfg(feature = "std")]
+impl SyntheticSignal {
+    /// Reading of the signal this long after the first sample, which is `offset` at phase 0
+    pub fn get_reading(&self, elapsed_ms: u64) -> f32 {
+        let phase = (elapsed_ms % self.period_ms) as f64 / self.period_ms as f64;
+        let value = match self.waveform {
+            Waveform::Sine => (phase * f64::consts::TAU).sin(),
+            Waveform::Sawtooth => match phase < 0.5 {
+                true => 2.0 * phase,
+                false => 2.0 * phase - 2.0,
+            },
+        };
+        self.offset + self.amplitude * value as f32
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.period_ms == 0 {
+            return Err("The period of a synthetic signal has to be positive".to_string());
+        }
+        if self.amplitude < 0.0 {
+            return Err("The amplitude of a synthetic signal must not be negative".to_string());
+        }
+        let plausible_range = self.sensor_kind.plausible_range();
+        if !plausible_range.contains(&(self.offset - self.amplitude))
+            || !plausible_range.contains(&(self.offset + self.amplitude))
+        {
+            return Err(format!(
+                "The {} signal has to stay within {plausible_range:?}, or the monitors quarantine its readings",
+                self.sensor_kind.name()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for SyntheticSignal {
+    type Err = String;
+
+    /// Parses `<sensor_kind>:<sine|sawtooth>:<offset>:<amplitude>:<period_ms>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(':').collect();
+        let [sensor_kind, waveform, offset, amplitude, period_ms] = fields[..] else {
+            return Err(format!(
+                "Unknown synthetic signal {s}, expected <sensor_kind>:<sine|sawtooth>:<offset>:<amplitude>:<period_ms>"
+            ));
+        };
+        let signal = SyntheticSignal {
+            sensor_kind: [
+                SensorKind::AirTemperature,
+                SensorKind::ProcessTemperature,
+                SensorKind::RotationalSpeed,
+                SensorKind::Torque,
+            ]
+            .into_iter()
+            .find(|kind| kind.name() == sensor_kind)
+            .ok_or_else(|| format!("Unknown sensor kind {sensor_kind}"))?,
+            waveform: match waveform {
+                "sine" => Waveform::Sine,
+                "sawtooth" => Waveform::Sawtooth,
+                _ => {
+                    return Err(format!(
+                        "Unknown waveform {waveform}, expected sine or sawtooth"
+                    ))
+                }
+            },
+            offset: offset
+                .parse()
+                .map_err(|e| format!("Could not parse offset {offset}: {e}"))?,
+            amplitude: amplitude
+                .parse()
+                .map_err(|e| format!("Could not parse amplitude {amplitude}: {e}"))?,
+            period_ms: period_ms
+                .parse()
+                .map_err(|e| format!("Could not parse period {period_ms}: {e}"))?,
+        };
+        signal.validate().map(|_| signal)
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for SyntheticSignal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let waveform = match self.waveform {
+            Waveform::Sine => "sine",
+            Waveform::Sawtooth => "sawtooth",
+        };
+        write!(
+            f,
+            "{}:{waveform}:{}:{}:{}",
+            self.sensor_kind.name(),
+            self.offset,
+            self.amplitude,
+            self.period_ms
+        )
+    }
+}
+
+/// Failure the sensors of a motor simulate during a span of the run, which makes the schedule of
+/// a run the exact ground truth its alerts are validated against
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub struct ScheduledFailure {
+    pub motor_id: u16,
+    pub failure: MotorFailure,
+    /// Time (in s) after the start of the run at which the failure begins
+    pub start_s: f64,
+    /// Time (in s) after the start of the run at which the failure ends
+    pub end_s: f64,
+}
+
+#[cfg(feature = "std")]
+impl ScheduledFailure {
+    pub fn overlaps(&self, other: &ScheduledFailure) -> bool {
+        self.motor_id == other.motor_id && self.start_s < other.end_s && other.start_s < self.end_s
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for ScheduledFailure {
+    type Err = String;
+
+    /// Parses `<motor_id>:<failure>:<start_s>-<end_s>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(':').collect();
+        let [motor_id, failure, span] = fields[..] else {
+            return Err(format!(
+                "Unknown scheduled failure {s}, expected <motor_id>:<failure>:<start_s>-<end_s>"
+            ));
+        };
+        let (start_s, end_s) = span
+            .split_once('-')
+            .ok_or_else(|| format!("Unknown failure span {span}, expected <start_s>-<end_s>"))?;
+        let scheduled_failure = ScheduledFailure {
+            motor_id: motor_id
+                .parse()
+                .map_err(|e| format!("Could not parse motor id {motor_id}: {e}"))?,
+            failure: MotorFailure::from_str(failure)
+                .map_err(|_| format!("Unknown motor failure {failure}"))?,
+            start_s: start_s
+                .parse()
+                .map_err(|e| format!("Could not parse start {start_s}: {e}"))?,
+            end_s: end_s
+                .parse()
+                .map_err(|e| format!("Could not parse end {end_s}: {e}"))?,
+        };
+        if !(0.0..scheduled_failure.end_s).contains(&scheduled_failure.start_s) {
+            return Err(format!(
+                "Invalid failure span {span}, expected a start of at least 0 before the end"
+            ));
+        }
+        Ok(scheduled_failure)
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ScheduledFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}-{}",
+            self.motor_id, self.failure, self.start_s, self.end_s
+        )
+    }
+}
+
+/// Readings a sensor emits instead of the ones of its data file when failures are scheduled:
+/// the reading of the scheduled failure of its motor during it, and the normal one otherwise
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ReadingSchedule {
+    pub normal_reading: f32,
+    pub failures: Vec<ScheduledReading>,
+}
+
+/// Reading a sensor emits from `start_s` until `end_s` after the start of the run
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub struct ScheduledReading {
+    pub start_s: f64,
+    pub end_s: f64,
+    pub reading: f32,
+}
+
+#[cfg(feature = "std")]
+impl ReadingSchedule {
+    /// Reading of the sensor this long after the start of the run
+    pub fn get_reading(&self, elapsed_s: f64) -> f32 {
+        self.failures
+            .iter()
+            .find(|failure| (failure.start_s..failure.end_s).contains(&elapsed_s))
+            .map_or(self.normal_reading, |failure| failure.reading)
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for ReadingSchedule {
+    type Err = String;
+
+    /// Parses `<normal_reading>[;<start_s>-<end_s>:<reading>]...`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split(';');
+        let normal_reading = fields.next().unwrap_or_default();
+        Ok(ReadingSchedule {
+            normal_reading: normal_reading
+                .parse()
+                .map_err(|e| format!("Could not parse normal reading {normal_reading}: {e}"))?,
+            failures: fields
+                .map(|failure| {
+                    let (span, reading) = failure.split_once(':').ok_or_else(|| {
+                        format!("Unknown scheduled reading {failure}, expected <start_s>-<end_s>:<reading>")
+                    })?;
+                    let (start_s, end_s) = span.split_once('-').ok_or_else(|| {
+                        format!("Unknown reading span {span}, expected <start_s>-<end_s>")
+                    })?;
+                    Ok(ScheduledReading {
+                        start_s: start_s
+                            .parse()
+                            .map_err(|e| format!("Could not parse start {start_s}: {e}"))?,
+                        end_s: end_s
+                            .parse()
+                            .map_err(|e| format!("Could not parse end {end_s}: {e}"))?,
+                        reading: reading
+                            .parse()
+                            .map_err(|e| format!("Could not parse reading {reading}: {e}"))?,
+                    })
+                })
+                .collect::<Result<Vec<ScheduledReading>, String>>()?,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ReadingSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.normal_reading)?;
+        for failure in &self.failures {
+            write!(
+                f,
+                ";{}-{}:{}",
+                failure.start_s, failure.end_s, failure.reading
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Endpoint the alerts of a motor are posted to as JSON
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertWebhook {
+    pub motor_id: u16,
+    pub url: String,
+}
+
+#[cfg(feature = "std")]
+impl FromStr for AlertWebhook {
+    type Err = String;
+
+    /// Parses `<motor_id>=<url>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (motor_id, url) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <motor_id>=<url>, got {s}"))?;
+        Ok(AlertWebhook {
+            motor_id: motor_id
+                .parse()
+                .map_err(|e| format!("Could not parse motor id {motor_id}: {e}"))?,
+            url: url.to_string(),
+        })
+    }
+}
+
+/// Where a monitor sends the alerts matched by a `RouteRule`
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RouteDestination {
+    CloudServer,
+    Drop,
+}
+
+#[cfg(feature = "std")]
+impl FromStr for RouteDestination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cloud" => Ok(RouteDestination::CloudServer),
+            "drop" => Ok(RouteDestination::Drop),
+            _ => Err(format!(
+                "Unknown route destination {s}, expected cloud or drop"
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for RouteDestination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteDestination::CloudServer => write!(f, "cloud"),
+            RouteDestination::Drop => write!(f, "drop"),
+        }
+    }
+}
+
+/// Rule of the alert routing of a monitor, sending the alerts of a failure (or of every failure
+/// if none is given) to a destination under a label
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteRule {
+    pub label: String,
+    pub failure: Option<MotorFailure>,
+    pub destination: RouteDestination,
+}
+
+#[cfg(feature = "std")]
+impl RouteRule {
+    pub fn matches(&self, failure: MotorFailure) -> bool {
+        self.failure
+            .is_none_or(|rule_failure| rule_failure == failure)
+    }
+
+    /// First rule of the routing matching the failure, none meaning the default destination
+    pub fn find(routing: &[RouteRule], failure: MotorFailure) -> Option<&RouteRule> {
+        routing.iter().find(|rule| rule.matches(failure))
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for RouteRule {
+    type Err = String;
+
+    /// Parses `<label>=<failure|*>:<cloud|drop>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (label, route) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <label>=<failure|*>:<cloud|drop>, got {s}"))?;
+        let (failure, destination) = route
+            .split_once(':')
+            .ok_or_else(|| format!("Expected <label>=<failure|*>:<cloud|drop>, got {s}"))?;
+        // The label is persisted as a column of the alert protocol
+        if label.is_empty() || label.contains([',', '=']) {
+            return Err(format!("Invalid route label {label}"));
+        }
+        Ok(RouteRule {
+            label: label.to_string(),
+            failure: match failure {
+                "*" => None,
+                _ => Some(
+                    MotorFailure::from_str(failure)
+                        .map_err(|_| format!("Unknown motor failure {failure}"))?,
+                ),
+            },
+            destination: destination.parse()?,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for RouteRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.failure {
+            Some(failure) => write!(f, "{}={failure}:{}", self.label, self.destination),
+            None => write!(f, "{}=*:{}", self.label, self.destination),
+        }
+    }
+}
+
+/// Alerts a monitor routed under a label, the ones no rule matched being counted under
+/// `DEFAULT_ROUTE_LABEL`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteCount {
+    pub label: String,
+    pub destination: RouteDestination,
+    pub alerts: u64,
+}
+
+/// Label the alerts no route rule matched are counted under, which are sent to the cloud server
+#[cfg(feature = "std")]
+pub const DEFAULT_ROUTE_LABEL: &str = "default";
+
+/// Prefix of the column of the alert protocol holding the label of the route of an alert
+#[cfg(feature = "std")]
+pub const ROUTE_LABEL: &str = "route=";
+
+/// Time exchange measuring the clock offset of a peer: the prober sends its time,
+/// the peer answers with its own, and the prober tells the peer the measured offset
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct ClockProbe {
+    pub sent: f64,
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct ClockReply {
+    pub sent: f64,
+    pub peer_time: f64,
+}
+
+/// Offset (in seconds) of the clock of the peer to the one of the prober
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct ClockOffset {
+    pub offset: f64,
+    pub round_trip_time: f64,
+}
+
+/// Sensor driver whose clock is off the most, sent by the motor driver to the test driver
+/// after checking the clocks of all sensor drivers
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct SensorClockReport {
+    pub worst_offset: Option<(SocketAddr, ClockOffset)>,
+}
+
+/// Request opening every connection of the motor driver to a sensor driver
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug)]
+pub enum SensorDriverRequest {
+    /// Answer a clock probe and close the connection
+    ClockProbe,
+    /// Echo the parameters and run the sensor with them
+    Run(Box<SensorParameters>),
+    /// Terminate the sensors of the run and close the connection
+    Abort(AbortRun),
+}
+
+/// Describes how the tool of a motor wears down over time
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct WearModel {
+    /// Tool wear accumulated per second of operation
+    pub wear_rate: f64,
+    /// Rotational speed (in rpm) above which the tool wears down
+    pub rotational_speed_threshold: f64,
+    pub tool_change: ToolChange,
+}
+
+/// When the worn down tool of a motor is replaced, resetting its wear to zero
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum ToolChange {
+    Periodic(Duration),
+    OnOverstrainFailure,
+}
+
+#[cfg(feature = "std")]
+impl ToolChange {
+    /// A tool change interval of 0 means the tool is changed on overstrain failures
+    pub fn from_interval_ms(interval_ms: u64) -> ToolChange {
+        match interval_ms {
+            0 => ToolChange::OnOverstrainFailure,
+            interval_ms => ToolChange::Periodic(Duration::from_millis(interval_ms)),
+        }
+    }
+
+    pub fn to_interval_ms(&self) -> u64 {
+        match self {
+            ToolChange::Periodic(interval) => interval.as_millis() as u64,
+            ToolChange::OnOverstrainFailure => 0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MotorDriverRunParameters {
+    /// Replaced by the one of the `RunStart` following the `ParameterAck`
+    pub start_time: f64,
+    pub duration: f64,
+    pub number_of_tcp_motor_groups: usize,
+    pub number_of_i2c_motor_groups: u8,
+    pub window_size_ms: u64,
+    pub sensor_listen_address: SocketAddr,
+    pub sensor_bind_port: u16,
+    pub sensor_sampling_interval: u32,
+    pub window_sampling_interval: u32,
+    pub request_processing_model: RequestProcessingModel,
+    pub motor_monitor_listen_address: SocketAddr,
+    pub sensor_socket_addresses: Vec<SocketAddr>,
+    pub thread_pool_size: usize,
+    pub wear_model: WearModel,
+    pub use_cumulative_age: bool,
+    pub warmup_samples: u32,
+    pub random_failure_prob: f32,
+    pub snapshot_path: Option<String>,
+    pub batch_recv: bool,
+    pub log_sample_rate: u64,
+    pub buffer_alerts: bool,
+    pub compress_alerts: bool,
+    pub detailed_alerts: bool,
+    pub strict_deserialization: bool,
+    /// Clock offset (in ms) of a sensor driver beyond which the run is aborted
+    pub max_clock_offset_ms: u64,
+    pub alert_webhooks: Vec<AlertWebhook>,
+    pub routing: Vec<RouteRule>,
+    pub run_id: u64,
+    pub compact_wire: bool,
+    /// Model of a second monitor fed with the same sensor readings
+    pub secondary_processing_model: Option<RequestProcessingModel>,
+    /// Port the second monitor listens on for sensors, on the host of the `sensor_listen_address`
+    pub secondary_sensor_port: u16,
+    pub transport: Transport,
+    pub forensic_alerts: bool,
+    pub report_post: bool,
+    pub memory_limit_mb: Option<u64>,
+    pub max_quarantine_rate: Option<f32>,
+    pub window_slide_ms: Option<u64>,
+    pub window_allowed_delay_ms: u64,
+    pub data_column: DataColumn,
+    /// Compresses the timing of the run, applied by the motor driver before starting it
+    pub time_scale: TimeScale,
+    pub cpu_cores: Vec<usize>,
+    pub startup_barrier_ms: u64,
+    pub averaging: AveragingStrategy,
+    /// Every this many messages of a sensor are sampled for provenance tracking, none if zero
+    pub provenance_sample_interval: u32,
+    pub max_sensor_connections: Option<usize>,
+    pub reject_excess_connections: bool,
+    pub expect_sensor_order: bool,
+    pub sensor_connect_retry: RetryPolicy,
+    pub sensor_connect_jitter_ms: u64,
+    pub sensor_reconnect_retry: Option<RetryPolicy>,
+    pub interpolate_gaps: bool,
+    pub otel_endpoint: Option<String>,
+    pub rule_thresholds: RuleThresholds,
+    /// Directory the files of the run are written to (in a subdirectory named after the
+    /// component), the working directory and the temporary one being used if absent
+    pub run_directory: Option<String>,
+    pub no_cloud: bool,
+    /// Signals the sensors of the given kinds emit instead of the readings of their data files
+    pub synthetic_signals: Vec<SyntheticSignal>,
+    /// Let the monitors export the aggregates of every evaluated window, which is high-volume
+    pub export_windows: bool,
+    /// Number of readings per sensor the windows hold instead of those of the window size
+    pub window_count: Option<usize>,
+    /// Failures the sensors simulate, all of their readings following the schedule if not empty
+    pub failure_schedule: Vec<ScheduledFailure>,
+}
+
+#[cfg(feature = "std")]
+impl MotorDriverRunParameters {
+    /// Compresses the duration, intervals and windows of the run by its time scale,
+    /// leaving a time scale of 1
+    pub fn compress_time(self) -> MotorDriverRunParameters {
+        let time_scale = self.time_scale;
+        MotorDriverRunParameters {
+            duration: time_scale.compress_secs(self.duration),
+            window_size_ms: time_scale.compress_ms(self.window_size_ms),
+            sensor_sampling_interval: time_scale.compress_ms(self.sensor_sampling_interval as u64)
+                as u32,
+            window_sampling_interval: time_scale.compress_ms(self.window_sampling_interval as u64)
+                as u32,
+            wear_model: time_scale.compress_wear_model(self.wear_model),
+            window_slide_ms: self
+                .window_slide_ms
+                .map(|window_slide_ms| time_scale.compress_ms(window_slide_ms)),
+            window_allowed_delay_ms: time_scale.compress_ms(self.window_allowed_delay_ms),
+            synthetic_signals: self
+                .synthetic_signals
+                .iter()
+                .map(|signal| SyntheticSignal {
+                    period_ms: time_scale.compress_ms(signal.period_ms),
+                    ..*signal
+                })
+                .collect(),
+            failure_schedule: self
+                .failure_schedule
+                .iter()
+                .map(|scheduled_failure| ScheduledFailure {
+                    start_s: time_scale.compress_secs(scheduled_failure.start_s),
+                    end_s: time_scale.compress_secs(scheduled_failure.end_s),
+                    ..*scheduled_failure
+                })
+                .collect(),
+            time_scale: TimeScale(1.0),
+            ..self
+        }
+    }
+}
+
+/// Factor by which the timing of a run is compressed, so that a scenario runs `time_scale` times
+/// faster than its nominal duration and intervals
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct TimeScale(pub f64);
+
+#[cfg(feature = "std")]
+impl TimeScale {
+    /// Compresses a nominal interval, keeping an interval of 0 (which disables what it times) at 0
+    pub fn compress_ms(&self, interval_ms: u64) -> u64 {
+        match interval_ms {
+            0 => 0,
+            interval_ms => ((interval_ms as f64 / self.0).round() as u64).max(1),
+        }
+    }
+
+    pub fn compress_secs(&self, duration: f64) -> f64 {
+        duration / self.0
+    }
+
+    /// The tool wears down per nominal second, so it wears down faster per second of wall time
+    pub fn compress_wear_model(&self, wear_model: WearModel) -> WearModel {
+        WearModel {
+            wear_rate: wear_model.wear_rate * self.0,
+            tool_change: ToolChange::from_interval_ms(
+                self.compress_ms(wear_model.tool_change.to_interval_ms()),
+            ),
+            ..wear_model
+        }
+    }
+}
+
+/// Benchmark data of a monitor as forwarded by the motor driver, tagged by its model
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MonitorBenchmarkData {
+    pub request_processing_model: RequestProcessingModel,
+    /// The benchmark data as written by the monitor, empty if it could not be read
+    pub benchmark_data: Vec<u8>,
+    /// Report of the panic the monitor exited with, if it crashed
+    pub crash_report: Option<CrashReport>,
+}
+
+/// Panic a monitor crashed with, as written to its crash file by its panic hook
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CrashReport {
+    pub message: String,
+    /// File, line and column of the panic
+    pub location: String,
+    pub backtrace: String,
+}
+
+/// Lifecycle of a run executed by the motor driver, as reported by its status endpoint
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RunState {
+    pub phase: RunPhase,
+    pub sensors: Vec<SensorDelivery>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum RunPhase {
+    Idle,
+    AwaitingParameters,
+    CheckingSensorClocks,
+    AwaitingStart,
+    ProvisioningSensors,
+    MonitorRunning { pid: u32 },
+    CollectingOutput,
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct SensorDelivery {
+    pub sensor_id: u32,
+    pub address: SocketAddr,
+    pub status: DeliveryStatus,
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+    /// The parameters echoed by the sensor driver differ from the sent ones
+    Mismatched,
+}
+
+#[cfg(feature = "std")]
+impl RunState {
+    pub fn new() -> Self {
+        RunState {
+            phase: RunPhase::Idle,
+            sensors: vec![],
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for RunState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Alert {
+    pub time: f64,
+    pub motor_id: u16,
+    pub failure: MotorFailure,
+    /// Fingerprints of the windows of the four sensors the alert was evaluated on,
+    /// only sent by monitors running in forensic mode
+    pub fingerprint: Option<[ChannelFingerprint; 4]>,
+    /// Set if the motor recovered from the `failure` instead of failing with it,
+    /// only sent by monitors built with the `recovery_alerts` feature
+    pub cleared: bool,
+    /// Messages sampled for provenance tracking in the windows the alert was evaluated on,
+    /// only sent by monitors tracking provenance
+    pub sampled_messages: Vec<SampledMessageId>,
+}
+
+/// Message sampled for provenance tracking, by its sensor and its number among the ones the sensor sent
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SampledMessageId {
+    pub sensor_id: u32,
+    pub sample_id: u32,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for SampledMessageId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.sensor_id, self.sample_id)
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for SampledMessageId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sensor_id, sample_id) = s.split_once(':').ok_or_else(|| {
+            format!("Sampled message id {s} is not of the form <sensor>:<sample>")
+        })?;
+        Ok(SampledMessageId {
+            sensor_id: sensor_id
+                .parse()
+                .map_err(|e| format!("Could not parse sensor id {sensor_id}: {e}"))?,
+            sample_id: sample_id
+                .parse()
+                .map_err(|e| format!("Could not parse sample id {sample_id}: {e}"))?,
+        })
+    }
+}
+
+/// Time a sensor sent a message sampled for provenance tracking at
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct SampledMessageSend {
+    pub id: SampledMessageId,
+    pub send_time: f64,
+}
+
+/// Label of the alert protocol column listing the sampled messages an alert was evaluated on
+#[cfg(feature = "std")]
+pub const SAMPLED_MESSAGES_LABEL: &str = "sampled=";
+
+/// Number of readings in the window of a sensor, and a hash of their (timestamp, reading) pairs,
+/// which is equal for equal window contents regardless of the model which evaluated them
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChannelFingerprint {
+    pub count: u32,
+    pub hash: u64,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ChannelFingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:016x}", self.count, self.hash)
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for ChannelFingerprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, hash) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Fingerprint {s} is not of the form <count>:<hash>"))?;
+        Ok(ChannelFingerprint {
+            count: count
+                .parse()
+                .map_err(|e| format!("Could not parse fingerprint count {count}: {e}"))?,
+            hash: u64::from_str_radix(hash, 16)
+                .map_err(|e| format!("Could not parse fingerprint hash {hash}: {e}"))?,
+        })
+    }
+}
+
+/// Encoding of the alerts sent to the cloud server, agreed on when the monitor connects
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum AlertEncoding {
+    Plain,
+    Compact,
+    /// Plain alerts with the averages of their windows, sent as `DetailedAlert`
+    Detailed,
+}
+
+/// Averages the rules of a motor were evaluated on, derived from the averages of the windows of its
+/// four sensors
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct WindowAverages {
+    /// Difference of the air and the process temperature (in K)
+    pub temperature_difference: f64,
+    /// Rotational speed (in rpm)
+    pub rotational_speed: f64,
+    /// Power (in W)
+    pub power: f64,
+    /// Torque (in Nm)
+    pub torque: f64,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for WindowAverages {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{};{};{};{}",
+            self.temperature_difference, self.rotational_speed, self.power, self.torque
+        )
+    }
+}
+
+/// Alert with the averages of the windows which triggered it, sent in the detailed alert encoding.
+/// Alerts which were not evaluated on windows, such as recovery alerts, carry no averages.
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetailedAlert {
+    pub alert: Alert,
+    pub averages: Option<WindowAverages>,
+}
+
+#[cfg(feature = "std")]
+impl From<Alert> for DetailedAlert {
+    fn from(alert: Alert) -> DetailedAlert {
+        DetailedAlert {
+            alert,
+            averages: None,
+        }
+    }
+}
+
+/// Alert with its time stored as the difference of its bit pattern to the one of the run start
+/// time, which is small and thus encoded in few bytes as varint, while staying lossless
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactAlert {
+    pub time_offset: i64,
+    pub motor_id: u16,
+    pub failure: MotorFailure,
+    pub fingerprint: Option<[ChannelFingerprint; 4]>,
+    pub cleared: bool,
+    pub sampled_messages: Vec<SampledMessageId>,
+}
+
+#[cfg(feature = "std")]
+impl CompactAlert {
+    pub fn from_alert(alert: &Alert, start_time: f64) -> CompactAlert {
+        CompactAlert {
+            time_offset: alert.time.to_bits().wrapping_sub(start_time.to_bits()) as i64,
+            motor_id: alert.motor_id,
+            failure: alert.failure,
+            fingerprint: alert.fingerprint,
+            cleared: alert.cleared,
+            sampled_messages: alert.sampled_messages.clone(),
+        }
+    }
+
+    pub fn to_alert(self, start_time: f64) -> Alert {
+        Alert {
+            time: f64::from_bits(start_time.to_bits().wrapping_add(self.time_offset as u64)),
+            motor_id: self.motor_id,
+            failure: self.failure,
+            fingerprint: self.fingerprint,
+            cleared: self.cleared,
+            sampled_messages: self.sampled_messages,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Alert {
+    pub fn to_csv(&self) -> String {
+        format!("{},{},{}", self.motor_id, self.time, self.failure)
+    }
+
+    pub fn from_csv(csv_line: String) -> Alert {
+        let values: Vec<&str> = csv_line.split(',').collect();
+        Alert {
+            motor_id: u16::from_str(values[0]).expect("Could not parse motor id"),
+            time: f64::from_str(values[1]).expect("Could not parse time"),
+            failure: MotorFailure::from_str(values[2]).expect("Could not parse MotorFailure"),
+            fingerprint: None,
+            cleared: false,
+            sampled_messages: vec![],
+        }
+    }
+
+    pub fn from_alert_with_delay(alert_with_delay: AlertWithDelay) -> Alert {
+        Alert {
+            time: alert_with_delay.time,
+            motor_id: alert_with_delay.motor_id,
+            failure: alert_with_delay.failure,
+            fingerprint: alert_with_delay.fingerprint,
+            // Recovery alerts are not persisted to the alert protocol
+            cleared: false,
+            sampled_messages: alert_with_delay.sampled_messages,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AlertWithDelay {
+    pub time: f64,
+    pub motor_id: u16,
+    pub failure: MotorFailure,
+    pub delay: f64,
+    pub fingerprint: Option<[ChannelFingerprint; 4]>,
+    pub sampled_messages: Vec<SampledMessageId>,
+}
+
+#[cfg(feature = "std")]
+impl AlertWithDelay {
+    pub fn from_csv(csv_line: String) -> AlertWithDelay {
+        let values: Vec<&str> = csv_line.split(',').collect();
+        AlertWithDelay {
+            motor_id: u16::from_str(values[0]).expect("Could not parse motor id"),
+            time: f64::from_str(values[1]).expect("Could not parse time"),
+            failure: MotorFailure::from_str(values[2]).expect("Could not parse MotorFailure"),
+            delay: f64::from_str(values[3]).expect("Could not parse delay"),
+            // The fingerprint columns follow the model of the monitor which sent the alert
+            fingerprint: values.get(5..9).map(|fingerprint| {
+                [0, 1, 2, 3].map(|index| {
+                    ChannelFingerprint::from_str(fingerprint[index])
+                        .expect("Could not parse window fingerprint")
+                })
+            }),
+            sampled_messages: values
+                .iter()
+                .find_map(|value| value.strip_prefix(SAMPLED_MESSAGES_LABEL))
+                .map(|sampled_messages| {
+                    sampled_messages
+                        .split(';')
+                        .filter(|id| !id.is_empty())
+                        .map(|id| {
+                            SampledMessageId::from_str(id)
+                                .expect("Could not parse sampled message id")
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Prefix of the column of the alert protocol holding the averages of the windows of a detailed alert
+#[cfg(feature = "std")]
+pub const WINDOW_AVERAGES_LABEL: &str = "averages=";
+
+/// Label of the line the cloud server appends to the alert protocol,
+/// stating how many alerts it dropped due to the rate limit
+#[cfg(feature = "std")]
+pub const DROPPED_ALERTS_LABEL: &str = "dropped_due_to_rate_limit";
+
+/// Label of the line the cloud server appends to the alert protocol,
+/// stating how many alerts it did not persist due to the disk budget
+#[cfg(feature = "std")]
+pub const UNPERSISTED_ALERTS_LABEL: &str = "unpersisted_due_to_disk_budget";
+
+/// Label of the line the cloud server appends to the alert protocol,
+/// stating how many alerts it did not persist because the disk was full
+#[cfg(feature = "std")]
+pub const UNWRITTEN_ALERTS_LABEL: &str = "unwritten_due_to_full_disk";
+
+/// Label of the line the cloud server writes to the alert protocol where it resumes writing
+/// after the disk was full, stating how many alerts are missing before it
+#[cfg(feature = "std")]
+pub const DISK_FULL_GAP_LABEL: &str = "gap_due_to_full_disk";
+
+/// Label of the line the cloud server appends to the alert protocol,
+/// stating how many bytes of alerts it received and how many they would take uncompressed
+#[cfg(feature = "std")]
+pub const ALERT_BYTES_LABEL: &str = "alert_bytes";
+
+/// Label of the line the cloud server appends to the alert protocol,
+/// stating how many bytes it wrote to and read from the connections of the monitors
+#[cfg(feature = "std")]
+pub const NETWORK_USAGE_LABEL: &str = "network_usage";
+
+/// Label of the line the cloud server appends to the alert protocol if the run was aborted,
+/// stating the reason of the abort
+#[cfg(feature = "std")]
+pub const ABORTED_LABEL: &str = "aborted";
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CloudServerRunParameters {
+    /// Replaced by the one of the `RunStart` following the `ParameterAck`
+    pub start_time: f64,
+    pub duration: f64,
+    pub motor_monitor_listen_address: SocketAddr,
+    pub request_processing_model: RequestProcessingModel,
+    pub run_id: u64,
+    /// Model of a second monitor sending alerts in the same run
+    pub secondary_processing_model: Option<RequestProcessingModel>,
+    /// Directory the alert protocol of the run is written to (in a subdirectory named after the
+    /// component), the working directory being used if absent
+    pub run_directory: Option<String>,
+    /// Alert routing of the monitors, whose labels the alerts are tagged with
+    pub routing: Vec<RouteRule>,
+}
+
+/// Sent by the motor driver and the cloud server once they parsed the run parameters
+/// (and the cloud server bound its monitor listener)
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParameterAck {
+    pub run_id: u64,
+    pub build_info: Option<BuildInfo>,
+}
+
+/// Sent by the test driver once the motor driver and the cloud server acknowledged the
+/// run parameters, so the start time cannot pass while the parameters still propagate
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct RunStart {
+    pub start_time: f64,
+}
+
+/// Sent by the test driver over its control connections once it gave up on a run,
+/// and forwarded by the motor driver to the sensor drivers.
+/// An abort of another run than the current one is ignored.
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AbortRun {
+    pub run_id: u64,
+    pub reason: String,
+}
+
+/// Announced by a monitor when connecting to the cloud server,
+/// which tags the alerts received over the connection with it
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct AlertSource {
+    pub run_id: u64,
+    pub request_processing_model: RequestProcessingModel,
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MotorSensorGroup {
+    at_sensor: SocketAddr,
+    pt_sensor: SocketAddr,
+    rs_sensor: SocketAddr,
+    tq_sensor: SocketAddr,
+}
+
+#[cfg(feature = "std")]
+impl Index<usize> for MotorSensorGroup {
+    type Output = SocketAddr;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.at_sensor,
+            1 => &self.pt_sensor,
+            2 => &self.rs_sensor,
+            3 => &self.tq_sensor,
+            _ => panic!("Invalid MotorData index"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl IntoIterator for MotorSensorGroup {
+    type Item = SocketAddr;
+    type IntoIter = std::array::IntoIter<Self::Item, 4>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIterator::into_iter([
+            self.at_sensor,
+            self.pt_sensor,
+            self.rs_sensor,
+            self.tq_sensor,
+        ])
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> IntoIterator for &'a MotorSensorGroup {
+    type Item = &'a SocketAddr;
+    type IntoIter = std::array::IntoIter<Self::Item, 4>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIterator::into_iter([
+            &self.at_sensor,
+            &self.pt_sensor,
+            &self.rs_sensor,
+            &self.tq_sensor,
+        ])
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkConfig {
+    pub cloud_server_address: IpAddr,
+    pub motor_monitor_address: IpAddr,
+    pub sensor_addresses: Vec<IpAddr>,
+}
+
+/// Ports the components of a run listen on, checked for overlaps and for exceeding the port
+/// range before the run starts
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct PortPlan {
+    base_port: u16,
+    number_of_sensors: u32,
+    per_sensor_ports: bool,
+    reserved_ports: Vec<(String, u16)>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PortPlanError {
+    OutOfRange {
+        first_port: u16,
+        last_port: u32,
+    },
+    Conflict {
+        port: u16,
+        first_use: String,
+        second_use: String,
+    },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for PortPlanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PortPlanError::OutOfRange {
+                first_port,
+                last_port,
+            } => write!(
+                f,
+                "the sensor ports {first_port} to {last_port} exceed the port range"
+            ),
+            PortPlanError::Conflict {
+                port,
+                first_use,
+                second_use,
+            } => write!(
+                f,
+                "port {port} is used both by the {first_use} and the {second_use}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl PortPlan {
+    pub fn new(
+        base_port: u16,
+        motor_groups: u16,
+        request_processing_model: RequestProcessingModel,
+    ) -> Result<PortPlan, PortPlanError> {
+        let port_plan = PortPlan {
+            base_port,
+            number_of_sensors: motor_groups as u32 * 4,
+            // SpringQL creates a source reader per sensor, the other models share one listener
+            per_sensor_ports: request_processing_model == RequestProcessingModel::SpringQL,
+            reserved_ports: vec![],
+        };
+        let last_port = port_plan.get_last_sensor_port();
+        match last_port > u16::MAX as u32 {
+            true => Err(PortPlanError::OutOfRange {
+                first_port: base_port,
+                last_port,
+            }),
+            false => Ok(port_plan),
+        }
+    }
+
+    /// Adds the port of another listener of the run, e.g. of the cloud server or a driver
+    pub fn reserve(mut self, port_use: &str, port: u16) -> Result<PortPlan, PortPlanError> {
+        let first_use = self.get_sensor_port_use(port).or_else(|| {
+            self.reserved_ports
+                .iter()
+                .find(|(_, reserved_port)| *reserved_port == port)
+                .map(|(reserved_use, _)| reserved_use.clone())
+        });
+        match first_use {
+            Some(first_use) => Err(PortPlanError::Conflict {
+                port,
+                first_use,
+                second_use: port_use.to_string(),
+            }),
+            None => {
+                self.reserved_ports.push((port_use.to_string(), port));
+                Ok(self)
+            }
+        }
+    }
+
+    /// Port the monitor listens on for the sensor with the given full id
+    pub fn get_sensor_port(&self, full_id: u32) -> u16 {
+        match self.per_sensor_ports {
+            true => self.base_port + full_id as u16,
+            false => self.base_port,
+        }
+    }
+
+    fn get_last_sensor_port(&self) -> u32 {
+        match self.per_sensor_ports && self.number_of_sensors > 0 {
+            true => self.base_port as u32 + self.number_of_sensors - 1,
+            false => self.base_port as u32,
+        }
+    }
+
+    fn get_sensor_port_use(&self, port: u16) -> Option<String> {
+        if port < self.base_port || port as u32 > self.get_last_sensor_port() {
+            return None;
+        }
+        match self.per_sensor_ports {
+            true => Some(format!("reader of sensor {}", port - self.base_port)),
+            false => Some("sensor listener".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_has_the_expected_reading_at_known_phases() {
+        let signal: SyntheticSignal = "torque:sine:40:20:1000".parse().unwrap();
+        assert_eq!(signal.get_reading(0), 40.0);
+        assert_eq!(signal.get_reading(250), 60.0);
+        assert_eq!(signal.get_reading(750), 20.0);
+        // Half way through the third period
+        assert_eq!(signal.get_reading(2500), 40.0);
+    }
+
+    #[test]
+    fn sawtooth_has_the_expected_reading_at_each_quarter() {
+        let signal: SyntheticSignal = "torque:sawtooth:40:20:1000".parse().unwrap();
+        let readings = [0, 250, 500, 750, 1000].map(|elapsed_ms| signal.get_reading(elapsed_ms));
+        assert_eq!(readings, [40.0, 50.0, 20.0, 30.0, 40.0]);
+    }
 }
@@ -0,0 +1,74 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod loopback;
+
+use core::fmt::Debug;
+
+use data_transfer_objects::{SensorKind, RANDOM_FAILURE_READING};
+use rand::prelude::{IteratorRandom, SmallRng};
+use rand::{Rng, SeedableRng};
+
+/// Controller side of an I2C bus the sensors are attached to
+pub trait I2cBus {
+    type Error: Debug;
+
+    fn set_slave_address(&mut self, address: u16) -> Result<(), Self::Error>;
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Self::Error>;
+}
+
+/// Produces the readings of a sensor by choosing lines of its data file at random,
+/// seeded with the sensor id, the same way the sensor binary does.
+/// Like there, lines which are no plausible reading of the kind of the sensor are skipped.
+pub struct ReadingGenerator<'a> {
+    readings: &'a str,
+    sensor_kind: SensorKind,
+    rng: SmallRng,
+    warmup_samples: u32,
+    random_failure_prob: f32,
+    number_of_samples: u32,
+}
+
+impl<'a> ReadingGenerator<'a> {
+    pub fn new(
+        sensor_id: u32,
+        readings: &'a str,
+        warmup_samples: u32,
+        random_failure_prob: f32,
+    ) -> ReadingGenerator<'a> {
+        ReadingGenerator {
+            readings,
+            sensor_kind: SensorKind::from_sensor_id(sensor_id),
+            rng: SmallRng::seed_from_u64(sensor_id as u64),
+            warmup_samples,
+            random_failure_prob,
+            number_of_samples: 0,
+        }
+    }
+
+    /// Takes the next sample, returning `None` while the sensor is warming up
+    pub fn next_reading(&mut self) -> Option<f32> {
+        let plausible_range = self.sensor_kind.plausible_range();
+        let reading: f32 = self
+            .readings
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .filter(|reading| plausible_range.contains(reading))
+            .choose_stable(&mut self.rng)
+            .expect("Data file contains no plausible readings");
+        // The generator is only advanced if failures are injected, so runs without them read the same data
+        let reading =
+            if self.random_failure_prob > 0.0 && self.rng.gen::<f32>() < self.random_failure_prob {
+                RANDOM_FAILURE_READING
+            } else {
+                reading
+            };
+        self.number_of_samples = self.number_of_samples.saturating_add(1);
+        if self.number_of_samples <= self.warmup_samples {
+            None
+        } else {
+            Some(reading)
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! In-memory I2C bus, connecting simulated sensors to the controller without hardware
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use data_transfer_objects::SensorMessage;
+
+use crate::{I2cBus, ReadingGenerator};
+
+/// Device side of the loopback bus, mirroring the link of the pico sensor firmware
+pub trait I2cPeripheral: Send {
+    /// Fills the buffer with the answer to a read of the controller, returning its length
+    fn respond(&mut self, buffer: &mut [u8]) -> usize;
+    /// Receives the bytes written by the controller, returning how many were accepted
+    fn receive(&mut self, buffer: &[u8]) -> usize;
+}
+
+#[derive(Debug)]
+pub enum LoopbackError {
+    NoSlaveAddress,
+    UnknownSlaveAddress(u16),
+}
+
+#[derive(Default)]
+pub struct LoopbackBus {
+    peripherals: HashMap<u16, Box<dyn I2cPeripheral>>,
+    slave_address: Option<u16>,
+}
+
+impl LoopbackBus {
+    pub fn new() -> LoopbackBus {
+        LoopbackBus::default()
+    }
+
+    pub fn attach(&mut self, address: u16, peripheral: impl I2cPeripheral + 'static) {
+        self.peripherals.insert(address, Box::new(peripheral));
+    }
+
+    fn get_peripheral(&mut self) -> Result<&mut Box<dyn I2cPeripheral>, LoopbackError> {
+        let slave_address = self.slave_address.ok_or(LoopbackError::NoSlaveAddress)?;
+        self.peripherals
+            .get_mut(&slave_address)
+            .ok_or(LoopbackError::UnknownSlaveAddress(slave_address))
+    }
+}
+
+impl I2cBus for LoopbackBus {
+    type Error = LoopbackError;
+
+    fn set_slave_address(&mut self, address: u16) -> Result<(), LoopbackError> {
+        if !self.peripherals.contains_key(&address) {
+            return Err(LoopbackError::UnknownSlaveAddress(address));
+        }
+        self.slave_address = Some(address);
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, LoopbackError> {
+        Ok(self.get_peripheral()?.respond(buffer))
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, LoopbackError> {
+        Ok(self.get_peripheral()?.receive(buffer))
+    }
+}
+
+/// Simulated pico sensor, answering a read with a COBS encoded message of its next reading
+/// once its sampling interval has elapsed, and with nothing otherwise
+pub struct SimulatedSensor {
+    sensor_id: u32,
+    sampling_interval: Duration,
+    last_sample: Option<Instant>,
+    generator: ReadingGenerator<'static>,
+}
+
+impl SimulatedSensor {
+    pub fn new(
+        sensor_id: u32,
+        readings: &'static str,
+        sampling_interval: Duration,
+        warmup_samples: u32,
+        random_failure_prob: f32,
+    ) -> SimulatedSensor {
+        SimulatedSensor {
+            sensor_id,
+            sampling_interval,
+            last_sample: None,
+            generator: ReadingGenerator::new(
+                sensor_id,
+                readings,
+                warmup_samples,
+                random_failure_prob,
+            ),
+        }
+    }
+}
+
+impl I2cPeripheral for SimulatedSensor {
+    fn respond(&mut self, buffer: &mut [u8]) -> usize {
+        if self
+            .last_sample
+            .is_some_and(|last_sample| last_sample.elapsed() < self.sampling_interval)
+        {
+            return 0;
+        }
+        self.last_sample = Some(Instant::now());
+        match self.generator.next_reading() {
+            Some(reading) => {
+                let message = SensorMessage {
+                    reading,
+                    sensor_id: self.sensor_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("Current time is before the unix epoch")
+                        .as_secs_f64(),
+                    sample_id: None,
+                };
+                postcard::to_slice_cobs(&message, buffer)
+                    .map(|message_bytes| message_bytes.len())
+                    .unwrap_or(0)
+            }
+            None => 0,
+        }
+    }
+
+    // The parameters of a simulated sensor are set on construction
+    fn receive(&mut self, buffer: &[u8]) -> usize {
+        buffer.len()
+    }
+}
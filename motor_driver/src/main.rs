@@ -1,29 +1,103 @@
 use std::io::Write;
-use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
 use std::ops::Shl;
-use std::process::{Command, Stdio};
-use std::{fs, thread};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{env, fs, io, process, thread};
 
-use log::{error, info};
-use postcard::to_allocvec;
+use log::{debug, error, info, warn};
 use serde::Deserialize;
 use threadpool::ThreadPool;
 
 use data_transfer_objects::{
-    MotorDriverRunParameters, MotorMonitorParameters, RequestProcessingModel, SensorParameters,
+    AbortRun, AveragingStrategy, BenchmarkOutput, ClockOffset, CrashReport, DeliveryStatus,
+    MonitorBenchmarkData, MotorDriverRunParameters, MotorMonitorParameters, ParameterAck, PortPlan,
+    PortPlanError, RequestProcessingModel, RuleThresholds, RunPhase, RunStart, RunState,
+    SampledMessageSend, SensorClockReport, SensorDelivery, SensorDriverRequest, SensorKind,
+    SensorParameters, SensorReport, Transport, WindowKind,
 };
+use utils::crash::CRASH_EXIT_CODE;
+use utils::transport::get_socket_path;
+use utils::watchdog::MEMORY_LIMIT_EXIT_CODE;
+use utils::ReadError;
 
 #[cfg(debug_assertions)]
 const CONFIG_PATH: &str = "resources/config-debug.toml";
 #[cfg(not(debug_assertions))]
 const CONFIG_PATH: &str = "/etc/config-production.toml";
 
+// How long to wait for a sensor driver to echo the parameters it received
+const SENSOR_ECHO_TIMEOUT: Duration = Duration::from_secs(5);
+// How long to wait for all sensor drivers to be connected to and echo their parameters
+const SENSOR_DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// How long to wait for a sensor driver to return the network usage and send log of the sensor
+// after the end of the run
+const SENSOR_EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_SEND_LOG_SIZE: usize = 1024 * 1024;
+
 #[derive(Deserialize)]
 struct MotorDriverParameters {
     test_driver_listen_address: SocketAddr,
+    /// Address the state of the current run is served on, not served if absent
+    status_listen_address: Option<SocketAddr>,
+    /// Restarts of a failing monitor in runs with a sensor reconnect retry policy, none if absent
+    #[serde(default)]
+    max_monitor_restarts: u32,
+    /// How long to wait for the sensor control tasks once the monitors exited, after which the
+    /// reports of the finished ones are forwarded without those of the hung ones
+    #[serde(default = "get_default_sensor_join_timeout_secs")]
+    sensor_join_timeout_secs: u64,
+}
+
+/// Abort of a run by the test driver, on which the monitors of the run are terminated
+/// and not restarted
+#[derive(Default)]
+struct RunAbort {
+    aborted: AtomicBool,
+    /// Process ids of the running monitors
+    monitor_pids: Mutex<Vec<u32>>,
+}
+
+impl RunAbort {
+    fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Terminates the running monitors, and the ones registered afterwards
+    fn abort(&self) {
+        let monitor_pids = self.monitor_pids.lock().unwrap();
+        self.aborted.store(true, Ordering::SeqCst);
+        for pid in monitor_pids.iter() {
+            terminate_motor_monitor(*pid);
+        }
+    }
+
+    fn register_monitor(&self, pid: u32) {
+        let mut monitor_pids = self.monitor_pids.lock().unwrap();
+        monitor_pids.push(pid);
+        if self.is_aborted() {
+            terminate_motor_monitor(pid);
+        }
+    }
+
+    fn unregister_monitor(&self, pid: u32) {
+        self.monitor_pids
+            .lock()
+            .unwrap()
+            .retain(|monitor_pid| *monitor_pid != pid);
+    }
+}
+
+/// Leaves the sensor drivers their `SENSOR_EXIT_TIMEOUT` to return the reports, and then some
+fn get_default_sensor_join_timeout_secs() -> u64 {
+    30
 }
 
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::init();
     let motor_driver_parameters: MotorDriverParameters =
         toml::from_str(&fs::read_to_string(CONFIG_PATH).expect("Could not read config file"))
@@ -39,16 +113,55 @@ fn main() {
         "Bound to {}",
         motor_driver_parameters.test_driver_listen_address
     );
+    let max_monitor_restarts = motor_driver_parameters.max_monitor_restarts;
+    if max_monitor_restarts > 0 {
+        info!("Restarting failed monitors only in runs with a sensor reconnect retry policy");
+    }
+    let sensor_join_timeout = Duration::from_secs(motor_driver_parameters.sensor_join_timeout_secs);
+    let run_state = Arc::new(Mutex::new(RunState::new()));
+    if let Some(status_listen_address) = motor_driver_parameters.status_listen_address {
+        serve_run_state(status_listen_address, run_state.clone());
+    }
     for test_driver_stream in listener.incoming() {
         info!("Received incoming request");
         match test_driver_stream {
             Ok(mut test_driver_stream) => {
+                let run_state = run_state.clone();
                 thread::spawn(move || {
                     info!("New run");
-                    let run_parameters =
-                        utils::read_object::<MotorDriverRunParameters>(&mut test_driver_stream)
-                            .expect("Could not get run parameters");
-                    execute_new_run(run_parameters, test_driver_stream);
+                    set_run_phase(&run_state, RunPhase::AwaitingParameters);
+                    // The test driver aborts without sending parameters if the clocks disagree
+                    let run_parameters = match utils::answer_clock_probe(&mut test_driver_stream)
+                        .and_then(|_| {
+                            utils::read_object::<MotorDriverRunParameters>(&mut test_driver_stream)
+                        }) {
+                        Ok(run_parameters) => run_parameters,
+                        Err(e) => {
+                            error!("Could not get run parameters: {e}");
+                            set_run_phase(&run_state, RunPhase::Idle);
+                            return;
+                        }
+                    };
+                    let parameter_ack = ParameterAck {
+                        run_id: run_parameters.run_id,
+                        build_info: utils::build_info::get().cloned(),
+                    };
+                    if let Err(e) = test_driver_stream.write_all(
+                        &utils::encode_object(&parameter_ack)
+                            .expect("Could not write parameter ack to Vec<u8>"),
+                    ) {
+                        error!("Could not acknowledge run parameters: {e}");
+                        set_run_phase(&run_state, RunPhase::Idle);
+                        return;
+                    }
+                    execute_new_run(
+                        run_parameters.compress_time(),
+                        test_driver_stream,
+                        max_monitor_restarts,
+                        sensor_join_timeout,
+                        &run_state,
+                    );
+                    set_run_phase(&run_state, RunPhase::Idle);
                     info!("Finished run");
                 });
             }
@@ -61,74 +174,468 @@ fn main() {
     info!("Quitting");
 }
 
-fn execute_new_run(motor_driver_parameters: MotorDriverRunParameters, test_driver: TcpStream) {
-    let motor_monitor_parameters = create_motor_monitor_parameters(&motor_driver_parameters);
+/// Answers every connection to `status_listen_address` with the current run state,
+/// returning the address the endpoint is bound to
+fn serve_run_state(
+    status_listen_address: SocketAddr,
+    run_state: Arc<Mutex<RunState>>,
+) -> SocketAddr {
+    let listener = TcpListener::bind(status_listen_address)
+        .unwrap_or_else(|e| panic!("Could not bind to {status_listen_address}: {e}"));
+    let status_listen_address = listener
+        .local_addr()
+        .expect("Could not get address of status listener");
+    info!("Serving run state on {status_listen_address}");
+    thread::spawn(move || {
+        for status_stream in listener.incoming() {
+            match status_stream {
+                Ok(mut status_stream) => {
+                    let data = utils::encode_object(&*run_state.lock().unwrap())
+                        .expect("Could not write run state to Vec<u8>");
+                    if let Err(e) = status_stream.write_all(&data) {
+                        warn!("Could not send run state: {e}");
+                    }
+                }
+                Err(e) => {
+                    error!("Error: {}", e);
+                }
+            }
+        }
+    });
+    status_listen_address
+}
+
+fn set_run_phase(run_state: &Mutex<RunState>, phase: RunPhase) {
+    debug!("Entering run phase {phase:?}");
+    run_state.lock().unwrap().phase = phase;
+}
+
+/// Enters the provisioning of the sensors, none of which got their parameters yet
+fn begin_sensor_provisioning(run_state: &Mutex<RunState>, sensors: Vec<SensorDelivery>) {
+    debug!("Entering run phase {:?}", RunPhase::ProvisioningSensors);
+    let mut run_state = run_state.lock().unwrap();
+    run_state.phase = RunPhase::ProvisioningSensors;
+    run_state.sensors = sensors;
+}
+
+fn set_sensor_delivery_status(run_state: &Mutex<RunState>, sensor_id: u32, status: DeliveryStatus) {
+    if let Some(sensor) = run_state
+        .lock()
+        .unwrap()
+        .sensors
+        .iter_mut()
+        .find(|sensor| sensor.sensor_id == sensor_id)
+    {
+        sensor.status = status;
+    }
+}
+
+fn execute_new_run(
+    mut motor_driver_parameters: MotorDriverRunParameters,
+    mut test_driver: TcpStream,
+    max_monitor_restarts: u32,
+    sensor_join_timeout: Duration,
+    run_state: &Arc<Mutex<RunState>>,
+) {
+    let sensor_clock_report = check_sensor_clocks(&motor_driver_parameters, run_state);
+    test_driver
+        .write_all(
+            &utils::encode_object(&sensor_clock_report)
+                .expect("Could not write sensor clock report to Vec<u8>"),
+        )
+        .expect("Could not send sensor clock report to test driver");
+    if let Some((sensor_driver_address, clock_offset)) = sensor_clock_report.worst_offset {
+        if clock_offset.offset.abs() * 1000.0 > motor_driver_parameters.max_clock_offset_ms as f64 {
+            error!(
+                "Clock of sensor driver {sensor_driver_address} is off by {:.1} ms, aborting run",
+                clock_offset.offset * 1000.0
+            );
+            return;
+        }
+    }
+    set_run_phase(run_state, RunPhase::AwaitingStart);
+    match utils::read_object::<RunStart>(&mut test_driver) {
+        Ok(run_start) => motor_driver_parameters.start_time = run_start.start_time,
+        Err(e) => {
+            error!("Could not get run start, aborting run: {e}");
+            return;
+        }
+    }
+    let port_plan = match get_port_plan(&motor_driver_parameters) {
+        Ok(port_plan) => port_plan,
+        Err(e) => {
+            error!("Could not plan the ports of the run, aborting run: {e}");
+            return;
+        }
+    };
+    let run_directory = match utils::create_run_directory(
+        motor_driver_parameters.run_directory.as_deref(),
+        "motor_driver",
+    ) {
+        Ok(run_directory) => run_directory,
+        Err(e) => {
+            error!("Could not create the run directory, aborting run: {e}");
+            return;
+        }
+    };
+    let motor_monitor_parameters =
+        create_motor_monitor_parameters(&motor_driver_parameters, run_directory.as_deref());
+    let run_abort = Arc::new(RunAbort::default());
+    watch_for_abort(
+        test_driver
+            .try_clone()
+            .expect("Could not clone test driver connection"),
+        &motor_driver_parameters,
+        &run_abort,
+    );
     let no_of_sensors = motor_driver_parameters.number_of_tcp_motor_groups * 4;
     let pool = ThreadPool::new(no_of_sensors);
-    setup_tcp_sensors(
+    let send_logs = Arc::new(Mutex::new(vec![]));
+    let sensor_report = Arc::new(Mutex::new(SensorReport::default()));
+    let undelivered_sensors = setup_tcp_sensors(
         motor_driver_parameters.clone(),
         &motor_monitor_parameters,
+        &port_plan,
         &pool,
+        run_state,
+        &send_logs,
+        &sensor_report,
     );
+    if !undelivered_sensors.is_empty() {
+        error!(
+            "Sensors {undelivered_sensors:?} did not receive their parameters intact, aborting run"
+        );
+        abort_sensor_drivers(
+            &motor_driver_parameters,
+            &AbortRun {
+                run_id: motor_driver_parameters.run_id,
+                reason: format!(
+                    "Sensors {undelivered_sensors:?} did not receive their parameters intact"
+                ),
+            },
+        );
+        // Also ends the watch for an abort of the run
+        let _ = test_driver.shutdown(Shutdown::Both);
+        return;
+    }
     info!("Setup sensors");
-    handle_motor_monitor(
-        motor_driver_parameters.request_processing_model,
-        motor_monitor_parameters,
-        test_driver,
+    let secondary_motor_monitor_parameters = motor_driver_parameters
+        .secondary_processing_model
+        .map(|secondary_processing_model| {
+            create_secondary_motor_monitor_parameters(
+                &motor_monitor_parameters,
+                run_directory.as_deref(),
+                secondary_processing_model,
+                motor_driver_parameters.secondary_sensor_port,
+            )
+        });
+    let monitor_benchmark_data = thread::scope(|scope| {
+        // Only the primary monitor persists snapshots, so the two do not overwrite each other's
+        let secondary_handle = secondary_motor_monitor_parameters.map(|parameters| {
+            scope.spawn(|| {
+                handle_motor_monitor(
+                    parameters,
+                    None,
+                    motor_driver_parameters.batch_recv,
+                    false,
+                    max_monitor_restarts,
+                    run_state,
+                    &run_abort,
+                )
+            })
+        });
+        let mut monitor_benchmark_data = vec![handle_motor_monitor(
+            motor_monitor_parameters,
+            motor_driver_parameters.snapshot_path.as_deref(),
+            motor_driver_parameters.batch_recv,
+            motor_driver_parameters.sensor_reconnect_retry.is_some(),
+            max_monitor_restarts,
+            run_state,
+            &run_abort,
+        )];
+        if let Some(secondary_handle) = secondary_handle {
+            monitor_benchmark_data.push(
+                secondary_handle
+                    .join()
+                    .expect("Secondary motor monitor thread panicked"),
+            );
+        }
+        monitor_benchmark_data
+    });
+    // The test driver gave up on the results, and the sensors are terminated by their drivers
+    if run_abort.is_aborted() {
+        warn!("Run was aborted, not forwarding its results");
+        return;
+    }
+    set_run_phase(run_state, RunPhase::CollectingOutput);
+    for monitor_benchmark_data in monitor_benchmark_data {
+        test_driver
+            .write_all(
+                &utils::encode_object(&monitor_benchmark_data)
+                    .expect("Could not write benchmark data to Vec<u8>"),
+            )
+            .expect("Failure writing benchmark data to TcpStream");
+    }
+    info!("Forwarded benchmark data");
+    if !join_sensor_tasks(&pool, sensor_join_timeout) {
+        warn!(
+            "{} sensor control tasks did not finish within {sensor_join_timeout:?}, forwarding the reports of the others",
+            pool.active_count()
+        );
+    }
+    let sensor_report = sensor_report.lock().unwrap();
+    test_driver
+        .write_all(
+            &utils::encode_object(&*sensor_report)
+                .expect("Could not write sensor report to Vec<u8>"),
+        )
+        .expect("Failure writing sensor report to TcpStream");
+    info!(
+        "Forwarded the network usage of the sensors ({} bytes written) and {} sensor builds",
+        sensor_report.network_usage.bytes_written,
+        sensor_report.builds.len()
     );
-    pool.join();
+    if motor_driver_parameters.provenance_sample_interval > 0 {
+        let send_logs = send_logs.lock().unwrap();
+        test_driver
+            .write_all(
+                &utils::encode_object(&*send_logs).expect("Could not write send logs to Vec<u8>"),
+            )
+            .expect("Failure writing send logs to TcpStream");
+        info!(
+            "Forwarded the send times of {} sampled messages",
+            send_logs.len()
+        );
+    }
+    // Ends the watch for an abort of the run
+    let _ = test_driver.shutdown(Shutdown::Read);
+}
+
+/// Watches the control connection of the test driver for an abort of the run, until it is closed.
+/// On an abort, the monitors are terminated and the abort is forwarded to the sensor drivers.
+fn watch_for_abort(
+    mut test_driver: TcpStream,
+    motor_driver_parameters: &MotorDriverRunParameters,
+    run_abort: &Arc<RunAbort>,
+) {
+    let motor_driver_parameters = motor_driver_parameters.clone();
+    let run_abort = Arc::clone(run_abort);
+    thread::spawn(move || {
+        let run_id = motor_driver_parameters.run_id;
+        while let Ok(abort_run) = utils::read_object::<AbortRun>(&mut test_driver) {
+            if abort_run.run_id != run_id {
+                info!(
+                    "Ignoring abort of run {}, the current run is {run_id}",
+                    abort_run.run_id
+                );
+                continue;
+            }
+            warn!("Run {run_id} was aborted: {}", abort_run.reason);
+            run_abort.abort();
+            abort_sensor_drivers(&motor_driver_parameters, &abort_run);
+            return;
+        }
+    });
 }
 
+/// Forwards the abort of the run to each sensor driver, which terminates the sensors of the run
+fn abort_sensor_drivers(motor_driver_parameters: &MotorDriverRunParameters, abort_run: &AbortRun) {
+    let mut sensor_driver_addresses = motor_driver_parameters.sensor_socket_addresses.clone();
+    sensor_driver_addresses.sort();
+    sensor_driver_addresses.dedup();
+    for sensor_driver_address in &sensor_driver_addresses {
+        if let Err(e) = TcpStream::connect(sensor_driver_address).and_then(|mut stream| {
+            write_sensor_driver_request(&SensorDriverRequest::Abort(abort_run.clone()), &mut stream)
+        }) {
+            error!("Could not forward abort to {sensor_driver_address}: {e}");
+        }
+    }
+}
+
+/// Waits for the sensor control tasks to finish, returning whether they did within the timeout.
+/// A task which hangs, e.g. connecting to an unresponsive sensor driver, is left running,
+/// so that it does not wedge the run.
+fn join_sensor_tasks(pool: &ThreadPool, timeout: Duration) -> bool {
+    let (finished_sender, finished_receiver) = mpsc::channel();
+    let pool = pool.clone();
+    thread::spawn(move || {
+        pool.join();
+        // The receiver is gone if the timeout elapsed first
+        let _ = finished_sender.send(());
+    });
+    finished_receiver.recv_timeout(timeout).is_ok()
+}
+
+/// Measures the clock offset of every sensor driver, returning the one which is off the most
+fn check_sensor_clocks(
+    motor_driver_parameters: &MotorDriverRunParameters,
+    run_state: &Mutex<RunState>,
+) -> SensorClockReport {
+    set_run_phase(run_state, RunPhase::CheckingSensorClocks);
+    let mut worst_offset: Option<(SocketAddr, ClockOffset)> = None;
+    for sensor_driver_address in &motor_driver_parameters.sensor_socket_addresses {
+        let clock_offset = TcpStream::connect(sensor_driver_address)
+            .map_err(ReadError::Io)
+            .and_then(|mut sensor_stream| {
+                write_sensor_driver_request(&SensorDriverRequest::ClockProbe, &mut sensor_stream)
+                    .map_err(ReadError::Io)?;
+                utils::measure_clock_offset(&mut sensor_stream)
+            });
+        match clock_offset {
+            Ok(clock_offset) => {
+                debug!(
+                    "Clock of sensor driver {sensor_driver_address} is off by {:.1} ms",
+                    clock_offset.offset * 1000.0
+                );
+                if worst_offset
+                    .is_none_or(|(_, worst)| clock_offset.offset.abs() > worst.offset.abs())
+                {
+                    worst_offset = Some((*sensor_driver_address, clock_offset));
+                }
+            }
+            Err(e) => warn!("Could not check clock of sensor driver {sensor_driver_address}: {e}"),
+        }
+    }
+    if let Some((sensor_driver_address, clock_offset)) = worst_offset {
+        info!(
+            "Clock of sensor driver {sensor_driver_address} is off the most, by {:.1} ms",
+            clock_offset.offset * 1000.0
+        );
+    }
+    SensorClockReport { worst_offset }
+}
+
+/// Sends the parameters to the sensor drivers, returning the ids of the sensors which did not
+/// receive them intact
 fn setup_tcp_sensors(
     motor_driver_parameters: MotorDriverRunParameters,
     motor_monitor_parameters: &MotorMonitorParameters,
+    port_plan: &PortPlan,
     pool: &ThreadPool,
-) {
+    run_state: &Arc<Mutex<RunState>>,
+    send_logs: &Arc<Mutex<Vec<SampledMessageSend>>>,
+    sensor_report: &Arc<Mutex<SensorReport>>,
+) -> Vec<u32> {
     let no_i2c = motor_monitor_parameters.number_of_i2c_motor_groups as u16;
+    let get_full_id = |index: usize| {
+        let motor_id = index / 4 + no_i2c as usize;
+        let sensor_id = index % 4;
+        (motor_id as u32).shl(2) + sensor_id as u32
+    };
+    begin_sensor_provisioning(
+        run_state,
+        motor_driver_parameters
+            .sensor_socket_addresses
+            .iter()
+            .enumerate()
+            .map(|(index, address)| SensorDelivery {
+                sensor_id: get_full_id(index),
+                address: *address,
+                status: DeliveryStatus::Pending,
+            })
+            .collect(),
+    );
+    let (delivery_sender, delivery_receiver) = mpsc::channel();
+    let mut sensor_ids = vec![];
     for (index, sensor_driver_address) in motor_driver_parameters
         .sensor_socket_addresses
         .clone()
         .into_iter()
         .enumerate()
     {
-        let motor_id = index / 4 + no_i2c as usize;
-        let sensor_id = index % 4;
-        let full_id: u32 = (motor_id as u32).shl(2) + sensor_id as u32;
-        let motor_monitor_listen_address =
-            get_motor_monitor_listen_address(motor_monitor_parameters, full_id as u16);
+        let full_id = get_full_id(index);
+        sensor_ids.push(full_id);
+        let motor_monitor_listen_address = SocketAddr::new(
+            motor_monitor_parameters.sensor_listen_address.ip(),
+            port_plan.get_sensor_port(full_id),
+        );
         let sensor_parameters = create_sensor_parameters(
             full_id,
             motor_monitor_listen_address,
             &motor_driver_parameters,
         );
+        let run_state = run_state.clone();
+        let send_logs = send_logs.clone();
+        let sensor_report = sensor_report.clone();
+        let delivery_sender = delivery_sender.clone();
         pool.execute(move || {
-            control_sensor(sensor_driver_address, sensor_parameters);
+            control_sensor(
+                sensor_driver_address,
+                sensor_parameters,
+                &send_logs,
+                &sensor_report,
+                |status| {
+                    set_sensor_delivery_status(&run_state, full_id, status);
+                    // The setup stops listening once it gave up on the deliveries
+                    let _ = delivery_sender.send((full_id, status));
+                },
+            );
         });
     }
+    await_sensor_deliveries(&delivery_receiver, sensor_ids)
 }
 
-fn get_motor_monitor_listen_address(
-    motor_monitor_parameters: &MotorMonitorParameters,
-    index: u16,
-) -> SocketAddr {
-    match motor_monitor_parameters.request_processing_model {
-        RequestProcessingModel::ReactiveStreaming => motor_monitor_parameters.sensor_listen_address,
-        RequestProcessingModel::ClientServer => motor_monitor_parameters.sensor_listen_address,
-        RequestProcessingModel::ObjectOriented => motor_monitor_parameters.sensor_listen_address,
-        RequestProcessingModel::SpringQL => SocketAddr::new(
-            motor_monitor_parameters.sensor_listen_address.ip(),
-            motor_monitor_parameters.sensor_listen_address.port() + index,
+/// Waits for the delivery statuses of the sensors, returning the ids of the ones whose parameters
+/// were not delivered intact, or not within the timeout
+fn await_sensor_deliveries(
+    delivery_receiver: &mpsc::Receiver<(u32, DeliveryStatus)>,
+    mut pending_sensor_ids: Vec<u32>,
+) -> Vec<u32> {
+    let deadline = Instant::now() + SENSOR_DELIVERY_TIMEOUT;
+    let mut undelivered_sensor_ids = vec![];
+    while !pending_sensor_ids.is_empty() {
+        match delivery_receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok((sensor_id, status)) => {
+                pending_sensor_ids.retain(|pending_sensor_id| *pending_sensor_id != sensor_id);
+                if status != DeliveryStatus::Delivered {
+                    undelivered_sensor_ids.push(sensor_id);
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    undelivered_sensor_ids.extend(pending_sensor_ids);
+    undelivered_sensor_ids
+}
+
+/// Plans the ports the sensors connect to, which must not overlap the ones of the cloud server
+/// and the secondary monitor
+fn get_port_plan(
+    motor_driver_parameters: &MotorDriverRunParameters,
+) -> Result<PortPlan, PortPlanError> {
+    let port_plan = PortPlan::new(
+        motor_driver_parameters.sensor_listen_address.port(),
+        (motor_driver_parameters.number_of_tcp_motor_groups
+            + motor_driver_parameters.number_of_i2c_motor_groups as usize) as u16,
+        motor_driver_parameters.request_processing_model,
+    )?
+    .reserve(
+        "cloud server monitor listener",
+        motor_driver_parameters.motor_monitor_listen_address.port(),
+    )?;
+    match motor_driver_parameters.secondary_processing_model {
+        Some(_) => port_plan.reserve(
+            "secondary sensor listener",
+            motor_driver_parameters.secondary_sensor_port,
         ),
+        None => Ok(port_plan),
     }
 }
 
+/// Runs the motor monitor to completion, returning the benchmark data it wrote
 fn handle_motor_monitor(
-    request_processing_model: RequestProcessingModel,
     motor_monitor_parameters: MotorMonitorParameters,
-    mut stream: TcpStream,
-) {
-    info!("Running motor monitor");
-    let output = create_run_command(request_processing_model)
+    snapshot_path: Option<&str>,
+    batch_recv: bool,
+    accept_reconnects: bool,
+    max_monitor_restarts: u32,
+    run_state: &Mutex<RunState>,
+    run_abort: &RunAbort,
+) -> MonitorBenchmarkData {
+    let request_processing_model = motor_monitor_parameters.request_processing_model;
+    info!("Running {request_processing_model:?} motor monitor");
+    let mut command = create_run_command(request_processing_model);
+    command
         .arg(motor_monitor_parameters.start_time.to_string())
         .arg(motor_monitor_parameters.duration.to_string())
         .arg(request_processing_model.to_string())
@@ -160,29 +667,354 @@ fn handle_motor_monitor(
                 .to_string(),
         )
         .arg(motor_monitor_parameters.thread_pool_size.to_string())
-        .stderr(Stdio::inherit())
-        // .stdout(Stdio::inherit())
-        .output()
+        .arg(motor_monitor_parameters.wear_model.wear_rate.to_string())
+        .arg(
+            motor_monitor_parameters
+                .wear_model
+                .rotational_speed_threshold
+                .to_string(),
+        )
+        .arg(
+            motor_monitor_parameters
+                .wear_model
+                .tool_change
+                .to_interval_ms()
+                .to_string(),
+        )
+        .arg(motor_monitor_parameters.use_cumulative_age.to_string())
+        .arg(motor_monitor_parameters.sensor_bind_port.to_string())
+        .arg("--run-id")
+        .arg(motor_monitor_parameters.run_id.to_string())
+        .stderr(Stdio::inherit());
+    match motor_monitor_parameters.benchmark_output {
+        BenchmarkOutput::Stdout => command.stdout(Stdio::piped()),
+        ref benchmark_output => command
+            .arg("--benchmark-output")
+            .arg(benchmark_output.to_string())
+            .stdout(Stdio::inherit()),
+    };
+    if let Some(snapshot_path) = snapshot_path {
+        if request_processing_model == RequestProcessingModel::ClientServer {
+            command.arg("--snapshot-path").arg(snapshot_path);
+        }
+    }
+    if batch_recv && request_processing_model == RequestProcessingModel::ClientServer {
+        command.arg("--batch-recv");
+    }
+    if accept_reconnects && request_processing_model == RequestProcessingModel::ClientServer {
+        command.arg("--accept-reconnects");
+    }
+    if motor_monitor_parameters.buffer_alerts {
+        command.arg("--buffer-alerts");
+    }
+    if motor_monitor_parameters.compress_alerts {
+        command.arg("--compress-alerts");
+    }
+    if motor_monitor_parameters.detailed_alerts {
+        command.arg("--detailed-alerts");
+    }
+    for alert_webhook in &motor_monitor_parameters.alert_webhooks {
+        command
+            .arg("--alert-webhook")
+            .arg(format!("{}={}", alert_webhook.motor_id, alert_webhook.url));
+    }
+    for route_rule in &motor_monitor_parameters.routing {
+        command.arg("--route").arg(route_rule.to_string());
+    }
+    if motor_monitor_parameters.strict_deserialization {
+        command.arg("--strict-deserialization");
+    }
+    if motor_monitor_parameters.compact_wire {
+        command.arg("--compact-wire");
+    }
+    if motor_monitor_parameters.forensic_alerts {
+        command.arg("--forensic-alerts");
+    }
+    if motor_monitor_parameters.track_provenance {
+        command.arg("--track-provenance");
+    }
+    if motor_monitor_parameters.report_post {
+        command.arg("--report-post");
+    }
+    if let Some(memory_limit_mb) = motor_monitor_parameters.memory_limit_mb {
+        command
+            .arg("--memory-limit-mb")
+            .arg(memory_limit_mb.to_string());
+    }
+    if let Some(max_sensor_connections) = motor_monitor_parameters.max_sensor_connections {
+        command
+            .arg("--max-sensor-connections")
+            .arg(max_sensor_connections.to_string());
+    }
+    if motor_monitor_parameters.reject_excess_connections {
+        command.arg("--reject-excess-connections");
+    }
+    if motor_monitor_parameters.expect_sensor_order {
+        command.arg("--expect-sensor-order");
+    }
+    if motor_monitor_parameters.interpolate_gaps
+        && request_processing_model == RequestProcessingModel::ClientServer
+    {
+        command.arg("--interpolate-gaps");
+    }
+    if let Some(otel_endpoint) = &motor_monitor_parameters.otel_endpoint {
+        command.arg("--otel-endpoint").arg(otel_endpoint);
+    }
+    if motor_monitor_parameters.no_cloud {
+        command.arg("--no-cloud");
+    }
+    if let Some(window_export) = &motor_monitor_parameters.window_export {
+        info!(
+            "The {request_processing_model:?} motor monitor exports its windows to {window_export}"
+        );
+        command.arg("--window-export").arg(window_export);
+    }
+    if let Some(max_quarantine_rate) = motor_monitor_parameters.max_quarantine_rate {
+        command
+            .arg("--max-quarantine-rate")
+            .arg(max_quarantine_rate.to_string());
+    }
+    if let Some(window_slide_ms) = motor_monitor_parameters.window_slide_ms {
+        command
+            .arg("--window-slide-ms")
+            .arg(window_slide_ms.to_string());
+    }
+    if motor_monitor_parameters.window_allowed_delay_ms > 0 {
+        command
+            .arg("--window-allowed-delay-ms")
+            .arg(motor_monitor_parameters.window_allowed_delay_ms.to_string());
+    }
+    if let WindowKind::Count(window_count) = motor_monitor_parameters.window_kind {
+        command.arg("--window-count").arg(window_count.to_string());
+    }
+    if motor_monitor_parameters.averaging != AveragingStrategy::Window {
+        command
+            .arg("--averaging")
+            .arg(motor_monitor_parameters.averaging.to_string());
+    }
+    if motor_monitor_parameters.rule_thresholds != RuleThresholds::default() {
+        command
+            .arg("--rule-thresholds")
+            .arg(motor_monitor_parameters.rule_thresholds.to_string());
+    }
+    if motor_monitor_parameters.startup_barrier_ms > 0 {
+        command
+            .arg("--startup-barrier-ms")
+            .arg(motor_monitor_parameters.startup_barrier_ms.to_string());
+    }
+    if !motor_monitor_parameters.cpu_cores.is_empty() {
+        command.arg("--cpu-cores").arg(
+            motor_monitor_parameters
+                .cpu_cores
+                .iter()
+                .map(|cpu_core| cpu_core.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+    }
+    if let Some(crash_file) = &motor_monitor_parameters.crash_file {
+        command.arg("--crash-file").arg(crash_file);
+    }
+    if motor_monitor_parameters.transport != Transport::Tcp {
+        command
+            .arg("--transport")
+            .arg(motor_monitor_parameters.transport.to_string());
+    }
+    if motor_monitor_parameters.log_sample_rate > 1 {
+        command
+            .arg("--log-sample-rate")
+            .arg(motor_monitor_parameters.log_sample_rate.to_string());
+    }
+    // A restarted monitor binds a new listener, which only receives readings if the sensors
+    // reconnect to it
+    let max_monitor_restarts = match accept_reconnects {
+        true => max_monitor_restarts,
+        false => 0,
+    };
+    let output = supervise_motor_monitor(
+        &mut command,
+        &motor_monitor_parameters,
+        max_monitor_restarts,
+        run_state,
+        run_abort,
+    );
+    info!("{request_processing_model:?} motor monitor run complete");
+    if motor_monitor_parameters.transport != Transport::Tcp {
+        // The socket file outlives the listener, so it is removed once the monitor exited
+        let _ = fs::remove_file(get_socket_path(
+            motor_monitor_parameters.run_id,
+            motor_monitor_parameters.sensor_listen_address.port(),
+        ));
+    }
+    if output.status.code() == Some(MEMORY_LIMIT_EXIT_CODE) {
+        error!(
+            "{request_processing_model:?} motor monitor exceeded its memory limit of {} MB",
+            motor_monitor_parameters.memory_limit_mb.unwrap_or_default()
+        );
+    }
+    MonitorBenchmarkData {
+        request_processing_model,
+        crash_report: read_crash_report(&motor_monitor_parameters, &output),
+        benchmark_data: read_benchmark_data(&motor_monitor_parameters.benchmark_output, output),
+    }
+}
+
+/// Returns the crash report the motor monitor wrote, if it exited with the crash exit code
+fn read_crash_report(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    output: &Output,
+) -> Option<CrashReport> {
+    if output.status.code() != Some(CRASH_EXIT_CODE) {
+        return None;
+    }
+    let crash_file = motor_monitor_parameters.crash_file.as_ref()?;
+    let crash_report = fs::read(crash_file)
+        .map_err(|e| e.to_string())
+        .and_then(|mut frame| {
+            utils::decode_object::<CrashReport>(&mut frame).map_err(|e| e.to_string())
+        });
+    let _ = fs::remove_file(crash_file);
+    match crash_report {
+        Ok(crash_report) => {
+            error!(
+                "Motor monitor panicked at {}: {}",
+                crash_report.location, crash_report.message
+            );
+            Some(crash_report)
+        }
+        Err(e) => {
+            warn!("Motor monitor crashed, but its crash report could not be read: {e}");
+            None
+        }
+    }
+}
+
+/// Returns the benchmark data the motor monitor wrote to its benchmark output
+fn read_benchmark_data(benchmark_output: &BenchmarkOutput, output: Output) -> Vec<u8> {
+    match benchmark_output {
+        BenchmarkOutput::Path(path) => {
+            let benchmark_data = fs::read(path).unwrap_or_else(|e| {
+                warn!("Could not read benchmark data from {path}: {e}");
+                vec![]
+            });
+            let _ = fs::remove_file(path);
+            benchmark_data
+        }
+        _ => output.stdout,
+    }
+}
+
+/// Runs the motor monitor, restarting it up to `max_monitor_restarts` times if it exits
+/// unsuccessfully before the run duration has elapsed and the run was not aborted.
+/// Only the output of the last execution is returned.
+fn supervise_motor_monitor(
+    command: &mut Command,
+    motor_monitor_parameters: &MotorMonitorParameters,
+    max_monitor_restarts: u32,
+    run_state: &Mutex<RunState>,
+    run_abort: &RunAbort,
+) -> Output {
+    let end_time = Duration::from_secs_f64(motor_monitor_parameters.start_time)
+        + Duration::from_secs_f64(motor_monitor_parameters.duration);
+    let mut output = run_motor_monitor(command, motor_monitor_parameters, run_state, run_abort)
         .expect("Failure when trying to run motor monitor program");
-    info!("Motor monitor run complete");
-    stream
-        .write_all(&output.stdout)
-        .expect("Failure writing sensor stdout to TcpStream");
-    info!("Forwarded benchmark data");
+    let mut restarts = 0;
+    while !output.status.success()
+        && restarts < max_monitor_restarts
+        && utils::get_now_duration() < end_time
+        && !run_abort.is_aborted()
+    {
+        restarts += 1;
+        warn!(
+            "Motor monitor exited with {}, restarting ({restarts}/{max_monitor_restarts})",
+            output.status
+        );
+        output = run_motor_monitor(command, motor_monitor_parameters, run_state, run_abort)
+            .expect("Failure when trying to restart motor monitor program");
+    }
+    output
+}
+
+fn run_motor_monitor(
+    command: &mut Command,
+    motor_monitor_parameters: &MotorMonitorParameters,
+    run_state: &Mutex<RunState>,
+    run_abort: &RunAbort,
+) -> io::Result<Output> {
+    // Benchmark data of a previous execution must not be mistaken for the one of this execution
+    if let BenchmarkOutput::Path(path) = &motor_monitor_parameters.benchmark_output {
+        let _ = fs::remove_file(path);
+    }
+    if let Some(crash_file) = &motor_monitor_parameters.crash_file {
+        let _ = fs::remove_file(crash_file);
+    }
+    let child = command.spawn()?;
+    let pid = child.id();
+    set_run_phase(run_state, RunPhase::MonitorRunning { pid });
+    run_abort.register_monitor(pid);
+    let output = child.wait_with_output();
+    run_abort.unregister_monitor(pid);
+    output
+}
+
+fn terminate_motor_monitor(pid: u32) {
+    info!("Terminating motor monitor {pid}");
+    if let Err(e) = utils::signal::terminate(pid) {
+        error!("Could not terminate motor monitor {pid}: {e}");
+    }
 }
 
-fn control_sensor(sensor_driver_address: SocketAddr, sensor_parameters: SensorParameters) {
+/// Sends the parameters to the sensor driver, reporting whether they were delivered intact,
+/// and collects the network usage and builds of the sensor and, once the sensor sampled messages
+/// for provenance tracking, the send log it returns after the run
+fn control_sensor(
+    sensor_driver_address: SocketAddr,
+    sensor_parameters: SensorParameters,
+    send_logs: &Mutex<Vec<SampledMessageSend>>,
+    sensor_report: &Mutex<SensorReport>,
+    report_delivery: impl FnOnce(DeliveryStatus),
+) {
     info!(
         "Sending info to sensor {}, driver address {}, motor monitor listen address {}",
         sensor_parameters.id, sensor_driver_address, sensor_parameters.motor_monitor_listen_address
     );
-    match TcpStream::connect(sensor_driver_address) {
-        Ok(mut sensor_stream) => {
-            write_sensor_parameters(&sensor_parameters, &mut sensor_stream);
-        }
-        Err(e) => {
-            error!("Failed to connect to {sensor_driver_address}: {}", e);
-        }
+    let mut sensor_stream =
+        match deliver_sensor_parameters(sensor_driver_address, &sensor_parameters) {
+            Ok(sensor_stream) => {
+                report_delivery(DeliveryStatus::Delivered);
+                sensor_stream
+            }
+            Err(status) => {
+                report_delivery(status);
+                return;
+            }
+        };
+    set_sensor_exit_timeout(&sensor_parameters, &mut sensor_stream);
+    let report = read_sensor_report(&sensor_parameters, &mut sensor_stream);
+    add_sensor_report(&mut sensor_report.lock().unwrap(), report);
+    if sensor_parameters.provenance_sample_interval > 0 {
+        let send_log = read_send_log(&sensor_parameters, &mut sensor_stream);
+        send_logs.lock().unwrap().extend(send_log);
+    }
+}
+
+/// Sends the parameters to the sensor driver and verifies the ones it echoes,
+/// returning the connection to it if they match
+fn deliver_sensor_parameters(
+    sensor_driver_address: SocketAddr,
+    sensor_parameters: &SensorParameters,
+) -> Result<TcpStream, DeliveryStatus> {
+    let mut sensor_stream = TcpStream::connect(sensor_driver_address).map_err(|e| {
+        error!("Failed to connect to {sensor_driver_address}: {e}");
+        DeliveryStatus::Failed
+    })?;
+    write_sensor_parameters(sensor_parameters, &mut sensor_stream).map_err(|e| {
+        error!("Failed to send parameters to {sensor_driver_address}: {e}");
+        DeliveryStatus::Failed
+    })?;
+    match verify_sensor_parameters(sensor_parameters, &mut sensor_stream) {
+        DeliveryStatus::Delivered => Ok(sensor_stream),
+        status => Err(status),
     }
 }
 
@@ -212,6 +1044,7 @@ fn create_run_command(request_processing_model: RequestProcessingModel) -> Comma
 
 fn create_motor_monitor_parameters(
     motor_driver_parameters: &MotorDriverRunParameters,
+    run_directory: Option<&Path>,
 ) -> MotorMonitorParameters {
     MotorMonitorParameters {
         start_time: motor_driver_parameters.start_time,
@@ -225,7 +1058,95 @@ fn create_motor_monitor_parameters(
         sensor_sampling_interval: motor_driver_parameters.sensor_sampling_interval,
         window_sampling_interval: motor_driver_parameters.window_sampling_interval,
         thread_pool_size: motor_driver_parameters.thread_pool_size,
+        wear_model: motor_driver_parameters.wear_model,
+        use_cumulative_age: motor_driver_parameters.use_cumulative_age,
+        sensor_bind_port: motor_driver_parameters.sensor_bind_port,
+        log_sample_rate: motor_driver_parameters.log_sample_rate,
+        buffer_alerts: motor_driver_parameters.buffer_alerts,
+        compress_alerts: motor_driver_parameters.compress_alerts,
+        detailed_alerts: motor_driver_parameters.detailed_alerts,
+        strict_deserialization: motor_driver_parameters.strict_deserialization,
+        alert_webhooks: motor_driver_parameters.alert_webhooks.clone(),
+        routing: motor_driver_parameters.routing.clone(),
+        // Keeps the stdout of the motor monitor free for human-readable output
+        benchmark_output: BenchmarkOutput::Path(get_monitor_output_path(
+            run_directory,
+            "motor_monitor_benchmark_data",
+        )),
+        run_id: motor_driver_parameters.run_id,
+        compact_wire: motor_driver_parameters.compact_wire,
+        transport: motor_driver_parameters.transport,
+        forensic_alerts: motor_driver_parameters.forensic_alerts,
+        report_post: motor_driver_parameters.report_post,
+        memory_limit_mb: motor_driver_parameters.memory_limit_mb,
+        max_quarantine_rate: motor_driver_parameters.max_quarantine_rate,
+        window_slide_ms: motor_driver_parameters.window_slide_ms,
+        cpu_cores: motor_driver_parameters.cpu_cores.clone(),
+        window_allowed_delay_ms: motor_driver_parameters.window_allowed_delay_ms,
+        startup_barrier_ms: motor_driver_parameters.startup_barrier_ms,
+        averaging: motor_driver_parameters.averaging,
+        track_provenance: motor_driver_parameters.provenance_sample_interval > 0,
+        max_sensor_connections: motor_driver_parameters.max_sensor_connections,
+        reject_excess_connections: motor_driver_parameters.reject_excess_connections,
+        expect_sensor_order: motor_driver_parameters.expect_sensor_order,
+        interpolate_gaps: motor_driver_parameters.interpolate_gaps,
+        otel_endpoint: motor_driver_parameters.otel_endpoint.clone(),
+        rule_thresholds: motor_driver_parameters.rule_thresholds,
+        no_cloud: motor_driver_parameters.no_cloud,
+        window_export: motor_driver_parameters
+            .export_windows
+            .then(|| get_monitor_output_path(run_directory, "motor_monitor_windows")),
+        window_kind: match motor_driver_parameters.window_count {
+            Some(window_count) => WindowKind::Count(window_count),
+            None => WindowKind::TimeMs(motor_driver_parameters.window_size_ms),
+        },
+        crash_file: Some(get_monitor_output_path(
+            run_directory,
+            "motor_monitor_crash",
+        )),
+    }
+}
+
+/// Parameters of the monitor running side by side with the primary one,
+/// which listens for the sensors on the secondary sensor port
+fn create_secondary_motor_monitor_parameters(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    run_directory: Option<&Path>,
+    secondary_processing_model: RequestProcessingModel,
+    secondary_sensor_port: u16,
+) -> MotorMonitorParameters {
+    MotorMonitorParameters {
+        request_processing_model: secondary_processing_model,
+        sensor_listen_address: SocketAddr::new(
+            motor_monitor_parameters.sensor_listen_address.ip(),
+            secondary_sensor_port,
+        ),
+        sensor_bind_port: secondary_sensor_port,
+        benchmark_output: BenchmarkOutput::Path(get_monitor_output_path(
+            run_directory,
+            "secondary_motor_monitor_benchmark_data",
+        )),
+        crash_file: Some(get_monitor_output_path(
+            run_directory,
+            "secondary_motor_monitor_crash",
+        )),
+        window_export: motor_monitor_parameters
+            .window_export
+            .as_ref()
+            .map(|_| get_monitor_output_path(run_directory, "secondary_motor_monitor_windows")),
+        ..motor_monitor_parameters.clone()
+    }
+}
+
+/// Path of a file the motor monitor writes for the motor driver, in the run directory if the run
+/// has one, and otherwise in the temporary directory, named by the process id of the motor driver
+fn get_monitor_output_path(run_directory: Option<&Path>, file_name: &str) -> String {
+    match run_directory {
+        Some(run_directory) => run_directory.join(file_name),
+        None => env::temp_dir().join(format!("{file_name}_{}", process::id())),
     }
+    .display()
+    .to_string()
 }
 
 fn create_sensor_parameters(
@@ -240,13 +1161,541 @@ fn create_sensor_parameters(
         request_processing_model: motor_driver_parameters.request_processing_model,
         motor_monitor_listen_address,
         start_time: motor_driver_parameters.start_time,
+        warmup_samples: motor_driver_parameters.warmup_samples,
+        random_failure_prob: motor_driver_parameters.random_failure_prob,
+        compact_wire: motor_driver_parameters.compact_wire,
+        transport: motor_driver_parameters.transport,
+        run_id: motor_driver_parameters.run_id,
+        data_column: motor_driver_parameters.data_column,
+        startup_barrier_ms: motor_driver_parameters.startup_barrier_ms,
+        provenance_sample_interval: motor_driver_parameters.provenance_sample_interval,
+        connect_retry: motor_driver_parameters.sensor_connect_retry,
+        connect_jitter_ms: motor_driver_parameters.sensor_connect_jitter_ms,
+        reconnect_retry: motor_driver_parameters.sensor_reconnect_retry,
+        otel_endpoint: motor_driver_parameters.otel_endpoint.clone(),
+        secondary_motor_monitor_listen_address: motor_driver_parameters
+            .secondary_processing_model
+            .map(|_| {
+                SocketAddr::new(
+                    motor_driver_parameters.sensor_listen_address.ip(),
+                    motor_driver_parameters.secondary_sensor_port,
+                )
+            }),
+        synthetic_signal: motor_driver_parameters
+            .synthetic_signals
+            .iter()
+            .find(|signal| signal.sensor_kind == SensorKind::from_sensor_id(id))
+            .copied(),
+        // The test driver checked that the readings of the scheduled failures can be derived
+        reading_schedule: (!motor_driver_parameters.failure_schedule.is_empty()).then(|| {
+            utils::failure_schedule::get_reading_schedule(
+                id,
+                &motor_driver_parameters.failure_schedule,
+                &motor_driver_parameters.rule_thresholds,
+            )
+            .expect("Could not derive the readings of the scheduled failures")
+        }),
+    }
+}
+
+/// Waits for what the sensor driver returns once the sensor exited until shortly after the run
+fn set_sensor_exit_timeout(sensor_parameters: &SensorParameters, stream: &mut TcpStream) {
+    let end_time =
+        Duration::from_secs_f64(sensor_parameters.start_time + sensor_parameters.duration);
+    let timeout = end_time.saturating_sub(utils::get_now_duration()) + SENSOR_EXIT_TIMEOUT;
+    stream
+        .set_read_timeout(Some(timeout))
+        .expect("Could not set sensor exit read timeout");
+}
+
+/// Reads the bytes the sensor exchanged with its monitors and the builds of the sensor
+/// and its driver, which the sensor driver returns once the sensor exited
+fn read_sensor_report(
+    sensor_parameters: &SensorParameters,
+    stream: &mut TcpStream,
+) -> SensorReport {
+    utils::read_object::<SensorReport>(stream).unwrap_or_else(|e| {
+        warn!(
+            "Could not read network usage of sensor {}: {e}",
+            sensor_parameters.id
+        );
+        SensorReport::default()
+    })
+}
+
+/// Sums up the network usage of the sensors, keeping each of their builds once
+fn add_sensor_report(sensor_reports: &mut SensorReport, sensor_report: SensorReport) {
+    sensor_reports.network_usage = sensor_reports.network_usage + sensor_report.network_usage;
+    for build in sensor_report.builds {
+        if !sensor_reports.builds.contains(&build) {
+            sensor_reports.builds.push(build);
+        }
     }
 }
 
-fn write_sensor_parameters(sensor_parameters: &SensorParameters, stream: &mut TcpStream) {
+/// Reads the send log the sensor driver returns once the sensor exited
+fn read_send_log(
+    sensor_parameters: &SensorParameters,
+    stream: &mut TcpStream,
+) -> Vec<SampledMessageSend> {
+    utils::read_object_with_limit::<Vec<SampledMessageSend>>(stream, MAX_SEND_LOG_SIZE)
+        .unwrap_or_else(|e| {
+            warn!(
+                "Could not read send log of sensor {}: {e}",
+                sensor_parameters.id
+            );
+            vec![]
+        })
+}
+
+fn write_sensor_parameters(
+    sensor_parameters: &SensorParameters,
+    stream: &mut TcpStream,
+) -> io::Result<()> {
+    write_sensor_driver_request(
+        &SensorDriverRequest::Run(Box::new(sensor_parameters.clone())),
+        stream,
+    )
+}
+
+fn write_sensor_driver_request(
+    request: &SensorDriverRequest,
+    stream: &mut TcpStream,
+) -> io::Result<()> {
     let vec: Vec<u8> =
-        to_allocvec(&sensor_parameters).expect("Could not write sensor parameters to Vec<u8>");
+        utils::encode_object(request).expect("Could not write sensor driver request to Vec<u8>");
+    stream.write_all(&vec)
+}
+
+/// Reads the parameters echoed by the sensor driver and compares them to the sent ones
+fn verify_sensor_parameters(
+    sensor_parameters: &SensorParameters,
+    stream: &mut TcpStream,
+) -> DeliveryStatus {
     stream
-        .write_all(&vec)
-        .expect("Could not write sensor parameters bytes to TcpStream");
+        .set_read_timeout(Some(SENSOR_ECHO_TIMEOUT))
+        .expect("Could not set sensor echo read timeout");
+    let echoed_parameters = match utils::read_object::<SensorParameters>(stream) {
+        Ok(echoed_parameters) => echoed_parameters,
+        Err(e) => {
+            error!(
+                "Sensor {} did not echo its parameters: {e}",
+                sensor_parameters.id
+            );
+            return DeliveryStatus::Failed;
+        }
+    };
+    if *sensor_parameters == echoed_parameters {
+        DeliveryStatus::Delivered
+    } else {
+        error!(
+            "Sensor {} received mismatching {}: sent {sensor_parameters:?}, echoed {echoed_parameters:?}",
+            sensor_parameters.id,
+            get_mismatched_fields(sensor_parameters, &echoed_parameters).join(", ")
+        );
+        DeliveryStatus::Mismatched
+    }
+}
+
+/// Names of the fields which differ, to log which parameters got corrupted
+fn get_mismatched_fields(sent: &SensorParameters, echoed: &SensorParameters) -> Vec<&'static str> {
+    let mut mismatched_fields = vec![];
+    if sent.id != echoed.id {
+        mismatched_fields.push("id");
+    }
+    if sent.start_time != echoed.start_time {
+        mismatched_fields.push("start_time");
+    }
+    if sent.duration != echoed.duration {
+        mismatched_fields.push("duration");
+    }
+    if sent.sampling_interval != echoed.sampling_interval {
+        mismatched_fields.push("sampling_interval");
+    }
+    if sent.request_processing_model != echoed.request_processing_model {
+        mismatched_fields.push("request_processing_model");
+    }
+    if sent.motor_monitor_listen_address != echoed.motor_monitor_listen_address {
+        mismatched_fields.push("motor_monitor_listen_address");
+    }
+    if sent.warmup_samples != echoed.warmup_samples {
+        mismatched_fields.push("warmup_samples");
+    }
+    if sent.random_failure_prob != echoed.random_failure_prob {
+        mismatched_fields.push("random_failure_prob");
+    }
+    if sent.compact_wire != echoed.compact_wire {
+        mismatched_fields.push("compact_wire");
+    }
+    if sent.transport != echoed.transport {
+        mismatched_fields.push("transport");
+    }
+    if sent.run_id != echoed.run_id {
+        mismatched_fields.push("run_id");
+    }
+    if sent.data_column != echoed.data_column {
+        mismatched_fields.push("data_column");
+    }
+    if sent.startup_barrier_ms != echoed.startup_barrier_ms {
+        mismatched_fields.push("startup_barrier_ms");
+    }
+    if sent.provenance_sample_interval != echoed.provenance_sample_interval {
+        mismatched_fields.push("provenance_sample_interval");
+    }
+    if sent.connect_retry != echoed.connect_retry {
+        mismatched_fields.push("connect_retry");
+    }
+    if sent.connect_jitter_ms != echoed.connect_jitter_ms {
+        mismatched_fields.push("connect_jitter_ms");
+    }
+    if sent.reconnect_retry != echoed.reconnect_retry {
+        mismatched_fields.push("reconnect_retry");
+    }
+    if sent.otel_endpoint != echoed.otel_endpoint {
+        mismatched_fields.push("otel_endpoint");
+    }
+    if sent.secondary_motor_monitor_listen_address != echoed.secondary_motor_monitor_listen_address
+    {
+        mismatched_fields.push("secondary_motor_monitor_listen_address");
+    }
+    if sent.synthetic_signal != echoed.synthetic_signal {
+        mismatched_fields.push("synthetic_signal");
+    }
+    if sent.reading_schedule != echoed.reading_schedule {
+        mismatched_fields.push("reading_schedule");
+    }
+    mismatched_fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_transfer_objects::{DataColumn, RetryPolicy, ToolChange, WearModel};
+
+    fn get_test_monitor_parameters(duration: f64) -> MotorMonitorParameters {
+        let address: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        MotorMonitorParameters {
+            start_time: utils::get_now_secs(),
+            duration,
+            request_processing_model: RequestProcessingModel::ClientServer,
+            number_of_tcp_motor_groups: 1,
+            number_of_i2c_motor_groups: 0,
+            window_size_ms: 1000,
+            sensor_listen_address: address,
+            motor_monitor_listen_address: address,
+            sensor_sampling_interval: 100,
+            window_sampling_interval: 100,
+            thread_pool_size: 1,
+            wear_model: WearModel {
+                wear_rate: 1.0,
+                rotational_speed_threshold: 0.0,
+                tool_change: ToolChange::OnOverstrainFailure,
+            },
+            use_cumulative_age: false,
+            sensor_bind_port: 0,
+            log_sample_rate: 1,
+            buffer_alerts: false,
+            compress_alerts: false,
+            detailed_alerts: false,
+            strict_deserialization: false,
+            alert_webhooks: vec![],
+            routing: vec![],
+            benchmark_output: BenchmarkOutput::Stdout,
+            run_id: 1,
+            compact_wire: false,
+            transport: Transport::Tcp,
+            forensic_alerts: false,
+            crash_file: None,
+            report_post: false,
+            memory_limit_mb: None,
+            max_quarantine_rate: None,
+            window_slide_ms: None,
+            window_allowed_delay_ms: 0,
+            cpu_cores: vec![],
+            startup_barrier_ms: 0,
+            averaging: AveragingStrategy::Window,
+            track_provenance: false,
+            max_sensor_connections: None,
+            reject_excess_connections: false,
+            expect_sensor_order: false,
+            interpolate_gaps: false,
+            otel_endpoint: None,
+            rule_thresholds: RuleThresholds::default(),
+            no_cloud: true,
+            window_export: None,
+            window_kind: WindowKind::TimeMs(1000),
+        }
+    }
+
+    /// Stub monitor, which fails on its first execution and writes its benchmark data afterwards
+    fn get_flaky_monitor_command(marker: &Path) -> Command {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(r#"if [ -e "$0" ]; then printf benchmark_data; else touch "$0"; exit 1; fi"#)
+            .arg(marker)
+            .stdout(Stdio::piped());
+        command
+    }
+
+    #[test]
+    fn monitor_failing_once_is_restarted() {
+        let marker = env::temp_dir().join(format!("flaky_monitor_{}", process::id()));
+        let _ = fs::remove_file(&marker);
+        let motor_monitor_parameters = get_test_monitor_parameters(60.0);
+
+        let output = supervise_motor_monitor(
+            &mut get_flaky_monitor_command(&marker),
+            &motor_monitor_parameters,
+            1,
+            &Mutex::new(RunState::new()),
+            &RunAbort::default(),
+        );
+
+        let _ = fs::remove_file(&marker);
+        assert!(output.status.success());
+        assert_eq!(
+            read_benchmark_data(&motor_monitor_parameters.benchmark_output, output),
+            b"benchmark_data"
+        );
+    }
+
+    #[test]
+    fn monitor_is_not_restarted_without_restarts() {
+        let marker = env::temp_dir().join(format!("failing_monitor_{}", process::id()));
+        let _ = fs::remove_file(&marker);
+
+        let output = supervise_motor_monitor(
+            &mut get_flaky_monitor_command(&marker),
+            &get_test_monitor_parameters(60.0),
+            0,
+            &Mutex::new(RunState::new()),
+            &RunAbort::default(),
+        );
+
+        let _ = fs::remove_file(&marker);
+        assert!(!output.status.success());
+    }
+
+    fn get_test_sensor_parameters() -> SensorParameters {
+        SensorParameters {
+            id: 4,
+            start_time: utils::get_now_secs(),
+            duration: 60.0,
+            sampling_interval: 100,
+            request_processing_model: RequestProcessingModel::ClientServer,
+            motor_monitor_listen_address: "127.0.0.1:9000".parse().unwrap(),
+            warmup_samples: 0,
+            random_failure_prob: 0.0,
+            compact_wire: false,
+            secondary_motor_monitor_listen_address: None,
+            transport: Transport::Tcp,
+            run_id: 1,
+            data_column: DataColumn::Last,
+            startup_barrier_ms: 0,
+            provenance_sample_interval: 0,
+            connect_retry: RetryPolicy::fast_local(),
+            connect_jitter_ms: 0,
+            reconnect_retry: None,
+            otel_endpoint: None,
+            synthetic_signal: None,
+            reading_schedule: None,
+        }
+    }
+
+    /// Stub sensor driver, which echoes the parameters it receives as altered by `alter`
+    /// in frames of `chunk_size` bytes, or closes the connection if `alter` returns none
+    fn spawn_sensor_driver(
+        chunk_size: usize,
+        alter: impl FnOnce(SensorParameters) -> Option<SensorParameters> + Send + 'static,
+    ) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            stream.set_nodelay(true).unwrap();
+            let SensorDriverRequest::Run(sensor_parameters) =
+                utils::read_object::<SensorDriverRequest>(&mut stream).unwrap()
+            else {
+                panic!("Expected the parameters of a run");
+            };
+            if let Some(echoed_parameters) = alter(*sensor_parameters) {
+                let frame = utils::encode_object(&echoed_parameters).unwrap();
+                for chunk in frame.chunks(chunk_size) {
+                    stream.write_all(chunk).unwrap();
+                    thread::sleep(Duration::from_millis(5));
+                }
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn parameters_echoed_in_split_frame_are_delivered() {
+        let sensor_parameters = get_test_sensor_parameters();
+        let sensor_driver_address = spawn_sensor_driver(7, Some);
+
+        assert!(deliver_sensor_parameters(sensor_driver_address, &sensor_parameters).is_ok());
+    }
+
+    #[test]
+    fn corrupted_parameters_fail_delivery() {
+        let sensor_parameters = get_test_sensor_parameters();
+        let sensor_driver_address = spawn_sensor_driver(7, |mut sensor_parameters| {
+            sensor_parameters.sampling_interval += 1;
+            Some(sensor_parameters)
+        });
+
+        assert_eq!(
+            deliver_sensor_parameters(sensor_driver_address, &sensor_parameters).err(),
+            Some(DeliveryStatus::Mismatched)
+        );
+    }
+
+    #[test]
+    fn missing_echo_fails_delivery() {
+        let sensor_parameters = get_test_sensor_parameters();
+        let sensor_driver_address = spawn_sensor_driver(7, |_| None);
+
+        assert_eq!(
+            deliver_sensor_parameters(sensor_driver_address, &sensor_parameters).err(),
+            Some(DeliveryStatus::Failed)
+        );
+    }
+
+    #[test]
+    fn undelivered_and_unreported_sensors_abort_setup() {
+        let (delivery_sender, delivery_receiver) = mpsc::channel();
+        delivery_sender
+            .send((4, DeliveryStatus::Delivered))
+            .unwrap();
+        delivery_sender
+            .send((5, DeliveryStatus::Mismatched))
+            .unwrap();
+        drop(delivery_sender);
+
+        assert_eq!(
+            await_sensor_deliveries(&delivery_receiver, vec![4, 5, 6]),
+            vec![5, 6]
+        );
+    }
+
+    fn get_free_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn monitor_binds_other_port_than_it_advertises() {
+        let mut motor_monitor_parameters = get_test_monitor_parameters(60.0);
+        let advertised_port = get_free_port();
+        motor_monitor_parameters.sensor_listen_address =
+            SocketAddr::from(([127, 0, 0, 1], advertised_port));
+        motor_monitor_parameters.sensor_bind_port = get_free_port();
+
+        let listener = utils::transport::SensorListener::bind(&motor_monitor_parameters).unwrap();
+
+        assert!(
+            TcpStream::connect(("127.0.0.1", motor_monitor_parameters.sensor_bind_port)).is_ok()
+        );
+        assert!(listener.accept().is_ok());
+        assert!(TcpStream::connect(("127.0.0.1", advertised_port)).is_err());
+    }
+
+    #[test]
+    fn hanging_sensor_control_task_does_not_wedge_the_run() {
+        // Sensor driver which accepts the connection, but never answers
+        let unresponsive_sensor_driver = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sensor_driver_address = unresponsive_sensor_driver.local_addr().unwrap();
+        let finished_sensors = Arc::new(Mutex::new(vec![]));
+        let pool = ThreadPool::new(2);
+        pool.execute(move || {
+            let mut stream = TcpStream::connect(sensor_driver_address).unwrap();
+            let _ = io::Read::read(&mut stream, &mut [0; 1]);
+        });
+        let finished_sensor = finished_sensors.clone();
+        pool.execute(move || finished_sensor.lock().unwrap().push(1));
+        let timeout = Duration::from_millis(200);
+        let start = Instant::now();
+        assert!(!join_sensor_tasks(&pool, timeout));
+        let elapsed = start.elapsed();
+        assert!(elapsed >= timeout);
+        assert!(elapsed < timeout + Duration::from_secs(1));
+        assert_eq!(pool.active_count(), 1);
+        assert_eq!(*finished_sensors.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn finished_sensor_control_tasks_are_joined() {
+        let pool = ThreadPool::new(2);
+        for _ in 0..2 {
+            pool.execute(|| thread::sleep(Duration::from_millis(10)));
+        }
+        assert!(join_sensor_tasks(&pool, Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn status_endpoint_reports_the_transitions_of_the_run() {
+        let run_state = Arc::new(Mutex::new(RunState::new()));
+        let status_address = serve_run_state("127.0.0.1:0".parse().unwrap(), run_state.clone());
+        let query_run_state = || {
+            let mut status_stream = TcpStream::connect(status_address).unwrap();
+            utils::read_object::<RunState>(&mut status_stream).unwrap()
+        };
+        assert_eq!(query_run_state(), RunState::new());
+
+        set_run_phase(&run_state, RunPhase::AwaitingParameters);
+        assert_eq!(query_run_state().phase, RunPhase::AwaitingParameters);
+
+        let sensor_address = "127.0.0.1:9000".parse().unwrap();
+        begin_sensor_provisioning(
+            &run_state,
+            (0..2)
+                .map(|sensor_id| SensorDelivery {
+                    sensor_id,
+                    address: sensor_address,
+                    status: DeliveryStatus::Pending,
+                })
+                .collect(),
+        );
+        set_sensor_delivery_status(&run_state, 1, DeliveryStatus::Failed);
+        // Sensors which are not part of the run are ignored
+        set_sensor_delivery_status(&run_state, 7, DeliveryStatus::Delivered);
+        let provisioning_state = query_run_state();
+        assert_eq!(provisioning_state.phase, RunPhase::ProvisioningSensors);
+        assert_eq!(
+            provisioning_state
+                .sensors
+                .iter()
+                .map(|sensor| (sensor.sensor_id, sensor.status))
+                .collect::<Vec<_>>(),
+            vec![(0, DeliveryStatus::Pending), (1, DeliveryStatus::Failed)]
+        );
+
+        set_run_phase(&run_state, RunPhase::MonitorRunning { pid: 42 });
+        let monitor_state = query_run_state();
+        assert_eq!(monitor_state.phase, RunPhase::MonitorRunning { pid: 42 });
+        // The deliveries stay reported while the monitor is running
+        assert_eq!(monitor_state.sensors, provisioning_state.sensors);
+
+        set_run_phase(&run_state, RunPhase::CollectingOutput);
+        assert_eq!(query_run_state().phase, RunPhase::CollectingOutput);
+        set_run_phase(&run_state, RunPhase::Idle);
+        assert_eq!(query_run_state().phase, RunPhase::Idle);
+    }
+
+    #[test]
+    fn config_without_the_optional_settings_is_accepted() {
+        let motor_driver_parameters: MotorDriverParameters =
+            toml::from_str(r#"test_driver_listen_address = "0.0.0.0:8001""#).unwrap();
+        assert_eq!(motor_driver_parameters.status_listen_address, None);
+        assert_eq!(motor_driver_parameters.max_monitor_restarts, 0);
+        assert_eq!(
+            motor_driver_parameters.sensor_join_timeout_secs,
+            get_default_sensor_join_timeout_secs()
+        );
+    }
 }
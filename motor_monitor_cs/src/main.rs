@@ -1,50 +1,174 @@
 use crate::motor_sensor_group_buffers::MotorGroupSensorsBuffers;
-use crate::sliding_window::SlidingWindow;
+use crate::sliding_window::{EvictionStrategy, SlidingWindow};
+use crate::snapshot::Snapshotter;
 use data_transfer_objects::{
-    Alert, BenchmarkDataType, MotorFailure, MotorMonitorParameters, SensorMessage,
+    Alert, BenchmarkDataType, ChannelFingerprint, DetailedAlert, HotPath, MotorFailure,
+    MotorMonitorParameters, RouteDestination, SampledMessageId, SensorMessage, WindowKind,
 };
 use env_logger::Target;
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
 use futures::future::RemoteHandle;
-use log::{debug, error, info};
-use postcard::to_allocvec_cobs;
-#[cfg(feature = "rpi")]
-use rppal::i2c::I2c;
+#[cfg(any(feature = "rpi", feature = "sim_i2c", test))]
+use i2c_bus::I2cBus;
+use log::{debug, error, info, log_enabled, warn, Level};
 use scheduler::Scheduler;
-use std::io::Write;
-#[cfg(feature = "rpi")]
+use std::collections::HashMap;
+use std::io::{ErrorKind, Write};
+#[cfg(any(feature = "rpi", feature = "sim_i2c", test))]
 use std::mem::size_of;
-use std::net::{TcpListener, TcpStream};
-#[cfg(feature = "rpi")]
+#[cfg(any(feature = "rpi", feature = "sim_i2c", test))]
 use std::ops::Shl;
 use std::ops::{BitAnd, Shr};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::time::Duration;
+use std::{io, thread};
+use utils::benchmark::RunStatistics;
+use utils::cloud_connection::AlertCodec;
+use utils::crash::EarlyExit;
+use utils::monitor_run::MonitorRun;
+use utils::network_usage::{CloudServerStream, CountingStream};
+use utils::profiling;
+use utils::quarantine::ReadingQuarantine;
+use utils::telemetry::TelemetrySpan;
+use utils::transport::{SensorListener, SensorStream};
+use utils::webhook::AlertWebhooks;
+use utils::window_diagnostics;
+use utils::{LogSampler, ReadError};
 
 mod motor_sensor_group_buffers;
+#[cfg(feature = "rpi")]
+mod rpi_i2c;
 mod rules_engine;
+mod sensor_buffer;
+#[cfg(feature = "sim_i2c")]
+mod sim_i2c;
 mod sliding_window;
+mod snapshot;
+
+#[derive(Debug, Copy, Clone)]
+enum SensorStatus {
+    Alive(u32),
+    Dead(u32),
+}
+
+/// Options of the consumer evaluating the windows, which only the client server monitor takes
+struct ConsumerOptions {
+    snapshot_path: Option<PathBuf>,
+    batch_recv: bool,
+    eviction_strategy: EvictionStrategy,
+}
+
+/// Connections and webhooks the alerts of the monitor are sent to
+struct AlertSink {
+    cloud_server: CloudServerStream,
+    alert_codec: AlertCodec,
+    alert_webhooks: AlertWebhooks,
+}
 
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::builder().target(Target::Stderr).init();
     let arguments: Vec<String> = std::env::args().collect();
     let motor_monitor_parameters: MotorMonitorParameters =
-        utils::get_motor_monitor_parameters(&arguments);
-    execute_client_server_procedure(&motor_monitor_parameters);
+        utils::arguments::get_motor_monitor_parameters(&arguments);
+    utils::resolved_config::log_resolved_config("motor_monitor_cs", &motor_monitor_parameters);
+    let run_statistics = RunStatistics::new(&motor_monitor_parameters);
+    let early_exit = EarlyExit::new(&motor_monitor_parameters, &run_statistics);
+    utils::crash::install_crash_handler(&motor_monitor_parameters, early_exit.clone());
+    utils::affinity::pin_to_cpu_cores(&motor_monitor_parameters);
+    utils::watchdog::start_memory_watchdog(&motor_monitor_parameters, early_exit);
+    let monitor_run = MonitorRun::new(
+        "motor_monitor_cs",
+        &motor_monitor_parameters,
+        run_statistics,
+    );
+    let consumer_options = ConsumerOptions {
+        snapshot_path: get_snapshot_path(&arguments),
+        batch_recv: arguments.iter().any(|argument| argument == "--batch-recv"),
+        eviction_strategy: get_eviction_strategy(&arguments),
+    };
+    let accept_reconnects = arguments
+        .iter()
+        .any(|argument| argument == "--accept-reconnects");
+    execute_client_server_procedure(
+        &motor_monitor_parameters,
+        &monitor_run,
+        consumer_options,
+        accept_reconnects,
+    );
+    monitor_run.telemetry.shutdown();
 }
 
-fn execute_client_server_procedure(motor_monitor_parameters: &MotorMonitorParameters) {
+/// Reads the optional `--eviction-strategy <message-time|high-watermark>` argument,
+/// evicting relative to the high watermark if it is absent
+fn get_eviction_strategy(arguments: &[String]) -> EvictionStrategy {
+    arguments
+        .iter()
+        .position(|argument| argument == "--eviction-strategy")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a strategy after --eviction-strategy")
+                .parse()
+                .expect("Could not parse eviction_strategy successfully")
+        })
+        .unwrap_or(EvictionStrategy::HighWatermark)
+}
+
+fn get_snapshot_path(arguments: &[String]) -> Option<PathBuf> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--snapshot-path")
+        .map(|index| {
+            PathBuf::from(
+                arguments
+                    .get(index + 1)
+                    .expect("Did not receive a path after --snapshot-path"),
+            )
+        })
+}
+
+fn execute_client_server_procedure(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    monitor_run: &MonitorRun,
+    consumer_options: ConsumerOptions,
+    accept_reconnects: bool,
+) {
     let (tx, rx) = channel();
+    let (status_tx, status_rx) = channel();
     let pool = ThreadPoolBuilder::new()
         .pool_size(motor_monitor_parameters.thread_pool_size)
         .create()
         .unwrap();
-    let mut handle_list = handle_sensors(*motor_monitor_parameters, tx, &pool);
+    // The consumer is started first, as accepting the sensors blocks while the connection cap
+    // queues some of them, and the readings of the ones accepted meanwhile are evaluated
+    let consumer_handle = handle_consumer(
+        rx,
+        status_rx,
+        motor_monitor_parameters,
+        monitor_run,
+        consumer_options,
+        &pool,
+    );
+    let mut handle_list = handle_sensors(
+        motor_monitor_parameters.clone(),
+        monitor_run,
+        tx,
+        status_tx,
+        accept_reconnects,
+        &pool,
+    );
     info!("Setup complete");
-    handle_list.push(handle_consumer(rx, motor_monitor_parameters, &pool));
+    handle_list.push(consumer_handle);
     wait_on_complete(handle_list);
     info!("Processing completed");
-    utils::save_benchmark_readings(0, BenchmarkDataType::MotorMonitor);
+    utils::benchmark::save_benchmark_readings(
+        0,
+        BenchmarkDataType::MotorMonitor,
+        &motor_monitor_parameters.benchmark_output,
+        &monitor_run.run_statistics,
+    );
     info!("Saved benchmark readings");
 }
 
@@ -56,29 +180,54 @@ fn wait_on_complete(handle_list: Vec<RemoteHandle<()>>) {
 
 fn handle_sensors(
     args: MotorMonitorParameters,
+    monitor_run: &MonitorRun,
     tx: Sender<SensorMessage>,
+    status_tx: Sender<SensorStatus>,
+    accept_reconnects: bool,
     pool: &ThreadPool,
 ) -> Vec<RemoteHandle<()>> {
-    setup_tcp_sensor_handlers(&args, tx.clone(), pool)
+    #[allow(unused_mut)]
+    let mut handle_list = setup_tcp_sensor_handlers(
+        &args,
+        monitor_run,
+        tx.clone(),
+        status_tx,
+        accept_reconnects,
+        pool,
+    );
+    #[cfg(feature = "rpi")]
+    handle_list.push(setup_i2c_sensor_handlers(
+        &args,
+        rpi_i2c::RpiI2c::new(),
+        tx,
+        pool,
+    ));
+    #[cfg(all(feature = "sim_i2c", not(feature = "rpi")))]
+    handle_list.push(setup_i2c_sensor_handlers(
+        &args,
+        sim_i2c::create_loopback_bus(&args),
+        tx,
+        pool,
+    ));
+    handle_list
 }
 
 fn setup_tcp_sensor_handlers(
     motor_monitor_parameters: &MotorMonitorParameters,
+    monitor_run: &MonitorRun,
     tx: Sender<SensorMessage>,
+    status_tx: Sender<SensorStatus>,
+    accept_reconnects: bool,
     pool: &ThreadPool,
 ) -> Vec<RemoteHandle<()>> {
     info!(
         "Listening on 0.0.0.0:{}",
-        motor_monitor_parameters.sensor_listen_address.port()
+        motor_monitor_parameters.sensor_bind_port
     );
-    let listener = TcpListener::bind(format!(
-        "0.0.0.0:{}",
-        motor_monitor_parameters.sensor_listen_address.port()
-    ))
-    .unwrap_or_else(|e| {
+    let listener = SensorListener::bind(motor_monitor_parameters).unwrap_or_else(|e| {
         panic!(
-            "Could not bind sensor data listener to {}: {e}",
-            motor_monitor_parameters.sensor_listen_address
+            "Could not bind sensor data listener to 0.0.0.0:{}: {e}",
+            motor_monitor_parameters.sensor_bind_port
         )
     });
     info!(
@@ -89,43 +238,156 @@ fn setup_tcp_sensor_handlers(
         + motor_monitor_parameters.number_of_i2c_motor_groups as usize;
     let total_number_of_sensors = total_number_of_motors * 4;
     let mut handle_list = vec![];
-    for _ in 0..total_number_of_sensors {
-        let tx = tx.clone();
-        let stream = listener.accept();
-        let handle = pool.schedule(move || {
-            match stream {
-                Ok((mut stream, _)) => {
-                    stream
-                        .set_read_timeout(Some(Duration::from_secs(5)))
-                        .expect("Could not set read timeout");
-                    while let Some(sensor_message) =
-                        utils::read_object::<SensorMessage>(&mut stream)
-                    {
-                        handle_sensor_message(sensor_message, &tx);
+    for slot in 0..total_number_of_sensors {
+        handle_list.push(schedule_sensor_handler(
+            listener.accept(),
+            motor_monitor_parameters
+                .expect_sensor_order
+                .then_some(slot as u32),
+            motor_monitor_parameters,
+            monitor_run,
+            tx.clone(),
+            status_tx.clone(),
+            pool,
+        ));
+    }
+    if accept_reconnects {
+        accept_reconnecting_sensors(
+            listener,
+            motor_monitor_parameters.clone(),
+            monitor_run.clone(),
+            tx,
+            status_tx,
+            pool.clone(),
+        );
+    }
+    handle_list
+}
+
+/// Keeps accepting the connections of sensors reconnecting after a dropped connection until the
+/// end of the run, handling them like the initial ones. The acceptor holds on to the channels
+/// until all of its handlers completed, so the consumer also awaits the reconnected sensors.
+fn accept_reconnecting_sensors(
+    listener: SensorListener,
+    motor_monitor_parameters: MotorMonitorParameters,
+    monitor_run: MonitorRun,
+    tx: Sender<SensorMessage>,
+    status_tx: Sender<SensorStatus>,
+    pool: ThreadPool,
+) {
+    let end_time = get_end_time(&motor_monitor_parameters);
+    thread::spawn(move || {
+        let mut handle_list = vec![];
+        while let Some(stream) = listener.accept_before(end_time).transpose() {
+            info!("Accepted a reconnecting sensor");
+            // A reconnecting sensor takes no place in the order of the initial ones
+            handle_list.push(schedule_sensor_handler(
+                stream,
+                None,
+                &motor_monitor_parameters,
+                &monitor_run,
+                tx.clone(),
+                status_tx.clone(),
+                &pool,
+            ));
+        }
+        wait_on_complete(handle_list);
+    });
+}
+
+/// End of the run, after which the connections of the sensors are expected to close
+fn get_end_time(motor_monitor_parameters: &MotorMonitorParameters) -> Duration {
+    Duration::from_secs_f64(motor_monitor_parameters.start_time)
+        + Duration::from_secs_f64(motor_monitor_parameters.duration)
+}
+
+fn schedule_sensor_handler(
+    stream: io::Result<SensorStream>,
+    expected_sensor_id: Option<u32>,
+    motor_monitor_parameters: &MotorMonitorParameters,
+    monitor_run: &MonitorRun,
+    tx: Sender<SensorMessage>,
+    status_tx: Sender<SensorStatus>,
+    pool: &ThreadPool,
+) -> RemoteHandle<()> {
+    let end_time = get_end_time(motor_monitor_parameters);
+    let sensor_bytes = monitor_run.run_statistics.sensor_bytes.clone();
+    let telemetry = monitor_run.telemetry.clone();
+    let mut log_sampler = LogSampler::new(motor_monitor_parameters.log_sample_rate);
+    let mut reading_quarantine = ReadingQuarantine::new(motor_monitor_parameters);
+    let strict_deserialization = motor_monitor_parameters.strict_deserialization;
+    let compact_wire = motor_monitor_parameters.compact_wire;
+    pool.schedule(move || {
+        match stream {
+            Ok(stream) => {
+                stream
+                    .set_read_timeout(Some(Duration::from_secs(5)))
+                    .expect("Could not set read timeout");
+                let mut stream = CountingStream::new(stream, sensor_bytes);
+                let mut sensor_id = None;
+                loop {
+                    let sensor_message = match utils::read_sensor_message(
+                        &mut stream,
+                        strict_deserialization,
+                        compact_wire,
+                    ) {
+                        Ok(sensor_message) => sensor_message,
+                        // Exits through the crash handler, which reports the malformed message
+                        Err(ReadError::Malformed) => panic!("{}", ReadError::Malformed),
+                        Err(_) => break,
+                    };
+                    if sensor_id.is_none() {
+                        if !utils::is_expected_sensor(expected_sensor_id, sensor_message.sensor_id)
+                        {
+                            break;
+                        }
+                        sensor_id = Some(sensor_message.sensor_id);
+                        report_sensor_status(
+                            SensorStatus::Alive(sensor_message.sensor_id),
+                            &status_tx,
+                        );
+                    }
+                    let _span = telemetry.start_span("monitor_ingest", || {
+                        vec![
+                            ("sensor_id", sensor_message.sensor_id.into()),
+                            ("motor_id", (sensor_message.sensor_id >> 2).into()),
+                            ("sensor_timestamp", sensor_message.timestamp.into()),
+                        ]
+                    });
+                    if reading_quarantine.admit(&sensor_message) {
+                        handle_sensor_message(sensor_message, &tx, &mut log_sampler);
                     }
                 }
-                Err(e) => {
-                    error!("Error: {e}");
-                    /* connection failed */
+                reading_quarantine.log_counts();
+                if let Some(sensor_id) = sensor_id {
+                    if utils::get_now_duration() < end_time {
+                        report_sensor_status(SensorStatus::Dead(sensor_id), &status_tx);
+                    }
                 }
             }
-        });
-        handle_list.push(handle);
-    }
-    handle_list
+            // A connection beyond the connection cap is rejected, not the listener failing
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => warn!("{e}"),
+            Err(e) => {
+                error!("Error: {e}");
+                /* connection failed */
+            }
+        }
+    })
 }
 
-#[cfg(feature = "rpi")]
-fn setup_i2c_sensor_handlers(
+#[cfg(any(feature = "rpi", feature = "sim_i2c", test))]
+fn setup_i2c_sensor_handlers<B: I2cBus + Send + 'static>(
     args: &MotorMonitorParameters,
+    mut i2c: B,
     tx: Sender<SensorMessage>,
     pool: &ThreadPool,
 ) -> RemoteHandle<()> {
-    let mut i2c = I2c::new().expect("Could not instantiate i2c object");
     let number_of_motor_groups = args.number_of_i2c_motor_groups;
+    let end_time = get_end_time(args);
     pool.schedule(move || {
-        let mut data = [0u8; size_of::<SensorMessage>()];
-        loop {
+        // Leave room for the COBS overhead and the frame delimiter
+        let mut data = [0u8; size_of::<SensorMessage>() + 2];
+        while utils::get_now_duration() < end_time {
             for motor_id in 0..number_of_motor_groups {
                 for sensor_no in 0..4u8 {
                     let sensor_id: u8 = (motor_id).shl(2) + sensor_no;
@@ -145,67 +407,319 @@ fn setup_i2c_sensor_handlers(
     })
 }
 
-fn handle_sensor_message(message: SensorMessage, tx: &Sender<SensorMessage>) {
-    debug!("{message:?}");
+fn handle_sensor_message(
+    message: SensorMessage,
+    tx: &Sender<SensorMessage>,
+    log_sampler: &mut LogSampler,
+) {
+    if log_enabled!(Level::Debug) && log_sampler.sample() {
+        debug!("{message:?}");
+    }
     tx.send(message)
         .expect("Could not send sensor message to handler");
 }
 
+fn report_sensor_status(status: SensorStatus, status_tx: &Sender<SensorStatus>) {
+    debug!("{status:?}");
+    status_tx
+        .send(status)
+        .expect("Could not send sensor status to handler");
+}
+
 fn handle_consumer(
     rx: Receiver<SensorMessage>,
+    status_rx: Receiver<SensorStatus>,
     motor_monitor_parameters: &MotorMonitorParameters,
+    monitor_run: &MonitorRun,
+    consumer_options: ConsumerOptions,
     pool: &ThreadPool,
 ) -> RemoteHandle<()> {
-    let mut cloud_server =
-        TcpStream::connect(motor_monitor_parameters.motor_monitor_listen_address)
-            .expect("Could not open connection to cloud server");
-    info!(
-        "Connected to {}",
-        motor_monitor_parameters.motor_monitor_listen_address
+    let (cloud_server, alert_codec) = utils::cloud_connection::connect_to_cloud_server(
+        motor_monitor_parameters,
+        monitor_run.run_statistics.cloud_server_bytes.clone(),
     );
-    let motor_monitor_parameters = *motor_monitor_parameters;
+    if !motor_monitor_parameters.no_cloud {
+        info!(
+            "Connected to {}",
+            motor_monitor_parameters.motor_monitor_listen_address
+        );
+    }
+    let mut alert_sink = AlertSink {
+        cloud_server,
+        alert_codec,
+        alert_webhooks: AlertWebhooks::start(
+            &motor_monitor_parameters.alert_webhooks,
+            motor_monitor_parameters.detailed_alerts,
+        ),
+    };
+    let motor_monitor_parameters = motor_monitor_parameters.clone();
+    let monitor_run = monitor_run.clone();
+    let ConsumerOptions {
+        snapshot_path,
+        batch_recv,
+        eviction_strategy,
+    } = consumer_options;
     pool.schedule(move || {
         let total_motors = motor_monitor_parameters.number_of_tcp_motor_groups
             + motor_monitor_parameters.number_of_i2c_motor_groups as usize;
         let mut buffers: Vec<MotorGroupSensorsBuffers> = Vec::with_capacity(total_motors);
         for _ in 0..total_motors {
-            buffers.push(MotorGroupSensorsBuffers::new(Duration::from_millis(
-                motor_monitor_parameters.window_size_ms
-                    / motor_monitor_parameters.sensor_sampling_interval as u64,
-            )))
+            buffers.push(MotorGroupSensorsBuffers::new(
+                Duration::from_millis(
+                    motor_monitor_parameters.window_size_ms
+                        / motor_monitor_parameters.sensor_sampling_interval as u64,
+                ),
+                motor_monitor_parameters.wear_model,
+                eviction_strategy,
+                motor_monitor_parameters.averaging,
+                motor_monitor_parameters.interpolate_gaps.then(|| {
+                    Duration::from_millis(motor_monitor_parameters.sensor_sampling_interval as u64)
+                }),
+                match motor_monitor_parameters.window_kind {
+                    WindowKind::TimeMs(_) => None,
+                    WindowKind::Count(window_count) => Some(window_count),
+                },
+            ))
+        }
+        let mut snapshotter = snapshot_path.map(|snapshot_path| {
+            Snapshotter::new(
+                snapshot_path,
+                Duration::from_millis(motor_monitor_parameters.window_sampling_interval as u64),
+            )
+        });
+        if let Some(snapshotter) = &snapshotter {
+            snapshotter.restore(
+                &mut buffers,
+                Duration::from_millis(motor_monitor_parameters.window_size_ms),
+            );
+        }
+        let mut sensor_handlers = HashMap::new();
+        // Without messages, the statuses are still drained once per window sampling interval,
+        // so a sensor whose handler died is marked stale even if no other sensor sends anymore
+        let status_interval =
+            Duration::from_millis(motor_monitor_parameters.window_sampling_interval as u64);
+        loop {
+            let message = match rx.recv_timeout(status_interval) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    update_sensor_statuses(&mut buffers, &status_rx, &mut sensor_handlers);
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            update_sensor_statuses(&mut buffers, &status_rx, &mut sensor_handlers);
+            let mut batch = vec![message];
+            // Drain all messages which are already available, so the rules are evaluated once per batch
+            if batch_recv {
+                batch.extend(rx.try_iter());
+            }
+            handle_messages(
+                &mut buffers,
+                batch,
+                &mut alert_sink,
+                &monitor_run,
+                &motor_monitor_parameters,
+            );
+            if let Some(snapshotter) = &mut snapshotter {
+                snapshotter.snapshot_if_due(&buffers);
+            }
         }
-        while let Ok(message) = rx.recv() {
-            handle_message(&mut buffers, message, &mut cloud_server);
+        if let Some(snapshotter) = &snapshotter {
+            snapshotter.log_cost();
+        }
+        let out_of_order_messages: u64 = buffers
+            .iter()
+            .map(|motor_group_buffers| motor_group_buffers.get_out_of_order_messages())
+            .sum();
+        if out_of_order_messages > 0 {
+            warn!("Received {out_of_order_messages} out-of-order sensor messages");
         }
     })
 }
 
-fn handle_message(
+/// Marks a sensor as stale once none of its handlers is connected anymore, and as live again
+/// once a handler of a reconnected sensor receives its readings.
+/// The handlers are counted, as the handler of a reconnected sensor may report it alive
+/// before the handler of the dropped connection reports it dead.
+fn update_sensor_statuses(
     buffers: &mut [MotorGroupSensorsBuffers],
-    message: SensorMessage,
-    cloud_server: &mut TcpStream,
+    status_rx: &Receiver<SensorStatus>,
+    sensor_handlers: &mut HashMap<u32, usize>,
+) {
+    while let Ok(status) = status_rx.try_recv() {
+        match status {
+            SensorStatus::Alive(sensor_id) => {
+                debug!("Sensor {sensor_id} is alive");
+                let handlers = sensor_handlers.entry(sensor_id).or_default();
+                *handlers += 1;
+                if *handlers == 1 {
+                    get_motor_group_buffers(buffers, sensor_id.shr(2))
+                        .mark_sensor_live(sensor_id.bitand(0x0003) as usize);
+                }
+            }
+            SensorStatus::Dead(sensor_id) => {
+                let handlers = sensor_handlers.entry(sensor_id).or_default();
+                *handlers = handlers.saturating_sub(1);
+                if *handlers == 0 {
+                    warn!("Handler of sensor {sensor_id} exited unexpectedly, marking it as stale");
+                    get_motor_group_buffers(buffers, sensor_id.shr(2))
+                        .mark_sensor_stale(sensor_id.bitand(0x0003) as usize);
+                }
+            }
+        }
+    }
+}
+
+/// Adds the messages to the buffers of their motor groups, and evaluates the rules once
+/// for every motor group which received a message, at the time of its latest message
+fn handle_messages(
+    buffers: &mut [MotorGroupSensorsBuffers],
+    messages: Vec<SensorMessage>,
+    alert_sink: &mut AlertSink,
+    monitor_run: &MonitorRun,
+    motor_monitor_parameters: &MotorMonitorParameters,
 ) {
-    let motor_group_id: u32 = message.sensor_id.shr(2);
-    let sensor_id = message.sensor_id.bitand(0x0003);
+    for (motor_group_id, timestamp) in add_messages(buffers, messages) {
+        evaluate_motor_group(
+            buffers,
+            motor_group_id,
+            timestamp,
+            alert_sink,
+            monitor_run,
+            motor_monitor_parameters,
+        );
+    }
+}
+
+/// Adds the messages to the buffers of their motor groups, returning the motor groups which
+/// received a message with the time of their latest one
+fn add_messages(
+    buffers: &mut [MotorGroupSensorsBuffers],
+    messages: Vec<SensorMessage>,
+) -> Vec<(u32, f64)> {
+    let mut updated_motor_groups: Vec<(u32, f64)> = vec![];
+    for message in messages {
+        let motor_group_id: u32 = message.sensor_id.shr(2);
+        let sensor_id = message.sensor_id.bitand(0x0003);
+        profiling::measure(HotPath::Window, || {
+            add_message_to_sensor_buffer(
+                message,
+                sensor_id,
+                get_motor_group_buffers(buffers, motor_group_id),
+            )
+        });
+        match updated_motor_groups
+            .iter_mut()
+            .find(|(updated_motor_group_id, _)| *updated_motor_group_id == motor_group_id)
+        {
+            Some((_, timestamp)) => *timestamp = f64::max(*timestamp, message.timestamp),
+            None => updated_motor_groups.push((motor_group_id, message.timestamp)),
+        }
+    }
+    updated_motor_groups
+}
+
+fn evaluate_motor_group(
+    buffers: &mut [MotorGroupSensorsBuffers],
+    motor_group_id: u32,
+    timestamp: f64,
+    alert_sink: &mut AlertSink,
+    monitor_run: &MonitorRun,
+    motor_monitor_parameters: &MotorMonitorParameters,
+) {
+    let span = monitor_run.telemetry.start_span("window_evaluation", || {
+        vec![
+            ("motor_id", motor_group_id.into()),
+            ("window_end", timestamp.into()),
+        ]
+    });
     let motor_group_buffers = get_motor_group_buffers(buffers, motor_group_id);
-    add_message_to_sensor_buffer(message, sensor_id, motor_group_buffers);
-    motor_group_buffers.refresh_caches(Duration::from_secs_f64(message.timestamp));
-    if motor_group_buffers.is_some() {
-        let rule_violated = rules_engine::violated_rule(motor_group_buffers);
+    profiling::measure(HotPath::Window, || {
+        motor_group_buffers.refresh_caches(Duration::from_secs_f64(timestamp))
+    });
+    if motor_group_buffers.is_some() && !motor_group_buffers.is_stale() {
+        if window_diagnostics::is_enabled() {
+            motor_group_buffers.log_windows(motor_group_id);
+        }
+        let rule_violated = profiling::measure(HotPath::Rules, || {
+            rules_engine::violated_rule(
+                motor_group_buffers,
+                motor_monitor_parameters
+                    .use_cumulative_age
+                    .then(|| Duration::from_secs_f64(motor_monitor_parameters.start_time)),
+                &motor_monitor_parameters.rule_thresholds,
+            )
+        });
+        if monitor_run.window_export.is_enabled() {
+            motor_group_buffers.export_window(motor_group_id, &monitor_run.window_export);
+        }
+        let time = motor_group_buffers.get_time();
+        if let Some(recovery_alert) =
+            motor_group_buffers
+                .recovery
+                .update(motor_group_id as u16, rule_violated, time)
+        {
+            info!(
+                "Motor {motor_group_id} recovered from {}",
+                recovery_alert.failure
+            );
+            send_alert(&recovery_alert.into(), alert_sink, monitor_run, &span);
+        }
         if let Some(failure) = rule_violated {
             info!("{motor_group_buffers:?}");
             info!("Found rule violation {failure} in motor {motor_group_id}");
-            let alert = create_alert(motor_group_id, motor_group_buffers.get_time(), failure);
-            let vec: Vec<u8> =
-                to_allocvec_cobs(&alert).expect("Could not write motor monitor alert to Vec<u8>");
-            cloud_server
-                .write_all(&vec)
-                .expect("Could not send motor alert to cloud server");
+            let alert = create_alert(
+                motor_group_id,
+                time,
+                failure,
+                motor_monitor_parameters
+                    .forensic_alerts
+                    .then(|| motor_group_buffers.get_fingerprint()),
+                match motor_monitor_parameters.track_provenance {
+                    true => motor_group_buffers.get_sampled_messages(),
+                    false => vec![],
+                },
+            );
+            send_alert(
+                &motor_group_buffers.attach_averages(alert),
+                alert_sink,
+                monitor_run,
+                &span,
+            );
             motor_group_buffers.reset();
         }
     }
 }
 
+fn send_alert(
+    detailed_alert: &DetailedAlert,
+    alert_sink: &mut AlertSink,
+    monitor_run: &MonitorRun,
+    window_evaluation_span: &TelemetrySpan,
+) {
+    let alert = &detailed_alert.alert;
+    let _span =
+        monitor_run
+            .telemetry
+            .start_child_span(window_evaluation_span, "alert_emission", || {
+                vec![
+                    ("motor_id", alert.motor_id.into()),
+                    ("failure", alert.failure.to_string().into()),
+                    ("alert_time", alert.time.into()),
+                    ("cleared", alert.cleared.into()),
+                ]
+            });
+    if monitor_run.run_statistics.routing.route(alert) == RouteDestination::CloudServer {
+        profiling::measure(HotPath::Alert, || {
+            alert_sink
+                .cloud_server
+                .write_all(&alert_sink.alert_codec.encode_detailed(detailed_alert))
+                .expect("Could not send motor alert to cloud server")
+        });
+    }
+    alert_sink.alert_webhooks.notify(detailed_alert);
+}
+
 fn add_message_to_sensor_buffer(
     message: SensorMessage,
     sensor_id: u32,
@@ -225,10 +739,405 @@ fn get_motor_group_buffers(
         .expect("Motor group id did not match to a motor group buffer")
 }
 
-fn create_alert(motor_group_id: u32, time: f64, failure: MotorFailure) -> Alert {
+fn create_alert(
+    motor_group_id: u32,
+    time: f64,
+    failure: MotorFailure,
+    fingerprint: Option<[ChannelFingerprint; 4]>,
+    sampled_messages: Vec<SampledMessageId>,
+) -> Alert {
     Alert {
         time,
         motor_id: motor_group_id as u16,
         failure,
+        fingerprint,
+        cleared: false,
+        sampled_messages,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_transfer_objects::{
+        AlertEncoding, AlertSource, AveragingStrategy, ToolChange, Transport, WearModel,
+    };
+    use i2c_bus::loopback::{LoopbackBus, SimulatedSensor};
+    use std::net::{SocketAddr, TcpListener};
+
+    fn get_test_buffers(motor_groups: usize) -> Vec<MotorGroupSensorsBuffers> {
+        (0..motor_groups)
+            .map(|_| {
+                MotorGroupSensorsBuffers::new(
+                    Duration::from_secs(1),
+                    WearModel {
+                        wear_rate: 1.0,
+                        rotational_speed_threshold: 0.0,
+                        tool_change: ToolChange::OnOverstrainFailure,
+                    },
+                    EvictionStrategy::MessageTime,
+                    AveragingStrategy::Window,
+                    None,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn get_test_message(sensor_id: u32, timestamp: f64) -> SensorMessage {
+        SensorMessage {
+            reading: 300.0,
+            sensor_id,
+            timestamp,
+            sample_id: None,
+        }
+    }
+
+    #[test]
+    fn batch_of_a_motor_group_is_evaluated_once() {
+        let mut buffers = get_test_buffers(2);
+        let batch = (0..10)
+            .map(|index| get_test_message(index % 4, 100.0 + index as f64 * 0.01))
+            .collect();
+        assert_eq!(add_messages(&mut buffers, batch), vec![(0, 100.09)]);
+        assert_eq!(buffers[0].air_temperature_sensor.len(), 3);
+        assert_eq!(buffers[0].torque_sensor.len(), 2);
+    }
+
+    #[test]
+    fn batch_is_evaluated_once_per_motor_group() {
+        let mut buffers = get_test_buffers(2);
+        let batch = vec![
+            get_test_message(0, 100.0),
+            get_test_message(4, 100.2),
+            get_test_message(1, 100.1),
+        ];
+        assert_eq!(
+            add_messages(&mut buffers, batch),
+            vec![(0, 100.1), (1, 100.2)]
+        );
+    }
+
+    /// Alert of a motor group with the averages attached, whose sensors read the given readings
+    fn get_detailed_alert(readings: [[f32; 2]; 4]) -> DetailedAlert {
+        let mut buffers = get_test_buffers(1);
+        for (sensor_id, sensor_readings) in readings.into_iter().enumerate() {
+            for (index, reading) in sensor_readings.into_iter().enumerate() {
+                buffers[0][sensor_id].add(SensorMessage {
+                    reading,
+                    sensor_id: sensor_id as u32,
+                    timestamp: 100.0 + index as f64 * 0.1,
+                    sample_id: None,
+                });
+            }
+        }
+        let alert = Alert {
+            time: 100.1,
+            motor_id: 0,
+            failure: MotorFailure::PowerFailure,
+            fingerprint: None,
+            cleared: false,
+            sampled_messages: vec![],
+        };
+        buffers[0].attach_averages(alert)
+    }
+
+    #[cfg(feature = "detailed_alerts")]
+    #[test]
+    fn detailed_alert_carries_the_window_averages() {
+        let detailed_alert = get_detailed_alert([
+            [300.0, 302.0],
+            [310.0, 312.0],
+            [1000.0, 2000.0],
+            [40.0, 60.0],
+        ]);
+        let averages = detailed_alert.averages.unwrap();
+        assert_eq!(averages.temperature_difference, -10.0);
+        assert_eq!(averages.rotational_speed, 1500.0);
+        assert_eq!(averages.torque, 50.0);
+        assert!((averages.power - 50.0 * 1500.0 / 60.0 * 2.0 * std::f64::consts::PI).abs() < 1e-9);
+        assert_eq!(detailed_alert.alert.time, 100.1);
+    }
+
+    #[cfg(not(feature = "detailed_alerts"))]
+    #[test]
+    fn alert_carries_no_averages_without_the_feature() {
+        let detailed_alert = get_detailed_alert([[300.0; 2], [310.0; 2], [1000.0; 2], [40.0; 2]]);
+        assert!(detailed_alert.averages.is_none());
+    }
+
+    #[test]
+    fn overstrain_alerts_of_a_high_torque_series_follow_the_wear_model() {
+        let mut buffers = vec![MotorGroupSensorsBuffers::new(
+            Duration::from_secs(1),
+            WearModel {
+                wear_rate: 100.0,
+                rotational_speed_threshold: 0.0,
+                tool_change: ToolChange::OnOverstrainFailure,
+            },
+            EvictionStrategy::MessageTime,
+            AveragingStrategy::Window,
+            None,
+            None,
+        )];
+        // The series of the test of the same name of the validator, received in one batch
+        // per window sampling interval of 1 s
+        let mut alert_times = vec![];
+        for window in 1..=10 {
+            let batch = (window * 10 - 9..=window * 10)
+                .flat_map(|index| {
+                    [300.0, 290.0, 1500.0, 50.0].into_iter().enumerate().map(
+                        move |(sensor_id, reading)| SensorMessage {
+                            reading,
+                            sensor_id: sensor_id as u32,
+                            timestamp: 1000.0 + index as f64 / 10.0,
+                            sample_id: None,
+                        },
+                    )
+                })
+                .collect();
+            for (motor_group_id, timestamp) in add_messages(&mut buffers, batch) {
+                let motor_group_buffers = &mut buffers[motor_group_id as usize];
+                motor_group_buffers.refresh_caches(Duration::from_secs_f64(timestamp));
+                let failure =
+                    rules_engine::violated_rule(motor_group_buffers, None, &Default::default());
+                if let Some(failure) = failure {
+                    assert_eq!(failure, MotorFailure::OverstrainFailure);
+                    alert_times.push(motor_group_buffers.get_time());
+                    motor_group_buffers.reset();
+                }
+            }
+        }
+        assert_eq!(alert_times, vec![1004.0, 1007.0, 1010.0]);
+    }
+
+    #[test]
+    fn cumulative_age_is_counted_from_the_connection_of_the_sensors() {
+        let mut buffers = get_test_buffers(1);
+        let start_time = Duration::from_secs(1000);
+        // The series of the test of the same name of the validator, up to its first alert,
+        // received in one batch per window sampling interval of 1 s
+        let mut alert_times = vec![];
+        for window in 3..=25 {
+            let batch = (window * 10 - 9..=window * 10)
+                .flat_map(|index| {
+                    [300.0, 290.0, 150.0, 500.0].into_iter().enumerate().map(
+                        move |(sensor_id, reading)| SensorMessage {
+                            reading,
+                            sensor_id: sensor_id as u32,
+                            timestamp: start_time.as_secs_f64() + index as f64 / 10.0,
+                            sample_id: None,
+                        },
+                    )
+                })
+                .collect();
+            for (motor_group_id, timestamp) in add_messages(&mut buffers, batch) {
+                let motor_group_buffers = &mut buffers[motor_group_id as usize];
+                motor_group_buffers.refresh_caches(Duration::from_secs_f64(timestamp));
+                let failure = rules_engine::violated_rule(
+                    motor_group_buffers,
+                    Some(start_time),
+                    &Default::default(),
+                );
+                if let Some(failure) = failure {
+                    assert_eq!(failure, MotorFailure::OverstrainFailure);
+                    alert_times.push(motor_group_buffers.get_time());
+                    motor_group_buffers.reset();
+                }
+            }
+        }
+        assert_eq!(alert_times, vec![1025.0]);
+    }
+
+    /// Stub cloud server, which agrees on the requested encoding and returns the alerts it
+    /// received until the monitor closed the connection
+    fn spawn_cloud_server() -> (SocketAddr, thread::JoinHandle<Vec<Alert>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            utils::read_object::<AlertSource>(&mut stream).unwrap();
+            let encoding = utils::read_object::<AlertEncoding>(&mut stream).unwrap();
+            stream
+                .write_all(&utils::encode_object(&encoding).unwrap())
+                .unwrap();
+            let mut alerts = vec![];
+            while let Ok(alert) = utils::read_object::<Alert>(&mut stream) {
+                alerts.push(alert);
+            }
+            alerts
+        });
+        (address, handle)
+    }
+
+    /// Parameters of a one second run of the motor groups, whose alerts are sent to the cloud
+    /// server at the address
+    fn get_test_parameters(
+        number_of_tcp_motor_groups: usize,
+        number_of_i2c_motor_groups: u8,
+        cloud_server_address: SocketAddr,
+    ) -> MotorMonitorParameters {
+        let start_time = utils::get_now_secs().to_string();
+        let number_of_tcp_motor_groups = number_of_tcp_motor_groups.to_string();
+        let number_of_i2c_motor_groups = number_of_i2c_motor_groups.to_string();
+        let cloud_server_address = cloud_server_address.to_string();
+        let arguments = [
+            "motor_monitor",
+            &start_time,
+            "1",
+            "ClientServer",
+            &number_of_tcp_motor_groups,
+            &number_of_i2c_motor_groups,
+            "1000",
+            "127.0.0.1:0",
+            &cloud_server_address,
+            "100",
+            "10",
+            "2",
+            "1.0",
+            "0",
+            "0",
+            "false",
+            "0",
+        ];
+        utils::arguments::get_motor_monitor_parameters(&arguments.map(String::from))
+    }
+
+    /// Messages and statuses the handler of a sensor connection, which expects the given sensor,
+    /// passes on for the three messages of the sensor
+    fn handle_sensor_connection(
+        expected_sensor_id: Option<u32>,
+        sensor_id: u32,
+    ) -> (Vec<SensorMessage>, Vec<SensorStatus>) {
+        let mut parameters = get_test_parameters(1, 0, "127.0.0.1:1".parse().unwrap());
+        // The sensor is only reported dead if its connection closes before the end of the run
+        parameters.duration = 60.0;
+        let monitor_run = MonitorRun::new(
+            "motor_monitor_cs",
+            &parameters,
+            RunStatistics::new(&parameters),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = SensorStream::connect(
+            listener.local_addr().unwrap(),
+            Transport::Tcp,
+            0,
+            Duration::from_secs(1),
+        );
+        let mut sensor = listener.accept().unwrap().0;
+        for index in 0..3 {
+            let message = get_test_message(sensor_id, 100.0 + index as f64);
+            sensor
+                .write_all(&utils::encode_object(&message).unwrap())
+                .unwrap();
+        }
+        drop(sensor);
+        let (tx, rx) = channel();
+        let (status_tx, status_rx) = channel();
+        let pool = ThreadPoolBuilder::new().pool_size(1).create().unwrap();
+        wait_on_complete(vec![schedule_sensor_handler(
+            stream,
+            expected_sensor_id,
+            &parameters,
+            &monitor_run,
+            tx,
+            status_tx,
+            &pool,
+        )]);
+        (rx.iter().collect(), status_rx.iter().collect())
+    }
+
+    #[test]
+    fn sensor_with_the_expected_id_is_handled() {
+        let (messages, statuses) = handle_sensor_connection(Some(1), 1);
+        assert_eq!(messages.len(), 3);
+        assert!(
+            matches!(
+                statuses[..],
+                [SensorStatus::Alive(1), SensorStatus::Dead(1)]
+            ),
+            "{statuses:?}"
+        );
+    }
+
+    #[test]
+    fn sensor_with_an_unexpected_id_is_flagged() {
+        let (messages, statuses) = handle_sensor_connection(Some(1), 2);
+        // The connection is closed before any of its readings is averaged into the wrong slot
+        assert!(messages.is_empty(), "{messages:?}");
+        assert!(statuses.is_empty(), "{statuses:?}");
+        // Without an expected order, any sensor is accepted on the connection
+        let (messages, _) = handle_sensor_connection(None, 2);
+        assert_eq!(messages.len(), 3);
+    }
+
+    /// Runs the monitor for a simulated i2c motor group whose power is too low throughout,
+    /// returning the statistics of the run
+    fn run_with_simulated_i2c_sensors(parameters: &MotorMonitorParameters) -> RunStatistics {
+        // The temperatures are plausible, while the rotational speed and the torque of the
+        // two other sensors amount to a power of about 1570 W
+        let mut bus = LoopbackBus::new();
+        for (sensor_id, readings) in ["300.0", "310.0", "1500.0", "10.0"].into_iter().enumerate() {
+            bus.attach(
+                sensor_id as u16,
+                SimulatedSensor::new(
+                    sensor_id as u32,
+                    readings,
+                    Duration::from_millis(10),
+                    0,
+                    0.0,
+                ),
+            );
+        }
+        let pool = ThreadPoolBuilder::new().pool_size(2).create().unwrap();
+        let (tx, rx) = channel();
+        let (_status_tx, status_rx) = channel();
+        let monitor_run = MonitorRun::new(
+            "motor_monitor_cs",
+            parameters,
+            RunStatistics::new(parameters),
+        );
+        let consumer = handle_consumer(
+            rx,
+            status_rx,
+            parameters,
+            &monitor_run,
+            ConsumerOptions {
+                snapshot_path: None,
+                batch_recv: false,
+                eviction_strategy: EvictionStrategy::MessageTime,
+            },
+            &pool,
+        );
+        let i2c_sensors = setup_i2c_sensor_handlers(parameters, bus, tx, &pool);
+        wait_on_complete(vec![i2c_sensors, consumer]);
+        monitor_run.run_statistics
+    }
+
+    #[test]
+    fn simulated_i2c_sensors_produce_an_alert() {
+        let (cloud_server_address, cloud_server) = spawn_cloud_server();
+        let parameters = get_test_parameters(0, 1, cloud_server_address);
+        run_with_simulated_i2c_sensors(&parameters);
+        let alerts = cloud_server.join().unwrap();
+        assert!(!alerts.is_empty(), "No alert was sent to the cloud server");
+        assert!(alerts
+            .iter()
+            .all(|alert| alert.motor_id == 0 && alert.failure == MotorFailure::PowerFailure));
+    }
+
+    #[test]
+    fn monitor_without_cloud_server_counts_its_alerts() {
+        // Nothing listens on the address, so connecting to it would fail the run
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let unused_address = listener.local_addr().unwrap();
+        drop(listener);
+        let mut parameters = get_test_parameters(0, 1, unused_address);
+        parameters.no_cloud = true;
+        let run_statistics = run_with_simulated_i2c_sensors(&parameters);
+        assert!(
+            run_statistics.routing.get_sent_alerts() > 0,
+            "No alert was counted"
+        );
     }
 }
@@ -2,15 +2,27 @@ use std::f64;
 use std::ops::{Index, IndexMut};
 use std::time::Duration;
 
-use crate::SlidingWindow;
+use data_transfer_objects::{
+    Alert, AveragingStrategy, ChannelFingerprint, DetailedAlert, SampledMessageId, WearModel,
+};
+use utils::recovery::RecoveryTracker;
+use utils::units::MotorReadings;
+use utils::window_export::WindowExport;
+use utils::ToolWear;
+
+use crate::sensor_buffer::SensorBuffer;
+use crate::sliding_window::EvictionStrategy;
+use crate::snapshot::MotorGroupSnapshot;
 
 #[derive(Debug)]
 pub struct MotorGroupSensorsBuffers {
-    pub air_temperature_sensor: SlidingWindow,
-    pub process_temperature_sensor: SlidingWindow,
-    pub rotational_speed_sensor: SlidingWindow,
-    pub torque_sensor: SlidingWindow,
-    pub age: Duration,
+    pub air_temperature_sensor: SensorBuffer,
+    pub process_temperature_sensor: SensorBuffer,
+    pub rotational_speed_sensor: SensorBuffer,
+    pub torque_sensor: SensorBuffer,
+    pub tool_wear: ToolWear,
+    pub recovery: RecoveryTracker,
+    stale_sensors: [bool; 4],
 }
 
 impl MotorGroupSensorsBuffers {
@@ -20,16 +32,52 @@ impl MotorGroupSensorsBuffers {
             && self.rotational_speed_sensor.len() > 0
             && self.torque_sensor.len() > 0
     }
+
+    pub(crate) fn is_stale(&self) -> bool {
+        self.stale_sensors.iter().any(|stale| *stale)
+    }
 }
 
 impl MotorGroupSensorsBuffers {
-    pub fn new(window_size: Duration) -> MotorGroupSensorsBuffers {
+    pub fn new(
+        window_size: Duration,
+        wear_model: WearModel,
+        eviction_strategy: EvictionStrategy,
+        averaging: AveragingStrategy,
+        interpolation_interval: Option<Duration>,
+        window_count: Option<usize>,
+    ) -> MotorGroupSensorsBuffers {
+        let new_sensor_buffer = || {
+            SensorBuffer::new(
+                averaging,
+                window_size,
+                eviction_strategy,
+                interpolation_interval,
+                window_count,
+            )
+        };
         MotorGroupSensorsBuffers {
-            air_temperature_sensor: SlidingWindow::new(window_size),
-            process_temperature_sensor: SlidingWindow::new(window_size),
-            rotational_speed_sensor: SlidingWindow::new(window_size),
-            torque_sensor: SlidingWindow::new(window_size),
-            age: utils::get_now_duration(),
+            air_temperature_sensor: new_sensor_buffer(),
+            process_temperature_sensor: new_sensor_buffer(),
+            rotational_speed_sensor: new_sensor_buffer(),
+            torque_sensor: new_sensor_buffer(),
+            tool_wear: ToolWear::new(wear_model),
+            recovery: RecoveryTracker::new(),
+            stale_sensors: [false; 4],
+        }
+    }
+
+    pub fn mark_sensor_stale(&mut self, sensor_id: usize) {
+        match self.stale_sensors.get_mut(sensor_id) {
+            Some(stale) => *stale = true,
+            None => panic!("Invalid MotorGroupSensorsBuffers index"),
+        }
+    }
+
+    pub fn mark_sensor_live(&mut self, sensor_id: usize) {
+        match self.stale_sensors.get_mut(sensor_id) {
+            Some(stale) => *stale = false,
+            None => panic!("Invalid MotorGroupSensorsBuffers index"),
         }
     }
 
@@ -40,28 +88,95 @@ impl MotorGroupSensorsBuffers {
         self.torque_sensor.refresh_cache(at_time);
     }
 
+    pub fn get_out_of_order_messages(&self) -> u64 {
+        (0..4)
+            .map(|index| self[index].get_out_of_order_messages())
+            .sum()
+    }
+
     pub fn reset(&mut self) {
         self.air_temperature_sensor.reset();
         self.process_temperature_sensor.reset();
         self.rotational_speed_sensor.reset();
         self.torque_sensor.reset();
-        self.age = utils::get_now_duration();
+    }
+
+    pub(crate) fn snapshot(&self) -> MotorGroupSnapshot {
+        MotorGroupSnapshot {
+            windows: [
+                self.air_temperature_sensor.iter().copied().collect(),
+                self.process_temperature_sensor.iter().copied().collect(),
+                self.rotational_speed_sensor.iter().copied().collect(),
+                self.torque_sensor.iter().copied().collect(),
+            ],
+            tool_wear: self.tool_wear,
+        }
+    }
+
+    pub(crate) fn restore(&mut self, snapshot: MotorGroupSnapshot) {
+        for (index, window) in snapshot.windows.into_iter().enumerate() {
+            self[index].restore(window);
+        }
+        self.tool_wear = snapshot.tool_wear;
+    }
+
+    pub(crate) fn get_fingerprint(&self) -> [ChannelFingerprint; 4] {
+        [0, 1, 2, 3].map(|index| utils::get_window_fingerprint(self[index].iter()))
+    }
+
+    pub(crate) fn get_sampled_messages(&self) -> Vec<SampledMessageId> {
+        utils::provenance::get_sampled_messages((0..4).flat_map(|index| self[index].iter()))
+    }
+
+    /// Logs the bounds and readings of the windows of the sensors with their averages,
+    /// of which a moving average keeps none
+    pub(crate) fn log_windows(&self, motor_group_id: u32) {
+        for sensor_id in 0..4 {
+            if let SensorBuffer::Window(window) = &self[sensor_id] {
+                let (window_start, window_end) = window.get_bounds();
+                let timestamps: Vec<f64> = window.iter().map(|message| message.timestamp).collect();
+                utils::window_diagnostics::log_window(
+                    motor_group_id,
+                    sensor_id as u32,
+                    (window_start.as_secs_f64(), window_end.as_secs_f64()),
+                    window.get_window_average(),
+                    &timestamps,
+                );
+            }
+        }
+    }
+
+    /// Averages of the sensors, typed by the channel of their buffer
+    pub(crate) fn get_readings(&self) -> MotorReadings {
+        MotorReadings::from_channels([0, 1, 2, 3].map(|index| self[index].get_average()))
+    }
+
+    /// Exports the averages of the sensors the rules are evaluated on
+    pub(crate) fn export_window(&self, motor_group_id: u32, window_export: &WindowExport) {
+        window_export.export_averages(motor_group_id, self.get_time(), self.get_readings());
+    }
+
+    /// Attaches the averages of the sensors the rules were evaluated on to the alert
+    pub(crate) fn attach_averages(&self, alert: Alert) -> DetailedAlert {
+        utils::detailed_alert::attach_sensor_averages(alert, self.get_readings())
     }
 
     pub(crate) fn get_time(&self) -> f64 {
-        self.rotational_speed_sensor
-            .iter()
-            .chain(self.process_temperature_sensor.iter())
-            .chain(self.rotational_speed_sensor.iter())
-            .chain(self.torque_sensor.iter())
-            .map(|sensor_message| sensor_message.timestamp)
-            .reduce(f64::max)
-            .expect("Trying to get time from empty sensor group buffers")
+        [
+            &self.rotational_speed_sensor,
+            &self.process_temperature_sensor,
+            &self.rotational_speed_sensor,
+            &self.torque_sensor,
+        ]
+        .into_iter()
+        .filter_map(SensorBuffer::get_latest_timestamp)
+        .reduce(f64::max)
+        .expect("Trying to get time from empty sensor group buffers")
     }
 }
 
 impl Index<usize> for MotorGroupSensorsBuffers {
-    type Output = SlidingWindow;
+    type Output = SensorBuffer;
 
     fn index(&self, index: usize) -> &Self::Output {
         match index {
@@ -0,0 +1,27 @@
+use i2c_bus::I2cBus;
+use rppal::i2c::{Error, I2c};
+
+/// The I2C controller of the Raspberry Pi
+pub struct RpiI2c(I2c);
+
+impl RpiI2c {
+    pub fn new() -> RpiI2c {
+        RpiI2c(I2c::new().expect("Could not instantiate i2c object"))
+    }
+}
+
+impl I2cBus for RpiI2c {
+    type Error = Error;
+
+    fn set_slave_address(&mut self, address: u16) -> Result<(), Error> {
+        self.0.set_slave_address(address)
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        self.0.read(buffer)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        self.0.write(buffer)
+    }
+}
@@ -1,24 +1,30 @@
-use data_transfer_objects::MotorFailure;
+use std::time::Duration;
+
+use data_transfer_objects::{MotorFailure, RuleThresholds};
 
 use crate::MotorGroupSensorsBuffers;
 
-pub fn violated_rule(motor_group_buffers: &MotorGroupSensorsBuffers) -> Option<MotorFailure> {
-    let air_temperature = motor_group_buffers
-        .air_temperature_sensor
-        .get_window_average();
-    let process_temperature = motor_group_buffers
-        .process_temperature_sensor
-        .get_window_average();
-    let rotational_speed = motor_group_buffers
-        .rotational_speed_sensor
-        .get_window_average();
-    let torque = motor_group_buffers.torque_sensor.get_window_average();
-    let age = utils::get_now_duration() - motor_group_buffers.age;
-    utils::sensor_data_indicates_failure(
-        air_temperature,
-        process_temperature,
-        rotational_speed,
-        torque,
-        age,
-    )
+/// Evaluates the rules on the windows of the motor group, taking its cumulative age as tool wear
+/// if passed the start time of the run to count it from
+pub fn violated_rule(
+    motor_group_buffers: &mut MotorGroupSensorsBuffers,
+    cumulative_age_start_time: Option<Duration>,
+    thresholds: &RuleThresholds,
+) -> Option<MotorFailure> {
+    let readings = motor_group_buffers.get_readings();
+    let time = Duration::from_secs_f64(motor_group_buffers.get_time());
+    let tool_wear = motor_group_buffers
+        .tool_wear
+        .update(readings.rotational_speed, time);
+    let tool_wear = match cumulative_age_start_time {
+        Some(start_time) => utils::get_cumulative_age(start_time, time),
+        None => tool_wear,
+    };
+    let failure = utils::sensor_data_indicates_failure(readings, tool_wear, thresholds);
+    if let Some(failure) = failure {
+        motor_group_buffers
+            .tool_wear
+            .register_failure(failure, time);
+    }
+    failure
 }
@@ -0,0 +1,109 @@
+use std::slice;
+use std::time::Duration;
+
+use data_transfer_objects::{AveragingStrategy, SensorMessage};
+use utils::ewma::Ewma;
+
+use crate::sliding_window::EvictionStrategy;
+use crate::SlidingWindow;
+
+/// Readings of a sensor the rules are evaluated on, either as a window of the readings themselves
+/// or as their exponentially weighted moving average
+#[derive(Debug)]
+pub enum SensorBuffer {
+    Window(SlidingWindow),
+    Ewma(Ewma),
+}
+
+impl SensorBuffer {
+    pub fn new(
+        averaging: AveragingStrategy,
+        window_size: Duration,
+        eviction_strategy: EvictionStrategy,
+        interpolation_interval: Option<Duration>,
+        window_count: Option<usize>,
+    ) -> SensorBuffer {
+        match averaging {
+            AveragingStrategy::Window => SensorBuffer::Window(SlidingWindow::new(
+                window_size,
+                eviction_strategy,
+                interpolation_interval,
+                window_count,
+            )),
+            AveragingStrategy::Ewma { alpha } => SensorBuffer::Ewma(Ewma::new(alpha)),
+        }
+    }
+
+    pub fn add(&mut self, element: SensorMessage) {
+        match self {
+            SensorBuffer::Window(window) => window.add(element),
+            SensorBuffer::Ewma(ewma) => ewma.update(element.reading as f64, element.timestamp),
+        }
+    }
+
+    pub fn get_average(&self) -> f64 {
+        match self {
+            SensorBuffer::Window(window) => window.get_window_average(),
+            SensorBuffer::Ewma(ewma) => ewma.get_value().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Evicts the readings which left the window, the average not depending on a window otherwise
+    pub fn refresh_cache(&mut self, at_time: Duration) {
+        if let SensorBuffer::Window(window) = self {
+            window.refresh_cache(at_time);
+        }
+    }
+
+    pub fn get_out_of_order_messages(&self) -> u64 {
+        match self {
+            SensorBuffer::Window(window) => window.get_out_of_order_messages(),
+            SensorBuffer::Ewma(_) => 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        match self {
+            SensorBuffer::Window(window) => window.reset(),
+            SensorBuffer::Ewma(ewma) => ewma.reset(),
+        }
+    }
+
+    /// Restores the readings of a snapshot, which are replayed into an average
+    pub fn restore(&mut self, elements: Vec<SensorMessage>) {
+        match self {
+            SensorBuffer::Window(window) => window.restore(elements),
+            SensorBuffer::Ewma(ewma) => {
+                for element in elements {
+                    ewma.update(element.reading as f64, element.timestamp);
+                }
+            }
+        }
+    }
+
+    /// Readings of the window, of which an average keeps none
+    pub fn iter(&self) -> slice::Iter<'_, SensorMessage> {
+        match self {
+            SensorBuffer::Window(window) => window.iter(),
+            SensorBuffer::Ewma(_) => [].iter(),
+        }
+    }
+
+    /// Number of readings of the window, or the effective number of readings of an average
+    pub fn len(&self) -> usize {
+        match self {
+            SensorBuffer::Window(window) => window.len(),
+            SensorBuffer::Ewma(ewma) => ewma.get_effective_sample_count(),
+        }
+    }
+
+    pub fn get_latest_timestamp(&self) -> Option<f64> {
+        match self {
+            SensorBuffer::Window(window) => window
+                .iter()
+                .map(|sensor_message| sensor_message.timestamp)
+                .reduce(f64::max),
+            SensorBuffer::Ewma(ewma) => ewma.get_latest_timestamp(),
+        }
+    }
+}
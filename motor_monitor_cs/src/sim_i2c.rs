@@ -0,0 +1,34 @@
+use std::ops::Shl;
+use std::time::Duration;
+
+use data_transfer_objects::MotorMonitorParameters;
+use i2c_bus::loopback::{LoopbackBus, SimulatedSensor};
+
+const SENSOR_READINGS: [&str; 4] = [
+    include_str!("../../pico_sensor/resources/air_temperature.txt"),
+    include_str!("../../pico_sensor/resources/process_temperature.txt"),
+    include_str!("../../pico_sensor/resources/rotational_speed.txt"),
+    include_str!("../../pico_sensor/resources/torque.txt"),
+];
+
+/// Creates an in-memory bus with a simulated pico sensor attached for every sensor of the
+/// i2c motor groups, so the i2c ingestion can be run without hardware
+pub fn create_loopback_bus(args: &MotorMonitorParameters) -> LoopbackBus {
+    let mut bus = LoopbackBus::new();
+    for motor_id in 0..args.number_of_i2c_motor_groups {
+        for sensor_no in 0..4u8 {
+            let sensor_id: u8 = (motor_id).shl(2) + sensor_no;
+            bus.attach(
+                sensor_id as u16,
+                SimulatedSensor::new(
+                    sensor_id as u32,
+                    SENSOR_READINGS[sensor_no as usize],
+                    Duration::from_millis(args.sensor_sampling_interval as u64),
+                    0,
+                    0.0,
+                ),
+            );
+        }
+    }
+    bus
+}
@@ -1,25 +1,85 @@
 use data_transfer_objects::SensorMessage;
+use std::slice;
+use std::str::FromStr;
 use std::time::Duration;
 
+/// Time the elements older than the window size are evicted relative to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EvictionStrategy {
+    /// The time passed when refreshing, which goes backwards with an out-of-order message
+    MessageTime,
+    /// The latest time seen so far, which never goes backwards
+    HighWatermark,
+}
+
+impl FromStr for EvictionStrategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "message-time" => Ok(EvictionStrategy::MessageTime),
+            "high-watermark" => Ok(EvictionStrategy::HighWatermark),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SlidingWindow {
     window_size: Duration,
     elements: Vec<SensorMessage>,
+    eviction_strategy: EvictionStrategy,
+    high_watermark: Duration,
+    out_of_order_messages: u64,
+    /// Sampling interval of the sensor, at which the readings are interpolated before averaging
+    interpolation_interval: Option<Duration>,
+    /// Start (exclusive) and end of the window as of the last eviction
+    bounds: (Duration, Duration),
+    /// Number of the latest readings the window holds instead of those of the window size
+    window_count: Option<usize>,
 }
 
 impl SlidingWindow {
-    pub fn new(window_size: Duration) -> SlidingWindow {
+    pub fn new(
+        window_size: Duration,
+        eviction_strategy: EvictionStrategy,
+        interpolation_interval: Option<Duration>,
+        window_count: Option<usize>,
+    ) -> SlidingWindow {
         SlidingWindow {
             window_size,
             elements: Vec::new(),
+            eviction_strategy,
+            high_watermark: Duration::ZERO,
+            out_of_order_messages: 0,
+            interpolation_interval,
+            bounds: (Duration::ZERO, Duration::ZERO),
+            window_count,
         }
     }
 
     pub fn add(&mut self, element: SensorMessage) {
+        let timestamp = Duration::from_secs_f64(element.timestamp);
+        if timestamp < self.high_watermark {
+            self.out_of_order_messages += 1;
+        }
+        self.high_watermark = self.high_watermark.max(timestamp);
         self.elements.push(element);
+        self.truncate_to_count();
+    }
+
+    /// Evicts the oldest readings beyond the count of a count-bounded window
+    fn truncate_to_count(&mut self) {
+        if let Some(window_count) = self.window_count {
+            let excess = self.elements.len().saturating_sub(window_count);
+            self.elements.drain(..excess);
+        }
     }
 
     pub fn get_window_average(&self) -> f64 {
+        if let Some(interpolation_interval) = self.interpolation_interval {
+            return self.get_interpolated_average(interpolation_interval);
+        }
         let reading_sum: f64 = self
             .elements
             .iter()
@@ -28,17 +88,95 @@ impl SlidingWindow {
         reading_sum / (self.elements.len() as f64)
     }
 
+    /// Average of the readings linearly interpolated at the times a sensor sampling at the interval
+    /// would have sent them, from the earliest to the latest reading of the window.
+    /// A gap of missed samples thereby weighs as much as the samples would have,
+    /// instead of the average being biased toward the readings around it.
+    pub fn get_interpolated_average(&self, sampling_interval: Duration) -> f64 {
+        let mut points: Vec<(f64, f64)> = self
+            .elements
+            .iter()
+            .map(|message| (message.timestamp, message.reading as f64))
+            .collect();
+        points.sort_by(|point, other_point| point.0.total_cmp(&other_point.0));
+        let interval = sampling_interval.as_secs_f64();
+        let (Some(first), Some(last)) = (points.first(), points.last()) else {
+            return f64::NAN;
+        };
+        if points.len() < 2 || interval <= 0.0 {
+            return points.iter().map(|(_, reading)| reading).sum::<f64>() / points.len() as f64;
+        }
+        let samples = ((last.0 - first.0) / interval).floor() as usize + 1;
+        let mut segment = 0;
+        let mut reading_sum = 0.0;
+        for sample in 0..samples {
+            let time = first.0 + sample as f64 * interval;
+            while segment + 2 < points.len() && points[segment + 1].0 < time {
+                segment += 1;
+            }
+            let (start_time, start_reading) = points[segment];
+            let (end_time, end_reading) = points[segment + 1];
+            reading_sum += match end_time > start_time {
+                true => {
+                    start_reading
+                        + (end_reading - start_reading) * (time - start_time)
+                            / (end_time - start_time)
+                }
+                false => start_reading,
+            };
+        }
+        reading_sum / samples as f64
+    }
+
+    /// Evicts the readings older than the window size, while a count-bounded window already
+    /// evicted its oldest readings when adding new ones
     pub fn refresh_cache(&mut self, at_time: Duration) {
-        self.elements.retain(|message| {
-            Duration::from_secs_f64(message.timestamp) > at_time - self.window_size
-        });
+        self.high_watermark = self.high_watermark.max(at_time);
+        let reference_time = match self.eviction_strategy {
+            EvictionStrategy::MessageTime => at_time,
+            EvictionStrategy::HighWatermark => self.high_watermark,
+        };
+        if self.window_count.is_some() {
+            let window_start = self
+                .elements
+                .iter()
+                .map(|message| Duration::from_secs_f64(message.timestamp))
+                .min()
+                .unwrap_or(reference_time);
+            self.bounds = (window_start, reference_time);
+            return;
+        }
+        let window_start = reference_time.saturating_sub(self.window_size);
+        self.elements
+            .retain(|message| Duration::from_secs_f64(message.timestamp) > window_start);
+        self.bounds = (window_start, reference_time);
+    }
+
+    /// Start (exclusive) and end of the window the readings were last evicted from,
+    /// the start of a count-bounded window being its oldest reading
+    pub fn get_bounds(&self) -> (Duration, Duration) {
+        self.bounds
+    }
+
+    /// Number of added messages which were older than a previously seen one
+    pub fn get_out_of_order_messages(&self) -> u64 {
+        self.out_of_order_messages
     }
 
     pub fn reset(&mut self) {
         self.elements = Vec::new();
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &SensorMessage> {
+    pub fn restore(&mut self, elements: Vec<SensorMessage>) {
+        self.high_watermark = elements
+            .iter()
+            .map(|message| Duration::from_secs_f64(message.timestamp))
+            .fold(self.high_watermark, Duration::max);
+        self.elements = elements;
+        self.truncate_to_count();
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, SensorMessage> {
         self.elements.iter()
     }
 
@@ -55,3 +193,158 @@ impl IntoIterator for SlidingWindow {
         self.elements.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_message(timestamp: f64, reading: f32) -> SensorMessage {
+        SensorMessage {
+            reading,
+            sensor_id: 0,
+            timestamp,
+            sample_id: None,
+        }
+    }
+
+    fn get_count_window(window_count: usize) -> SlidingWindow {
+        SlidingWindow::new(
+            Duration::from_secs(1),
+            EvictionStrategy::MessageTime,
+            None,
+            Some(window_count),
+        )
+    }
+
+    #[test]
+    fn count_window_keeps_the_latest_readings() {
+        let mut window = get_count_window(20);
+        for index in 0..50 {
+            window.add(get_message(index as f64 * 0.1, index as f32));
+        }
+        assert_eq!(window.len(), 20);
+        assert_eq!(window.iter().next().unwrap().reading, 30.0);
+        // The average of the readings 30 to 49
+        assert_eq!(window.get_window_average(), 39.5);
+    }
+
+    #[test]
+    fn count_window_does_not_evict_by_time() {
+        let mut window = get_count_window(20);
+        for index in 0..5 {
+            window.add(get_message(index as f64, index as f32));
+        }
+        window.refresh_cache(Duration::from_secs(60));
+        assert_eq!(window.len(), 5);
+        assert_eq!(
+            window.get_bounds(),
+            (Duration::ZERO, Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn restored_count_window_keeps_the_latest_readings() {
+        let mut window = get_count_window(3);
+        window.restore(
+            (0..5)
+                .map(|index| get_message(index as f64, index as f32))
+                .collect(),
+        );
+        let readings: Vec<f32> = window.iter().map(|message| message.reading).collect();
+        assert_eq!(readings, [2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn time_window_evicts_readings_older_than_its_size() {
+        let mut window = SlidingWindow::new(
+            Duration::from_secs(1),
+            EvictionStrategy::MessageTime,
+            None,
+            None,
+        );
+        for index in 0..50 {
+            window.add(get_message(index as f64 * 0.1, index as f32));
+        }
+        window.refresh_cache(Duration::from_secs_f64(4.9));
+        assert_eq!(window.len(), 10);
+    }
+
+    #[test]
+    fn logged_bounds_of_a_time_window_span_its_size() {
+        let mut window = SlidingWindow::new(
+            Duration::from_millis(1500),
+            EvictionStrategy::MessageTime,
+            None,
+            None,
+        );
+        for index in 0..50 {
+            window.add(get_message(index as f64 * 0.1, index as f32));
+        }
+        window.refresh_cache(Duration::from_secs_f64(4.9));
+        let (window_start, window_end) = window.get_bounds();
+        let timestamps: Vec<f64> = window.iter().map(|message| message.timestamp).collect();
+        let logged_window = utils::window_diagnostics::format_window(
+            0,
+            0,
+            (window_start.as_secs_f64(), window_end.as_secs_f64()),
+            window.get_window_average(),
+            &timestamps,
+        );
+        assert!(
+            logged_window.contains("[3.400, 4.900] (1.500 s)"),
+            "{logged_window}"
+        );
+        assert!(timestamps.iter().all(|timestamp| *timestamp > 3.4 - 1e-9));
+    }
+
+    #[test]
+    fn out_of_order_message_does_not_move_a_high_watermark_window_back() {
+        let mut window = SlidingWindow::new(
+            Duration::from_secs(1),
+            EvictionStrategy::HighWatermark,
+            None,
+            None,
+        );
+        for index in 0..50 {
+            window.add(get_message(index as f64 * 0.1, index as f32));
+        }
+        window.refresh_cache(Duration::from_secs_f64(4.9));
+        assert_eq!(window.get_out_of_order_messages(), 0);
+        window.add(get_message(2.0, 100.0));
+        window.refresh_cache(Duration::from_secs_f64(2.0));
+        assert_eq!(window.get_out_of_order_messages(), 1);
+        // The readings of (3.9 s, 4.9 s] are kept, while the older message is evicted
+        assert_eq!(window.len(), 10);
+        assert!(window.iter().all(|message| message.reading != 100.0));
+        assert_eq!(
+            window.get_bounds(),
+            (Duration::from_secs_f64(3.9), Duration::from_secs_f64(4.9))
+        );
+    }
+
+    #[test]
+    fn interpolation_fills_the_gaps_of_a_window() {
+        let get_gappy_window = |interpolation_interval| {
+            let mut window = SlidingWindow::new(
+                Duration::from_secs(10),
+                EvictionStrategy::MessageTime,
+                interpolation_interval,
+                None,
+            );
+            // The samples at 3 s, 4 s and 5 s are missing
+            for (timestamp, reading) in
+                [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (6.0, 40.0), (7.0, 40.0)]
+            {
+                window.add(get_message(timestamp, reading));
+            }
+            window
+        };
+        // The naive average only weighs the readings around the gap
+        assert_eq!(get_gappy_window(None).get_window_average(), 16.0);
+        // The gap is interpolated as 10, 20 and 30
+        assert_eq!(
+            get_gappy_window(Some(Duration::from_secs(1))).get_window_average(),
+            17.5
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use data_transfer_objects::SensorMessage;
+use utils::disk_writer;
+use utils::ToolWear;
+
+use crate::MotorGroupSensorsBuffers;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MotorGroupSnapshot {
+    pub windows: [Vec<SensorMessage>; 4],
+    pub tool_wear: ToolWear,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MonitorSnapshot {
+    taken_at: Duration,
+    motor_groups: Vec<MotorGroupSnapshot>,
+}
+
+/// Periodically persists the state of the motor group buffers,
+/// so that a restarted monitor can continue with the window history of its predecessor.
+pub struct Snapshotter {
+    path: PathBuf,
+    interval: Duration,
+    last_snapshot: Duration,
+    total_cost: Duration,
+    number_of_snapshots: u32,
+    /// Snapshots skipped because the disk was full, in total and since the last one taken
+    skipped_snapshots: u32,
+    consecutively_skipped_snapshots: u32,
+}
+
+impl Snapshotter {
+    pub fn new(path: PathBuf, interval: Duration) -> Snapshotter {
+        Snapshotter {
+            path,
+            interval,
+            last_snapshot: utils::get_now_duration(),
+            total_cost: Duration::ZERO,
+            number_of_snapshots: 0,
+            skipped_snapshots: 0,
+            consecutively_skipped_snapshots: 0,
+        }
+    }
+
+    /// Restores the buffers from an existing snapshot, if it was taken less than `freshness_bound` ago
+    pub fn restore(&self, buffers: &mut [MotorGroupSensorsBuffers], freshness_bound: Duration) {
+        let Ok(data) = fs::read(&self.path) else {
+            info!("No snapshot found at {:?}", self.path);
+            return;
+        };
+        let snapshot: MonitorSnapshot = match postcard::from_bytes(&data) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("Could not parse snapshot at {:?}: {e}", self.path);
+                return;
+            }
+        };
+        let age = utils::get_now_duration().saturating_sub(snapshot.taken_at);
+        if age > freshness_bound || snapshot.motor_groups.len() != buffers.len() {
+            info!("Discarding snapshot taken {age:?} ago");
+            return;
+        }
+        let timestamps = snapshot
+            .motor_groups
+            .iter()
+            .flat_map(|motor_group| motor_group.windows.iter().flatten())
+            .map(|message| message.timestamp);
+        let recovered_history = timestamps
+            .clone()
+            .reduce(f64::max)
+            .zip(timestamps.clone().reduce(f64::min))
+            .map(|(newest, oldest)| Duration::from_secs_f64(newest - oldest))
+            .unwrap_or_default();
+        info!(
+            "Recovered {} messages covering {recovered_history:?} of window history from snapshot taken {age:?} ago",
+            timestamps.count()
+        );
+        for (motor_group_buffers, motor_group_snapshot) in
+            buffers.iter_mut().zip(snapshot.motor_groups)
+        {
+            motor_group_buffers.restore(motor_group_snapshot);
+        }
+    }
+
+    pub fn snapshot_if_due(&mut self, buffers: &[MotorGroupSensorsBuffers]) {
+        let now = utils::get_now_duration();
+        if now.saturating_sub(self.last_snapshot) < self.interval {
+            return;
+        }
+        let snapshot = MonitorSnapshot {
+            taken_at: now,
+            motor_groups: buffers
+                .iter()
+                .map(|motor_group_buffers| motor_group_buffers.snapshot())
+                .collect(),
+        };
+        let data = postcard::to_allocvec(&snapshot).expect("Could not write snapshot to Vec<u8>");
+        // Write to a temporary file first, so that a crash never leaves a partial snapshot behind
+        let temporary_path = self.path.with_extension("tmp");
+        self.last_snapshot = now;
+        match fs::write(&temporary_path, data) {
+            Ok(()) => {}
+            // The previous snapshot is kept, and the next one is attempted after the interval
+            Err(e) if disk_writer::is_disk_full(&e) => {
+                let _ = fs::remove_file(&temporary_path);
+                if self.consecutively_skipped_snapshots == 0 {
+                    error!("DISK FULL: could not write snapshot, skipping snapshots until there is space again: {e}");
+                }
+                self.skipped_snapshots += 1;
+                self.consecutively_skipped_snapshots += 1;
+                return;
+            }
+            Err(e) => panic!("Could not write snapshot file: {e}"),
+        }
+        fs::rename(&temporary_path, &self.path).expect("Could not replace snapshot file");
+        if self.consecutively_skipped_snapshots > 0 {
+            warn!(
+                "Resumed taking snapshots after skipping {}",
+                self.consecutively_skipped_snapshots
+            );
+            self.consecutively_skipped_snapshots = 0;
+        }
+        let cost = utils::get_now_duration().saturating_sub(now);
+        self.total_cost += cost;
+        self.number_of_snapshots += 1;
+        debug!("Took snapshot in {cost:?}");
+    }
+
+    pub fn log_cost(&self) {
+        info!(
+            "Took {} snapshots in {:?}",
+            self.number_of_snapshots, self.total_cost
+        );
+        if self.skipped_snapshots > 0 {
+            warn!(
+                "Skipped {} snapshots as the disk was full",
+                self.skipped_snapshots
+            );
+        }
+    }
+}
@@ -1,8 +1,5 @@
-use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
 use std::ops::Shl;
-use std::str::FromStr;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::sync::{mpsc, Arc};
 
 use env_logger::Target;
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
@@ -11,59 +8,110 @@ use log::{debug, info};
 
 use data_transfer_objects::{BenchmarkDataType, MotorMonitorParameters};
 use scheduler::Scheduler;
+use utils::benchmark::RunStatistics;
+use utils::crash::EarlyExit;
+use utils::frame_writer::SharedFrameWriter;
+use utils::transport::SensorListener;
+use utils::webhook::AlertWebhooks;
+use utils::window_export::WindowExport;
 
 mod monitor;
 mod sensor;
 
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::builder().target(Target::Stderr).init();
     let arguments: Vec<String> = std::env::args().collect();
     let motor_monitor_parameters: MotorMonitorParameters =
-        utils::get_motor_monitor_parameters(&arguments);
+        utils::arguments::get_motor_monitor_parameters(&arguments);
+    utils::resolved_config::log_resolved_config("motor_monitor_oo", &motor_monitor_parameters);
+    let run_statistics = RunStatistics::new(&motor_monitor_parameters);
+    let early_exit = EarlyExit::new(&motor_monitor_parameters, &run_statistics);
+    utils::crash::install_crash_handler(&motor_monitor_parameters, early_exit.clone());
+    utils::affinity::pin_to_cpu_cores(&motor_monitor_parameters);
+    utils::watchdog::start_memory_watchdog(&motor_monitor_parameters, early_exit);
+    let window_export = Arc::new(WindowExport::new(&motor_monitor_parameters));
     info!("Running procedure");
-    execute_procedure(motor_monitor_parameters);
+    execute_procedure(
+        motor_monitor_parameters.clone(),
+        &run_statistics,
+        window_export,
+    );
     info!("Processing completed");
-    utils::save_benchmark_readings(0, BenchmarkDataType::MotorMonitor);
+    utils::benchmark::save_benchmark_readings(
+        0,
+        BenchmarkDataType::MotorMonitor,
+        &motor_monitor_parameters.benchmark_output,
+        &run_statistics,
+    );
     info!("Saved benchmark readings");
 }
 
-fn execute_procedure(motor_monitor_parameters: MotorMonitorParameters) {
+fn execute_procedure(
+    motor_monitor_parameters: MotorMonitorParameters,
+    run_statistics: &RunStatistics,
+    window_export: Arc<WindowExport>,
+) {
     let pool = ThreadPoolBuilder::new()
         .pool_size(motor_monitor_parameters.thread_pool_size)
         .create()
         .unwrap();
-    let handle_list = setup_threads(motor_monitor_parameters, pool);
+    let handle_list = setup_threads(
+        motor_monitor_parameters,
+        run_statistics,
+        window_export,
+        pool,
+    );
     wait_on_complete(handle_list);
 }
 
 fn setup_threads(
     motor_monitor_parameters: MotorMonitorParameters,
+    run_statistics: &RunStatistics,
+    window_export: Arc<WindowExport>,
     thread_pool: ThreadPool,
 ) -> Vec<RemoteHandle<()>> {
-    let cloud_server = TcpStream::connect(motor_monitor_parameters.motor_monitor_listen_address)
-        .expect("Could not open connection to cloud server");
+    // Only the motor groups of the tcp sensors are monitored
+    utils::arguments::reject_i2c_motor_groups(&motor_monitor_parameters);
+    let (cloud_server, alert_codec) = utils::cloud_connection::connect_to_cloud_server(
+        &motor_monitor_parameters,
+        run_statistics.cloud_server_bytes.clone(),
+    );
+    // Each motor monitor blocks on its own alerts anyway, so they take turns on the stream
+    let cloud_server = SharedFrameWriter::locked(cloud_server);
     info!(
         "Connected to {}",
         motor_monitor_parameters.motor_monitor_listen_address
     );
-    let listen_address = SocketAddr::new(
-        IpAddr::from_str("0.0.0.0").unwrap(),
-        motor_monitor_parameters.sensor_listen_address.port(),
+    let alert_webhooks = AlertWebhooks::start(
+        &motor_monitor_parameters.alert_webhooks,
+        motor_monitor_parameters.detailed_alerts,
+    );
+    let listener = SensorListener::bind(&motor_monitor_parameters).unwrap();
+    debug!(
+        "Bound to 0.0.0.0:{}",
+        motor_monitor_parameters.sensor_bind_port
     );
-    let listener = TcpListener::bind(listen_address).unwrap();
-    debug!("Bound to {:?}", listen_address);
     let mut handles = vec![];
     for motor_id in 0..motor_monitor_parameters.number_of_tcp_motor_groups {
         let (sender, receiver) = mpsc::channel();
-        let monitor = monitor::MotorMonitor::build(receiver, cloud_server.try_clone().unwrap());
+        let monitor = monitor::MotorMonitor::build(
+            receiver,
+            cloud_server.clone(),
+            alert_codec,
+            alert_webhooks.clone(),
+            run_statistics.routing.clone(),
+            window_export.clone(),
+            &motor_monitor_parameters,
+        );
         handles.push(thread_pool.schedule(move || monitor.run()));
         for sensor_id in 0..4 {
             let full_id: u32 = (motor_id as u32).shl(2) + sensor_id as u32;
             let sensor = sensor::Sensor::build(
-                Duration::from_millis(motor_monitor_parameters.window_size_ms),
-                Duration::from_millis(motor_monitor_parameters.window_sampling_interval as u64),
                 sender.clone(),
                 listener.try_clone().unwrap(),
+                run_statistics.sensor_bytes.clone(),
+                &motor_monitor_parameters,
             );
             handles.push(thread_pool.schedule(move || sensor.run()))
         }
@@ -76,3 +124,44 @@ fn wait_on_complete(handle_list: Vec<RemoteHandle<()>>) {
         futures::executor::block_on(handle);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "The ObjectOriented monitor does not support i2c motor groups")]
+    fn i2c_motor_groups_are_rejected() {
+        let start_time = utils::get_now_secs().to_string();
+        let arguments = [
+            "motor_monitor",
+            &start_time,
+            "1",
+            "ObjectOriented",
+            "1",
+            "1",
+            "1000",
+            "127.0.0.1:0",
+            "127.0.0.1:1",
+            "100",
+            "10",
+            "2",
+            "1.0",
+            "0",
+            "0",
+            "false",
+            "0",
+        ];
+        let motor_monitor_parameters =
+            utils::arguments::get_motor_monitor_parameters(&arguments.map(String::from));
+        let run_statistics = RunStatistics::new(&motor_monitor_parameters);
+        let window_export = Arc::new(WindowExport::new(&motor_monitor_parameters));
+        let pool = ThreadPoolBuilder::new().pool_size(1).create().unwrap();
+        setup_threads(
+            motor_monitor_parameters,
+            &run_statistics,
+            window_export,
+            pool,
+        );
+    }
+}
@@ -1,37 +1,73 @@
-use std::io::Write;
-use std::net::TcpStream;
 use std::ops::{BitAnd, Shr};
 use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
 
 use log::{debug, info};
-use postcard::to_allocvec_cobs;
 
-use data_transfer_objects::Alert;
+use data_transfer_objects::{
+    Alert, ChannelFingerprint, DetailedAlert, HotPath, MotorMonitorParameters, RouteDestination,
+    RuleThresholds,
+};
+use utils::cloud_connection::AlertCodec;
+use utils::frame_writer::SharedFrameWriter;
+use utils::profiling;
+use utils::recovery::RecoveryTracker;
+use utils::routing::Routing;
+use utils::units::MotorReadings;
+use utils::webhook::AlertWebhooks;
+use utils::window_export::WindowExport;
+use utils::ToolWear;
 
 use crate::sensor::SensorAverage;
 
 pub struct MotorMonitor {
     // motor_id: u32,
     pub sensor_data_receiver: Receiver<SensorAverage>,
-    pub cloud_server: TcpStream,
+    pub cloud_server: SharedFrameWriter,
+    pub alert_codec: AlertCodec,
+    pub alert_webhooks: AlertWebhooks,
+    pub routing: Arc<Routing>,
+    pub window_export: Arc<WindowExport>,
     pub air_temperature: Option<SensorAverage>,
     pub process_temperature: Option<SensorAverage>,
     pub rotational_speed: Option<SensorAverage>,
     pub torque: Option<SensorAverage>,
+    pub tool_wear: ToolWear,
+    /// Start time of the run the cumulative age of the motor is counted from, which is then taken
+    /// as its tool wear
+    pub cumulative_age_start_time: Option<Duration>,
+    pub rule_thresholds: RuleThresholds,
+    pub recovery: RecoveryTracker,
 }
 
 impl MotorMonitor {
     pub fn build(
         sensor_data_receiver: Receiver<SensorAverage>,
-        cloud_server: TcpStream,
+        cloud_server: SharedFrameWriter,
+        alert_codec: AlertCodec,
+        alert_webhooks: AlertWebhooks,
+        routing: Arc<Routing>,
+        window_export: Arc<WindowExport>,
+        motor_monitor_parameters: &MotorMonitorParameters,
     ) -> MotorMonitor {
         MotorMonitor {
             sensor_data_receiver,
             cloud_server,
+            alert_codec,
+            alert_webhooks,
+            routing,
+            window_export,
             air_temperature: None,
             process_temperature: None,
             rotational_speed: None,
             torque: None,
+            tool_wear: ToolWear::new(motor_monitor_parameters.wear_model),
+            cumulative_age_start_time: motor_monitor_parameters
+                .use_cumulative_age
+                .then(|| Duration::from_secs_f64(motor_monitor_parameters.start_time)),
+            rule_thresholds: motor_monitor_parameters.rule_thresholds,
+            recovery: RecoveryTracker::new(),
         }
     }
 
@@ -55,32 +91,68 @@ impl MotorMonitor {
                                 + rotational_speed.number_of_values
                                 + torque.number_of_values)
                                 / 4;
-                            if let Some(failure) = utils::averages_indicate_failure(
+                            let time = [
+                                air_temperature.timestamp,
+                                process_temperature.timestamp,
+                                rotational_speed.timestamp,
+                                torque.timestamp,
+                            ]
+                            .into_iter()
+                            .reduce(f64::max)
+                            .unwrap();
+                            let readings = MotorReadings::from_channels([
                                 air_temperature.average,
                                 process_temperature.average,
                                 rotational_speed.average,
                                 torque.average,
-                                avg_number_of_values,
-                            ) {
+                            ]);
+                            let failure = profiling::measure(HotPath::Rules, || {
+                                let tool_wear = self.tool_wear.update(
+                                    readings.rotational_speed,
+                                    Duration::from_secs_f64(time),
+                                );
+                                let tool_wear = match self.cumulative_age_start_time {
+                                    Some(start_time) => utils::get_cumulative_age(
+                                        start_time,
+                                        Duration::from_secs_f64(time),
+                                    ),
+                                    None => tool_wear,
+                                };
+                                utils::averages_indicate_failure(
+                                    readings,
+                                    avg_number_of_values,
+                                    tool_wear,
+                                    &self.rule_thresholds,
+                                )
+                            });
+                            self.window_export.export_averages(motor_id, time, readings);
+                            if let Some(recovery_alert) =
+                                self.recovery.update(motor_id as u16, failure, time)
+                            {
+                                info!("Motor {motor_id} recovered from {}", recovery_alert.failure);
+                                self.send_alert(&recovery_alert.into());
+                            }
+                            if let Some(failure) = failure {
                                 info!("Found rule violation {failure} in motor {}", motor_id);
+                                self.tool_wear
+                                    .register_failure(failure, Duration::from_secs_f64(time));
                                 let alert = Alert {
-                                    time: [
-                                        air_temperature.timestamp,
-                                        process_temperature.timestamp,
-                                        rotational_speed.timestamp,
-                                        torque.timestamp,
-                                    ]
-                                    .into_iter()
-                                    .reduce(f64::max)
-                                    .unwrap(),
+                                    time,
                                     motor_id: motor_id as u16,
                                     failure,
+                                    fingerprint: get_fingerprint([
+                                        air_temperature,
+                                        process_temperature,
+                                        rotational_speed,
+                                        torque,
+                                    ]),
+                                    cleared: false,
+                                    // The averages carry no messages to track provenance of
+                                    sampled_messages: vec![],
                                 };
-                                let vec: Vec<u8> = to_allocvec_cobs(&alert)
-                                    .expect("Could not write motor monitor alert to Vec<u8>");
-                                self.cloud_server
-                                    .write_all(&vec)
-                                    .expect("Could not send motor alert to cloud server");
+                                self.send_alert(&utils::detailed_alert::attach_sensor_averages(
+                                    alert, readings,
+                                ));
                                 self.process_temperature = None;
                                 self.air_temperature = None;
                                 self.rotational_speed = None;
@@ -93,4 +165,27 @@ impl MotorMonitor {
         }
         debug!("Exiting monitor");
     }
+
+    fn send_alert(&self, detailed_alert: &DetailedAlert) {
+        if self.routing.route(&detailed_alert.alert) == RouteDestination::CloudServer {
+            profiling::measure(HotPath::Alert, || {
+                self.cloud_server
+                    .write_frame(&self.alert_codec.encode_detailed(detailed_alert))
+                    .expect("Could not send motor alert to cloud server")
+            });
+        }
+        self.alert_webhooks.notify(detailed_alert);
+    }
+}
+
+/// Fingerprints of the windows the averages were computed over, if all of them have one
+fn get_fingerprint(sensor_averages: [&SensorAverage; 4]) -> Option<[ChannelFingerprint; 4]> {
+    let [air_temperature, process_temperature, rotational_speed, torque] =
+        sensor_averages.map(|sensor_average| sensor_average.fingerprint);
+    Some([
+        air_temperature?,
+        process_temperature?,
+        rotational_speed?,
+        torque?,
+    ])
 }
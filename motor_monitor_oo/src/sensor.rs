@@ -1,29 +1,55 @@
-use data_transfer_objects::SensorMessage;
-use log::debug;
-use std::net::TcpListener;
+use data_transfer_objects::{
+    AveragingStrategy, ChannelFingerprint, HotPath, MotorMonitorParameters, SensorMessage,
+    WindowKind,
+};
+use log::{debug, log_enabled, warn, Level};
+use std::collections::VecDeque;
+use std::io::ErrorKind;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::time::Duration;
+use utils::ewma::Ewma;
+use utils::network_usage::{ByteCounter, CountingStream};
+use utils::profiling;
+use utils::quarantine::ReadingQuarantine;
+use utils::transport::SensorListener;
+use utils::{LogSampler, ReadError};
 
 pub struct SensorAverage {
     pub average: f64,
     pub number_of_values: usize,
     pub sensor_id: u32,
     pub timestamp: f64,
+    pub fingerprint: Option<ChannelFingerprint>,
 }
 
 struct SlidingWindow {
-    size: Duration,
-    last_sent: Duration,
-    elements: Vec<SensorMessage>,
+    kind: WindowKind,
+    elements: VecDeque<SensorMessage>,
 }
 
 impl SlidingWindow {
+    /// Adds the reading, evicting the oldest one beyond the count of a count-bounded window
+    fn push(&mut self, message: SensorMessage) {
+        self.elements.push_back(message);
+        if let WindowKind::Count(window_count) = self.kind {
+            if self.elements.len() > window_count {
+                self.elements.pop_front();
+            }
+        }
+    }
+
+    /// Evicts the readings older than the window size, a count-bounded window evicting none
     fn update(&mut self) {
+        let WindowKind::TimeMs(window_size_ms) = self.kind else {
+            return;
+        };
+        let size = Duration::from_millis(window_size_ms);
         let now = utils::get_now_duration();
         self.elements.retain(|message| {
             now.checked_sub(Duration::from_secs_f64(message.timestamp))
                 .unwrap_or(Duration::from_secs(0))
-                <= self.size
+                <= size
         });
     }
 
@@ -41,64 +67,134 @@ impl SlidingWindow {
     }
 }
 
+/// Readings of the sensor the sent averages are computed from
+enum Averaging {
+    Window(SlidingWindow),
+    Ewma(Ewma),
+}
+
 pub struct Sensor {
     // sensor_id: u32,
     pub monitor_connection: Sender<SensorAverage>,
-    pub listener: TcpListener,
+    pub listener: SensorListener,
+    /// Bytes exchanged with the sensors of all motors
+    sensor_bytes: Arc<ByteCounter>,
     pub interval: Duration,
-    window: SlidingWindow,
+    last_sent: Duration,
+    averaging: Averaging,
+    log_sampler: LogSampler,
+    reading_quarantine: ReadingQuarantine,
+    strict_deserialization: bool,
+    compact_wire: bool,
+    forensic_alerts: bool,
 }
 
 impl Sensor {
     pub fn build(
-        window_size: Duration,
-        interval: Duration,
         monitor_connection: Sender<SensorAverage>,
-        listener: TcpListener,
+        listener: SensorListener,
+        sensor_bytes: Arc<ByteCounter>,
+        motor_monitor_parameters: &MotorMonitorParameters,
     ) -> Sensor {
         Sensor {
             monitor_connection,
             listener,
-            interval,
-            window: SlidingWindow {
-                size: window_size,
-                last_sent: utils::get_now_duration(),
-                elements: vec![],
+            sensor_bytes,
+            interval: Duration::from_millis(
+                motor_monitor_parameters.window_sampling_interval as u64,
+            ),
+            log_sampler: LogSampler::new(motor_monitor_parameters.log_sample_rate),
+            reading_quarantine: ReadingQuarantine::new(motor_monitor_parameters),
+            strict_deserialization: motor_monitor_parameters.strict_deserialization,
+            compact_wire: motor_monitor_parameters.compact_wire,
+            forensic_alerts: motor_monitor_parameters.forensic_alerts,
+            last_sent: utils::get_now_duration(),
+            averaging: match motor_monitor_parameters.averaging {
+                AveragingStrategy::Window => Averaging::Window(SlidingWindow {
+                    kind: motor_monitor_parameters.window_kind,
+                    elements: VecDeque::new(),
+                }),
+                AveragingStrategy::Ewma { alpha } => Averaging::Ewma(Ewma::new(alpha)),
             },
         }
     }
 
     pub fn run(mut self) {
-        let (mut stream, _) = self.listener.accept().unwrap();
+        let stream = match self.listener.accept() {
+            Ok(stream) => stream,
+            // A connection beyond the connection cap is rejected, not the listener failing
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                warn!("{e}");
+                return;
+            }
+            Err(e) => panic!("Could not accept sensor connection: {e}"),
+        };
         debug!("Accepted stream");
         stream
             .set_read_timeout(Some(Duration::from_secs(5)))
             .expect("Could not set read timeout");
-        while let Some(sensor_message) = utils::read_object::<SensorMessage>(&mut stream) {
-            self.handle_sensor_message(sensor_message);
+        let mut stream = CountingStream::new(stream, self.sensor_bytes.clone());
+        loop {
+            let sensor_message = match utils::read_sensor_message(
+                &mut stream,
+                self.strict_deserialization,
+                self.compact_wire,
+            ) {
+                Ok(sensor_message) => sensor_message,
+                // Exits through the crash handler, which reports the malformed message
+                Err(ReadError::Malformed) => panic!("{}", ReadError::Malformed),
+                Err(_) => break,
+            };
+            if self.reading_quarantine.admit(&sensor_message) {
+                self.handle_sensor_message(sensor_message);
+            }
         }
+        self.reading_quarantine.log_counts();
         debug!("Exiting sensor");
     }
 
     fn handle_sensor_message(&mut self, message: SensorMessage) {
-        debug!("{message:?}");
-        self.window.elements.push(message);
+        if log_enabled!(Level::Debug) && self.log_sampler.sample() {
+            debug!("{message:?}");
+        }
+        profiling::measure(HotPath::Window, || match &mut self.averaging {
+            Averaging::Window(window) => window.push(message),
+            Averaging::Ewma(ewma) => ewma.update(message.reading as f64, message.timestamp),
+        });
         let now = utils::get_now_duration();
         if now
-            .checked_sub(self.window.last_sent)
+            .checked_sub(self.last_sent)
             .unwrap_or(Duration::from_secs(0))
             >= self.interval
         {
-            self.window.update();
+            let (average, number_of_values, fingerprint) =
+                profiling::measure(HotPath::Window, || match &mut self.averaging {
+                    Averaging::Window(window) => {
+                        window.update();
+                        (
+                            window.get_window_average(),
+                            window.elements.len(),
+                            self.forensic_alerts
+                                .then(|| utils::get_window_fingerprint(&window.elements)),
+                        )
+                    }
+                    // An average keeps no readings to fingerprint
+                    Averaging::Ewma(ewma) => (
+                        ewma.get_value().unwrap_or(0f64),
+                        ewma.get_effective_sample_count(),
+                        None,
+                    ),
+                });
             self.monitor_connection
                 .send(SensorAverage {
-                    average: self.window.get_window_average(),
-                    number_of_values: self.window.elements.len(),
+                    average,
+                    number_of_values,
                     timestamp: message.timestamp,
                     sensor_id: message.sensor_id,
+                    fingerprint,
                 })
                 .unwrap();
-            self.window.last_sent = now;
+            self.last_sent = now;
         }
     }
 }
@@ -0,0 +1,144 @@
+use std::fmt::Formatter;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::{fmt, io};
+
+use futures::channel::oneshot;
+use futures::future::{select, Either, RemoteHandle};
+use log::error;
+
+/// Reason the pipeline cannot deliver any further alerts
+#[derive(Debug)]
+pub enum PipelineError {
+    /// The sensor listener could not be bound, so no readings arrive
+    Bind(io::Error),
+    /// A sensor connection could not be accepted
+    Accept(io::Error),
+    /// An alert could not be written to the cloud server
+    AlertWrite(io::Error),
+    /// A stage panicked, with the message of the panic
+    Panic(String),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Bind(e) => write!(f, "Could not bind sensor listener: {e}"),
+            PipelineError::Accept(e) => write!(f, "Could not accept sensor connection: {e}"),
+            PipelineError::AlertWrite(e) => {
+                write!(f, "Could not send motor alert to cloud server: {e}")
+            }
+            PipelineError::Panic(message) => write!(f, "Pipeline stage panicked: {message}"),
+        }
+    }
+}
+
+/// Lets the stages of the pipeline report an error, ending the pipeline instead of
+/// leaving it idle until the end of the run.
+/// Only the first error is reported, the following ones are logged.
+#[derive(Clone)]
+pub struct PipelineFailure {
+    sender: Arc<Mutex<Option<oneshot::Sender<PipelineError>>>>,
+}
+
+impl PipelineFailure {
+    pub fn new() -> (PipelineFailure, oneshot::Receiver<PipelineError>) {
+        let (sender, receiver) = oneshot::channel();
+        (
+            PipelineFailure {
+                sender: Arc::new(Mutex::new(Some(sender))),
+            },
+            receiver,
+        )
+    }
+
+    pub fn fail(&self, pipeline_error: PipelineError) {
+        let sender = self
+            .sender
+            .lock()
+            .expect("Pipeline failure lock was poisoned")
+            .take();
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(pipeline_error);
+            }
+            None => error!("{pipeline_error}"),
+        }
+    }
+}
+
+/// Runs a stage of the pipeline, reporting a panic of it as a failure of the pipeline instead of
+/// unwinding through the thread pool, which would leave the pipeline idle
+pub fn catch_stage_panic(pipeline_failure: &PipelineFailure, stage: impl FnOnce()) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(stage)) {
+        pipeline_failure.fail(PipelineError::Panic(utils::crash::get_panic_message(
+            payload.as_ref(),
+        )));
+    }
+}
+
+/// Completes once the pipeline completes, or as soon as one of its stages fails
+pub async fn await_pipeline(
+    handle: RemoteHandle<()>,
+    failure: oneshot::Receiver<PipelineError>,
+) -> Result<(), PipelineError> {
+    match select(handle, failure).await {
+        Either::Left(((), _)) => Ok(()),
+        Either::Right((Ok(pipeline_error), _)) => Err(pipeline_error),
+        // All stages are gone without failing, so only the pipeline remains to complete
+        Either::Right((Err(oneshot::Canceled), handle)) => {
+            handle.await;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use futures::executor::{block_on, ThreadPoolBuilder};
+    use futures::future::{pending, FutureExt};
+
+    use super::*;
+
+    #[test]
+    fn subscriber_panicking_on_the_third_alert_fails_the_pipeline() {
+        let (pipeline_failure, failure_receiver) = PipelineFailure::new();
+        let pool = ThreadPoolBuilder::new().pool_size(1).create().unwrap();
+        // Like a pipeline whose sensors keep sending until the end of the run
+        let (pipeline, handle) = pending::<()>().remote_handle();
+        pool.spawn_ok(pipeline);
+        let mut alerts = 0;
+        let mut subscriber = move |_alert: u32| {
+            catch_stage_panic(&pipeline_failure, || {
+                alerts += 1;
+                if alerts == 3 {
+                    panic!("Could not send motor alert to cloud server");
+                }
+            })
+        };
+        pool.spawn_ok(async move {
+            for alert in 0..5 {
+                subscriber(alert);
+            }
+        });
+        let (result_sender, result_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            result_sender.send(block_on(await_pipeline(handle, failure_receiver)))
+        });
+        let result = result_receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Pipeline did not fail in time");
+        assert!(
+            matches!(
+                &result,
+                Err(PipelineError::Panic(message))
+                    if message == "Could not send motor alert to cloud server"
+            ),
+            "{result:?}"
+        );
+    }
+}
@@ -1,20 +1,43 @@
 use data_transfer_objects::{
-    Alert, BenchmarkDataType, MotorFailure, MotorMonitorParameters, SensorMessage,
+    Alert, AveragingStrategy, BenchmarkDataType, ChannelFingerprint, DetailedAlert, HotPath,
+    MotorFailure, MotorMonitorParameters, RouteDestination, RuleThresholds, SampledMessageId,
+    SensorMessage, WindowKind,
 };
 use env_logger::Target;
+use failure::{PipelineError, PipelineFailure};
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
-use futures::future::RemoteHandle;
-use log::{debug, info, trace};
-use postcard::to_allocvec_cobs;
+use futures::Future;
+use log::{debug, info, log_enabled, trace, warn, Level};
 use rx_rust_mp::create::create;
 use rx_rust_mp::from_iter::from_iter;
 use rx_rust_mp::observable::Observable;
 use rx_rust_mp::observer::Observer;
+use std::collections::HashMap;
 use std::f64;
-use std::io::Write;
-use std::net::{TcpListener, TcpStream};
-use std::ops::{BitAnd, Index, IndexMut, Shr};
+use std::io::ErrorKind;
+use std::ops::{BitAnd, Index, IndexMut, Shl, Shr};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use utils::benchmark::RunStatistics;
+use utils::cadence_window::CadenceWindows;
+use utils::cloud_connection::AlertCodec;
+use utils::crash::EarlyExit;
+use utils::ewma::Ewma;
+use utils::frame_writer::SharedFrameWriter;
+use utils::monitor_run::MonitorRun;
+use utils::network_usage::CountingStream;
+use utils::profiling;
+use utils::quarantine::ReadingQuarantine;
+use utils::recovery::RecoveryTracker;
+use utils::transport::{SensorListener, SensorStream};
+use utils::units::MotorReadings;
+use utils::webhook::AlertWebhooks;
+use utils::window_diagnostics;
+use utils::window_export::WindowExport;
+use utils::{LogSampler, ReadError, ToolWear};
+
+mod failure;
 
 #[derive(Debug, Copy, Clone)]
 struct SensorAverage {
@@ -54,6 +77,30 @@ impl MotorData {
         .reduce(f64::max)
         .expect("Trying to extract timestamp from empty motor data")
     }
+
+    /// Averages of the sensors, typed by their channel, if there are all of them
+    fn get_readings(&self) -> Option<MotorReadings> {
+        let mut readings = [0f64; 4];
+        for (index, reading) in readings.iter_mut().enumerate() {
+            *reading = self[index]?.reading;
+        }
+        Some(MotorReadings::from_channels(readings))
+    }
+
+    /// Exports the averages of the sensors the rules are evaluated on, if there are all of them
+    fn export(&self, motor_id: u32, window_export: &WindowExport) {
+        if let Some(readings) = self.get_readings() {
+            window_export.export_averages(motor_id, self.get_time(), readings);
+        }
+    }
+
+    /// Attaches the averages of the sensors the rules were evaluated on to the alert
+    fn attach_averages(&self, alert: Alert) -> DetailedAlert {
+        match self.get_readings() {
+            Some(readings) => utils::detailed_alert::attach_sensor_averages(alert, readings),
+            None => DetailedAlert::from(alert),
+        }
+    }
 }
 
 impl Index<usize> for MotorData {
@@ -83,110 +130,211 @@ impl IndexMut<usize> for MotorData {
 }
 
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::builder().target(Target::Stderr).init();
     let arguments: Vec<String> = std::env::args().collect();
     let motor_monitor_parameters: MotorMonitorParameters =
-        utils::get_motor_monitor_parameters(&arguments);
-    let cloud_server = TcpStream::connect(motor_monitor_parameters.motor_monitor_listen_address)
-        .expect("Could not open connection to cloud server");
+        utils::arguments::get_motor_monitor_parameters(&arguments);
+    utils::resolved_config::log_resolved_config("motor_monitor_rx", &motor_monitor_parameters);
+    let run_statistics = RunStatistics::new(&motor_monitor_parameters);
+    let early_exit = EarlyExit::new(&motor_monitor_parameters, &run_statistics);
+    utils::crash::install_crash_handler(&motor_monitor_parameters, early_exit.clone());
+    utils::affinity::pin_to_cpu_cores(&motor_monitor_parameters);
+    utils::watchdog::start_memory_watchdog(&motor_monitor_parameters, early_exit);
+    utils::arguments::reject_i2c_motor_groups(&motor_monitor_parameters);
+    let monitor_run = MonitorRun::new(
+        "motor_monitor_rx",
+        &motor_monitor_parameters,
+        run_statistics,
+    );
+    let (cloud_server, alert_codec) = utils::cloud_connection::connect_to_cloud_server(
+        &motor_monitor_parameters,
+        monitor_run.run_statistics.cloud_server_bytes.clone(),
+    );
+    // Keeps the writes to the cloud server off the threads of the pool
+    let cloud_server = SharedFrameWriter::spawn(cloud_server);
+    let alert_webhooks = AlertWebhooks::start(
+        &motor_monitor_parameters.alert_webhooks,
+        motor_monitor_parameters.detailed_alerts,
+    );
     let pool = ThreadPoolBuilder::new()
         .pool_size(motor_monitor_parameters.thread_pool_size)
         .create()
         .unwrap();
     info!("Running procedure");
-    let handle =
-        execute_reactive_streaming_procedure(&motor_monitor_parameters, &cloud_server, pool);
-    futures::executor::block_on(handle);
+    let pipeline = execute_reactive_streaming_procedure(
+        &motor_monitor_parameters,
+        &cloud_server,
+        alert_codec,
+        alert_webhooks,
+        &monitor_run,
+        pool,
+    );
+    // Reported by the crash handler, which flags the benchmark data as crashed
+    futures::executor::block_on(pipeline).unwrap_or_else(|e| panic!("Pipeline failed: {e}"));
+    cloud_server
+        .flush()
+        .expect("Could not send motor alerts to cloud server");
     info!("Processing completed");
-    utils::save_benchmark_readings(0, BenchmarkDataType::MotorMonitor);
+    monitor_run.telemetry.shutdown();
+    utils::benchmark::save_benchmark_readings(
+        0,
+        BenchmarkDataType::MotorMonitor,
+        &motor_monitor_parameters.benchmark_output,
+        &monitor_run.run_statistics,
+    );
     info!("Saved benchmark readings");
 }
 
 fn execute_reactive_streaming_procedure(
     motor_monitor_parameters: &MotorMonitorParameters,
-    cloud_server: &TcpStream,
+    cloud_server: &SharedFrameWriter,
+    alert_codec: AlertCodec,
+    alert_webhooks: AlertWebhooks,
+    monitor_run: &MonitorRun,
     pool: ThreadPool,
-) -> RemoteHandle<()> {
-    let mut cloud_server = cloud_server
-        .try_clone()
-        .expect("Could not clone tcp stream");
+) -> impl Future<Output = Result<(), PipelineError>> {
+    let cloud_server = cloud_server.clone();
+    let (pipeline_failure, failure_receiver) = PipelineFailure::new();
+    let listener_failure = pipeline_failure.clone();
     let total_number_of_motors = motor_monitor_parameters.number_of_tcp_motor_groups
         + motor_monitor_parameters.number_of_i2c_motor_groups as usize;
     let total_number_of_sensors = total_number_of_motors * 4;
+    let tool_wear = ToolWear::new(motor_monitor_parameters.wear_model);
+    let tool_wears = Arc::new(Mutex::new(vec![tool_wear; total_number_of_motors]));
+    let recoveries = Arc::new(Mutex::new(vec![
+        RecoveryTracker::new();
+        total_number_of_motors
+    ]));
+    // With moving averages, the windows only set the cadence of the evaluation, so that each
+    // reading is part of a single one, and the averages are kept per sensor across them
+    let (window_size_ms, ewmas) = match motor_monitor_parameters.averaging {
+        AveragingStrategy::Window => (get_window_size_ms(&motor_monitor_parameters), None),
+        AveragingStrategy::Ewma { alpha } => (
+            motor_monitor_parameters.window_sampling_interval as u64,
+            Some(Arc::new(Mutex::new(vec![
+                Ewma::new(alpha);
+                total_number_of_sensors
+            ]))),
+        ),
+    };
     let listen_pool = ThreadPoolBuilder::new().pool_size(1).create().unwrap();
     let read_message_pool = ThreadPoolBuilder::new()
         .pool_size(motor_monitor_parameters.number_of_tcp_motor_groups * 4 * 2)
         .create()
         .unwrap();
-    let sensor_listen_address = motor_monitor_parameters.sensor_listen_address;
-    create(move |subscriber| {
-        let listen_address = format!("0.0.0.0:{}", sensor_listen_address.port());
-        info!("Listening on {}", listen_address);
-        match TcpListener::bind(listen_address.clone()) {
-            Ok(listener) => {
-                info!("Bound listener on sensor listener address {listen_address}");
-                for _ in 0..total_number_of_sensors {
-                    match listener.accept() {
-                        Ok((stream, _)) => {
-                            subscriber.next(stream).unwrap();
-                        }
-                        Err(e) => subscriber.error(e).unwrap(),
-                    }
-                }
-            }
-            Err(e) => subscriber.error(e).unwrap(),
-        }
-        info!("Bound to all sensors");
-    })
-    .subscribe_on(listen_pool)
-    .flat_map(|mut stream| {
-        stream
-            .set_read_timeout(Some(Duration::from_secs(5)))
-            .expect("Could not set read timeout");
-        create(move |subscriber| {
-            while let Some(sensor_message) = utils::read_object::<SensorMessage>(&mut stream) {
-                trace!("{sensor_message:?}");
-                subscriber.next(sensor_message).unwrap();
-            }
-            info!("Reading from sensor completed");
-        })
+    let window_pool = ThreadPoolBuilder::new().pool_size(1).create().unwrap();
+    let sensor_listener_parameters = motor_monitor_parameters.clone();
+    let sensor_listener_run = monitor_run.clone();
+    let window_export = monitor_run.window_export.clone();
+    let telemetry = monitor_run.telemetry.clone();
+    let alert_telemetry = monitor_run.telemetry.clone();
+    let routing = monitor_run.run_statistics.routing.clone();
+    let forensic_alerts = motor_monitor_parameters.forensic_alerts;
+    let track_provenance = motor_monitor_parameters.track_provenance;
+    let rule_thresholds = motor_monitor_parameters.rule_thresholds;
+    let cumulative_age_start_time = motor_monitor_parameters
+        .use_cumulative_age
+        .then(|| Duration::from_secs_f64(motor_monitor_parameters.start_time));
+    let window_count = match motor_monitor_parameters.window_kind {
+        WindowKind::TimeMs(_) => None,
+        WindowKind::Count(window_count) => Some(window_count),
+    };
+    let window_sampling_interval =
+        Duration::from_millis(motor_monitor_parameters.window_sampling_interval as u64);
+    let handle = create(move |subscriber| {
+        let (reading_sender, readings) = mpsc::channel();
+        let sensor_listener_parameters = sensor_listener_parameters.clone();
+        let sensor_listener_run = sensor_listener_run.clone();
+        let read_message_pool = read_message_pool.clone();
+        let listener_failure = listener_failure.clone();
+        listen_pool.spawn_ok(async move {
+            accept_sensors(
+                &sensor_listener_parameters,
+                total_number_of_sensors,
+                reading_sender,
+                &sensor_listener_run,
+                &read_message_pool,
+                &listener_failure,
+            )
+        });
+        // Emits the windows at the window sampling interval, like the other models evaluate them
+        let cadence_windows = CadenceWindows::new(
+            window_sampling_interval,
+            Duration::from_millis(window_size_ms),
+        );
+        emit_windows(readings, cadence_windows, |window| {
+            subscriber.next(window).unwrap()
+        });
     })
-    .subscribe_on(read_message_pool)
-    .sliding_window(
-        Duration::from_millis(motor_monitor_parameters.window_sampling_interval as u64),
-        Duration::from_millis(motor_monitor_parameters.window_size_ms),
-        |timed_sensor_message: &SensorMessage| {
-            Duration::from_secs_f64(timed_sensor_message.timestamp)
-        },
-    )
-    .flat_map(move |timed_sensor_messages| {
-        // eprintln!("Messages: {timed_sensor_messages:?}");
+    .subscribe_on(window_pool)
+    .flat_map(move |mut timed_sensor_messages: Vec<SensorMessage>| {
+        if let Some(window_count) = window_count {
+            timed_sensor_messages = truncate_to_count(timed_sensor_messages, window_count);
+        }
+        trace!("Messages: {timed_sensor_messages:?}");
+        if ewmas.is_none() && window_diagnostics::is_enabled() {
+            log_windows(&timed_sensor_messages, window_size_ms);
+        }
+        let tool_wears = tool_wears.clone();
+        let recoveries = recoveries.clone();
+        let window =
+            (forensic_alerts || track_provenance).then(|| Arc::new(timed_sensor_messages.clone()));
+        let ewmas = ewmas.clone();
+        let window_export = window_export.clone();
+        let telemetry = telemetry.clone();
         from_iter(timed_sensor_messages)
             .group_by(|message: &SensorMessage| message.sensor_id)
             .flat_map(move |sensor_messages| {
                 let sensor_id = sensor_messages.key;
+                let scanned_ewmas = ewmas.clone();
+                let ewmas = ewmas.clone();
                 sensor_messages
                     .map(|message: SensorMessage| (message.reading, message.timestamp))
                     .reduce(
                         (0f64, 0f64, 0f64),
-                        |(i, reading, time), (new_reading, new_time)| {
-                            (
-                                i + 1f64,
-                                reading + new_reading as f64,
-                                f64::max(time, new_time),
-                            )
+                        move |(i, reading, time), (new_reading, new_time)| {
+                            profiling::measure(HotPath::Window, || {
+                                if let Some(ewmas) = &scanned_ewmas {
+                                    ewmas.lock().unwrap()[sensor_id as usize]
+                                        .update(new_reading as f64, new_time);
+                                }
+                                (
+                                    i + 1f64,
+                                    reading + new_reading as f64,
+                                    f64::max(time, new_time),
+                                )
+                            })
                         },
                     )
-                    .map(move |(i, sum_reading, max_time)| SensorAverage {
-                        sensor_id,
-                        reading: sum_reading / i,
-                        number_of_values: i as usize,
-                        timestamp: max_time,
+                    .map(move |(i, sum_reading, max_time)| {
+                        profiling::measure(HotPath::Window, || match &ewmas {
+                            None => SensorAverage {
+                                sensor_id,
+                                reading: sum_reading / i,
+                                number_of_values: i as usize,
+                                timestamp: max_time,
+                            },
+                            Some(ewmas) => {
+                                let ewma = ewmas.lock().unwrap()[sensor_id as usize];
+                                SensorAverage {
+                                    sensor_id,
+                                    reading: ewma.get_value().unwrap_or(0f64),
+                                    number_of_values: ewma.get_effective_sample_count(),
+                                    timestamp: max_time,
+                                }
+                            }
+                        })
                     })
             })
             .group_by(|sensor_message| get_motor_id(sensor_message.sensor_id))
             .flat_map(move |motor_group| {
                 let motor_id = motor_group.key;
+                let tool_wears = tool_wears.clone();
+                let recoveries = recoveries.clone();
+                let window = window.clone();
+                let window_export = window_export.clone();
+                let telemetry = telemetry.clone();
                 motor_group
                     .reduce(
                         MotorData::default(),
@@ -197,31 +345,214 @@ fn execute_reactive_streaming_procedure(
                         },
                     )
                     .map(move |motor_data| {
-                        violated_rule(&motor_data).map(|violated_rule| Alert {
-                            time: motor_data.get_time(),
-                            motor_id: motor_id as u16,
-                            failure: violated_rule,
-                        })
+                        let mut span = telemetry.start_span("window_evaluation", || {
+                            vec![
+                                ("motor_id", motor_id.into()),
+                                ("window_end", motor_data.get_time().into()),
+                            ]
+                        });
+                        let mut tool_wears = tool_wears.lock().unwrap();
+                        let failure = profiling::measure(HotPath::Rules, || {
+                            violated_rule(
+                                &motor_data,
+                                &mut tool_wears[motor_id as usize],
+                                cumulative_age_start_time,
+                                &rule_thresholds,
+                            )
+                        });
+                        if window_export.is_enabled() {
+                            motor_data.export(motor_id, &window_export);
+                        }
+                        if let Some(failure) = failure {
+                            span.set_attribute("failure", || failure.to_string().into());
+                        }
+                        // Only complete windows tell whether the motor is healthy
+                        let recovery_alert = match motor_data.contains_all_data() {
+                            true => recoveries.lock().unwrap()[motor_id as usize].update(
+                                motor_id as u16,
+                                failure,
+                                motor_data.get_time(),
+                            ),
+                            false => None,
+                        };
+                        failure
+                            .map(|violated_rule| Alert {
+                                time: motor_data.get_time(),
+                                motor_id: motor_id as u16,
+                                failure: violated_rule,
+                                fingerprint: window
+                                    .as_ref()
+                                    .filter(|_| forensic_alerts)
+                                    .map(|window| get_fingerprint(window, motor_id)),
+                                cleared: false,
+                                sampled_messages: window
+                                    .as_ref()
+                                    .filter(|_| track_provenance)
+                                    .map(|window| get_sampled_messages(window, motor_id))
+                                    .unwrap_or_default(),
+                            })
+                            .map(|alert| motor_data.attach_averages(alert))
+                            .or(recovery_alert.map(DetailedAlert::from))
                     })
             })
     })
     .filter(|alert| alert.is_some())
     .map(|alert| alert.unwrap())
     .subscribe(
-        move |alert| {
-            info!("{alert:?}");
-            let vec: Vec<u8> =
-                to_allocvec_cobs(&alert).expect("Could not write motor monitor alert to Vec<u8>");
-            cloud_server
-                .write_all(&vec)
-                .expect("Could not send motor alert to cloud server");
-            debug!("Sent alert to server");
+        move |detailed_alert| {
+            failure::catch_stage_panic(&pipeline_failure, || {
+                let alert = &detailed_alert.alert;
+                info!("{alert:?}");
+                // Emitted by another stage of the pipeline than the evaluation of its window,
+                // so it is correlated with it by the motor id and alert time instead of nested
+                let _span = alert_telemetry.start_span("alert_emission", || {
+                    vec![
+                        ("motor_id", alert.motor_id.into()),
+                        ("failure", alert.failure.to_string().into()),
+                        ("alert_time", alert.time.into()),
+                        ("cleared", alert.cleared.into()),
+                    ]
+                });
+                if routing.route(alert) == RouteDestination::CloudServer {
+                    if let Err(e) = profiling::measure(HotPath::Alert, || {
+                        cloud_server.write_frame(&alert_codec.encode_detailed(&detailed_alert))
+                    }) {
+                        pipeline_failure.fail(PipelineError::AlertWrite(e));
+                        return;
+                    }
+                }
+                alert_webhooks.notify(&detailed_alert);
+                debug!("Sent alert to server");
+            })
         },
         pool,
-    )
+    );
+    failure::await_pipeline(handle, failure_receiver)
+}
+
+/// Accepts the sensors, reading the messages of each on its own thread of the pool.
+/// The readings end once all sensors are read, as the sender is only held by their readers then.
+fn accept_sensors(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    total_number_of_sensors: usize,
+    reading_sender: Sender<SensorMessage>,
+    monitor_run: &MonitorRun,
+    read_message_pool: &ThreadPool,
+    listener_failure: &PipelineFailure,
+) {
+    let listen_address = format!("0.0.0.0:{}", motor_monitor_parameters.sensor_bind_port);
+    info!("Listening on {}", listen_address);
+    match SensorListener::bind(motor_monitor_parameters) {
+        Ok(listener) => {
+            info!("Bound listener on sensor listener address {listen_address}");
+            for slot in 0..total_number_of_sensors {
+                match listener.accept() {
+                    Ok(stream) => {
+                        let expected_sensor_id = motor_monitor_parameters
+                            .expect_sensor_order
+                            .then_some(slot as u32);
+                        let motor_monitor_parameters = motor_monitor_parameters.clone();
+                        let reading_sender = reading_sender.clone();
+                        let monitor_run = monitor_run.clone();
+                        read_message_pool.spawn_ok(async move {
+                            read_sensor(
+                                stream,
+                                expected_sensor_id,
+                                &motor_monitor_parameters,
+                                reading_sender,
+                                &monitor_run,
+                            )
+                        });
+                    }
+                    // A connection beyond the connection cap is rejected, not the listener failing
+                    Err(e) if e.kind() == ErrorKind::ConnectionRefused => warn!("{e}"),
+                    Err(e) => listener_failure.fail(PipelineError::Accept(e)),
+                }
+            }
+        }
+        Err(e) => listener_failure.fail(PipelineError::Bind(e)),
+    }
+    info!("Bound to all sensors");
 }
 
-fn violated_rule(sensor_average_readings: &MotorData) -> Option<MotorFailure> {
+fn read_sensor(
+    stream: SensorStream,
+    expected_sensor_id: Option<u32>,
+    motor_monitor_parameters: &MotorMonitorParameters,
+    reading_sender: Sender<SensorMessage>,
+    monitor_run: &MonitorRun,
+) {
+    let mut stream = CountingStream::new(stream, monitor_run.run_statistics.sensor_bytes.clone());
+    let mut log_sampler = LogSampler::new(motor_monitor_parameters.log_sample_rate);
+    let mut reading_quarantine = ReadingQuarantine::new(motor_monitor_parameters);
+    stream
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("Could not set read timeout");
+    let mut first_message = true;
+    loop {
+        let sensor_message = match utils::read_sensor_message(
+            &mut stream,
+            motor_monitor_parameters.strict_deserialization,
+            motor_monitor_parameters.compact_wire,
+        ) {
+            Ok(sensor_message) => sensor_message,
+            // Exits through the crash handler, which reports the malformed message
+            Err(ReadError::Malformed) => panic!("{}", ReadError::Malformed),
+            Err(_) => break,
+        };
+        if first_message {
+            if !utils::is_expected_sensor(expected_sensor_id, sensor_message.sensor_id) {
+                break;
+            }
+            first_message = false;
+        }
+        if log_enabled!(Level::Trace) && log_sampler.sample() {
+            trace!("{sensor_message:?}");
+        }
+        let _span = monitor_run.telemetry.start_span("monitor_ingest", || {
+            vec![
+                ("sensor_id", sensor_message.sensor_id.into()),
+                ("motor_id", get_motor_id(sensor_message.sensor_id).into()),
+                ("sensor_timestamp", sensor_message.timestamp.into()),
+            ]
+        });
+        if reading_quarantine.admit(&sensor_message) && reading_sender.send(sensor_message).is_err()
+        {
+            break;
+        }
+    }
+    reading_quarantine.log_counts();
+    info!("Reading from sensor completed");
+}
+
+/// Emits the windows of the readings as they arrive, and the window still pending once all of
+/// them arrived, so that the readings at the end of the run are evaluated as well
+fn emit_windows(
+    readings: Receiver<SensorMessage>,
+    mut cadence_windows: CadenceWindows<SensorMessage>,
+    mut emit: impl FnMut(Vec<SensorMessage>),
+) {
+    for sensor_message in readings {
+        let timestamp = Duration::from_secs_f64(sensor_message.timestamp);
+        cadence_windows
+            .push(timestamp, sensor_message)
+            .into_iter()
+            .for_each(&mut emit);
+    }
+    if let Some(window) = cadence_windows.finish() {
+        emit(window);
+    }
+}
+
+/// Evaluates the rules on the averages of the motor, taking its cumulative age as tool wear
+/// if passed the start time of the run to count it from
+fn violated_rule(
+    sensor_average_readings: &MotorData,
+    tool_wear: &mut ToolWear,
+    cumulative_age_start_time: Option<Duration>,
+    thresholds: &RuleThresholds,
+) -> Option<MotorFailure> {
     if !sensor_average_readings.contains_all_data() {
         trace!("{sensor_average_readings:?}");
         return None;
@@ -236,19 +567,127 @@ fn violated_rule(sensor_average_readings: &MotorData) -> Option<MotorFailure> {
         rotational_speed.reading,
         torque.reading,
     );
-    utils::averages_indicate_failure(
-        air_temperature.reading,
-        process_temperature.reading,
-        rotational_speed.reading,
-        torque.reading,
+    let readings = sensor_average_readings
+        .get_readings()
+        .expect("Motor data misses a sensor");
+    let time = Duration::from_secs_f64(sensor_average_readings.get_time());
+    let wear = tool_wear.update(readings.rotational_speed, time);
+    let wear = match cumulative_age_start_time {
+        Some(start_time) => utils::get_cumulative_age(start_time, time),
+        None => wear,
+    };
+    let failure = utils::averages_indicate_failure(
+        readings,
         (air_temperature.number_of_values
             + process_temperature.number_of_values
             + rotational_speed.number_of_values
             + torque.number_of_values)
             / 4,
+        wear,
+        thresholds,
+    );
+    if let Some(failure) = failure {
+        tool_wear.register_failure(failure, time);
+    }
+    failure
+}
+
+/// Fingerprints of the windows of the sensors of the motor
+fn get_fingerprint(window: &[SensorMessage], motor_id: u32) -> [ChannelFingerprint; 4] {
+    [0, 1, 2, 3].map(|sensor_id| {
+        utils::get_window_fingerprint(
+            window
+                .iter()
+                .filter(|message| message.sensor_id == motor_id.shl(2) + sensor_id),
+        )
+    })
+}
+
+fn get_sampled_messages(window: &[SensorMessage], motor_id: u32) -> Vec<SampledMessageId> {
+    utils::provenance::get_sampled_messages(
+        window
+            .iter()
+            .filter(|message| get_motor_id(message.sensor_id) == motor_id),
     )
 }
 
+/// Size of the windows the cadence windows emit. They only slide by time, so a count-bounded
+/// window is cut from one spanning twice the time its readings take to arrive, which holds all
+/// of them unless the sensor missed more samples than that.
+/// As they are cut from the emitted windows, count-bounded windows follow the same cadence.
+fn get_window_size_ms(motor_monitor_parameters: &MotorMonitorParameters) -> u64 {
+    match motor_monitor_parameters.window_kind {
+        WindowKind::TimeMs(window_size_ms) => window_size_ms,
+        WindowKind::Count(window_count) => {
+            2 * window_count as u64 * motor_monitor_parameters.sensor_sampling_interval as u64
+        }
+    }
+}
+
+/// Keeps the latest readings of each sensor in a window, up to the count of a count-bounded window
+fn truncate_to_count(
+    timed_sensor_messages: Vec<SensorMessage>,
+    window_count: usize,
+) -> Vec<SensorMessage> {
+    let mut readings_per_sensor: HashMap<u32, usize> = HashMap::new();
+    let mut kept: Vec<SensorMessage> = timed_sensor_messages
+        .into_iter()
+        .rev()
+        .filter(|message| {
+            let readings = readings_per_sensor.entry(message.sensor_id).or_default();
+            *readings += 1;
+            *readings <= window_count
+        })
+        .collect();
+    kept.reverse();
+    kept
+}
+
+/// Logs the readings of each sensor in a window with their average. The window is taken to end
+/// with its latest reading and span the window size before it.
+fn log_windows(timed_sensor_messages: &[SensorMessage], window_size_ms: u64) {
+    let Some((window_start, window_end)) = get_window_bounds(timed_sensor_messages, window_size_ms)
+    else {
+        return;
+    };
+    let mut sensor_ids: Vec<u32> = timed_sensor_messages
+        .iter()
+        .map(|message| message.sensor_id)
+        .collect();
+    sensor_ids.sort_unstable();
+    sensor_ids.dedup();
+    for sensor_id in sensor_ids {
+        let (timestamps, readings): (Vec<f64>, Vec<f64>) = timed_sensor_messages
+            .iter()
+            .filter(|message| message.sensor_id == sensor_id)
+            .map(|message| (message.timestamp, message.reading as f64))
+            .unzip();
+        window_diagnostics::log_window(
+            get_motor_id(sensor_id),
+            get_sensor_id(sensor_id),
+            (window_start, window_end),
+            readings.iter().sum::<f64>() / readings.len() as f64,
+            &timestamps,
+        );
+    }
+}
+
+/// Start and end (in s since the epoch) of the window the operator emitted the messages of,
+/// which ends with the latest message, if there is one
+fn get_window_bounds(
+    timed_sensor_messages: &[SensorMessage],
+    window_size_ms: u64,
+) -> Option<(f64, f64)> {
+    let window_end = timed_sensor_messages
+        .iter()
+        .map(|message| message.timestamp)
+        .reduce(f64::max)?;
+    Some((
+        window_end - Duration::from_millis(window_size_ms).as_secs_f64(),
+        window_end,
+    ))
+}
+
 fn get_motor_id(sensor_id: u32) -> u32 {
     sensor_id.shr(2)
 }
@@ -256,3 +695,61 @@ fn get_motor_id(sensor_id: u32) -> u32 {
 fn get_sensor_id(sensor_id: u32) -> u32 {
     sensor_id.bitand(0x0003)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_message(timestamp: f64) -> SensorMessage {
+        SensorMessage {
+            reading: 1.0,
+            sensor_id: 0,
+            timestamp,
+            sample_id: None,
+        }
+    }
+
+    #[test]
+    fn pending_window_is_emitted_once_the_readings_complete() {
+        let windows = Arc::new(Mutex::new(vec![]));
+        let emitted_windows = windows.clone();
+        let pool = ThreadPoolBuilder::new().pool_size(1).create().unwrap();
+        let handle = create(move |subscriber| {
+            let (reading_sender, readings) = mpsc::channel();
+            for timestamp in [0.0, 0.6, 1.1, 1.4] {
+                reading_sender.send(get_message(timestamp)).unwrap();
+            }
+            drop(reading_sender);
+            let cadence_windows =
+                CadenceWindows::new(Duration::from_secs(1), Duration::from_millis(1500));
+            emit_windows(readings, cadence_windows, |window| {
+                subscriber.next(window).unwrap()
+            });
+        })
+        .map(|window: Vec<SensorMessage>| {
+            window
+                .iter()
+                .map(|message| message.timestamp)
+                .collect::<Vec<f64>>()
+        })
+        .subscribe(
+            move |timestamps| emitted_windows.lock().unwrap().push(timestamps),
+            pool,
+        );
+        futures::executor::block_on(handle);
+        // The second window ends at 2 s, after the last reading
+        assert_eq!(
+            *windows.lock().unwrap(),
+            vec![vec![0.0, 0.6], vec![0.6, 1.1, 1.4]]
+        );
+    }
+
+    #[test]
+    fn logged_window_bounds_span_the_configured_window_size() {
+        let messages = [get_message(3.6), get_message(4.9), get_message(4.2)];
+        let (window_start, window_end) = get_window_bounds(&messages, 1500).unwrap();
+        assert!((window_start - 3.4).abs() < 1e-9);
+        assert_eq!(window_end, 4.9);
+        assert_eq!(get_window_bounds(&[], 1500), None);
+    }
+}
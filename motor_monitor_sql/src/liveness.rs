@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use log::{info, warn};
+
+use data_transfer_objects::MotorMonitorParameters;
+use utils::SENSOR_CONNECTION_DELAY;
+
+/// Window sampling intervals without a row after which the sources of a motor are considered stalled
+const STALL_INTERVALS: u32 = 3;
+
+/// Detects a motor whose source readers accepted the connections of its sensors, but do not
+/// produce any rows (e.g. after failing to parse the first line), whose joins then never complete.
+/// SpringQL cannot drop the readers and pumps of the motor to recreate them, so the motor is
+/// flagged as degraded instead of passing as one which produced few alerts.
+pub struct LivenessMonitor {
+    motor_id: usize,
+    stall_timeout: Duration,
+    last_row: Duration,
+    stalled: bool,
+    degraded: bool,
+}
+
+impl LivenessMonitor {
+    pub fn new(motor_id: usize, motor_monitor_parameters: &MotorMonitorParameters) -> Self {
+        // The first row is only due once the sensors connected, passed the startup barrier,
+        // and the first windows closed
+        let first_row = Duration::from_secs_f64(motor_monitor_parameters.start_time)
+            + SENSOR_CONNECTION_DELAY
+            + Duration::from_millis(motor_monitor_parameters.startup_barrier_ms)
+            + Duration::from_millis(
+                motor_monitor_parameters.window_size_ms
+                    + motor_monitor_parameters.window_allowed_delay_ms,
+            );
+        LivenessMonitor {
+            motor_id,
+            stall_timeout: Duration::from_millis(
+                motor_monitor_parameters.window_sampling_interval as u64,
+            ) * STALL_INTERVALS,
+            last_row: first_row,
+            stalled: false,
+            degraded: false,
+        }
+    }
+
+    pub fn register_row(&mut self, now: Duration) {
+        if self.stalled {
+            info!("Motor {} produces rows again", self.motor_id);
+            self.stalled = false;
+        }
+        self.last_row = now;
+    }
+
+    pub fn check(&mut self, now: Duration) {
+        if !self.stalled && now > self.last_row + self.stall_timeout {
+            warn!(
+                "Motor {} produced no rows for {:.1} s, its source readers presumably stalled; as SpringQL cannot recreate them, the motor is flagged as degraded",
+                self.motor_id,
+                (now - self.last_row).as_secs_f64()
+            );
+            self.stalled = true;
+            self.degraded = true;
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+}
@@ -1,6 +1,6 @@
-use std::io::Write;
-use std::net::{TcpListener, TcpStream};
+use std::net::SocketAddr;
 use std::ops::{BitAnd, Shl, Shr};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Arc};
 use std::thread;
@@ -10,28 +10,41 @@ use chrono::NaiveDateTime;
 use env_logger::Target;
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
 use futures::future::RemoteHandle;
-use log::{debug, error, info};
-use postcard::to_allocvec_cobs;
+use log::{debug, error, info, log_enabled, warn, Level};
 use springql::{SpringConfig, SpringPipeline, SpringSinkRow};
 
-use data_transfer_objects::{Alert, BenchmarkDataType, MotorFailure, MotorMonitorParameters};
+use data_transfer_objects::{
+    Alert, AveragingStrategy, BenchmarkDataType, DetailedAlert, HotPath, MemorySample,
+    MotorFailure, MotorMonitorParameters, PortPlan, RouteDestination,
+};
+use liveness::LivenessMonitor;
+use memory::{ConsoleReportReceiver, MemorySampler};
 use scheduler::Scheduler;
+use utils::benchmark::RunStatistics;
+use utils::cloud_connection::AlertCodec;
+use utils::crash::EarlyExit;
+use utils::frame_writer::SharedFrameWriter;
+use utils::profiling;
+use utils::recovery::RecoveryTracker;
+use utils::routing::Routing;
+use utils::units::{Kelvin, NewtonMeter, Rpm, Watt};
+use utils::webhook::AlertWebhooks;
+use utils::window_export::WindowExport;
+use utils::{LogSampler, ToolWear};
+
+mod liveness;
+mod memory;
 
 #[derive(Debug, Copy, Clone, Default)]
 struct MotorData {
     timestamp: f64,
     motor_id: u32,
-    temperature_difference: Option<f32>,
-    rotational_speed: Option<f32>,
-    power: Option<f32>,
-    torque: Option<f32>,
+    temperature_difference: Option<Kelvin>,
+    rotational_speed: Option<Rpm>,
+    power: Option<Watt>,
+    torque: Option<NewtonMeter>,
 }
 
-#[cfg(debug_assertions)]
-const POST_MONITORING: bool = true;
-#[cfg(not(debug_assertions))]
-const POST_MONITORING: bool = false;
-
 impl MotorData {
     fn from_springql_row(row: SpringSinkRow) -> MotorData {
         MotorData {
@@ -39,13 +52,19 @@ impl MotorData {
             motor_id: row
                 .get_not_null_by_index(1)
                 .expect("Could not get motor_id"),
-            temperature_difference: row.get_not_null_by_index::<f32>(2).ok(),
-            rotational_speed: row.get_not_null_by_index::<f32>(3).ok(),
-            power: row.get_not_null_by_index::<f32>(4).ok(),
-            torque: row.get_not_null_by_index::<f32>(5).ok(),
+            temperature_difference: Self::get_column(&row, 2).map(Kelvin),
+            rotational_speed: Self::get_column(&row, 3).map(Rpm),
+            power: Self::get_column(&row, 4).map(Watt),
+            torque: Self::get_column(&row, 5).map(NewtonMeter),
         }
     }
 
+    fn get_column(row: &SpringSinkRow, index: usize) -> Option<f64> {
+        row.get_not_null_by_index::<f32>(index)
+            .ok()
+            .map(|value| value as f64)
+    }
+
     fn is_some(&self) -> bool {
         self.temperature_difference.is_some()
             && self.power.is_some()
@@ -69,37 +88,160 @@ impl MotorData {
     }
 }
 
+/// Connection, webhooks and routing the alerts of the monitor are sent by
+#[derive(Clone)]
+struct AlertSink {
+    cloud_server: SharedFrameWriter,
+    alert_codec: AlertCodec,
+    alert_webhooks: AlertWebhooks,
+    routing: Arc<Routing>,
+}
+
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::builder().target(Target::Stderr).init();
     let arguments: Vec<String> = std::env::args().collect();
     let motor_monitor_parameters: MotorMonitorParameters =
-        utils::get_motor_monitor_parameters(&arguments);
+        utils::arguments::get_motor_monitor_parameters(&arguments);
+    utils::resolved_config::log_resolved_config("motor_monitor_sql", &motor_monitor_parameters);
+    let run_statistics = RunStatistics::new(&motor_monitor_parameters);
+    let early_exit = EarlyExit::new(&motor_monitor_parameters, &run_statistics);
+    utils::crash::install_crash_handler(&motor_monitor_parameters, early_exit.clone());
+    utils::affinity::pin_to_cpu_cores(&motor_monitor_parameters);
+    utils::watchdog::start_memory_watchdog(&motor_monitor_parameters, early_exit);
+    let window_export = Arc::new(WindowExport::new(&motor_monitor_parameters));
+    utils::arguments::reject_i2c_motor_groups(&motor_monitor_parameters);
+    assert!(
+        !motor_monitor_parameters.forensic_alerts,
+        "The SpringQL monitor does not support forensic alerts, as it has no access to the raw windows"
+    );
+    assert_eq!(
+        motor_monitor_parameters.averaging,
+        AveragingStrategy::Window,
+        "The SpringQL monitor only supports averaging windows, which its pumps compute"
+    );
+    assert!(
+        matches!(motor_monitor_parameters.window_kind, WindowKind::TimeMs(_)),
+        "The SpringQL monitor only supports windows framed by time, as SpringQL has no row-based windows"
+    );
     info!("Running procedure");
-    execute_procedure(motor_monitor_parameters);
+    let (degraded_motors, memory_breakdown) = execute_procedure(
+        motor_monitor_parameters.clone(),
+        &run_statistics,
+        window_export,
+    );
     info!("Processing completed");
-    utils::save_benchmark_readings(0, BenchmarkDataType::MotorMonitor);
+    if degraded_motors > 0 {
+        warn!("{degraded_motors} motors degraded during the run");
+    }
+    utils::benchmark::save_benchmark_readings_with_memory_breakdown(
+        0,
+        BenchmarkDataType::MotorMonitor,
+        &motor_monitor_parameters.benchmark_output,
+        &run_statistics,
+        degraded_motors,
+        &memory_breakdown,
+    );
     info!("Saved benchmark readings");
 }
 
-fn execute_procedure(motor_monitor_parameters: MotorMonitorParameters) {
+/// Runs the pipeline, returning the number of degraded motors and the memory samples taken.
+/// The threads started while setting up the pipeline are attributed to SpringQL.
+fn execute_procedure(
+    motor_monitor_parameters: MotorMonitorParameters,
+    run_statistics: &RunStatistics,
+    window_export: Arc<WindowExport>,
+) -> (u32, Vec<MemorySample>) {
     let pool = ThreadPoolBuilder::new()
         .pool_size(motor_monitor_parameters.thread_pool_size)
         .create()
         .unwrap();
-    let pipeline = setup_processing_pipeline(motor_monitor_parameters);
-    let handle_list = evaluate_results(pipeline, motor_monitor_parameters, pool);
+    // Unless they are meant for the web console, the reports of SpringQL are captured locally
+    let console_report_receiver = match motor_monitor_parameters.report_post {
+        true => None,
+        false => ConsoleReportReceiver::bind()
+            .map_err(|e| warn!("Could not bind web console report receiver: {e}"))
+            .ok(),
+    };
+    let threads_before_pipeline = memory::get_thread_ids();
+    let pipeline = setup_processing_pipeline(
+        &motor_monitor_parameters,
+        console_report_receiver
+            .as_ref()
+            .map(|console_report_receiver| console_report_receiver.address),
+    );
+    let engine_threads = memory::get_thread_ids()
+        .difference(&threads_before_pipeline)
+        .copied()
+        .collect();
+    let memory_sampler = MemorySampler::start(
+        motor_monitor_parameters.start_time,
+        engine_threads,
+        console_report_receiver,
+    );
+    let degraded_motors = Arc::new(AtomicU32::new(0));
+    let handle_list = evaluate_results(
+        pipeline,
+        motor_monitor_parameters,
+        pool,
+        degraded_motors.clone(),
+        run_statistics,
+        window_export,
+    );
     wait_on_complete(handle_list);
+    (
+        degraded_motors.load(Ordering::Relaxed),
+        memory_sampler.stop(),
+    )
 }
 
-fn setup_processing_pipeline(
-    motor_monitor_parameters: MotorMonitorParameters,
-) -> Arc<SpringPipeline> {
+/// Window clause of all pumps, which slide by the `window_sampling_interval`
+/// unless another slide is given
+fn get_sliding_window_clause(motor_monitor_parameters: &MotorMonitorParameters) -> String {
+    format!(
+        "SLIDING WINDOW DURATION_MILLIS({}), DURATION_MILLIS({}), DURATION_MILLIS({})",
+        motor_monitor_parameters.window_size_ms,
+        motor_monitor_parameters
+            .window_slide_ms
+            .unwrap_or(motor_monitor_parameters.window_sampling_interval as u64),
+        motor_monitor_parameters.window_allowed_delay_ms
+    )
+}
+
+/// Configuration of the SpringQL pipeline, which posts its reports to the local receiver at
+/// `console_report_address` if there is one, and otherwise to the web console only if
+/// `report_post` is set
+fn get_spring_config(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    console_report_address: Option<SocketAddr>,
+) -> SpringConfig {
     let mut config = SpringConfig::default();
-    config.web_console.enable_report_post = POST_MONITORING;
+    config.web_console.enable_report_post = motor_monitor_parameters.report_post;
+    if let Some(console_report_address) = console_report_address {
+        config.web_console.enable_report_post = true;
+        config.web_console.host = console_report_address.ip().to_string();
+        config.web_console.port = console_report_address.port();
+        config.web_console.report_interval_msec = memory::SAMPLING_INTERVAL.as_millis() as u32;
+    }
     config.worker.n_source_worker_threads =
         motor_monitor_parameters.number_of_tcp_motor_groups as u16 * 4; // one per source
     config.worker.n_generic_worker_threads =
         motor_monitor_parameters.thread_pool_size as u16 - config.worker.n_source_worker_threads; // rest for the other tasks
+    config
+}
+
+fn setup_processing_pipeline(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    console_report_address: Option<SocketAddr>,
+) -> Arc<SpringPipeline> {
+    let config = get_spring_config(motor_monitor_parameters, console_report_address);
+    let port_plan = PortPlan::new(
+        motor_monitor_parameters.sensor_bind_port,
+        motor_monitor_parameters.number_of_tcp_motor_groups as u16,
+        motor_monitor_parameters.request_processing_model,
+    )
+    .unwrap_or_else(|e| panic!("Could not plan the sensor reader ports: {e}"));
+    let sliding_window = get_sliding_window_clause(motor_monitor_parameters);
     let pipeline = Arc::new(SpringPipeline::new(&config).unwrap());
     for motor_id in 0..motor_monitor_parameters.number_of_tcp_motor_groups {
         pipeline
@@ -138,7 +280,7 @@ fn setup_processing_pipeline(
                         PROTOCOL 'TCP',
                         PORT '{}'
                     );
-                    ", motor_monitor_parameters.sensor_listen_address.port() + full_id as u16))
+                    ", port_plan.get_sensor_port(full_id)))
                 .unwrap();
 
             pipeline
@@ -164,11 +306,9 @@ fn setup_processing_pipeline(
                         AVG(sensor_data_{motor_id}_{sensor_id}.reading) AS avg_reading
                     FROM sensor_data_{motor_id}_{sensor_id}
                     GROUP BY min_ts, sensor_id
-                    SLIDING WINDOW DURATION_MILLIS({}), DURATION_MILLIS({}), DURATION_MILLIS(0);
+                    {sliding_window};
                     ",
                     motor_monitor_parameters.window_size_ms,
-                    motor_monitor_parameters.window_size_ms,
-                    motor_monitor_parameters.window_sampling_interval
                 ))
                 .unwrap()
         }
@@ -195,10 +335,9 @@ fn setup_processing_pipeline(
                     FROM sensor_average_{motor_id}_0
                     LEFT OUTER JOIN sensor_average_{motor_id}_1
                         ON sensor_average_{motor_id}_0.min_ts = sensor_average_{motor_id}_1.min_ts
-                    SLIDING WINDOW DURATION_MILLIS({}), DURATION_MILLIS({}), DURATION_MILLIS(0);
+                    {sliding_window};
                     ",
-                motor_monitor_parameters.window_size_ms,
-                motor_monitor_parameters.window_sampling_interval))
+            ))
             .unwrap();
 
         pipeline
@@ -227,10 +366,9 @@ fn setup_processing_pipeline(
                     FROM sensor_average_{motor_id}_2
                     LEFT OUTER JOIN sensor_average_{motor_id}_3
                         ON sensor_average_{motor_id}_2.min_ts = sensor_average_{motor_id}_3.min_ts
-                    SLIDING WINDOW DURATION_MILLIS({}), DURATION_MILLIS({}), DURATION_MILLIS(0);
+                    {sliding_window};
                     ",
-                motor_monitor_parameters.window_size_ms,
-                motor_monitor_parameters.window_sampling_interval))
+            ))
             .unwrap();
 
         pipeline
@@ -248,10 +386,9 @@ fn setup_processing_pipeline(
                     FROM sensor_data_joined_{motor_id}_0_1
                     LEFT OUTER JOIN sensor_data_joined_{motor_id}_2_3
                         ON sensor_data_joined_{motor_id}_0_1.min_ts = sensor_data_joined_{motor_id}_2_3.min_ts
-                    SLIDING WINDOW DURATION_MILLIS({}), DURATION_MILLIS({}), DURATION_MILLIS(0);
+                    {sliding_window};
                     ",
-                motor_monitor_parameters.window_size_ms,
-                motor_monitor_parameters.window_sampling_interval))
+            ))
             .unwrap();
 
         pipeline
@@ -272,22 +409,41 @@ fn evaluate_results(
     pipeline: Arc<SpringPipeline>,
     motor_monitor_parameters: MotorMonitorParameters,
     pool: ThreadPool,
+    degraded_motors: Arc<AtomicU32>,
+    run_statistics: &RunStatistics,
+    window_export: Arc<WindowExport>,
 ) -> Vec<RemoteHandle<()>> {
-    let cloud_server = TcpStream::connect(motor_monitor_parameters.motor_monitor_listen_address)
-        .expect("Could not open connection to cloud server");
+    let (cloud_server, alert_codec) = utils::cloud_connection::connect_to_cloud_server(
+        &motor_monitor_parameters,
+        run_statistics.cloud_server_bytes.clone(),
+    );
+    let alert_sink = AlertSink {
+        cloud_server: SharedFrameWriter::locked(cloud_server),
+        alert_codec,
+        alert_webhooks: AlertWebhooks::start(
+            &motor_monitor_parameters.alert_webhooks,
+            motor_monitor_parameters.detailed_alerts,
+        ),
+        routing: run_statistics.routing.clone(),
+    };
     let mut handle_list = Vec::new();
     for motor_id in 0..motor_monitor_parameters.number_of_tcp_motor_groups {
-        let cloud_server = cloud_server
-            .try_clone()
-            .expect("Could not clone TCP stream");
+        let alert_sink = alert_sink.clone();
+        let window_export = window_export.clone();
         let pipeline = pipeline.clone();
+        let motor_monitor_parameters = motor_monitor_parameters.clone();
+        let degraded_motors = degraded_motors.clone();
         handle_list.push(pool.schedule(move || {
-            handle_pipeline_output(
+            let degraded = handle_pipeline_output(
                 motor_id,
                 pipeline.clone(),
                 &motor_monitor_parameters,
-                cloud_server,
-            )
+                &alert_sink,
+                &window_export,
+            );
+            if degraded {
+                degraded_motors.fetch_add(1, Ordering::Relaxed);
+            }
         }))
     }
     handle_list
@@ -297,24 +453,34 @@ fn handle_pipeline_output(
     motor_id: usize,
     pipeline: Arc<SpringPipeline>,
     motor_monitor_parameters: &MotorMonitorParameters,
-    mut cloud_server: TcpStream,
-) {
+    alert_sink: &AlertSink,
+    window_export: &WindowExport,
+) -> bool {
     let end_time = Duration::from_secs_f64(motor_monitor_parameters.start_time)
         + Duration::from_secs_f64(motor_monitor_parameters.duration);
-    let mut motor_age = utils::get_now_duration();
+    let mut liveness = LivenessMonitor::new(motor_id, motor_monitor_parameters);
+    let mut tool_wear = ToolWear::new(motor_monitor_parameters.wear_model);
+    let mut recovery = RecoveryTracker::new();
     let mut last_message = 0f64;
+    let mut log_sampler = LogSampler::new(motor_monitor_parameters.log_sample_rate);
     loop {
         loop {
             match pipeline.pop_non_blocking(format!("motor_averages_{motor_id}").as_str()) {
                 Ok(Some(row)) => {
+                    liveness.register_row(utils::get_now_duration());
                     let motor_data = MotorData::from_springql_row(row);
                     if last_message != motor_data.timestamp {
                         last_message = motor_data.timestamp;
-                        motor_age = handle_row(
+                        if log_enabled!(Level::Debug) && log_sampler.sample() {
+                            debug!("{motor_data:?}");
+                        }
+                        handle_row(
                             motor_data,
-                            motor_age,
-                            &mut cloud_server,
-                            motor_monitor_parameters.window_size_ms,
+                            &mut tool_wear,
+                            &mut recovery,
+                            alert_sink,
+                            window_export,
+                            motor_monitor_parameters,
                         );
                     }
                 }
@@ -325,50 +491,102 @@ fn handle_pipeline_output(
         thread::sleep(Duration::from_millis(
             (motor_monitor_parameters.sensor_sampling_interval / 2) as u64,
         ));
-        if utils::get_now_duration() >= end_time {
-            return;
+        let now = utils::get_now_duration();
+        if now >= end_time {
+            return liveness.is_degraded();
         }
+        liveness.check(now);
     }
 }
 
 fn handle_row(
     motor_data: MotorData,
-    motor_age: Duration,
-    cloud_server: &mut TcpStream,
-    window_size: u64,
-) -> Duration {
-    debug!("{motor_data:?}");
+    tool_wear: &mut ToolWear,
+    recovery: &mut RecoveryTracker,
+    alert_sink: &AlertSink,
+    window_export: &WindowExport,
+    motor_monitor_parameters: &MotorMonitorParameters,
+) {
     if motor_data.is_some() {
-        if let Some(motor_failure) = utils::relevant_data_indicates_failure(
-            motor_data.temperature_difference.unwrap() as f64,
-            motor_data.rotational_speed.unwrap() as f64,
-            motor_data.power.unwrap() as f64,
-            motor_data.torque.unwrap() as f64
-                * (utils::get_now_duration() - motor_age).as_secs_f64(),
-        ) {
-            send_motor_alert(motor_failure, motor_data, cloud_server, window_size);
-            let now = utils::get_now_duration();
-            return now;
+        let time = Duration::from_secs_f64(motor_data.timestamp);
+        let rotational_speed = motor_data.rotational_speed.unwrap();
+        window_export.export(
+            motor_data.motor_id,
+            motor_data.timestamp,
+            motor_data.temperature_difference.unwrap(),
+            rotational_speed,
+            motor_data.power.unwrap(),
+            motor_data.torque.unwrap(),
+        );
+        let failure = profiling::measure(HotPath::Rules, || {
+            let wear = tool_wear.update(rotational_speed, time);
+            let wear = match motor_monitor_parameters.use_cumulative_age {
+                true => utils::get_cumulative_age(
+                    Duration::from_secs_f64(motor_monitor_parameters.start_time),
+                    time,
+                ),
+                false => wear,
+            };
+            utils::relevant_data_indicates_failure(
+                motor_data.temperature_difference.unwrap(),
+                rotational_speed,
+                motor_data.power.unwrap(),
+                wear * motor_data.torque.unwrap(),
+                &motor_monitor_parameters.rule_thresholds,
+            )
+        });
+        if let Some(recovery_alert) =
+            recovery.update(motor_data.motor_id as u16, failure, motor_data.timestamp)
+        {
+            send_alert(&recovery_alert.into(), alert_sink);
+        }
+        if let Some(motor_failure) = failure {
+            tool_wear.register_failure(motor_failure, time);
+            send_motor_alert(
+                motor_failure,
+                motor_data,
+                alert_sink,
+                motor_monitor_parameters.window_size_ms,
+            );
         }
     }
-    motor_age
 }
 
 fn send_motor_alert(
     motor_failure: MotorFailure,
     motor_data: MotorData,
-    cloud_server: &mut TcpStream,
+    alert_sink: &AlertSink,
     window_size: u64,
 ) {
     let alert = Alert {
         time: motor_data.timestamp,
         motor_id: motor_data.motor_id as u16,
         failure: motor_failure,
+        fingerprint: None,
+        cleared: false,
+        sampled_messages: vec![],
     };
-    info!("{alert:?}");
-    let vec: Vec<u8> =
-        to_allocvec_cobs(&alert).expect("Could not write motor monitor alert to Vec<u8>");
-    let _ = cloud_server.write_all(&vec);
+    // The stream already aggregates the averages the way the rules use them
+    let detailed_alert = utils::detailed_alert::attach_averages(
+        alert,
+        motor_data.temperature_difference.unwrap(),
+        motor_data.rotational_speed.unwrap(),
+        motor_data.power.unwrap(),
+        motor_data.torque.unwrap(),
+    );
+    send_alert(&detailed_alert, alert_sink);
+}
+
+fn send_alert(detailed_alert: &DetailedAlert, alert_sink: &AlertSink) {
+    info!("{:?}", detailed_alert.alert);
+    if alert_sink.routing.route(&detailed_alert.alert) == RouteDestination::CloudServer {
+        let _ = profiling::measure(HotPath::Alert, || {
+            alert_sink
+                .cloud_server
+                .write_frame(&alert_sink.alert_codec.encode_detailed(detailed_alert))
+        });
+    }
+    alert_sink.alert_webhooks.notify(detailed_alert);
     debug!("Sent alert to server");
 }
 
@@ -377,3 +595,71 @@ fn wait_on_complete(handle_list: Vec<RemoteHandle<()>>) {
         futures::executor::block_on(handle);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_parameters(flags: &[&str]) -> MotorMonitorParameters {
+        let start_time = utils::get_now_secs().to_string();
+        let arguments = [
+            "motor_monitor",
+            &start_time,
+            "1",
+            "SpringQL",
+            "1",
+            "0",
+            "1000",
+            "127.0.0.1:0",
+            "127.0.0.1:1",
+            "100",
+            "10",
+            "8",
+            "1.0",
+            "0",
+            "0",
+            "false",
+            "0",
+        ];
+        let arguments: Vec<String> = arguments
+            .iter()
+            .chain(flags)
+            .map(|argument| argument.to_string())
+            .collect();
+        utils::arguments::get_motor_monitor_parameters(&arguments)
+    }
+
+    #[test]
+    fn report_post_flag_controls_the_web_console_post() {
+        let config = get_spring_config(&get_test_parameters(&[]), None);
+        assert!(!config.web_console.enable_report_post);
+        let config = get_spring_config(&get_test_parameters(&["--report-post"]), None);
+        assert!(config.web_console.enable_report_post);
+    }
+
+    #[test]
+    fn reports_are_posted_to_the_local_receiver() {
+        let console_report_address: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let config = get_spring_config(&get_test_parameters(&[]), Some(console_report_address));
+        assert!(config.web_console.enable_report_post);
+        assert_eq!(config.web_console.host, "127.0.0.1");
+        assert_eq!(config.web_console.port, 4000);
+    }
+
+    #[test]
+    fn sliding_window_clause_has_the_configured_slide_and_delay() {
+        assert_eq!(
+            get_sliding_window_clause(&get_test_parameters(&[])),
+            "SLIDING WINDOW DURATION_MILLIS(1000), DURATION_MILLIS(100), DURATION_MILLIS(0)"
+        );
+        assert_eq!(
+            get_sliding_window_clause(&get_test_parameters(&[
+                "--window-slide-ms",
+                "250",
+                "--window-allowed-delay-ms",
+                "40"
+            ])),
+            "SLIDING WINDOW DURATION_MILLIS(1000), DURATION_MILLIS(250), DURATION_MILLIS(40)"
+        );
+    }
+}
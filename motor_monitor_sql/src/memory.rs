@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::{fs, thread};
+
+use log::{debug, warn};
+use procfs::process::{MemoryMap, Process};
+use procfs::ProcResult;
+use serde_json::Value;
+
+use data_transfer_objects::MemorySample;
+
+pub const SAMPLING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Receives the reports SpringQL posts to its web console, keeping the number of bytes
+/// buffered in the queues of the last one
+pub struct ConsoleReportReceiver {
+    pub address: SocketAddr,
+    engine_queue_bytes: Arc<Mutex<Option<u64>>>,
+}
+
+impl ConsoleReportReceiver {
+    pub fn bind() -> io::Result<ConsoleReportReceiver> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let engine_queue_bytes = Arc::new(Mutex::new(None));
+        let reported_queue_bytes = Arc::clone(&engine_queue_bytes);
+        thread::spawn(move || {
+            for report_stream in listener.incoming().flatten() {
+                match read_report(report_stream) {
+                    Ok(queue_bytes) => {
+                        *reported_queue_bytes
+                            .lock()
+                            .expect("Console report lock was poisoned") = Some(queue_bytes)
+                    }
+                    Err(e) => debug!("Could not read web console report: {e}"),
+                }
+            }
+        });
+        Ok(ConsoleReportReceiver {
+            address,
+            engine_queue_bytes,
+        })
+    }
+
+    fn get_engine_queue_bytes(&self) -> Option<u64> {
+        *self
+            .engine_queue_bytes
+            .lock()
+            .expect("Console report lock was poisoned")
+    }
+}
+
+/// Reads a single HTTP request carrying a report, answering it so the connection is not reused
+fn read_report(report_stream: TcpStream) -> io::Result<u64> {
+    let mut reader = BufReader::new(report_stream.try_clone()?);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    (&report_stream)
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+    let report: Value = serde_json::from_slice(&body)?;
+    Ok(sum_total_bytes(&report["queues"]))
+}
+
+/// Sums the `total_bytes` of the row and window queues, without relying on the rest of the layout
+/// of the report
+fn sum_total_bytes(value: &Value) -> u64 {
+    match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| match (key.as_str(), value.as_u64()) {
+                ("total_bytes", Some(total_bytes)) => total_bytes,
+                _ => sum_total_bytes(value),
+            })
+            .sum(),
+        Value::Array(array) => array.iter().map(sum_total_bytes).sum(),
+        _ => 0,
+    }
+}
+
+/// Samples the memory of the process until it is stopped, attributing the stacks of the
+/// `engine_threads` to the engine
+pub struct MemorySampler {
+    stopped: Arc<AtomicBool>,
+    handle: JoinHandle<Vec<MemorySample>>,
+}
+
+impl MemorySampler {
+    pub fn start(
+        start_time: f64,
+        engine_threads: HashSet<i32>,
+        console_report_receiver: Option<ConsoleReportReceiver>,
+    ) -> MemorySampler {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let sampler_stopped = Arc::clone(&stopped);
+        let handle = thread::spawn(move || {
+            let me = Process::myself().expect("Could not get process info handle");
+            let mut memory_samples = vec![];
+            while !sampler_stopped.load(Ordering::Relaxed) {
+                let engine_queue_bytes = console_report_receiver
+                    .as_ref()
+                    .and_then(ConsoleReportReceiver::get_engine_queue_bytes);
+                match sample(&me, start_time, &engine_threads, engine_queue_bytes) {
+                    Ok(memory_sample) => memory_samples.push(memory_sample),
+                    Err(e) => warn!("Could not sample memory: {e}"),
+                }
+                thread::sleep(SAMPLING_INTERVAL);
+            }
+            memory_samples
+        });
+        MemorySampler { stopped, handle }
+    }
+
+    pub fn stop(self) -> Vec<MemorySample> {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.handle.join().expect("Memory sampler panicked")
+    }
+}
+
+pub fn get_thread_ids() -> HashSet<i32> {
+    Process::myself()
+        .and_then(|me| me.tasks())
+        .map(|tasks| tasks.flatten().map(|task| task.tid).collect())
+        .unwrap_or_default()
+}
+
+fn sample(
+    me: &Process,
+    start_time: f64,
+    engine_threads: &HashSet<i32>,
+    engine_queue_bytes: Option<u64>,
+) -> ProcResult<MemorySample> {
+    let rollup = me.smaps_rollup()?;
+    let get_rollup_kb = |key: &str| {
+        rollup
+            .memory_map_rollup
+            .memory_maps
+            .first()
+            .and_then(|memory_map| memory_map.extension.map.get(key))
+            .map_or(0, |bytes| bytes / 1024)
+    };
+    let memory_maps = me.smaps()?.memory_maps;
+    let mut memory_sample = MemorySample {
+        time: utils::get_now_duration().as_secs_f64() - start_time,
+        resident_set_size: get_rollup_kb("Rss"),
+        anonymous: get_rollup_kb("Anonymous"),
+        engine_threads: 0,
+        other_threads: 0,
+        engine_stacks: 0,
+        other_stacks: 0,
+        engine_queue_bytes,
+    };
+    for tid in get_thread_ids() {
+        let stack_kb = get_stack_pointer(tid)
+            .and_then(|stack_pointer| get_mapping(&memory_maps, stack_pointer))
+            .and_then(|memory_map| memory_map.extension.map.get("Rss"))
+            .map_or(0, |bytes| bytes / 1024);
+        if engine_threads.contains(&tid) {
+            memory_sample.engine_threads += 1;
+            memory_sample.engine_stacks += stack_kb;
+        } else {
+            memory_sample.other_threads += 1;
+            memory_sample.other_stacks += stack_kb;
+        }
+    }
+    Ok(memory_sample)
+}
+
+/// Stack pointer of a thread blocked in a system call, which its syscall file lists after the
+/// number and the six arguments of the system call
+fn get_stack_pointer(tid: i32) -> Option<u64> {
+    let syscall = fs::read_to_string(format!("/proc/self/task/{tid}/syscall")).ok()?;
+    let stack_pointer = syscall.split_whitespace().nth(7)?;
+    u64::from_str_radix(stack_pointer.trim_start_matches("0x"), 16).ok()
+}
+
+fn get_mapping(memory_maps: &[MemoryMap], address: u64) -> Option<&MemoryMap> {
+    memory_maps
+        .iter()
+        .find(|memory_map| (memory_map.address.0..memory_map.address.1).contains(&address))
+}
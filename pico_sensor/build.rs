@@ -1,5 +1,7 @@
 use std::{env, fs};
 
+use data_transfer_objects::SensorKind;
+
 fn main() {
     // Tell Cargo that if the given file changes, to rerun this build script.
     println!("cargo:rerun-if-changed=resources/*.txt");
@@ -8,8 +10,30 @@ fn main() {
     let sensor_id = env::var("SENSOR_ID").unwrap_or_else(|_| String::from("1"));
     fs::write(format!("{out_dir}/sensor_id.in",), sensor_id.clone()).unwrap();
 
+    println!("cargo:rerun-if-env-changed=SENSOR_TRANSPORT");
+    let sensor_transport = env::var("SENSOR_TRANSPORT").unwrap_or_else(|_| String::from("I2c"));
+    match sensor_transport.as_str() {
+        "I2c" => {}
+        "UsbCdc" => {
+            if env::var("CARGO_FEATURE_USB").is_err() {
+                panic!("SENSOR_TRANSPORT=UsbCdc requires the usb feature to be enabled");
+            }
+        }
+        _ => panic!("Unknown SENSOR_TRANSPORT {sensor_transport}, expected I2c or UsbCdc"),
+    }
+    fs::write(
+        format!("{out_dir}/sensor_transport.in"),
+        format!("SensorTransport::{sensor_transport}"),
+    )
+    .unwrap();
+
+    let sensor_kind = SensorKind::from_sensor_id(
+        sensor_id
+            .parse()
+            .unwrap_or_else(|_| panic!("SENSOR_ID {sensor_id} is not a sensor id")),
+    );
     fs::copy(
-        format!("resources/{}.txt", sensor_id),
+        format!("resources/{}.txt", sensor_kind.name()),
         format!("{out_dir}/sensor_readings.txt"),
     )
     .unwrap();
@@ -2,12 +2,13 @@
 #![no_std]
 
 use core::mem::size_of;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use heapless::spsc::{Consumer, Producer, Queue};
 
 // Ensure we halt the program on panic (if we don't mention this crate it won't
 // be linked)
 use panic_halt as _;
-use rand::prelude::{IteratorRandom, SmallRng};
-use rand::SeedableRng;
 // The macro for our start-up function
 use rp_pico::entry;
 // A shorter alias for the Hardware Abstraction Layer, which provides
@@ -16,19 +17,85 @@ use rp_pico::hal;
 // A shorter alias for the Peripheral Access Crate, which provides low-level
 // register access
 use rp_pico::hal::pac;
-// Pull in any important traits
-use rp_pico::hal::prelude::*;
+// Support for running the sampling on the second core
+use rp_pico::hal::multicore::{Multicore, Stack};
+use rp_pico::hal::sio::SioFifo;
+// USB device support and the CDC-ACM (serial) class on top of it
+#[cfg(feature = "usb")]
+use usb_device::{class_prelude::UsbBusAllocator, prelude::*};
+#[cfg(feature = "usb")]
+use usbd_serial::SerialPort;
 
 use data_transfer_objects::{SensorMessage, SensorParameters};
+use i2c_bus::ReadingGenerator;
+
+#[allow(dead_code)]
+enum SensorTransport {
+    I2c,
+    UsbCdc,
+}
 
 const SENSOR_ID: u16 = include!(concat!(env!("OUT_DIR"), "/sensor_id.in"));
+const SENSOR_TRANSPORT: SensorTransport =
+    include!(concat!(env!("OUT_DIR"), "/sensor_transport.in"));
 const SENSOR_READINGS: &str = include_str!(concat!(env!("OUT_DIR"), "/sensor_readings.txt"));
+// Holds one less reading than its size
+const RING_BUFFER_SIZE: usize = 64;
+
+// Readings are produced on core 1 and sent on core 0
+static mut READINGS: Queue<SensorMessage, RING_BUFFER_SIZE> = Queue::new();
+static mut CORE1_STACK: Stack<4096> = Stack::new();
+// Handed to core 1 before it is woken via the SIO FIFO
+static mut RUN_PARAMETERS: Option<SensorParameters> = None;
+static PRODUCER_DONE: AtomicBool = AtomicBool::new(true);
+
+/// The link the sensor receives its parameters over and sends its readings on
+trait SensorLink {
+    fn receive(&mut self, buffer: &mut [u8]) -> usize;
+    fn send(&mut self, buffer: &[u8]) -> usize;
+}
+
+impl<Pins> SensorLink for hal::i2c::peripheral::I2CPeripheralEventIterator<pac::I2C0, Pins> {
+    fn receive(&mut self, buffer: &mut [u8]) -> usize {
+        self.read(buffer)
+    }
+
+    fn send(&mut self, buffer: &[u8]) -> usize {
+        self.write(buffer)
+    }
+}
+
+#[cfg(feature = "usb")]
+struct UsbSerial<'a> {
+    device: UsbDevice<'a, hal::usb::UsbBus>,
+    serial: SerialPort<'a, hal::usb::UsbBus>,
+}
+
+#[cfg(feature = "usb")]
+impl SensorLink for UsbSerial<'_> {
+    // Keep polling until a full COBS frame (terminated by a zero byte) has arrived
+    fn receive(&mut self, buffer: &mut [u8]) -> usize {
+        let mut count = 0;
+        while count < buffer.len() && !buffer[..count].contains(&0) {
+            if self.device.poll(&mut [&mut self.serial]) {
+                if let Ok(read) = self.serial.read(&mut buffer[count..]) {
+                    count += read;
+                }
+            }
+        }
+        count
+    }
+
+    fn send(&mut self, buffer: &[u8]) -> usize {
+        self.device.poll(&mut [&mut self.serial]);
+        self.serial.write(buffer).unwrap_or(0)
+    }
+}
 
 #[entry]
 fn main() -> ! {
     // Grab our singleton objects
     let mut pac = pac::Peripherals::take().unwrap();
-    let core = pac::CorePeripherals::take().unwrap();
 
     // Set up the watchdog driver - needed by the clock setup code
     let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
@@ -36,6 +103,7 @@ fn main() -> ! {
     // Configure the clocks
     //
     // The default is to generate a 125 MHz system clock
+    #[cfg_attr(not(feature = "usb"), allow(unused_variables))]
     let clocks = hal::clocks::init_clocks_and_plls(
         rp_pico::XOSC_CRYSTAL_FREQ,
         pac.XOSC,
@@ -49,7 +117,7 @@ fn main() -> ! {
     .unwrap();
 
     // The single-cycle I/O block controls our GPIO pins
-    let sio = hal::Sio::new(pac.SIO);
+    let mut sio = hal::Sio::new(pac.SIO);
 
     // Set the pins up according to their function on this particular board
     let pins = rp_pico::Pins::new(
@@ -59,52 +127,140 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
-    // Configure two pins as being I²C, not GPIO
-    let sda_pin = pins.gpio16.into_mode::<hal::gpio::FunctionI2C>();
-    let scl_pin = pins.gpio17.into_mode::<hal::gpio::FunctionI2C>();
-
-    // Create the I²C driver, using the two pre-configured pins. This will fail
-    // at compile time if the pins are in the wrong mode, or if this I²C
-    // peripheral isn't available on these pins!
-    let mut i2c = hal::I2C::new_peripheral_event_iterator(
-        pac.I2C0,
-        sda_pin,
-        scl_pin,
-        &mut pac.RESETS,
-        SENSOR_ID,
-    );
-    // The delay object lets us wait for specified amounts of time (in
-    // milliseconds)
-    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    // The hardware timer counts microseconds since boot, independent of the i2c polling
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // Core 1 samples the readings into the ring buffer, core 0 sends them once the master asks
+    let (producer, mut consumer) = unsafe { READINGS.split() };
+    let mut multicore = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+    let cores = multicore.cores();
+    cores[1]
+        .spawn(unsafe { &mut CORE1_STACK.mem }, move || {
+            produce_readings(producer, timer)
+        })
+        .expect("Could not start sampling core");
+
+    match SENSOR_TRANSPORT {
+        SensorTransport::I2c => {
+            // Configure two pins as being I²C, not GPIO
+            let sda_pin = pins.gpio16.into_mode::<hal::gpio::FunctionI2C>();
+            let scl_pin = pins.gpio17.into_mode::<hal::gpio::FunctionI2C>();
+
+            // Create the I²C driver, using the two pre-configured pins. This will fail
+            // at compile time if the pins are in the wrong mode, or if this I²C
+            // peripheral isn't available on these pins!
+            let mut i2c = hal::I2C::new_peripheral_event_iterator(
+                pac.I2C0,
+                sda_pin,
+                scl_pin,
+                &mut pac.RESETS,
+                SENSOR_ID,
+            );
+            run_sensor(&mut i2c, &mut consumer, &mut sio.fifo)
+        }
+        #[cfg(feature = "usb")]
+        SensorTransport::UsbCdc => {
+            // Set up the USB driver, clocked by the dedicated 48 MHz USB PLL
+            let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+                pac.USBCTRL_REGS,
+                pac.USBCTRL_DPRAM,
+                clocks.usb_clock,
+                true,
+                &mut pac.RESETS,
+            ));
+            let serial = SerialPort::new(&usb_bus);
+            let device = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+                .manufacturer("Reactive Streaming on the Edge")
+                .product("Pico Sensor")
+                .serial_number("0")
+                .device_class(usbd_serial::USB_CLASS_CDC)
+                .build();
+            run_sensor(
+                &mut UsbSerial { device, serial },
+                &mut consumer,
+                &mut sio.fifo,
+            )
+        }
+        #[cfg(not(feature = "usb"))]
+        SensorTransport::UsbCdc => panic!("USB CDC transport requires the usb feature"),
+    }
+}
+
+/// Receives the parameters of a run, lets core 1 sample the readings, and sends them on
+/// until core 1 finished the run and the ring buffer is drained
+fn run_sensor(
+    link: &mut impl SensorLink,
+    readings: &mut Consumer<'static, SensorMessage, RING_BUFFER_SIZE>,
+    fifo: &mut SioFifo,
+) -> ! {
     loop {
         let mut sensor_parameters_buffer = [0; size_of::<SensorParameters>()];
-        i2c.read(&mut sensor_parameters_buffer);
+        link.receive(&mut sensor_parameters_buffer);
         let sensor_parameters =
             postcard::from_bytes_cobs::<SensorParameters>(&mut sensor_parameters_buffer)
                 .expect("Could not decode parameters");
-        let start_instant = fugit::TimerInstantU32::<1_000_000>::from_ticks(0);
-        let mut rng = SmallRng::seed_from_u64(sensor_parameters.id as u64);
+        unsafe { RUN_PARAMETERS = Some(sensor_parameters) };
+        PRODUCER_DONE.store(false, Ordering::Release);
+        fifo.write_blocking(1);
         let mut message_buffer = [0u8; 32];
-        while start_instant.duration_since_epoch().to_secs() < sensor_parameters.duration as u32 {
-            let sensor_reading: f32 = SENSOR_READINGS
-                .lines()
-                .choose_stable(&mut rng)
-                .expect("Data file iterator is empty")
-                .parse()
-                .expect("Error parsing data file line");
-            let message_bytes = postcard::to_slice_cobs(
-                &SensorMessage {
-                    reading: sensor_reading,
+        loop {
+            // Checked before dequeueing, so no reading enqueued before the run ended is missed
+            let producer_done = PRODUCER_DONE.load(Ordering::Acquire);
+            match readings.dequeue() {
+                Some(message) => {
+                    let message_bytes = postcard::to_slice_cobs(&message, &mut message_buffer)
+                        .expect("Could not encode sensor message to vector");
+                    let mut i = 0;
+                    while i < message_bytes.len() {
+                        i += link.send(&message_bytes[i..]);
+                    }
+                }
+                None if producer_done => break,
+                None => {}
+            }
+        }
+    }
+}
+
+/// Runs on core 1, taking a sample every sampling interval of a run as measured by the
+/// hardware timer, and stamping it with the time since the start of the run
+fn produce_readings(
+    mut readings: Producer<'static, SensorMessage, RING_BUFFER_SIZE>,
+    timer: hal::Timer,
+) -> ! {
+    let pac = unsafe { pac::Peripherals::steal() };
+    let mut sio = hal::Sio::new(pac.SIO);
+    loop {
+        sio.fifo.read_blocking();
+        let sensor_parameters =
+            unsafe { RUN_PARAMETERS.take() }.expect("Sampling core woken without parameters");
+        let mut reading_generator = ReadingGenerator::new(
+            sensor_parameters.id,
+            SENSOR_READINGS,
+            sensor_parameters.warmup_samples,
+            sensor_parameters.random_failure_prob,
+        );
+        let start = timer.get_counter();
+        let end = start + (sensor_parameters.duration * 1_000_000.0) as u64;
+        let sampling_interval = sensor_parameters.sampling_interval as u64 * 1000;
+        let mut next_sample = start;
+        while next_sample < end {
+            while timer.get_counter() < next_sample {}
+            // Readings taken while the sensor is warming up are discarded
+            if let Some(reading) = reading_generator.next_reading() {
+                let milliseconds_since_start = (next_sample - start) / 1000;
+                // If the master falls too far behind, the newest readings are dropped instead of
+                // delaying the following samples
+                let _ = readings.enqueue(SensorMessage {
+                    reading,
                     sensor_id: sensor_parameters.id,
-                },
-                &mut message_buffer,
-            )
-            .expect("Could not encode sensor message to vector");
-            let mut i = 0;
-            while i < message_bytes.len() {
-                i += i2c.write(&message_bytes[i..]);
+                    timestamp: sensor_parameters.start_time
+                        + milliseconds_since_start as f64 / 1000.0,
+                    sample_id: None,
+                });
             }
-            delay.delay_ms(sensor_parameters.sampling_interval);
+            next_sample += sampling_interval;
         }
+        PRODUCER_DONE.store(true, Ordering::Release);
     }
 }
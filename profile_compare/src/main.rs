@@ -0,0 +1,190 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use data_transfer_objects::HotPath;
+
+const HEADER: &str =
+    "model,hot_path,calls,nanoseconds,processed_messages,alerts,ns_per_message,ns_per_alert";
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about)]
+struct Args {
+    /// Hot path breakdowns (`hot_paths.csv`) persisted by the test driver, e.g. one per monitor
+    #[clap(value_parser, required = true)]
+    breakdowns: Vec<PathBuf>,
+
+    /// File the comparison is additionally written to as CSV
+    #[clap(long, value_parser)]
+    output: Option<PathBuf>,
+}
+
+/// Time of a hot path, summed over all breakdowns of a model
+#[derive(Default, Copy, Clone)]
+struct HotPathTotal {
+    calls: u64,
+    nanoseconds: u64,
+}
+
+/// Hot path times of a model, summed over all of its breakdowns, e.g. of repeated runs
+struct ModelProfile {
+    model: String,
+    processed_messages: u64,
+    alerts: u64,
+    hot_paths: [HotPathTotal; 4],
+}
+
+impl ModelProfile {
+    fn get_total_nanoseconds(&self) -> u64 {
+        self.hot_paths
+            .iter()
+            .map(|hot_path| hot_path.nanoseconds)
+            .sum()
+    }
+}
+
+fn main() {
+    if env::args().any(|argument| argument == "--version-full") {
+        println!("{}", data_transfer_objects::build_info!());
+        return;
+    }
+    let args = Args::parse();
+    let mut profiles: Vec<ModelProfile> = vec![];
+    for breakdown in &args.breakdowns {
+        add_breakdown(&mut profiles, breakdown);
+    }
+    print!("{}", get_table(&profiles));
+    if let Some(output) = &args.output {
+        fs::write(output, get_csv(&profiles)).expect("Could not write comparison file");
+    }
+}
+
+/// Adds the hot path times of the breakdown to the profile of its model
+fn add_breakdown(profiles: &mut Vec<ModelProfile>, breakdown: &Path) {
+    let content = fs::read_to_string(breakdown)
+        .unwrap_or_else(|e| panic!("Could not read {}: {e}", breakdown.display()));
+    let mut lines = content.lines();
+    assert_eq!(
+        lines.next(),
+        Some(HEADER),
+        "{} is no hot path breakdown",
+        breakdown.display()
+    );
+    let mut counted_totals = false;
+    for line in lines.filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let [model, hot_path, calls, nanoseconds, processed_messages, alerts, ..] = fields[..]
+        else {
+            panic!("Malformed line in {}: {line}", breakdown.display());
+        };
+        let parse = |field: &str| -> u64 {
+            field
+                .parse()
+                .unwrap_or_else(|_| panic!("Malformed line in {}: {line}", breakdown.display()))
+        };
+        let index = HotPath::ALL
+            .iter()
+            .position(|known_hot_path| known_hot_path.name() == hot_path)
+            .unwrap_or_else(|| panic!("Unknown hot path {hot_path} in {}", breakdown.display()));
+        let profile = match profiles.iter().position(|profile| profile.model == model) {
+            Some(position) => &mut profiles[position],
+            None => {
+                profiles.push(ModelProfile {
+                    model: model.to_string(),
+                    processed_messages: 0,
+                    alerts: 0,
+                    hot_paths: Default::default(),
+                });
+                profiles.last_mut().unwrap()
+            }
+        };
+        // Every line of a breakdown repeats the totals of its run
+        if !counted_totals {
+            profile.processed_messages += parse(processed_messages);
+            profile.alerts += parse(alerts);
+            counted_totals = true;
+        }
+        profile.hot_paths[index].calls += parse(calls);
+        profile.hot_paths[index].nanoseconds += parse(nanoseconds);
+    }
+}
+
+/// Nanoseconds per message of each hot path and its share of the time of all hot paths of the model,
+/// followed by the nanoseconds per alert
+fn get_table(profiles: &[ModelProfile]) -> String {
+    let mut table = String::new();
+    write_section(&mut table, "ns per message", profiles, |profile| {
+        profile.processed_messages
+    });
+    write_section(&mut table, "ns per alert", profiles, |profile| {
+        profile.alerts
+    });
+    table
+}
+
+fn write_section(
+    table: &mut String,
+    title: &str,
+    profiles: &[ModelProfile],
+    get_count: fn(&ModelProfile) -> u64,
+) {
+    write!(table, "{title:<20}").unwrap();
+    for hot_path in HotPath::ALL {
+        write!(table, "{:>20}", hot_path.name()).unwrap();
+    }
+    writeln!(table, "{:>12}", "total").unwrap();
+    for profile in profiles {
+        let total = profile.get_total_nanoseconds();
+        write!(table, "{:<20}", profile.model).unwrap();
+        for hot_path in &profile.hot_paths {
+            let share = match total {
+                0 => 0.0,
+                total => 100.0 * hot_path.nanoseconds as f64 / total as f64,
+            };
+            let cell = format!(
+                "{} ({share:.1} %)",
+                per(hot_path.nanoseconds, get_count(profile))
+            );
+            write!(table, "{cell:>20}").unwrap();
+        }
+        writeln!(table, "{:>12}", per(total, get_count(profile))).unwrap();
+    }
+    writeln!(table).unwrap();
+}
+
+fn get_csv(profiles: &[ModelProfile]) -> String {
+    let mut csv = format!("{HEADER},share\n");
+    for profile in profiles {
+        let total = profile.get_total_nanoseconds();
+        for (hot_path, hot_path_total) in HotPath::ALL.iter().zip(profile.hot_paths) {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{}",
+                profile.model,
+                hot_path.name(),
+                hot_path_total.calls,
+                hot_path_total.nanoseconds,
+                profile.processed_messages,
+                profile.alerts,
+                per(hot_path_total.nanoseconds, profile.processed_messages),
+                per(hot_path_total.nanoseconds, profile.alerts),
+                match total {
+                    0 => String::new(),
+                    total => (hot_path_total.nanoseconds as f64 / total as f64).to_string(),
+                }
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+/// Nanoseconds per message or alert, left empty if there were none
+fn per(nanoseconds: u64, count: u64) -> String {
+    match count {
+        0 => String::new(),
+        count => format!("{:.1}", nanoseconds as f64 / count as f64),
+    }
+}
@@ -1,31 +1,124 @@
 use chrono::NaiveDateTime;
 use env_logger::Target;
-use log::{debug, info};
-use postcard::to_allocvec_cobs;
+use log::{debug, info, warn};
 use rand::prelude::IteratorRandom;
 use rand::rngs::SmallRng;
-use rand::SeedableRng;
-use std::io::{BufRead, Write};
-use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use rand::{Rng, SeedableRng};
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::path::Path;
 use std::str::FromStr;
-use std::time::Duration;
-use std::{fs, thread};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fs, hint, io, thread};
 
-use data_transfer_objects::{RequestProcessingModel, SensorMessage, SensorParameters};
+use data_transfer_objects::{
+    CompactSensorMessage, ReadingSchedule, RequestProcessingModel, RetryPolicy, SensorKind,
+    SensorMessage, SensorParameters, SensorReport, SyntheticSignal, RANDOM_FAILURE_READING,
+};
+use utils::network_usage::{ByteCounter, CountingStream};
+use utils::retry;
+use utils::retry::Retryable;
+use utils::telemetry::Telemetry;
+use utils::transport::SensorStream;
+use utils::SENSOR_CONNECTION_DELAY;
+
+/// Timeout of a connection attempt, extended by the connect jitter window
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time before the startup barrier from which the sensor spins instead of sleeping,
+/// as a sleep may overshoot by a scheduling quantum
+const BARRIER_SPIN_TIME: Duration = Duration::from_millis(2);
+
+/// Where the readings of the sensor come from
+enum SensorSource {
+    /// Readings drawn at random from the plausible ones of the data file
+    File(Vec<f32>),
+    /// Readings of the signal at the time of the sample
+    Synthetic(SyntheticSignal),
+    /// Readings of the scheduled failures of the motor at the time of the sample
+    Scheduled(ReadingSchedule),
+}
+
+impl SensorSource {
+    fn get_reading(
+        &self,
+        number_of_samples: u32,
+        sampling_interval: u32,
+        elapsed: Duration,
+        rng: &mut SmallRng,
+    ) -> f32 {
+        match self {
+            SensorSource::File(readings) => *readings
+                .iter()
+                .choose_stable(rng)
+                .expect("Data file iterator is empty"),
+            // Derived from the number of the sample instead of the clock, so that the readings
+            // do not depend on the scheduling of the sensor
+            SensorSource::Synthetic(signal) => {
+                signal.get_reading(number_of_samples as u64 * sampling_interval as u64)
+            }
+            // Derived from the clock, which the validator measures the detection latency with
+            SensorSource::Scheduled(reading_schedule) => {
+                reading_schedule.get_reading(elapsed.as_secs_f64())
+            }
+        }
+    }
+}
 
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::builder().target(Target::Stderr).init();
     let arguments: Vec<String> = std::env::args().collect();
-    let data_path = get_and_validate_path(&arguments);
 
     let sensor_parameters: SensorParameters = get_sensor_parameters(&arguments);
+    eprintln!("Effective parameters: {sensor_parameters:?}");
+    utils::resolved_config::log_resolved_config(
+        &format!("sensor_{}", sensor_parameters.id),
+        &sensor_parameters,
+    );
     let mut rng = SmallRng::seed_from_u64(sensor_parameters.id as u64);
+    let sensor_source = match (
+        sensor_parameters.synthetic_signal,
+        &sensor_parameters.reading_schedule,
+    ) {
+        (_, Some(reading_schedule)) => SensorSource::Scheduled(reading_schedule.clone()),
+        (Some(signal), None) => SensorSource::Synthetic(signal),
+        (None, None) => SensorSource::File(load_readings(
+            get_and_validate_path(&arguments),
+            &sensor_parameters,
+        )),
+    };
 
-    execute_client_server_procedure(data_path, &sensor_parameters, &mut rng);
+    let telemetry = Telemetry::new("sensor", sensor_parameters.otel_endpoint.as_deref());
+    let monitor_bytes = Arc::new(ByteCounter::new());
+    execute_client_server_procedure(
+        &sensor_source,
+        &sensor_parameters,
+        &mut rng,
+        &telemetry,
+        &monitor_bytes,
+    );
+    report(&monitor_bytes);
+    telemetry.shutdown();
     info!("Finished benchmark run");
 }
 
+/// Writes the bytes exchanged with the monitors and the build of the sensor to stdout,
+/// from where the sensor driver returns them
+fn report(monitor_bytes: &ByteCounter) {
+    let sensor_report = SensorReport {
+        network_usage: monitor_bytes.get_usage(),
+        builds: utils::build_info::get().cloned().into_iter().collect(),
+    };
+    io::stdout()
+        .write_all(
+            &utils::encode_object(&sensor_report)
+                .expect("Could not write sensor report to Vec<u8>"),
+        )
+        .expect("Could not write sensor report to stdout");
+}
+
 fn get_and_validate_path(args: &[String]) -> &Path {
     let path = args.get(1).expect("Did not receive at least 1 argument");
     let path = Path::new(path);
@@ -34,6 +127,23 @@ fn get_and_validate_path(args: &[String]) -> &Path {
     path
 }
 
+/// Loads the plausible readings of the data file up front,
+/// so that a corrupted file fails the sensor before the run starts
+fn load_readings(data_path: &Path, sensor_parameters: &SensorParameters) -> Vec<f32> {
+    let data = fs::read_to_string(data_path).expect("Failure reading sensor data");
+    let readings = utils::quarantine::parse_sensor_data(
+        &data,
+        SensorKind::from_sensor_id(sensor_parameters.id),
+        sensor_parameters.data_column,
+    );
+    assert!(
+        !readings.is_empty(),
+        "Data file {} contains no plausible readings",
+        data_path.display()
+    );
+    readings
+}
+
 fn get_sensor_parameters(arguments: &[String]) -> SensorParameters {
     SensorParameters {
         id: arguments
@@ -67,22 +177,238 @@ fn get_sensor_parameters(arguments: &[String]) -> SensorParameters {
             .expect("Did not receive at least 7 arguments")
             .parse()
             .expect("Could not parse start time successfully"),
+        warmup_samples: arguments
+            .get(8)
+            .expect("Did not receive at least 8 arguments")
+            .parse()
+            .expect("Could not parse warmup samples successfully"),
+        random_failure_prob: arguments
+            .get(9)
+            .expect("Did not receive at least 9 arguments")
+            .parse()
+            .expect("Could not parse random failure probability successfully"),
+        compact_wire: arguments
+            .get(10)
+            .expect("Did not receive at least 10 arguments")
+            .parse()
+            .expect("Could not parse compact wire flag successfully"),
+        transport: arguments
+            .get(11)
+            .expect("Did not receive at least 11 arguments")
+            .parse()
+            .expect("Could not parse transport successfully"),
+        run_id: arguments
+            .get(12)
+            .expect("Did not receive at least 12 arguments")
+            .parse()
+            .expect("Could not parse run id successfully"),
+        data_column: arguments
+            .get(13)
+            .expect("Did not receive at least 13 arguments")
+            .parse()
+            .expect("Could not parse data column successfully"),
+        startup_barrier_ms: arguments
+            .get(14)
+            .expect("Did not receive at least 14 arguments")
+            .parse()
+            .expect("Could not parse startup barrier successfully"),
+        provenance_sample_interval: arguments
+            .get(15)
+            .expect("Did not receive at least 15 arguments")
+            .parse()
+            .expect("Could not parse provenance sample interval successfully"),
+        connect_retry: arguments
+            .get(16)
+            .expect("Did not receive at least 16 arguments")
+            .parse()
+            .expect("Could not parse connect retry policy successfully"),
+        secondary_motor_monitor_listen_address: arguments
+            .get(17)
+            .filter(|argument| !argument.starts_with("--"))
+            .map(|address| {
+                address
+                    .parse()
+                    .expect("Could not parse secondary motor monitor listen address successfully")
+            }),
+        otel_endpoint: arguments
+            .iter()
+            .position(|argument| argument == "--otel-endpoint")
+            .map(|index| {
+                arguments
+                    .get(index + 1)
+                    .expect("Did not receive an endpoint after --otel-endpoint")
+                    .clone()
+            }),
+        connect_jitter_ms: arguments
+            .iter()
+            .position(|argument| argument == "--connect-jitter-ms")
+            .map_or(0, |index| {
+                arguments
+                    .get(index + 1)
+                    .expect("Did not receive a number after --connect-jitter-ms")
+                    .parse()
+                    .expect("Could not parse connect jitter successfully")
+            }),
+        reconnect_retry: arguments
+            .iter()
+            .position(|argument| argument == "--reconnect-retry")
+            .map(|index| {
+                arguments
+                    .get(index + 1)
+                    .expect("Did not receive a retry policy after --reconnect-retry")
+                    .parse()
+                    .expect("Could not parse reconnect retry policy successfully")
+            }),
+        synthetic_signal: arguments
+            .iter()
+            .position(|argument| argument == "--synthetic-signal")
+            .map(|index| {
+                arguments
+                    .get(index + 1)
+                    .expect("Did not receive a signal after --synthetic-signal")
+                    .parse()
+                    .expect("Could not parse synthetic signal successfully")
+            }),
+        reading_schedule: arguments
+            .iter()
+            .position(|argument| argument == "--reading-schedule")
+            .map(|index| {
+                arguments
+                    .get(index + 1)
+                    .expect("Did not receive a schedule after --reading-schedule")
+                    .parse()
+                    .expect("Could not parse reading schedule successfully")
+            }),
     }
 }
 
-fn get_monitor_connection(sensor_parameters: &SensorParameters) -> TcpStream {
-    let connect_to = format!(
+/// Connection to a monitor, remembering its resolved address to reconnect to it
+struct MonitorConnection {
+    address: SocketAddr,
+    stream: CountingStream<SensorStream>,
+}
+
+fn get_monitor_connection(
+    motor_monitor_listen_address: SocketAddr,
+    sensor_parameters: &SensorParameters,
+    monitor_bytes: &Arc<ByteCounter>,
+) -> MonitorConnection {
+    let address = format!(
         "{}:{}",
-        get_monitor_address(sensor_parameters.motor_monitor_listen_address.ip()),
-        sensor_parameters.motor_monitor_listen_address.port(),
+        get_monitor_address(motor_monitor_listen_address.ip()),
+        motor_monitor_listen_address.port(),
     )
     .to_socket_addrs()
     .unwrap()
     .next()
     .unwrap();
-    thread::sleep(Duration::from_secs(2));
-    TcpStream::connect_timeout(&connect_to, Duration::from_secs(5))
-        .unwrap_or_else(|e| panic!("Could not connect to {connect_to:?}: {e}"))
+    MonitorConnection {
+        address,
+        stream: connect(
+            address,
+            sensor_parameters,
+            &sensor_parameters.connect_retry,
+            monitor_bytes.clone(),
+        ),
+    }
+}
+
+fn connect(
+    address: SocketAddr,
+    sensor_parameters: &SensorParameters,
+    retry_policy: &RetryPolicy,
+    monitor_bytes: Arc<ByteCounter>,
+) -> CountingStream<SensorStream> {
+    let stream = retry::retry(
+        retry_policy,
+        || {
+            SensorStream::connect(
+                address,
+                sensor_parameters.transport,
+                sensor_parameters.run_id,
+                // Other sensors may still be connecting within the jitter window
+                CONNECT_TIMEOUT + Duration::from_millis(sensor_parameters.connect_jitter_ms),
+            )
+        },
+        classify_connection_error,
+    )
+    .unwrap_or_else(|e| panic!("Could not connect to {address:?}: {e}"));
+    CountingStream::new(stream, monitor_bytes)
+}
+
+/// Reconnects to the monitor which dropped the connection right away, without the connection
+/// delay or the startup barrier, so that the sensor keeps its sampling cadence and only misses
+/// the samples it would have taken while reconnecting
+fn reconnect(
+    connection: &mut MonitorConnection,
+    sensor_parameters: &SensorParameters,
+    error: io::Error,
+) {
+    let Some(reconnect_retry) = &sensor_parameters.reconnect_retry else {
+        panic!("Could not write sensor reading bytes to monitor: {error:?}");
+    };
+    warn!(
+        "Lost connection to {}, reconnecting: {error}",
+        connection.address
+    );
+    let start = Instant::now();
+    let monitor_bytes = connection.stream.get_counter().clone();
+    connection.stream = connect(
+        connection.address,
+        sensor_parameters,
+        reconnect_retry,
+        monitor_bytes,
+    );
+    let reconnect_time = start.elapsed();
+    warn!(
+        "Reconnected to {} after {} ms, missing about {} samples",
+        connection.address,
+        reconnect_time.as_millis(),
+        reconnect_time.as_millis() / sensor_parameters.sampling_interval.max(1) as u128
+    );
+}
+
+/// Errors of a monitor which is not listening yet, or whose backlog is full
+fn classify_connection_error(error: &io::Error) -> Retryable {
+    match error.kind() {
+        ErrorKind::ConnectionRefused
+        | ErrorKind::ConnectionReset
+        | ErrorKind::TimedOut
+        | ErrorKind::NotFound => Retryable::Retry,
+        _ => Retryable::GiveUp,
+    }
+}
+
+/// Connects to the monitor, and to the secondary one if there is one,
+/// which are sent the same readings
+fn get_monitor_connections(
+    sensor_parameters: &SensorParameters,
+    monitor_bytes: &Arc<ByteCounter>,
+) -> Vec<MonitorConnection> {
+    thread::sleep(
+        SENSOR_CONNECTION_DELAY
+            + utils::get_connect_jitter(sensor_parameters.id, sensor_parameters.connect_jitter_ms),
+    );
+    // Connecting is retried according to the connect retry policy while the monitor is not
+    // listening yet
+    let mut connections = vec![get_monitor_connection(
+        sensor_parameters.motor_monitor_listen_address,
+        sensor_parameters,
+        monitor_bytes,
+    )];
+    info!(
+        "Connected to {}",
+        sensor_parameters.motor_monitor_listen_address
+    );
+    if let Some(secondary_address) = sensor_parameters.secondary_motor_monitor_listen_address {
+        connections.push(get_monitor_connection(
+            secondary_address,
+            sensor_parameters,
+            monitor_bytes,
+        ));
+        info!("Connected to secondary monitor {secondary_address}");
+    }
+    connections
 }
 
 #[cfg(debug_assertions)]
@@ -96,9 +422,11 @@ fn get_monitor_address(_addr: IpAddr) -> String {
 }
 
 fn execute_client_server_procedure(
-    data_path: &Path,
+    sensor_source: &SensorSource,
     sensor_parameters: &SensorParameters,
-    mut rng: &mut SmallRng,
+    rng: &mut SmallRng,
+    telemetry: &Telemetry,
+    monitor_bytes: &Arc<ByteCounter>,
 ) {
     let start_time = Duration::from_secs_f64(sensor_parameters.start_time);
     let end_time = start_time + Duration::from_secs_f64(sensor_parameters.duration);
@@ -107,53 +435,137 @@ fn execute_client_server_procedure(
         (start_time - utils::get_now_duration()).as_secs_f64()
     );
     thread::sleep(start_time - utils::get_now_duration());
-    let mut stream = get_monitor_connection(sensor_parameters);
-    info!(
-        "Connected to {}",
-        sensor_parameters.motor_monitor_listen_address
-    );
+    let mut connections = get_monitor_connections(sensor_parameters, monitor_bytes);
+    if sensor_parameters.startup_barrier_ms > 0 {
+        await_startup_barrier(sensor_parameters);
+    }
+    let mut send_log = create_send_log(sensor_parameters);
+    let mut number_of_samples = 0;
+    let mut sent_messages = 0;
     while utils::get_now_duration() < end_time {
-        let sensor_reading = fs::read(data_path)
-            .expect("Failure reading sensor data")
-            .lines()
-            .choose_stable(&mut rng)
-            .expect("Data file iterator is empty")
-            .expect("Error reading from data file iterator")
-            .parse()
-            .expect("Error parsing data fileline");
-        send_sensor_reading(sensor_parameters, sensor_reading, &mut stream);
+        let sensor_reading = sensor_source.get_reading(
+            number_of_samples,
+            sensor_parameters.sampling_interval,
+            utils::get_now_duration().saturating_sub(start_time),
+            rng,
+        );
+        let sensor_reading =
+            inject_random_failure(sensor_reading, sensor_parameters.random_failure_prob, rng);
+        // Readings taken while the sensor is warming up are discarded
+        if number_of_samples < sensor_parameters.warmup_samples {
+            debug!("Discarding warm-up reading {sensor_reading}");
+        } else {
+            let sample_id = (sensor_parameters.provenance_sample_interval > 0
+                && sent_messages % sensor_parameters.provenance_sample_interval == 0)
+                .then_some(sent_messages);
+            let mut span = telemetry.start_span("sensor_send", || {
+                vec![("sensor_id", sensor_parameters.id.into())]
+            });
+            let message = send_sensor_reading(
+                sensor_parameters,
+                sensor_reading,
+                sample_id,
+                &mut connections,
+            );
+            span.set_attribute("sensor_timestamp", || message.timestamp.into());
+            if let Some(send_log) = send_log.as_mut() {
+                utils::provenance::log_send(send_log, &message);
+            }
+            sent_messages += 1;
+        }
+        number_of_samples += 1;
         thread::sleep(Duration::from_millis(
             sensor_parameters.sampling_interval as u64,
         ))
     }
+    // A send log cut short by a full disk only loses the provenance of some sampled messages
+    if let Some(Err(e)) = send_log.as_mut().map(|send_log| send_log.flush()) {
+        warn!("Could not flush send log: {e}");
+    }
+}
+
+/// Creates the log of the send times of the sampled messages, if messages are sampled
+fn create_send_log(sensor_parameters: &SensorParameters) -> Option<BufWriter<File>> {
+    (sensor_parameters.provenance_sample_interval > 0).then(|| {
+        let path =
+            utils::provenance::get_send_log_path(sensor_parameters.run_id, sensor_parameters.id);
+        BufWriter::new(
+            File::create(&path)
+                .unwrap_or_else(|e| panic!("Could not create send log {}: {e}", path.display())),
+        )
+    })
+}
+
+/// Waits until the startup barrier, at which all sensors of the run take their first sample,
+/// regardless of how long each of them took to connect
+fn await_startup_barrier(sensor_parameters: &SensorParameters) {
+    let barrier = Duration::from_secs_f64(sensor_parameters.start_time)
+        + SENSOR_CONNECTION_DELAY
+        + Duration::from_millis(sensor_parameters.startup_barrier_ms);
+    let now = utils::get_now_duration();
+    if now > barrier {
+        warn!(
+            "Connected {} ms after the startup barrier, sampling right away",
+            (now - barrier).as_millis()
+        );
+        return;
+    }
+    thread::sleep((barrier - now).saturating_sub(BARRIER_SPIN_TIME));
+    while utils::get_now_duration() < barrier {
+        hint::spin_loop();
+    }
+}
+
+/// Replaces the reading with the random failure reading with the given probability.
+/// The generator is only advanced if failures are injected, so runs without them read the same data.
+fn inject_random_failure(sensor_reading: f32, random_failure_prob: f32, rng: &mut SmallRng) -> f32 {
+    if random_failure_prob > 0.0 && rng.gen::<f32>() < random_failure_prob {
+        debug!("Injecting random failure instead of {sensor_reading}");
+        RANDOM_FAILURE_READING
+    } else {
+        sensor_reading
+    }
 }
 
 fn send_sensor_reading(
     sensor_parameters: &SensorParameters,
     sensor_reading: f32,
-    stream: &mut TcpStream,
-) {
+    sample_id: Option<u32>,
+    connections: &mut [MonitorConnection],
+) -> SensorMessage {
     let message = SensorMessage {
         reading: sensor_reading,
         sensor_id: sensor_parameters.id,
         timestamp: utils::get_now_duration().as_secs_f64(),
+        sample_id,
     };
     debug!("Read {sensor_reading} at {}", message.timestamp);
     let vec: Vec<u8> = match sensor_parameters.request_processing_model {
+        RequestProcessingModel::SpringQL => jsonify(message).as_bytes().to_vec(),
+        _ if sensor_parameters.compact_wire => {
+            utils::encode_object(&CompactSensorMessage::from(message))
+                .expect("Could not write sensor reading to Vec<u8>")
+        }
         RequestProcessingModel::ReactiveStreaming => {
-            to_allocvec_cobs(&message).expect("Could not write sensor reading to Vec<u8>")
+            utils::encode_object(&message).expect("Could not write sensor reading to Vec<u8>")
         }
         RequestProcessingModel::ClientServer => {
-            to_allocvec_cobs(&message).expect("Could not write sensor reading to Vec<u8>")
+            utils::encode_object(&message).expect("Could not write sensor reading to Vec<u8>")
         }
         RequestProcessingModel::ObjectOriented => {
-            to_allocvec_cobs(&message).expect("Could not write sensor reading to Vec<u8>")
+            utils::encode_object(&message).expect("Could not write sensor reading to Vec<u8>")
         }
-        RequestProcessingModel::SpringQL => jsonify(message).as_bytes().to_vec(),
     };
-    stream
-        .write_all(&vec)
-        .expect("Could not write sensor reading bytes to TcpStream");
+    for connection in connections {
+        if let Err(e) = connection.stream.write_all(&vec) {
+            reconnect(connection, sensor_parameters, e);
+            connection
+                .stream
+                .write_all(&vec)
+                .expect("Could not write sensor reading bytes to monitor after reconnecting");
+        }
+    }
+    message
 }
 
 fn jsonify(message: SensorMessage) -> String {
@@ -174,3 +586,205 @@ fn to_rfc3339(message: SensorMessage) -> String {
     .unwrap()
     .to_rfc3339()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_transfer_objects::{DataColumn, Transport, Waveform};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+
+    const SAMPLING_INTERVAL_MS: u32 = 10;
+
+    /// Signal whose reading is the number of the sample, for the first thousand samples
+    const SAMPLE_NUMBER_SIGNAL: SyntheticSignal = SyntheticSignal {
+        sensor_kind: SensorKind::AirTemperature,
+        waveform: Waveform::Sawtooth,
+        offset: 0.0,
+        amplitude: 1000.0,
+        period_ms: 2000 * SAMPLING_INTERVAL_MS as u64,
+    };
+
+    /// Parameters of a sensor sampling for `sampling_time` after the connection delay
+    fn get_test_sensor_parameters(
+        motor_monitor_listen_address: SocketAddr,
+        sampling_time: Duration,
+    ) -> SensorParameters {
+        SensorParameters {
+            id: 4,
+            // The sensor sleeps until the start, which must not have passed yet
+            start_time: utils::get_now_secs() + 0.1,
+            duration: (SENSOR_CONNECTION_DELAY + sampling_time).as_secs_f64(),
+            sampling_interval: SAMPLING_INTERVAL_MS,
+            request_processing_model: RequestProcessingModel::ClientServer,
+            motor_monitor_listen_address,
+            warmup_samples: 0,
+            random_failure_prob: 0.0,
+            compact_wire: false,
+            secondary_motor_monitor_listen_address: None,
+            transport: Transport::Tcp,
+            run_id: 1,
+            data_column: DataColumn::Last,
+            startup_barrier_ms: 0,
+            provenance_sample_interval: 0,
+            connect_retry: RetryPolicy::fast_local(),
+            connect_jitter_ms: 0,
+            reconnect_retry: None,
+            otel_endpoint: None,
+            synthetic_signal: Some(SAMPLE_NUMBER_SIGNAL),
+            reading_schedule: None,
+        }
+    }
+
+    /// Readings received over the connection until the sensor closes it
+    fn receive_readings(mut stream: TcpStream) -> Vec<f32> {
+        let mut readings = vec![];
+        while let Ok(message) = utils::read_object::<SensorMessage>(&mut stream) {
+            readings.push(message.reading);
+        }
+        readings
+    }
+
+    /// Numbers of the samples received over the connection until the sensor closes it
+    fn receive_sample_numbers(stream: TcpStream) -> Vec<u32> {
+        receive_readings(stream)
+            .into_iter()
+            .map(|reading| reading.round() as u32)
+            .collect()
+    }
+
+    fn run_sensor(sensor_parameters: &SensorParameters) {
+        execute_client_server_procedure(
+            &SensorSource::Synthetic(SAMPLE_NUMBER_SIGNAL),
+            sensor_parameters,
+            &mut SmallRng::seed_from_u64(0),
+            &Telemetry::default(),
+            &Arc::new(ByteCounter::new()),
+        );
+    }
+
+    #[test]
+    fn warmup_readings_are_not_sent() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sensor_parameters =
+            get_test_sensor_parameters(listener.local_addr().unwrap(), Duration::from_millis(300));
+        sensor_parameters.warmup_samples = 3;
+        let monitor = thread::spawn(move || receive_sample_numbers(listener.accept().unwrap().0));
+
+        run_sensor(&sensor_parameters);
+
+        let sample_numbers = monitor.join().unwrap();
+        assert!(sample_numbers.len() > 3, "{sample_numbers:?}");
+        let expected_sample_numbers: Vec<u32> = (3..3 + sample_numbers.len() as u32).collect();
+        assert_eq!(sample_numbers, expected_sample_numbers);
+    }
+
+    #[test]
+    fn every_reading_is_a_random_failure_with_probability_one() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sensor_parameters =
+            get_test_sensor_parameters(listener.local_addr().unwrap(), Duration::from_millis(200));
+        sensor_parameters.random_failure_prob = 1.0;
+        let monitor = thread::spawn(move || receive_readings(listener.accept().unwrap().0));
+
+        run_sensor(&sensor_parameters);
+
+        let readings = monitor.join().unwrap();
+        assert!(!readings.is_empty());
+        assert!(
+            readings
+                .iter()
+                .all(|reading| *reading == RANDOM_FAILURE_READING),
+            "{readings:?}"
+        );
+    }
+
+    #[test]
+    fn dropped_connection_is_recovered_with_few_missing_samples() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sensor_parameters =
+            get_test_sensor_parameters(listener.local_addr().unwrap(), Duration::from_millis(500));
+        sensor_parameters.reconnect_retry = Some(RetryPolicy::fast_local());
+        let monitor = thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            let sample_numbers_before_drop: Vec<u32> = (0..10)
+                .map(|_| {
+                    utils::read_object::<SensorMessage>(&mut stream)
+                        .unwrap()
+                        .reading
+                        .round() as u32
+                })
+                .collect();
+            stream.shutdown(Shutdown::Both).unwrap();
+            drop(stream);
+            let sample_numbers_after_drop = receive_sample_numbers(listener.accept().unwrap().0);
+            (sample_numbers_before_drop, sample_numbers_after_drop)
+        });
+
+        run_sensor(&sensor_parameters);
+
+        let (before_drop, after_drop) = monitor.join().unwrap();
+        assert_eq!(before_drop, (0..10).collect::<Vec<u32>>());
+        assert!(after_drop.len() > 10, "{after_drop:?}");
+        // Only the samples sent until the sensor noticed the drop are lost
+        let first_after_drop = after_drop[0];
+        assert!(
+            (10..=15).contains(&first_after_drop),
+            "Missed the samples 10 to {first_after_drop}"
+        );
+        let expected_after_drop: Vec<u32> =
+            (first_after_drop..first_after_drop + after_drop.len() as u32).collect();
+        assert_eq!(after_drop, expected_after_drop);
+    }
+
+    #[test]
+    fn sensors_take_their_first_sample_at_the_startup_barrier() {
+        let startup_barrier_ms = 400;
+        let start_time = utils::get_now_secs() + 0.1;
+        let sensors: Vec<_> = [4, 5]
+            .into_iter()
+            .map(|id| {
+                let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                let mut sensor_parameters = get_test_sensor_parameters(
+                    listener.local_addr().unwrap(),
+                    Duration::from_millis(startup_barrier_ms + 200),
+                );
+                sensor_parameters.id = id;
+                sensor_parameters.start_time = start_time;
+                // The sensors connect at different times within the jitter window
+                sensor_parameters.connect_jitter_ms = 200;
+                sensor_parameters.startup_barrier_ms = startup_barrier_ms;
+                let monitor = thread::spawn(move || {
+                    let mut stream = listener.accept().unwrap().0;
+                    let first_message: SensorMessage = utils::read_object(&mut stream).unwrap();
+                    receive_readings(stream);
+                    first_message.timestamp
+                });
+                let sensor = thread::spawn(move || run_sensor(&sensor_parameters));
+                (monitor, sensor)
+            })
+            .collect();
+        let first_timestamps: Vec<f64> = sensors
+            .into_iter()
+            .map(|(monitor, sensor)| {
+                sensor.join().unwrap();
+                monitor.join().unwrap()
+            })
+            .collect();
+        let barrier = start_time
+            + SENSOR_CONNECTION_DELAY.as_secs_f64()
+            + Duration::from_millis(startup_barrier_ms).as_secs_f64();
+        for first_timestamp in &first_timestamps {
+            assert!(
+                *first_timestamp >= barrier,
+                "Sampled {} s before the barrier",
+                barrier - first_timestamp
+            );
+        }
+        let offset = (first_timestamps[0] - first_timestamps[1]).abs();
+        assert!(
+            offset < 0.005,
+            "The first samples are {} ms apart",
+            offset * 1000.0
+        );
+    }
+}
@@ -1,19 +1,31 @@
-use log::{error, info};
-use std::io::Read;
-use std::mem::size_of;
+use log::{error, info, warn};
+use std::io::Write;
 use std::net::{TcpListener, TcpStream};
 use std::ops::BitAnd;
+use std::path::Path;
 use std::process::{Command, Stdio};
-use std::thread;
+use std::sync::Mutex;
+use std::{fs, thread};
 
-use data_transfer_objects::SensorParameters;
+use data_transfer_objects::{
+    AbortRun, NetworkUsage, SensorDriverRequest, SensorKind, SensorParameters, SensorReport,
+};
 
 #[cfg(debug_assertions)]
 const RESOURCE_PATH: &str = "resources";
 #[cfg(not(debug_assertions))]
 const RESOURCE_PATH: &str = "/etc";
+/// Working directory of the sensor processes, which the resource path is relative to
+#[cfg(debug_assertions)]
+const SENSOR_DIRECTORY: &str = "../sensor";
+#[cfg(not(debug_assertions))]
+const SENSOR_DIRECTORY: &str = ".";
+
+/// Run ids and process ids of the running sensors, which are terminated if their run is aborted
+static RUNNING_SENSORS: Mutex<Vec<(u64, u32)>> = Mutex::new(vec![]);
 
 fn main() {
+    utils::build_info::init(data_transfer_objects::build_info!());
     env_logger::init();
     let listener_address = std::env::args().nth(1).expect("no listener address given");
     let listener = TcpListener::bind(listener_address.clone())
@@ -37,37 +49,196 @@ fn main() {
 }
 
 fn start_new_run(mut stream: TcpStream) {
-    let mut data = [0; size_of::<SensorParameters>()];
-    let _read = stream
-        .read(&mut data)
-        .expect("Failure reading data from TcpStream");
-    let sensor_parameters: SensorParameters =
-        postcard::from_bytes(&data).expect("Failure parsing data into SensorParameters");
+    let sensor_parameters = match utils::read_object::<SensorDriverRequest>(&mut stream)
+        .expect("Failure parsing data into SensorDriverRequest")
+    {
+        SensorDriverRequest::ClockProbe => {
+            if let Err(e) = utils::answer_clock_probe(&mut stream) {
+                error!("Could not answer clock probe: {e}");
+            }
+            return;
+        }
+        SensorDriverRequest::Run(sensor_parameters) => *sensor_parameters,
+        SensorDriverRequest::Abort(abort_run) => {
+            abort_sensors(&abort_run);
+            return;
+        }
+    };
+    // Echo the parsed parameters, so the motor driver can verify they arrived intact
+    stream
+        .write_all(
+            &utils::encode_object(&sensor_parameters)
+                .expect("Could not write sensor parameters to Vec<u8>"),
+        )
+        .expect("Could not echo sensor parameters");
     info!(
         "Running sensor {}, motor monitor listen address {}",
         sensor_parameters.id, sensor_parameters.motor_monitor_listen_address
     );
-    create_run_command()
-        .arg(format!(
-            "{}/{}.txt",
-            RESOURCE_PATH,
-            sensor_parameters.id.bitand(0x0003)
-        ))
+    let mut command = create_run_command();
+    command
+        .arg(get_data_file_path(sensor_parameters.id))
         .arg(sensor_parameters.id.to_string())
         .arg(sensor_parameters.duration.to_string())
         .arg(sensor_parameters.sampling_interval.to_string())
         .arg(sensor_parameters.request_processing_model.to_string())
         .arg(sensor_parameters.motor_monitor_listen_address.to_string())
         .arg(sensor_parameters.start_time.to_string())
+        .arg(sensor_parameters.warmup_samples.to_string())
+        .arg(sensor_parameters.random_failure_prob.to_string())
+        .arg(sensor_parameters.compact_wire.to_string())
+        .arg(sensor_parameters.transport.to_string())
+        .arg(sensor_parameters.run_id.to_string())
+        .arg(sensor_parameters.data_column.to_string())
+        .arg(sensor_parameters.startup_barrier_ms.to_string())
+        .arg(sensor_parameters.provenance_sample_interval.to_string())
+        .arg(sensor_parameters.connect_retry.to_string());
+    if let Some(secondary_address) = sensor_parameters.secondary_motor_monitor_listen_address {
+        command.arg(secondary_address.to_string());
+    }
+    if let Some(otel_endpoint) = &sensor_parameters.otel_endpoint {
+        command.arg("--otel-endpoint").arg(otel_endpoint);
+    }
+    if sensor_parameters.connect_jitter_ms > 0 {
+        command
+            .arg("--connect-jitter-ms")
+            .arg(sensor_parameters.connect_jitter_ms.to_string());
+    }
+    if let Some(reconnect_retry) = &sensor_parameters.reconnect_retry {
+        command
+            .arg("--reconnect-retry")
+            .arg(reconnect_retry.to_string());
+    }
+    if let Some(synthetic_signal) = &sensor_parameters.synthetic_signal {
+        command
+            .arg("--synthetic-signal")
+            .arg(synthetic_signal.to_string());
+    }
+    if let Some(reading_schedule) = &sensor_parameters.reading_schedule {
+        command
+            .arg("--reading-schedule")
+            .arg(reading_schedule.to_string());
+    }
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
         .stderr(Stdio::inherit())
-        .output()
+        .spawn()
         .expect("Failure when trying to run sensor program");
+    let sensor = (sensor_parameters.run_id, child.id());
+    RUNNING_SENSORS.lock().unwrap().push(sensor);
+    let output = child.wait_with_output();
+    RUNNING_SENSORS
+        .lock()
+        .unwrap()
+        .retain(|running_sensor| *running_sensor != sensor);
+    let mut output = output.expect("Failure when waiting for sensor program");
+    return_report(&sensor_parameters, &mut output.stdout, &mut stream);
+    if sensor_parameters.provenance_sample_interval > 0 {
+        return_send_log(&sensor_parameters, &mut stream);
+    }
+}
+
+/// Terminates the sensors of the aborted run, an abort of a run without running sensors is ignored
+fn abort_sensors(abort_run: &AbortRun) {
+    let running_sensors = RUNNING_SENSORS.lock().unwrap();
+    let pids: Vec<u32> = running_sensors
+        .iter()
+        .filter(|(run_id, _)| *run_id == abort_run.run_id)
+        .map(|(_, pid)| *pid)
+        .collect();
+    if pids.is_empty() {
+        info!(
+            "Ignoring abort of run {}, which has no running sensors",
+            abort_run.run_id
+        );
+        return;
+    }
+    warn!(
+        "Run {} was aborted ({}), terminating {} sensors",
+        abort_run.run_id,
+        abort_run.reason,
+        pids.len()
+    );
+    for pid in pids {
+        if let Err(e) = utils::signal::terminate(pid) {
+            error!("Could not terminate sensor {pid}: {e}");
+        }
+    }
+}
+
+/// Sends the bytes the sensor exchanged with its monitors and its build, which it wrote to stdout,
+/// together with the build of the sensor driver to the motor driver,
+/// or no bytes and builds if the sensor failed before reporting them
+fn return_report(
+    sensor_parameters: &SensorParameters,
+    sensor_output: &mut [u8],
+    stream: &mut TcpStream,
+) {
+    let mut sensor_report =
+        utils::decode_object::<SensorReport>(sensor_output).unwrap_or_else(|e| {
+            warn!(
+                "Sensor {} did not report its network usage: {e}",
+                sensor_parameters.id
+            );
+            SensorReport {
+                network_usage: NetworkUsage::default(),
+                builds: vec![],
+            }
+        });
+    sensor_report
+        .builds
+        .extend(utils::build_info::get().cloned());
+    if let Err(e) = stream.write_all(
+        &utils::encode_object(&sensor_report).expect("Could not write sensor report to Vec<u8>"),
+    ) {
+        error!("Could not return sensor report: {e}");
+    }
+}
+
+/// Sends the send times of the sampled messages the sensor logged to the motor driver,
+/// and removes the send log
+fn return_send_log(sensor_parameters: &SensorParameters, stream: &mut TcpStream) {
+    let send_log = utils::provenance::read_send_log(sensor_parameters.run_id, sensor_parameters.id);
+    info!(
+        "Returning the send times of {} sampled messages of sensor {}",
+        send_log.len(),
+        sensor_parameters.id
+    );
+    let _ = fs::remove_file(utils::provenance::get_send_log_path(
+        sensor_parameters.run_id,
+        sensor_parameters.id,
+    ));
+    if let Err(e) = stream
+        .write_all(&utils::encode_object(&send_log).expect("Could not write send log to Vec<u8>"))
+    {
+        error!("Could not return send log: {e}");
+    }
+}
+
+fn get_data_file_path(sensor_id: u32) -> String {
+    resolve_data_file_path(Path::new(SENSOR_DIRECTORY), sensor_id)
+}
+
+/// Resolves the data file by the kind of the sensor,
+/// falling back to the file numbered by the lower two bits of its id.
+/// The returned path is relative to the working directory of the sensor.
+fn resolve_data_file_path(sensor_directory: &Path, sensor_id: u32) -> String {
+    let kind_path = format!(
+        "{RESOURCE_PATH}/{}.txt",
+        SensorKind::from_sensor_id(sensor_id).name()
+    );
+    if sensor_directory.join(&kind_path).exists() {
+        kind_path
+    } else {
+        format!("{RESOURCE_PATH}/{}.txt", sensor_id.bitand(0x0003))
+    }
 }
 
 #[cfg(debug_assertions)]
 fn create_run_command() -> Command {
     let mut command = Command::new("cargo");
-    command.current_dir("../sensor").arg("run").arg("--");
+    command.current_dir(SENSOR_DIRECTORY).arg("run").arg("--");
     command
 }
 
@@ -75,3 +246,62 @@ fn create_run_command() -> Command {
 fn create_run_command() -> Command {
     Command::new("sensor")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::{env, process};
+
+    fn create_sensor_directory(name: &str, file_names: &[&str]) -> PathBuf {
+        let sensor_directory = env::temp_dir().join(format!("{name}_{}", process::id()));
+        let resource_directory = sensor_directory.join(RESOURCE_PATH.trim_start_matches('/'));
+        fs::create_dir_all(&resource_directory).unwrap();
+        for file_name in file_names {
+            fs::write(resource_directory.join(file_name), "").unwrap();
+        }
+        sensor_directory
+    }
+
+    #[test]
+    fn data_files_named_by_kind_are_resolved() {
+        let sensor_directory = create_sensor_directory(
+            "kind_named_data_files",
+            &[
+                "air_temperature.txt",
+                "process_temperature.txt",
+                "rotational_speed.txt",
+                "torque.txt",
+            ],
+        );
+        let paths: Vec<String> = (4..8)
+            .map(|sensor_id| resolve_data_file_path(&sensor_directory, sensor_id))
+            .collect();
+        let _ = fs::remove_dir_all(&sensor_directory);
+        assert_eq!(
+            paths,
+            [
+                "air_temperature.txt",
+                "process_temperature.txt",
+                "rotational_speed.txt",
+                "torque.txt"
+            ]
+            .map(|file_name| format!("{RESOURCE_PATH}/{file_name}"))
+        );
+    }
+
+    #[test]
+    fn data_files_named_by_number_are_resolved() {
+        let sensor_directory =
+            create_sensor_directory("numbered_data_files", &["0.txt", "1.txt", "2.txt", "3.txt"]);
+        let paths: Vec<String> = (4..8)
+            .map(|sensor_id| resolve_data_file_path(&sensor_directory, sensor_id))
+            .collect();
+        let _ = fs::remove_dir_all(&sensor_directory);
+        assert_eq!(
+            paths,
+            ["0.txt", "1.txt", "2.txt", "3.txt"]
+                .map(|file_name| format!("{RESOURCE_PATH}/{file_name}"))
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Runs of the components on the local machine, shared by the smoke test and `bench_regress`
+
+use std::fs::File;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener};
+use std::os::unix::fs::symlink;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+use std::{env, fs, io, path, thread};
+
+use log::info;
+
+use data_transfer_objects::SensorKind;
+
+/// Time the whole run may take, not counting the build
+const TIME_LIMIT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const RUN_DURATION_S: u64 = 5;
+const START_DELAY_S: u64 = 5;
+const PACKAGES: [&str; 6] = [
+    "cloud_server",
+    "motor_driver",
+    "sensor_driver",
+    "test_driver",
+    "sensor",
+    "motor_monitor_cs",
+];
+
+/// Steps of the critical path, reported when the smoke test fails
+#[derive(Debug, Copy, Clone)]
+pub enum Stage {
+    Build,
+    StartComponents,
+    TestRun,
+    SensorIngress,
+    BenchmarkData,
+    AlertProtocol,
+    ComponentExits,
+}
+
+pub struct StageFailure {
+    pub stage: Stage,
+    pub reason: String,
+}
+
+impl StageFailure {
+    pub fn new(stage: Stage, reason: impl Into<String>) -> StageFailure {
+        StageFailure {
+            stage,
+            reason: reason.into(),
+        }
+    }
+}
+
+struct Ports {
+    motor_driver_control: u16,
+    motor_driver_status: u16,
+    cloud_server_control: u16,
+    cloud_server_alerts: u16,
+    motor_monitor_sensors: u16,
+    sensor_driver: u16,
+}
+
+impl Ports {
+    /// Lets the OS pick free ports, which are held until all are picked so that they differ
+    fn allocate() -> io::Result<Ports> {
+        let listeners = (0..6)
+            .map(|_| TcpListener::bind("127.0.0.1:0"))
+            .collect::<io::Result<Vec<TcpListener>>>()?;
+        let ports = listeners
+            .iter()
+            .map(|listener| listener.local_addr().map(|address| address.port()))
+            .collect::<io::Result<Vec<u16>>>()?;
+        Ok(Ports {
+            motor_driver_control: ports[0],
+            motor_driver_status: ports[1],
+            cloud_server_control: ports[2],
+            cloud_server_alerts: ports[3],
+            motor_monitor_sensors: ports[4],
+            sensor_driver: ports[5],
+        })
+    }
+}
+
+/// Long-running components, each started in its own process group, which is killed
+/// (including the sensors and the motor monitor they started) when dropped
+#[derive(Default)]
+struct Components {
+    children: Vec<(&'static str, Child)>,
+}
+
+impl Components {
+    fn spawn(&mut self, name: &'static str, command: &mut Command) -> io::Result<()> {
+        let child = command.process_group(0).spawn()?;
+        info!("Started {name} (pid {})", child.id());
+        self.children.push((name, child));
+        Ok(())
+    }
+
+    /// Fails with the first component which already exited
+    fn check_running(&mut self) -> Result<(), String> {
+        for (name, child) in &mut self.children {
+            if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+                return Err(format!("{name} exited with {status}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Components {
+    fn drop(&mut self) {
+        for (name, child) in &mut self.children {
+            kill_process_group(child);
+            info!("Stopped {name}");
+        }
+    }
+}
+
+fn kill_process_group(child: &mut Child) {
+    // The process group id equals the pid of its leader
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+/// Components of a run on the local machine, which are stopped when dropped
+pub struct LocalRun {
+    components: Components,
+    test_run_dir: PathBuf,
+}
+
+impl LocalRun {
+    /// Starts the components from the binaries in the binary directory, with the sensor and the
+    /// motor monitor run from the workspace, and lets the test driver execute a run
+    pub fn execute(
+        workspace: &Path,
+        binary_dir: &Path,
+        run_dir: &Path,
+    ) -> Result<LocalRun, StageFailure> {
+        let deadline = Instant::now() + TIME_LIMIT;
+        let ports = Ports::allocate().map_err(|e| {
+            StageFailure::new(
+                Stage::StartComponents,
+                format!("Could not allocate ports: {e}"),
+            )
+        })?;
+        prepare_run_dir(workspace, run_dir, &ports).map_err(|e| {
+            StageFailure::new(
+                Stage::StartComponents,
+                format!("Could not prepare {}: {e}", run_dir.display()),
+            )
+        })?;
+        let mut components = Components::default();
+        start_components(&mut components, binary_dir, run_dir, &ports, deadline)
+            .map_err(|reason| StageFailure::new(Stage::StartComponents, reason))?;
+        let test_run_dir = run_test_driver(&mut components, binary_dir, run_dir, deadline)
+            .map_err(|reason| StageFailure::new(Stage::TestRun, reason))?;
+        Ok(LocalRun {
+            components,
+            test_run_dir,
+        })
+    }
+
+    /// Run directory the test driver persisted its results to, with those of the motor driver and
+    /// the cloud server in its `motor_driver` and `cloud_server` subdirectories
+    pub fn get_test_run_dir(&self) -> &Path {
+        &self.test_run_dir
+    }
+
+    /// Fails with the first component which exited during the run
+    pub fn check_components(&mut self) -> Result<(), StageFailure> {
+        self.components
+            .check_running()
+            .map_err(|reason| StageFailure::new(Stage::ComponentExits, reason))
+    }
+}
+
+/// Builds every package on its own, the same way the drivers `cargo run` the sensor and the
+/// motor monitor, so that nothing is compiled while the run is timed
+pub fn build_packages(workspace: &Path) -> Result<(), StageFailure> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    for package in PACKAGES {
+        info!("Building {package}");
+        let status = Command::new(&cargo)
+            .current_dir(workspace)
+            .args(["build", "-p", package])
+            .status()
+            .map_err(|e| StageFailure::new(Stage::Build, format!("Could not run cargo: {e}")))?;
+        if !status.success() {
+            return Err(StageFailure::new(
+                Stage::Build,
+                format!("Building {package} exited with {status}"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The components are built into the same directory as the smoke test
+pub fn get_binary_dir() -> PathBuf {
+    env::current_exe()
+        .expect("Could not get path of the smoke test binary")
+        .parent()
+        .expect("Could not get binary directory")
+        .to_path_buf()
+}
+
+/// Mirrors the layout of the workspace the debug builds expect, with generated config files
+/// and links to the sensor, the motor monitor and the sensor readings
+fn prepare_run_dir(workspace: &Path, run_dir: &Path, ports: &Ports) -> io::Result<()> {
+    for component in ["cloud_server", "motor_driver", "test_driver"] {
+        fs::create_dir_all(run_dir.join(component).join("resources"))?;
+    }
+    fs::create_dir_all(run_dir.join("sensor_driver"))?;
+    symlink(workspace.join("sensor"), run_dir.join("sensor"))?;
+    symlink(
+        workspace.join("motor_monitor_cs"),
+        run_dir.join("motor_monitor_cs"),
+    )?;
+    for sensor_id in 0..4 {
+        let file_name = format!("{}.txt", SensorKind::from_sensor_id(sensor_id).name());
+        symlink(
+            workspace
+                .join("test_driver")
+                .join("resources")
+                .join(&file_name),
+            run_dir
+                .join("test_driver")
+                .join("resources")
+                .join(&file_name),
+        )?;
+    }
+    fs::write(
+        run_dir.join("cloud_server/resources/config-debug.toml"),
+        format!(
+            "test_driver_listen_address = \"127.0.0.1:{}\"\n\
+            alert_drain_timeout_ms = 500\n",
+            ports.cloud_server_control
+        ),
+    )?;
+    fs::write(
+        run_dir.join("motor_driver/resources/config-debug.toml"),
+        format!(
+            "test_driver_listen_address = \"127.0.0.1:{}\"\n\
+            status_listen_address = \"127.0.0.1:{}\"\n\
+            max_monitor_restarts = 0\n",
+            ports.motor_driver_control, ports.motor_driver_status
+        ),
+    )?;
+    let sensor_driver_address = format!("\"127.0.0.1:{}\"", ports.sensor_driver);
+    fs::write(
+        run_dir.join("test_driver/resources/config-debug.toml"),
+        format!(
+            "[test_run]\n\
+            start_delay = {START_DELAY_S}\n\
+            benchmark_data_timeout = 20\n\
+            connect_timeout = 10\n\
+            \n\
+            [motor_monitor]\n\
+            sensor_listen_address = \"127.0.0.1:{}\"\n\
+            \n\
+            [motor_driver]\n\
+            test_driver_listen_address = \"127.0.0.1:{}\"\n\
+            status_listen_address = \"127.0.0.1:{}\"\n\
+            sensor_socket_addresses = [{}]\n\
+            \n\
+            [cloud_server]\n\
+            test_driver_listen_address = \"127.0.0.1:{}\"\n\
+            motor_monitor_listen_address = \"127.0.0.1:{}\"\n",
+            ports.motor_monitor_sensors,
+            ports.motor_driver_control,
+            ports.motor_driver_status,
+            [sensor_driver_address.as_str(); 4].join(", "),
+            ports.cloud_server_control,
+            ports.cloud_server_alerts,
+        ),
+    )?;
+    Ok(())
+}
+
+fn start_components(
+    components: &mut Components,
+    binary_dir: &Path,
+    run_dir: &Path,
+    ports: &Ports,
+    deadline: Instant,
+) -> Result<(), String> {
+    components
+        .spawn(
+            "cloud_server",
+            &mut create_command(binary_dir, run_dir, "cloud_server", "info")?,
+        )
+        .map_err(|e| format!("Could not start cloud_server: {e}"))?;
+    components
+        .spawn(
+            "sensor_driver",
+            create_command(binary_dir, run_dir, "sensor_driver", "info")?
+                .arg(format!("127.0.0.1:{}", ports.sensor_driver)),
+        )
+        .map_err(|e| format!("Could not start sensor_driver: {e}"))?;
+    // The motor monitor inherits the log level, logging every sensor message it receives
+    components
+        .spawn(
+            "motor_driver",
+            &mut create_command(
+                binary_dir,
+                run_dir,
+                "motor_driver",
+                "info,motor_monitor_cs=debug",
+            )?,
+        )
+        .map_err(|e| format!("Could not start motor_driver: {e}"))?;
+    for (name, port) in [
+        ("cloud_server", ports.cloud_server_control),
+        ("sensor_driver", ports.sensor_driver),
+        ("motor_driver", ports.motor_driver_control),
+    ] {
+        wait_until_bound(components, port, deadline)
+            .map_err(|reason| format!("{name} did not start listening: {reason}"))?;
+    }
+    Ok(())
+}
+
+/// Runs the binary of the component in its directory of the run, logging to `<component>.log`
+fn create_command(
+    binary_dir: &Path,
+    run_dir: &Path,
+    component: &str,
+    log_level: &str,
+) -> Result<Command, String> {
+    let log_file = File::create(run_dir.join(format!("{component}.log")))
+        .map_err(|e| format!("Could not create log file of {component}: {e}"))?;
+    let mut command = Command::new(binary_dir.join(component));
+    command
+        .current_dir(run_dir.join(component))
+        .env("RUST_LOG", log_level)
+        .stdout(
+            log_file
+                .try_clone()
+                .map_err(|e| format!("Could not clone log file of {component}: {e}"))?,
+        )
+        .stderr(log_file);
+    Ok(command)
+}
+
+/// Waits until the port is taken, without connecting, as that would start a run
+fn wait_until_bound(
+    components: &mut Components,
+    port: u16,
+    deadline: Instant,
+) -> Result<(), String> {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    loop {
+        match TcpListener::bind(address) {
+            Err(e) if e.kind() == ErrorKind::AddrInUse => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+            Ok(listener) => drop(listener),
+        }
+        components.check_running()?;
+        if Instant::now() >= deadline {
+            return Err(format!("{address} not bound within the time limit"));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Lets the test driver execute a run in a run directory below `<run_dir>/runs`, returning it
+fn run_test_driver(
+    components: &mut Components,
+    binary_dir: &Path,
+    run_dir: &Path,
+    deadline: Instant,
+) -> Result<PathBuf, String> {
+    // Resolved against the working directory of each component otherwise
+    let results_dir = path::absolute(run_dir.join("runs"))
+        .map_err(|e| format!("Could not resolve the results directory: {e}"))?;
+    let mut test_driver = create_command(binary_dir, run_dir, "test_driver", "info")?
+        .arg("--results-dir")
+        .arg(&results_dir)
+        .arg("--run-directories")
+        .args([
+            "--duration",
+            &RUN_DURATION_S.to_string(),
+            "--window-size-ms",
+            "1000",
+            "--window-sampling-interval-ms",
+            "1000",
+            "--sensor-sampling-interval-ms",
+            "100",
+            "ClientServer",
+        ])
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("Could not start test_driver: {e}"))?;
+    info!("Started test_driver (pid {})", test_driver.id());
+    let status = wait_for_exit(&mut test_driver, components, deadline);
+    if status.is_err() {
+        kill_process_group(&mut test_driver);
+    }
+    let status = status?;
+    if !status.success() {
+        return Err(format!("test_driver exited with {status}"));
+    }
+    find_test_run_dir(&results_dir)
+        .map_err(|e| format!("Could not find the run directory of the test driver: {e}"))
+}
+
+/// The only run directory in the results directory, as each local run executes a single run
+fn find_test_run_dir(results_dir: &Path) -> io::Result<PathBuf> {
+    let mut test_run_dirs = vec![];
+    for entry in fs::read_dir(results_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && entry.file_name().to_string_lossy().starts_with("run_") {
+            test_run_dirs.push(entry.path());
+        }
+    }
+    match <[PathBuf; 1]>::try_from(test_run_dirs) {
+        Ok([test_run_dir]) => Ok(test_run_dir),
+        Err(test_run_dirs) => Err(io::Error::other(format!(
+            "{} run directories in {}",
+            test_run_dirs.len(),
+            results_dir.display()
+        ))),
+    }
+}
+
+fn wait_for_exit(
+    child: &mut Child,
+    components: &mut Components,
+    deadline: Instant,
+) -> Result<ExitStatus, String> {
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            return Ok(status);
+        }
+        components.check_running()?;
+        if Instant::now() >= deadline {
+            return Err("test_driver did not finish within the time limit".to_string());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
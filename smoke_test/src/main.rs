@@ -0,0 +1,81 @@
+use std::path::Path;
+use std::{env, fs, process};
+
+use env_logger::Env;
+use log::{error, info};
+
+use smoke_test::{build_packages, get_binary_dir, LocalRun, Stage, StageFailure};
+
+fn main() {
+    if env::args().any(|argument| argument == "--version-full") {
+        println!("{}", data_transfer_objects::build_info!());
+        return;
+    }
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("Could not get workspace directory")
+        .to_path_buf();
+    let run_dir = env::temp_dir().join(format!("smoke_test_{}", process::id()));
+    match run_smoke_test(&workspace, &run_dir) {
+        Ok(()) => {
+            info!("Smoke test passed");
+            let _ = fs::remove_dir_all(&run_dir);
+        }
+        Err(failure) => {
+            error!(
+                "Smoke test failed at stage {:?}: {}",
+                failure.stage, failure.reason
+            );
+            error!(
+                "The logs of the components are kept in {}",
+                run_dir.display()
+            );
+            process::exit(1);
+        }
+    }
+}
+
+fn run_smoke_test(workspace: &Path, run_dir: &Path) -> Result<(), StageFailure> {
+    build_packages(workspace)?;
+    let mut local_run = LocalRun::execute(workspace, &get_binary_dir(), run_dir)?;
+    check_sensor_ingress(run_dir)?;
+    check_benchmark_data(local_run.get_test_run_dir())?;
+    check_alert_protocol(local_run.get_test_run_dir())?;
+    local_run.check_components()
+}
+
+fn check_sensor_ingress(run_dir: &Path) -> Result<(), StageFailure> {
+    let log = fs::read_to_string(run_dir.join("motor_driver.log")).unwrap_or_default();
+    match log.contains("SensorMessage {") {
+        true => Ok(()),
+        false => Err(StageFailure::new(
+            Stage::SensorIngress,
+            "The motor monitor did not log any received SensorMessage (see motor_driver.log)",
+        )),
+    }
+}
+
+/// The test driver only persists the benchmark data after parsing it
+fn check_benchmark_data(test_run_dir: &Path) -> Result<(), StageFailure> {
+    match fs::read_to_string(test_run_dir.join("motor_monitor_results.csv")) {
+        Ok(results) if !results.trim().is_empty() => Ok(()),
+        _ => Err(StageFailure::new(
+            Stage::BenchmarkData,
+            "The test driver did not persist any BenchmarkData of the motor monitor (see test_driver.log)",
+        )),
+    }
+}
+
+/// The run may not produce any alert, so only the existence of the files is checked
+fn check_alert_protocol(test_run_dir: &Path) -> Result<(), StageFailure> {
+    for path in ["cloud_server/alert_protocol.csv", "alert_failures.csv"] {
+        if !test_run_dir.join(path).exists() {
+            return Err(StageFailure::new(
+                Stage::AlertProtocol,
+                format!("{path} was not written (see cloud_server.log and test_driver.log)"),
+            ));
+        }
+    }
+    Ok(())
+}
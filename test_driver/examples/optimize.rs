@@ -0,0 +1,72 @@
+//! Runs the ClientServer model with three window configurations and compares their results.
+//! Expects the same environment as the test driver binary (the cloud server and the motor driver
+//! running, started from the test driver directory).
+
+use test_driver::{MonitorOutcome, RunOutcome};
+
+const WINDOW_CONFIGURATIONS: [(&str, &str); 3] =
+    [("1000", "500"), ("3000", "1000"), ("6000", "2000")];
+
+fn main() {
+    env_logger::init();
+    let mut outcomes = vec![];
+    for (window_size_ms, window_sampling_interval_ms) in WINDOW_CONFIGURATIONS {
+        let label = format!("{window_size_ms}/{window_sampling_interval_ms}");
+        let args = test_driver::parse_args([
+            "test_driver",
+            "ClientServer",
+            "--duration",
+            "30",
+            "--window-size-ms",
+            window_size_ms,
+            "--window-sampling-interval-ms",
+            window_sampling_interval_ms,
+            "--results-dir",
+            &format!("optimize_results/{window_size_ms}_{window_sampling_interval_ms}"),
+        ])
+        .unwrap_or_else(|e| e.exit());
+        let config = test_driver::get_config(&args);
+        match test_driver::run_benchmark(args, config, &test_driver::AbortHandle::new()) {
+            Ok(outcome) => outcomes.push((label, outcome)),
+            Err(e) => eprintln!("Run with windows {label} failed: {e}"),
+        }
+    }
+    println!(
+        "{:<12} {:>14} {:>15} {:>13} {:>14}",
+        "window (ms)", "alert failures", "mean delay (s)", "cpu (ticks)", "peak rss (kB)"
+    );
+    for (label, outcome) in &outcomes {
+        print_row(label, outcome);
+    }
+}
+
+fn print_row(label: &str, outcome: &RunOutcome) {
+    let Some(monitor) = outcome.monitors.first() else {
+        return;
+    };
+    let (cpu_time, peak_resident_set_size) = monitor
+        .benchmark_data
+        .as_ref()
+        .map(|benchmark_data| {
+            (
+                (benchmark_data.time_spent_in_user_mode + benchmark_data.time_spent_in_kernel_mode)
+                    .to_string(),
+                benchmark_data.peak_resident_set_size.to_string(),
+            )
+        })
+        .unwrap_or(("-".to_string(), "-".to_string()));
+    println!(
+        "{label:<12} {:>14} {:>15} {cpu_time:>13} {peak_resident_set_size:>14}",
+        monitor.get_alert_failures(),
+        get_mean_delay(monitor)
+            .map(|mean_delay| format!("{mean_delay:.3}"))
+            .unwrap_or("-".to_string()),
+    );
+}
+
+fn get_mean_delay(monitor: &MonitorOutcome) -> Option<f64> {
+    if monitor.alerts.is_empty() {
+        return None;
+    }
+    Some(monitor.alerts.iter().map(|(_, delay)| delay).sum::<f64>() / monitor.alerts.len() as f64)
+}
@@ -0,0 +1,167 @@
+use std::io::Write;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use log::{error, info, warn};
+
+use data_transfer_objects::AbortRun;
+
+use crate::run_directory;
+
+/// Run in progress, which is aborted over the control connections of its components
+struct AbortableRun {
+    run_id: u64,
+    /// Directory the results of the run are persisted to, which is marked as aborted
+    results_directory: PathBuf,
+    control_connections: Vec<(&'static str, TcpStream)>,
+    abort_reason: Option<String>,
+}
+
+/// Aborts the run of `run_benchmark` it is passed to, while that run is in progress.
+/// A clone aborts the same run, e.g. from the handler of an interrupt; a handle is meant for one
+/// run at a time.
+#[derive(Clone, Default)]
+pub struct AbortHandle {
+    run: Arc<Mutex<Option<AbortableRun>>>,
+}
+
+impl AbortHandle {
+    pub fn new() -> AbortHandle {
+        AbortHandle::default()
+    }
+
+    /// Makes the run the one `abort` aborts
+    pub(crate) fn begin(&self, run_id: u64, results_directory: &Path) {
+        *self.run.lock().unwrap() = Some(AbortableRun {
+            run_id,
+            results_directory: results_directory.to_path_buf(),
+            control_connections: vec![],
+            abort_reason: None,
+        });
+    }
+
+    /// Adds the control connection of a component of the run, which an abort is sent over.
+    /// The components only watch for an abort once they received the start of the run.
+    pub(crate) fn register(&self, component: &'static str, control_connection: &TcpStream) {
+        let mut run = self.run.lock().unwrap();
+        let Some(run) = run.as_mut() else {
+            return;
+        };
+        match control_connection.try_clone() {
+            Ok(control_connection) => run
+                .control_connections
+                .push((component, control_connection)),
+            Err(e) => {
+                warn!("Could not clone the connection to {component}, it cannot be aborted: {e}")
+            }
+        }
+    }
+
+    /// Ends the run, returning the reason of its abort if it was aborted
+    pub(crate) fn end(&self) -> Option<String> {
+        self.run
+            .lock()
+            .unwrap()
+            .take()
+            .and_then(|run| run.abort_reason)
+    }
+
+    /// Sends an abort of the run in progress to its components and marks its results as aborted,
+    /// returning its id.
+    /// Without a run in progress, or if the run was aborted before, nothing is sent.
+    pub fn abort(&self, reason: &str) -> Option<u64> {
+        let mut run = self.run.lock().unwrap();
+        let Some(run) = run.as_mut() else {
+            info!("Ignoring abort, as no run is in progress");
+            return None;
+        };
+        if run.abort_reason.is_some() {
+            return Some(run.run_id);
+        }
+        warn!("Aborting run {}: {reason}", run.run_id);
+        let abort_run = AbortRun {
+            run_id: run.run_id,
+            reason: reason.to_string(),
+        };
+        let frame = utils::encode_object(&abort_run).expect("Could not write abort to bytes");
+        for (component, control_connection) in &mut run.control_connections {
+            // The component may have closed the connection already, e.g. after crashing
+            if let Err(e) = control_connection.write_all(&frame) {
+                error!("Could not send abort to {component}: {e}");
+            }
+        }
+        run_directory::mark_aborted(&run.results_directory, reason);
+        run.abort_reason = Some(reason.to_string());
+        Some(run.run_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::{env, fs, process};
+
+    use super::*;
+
+    /// Control connection of a component, and the end of the test driver registered with the run
+    fn connect() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let test_driver_end = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (component_end, _) = listener.accept().unwrap();
+        (component_end, test_driver_end)
+    }
+
+    fn create_results_directory(name: &str) -> PathBuf {
+        let results_directory =
+            env::temp_dir().join(format!("test_driver_abort_{name}_{}", process::id()));
+        let _ = fs::remove_dir_all(&results_directory);
+        fs::create_dir_all(&results_directory).unwrap();
+        results_directory
+    }
+
+    #[test]
+    fn abort_is_sent_to_the_components_of_the_run() {
+        let results_directory = create_results_directory("sent");
+        let abort_handle = AbortHandle::new();
+        abort_handle.begin(1, &results_directory);
+        let (mut component_end, test_driver_end) = connect();
+        abort_handle.register("motor_driver", &test_driver_end);
+        assert_eq!(abort_handle.clone().abort("interrupted"), Some(1));
+        let abort_run: AbortRun = utils::read_object(&mut component_end).unwrap();
+        assert_eq!(abort_run.run_id, 1);
+        assert_eq!(abort_run.reason, "interrupted");
+        assert_eq!(
+            fs::read_to_string(results_directory.join("run_status.txt")).unwrap(),
+            "aborted: interrupted"
+        );
+        // Only the first reason is kept
+        abort_handle.abort("timed out");
+        assert_eq!(abort_handle.end().as_deref(), Some("interrupted"));
+        fs::remove_dir_all(results_directory).unwrap();
+    }
+
+    #[test]
+    fn abort_does_not_affect_the_run_of_another_handle() {
+        let results_directory = create_results_directory("other");
+        let abort_handle = AbortHandle::new();
+        let other_abort_handle = AbortHandle::new();
+        abort_handle.begin(1, &results_directory);
+        other_abort_handle.begin(2, &results_directory);
+        assert_eq!(other_abort_handle.abort("interrupted"), Some(2));
+        assert_eq!(abort_handle.end(), None);
+        assert_eq!(other_abort_handle.end().as_deref(), Some("interrupted"));
+        fs::remove_dir_all(results_directory).unwrap();
+    }
+
+    #[test]
+    fn abort_after_the_end_of_the_run_is_ignored() {
+        let results_directory = create_results_directory("ended");
+        let abort_handle = AbortHandle::new();
+        abort_handle.begin(1, &results_directory);
+        assert_eq!(abort_handle.end(), None);
+        assert_eq!(abort_handle.abort("interrupted"), None);
+        assert!(!results_directory.join("run_status.txt").exists());
+        fs::remove_dir_all(results_directory).unwrap();
+    }
+}
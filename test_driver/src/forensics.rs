@@ -0,0 +1,103 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use data_transfer_objects::Alert;
+
+/// Cause of the primary and secondary monitor disagreeing on an alert
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Cause {
+    /// The windows the alerts were evaluated on differ
+    DifferentData,
+    /// The windows are equal, but the monitors detected different failures in them
+    DifferentComputation,
+    /// Only one of the monitors alerted, so there are no windows of the other one to compare to
+    Unmatched,
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Cause::DifferentData => write!(f, "different_data"),
+            Cause::DifferentComputation => write!(f, "different_computation"),
+            Cause::Unmatched => write!(f, "unmatched"),
+        }
+    }
+}
+
+pub struct Disagreement {
+    pub primary_alert: Option<Alert>,
+    pub secondary_alert: Option<Alert>,
+    pub cause: Cause,
+}
+
+/// Pairs the alerts of the two monitors raised for the same motor within the tolerance.
+/// Pairs of the same failure agree, the others are classified by the fingerprints of the
+/// windows they were evaluated on, preferring a counterpart with the same windows.
+pub fn get_disagreements(
+    primary_alerts: &[Alert],
+    secondary_alerts: &[Alert],
+    tolerance: f64,
+) -> Vec<Disagreement> {
+    let is_close = |primary_alert: &Alert, secondary_alert: &Alert| {
+        primary_alert.motor_id == secondary_alert.motor_id
+            && (primary_alert.time - secondary_alert.time).abs() <= tolerance
+    };
+    let mut unmatched_primary_alerts: Vec<&Alert> = vec![];
+    let mut unmatched_secondary_alerts: Vec<&Alert> = secondary_alerts.iter().collect();
+    for primary_alert in primary_alerts {
+        match unmatched_secondary_alerts
+            .iter()
+            .position(|secondary_alert| {
+                is_close(primary_alert, secondary_alert)
+                    && primary_alert.failure == secondary_alert.failure
+            }) {
+            Some(index) => {
+                unmatched_secondary_alerts.remove(index);
+            }
+            None => unmatched_primary_alerts.push(primary_alert),
+        }
+    }
+    let mut disagreements = vec![];
+    for primary_alert in unmatched_primary_alerts {
+        let counterpart = unmatched_secondary_alerts
+            .iter()
+            .position(|secondary_alert| {
+                is_close(primary_alert, secondary_alert)
+                    && primary_alert.fingerprint == secondary_alert.fingerprint
+            })
+            .or_else(|| {
+                unmatched_secondary_alerts
+                    .iter()
+                    .position(|secondary_alert| is_close(primary_alert, secondary_alert))
+            });
+        disagreements.push(match counterpart {
+            Some(index) => {
+                let secondary_alert = unmatched_secondary_alerts.remove(index);
+                Disagreement {
+                    primary_alert: Some(primary_alert.clone()),
+                    secondary_alert: Some(secondary_alert.clone()),
+                    cause: if primary_alert.fingerprint == secondary_alert.fingerprint {
+                        Cause::DifferentComputation
+                    } else {
+                        Cause::DifferentData
+                    },
+                }
+            }
+            None => Disagreement {
+                primary_alert: Some(primary_alert.clone()),
+                secondary_alert: None,
+                cause: Cause::Unmatched,
+            },
+        });
+    }
+    disagreements.extend(
+        unmatched_secondary_alerts
+            .into_iter()
+            .map(|secondary_alert| Disagreement {
+                primary_alert: None,
+                secondary_alert: Some(secondary_alert.clone()),
+                cause: Cause::Unmatched,
+            }),
+    );
+    disagreements
+}
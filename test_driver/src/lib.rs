@@ -0,0 +1,2280 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt::Formatter;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, fs, io, str, thread};
+
+use clap::builder::TypedValueParser;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+
+pub use abort::AbortHandle;
+pub use data_transfer_objects::{
+    Alert, AlertWebhook, AveragingStrategy, BenchmarkData, BuildInfo, CrashReport, DataColumn,
+    MotorFailure, RequestProcessingModel, RouteRule, RunState, Transport,
+};
+use data_transfer_objects::{
+    AlertWithDelay, ClockOffset, CloudServerRunParameters, HotPathProfile, MemorySample,
+    MonitorBenchmarkData, MotorDriverRunParameters, NetworkConfig, NetworkUsage, ParameterAck,
+    PortPlan, PortPlanError, RetryPolicy, RouteCount, RuleThresholds, RunStart, SampledMessageSend,
+    ScheduledFailure, SensorClockReport, SensorReport, SyntheticSignal, TimeScale, ToolChange,
+    WearModel, WindowKind, ABORTED_LABEL, ALERT_BYTES_LABEL, DISK_FULL_GAP_LABEL,
+    DROPPED_ALERTS_LABEL, NETWORK_USAGE_LABEL, UNPERSISTED_ALERTS_LABEL, UNWRITTEN_ALERTS_LABEL,
+};
+use run_directory::RunDirectoryCleanup;
+use utils::disk_writer::DiskWriter;
+pub use validator::{Accuracy, AlertMatching, Detection};
+
+mod abort;
+mod forensics;
+mod profile;
+mod provenance;
+mod run_directory;
+mod validator;
+
+#[cfg(debug_assertions)]
+const CONFIG_PATH: &str = "resources/config-debug.toml";
+/// Where the bench executor persists the network config when not given its run's one
+const NETWORK_CONFIG_PATH: &str = "../network_config.toml";
+#[cfg(debug_assertions)]
+const MONITOR_IP: &str = "127.0.0.1";
+#[cfg(not(debug_assertions))]
+const MONITOR_IP: &str = "192.168.178.51";
+/// Room for the backtrace of a crash report and the memory samples next to the benchmark data
+const MAX_BENCHMARK_DATA_SIZE: usize = 1024 * 1024;
+/// Room for the send times of the sampled messages of all sensors
+const MAX_SEND_LOGS_SIZE: usize = 16 * 1024 * 1024;
+const STATUS_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about)]
+pub struct RunArgs {
+    /// Number of motor groups connected via tcp
+    #[clap(long, value_parser, default_value_t = 1, short)]
+    pub motor_groups_tcp: u16,
+
+    /// Number of motor groups connected via i2c (only supported by the ClientServer model)
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub motor_groups_i2c: u8,
+
+    /// Sensor sampling interval in milliseconds
+    #[clap(short, long, value_parser, default_value_t = 30)]
+    pub duration: u64,
+
+    /// Request Processing Model to use
+    #[clap(required_unless_present = "status", value_enum, value_parser = clap::builder::PossibleValuesParser::new(["ClientServer", "ReactiveStreaming", "SpringQL", "ObjectOriented"]).map(| s | parse_request_processing_model(& s)))]
+    pub request_processing_model: Option<RequestProcessingModel>,
+
+    /// Size of the window averaged for determining sensor reading value
+    #[clap(long, value_parser, default_value_t = 3000)]
+    pub window_size_ms: u64,
+
+    /// Bound the windows by this many readings per sensor instead of by their size in time,
+    /// for comparing against count-based windows (not supported by SpringQL)
+    #[clap(long, value_parser)]
+    pub window_count: Option<usize>,
+
+    /// Window sampling interval in milliseconds
+    #[clap(short, long, value_parser, default_value_t = 1000)]
+    pub window_sampling_interval_ms: u32,
+
+    /// Slide of the windows in milliseconds, to tune their overlap independently of the window
+    /// sampling interval (SpringQL only)
+    #[clap(long, value_parser)]
+    pub window_slide_ms: Option<u64>,
+
+    /// Time in milliseconds the windows wait for late readings before they are closed (SpringQL only)
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub window_allowed_delay_ms: u64,
+
+    /// How the readings of a sensor are averaged: `window`, or `ewma:<alpha>` for an exponentially
+    /// weighted moving average weighting each new reading by alpha (not supported by SpringQL)
+    #[clap(long, default_value = "window")]
+    pub averaging: AveragingStrategy,
+
+    /// Sampling interval of sensor in milliseconds
+    #[clap(short, long, value_parser, default_value_t = 1000)]
+    pub sensor_sampling_interval_ms: u32,
+
+    /// Size of the thread pool
+    #[clap(short, long, value_parser, default_value_t = 40)]
+    pub thread_pool_size: usize,
+
+    /// Tool wear accumulated per second of operation
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub wear_rate: f64,
+
+    /// Rotational speed in rpm above which the tool wears down
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub wear_rotational_speed_threshold: f64,
+
+    /// Interval in milliseconds after which the tool is changed, 0 to change it on overstrain failures
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub tool_change_interval_ms: u64,
+
+    /// Use the time since the sensors of the motor connected as tool wear for the overstrain rule
+    #[clap(long)]
+    pub use_cumulative_age: bool,
+
+    /// Number of initial readings each sensor discards while warming up
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub warmup_samples: u32,
+
+    /// Probability with which each sensor emits a random failure reading per sample
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub random_failure_prob: f32,
+
+    /// File the motor monitor periodically persists its state to, and restores it from after a restart (ClientServer only)
+    #[clap(long, value_parser)]
+    pub snapshot_path: Option<String>,
+
+    /// Let the motor monitor evaluate the rules once per batch of available messages (ClientServer only)
+    #[clap(long)]
+    pub batch_recv: bool,
+
+    /// Export spans of the sensor sends, monitor ingest, window evaluations and alerts to this
+    /// OpenTelemetry collector (OTLP/HTTP, `host:port` or URL), if the sensors and monitors are
+    /// built with the otel feature (ClientServer and ReactiveStreaming only)
+    #[clap(long, value_parser)]
+    pub otel_endpoint: Option<String>,
+
+    /// Let the motor monitor interpolate the readings of a window at the sensor sampling interval
+    /// before averaging them, so that missed samples do not bias the average (ClientServer only)
+    #[clap(long)]
+    pub interpolate_gaps: bool,
+
+    /// Only log every nth message on the hot paths of the motor monitor when debug logging is enabled
+    #[clap(long, value_parser, default_value_t = 1)]
+    pub log_sample_rate: u64,
+
+    /// Keep Nagle's algorithm enabled on the alert connection of the motor monitor
+    #[clap(long)]
+    pub buffer_alerts: bool,
+
+    /// Let the motor monitor send alerts in the compact encoding, if both it and the cloud server support it
+    #[clap(long)]
+    pub compress_alerts: bool,
+
+    /// Let the motor monitor send its alerts with the averages of the windows which triggered them
+    /// (temperature difference, rotational speed, power and torque), if it is built with the
+    /// detailed_alerts feature; takes precedence over --compress-alerts
+    #[clap(long)]
+    pub detailed_alerts: bool,
+
+    /// Let the sensors send their readings in fixed point (hundredths) with millisecond timestamps
+    #[clap(long)]
+    pub compact_wire: bool,
+
+    /// Factor by which the run is compressed in wall time: the duration, the sampling intervals
+    /// and the windows are divided by it, and the wear rate multiplied
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub time_scale: f64,
+
+    /// Column of the comma separated lines of the sensor data files the readings are read from:
+    /// `last` (also reading files of bare readings or `timestamp,value` lines), or its index from 0
+    #[clap(long, default_value = "last")]
+    pub data_column: DataColumn,
+
+    /// How the sensors connect to the monitor: `tcp`, `uds` (Unix domain sockets), or `local`
+    /// (Unix domain sockets if the monitor has a loopback address)
+    #[clap(long, default_value = "tcp")]
+    pub transport: Transport,
+
+    /// Persist the precision, recall and F1 score of the alerts per failure type to accuracy.csv
+    #[clap(long)]
+    pub accuracy: bool,
+
+    /// How the received alerts are matched with the expected ones, `first-fit` being the former
+    /// matching, kept for comparison
+    #[clap(long, value_enum, default_value = "optimal")]
+    pub alert_matching: AlertMatching,
+
+    /// Let the monitors attach fingerprints of the evaluated windows to their alerts, and with a
+    /// secondary monitor, classify the disagreements of the monitors in forensics.csv
+    #[clap(long)]
+    pub forensic_alerts: bool,
+
+    /// Let a SpringQL monitor post its monitoring reports to the SpringQL web console
+    #[clap(long)]
+    pub report_post: bool,
+
+    /// Let the monitors exit with their benchmark data once their resident set size exceeds this many MB
+    #[clap(long)]
+    pub memory_limit_mb: Option<u64>,
+
+    /// Let the monitors handle at most this many sensor connections at the same time, queuing
+    /// further ones in the accept backlog until a connection closes
+    #[clap(long)]
+    pub max_sensor_connections: Option<usize>,
+
+    /// Reject the sensor connections beyond --max-sensor-connections instead of queuing them
+    #[clap(long, requires = "max_sensor_connections")]
+    pub reject_excess_connections: bool,
+
+    /// Let the monitors expect the sensors to connect in the order of their ids, closing the
+    /// connection of a sensor in the place of another one (only supported by the ClientServer
+    /// and ReactiveStreaming models)
+    #[clap(long)]
+    pub expect_sensor_order: bool,
+
+    /// Fail the run once more than this share (0-1) of the readings of a sensor are implausible,
+    /// instead of only quarantining them
+    #[clap(long)]
+    pub max_quarantine_rate: Option<f32>,
+
+    /// Pin the threads of the monitors to these CPU cores (comma separated), to keep them from
+    /// migrating between cores during the run
+    #[clap(long, value_delimiter = ',')]
+    pub cpu_cores: Vec<usize>,
+
+    /// Let all sensors take their first sample at the same instant, this many milliseconds after
+    /// they connect to the monitor, instead of each one as soon as it connected (0 to disable)
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub startup_barrier_ms: u64,
+
+    /// Let the sensors sample every this many messages for provenance tracking, and persist when
+    /// each sampled message was sent, evaluated in a window and alerted on in provenance.csv
+    /// (0 to disable, only supported by the ClientServer and ReactiveStreaming models)
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub provenance_sample_interval: u32,
+
+    /// Retry policy of the sensors connecting to the monitor (`fast_local`, `patient_network`, or
+    /// `<max_attempts>:<initial_delay_ms>:<multiplier>:<max_delay_ms>:<jitter>`)
+    #[clap(long, value_parser, default_value = "fast_local")]
+    pub sensor_connect_retry: RetryPolicy,
+
+    /// Let each sensor delay connecting to the monitor by up to this many milliseconds,
+    /// derived from its id, to spread the connections of many sensors over that window
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub sensor_connect_jitter_ms: u64,
+
+    /// Let the sensors reconnect right away to a monitor which dropped their connection mid-run,
+    /// retrying with this policy, instead of failing (only supported by the ClientServer model)
+    #[clap(long, value_parser)]
+    pub sensor_reconnect_retry: Option<RetryPolicy>,
+
+    /// Thresholds of the failure rules of the motor monitor and the validator (`default`, or
+    /// `<temperature_difference>:<rotational_speed>:<min_power>:<max_power>:<overstrain_limit>`
+    /// in K, rpm, W, W and minNm), to vary them across runs without recompiling
+    #[clap(long, value_parser, default_value = "default")]
+    pub rule_thresholds: RuleThresholds,
+
+    /// Let the motor monitor exit on the first malformed sensor message instead of skipping it
+    #[clap(long)]
+    pub strict_deserialization: bool,
+
+    /// Post the alerts of a motor as JSON to a URL, in addition to the cloud server
+    /// (`<motor_id>=<url>`, repeatable)
+    #[clap(long = "alert-webhook")]
+    pub alert_webhooks: Vec<AlertWebhook>,
+
+    /// Send the alerts of a failure (or of every failure, for `*`) to the cloud server or drop them,
+    /// counting them under the label (`<label>=<failure|*>:<cloud|drop>`, repeatable, the first
+    /// matching rule applying, e.g. `dashboard=HeatDissipationFailure:drop`)
+    #[clap(long = "route")]
+    pub routing: Vec<RouteRule>,
+
+    /// Let the sensors of a kind emit a deterministic signal instead of the readings of their data
+    /// file, which the validator evaluates the same way
+    /// (`<sensor_kind>:<sine|sawtooth>:<offset>:<amplitude>:<period_ms>`, repeatable, e.g.
+    /// `torque:sine:40:30:10000`)
+    #[clap(long = "synthetic-signal")]
+    pub synthetic_signals: Vec<SyntheticSignal>,
+
+    /// Let the sensors of a motor simulate a failure during a span of the run (in s after its start)
+    /// and emit readings safely within the normal band otherwise, which the validator takes as the
+    /// exact ground truth (`<motor_id>:<failure>:<start_s>-<end_s>`, repeatable, e.g.
+    /// `0:HeatDissipationFailure:20-35`)
+    #[clap(long = "failure-schedule")]
+    pub failure_schedule: Vec<ScheduledFailure>,
+
+    /// Request Processing Model of a second monitor fed with the same sensor readings, for side-by-side runs
+    #[clap(long, value_parser = clap::builder::PossibleValuesParser::new(["ClientServer", "ReactiveStreaming", "ObjectOriented"]).map(| s | parse_request_processing_model(& s)))]
+    pub secondary_processing_model: Option<RequestProcessingModel>,
+
+    /// Maximum clock offset (in ms) of any component before the run is aborted
+    #[clap(long, default_value_t = 500)]
+    pub max_clock_offset_ms: u64,
+
+    /// Profile providing the defaults of the window, sampling and thread pool parameters not passed explicitly
+    #[clap(long, value_enum)]
+    pub profile: Option<profile::Profile>,
+
+    /// Abort before starting the run if the parameters are known to produce a meaningless run
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Directory the results of the run are persisted to, created if it does not exist
+    #[clap(long, value_parser, default_value = ".")]
+    pub results_dir: PathBuf,
+
+    /// Let the results and the temporary files of all components go to a directory of the run
+    /// (`<results_dir>/run_<run_id>`), instead of shared locations overwritten by the next run
+    #[clap(long)]
+    pub run_directories: bool,
+
+    /// What is removed of the run directories once a run finished (`keep`, `delete-on-success` or
+    /// `keep-last:<n>`); those of failed runs are always kept
+    #[clap(long, value_parser, default_value = "keep")]
+    pub run_directory_cleanup: RunDirectoryCleanup,
+
+    /// Network config the addresses of the components are read from in release builds, as
+    /// persisted by the bench executor to the directory of its test run
+    #[clap(long, value_parser, default_value = NETWORK_CONFIG_PATH)]
+    pub network_config: PathBuf,
+
+    /// Run without the cloud server, the monitors only counting their alerts, so that the
+    /// throughput is measured without the cost of delivering the alerts
+    #[clap(long)]
+    pub no_cloud: bool,
+
+    /// Let the motor monitors export the aggregates of every evaluated window to a CSV file in the
+    /// directory of the motor driver (its run directory, or else the temporary one)
+    #[clap(long)]
+    pub export_windows: bool,
+
+    /// Query the run state from the motor driver status endpoint at this address and exit
+    #[clap(long, value_parser)]
+    pub status: Option<SocketAddr>,
+}
+
+impl RunArgs {
+    /// The model of the primary monitor, which is only absent when querying the run state
+    pub fn request_processing_model(&self) -> RequestProcessingModel {
+        self.request_processing_model
+            .expect("No request processing model given")
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Config {
+    pub test_run: TestRunConfig,
+    pub motor_monitor: MotorMonitorConfig,
+    pub motor_driver: MotorDriverConfig,
+    pub cloud_server: CloudServerConfig,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TestRunConfig {
+    pub start_delay: u64,
+    pub benchmark_data_timeout: u64,
+    pub connect_timeout: u64,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MotorMonitorConfig {
+    pub sensor_listen_address: SocketAddr,
+    /// Port the monitor binds to, defaults to the port of the `sensor_listen_address`
+    pub sensor_bind_port: Option<u16>,
+    /// Port of the secondary monitor, defaults to the one after the port of the `sensor_listen_address`
+    pub secondary_sensor_port: Option<u16>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MotorDriverConfig {
+    pub test_driver_listen_address: SocketAddr,
+    pub status_listen_address: SocketAddr,
+    pub sensor_socket_addresses: Vec<SocketAddr>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct CloudServerConfig {
+    pub motor_monitor_listen_address: SocketAddr,
+    pub test_driver_listen_address: SocketAddr,
+}
+
+fn parse_request_processing_model(s: &str) -> RequestProcessingModel {
+    RequestProcessingModel::from_str(s).expect("Could not parse RequestProcessingModel")
+}
+
+/// Reason a benchmark run could not be carried out
+#[derive(Debug)]
+pub enum RunError {
+    /// The arguments combine options which are not supported together
+    ArgumentConflict(String),
+    /// The parameters are known to produce a meaningless run, and `--strict` was passed
+    ParameterWarnings(Vec<String>),
+    /// A component could not be reached
+    Connect(SocketAddr, io::Error),
+    /// The clock of a component is off by more than `--max-clock-offset-ms`
+    ClockOffset {
+        component: String,
+        offset_ms: f64,
+        max_offset_ms: u64,
+    },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::ArgumentConflict(conflict) => write!(f, "{conflict}"),
+            RunError::ParameterWarnings(warnings) => {
+                write!(f, "Aborting due to {} parameter warnings", warnings.len())
+            }
+            RunError::Connect(address, e) => write!(f, "Could not connect to {address}: {e}"),
+            RunError::ClockOffset {
+                component,
+                offset_ms,
+                max_offset_ms,
+            } => write!(
+                f,
+                "Clock of {component} is off by {offset_ms:.1} ms, more than the allowed {max_offset_ms} ms, aborting run"
+            ),
+        }
+    }
+}
+
+/// Results of a benchmark run, as persisted to the files of its results directory
+pub struct RunOutcome {
+    pub run_id: u64,
+    pub start_time: Duration,
+    pub monitors: Vec<MonitorOutcome>,
+    /// Files written to the results directory during the run
+    pub artifacts: Vec<PathBuf>,
+    /// Alerts the cloud server did not persist because its disk was full
+    pub unwritten_alerts: u64,
+    /// Records which were not written to the files of the results directory because the disk
+    /// was full, by file
+    pub unwritten_records: Vec<(PathBuf, u64)>,
+    /// Builds of the components the test driver talked to, including its own
+    pub builds: Vec<BuildInfo>,
+    /// Reason the run was aborted, if it was
+    pub abort_reason: Option<String>,
+}
+
+/// Results of one of the monitors of a benchmark run
+pub struct MonitorOutcome {
+    pub request_processing_model: RequestProcessingModel,
+    /// Absent if the benchmark data of the monitor did not arrive
+    pub benchmark_data: Option<BenchmarkData>,
+    pub crash_report: Option<CrashReport>,
+    /// Received alerts with their delay in seconds
+    pub alerts: Vec<(Alert, f64)>,
+    pub accuracies: Vec<(MotorFailure, Accuracy)>,
+}
+
+impl RunOutcome {
+    /// Why the run is considered failed, if it was aborted, a monitor crashed or its benchmark
+    /// data did not arrive
+    pub fn get_failure(&self) -> Option<String> {
+        if let Some(abort_reason) = &self.abort_reason {
+            return Some(run_directory::get_abort_status(abort_reason));
+        }
+        self.monitors.iter().find_map(|monitor| {
+            let model = monitor.request_processing_model;
+            match (&monitor.benchmark_data, &monitor.crash_report) {
+                (_, Some(_)) => Some(format!("the {model:?} monitor crashed")),
+                (None, None) => Some(format!(
+                    "the benchmark data of the {model:?} monitor did not arrive"
+                )),
+                (Some(_), None) => None,
+            }
+        })
+    }
+}
+
+impl MonitorOutcome {
+    /// Alerts which could not be matched with the expected ones, as persisted to `alert_failures.csv`
+    pub fn get_alert_failures(&self) -> usize {
+        self.accuracies
+            .iter()
+            .map(|(_, accuracy)| accuracy.get_mismatches())
+            .sum()
+    }
+}
+
+/// Parses the run arguments from the command line arguments (starting with the program name),
+/// filling in the defaults of the profile if one is passed
+pub fn parse_args<I, T>(arguments: I) -> Result<RunArgs, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = RunArgs::command().try_get_matches_from(arguments)?;
+    let mut args = RunArgs::from_arg_matches(&matches)?;
+    profile::apply_profile(&mut args, &matches);
+    Ok(args)
+}
+
+/// Executes a single benchmark run with the given parameters against the components of the
+/// config, persisting its results to `args.results_dir` and returning them.
+/// The abort handle (or a clone of it) aborts the run while it is in progress.
+pub fn run_benchmark(
+    args: RunArgs,
+    config: Config,
+    abort_handle: &AbortHandle,
+) -> Result<RunOutcome, RunError> {
+    check_arguments(&args)?;
+    let warnings = profile::lint(&args, &config);
+    for warning in &warnings {
+        warn!("{warning}");
+    }
+    if args.strict && !warnings.is_empty() {
+        return Err(RunError::ParameterWarnings(warnings));
+    }
+    // Lets a persistent cloud server assign the alerts of the monitor to this run
+    let run_id = rand::random::<u64>();
+    let run_directory = args
+        .run_directories
+        .then(|| run_directory::get_path(&args.results_dir, run_id));
+    match &run_directory {
+        Some(run_directory) => info!("Persisting the run to {}", run_directory.display()),
+        None => run_directory::clear_status(&args.results_dir),
+    }
+    abort_handle.begin(
+        run_id,
+        run_directory.as_deref().unwrap_or(&args.results_dir),
+    );
+    let outcome = execute_benchmark_run(
+        &args,
+        &config,
+        run_id,
+        run_directory.as_deref(),
+        abort_handle,
+    );
+    let abort_reason = abort_handle.end();
+    let outcome = outcome.map(|outcome| RunOutcome {
+        abort_reason,
+        ..outcome
+    });
+    let Some(run_directory) = run_directory else {
+        return outcome;
+    };
+    let failure = match &outcome {
+        Ok(outcome) => outcome.get_failure(),
+        Err(e) => Some(e.to_string()),
+    };
+    run_directory::finish(
+        &args.results_dir,
+        &run_directory,
+        failure,
+        args.run_directory_cleanup,
+    );
+    outcome
+}
+
+/// Rejects the combinations of arguments which are not supported
+fn check_arguments(args: &RunArgs) -> Result<(), RunError> {
+    if args.request_processing_model.is_none() {
+        return Err(RunError::ArgumentConflict(
+            "A request processing model is required to run a benchmark".to_string(),
+        ));
+    }
+    if args.motor_groups_i2c > 0
+        && args.request_processing_model() != RequestProcessingModel::ClientServer
+    {
+        return Err(RunError::ArgumentConflict(format!(
+            "--motor-groups-i2c is only supported by the ClientServer model, not {:?}",
+            args.request_processing_model()
+        )));
+    }
+    if args.transport != Transport::Tcp
+        && args.request_processing_model() == RequestProcessingModel::SpringQL
+    {
+        return Err(RunError::ArgumentConflict(
+            "--transport is only supported by the models reading the sensors themselves, not SpringQL".to_string(),
+        ));
+    }
+    if args.max_sensor_connections.is_some()
+        && args.request_processing_model() == RequestProcessingModel::SpringQL
+    {
+        return Err(RunError::ArgumentConflict(
+            "--max-sensor-connections is not supported by SpringQL, whose sources accept the sensors"
+                .to_string(),
+        ));
+    }
+    if args.max_sensor_connections == Some(0) {
+        return Err(RunError::ArgumentConflict(
+            "--max-sensor-connections has to allow at least one connection".to_string(),
+        ));
+    }
+    if args.forensic_alerts && args.request_processing_model() == RequestProcessingModel::SpringQL {
+        return Err(RunError::ArgumentConflict(
+            "--forensic-alerts is not supported by SpringQL, which does not expose its windows"
+                .to_string(),
+        ));
+    }
+    if args.provenance_sample_interval > 0
+        && !matches!(
+            args.request_processing_model(),
+            RequestProcessingModel::ClientServer | RequestProcessingModel::ReactiveStreaming
+        )
+    {
+        return Err(RunError::ArgumentConflict(format!(
+            "--provenance-sample-interval is only supported by the models keeping the messages of their windows, not {:?}",
+            args.request_processing_model()
+        )));
+    }
+    if (args.window_slide_ms.is_some() || args.window_allowed_delay_ms > 0)
+        && args.request_processing_model() != RequestProcessingModel::SpringQL
+    {
+        return Err(RunError::ArgumentConflict(format!(
+            "--window-slide-ms and --window-allowed-delay-ms are only supported by SpringQL, not {:?}",
+            args.request_processing_model()
+        )));
+    }
+    if args.expect_sensor_order {
+        check_expected_sensor_order(args)?;
+    }
+    check_rule_thresholds(args)?;
+    if args.sensor_reconnect_retry.is_some()
+        && (args.request_processing_model() != RequestProcessingModel::ClientServer
+            || args.secondary_processing_model.is_some())
+    {
+        return Err(RunError::ArgumentConflict(
+            "--sensor-reconnect-retry is only supported by a single ClientServer monitor, which accepts the reconnecting sensors".to_string(),
+        ));
+    }
+    // SpringQL only passes on the averages of the readings, in which the random failure reading
+    // cannot be told apart from other readings far out of range
+    if args.random_failure_prob > 0.0
+        && (args.request_processing_model() == RequestProcessingModel::SpringQL
+            || args.secondary_processing_model == Some(RequestProcessingModel::SpringQL))
+    {
+        return Err(RunError::ArgumentConflict(
+            "--random-failure-prob is not supported by SpringQL, whose rules cannot detect the random failure reading".to_string(),
+        ));
+    }
+    if args.time_scale <= 0.0 {
+        return Err(RunError::ArgumentConflict(format!(
+            "--time-scale has to be positive, not {}",
+            args.time_scale
+        )));
+    }
+    if args.time_scale != 1.0 && args.use_cumulative_age {
+        return Err(RunError::ArgumentConflict(
+            "--use-cumulative-age counts the age of a motor in wall time, which --time-scale does not compress".to_string(),
+        ));
+    }
+    if args.averaging != AveragingStrategy::Window {
+        check_moving_average(args)?;
+    }
+    if let Some(window_count) = args.window_count {
+        check_window_count(args, window_count)?;
+    }
+    if args.no_cloud {
+        check_no_cloud(args)?;
+    }
+    if args.run_directory_cleanup != RunDirectoryCleanup::Keep && !args.run_directories {
+        return Err(RunError::ArgumentConflict(format!(
+            "--run-directory-cleanup {} needs --run-directories, as the results directory is shared with other runs",
+            args.run_directory_cleanup
+        )));
+    }
+    check_synthetic_signals(&args.synthetic_signals)?;
+    if !args.failure_schedule.is_empty() {
+        check_failure_schedule(args)?;
+    }
+    if let Some(secondary_processing_model) = args.secondary_processing_model {
+        check_secondary_processing_model(args, secondary_processing_model)?;
+    }
+    Ok(())
+}
+
+/// Rejects two signals for the same kind of sensor, of which the sensors could only emit one
+fn check_synthetic_signals(synthetic_signals: &[SyntheticSignal]) -> Result<(), RunError> {
+    for (index, signal) in synthetic_signals.iter().enumerate() {
+        if synthetic_signals[..index]
+            .iter()
+            .any(|other_signal| other_signal.sensor_kind == signal.sensor_kind)
+        {
+            return Err(RunError::ArgumentConflict(format!(
+                "--synthetic-signal is given twice for the {} sensors",
+                signal.sensor_kind.name()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Rejects schedules the sensors cannot follow, and the arguments which would make their readings
+/// deviate from the schedule
+fn check_failure_schedule(args: &RunArgs) -> Result<(), RunError> {
+    if !args.synthetic_signals.is_empty() || args.random_failure_prob > 0.0 {
+        return Err(RunError::ArgumentConflict(
+            "--failure-schedule determines all readings of the sensors, which --synthetic-signal and --random-failure-prob would change".to_string(),
+        ));
+    }
+    if args.motor_groups_i2c > 0 {
+        return Err(RunError::ArgumentConflict(
+            "--failure-schedule is not supported by the sensors on the I2C bus".to_string(),
+        ));
+    }
+    for (index, scheduled_failure) in args.failure_schedule.iter().enumerate() {
+        if scheduled_failure.motor_id >= args.motor_groups_tcp {
+            return Err(RunError::ArgumentConflict(format!(
+                "--failure-schedule {scheduled_failure} is scheduled for a motor the run with {} motor groups does not have",
+                args.motor_groups_tcp
+            )));
+        }
+        if scheduled_failure.end_s > args.duration as f64 {
+            return Err(RunError::ArgumentConflict(format!(
+                "--failure-schedule {scheduled_failure} ends after the run of {} s",
+                args.duration
+            )));
+        }
+        if let Some(other_failure) = args.failure_schedule[..index]
+            .iter()
+            .find(|other_failure| other_failure.overlaps(scheduled_failure))
+        {
+            return Err(RunError::ArgumentConflict(format!(
+                "--failure-schedule {scheduled_failure} overlaps with {other_failure}"
+            )));
+        }
+        utils::failure_schedule::get_target_readings(
+            Some(scheduled_failure.failure),
+            &args.rule_thresholds,
+        )
+        .map_err(RunError::ArgumentConflict)?;
+    }
+    Ok(())
+}
+
+/// Rejects an expected sensor order for the monitors whose sources accept the sensors, and for
+/// the sensors connecting out of order
+fn check_expected_sensor_order(args: &RunArgs) -> Result<(), RunError> {
+    for request_processing_model in [
+        Some(args.request_processing_model()),
+        args.secondary_processing_model,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !matches!(
+            request_processing_model,
+            RequestProcessingModel::ClientServer | RequestProcessingModel::ReactiveStreaming
+        ) {
+            return Err(RunError::ArgumentConflict(format!(
+                "--expect-sensor-order is only supported by the models counting the accepted sensors, not {request_processing_model:?}"
+            )));
+        }
+    }
+    if args.sensor_connect_jitter_ms > 0 {
+        return Err(RunError::ArgumentConflict(
+            "--expect-sensor-order needs the sensors to connect in the order of their ids, which --sensor-connect-jitter-ms shuffles".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects moving averages for the monitors which cannot compute them, and for the options which
+/// need the readings of the windows
+fn check_moving_average(args: &RunArgs) -> Result<(), RunError> {
+    if args.request_processing_model() == RequestProcessingModel::SpringQL
+        || args.secondary_processing_model == Some(RequestProcessingModel::SpringQL)
+    {
+        return Err(RunError::ArgumentConflict(
+            "--averaging ewma is not supported by SpringQL, whose pumps average windows"
+                .to_string(),
+        ));
+    }
+    if args.forensic_alerts || args.snapshot_path.is_some() {
+        return Err(RunError::ArgumentConflict(
+            "--forensic-alerts and --snapshot-path need the readings of the windows, which --averaging ewma does not keep".to_string(),
+        ));
+    }
+    if args.provenance_sample_interval > 0 {
+        return Err(RunError::ArgumentConflict(
+            "--provenance-sample-interval needs the messages of the windows, which --averaging ewma does not keep".to_string(),
+        ));
+    }
+    if args.interpolate_gaps {
+        return Err(RunError::ArgumentConflict(
+            "--interpolate-gaps needs the readings of the windows, which --averaging ewma does not keep".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects empty count-based windows, and them for the monitors and averaging which cannot bound
+/// their windows by count
+fn check_window_count(args: &RunArgs, window_count: usize) -> Result<(), RunError> {
+    if window_count == 0 {
+        return Err(RunError::ArgumentConflict(
+            "--window-count has to hold at least one reading".to_string(),
+        ));
+    }
+    if args.request_processing_model() == RequestProcessingModel::SpringQL
+        || args.secondary_processing_model == Some(RequestProcessingModel::SpringQL)
+    {
+        return Err(RunError::ArgumentConflict(
+            "--window-count is not supported by SpringQL, whose windows are only framed by time"
+                .to_string(),
+        ));
+    }
+    if args.averaging != AveragingStrategy::Window {
+        return Err(RunError::ArgumentConflict(
+            "--window-count bounds the windows, which --averaging ewma does not keep".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects the options which evaluate the alerts, as without the cloud server only their number
+/// is known
+fn check_no_cloud(args: &RunArgs) -> Result<(), RunError> {
+    let conflict = if args.accuracy {
+        Some("--accuracy")
+    } else if args.forensic_alerts {
+        Some("--forensic-alerts")
+    } else if args.provenance_sample_interval > 0 {
+        Some("--provenance-sample-interval")
+    } else {
+        None
+    };
+    match conflict {
+        Some(conflict) => Err(RunError::ArgumentConflict(format!(
+            "{conflict} needs the alerts collected by the cloud server, which --no-cloud does not start"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Rejects the secondary model if the two monitors cannot be fed the same sensor input,
+/// or their alerts cannot be told apart
+fn check_secondary_processing_model(
+    args: &RunArgs,
+    secondary_processing_model: RequestProcessingModel,
+) -> Result<(), RunError> {
+    let conflict = if args.request_processing_model() == RequestProcessingModel::SpringQL {
+        Some("the SpringQL model receives JSON sensor readings, which the secondary monitor cannot read")
+    } else if args.request_processing_model() == secondary_processing_model {
+        Some("the alerts of two monitors of the same model cannot be told apart")
+    } else if args.motor_groups_i2c > 0 {
+        Some("the readings of i2c sensors are not duplicated to the secondary monitor")
+    } else {
+        None
+    };
+    match conflict {
+        Some(conflict) => Err(RunError::ArgumentConflict(format!(
+            "--secondary-processing-model {secondary_processing_model:?} cannot be used with {:?}: {conflict}",
+            args.request_processing_model()
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(debug_assertions)]
+pub fn get_config(_args: &RunArgs) -> Config {
+    toml::from_str(&fs::read_to_string(CONFIG_PATH).expect("Could not read config file"))
+        .expect("Could not parse config file")
+}
+
+#[cfg(not(debug_assertions))]
+pub fn get_config(args: &RunArgs) -> Config {
+    let network: NetworkConfig = toml::from_str(
+        &fs::read_to_string(&args.network_config).expect("Could not read config file"),
+    )
+    .expect("Could not parse config file");
+    Config {
+        test_run: TestRunConfig {
+            start_delay: 5,
+            benchmark_data_timeout: 60,
+            connect_timeout: 10,
+        },
+        motor_monitor: MotorMonitorConfig {
+            sensor_listen_address: SocketAddr::new(network.motor_monitor_address, 9000),
+            sensor_bind_port: None,
+            secondary_sensor_port: None,
+        },
+        motor_driver: MotorDriverConfig {
+            test_driver_listen_address: SocketAddr::new(network.motor_monitor_address, 8000),
+            status_listen_address: SocketAddr::new(network.motor_monitor_address, 8002),
+            sensor_socket_addresses: network
+                .sensor_addresses
+                .iter()
+                .map(|ip_addr| SocketAddr::new(*ip_addr, 11000))
+                .collect(),
+        },
+        cloud_server: CloudServerConfig {
+            motor_monitor_listen_address: SocketAddr::new(network.cloud_server_address, 10000),
+            test_driver_listen_address: SocketAddr::new(network.cloud_server_address, 8001),
+        },
+    }
+}
+
+fn get_sensor_bind_port(config: &Config) -> u16 {
+    config
+        .motor_monitor
+        .sensor_bind_port
+        .unwrap_or(config.motor_monitor.sensor_listen_address.port())
+}
+
+fn get_secondary_sensor_port(config: &Config) -> u16 {
+    config
+        .motor_monitor
+        .secondary_sensor_port
+        .unwrap_or(config.motor_monitor.sensor_listen_address.port() + 1)
+}
+
+/// Plans the ports the monitor binds for the sensors, which must not overlap the ones of the
+/// drivers, the cloud server and the secondary monitor
+fn get_port_plan(args: &RunArgs, config: &Config) -> Result<PortPlan, PortPlanError> {
+    let port_plan = PortPlan::new(
+        get_sensor_bind_port(config),
+        args.motor_groups_tcp + args.motor_groups_i2c as u16,
+        args.request_processing_model(),
+    )?
+    .reserve(
+        "motor driver control listener",
+        config.motor_driver.test_driver_listen_address.port(),
+    )?
+    .reserve(
+        "motor driver status listener",
+        config.motor_driver.status_listen_address.port(),
+    )?
+    .reserve(
+        "cloud server control listener",
+        config.cloud_server.test_driver_listen_address.port(),
+    )?
+    .reserve(
+        "cloud server monitor listener",
+        config.cloud_server.motor_monitor_listen_address.port(),
+    )?;
+    match args.secondary_processing_model {
+        Some(_) => port_plan.reserve(
+            "secondary sensor listener",
+            get_secondary_sensor_port(config),
+        ),
+        None => Ok(port_plan),
+    }
+}
+
+/// Models of the monitors of the run, with the prefix of the files their results are persisted to
+/// Rejects heat dissipation and power thresholds for the models testing the averages against the
+/// confidence intervals of the dataset instead, which would silently ignore them
+fn check_rule_thresholds(args: &RunArgs) -> Result<(), RunError> {
+    let default_thresholds = RuleThresholds::default();
+    let thresholds = RuleThresholds {
+        overstrain_limit: default_thresholds.overstrain_limit,
+        ..args.rule_thresholds
+    };
+    if thresholds == default_thresholds {
+        return Ok(());
+    }
+    match get_monitors(args).into_iter().find(|(model, _)| {
+        matches!(
+            model,
+            RequestProcessingModel::ReactiveStreaming | RequestProcessingModel::ObjectOriented
+        )
+    }) {
+        Some((model, _)) => Err(RunError::ArgumentConflict(format!(
+            "The heat dissipation and power thresholds of --rule-thresholds are not supported by {model:?}, \
+             which only applies the overstrain limit"
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn get_monitors(args: &RunArgs) -> Vec<(RequestProcessingModel, &'static str)> {
+    let mut monitors = vec![(args.request_processing_model(), "")];
+    if let Some(secondary_processing_model) = args.secondary_processing_model {
+        monitors.push((secondary_processing_model, "secondary_"));
+    }
+    monitors
+}
+
+/// Executes the run, persisting its results to the run directory if there is one
+fn execute_benchmark_run(
+    args: &RunArgs,
+    config: &Config,
+    run_id: u64,
+    run_directory: Option<&Path>,
+    abort_handle: &AbortHandle,
+) -> Result<RunOutcome, RunError> {
+    let start_delay = match args.request_processing_model() {
+        RequestProcessingModel::ReactiveStreaming => config.test_run.start_delay,
+        RequestProcessingModel::ClientServer => config.test_run.start_delay,
+        RequestProcessingModel::SpringQL => (args.motor_groups_tcp * 4 * 4) as u64, //each sensor port takes about 4 seconds to open
+        RequestProcessingModel::ObjectOriented => config.test_run.start_delay,
+    };
+    let mut results = ResultsDirectory::create(run_directory.unwrap_or(&args.results_dir));
+    let mut builds: Vec<BuildInfo> = utils::build_info::get().cloned().into_iter().collect();
+
+    // Both connections are established before the start time is scheduled,
+    // so the connection setup cannot eat into the start delay
+    let mut motor_driver_connection = connect_to_motor_driver(config)?;
+    let mut cloud_server_connection = match args.no_cloud {
+        true => None,
+        false => Some(connect_to_cloud_server(config)?),
+    };
+    check_clocks(
+        args,
+        &mut motor_driver_connection,
+        cloud_server_connection.as_mut(),
+        &mut results,
+    )?;
+    let parameters_sent_at = Instant::now();
+    send_motor_driver_parameters(
+        create_motor_driver_parameters(args, config, run_id, run_directory),
+        &mut motor_driver_connection,
+    );
+    if let Some(cloud_server_connection) = &mut cloud_server_connection {
+        send_cloud_server_parameters(
+            create_cloud_server_parameters(args, config, run_id, run_directory),
+            cloud_server_connection,
+        );
+    }
+    builds.extend(await_parameter_ack(
+        "motor_driver",
+        run_id,
+        parameters_sent_at,
+        &mut motor_driver_connection,
+    ));
+    if let Some(cloud_server_connection) = &mut cloud_server_connection {
+        builds.extend(await_parameter_ack(
+            "cloud_server",
+            run_id,
+            parameters_sent_at,
+            cloud_server_connection,
+        ));
+    }
+    check_sensor_clocks(args, &mut motor_driver_connection, &results)?;
+    let start_time = utils::get_now_duration() + Duration::from_secs(start_delay);
+    // The cloud server registers the run before the motor driver starts the monitors
+    if let Some(cloud_server_connection) = &mut cloud_server_connection {
+        send_run_start(start_time, cloud_server_connection);
+    }
+    send_run_start(start_time, &mut motor_driver_connection);
+    info!("Scheduled start of the run in {start_delay} s");
+    if !args.failure_schedule.is_empty() {
+        persist_failure_schedule(args, start_time, &mut results);
+    }
+    // The components watch for an abort from the start of the run on
+    abort_handle.register("motor_driver", &motor_driver_connection);
+    if let Some(cloud_server_connection) = &cloud_server_connection {
+        abort_handle.register("cloud_server", cloud_server_connection);
+    }
+
+    thread::sleep(utils::get_duration_to_end(
+        start_time,
+        get_run_duration(args),
+    ));
+
+    motor_driver_connection
+        .set_read_timeout(Some(Duration::from_secs(
+            config.test_run.benchmark_data_timeout,
+        )))
+        .expect("Could not set benchmark data read timeout");
+    let mut monitor_results =
+        save_benchmark_results(args, &mut motor_driver_connection, &mut results);
+    builds.extend(
+        monitor_results
+            .iter()
+            .filter_map(|(_, benchmark_data, _)| benchmark_data.build_info.clone()),
+    );
+    // The network usage of the sensors follows the benchmark data of all monitors
+    let sensor_report = if monitor_results.len() == get_monitors(args).len() {
+        info!("Saved benchmark results");
+        get_sensor_report(&mut motor_driver_connection)
+    } else {
+        // Stops the monitors and sensors which are still running, and has the cloud server ship
+        // the alerts it received right away
+        abort_handle.abort("the benchmark data did not arrive");
+        diagnose_motor_driver(config, &mut results);
+        None
+    };
+    let sensor_network_usage = sensor_report.map(|sensor_report| {
+        builds.extend(sensor_report.builds);
+        sensor_report.network_usage
+    });
+    persist_network_usage(sensor_network_usage, &monitor_results, &mut results);
+    check_builds(&builds);
+    persist_builds(&builds, &mut results);
+    let send_logs = match args.provenance_sample_interval > 0 {
+        true => get_send_logs(&mut motor_driver_connection),
+        false => vec![],
+    };
+    let (tagged_alerts, unwritten_alerts) = match &mut cloud_server_connection {
+        Some(cloud_server_connection) => {
+            let alerts = get_alerts_with_delays(args, cloud_server_connection, &mut results);
+            info!("Fetched alerts");
+            alerts
+        }
+        None => (vec![], 0),
+    };
+    // The alerts of each monitor are validated on their own
+    let mut monitors = vec![];
+    for (request_processing_model, results_prefix) in get_monitors(args) {
+        let (alerts, delays): (Vec<Alert>, Vec<f64>) = tagged_alerts
+            .iter()
+            .filter(|(model, _, _)| *model == request_processing_model)
+            .map(|(_, alert, delay)| (alert.clone(), *delay))
+            .unzip();
+        // Without the cloud server there are no alerts to validate, only the counts of the monitors
+        let accuracies = match args.no_cloud {
+            true => vec![],
+            false => {
+                let accuracies =
+                    validator::validate_alerts(args, request_processing_model, start_time, &alerts);
+                info!("Validated alerts of the {request_processing_model:?} monitor");
+                persist_delays(results_prefix, &delays, &mut results);
+                if !args.failure_schedule.is_empty() {
+                    persist_detections(
+                        results_prefix,
+                        &validator::get_detections(args, start_time, &alerts),
+                        &mut results,
+                    );
+                }
+                accuracies
+            }
+        };
+        let (benchmark_data, crash_report) = monitor_results
+            .iter()
+            .position(|(model, _, _)| *model == request_processing_model)
+            .map(|index| {
+                let (_, benchmark_data, crash_report) = monitor_results.swap_remove(index);
+                (Some(benchmark_data), crash_report)
+            })
+            .unwrap_or_default();
+        let monitor = MonitorOutcome {
+            request_processing_model,
+            benchmark_data,
+            crash_report,
+            alerts: alerts.into_iter().zip(delays).collect(),
+            accuracies,
+        };
+        if !args.no_cloud {
+            persist_failures(results_prefix, monitor.get_alert_failures(), &mut results);
+        }
+        if let Some(benchmark_data) = &monitor.benchmark_data {
+            persist_sent_alerts(results_prefix, benchmark_data.sent_alerts, &mut results);
+            if !benchmark_data.route_counts.is_empty() {
+                persist_route_counts(
+                    results_prefix,
+                    request_processing_model,
+                    &benchmark_data.route_counts,
+                    &mut results,
+                );
+            }
+            if let Some(hot_path_profile) = &benchmark_data.hot_path_profile {
+                persist_hot_paths(
+                    results_prefix,
+                    request_processing_model,
+                    hot_path_profile,
+                    benchmark_data.sent_alerts,
+                    &mut results,
+                );
+            }
+        }
+        // The secondary monitor gets the same sampled messages, but is not tracking provenance
+        if args.provenance_sample_interval > 0
+            && request_processing_model == args.request_processing_model()
+        {
+            persist_provenance(
+                &provenance::get_provenance(&send_logs, &monitor.alerts),
+                &mut results,
+            );
+        }
+        if args.accuracy {
+            persist_accuracies(results_prefix, &monitor.accuracies, &mut results);
+        }
+        monitors.push(monitor);
+    }
+    if let (true, Some(secondary_processing_model)) =
+        (args.forensic_alerts, args.secondary_processing_model)
+    {
+        let get_alerts = |request_processing_model: RequestProcessingModel| -> Vec<Alert> {
+            tagged_alerts
+                .iter()
+                .filter(|(model, _, _)| *model == request_processing_model)
+                .map(|(_, alert, _)| alert.clone())
+                .collect()
+        };
+        persist_disagreements(
+            &forensics::get_disagreements(
+                &get_alerts(args.request_processing_model()),
+                &get_alerts(secondary_processing_model),
+                get_window_sampling_interval(args).as_secs_f64(),
+            ),
+            &mut results,
+        );
+    }
+    let unwritten_records = results.get_unwritten_records();
+    log_unwritten_records(unwritten_alerts, &unwritten_records);
+    info!("Finished test run");
+    Ok(RunOutcome {
+        run_id,
+        start_time,
+        monitors,
+        artifacts: results.artifacts,
+        unwritten_alerts,
+        unwritten_records,
+        builds,
+        abort_reason: None,
+    })
+}
+
+fn connect_to_motor_driver(config: &Config) -> Result<TcpStream, RunError> {
+    connect_to_remote(
+        SocketAddr::from_str(
+            format!(
+                "{MONITOR_IP}:{}",
+                config.motor_driver.test_driver_listen_address.port()
+            )
+            .as_str(),
+        )
+        .unwrap(),
+        Duration::from_secs(config.test_run.connect_timeout),
+    ) //todo
+}
+
+fn connect_to_cloud_server(config: &Config) -> Result<TcpStream, RunError> {
+    connect_to_remote(
+        SocketAddr::from_str(
+            format!(
+                "127.0.0.1:{}",
+                config.cloud_server.test_driver_listen_address.port()
+            )
+            .as_str(),
+        )
+        .unwrap(),
+        Duration::from_secs(config.test_run.connect_timeout),
+    )
+}
+
+/// Measures the clock offsets of the motor driver and the cloud server,
+/// aborting the run if one of them exceeds the maximum
+fn check_clocks(
+    args: &RunArgs,
+    motor_driver_connection: &mut TcpStream,
+    cloud_server_connection: Option<&mut TcpStream>,
+    results: &mut ResultsDirectory,
+) -> Result<(), RunError> {
+    let mut clock_offsets = vec![(
+        "motor_driver",
+        utils::measure_clock_offset(motor_driver_connection)
+            .expect("Could not measure clock offset of motor driver"),
+    )];
+    if let Some(cloud_server_connection) = cloud_server_connection {
+        clock_offsets.push((
+            "cloud_server",
+            utils::measure_clock_offset(cloud_server_connection)
+                .expect("Could not measure clock offset of cloud server"),
+        ));
+    }
+    // Truncates the offsets of the previous run, they are appended afterward
+    results.open("clock_offsets.csv");
+    for (component, clock_offset) in &clock_offsets {
+        persist_clock_offset(component, clock_offset, results);
+    }
+    for (component, clock_offset) in &clock_offsets {
+        abort_on_clock_offset(args, component, clock_offset)?;
+    }
+    Ok(())
+}
+
+fn persist_clock_offset(component: &str, clock_offset: &ClockOffset, results: &ResultsDirectory) {
+    info!(
+        "Clock of {component} is off by {:.1} ms (round trip time {:.1} ms)",
+        clock_offset.offset * 1000.0,
+        clock_offset.round_trip_time * 1000.0
+    );
+    let mut clock_offset_file = results.open_append("clock_offsets.csv");
+    writeln!(
+        clock_offset_file,
+        "{component},{},{}",
+        clock_offset.offset * 1000.0,
+        clock_offset.round_trip_time * 1000.0
+    )
+    .expect("Could not write to clock offsets file");
+}
+
+fn abort_on_clock_offset(
+    args: &RunArgs,
+    component: &str,
+    clock_offset: &ClockOffset,
+) -> Result<(), RunError> {
+    if clock_offset.offset.abs() * 1000.0 > args.max_clock_offset_ms as f64 {
+        return Err(RunError::ClockOffset {
+            component: component.to_string(),
+            offset_ms: clock_offset.offset * 1000.0,
+            max_offset_ms: args.max_clock_offset_ms,
+        });
+    }
+    Ok(())
+}
+
+/// Waits for the component to acknowledge the run parameters, logging how long they took to propagate,
+/// and returns the build of the component
+fn await_parameter_ack(
+    component: &str,
+    run_id: u64,
+    parameters_sent_at: Instant,
+    connection: &mut TcpStream,
+) -> Option<BuildInfo> {
+    let parameter_ack = utils::read_object::<ParameterAck>(connection)
+        .unwrap_or_else(|e| panic!("Could not get parameter ack from {component}: {e}"));
+    assert_eq!(
+        parameter_ack.run_id, run_id,
+        "{component} acknowledged the parameters of another run"
+    );
+    info!(
+        "{component} acknowledged the parameters after {:.1} ms",
+        parameters_sent_at.elapsed().as_secs_f64() * 1000.0
+    );
+    parameter_ack.build_info
+}
+
+fn send_run_start(start_time: Duration, connection: &mut TcpStream) {
+    let run_start = RunStart {
+        start_time: start_time.as_secs_f64(),
+    };
+    connection
+        .write_all(&utils::encode_object(&run_start).expect("Could not write run start to bytes"))
+        .expect("Could not send run start");
+}
+
+fn check_sensor_clocks(
+    args: &RunArgs,
+    motor_driver_connection: &mut TcpStream,
+    results: &ResultsDirectory,
+) -> Result<(), RunError> {
+    let sensor_clock_report = utils::read_object::<SensorClockReport>(motor_driver_connection)
+        .expect("Could not get sensor clock report from motor driver");
+    if let Some((sensor_driver_address, clock_offset)) = sensor_clock_report.worst_offset {
+        let component = format!("sensor_driver {sensor_driver_address}");
+        persist_clock_offset(&component, &clock_offset, results);
+        abort_on_clock_offset(args, &component, &clock_offset)?;
+    }
+    Ok(())
+}
+
+fn connect_to_remote(address: SocketAddr, timeout: Duration) -> Result<TcpStream, RunError> {
+    info!("Connecting to {address}");
+    let stream =
+        TcpStream::connect_timeout(&address, timeout).map_err(|e| RunError::Connect(address, e))?;
+    info!("Connected to {address}");
+    Ok(stream)
+}
+
+fn create_motor_driver_parameters(
+    args: &RunArgs,
+    config: &Config,
+    run_id: u64,
+    run_directory: Option<&Path>,
+) -> MotorDriverRunParameters {
+    let sensor_socket_addresses = match !config.motor_driver.sensor_socket_addresses.is_empty() {
+        true => config.motor_driver.sensor_socket_addresses.clone(),
+        false => fs::read_to_string("sensor_socket_addresses.txt")
+            .unwrap()
+            .lines()
+            .map(|line| SocketAddr::from_str(line).unwrap())
+            .collect(),
+    };
+    MotorDriverRunParameters {
+        // Set by the run start once the parameters were acknowledged
+        start_time: 0.0,
+        duration: Duration::from_secs(args.duration).as_secs_f64(),
+        number_of_tcp_motor_groups: args.motor_groups_tcp as usize,
+        number_of_i2c_motor_groups: args.motor_groups_i2c,
+        window_size_ms: args.window_size_ms,
+        sensor_listen_address: config.motor_monitor.sensor_listen_address,
+        sensor_bind_port: get_sensor_bind_port(config),
+        sensor_sampling_interval: args.sensor_sampling_interval_ms,
+        window_sampling_interval: args.window_sampling_interval_ms,
+        request_processing_model: args.request_processing_model(),
+        motor_monitor_listen_address: config.cloud_server.motor_monitor_listen_address,
+        sensor_socket_addresses,
+        thread_pool_size: args.thread_pool_size,
+        wear_model: get_wear_model(args),
+        use_cumulative_age: args.use_cumulative_age,
+        warmup_samples: args.warmup_samples,
+        random_failure_prob: args.random_failure_prob,
+        snapshot_path: args.snapshot_path.clone(),
+        batch_recv: args.batch_recv,
+        log_sample_rate: args.log_sample_rate,
+        buffer_alerts: args.buffer_alerts,
+        compress_alerts: args.compress_alerts,
+        detailed_alerts: args.detailed_alerts,
+        strict_deserialization: args.strict_deserialization,
+        max_clock_offset_ms: args.max_clock_offset_ms,
+        alert_webhooks: args.alert_webhooks.clone(),
+        routing: args.routing.clone(),
+        run_id,
+        compact_wire: args.compact_wire,
+        secondary_processing_model: args.secondary_processing_model,
+        secondary_sensor_port: get_secondary_sensor_port(config),
+        transport: args.transport,
+        forensic_alerts: args.forensic_alerts,
+        report_post: args.report_post,
+        memory_limit_mb: args.memory_limit_mb,
+        max_quarantine_rate: args.max_quarantine_rate,
+        window_slide_ms: args.window_slide_ms,
+        window_allowed_delay_ms: args.window_allowed_delay_ms,
+        data_column: args.data_column,
+        time_scale: get_time_scale(args),
+        cpu_cores: args.cpu_cores.clone(),
+        startup_barrier_ms: args.startup_barrier_ms,
+        averaging: args.averaging,
+        provenance_sample_interval: args.provenance_sample_interval,
+        max_sensor_connections: args.max_sensor_connections,
+        reject_excess_connections: args.reject_excess_connections,
+        expect_sensor_order: args.expect_sensor_order,
+        sensor_connect_retry: args.sensor_connect_retry,
+        sensor_connect_jitter_ms: args.sensor_connect_jitter_ms,
+        sensor_reconnect_retry: args.sensor_reconnect_retry,
+        interpolate_gaps: args.interpolate_gaps,
+        rule_thresholds: args.rule_thresholds,
+        otel_endpoint: args.otel_endpoint.clone(),
+        run_directory: run_directory.map(|path| path.display().to_string()),
+        no_cloud: args.no_cloud,
+        synthetic_signals: args.synthetic_signals.clone(),
+        failure_schedule: args.failure_schedule.clone(),
+        export_windows: args.export_windows,
+        window_count: args.window_count,
+    }
+}
+
+fn get_time_scale(args: &RunArgs) -> TimeScale {
+    TimeScale(args.time_scale)
+}
+
+/// Wall time the run takes, compressed by the time scale
+fn get_run_duration(args: &RunArgs) -> Duration {
+    Duration::from_secs_f64(get_time_scale(args).compress_secs(args.duration as f64))
+}
+
+fn get_sensor_sampling_interval(args: &RunArgs) -> Duration {
+    Duration::from_millis(get_time_scale(args).compress_ms(args.sensor_sampling_interval_ms as u64))
+}
+
+/// Extent of the windows, whose size in time is compressed like the one the monitors receive
+fn get_window_kind(args: &RunArgs) -> WindowKind {
+    match args.window_count {
+        Some(window_count) => WindowKind::Count(window_count),
+        None => WindowKind::TimeMs(get_time_scale(args).compress_ms(args.window_size_ms)),
+    }
+}
+
+fn get_window_sampling_interval(args: &RunArgs) -> Duration {
+    Duration::from_millis(get_time_scale(args).compress_ms(args.window_sampling_interval_ms as u64))
+}
+
+fn get_wear_model(args: &RunArgs) -> WearModel {
+    WearModel {
+        wear_rate: args.wear_rate,
+        rotational_speed_threshold: args.wear_rotational_speed_threshold,
+        tool_change: ToolChange::from_interval_ms(args.tool_change_interval_ms),
+    }
+}
+
+fn send_motor_driver_parameters(
+    motor_driver_parameters: MotorDriverRunParameters,
+    tcp_stream: &mut TcpStream,
+) {
+    let data = utils::encode_object(&motor_driver_parameters)
+        .expect("Could not write motor diver parameters to bytes");
+    debug!("Motor driver parameters size: {}", data.len());
+    tcp_stream
+        .write_all(&data)
+        .expect("Could not send parameters to sensor driver");
+    info!("Sent motor server parameters")
+}
+
+fn create_cloud_server_parameters(
+    args: &RunArgs,
+    config: &Config,
+    run_id: u64,
+    run_directory: Option<&Path>,
+) -> CloudServerRunParameters {
+    CloudServerRunParameters {
+        // Set by the run start once the parameters were acknowledged
+        start_time: 0.0,
+        duration: get_run_duration(args).as_secs_f64(),
+        motor_monitor_listen_address: config.cloud_server.motor_monitor_listen_address,
+        request_processing_model: args.request_processing_model(),
+        run_id,
+        secondary_processing_model: args.secondary_processing_model,
+        run_directory: run_directory.map(|path| path.display().to_string()),
+        routing: args.routing.clone(),
+    }
+}
+
+fn send_cloud_server_parameters(
+    cloud_server_parameters: CloudServerRunParameters,
+    tcp_stream: &mut TcpStream,
+) {
+    let data = utils::encode_object(&cloud_server_parameters)
+        .expect("Could not write motor diver parameters to bytes");
+    tcp_stream
+        .write_all(&data)
+        .expect("Could not send parameters to sensor driver");
+    info!("Sent cloud server parameters")
+}
+
+/// Persists the benchmark data of every monitor, which the motor driver forwards tagged by model.
+/// Returns the benchmark data read before the first one which could not be read.
+fn save_benchmark_results(
+    args: &RunArgs,
+    tcp_stream: &mut TcpStream,
+    results: &mut ResultsDirectory,
+) -> Vec<(RequestProcessingModel, BenchmarkData, Option<CrashReport>)> {
+    let mut monitor_results = vec![];
+    for _ in get_monitors(args) {
+        let mut monitor_benchmark_data = match utils::read_object_with_limit::<MonitorBenchmarkData>(
+            tcp_stream,
+            MAX_BENCHMARK_DATA_SIZE,
+        ) {
+            Ok(monitor_benchmark_data) => monitor_benchmark_data,
+            Err(e) => {
+                error!("Could not read benchmark data: {e}");
+                break;
+            }
+        };
+        let request_processing_model = monitor_benchmark_data.request_processing_model;
+        // The benchmark data may be followed by the memory samples of the monitor
+        let mut frames = monitor_benchmark_data
+            .benchmark_data
+            .split_inclusive_mut(|byte| *byte == 0);
+        let benchmark_data = match utils::decode_object::<BenchmarkData>(
+            frames.next().unwrap_or_default(),
+        ) {
+            Ok(benchmark_data) => benchmark_data,
+            Err(e) => {
+                error!("Could not parse benchmark data of the {request_processing_model:?} monitor: {e}");
+                break;
+            }
+        };
+        let Some((_, results_prefix)) = get_monitors(args)
+            .into_iter()
+            .find(|(model, _)| *model == request_processing_model)
+        else {
+            error!("Received benchmark data of an unexpected {request_processing_model:?} monitor");
+            break;
+        };
+        if let Some(crash_report) = &monitor_benchmark_data.crash_report {
+            error!(
+                "The {request_processing_model:?} monitor crashed at {}: {}",
+                crash_report.location, crash_report.message
+            );
+            write!(
+                results.open(&format!("{results_prefix}crash_report.txt")),
+                "{}\n{}\n\n{}\n",
+                crash_report.location,
+                crash_report.message,
+                crash_report.backtrace
+            )
+            .expect("Could not write crash report");
+        }
+        let mut motor_monitor_benchmark_data =
+            results.open(&format!("{results_prefix}motor_monitor_results.csv"));
+        let mut benchmark_line = benchmark_data.to_csv_string();
+        // Tells the runs of the same parameters apart, as only the averaging or window kind differs
+        if args.averaging != AveragingStrategy::Window {
+            benchmark_line = format!(
+                "{},averaging={}\n",
+                benchmark_line.trim_end(),
+                args.averaging
+            );
+        }
+        if let Some(window_count) = args.window_count {
+            benchmark_line = format!(
+                "{},window={}\n",
+                benchmark_line.trim_end(),
+                WindowKind::Count(window_count)
+            );
+        }
+        motor_monitor_benchmark_data
+            .write_record(&benchmark_line)
+            .expect("Could not write motor monitor benchmark data");
+        let memory_breakdown: Vec<MemorySample> = frames
+            .filter_map(|frame| {
+                utils::decode_object(frame)
+                    .map_err(|e| warn!("Could not parse memory sample: {e}"))
+                    .ok()
+            })
+            .collect();
+        if !memory_breakdown.is_empty() {
+            persist_memory_breakdown(results_prefix, &memory_breakdown, results);
+        }
+        info!("Read benchmark data of the {request_processing_model:?} monitor");
+        monitor_results.push((
+            request_processing_model,
+            benchmark_data,
+            monitor_benchmark_data.crash_report,
+        ));
+    }
+    monitor_results
+}
+
+fn persist_memory_breakdown(
+    results_prefix: &str,
+    memory_breakdown: &[MemorySample],
+    results: &mut ResultsDirectory,
+) {
+    let mut memory_breakdown_file = results.open(&format!(
+        "{results_prefix}motor_monitor_memory_breakdown.csv"
+    ));
+    for memory_sample in memory_breakdown {
+        memory_breakdown_file
+            .write_record(&memory_sample.to_csv_string())
+            .expect("Could not write memory breakdown");
+    }
+}
+
+/// Fetches the run state of the motor driver after the benchmark data did not arrive,
+/// and persists it so the cause of the failure is part of the run results
+fn diagnose_motor_driver(config: &Config, results: &mut ResultsDirectory) {
+    let status_address = SocketAddr::from_str(
+        format!(
+            "{MONITOR_IP}:{}",
+            config.motor_driver.status_listen_address.port()
+        )
+        .as_str(),
+    )
+    .unwrap();
+    let run_state = query_run_state(status_address);
+    error!("Motor driver run state: {run_state:?}");
+    let mut status_file = results.open("motor_driver_status.txt");
+    writeln!(status_file, "{run_state:?}").expect("Could not write to motor driver status file");
+}
+
+/// Queries the run state from the status endpoint of the motor driver
+pub fn query_run_state(status_address: SocketAddr) -> RunState {
+    let mut status_stream =
+        connect_to_remote(status_address, STATUS_CONNECT_TIMEOUT).unwrap_or_else(|e| panic!("{e}"));
+    utils::read_object::<RunState>(&mut status_stream).expect("Could not read run state")
+}
+
+/// Directory the results of a run are persisted to, keeping track of the files written to it,
+/// and of the records which were dropped from them because the disk was full
+struct ResultsDirectory {
+    path: PathBuf,
+    artifacts: Vec<PathBuf>,
+    unwritten_records: HashMap<PathBuf, Arc<AtomicU64>>,
+}
+
+impl ResultsDirectory {
+    fn create(path: &Path) -> ResultsDirectory {
+        fs::create_dir_all(path).expect("Could not create results directory");
+        ResultsDirectory {
+            path: path.to_path_buf(),
+            artifacts: vec![],
+            unwritten_records: HashMap::new(),
+        }
+    }
+
+    /// Opens a results file, truncating the one of a previous run in the same directory
+    fn open(&mut self, file_name: &str) -> DiskWriter {
+        let path = self.path.join(file_name);
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .expect("Could not open results protocol file for writing");
+        if !self.artifacts.contains(&path) {
+            self.artifacts.push(path.clone());
+        }
+        let unwritten_records = Arc::clone(self.unwritten_records.entry(path.clone()).or_default());
+        DiskWriter::new(&path, file, unwritten_records)
+    }
+
+    /// Opens a results file opened before during the run to append to it
+    fn open_append(&self, file_name: &str) -> DiskWriter {
+        let path = self.path.join(file_name);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .expect("Could not open results protocol file for appending");
+        let unwritten_records = self
+            .unwritten_records
+            .get(&path)
+            .cloned()
+            .unwrap_or_default();
+        DiskWriter::new(&path, file, unwritten_records)
+    }
+
+    /// Files which records were dropped from because the disk was full, with their number
+    fn get_unwritten_records(&self) -> Vec<(PathBuf, u64)> {
+        self.artifacts
+            .iter()
+            .filter_map(|path| {
+                let unwritten_records = self.unwritten_records.get(path)?.load(Ordering::Relaxed);
+                (unwritten_records > 0).then(|| (path.clone(), unwritten_records))
+            })
+            .collect()
+    }
+}
+
+/// Quantifies the results lost to a full disk, of the cloud server and of the results directory
+fn log_unwritten_records(unwritten_alerts: u64, unwritten_records: &[(PathBuf, u64)]) {
+    if unwritten_alerts > 0 {
+        warn!("Cloud server did not persist {unwritten_alerts} alerts as its disk was full, they are missing from the results");
+    }
+    for (path, unwritten_records) in unwritten_records {
+        warn!(
+            "Dropped {unwritten_records} records of {} as the disk was full",
+            path.display()
+        );
+    }
+}
+
+/// Parses the segments of the alert protocol while they are streamed by the cloud server,
+/// each prefixed with its length (as little endian u64), until the connection is closed.
+/// Returns the alerts with their delays, tagged with the model of the monitor which sent them,
+/// and the number of alerts the cloud server did not persist because its disk was full.
+fn get_alerts_with_delays(
+    args: &RunArgs,
+    cloud_server_stream: &mut TcpStream,
+    results: &mut ResultsDirectory,
+) -> (Vec<(RequestProcessingModel, Alert, f64)>, u64) {
+    let mut alerts = vec![];
+    let mut unwritten_alerts = 0;
+    let mut segment_length = [0u8; 8];
+    let mut number_of_segments = 0;
+    while cloud_server_stream
+        .read_exact(&mut segment_length)
+        .map(|_| true)
+        .or_else(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => Ok(false),
+            _ => Err(e),
+        })
+        .expect("Could not get alert protocol segment length from cloud server")
+    {
+        let segment = BufReader::new(
+            Read::by_ref(cloud_server_stream).take(u64::from_le_bytes(segment_length)),
+        );
+        for line in segment.lines() {
+            let line = line.expect("Could not get alert protocol segment from cloud server");
+            debug!("{line:?}");
+            if let Some(dropped_alerts) = line.strip_prefix(DROPPED_ALERTS_LABEL) {
+                info!(
+                    "Cloud server dropped {} alerts due to the rate limit",
+                    dropped_alerts.trim_start_matches(',')
+                );
+            } else if let Some(unpersisted_alerts) = line.strip_prefix(UNPERSISTED_ALERTS_LABEL) {
+                info!(
+                    "Cloud server did not persist {} alerts due to the disk budget",
+                    unpersisted_alerts.trim_start_matches(',')
+                );
+            } else if let Some(unwritten) = line.strip_prefix(UNWRITTEN_ALERTS_LABEL) {
+                unwritten_alerts = unwritten
+                    .trim_start_matches(',')
+                    .parse()
+                    .expect("Could not parse number of unwritten alerts");
+            } else if let Some(gap) = line.strip_prefix(DISK_FULL_GAP_LABEL) {
+                warn!(
+                    "Alert protocol of the cloud server is missing {} alerts as its disk was full",
+                    gap.trim_start_matches(',')
+                );
+            } else if let Some(alert_bytes) = line.strip_prefix(ALERT_BYTES_LABEL) {
+                persist_alert_bytes(alert_bytes.trim_start_matches(','), results);
+            } else if let Some(network_usage) = line.strip_prefix(NETWORK_USAGE_LABEL) {
+                persist_cloud_server_network_usage(network_usage.trim_start_matches(','), results);
+            } else if let Some(abort_reason) = line.strip_prefix(ABORTED_LABEL) {
+                warn!(
+                    "Cloud server shipped the alerts of the aborted run: {}",
+                    abort_reason.trim_start_matches(',')
+                );
+            } else {
+                // Alerts of cloud servers not tagging them are attributed to the primary monitor
+                let request_processing_model = line
+                    .split(',')
+                    .nth(4)
+                    .map(parse_request_processing_model)
+                    .unwrap_or(args.request_processing_model());
+                let alert_with_delay = AlertWithDelay::from_csv(line);
+                let delay = alert_with_delay.delay;
+                alerts.push((
+                    request_processing_model,
+                    Alert::from_alert_with_delay(alert_with_delay),
+                    delay,
+                ));
+            }
+        }
+        number_of_segments += 1;
+    }
+    debug!("Received {number_of_segments} alert protocol segments");
+    (alerts, unwritten_alerts)
+}
+
+/// Reads the send times of the sampled messages of all sensors, which the motor driver forwards
+/// after the benchmark data
+fn get_send_logs(motor_driver_stream: &mut TcpStream) -> Vec<SampledMessageSend> {
+    utils::read_object_with_limit::<Vec<SampledMessageSend>>(
+        motor_driver_stream,
+        MAX_SEND_LOGS_SIZE,
+    )
+    .unwrap_or_else(|e| {
+        error!("Could not read send logs: {e}");
+        vec![]
+    })
+}
+
+/// Reads the bytes all sensors exchanged with the monitors and the builds of the sensors and
+/// their drivers, which the motor driver forwards after the benchmark data
+fn get_sensor_report(motor_driver_stream: &mut TcpStream) -> Option<SensorReport> {
+    utils::read_object::<SensorReport>(motor_driver_stream)
+        .map_err(|e| error!("Could not read network usage of the sensors: {e}"))
+        .ok()
+}
+
+/// Warns if the components were built from different commits, which is not failing the run,
+/// as mixed deployments are sometimes intentional
+fn check_builds(builds: &[BuildInfo]) {
+    let first_build = match builds.first() {
+        Some(first_build) => first_build,
+        None => return,
+    };
+    if builds
+        .iter()
+        .any(|build| build.git_hash != first_build.git_hash)
+    {
+        let git_hashes: Vec<String> = builds
+            .iter()
+            .map(|build| format!("{} {}", build.component, build.git_hash))
+            .collect();
+        warn!(
+            "Components were built from different commits: {}",
+            git_hashes.join(", ")
+        );
+    }
+}
+
+/// Persists the builds of the components the test driver talked to during the run
+fn persist_builds(builds: &[BuildInfo], results: &mut ResultsDirectory) {
+    let mut builds_file = results.open("builds.csv");
+    writeln!(
+        builds_file,
+        "component,version,git_hash,dirty,build_time,profile,protocol_version"
+    )
+    .expect("Could not write to builds file");
+    for build in builds {
+        writeln!(
+            builds_file,
+            "{},{},{},{},{},{},{}",
+            build.component,
+            build.version,
+            build.git_hash,
+            build.dirty,
+            build.build_time,
+            build.profile,
+            build
+                .protocol_version
+                .map(|protocol_version| protocol_version.to_string())
+                .unwrap_or_default()
+        )
+        .expect("Could not write to builds file");
+    }
+}
+
+/// Persists the bytes each component wrote to and read from its peers,
+/// leaving out the ones which did not report them
+fn persist_network_usage(
+    sensor_network_usage: Option<NetworkUsage>,
+    monitor_results: &[(RequestProcessingModel, BenchmarkData, Option<CrashReport>)],
+    results: &mut ResultsDirectory,
+) {
+    let mut network_usage_file = results.open("network_usage.csv");
+    writeln!(
+        network_usage_file,
+        "component,peer,bytes_written,bytes_read"
+    )
+    .expect("Could not write to network usage file");
+    let mut write_network_usage = |component: &str, peer: &str, network_usage: NetworkUsage| {
+        writeln!(
+            network_usage_file,
+            "{component},{peer},{},{}",
+            network_usage.bytes_written, network_usage.bytes_read
+        )
+        .expect("Could not write to network usage file")
+    };
+    if let Some(sensor_network_usage) = sensor_network_usage {
+        write_network_usage("sensors", "monitors", sensor_network_usage);
+    }
+    for (request_processing_model, benchmark_data, _) in monitor_results {
+        let component = request_processing_model.to_string();
+        if let Some(sensor_network_usage) = benchmark_data.sensor_network_usage {
+            write_network_usage(&component, "sensors", sensor_network_usage);
+        }
+        write_network_usage(
+            &component,
+            "cloud_server",
+            benchmark_data.cloud_server_network_usage,
+        );
+    }
+}
+
+/// Appends the bytes the cloud server exchanged with the monitors, as (written,read)
+fn persist_cloud_server_network_usage(network_usage: &str, results: &ResultsDirectory) {
+    info!("Cloud server exchanged {network_usage} (written,read) bytes with the monitors");
+    writeln!(
+        results.open_append("network_usage.csv"),
+        "cloud_server,monitors,{network_usage}"
+    )
+    .expect("Could not write to network usage file");
+}
+
+fn persist_delays(results_prefix: &str, delays: &[f64], results: &mut ResultsDirectory) {
+    if !delays.is_empty() {
+        let mut delay_file = results.open(&format!("{results_prefix}alert_delays.csv"));
+        write!(
+            delay_file,
+            "{},",
+            delays
+                .iter()
+                .map(|delay| delay.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+        .expect("Could not write to alert delays file");
+    }
+}
+
+/// Persists the bytes of alerts the cloud server received, and how many they would take uncompressed
+fn persist_alert_bytes(alert_bytes: &str, results: &mut ResultsDirectory) {
+    info!("Cloud server received {alert_bytes} (received,uncompressed) bytes of alerts");
+    let mut alert_bytes_file = results.open("alert_bytes.csv");
+    write!(alert_bytes_file, "{alert_bytes},").expect("Could not write to alert bytes file");
+}
+
+// While it does not really make sense to persist a single value to a file,
+// this is done so that the external interface stays the same over the different
+// result metrics of the service (resource usage, delays, failures)
+fn persist_failures(results_prefix: &str, failures: usize, results: &mut ResultsDirectory) {
+    let mut failure_file = results.open(&format!("{results_prefix}alert_failures.csv"));
+    write!(failure_file, "{failures},").expect("Could not write to failures file");
+}
+
+/// Persists the number of alerts the monitor raised, which is all that is known of them without
+/// the cloud server
+fn persist_sent_alerts(results_prefix: &str, sent_alerts: u64, results: &mut ResultsDirectory) {
+    info!("Monitor raised {sent_alerts} alerts");
+    let mut sent_alerts_file = results.open(&format!("{results_prefix}sent_alerts.csv"));
+    write!(sent_alerts_file, "{sent_alerts},").expect("Could not write to sent alerts file");
+}
+
+/// Persists the number of alerts the monitor routed per rule, one line per rule and run
+fn persist_route_counts(
+    results_prefix: &str,
+    request_processing_model: RequestProcessingModel,
+    route_counts: &[RouteCount],
+    results: &mut ResultsDirectory,
+) {
+    let mut route_counts_file = results.open(&format!("{results_prefix}alert_routes.csv"));
+    for route_count in route_counts {
+        info!(
+            "Monitor routed {} alerts to {} as {}",
+            route_count.alerts, route_count.destination, route_count.label
+        );
+        writeln!(
+            route_counts_file,
+            "{request_processing_model:?},{},{},{}",
+            route_count.label, route_count.destination, route_count.alerts
+        )
+        .expect("Could not write to alert routes file");
+    }
+}
+
+/// Persists the scheduled failures with the times they begin and end at, as the ground truth of
+/// the run for analyzing its alerts afterwards
+fn persist_failure_schedule(args: &RunArgs, start_time: Duration, results: &mut ResultsDirectory) {
+    let mut schedule_file = results.open("failure_schedule.csv");
+    writeln!(schedule_file, "motor_id,failure,start_s,end_s,onset,end")
+        .expect("Could not write to failure schedule file");
+    for scheduled_failure in &args.failure_schedule {
+        writeln!(
+            schedule_file,
+            "{},{},{},{},{},{}",
+            scheduled_failure.motor_id,
+            scheduled_failure.failure,
+            scheduled_failure.start_s,
+            scheduled_failure.end_s,
+            validator::get_schedule_time(args, start_time, scheduled_failure.start_s),
+            validator::get_schedule_time(args, start_time, scheduled_failure.end_s)
+        )
+        .expect("Could not write to failure schedule file");
+    }
+}
+
+/// Persists the time from the onset of each scheduled failure until the monitor first alerted it,
+/// left empty if it did not
+fn persist_detections(
+    results_prefix: &str,
+    detections: &[Detection],
+    results: &mut ResultsDirectory,
+) {
+    let mut detection_file = results.open(&format!("{results_prefix}detection_latencies.csv"));
+    writeln!(detection_file, "motor_id,failure,onset,latency")
+        .expect("Could not write to detection latencies file");
+    for detection in detections {
+        writeln!(
+            detection_file,
+            "{},{},{},{}",
+            detection.scheduled_failure.motor_id,
+            detection.scheduled_failure.failure,
+            detection.onset,
+            detection
+                .latency
+                .map(|latency| latency.to_string())
+                .unwrap_or_default()
+        )
+        .expect("Could not write to detection latencies file");
+    }
+}
+
+/// Persists the time the monitor spent in each of its hot paths, normalized per processed message
+/// and per raised alert (left empty if there were none), for `profile_compare` to compare the
+/// monitors by
+fn persist_hot_paths(
+    results_prefix: &str,
+    request_processing_model: RequestProcessingModel,
+    hot_path_profile: &HotPathProfile,
+    sent_alerts: u64,
+    results: &mut ResultsDirectory,
+) {
+    let mut hot_paths_file = results.open(&format!("{results_prefix}hot_paths.csv"));
+    writeln!(
+        hot_paths_file,
+        "model,hot_path,calls,nanoseconds,processed_messages,alerts,ns_per_message,ns_per_alert"
+    )
+    .expect("Could not write to hot paths file");
+    let per = |nanoseconds: u64, count: u64| match count {
+        0 => String::new(),
+        count => (nanoseconds as f64 / count as f64).to_string(),
+    };
+    for hot_path_time in &hot_path_profile.hot_paths {
+        writeln!(
+            hot_paths_file,
+            "{request_processing_model:?},{},{},{},{},{sent_alerts},{},{}",
+            hot_path_time.hot_path.name(),
+            hot_path_time.calls,
+            hot_path_time.nanoseconds,
+            hot_path_profile.processed_messages,
+            per(
+                hot_path_time.nanoseconds,
+                hot_path_profile.processed_messages
+            ),
+            per(hot_path_time.nanoseconds, sent_alerts),
+        )
+        .expect("Could not write to hot paths file");
+    }
+}
+
+/// Persists the matches of the alerts per failure type, with the precision, recall and F1 score
+/// derived from them and the time offsets of the matched alerts (left empty if undefined, e.g. the
+/// precision if no alerts were received)
+fn persist_accuracies(
+    results_prefix: &str,
+    accuracies: &[(MotorFailure, Accuracy)],
+    results: &mut ResultsDirectory,
+) {
+    let format_optional =
+        |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+    let mut accuracy_file = results.open(&format!("{results_prefix}accuracy.csv"));
+    writeln!(
+        accuracy_file,
+        "failure,true_positives,false_positives,false_negatives,precision,recall,f1,mean_offset,p95_offset"
+    )
+    .expect("Could not write to accuracy file");
+    for (failure, accuracy) in accuracies {
+        writeln!(
+            accuracy_file,
+            "{failure},{},{},{},{},{},{},{},{}",
+            accuracy.true_positives,
+            accuracy.false_positives,
+            accuracy.false_negatives,
+            format_optional(accuracy.get_precision()),
+            format_optional(accuracy.get_recall()),
+            format_optional(accuracy.get_f1_score()),
+            format_optional(accuracy.mean_offset),
+            format_optional(accuracy.p95_offset)
+        )
+        .expect("Could not write to accuracy file");
+    }
+}
+
+/// Persists the hops of the sampled messages, with the alert columns left empty for the messages
+/// which contributed to no alert
+fn persist_provenance(provenance: &[provenance::Provenance], results: &mut ResultsDirectory) {
+    info!(
+        "{} of {} sampled messages contributed to an alert",
+        provenance
+            .iter()
+            .filter(|provenance| provenance.alert.is_some())
+            .map(|provenance| provenance.send.id)
+            .collect::<HashSet<_>>()
+            .len(),
+        provenance
+            .iter()
+            .map(|provenance| provenance.send.id)
+            .collect::<HashSet<_>>()
+            .len()
+    );
+    let mut provenance_file = results.open("provenance.csv");
+    writeln!(
+        provenance_file,
+        "sensor_id,sample_id,send_time,motor_id,failure,window_time,arrival_time,window_latency,alert_latency"
+    )
+    .expect("Could not write to provenance file");
+    for provenance in provenance {
+        let send = &provenance.send;
+        let alert_columns = provenance
+            .alert
+            .as_ref()
+            .map(|alert| {
+                format!(
+                    "{},{},{},{},{},{}",
+                    alert.motor_id,
+                    alert.failure,
+                    alert.window_time,
+                    alert.arrival_time,
+                    alert.window_time - send.send_time,
+                    alert.arrival_time - alert.window_time
+                )
+            })
+            .unwrap_or_else(|| ",,,,,".to_string());
+        writeln!(
+            provenance_file,
+            "{},{},{},{alert_columns}",
+            send.id.sensor_id, send.id.sample_id, send.send_time
+        )
+        .expect("Could not write to provenance file");
+    }
+}
+
+/// Persists the alerts the primary and secondary monitor disagree on, with the cause of each
+/// disagreement (the columns of a monitor are left empty if it did not alert)
+fn persist_disagreements(
+    disagreements: &[forensics::Disagreement],
+    results: &mut ResultsDirectory,
+) {
+    info!(
+        "Monitors disagreed on {} alerts, {} due to different data, {} due to different computation",
+        disagreements.len(),
+        disagreements
+            .iter()
+            .filter(|disagreement| disagreement.cause == forensics::Cause::DifferentData)
+            .count(),
+        disagreements
+            .iter()
+            .filter(|disagreement| disagreement.cause == forensics::Cause::DifferentComputation)
+            .count()
+    );
+    let format_alert = |alert: Option<&Alert>| {
+        alert
+            .map(|alert| format!("{},{}", alert.time, alert.failure))
+            .unwrap_or_else(|| ",".to_string())
+    };
+    let mut forensics_file = results.open("forensics.csv");
+    writeln!(
+        forensics_file,
+        "motor_id,primary_time,primary_failure,secondary_time,secondary_failure,cause"
+    )
+    .expect("Could not write to forensics file");
+    for disagreement in disagreements {
+        let motor_id = disagreement
+            .primary_alert
+            .as_ref()
+            .or(disagreement.secondary_alert.as_ref())
+            .expect("A disagreement has at least one alert")
+            .motor_id;
+        writeln!(
+            forensics_file,
+            "{motor_id},{},{},{}",
+            format_alert(disagreement.primary_alert.as_ref()),
+            format_alert(disagreement.secondary_alert.as_ref()),
+            disagreement.cause
+        )
+        .expect("Could not write to forensics file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_processing_model_is_required_without_status() {
+        assert!(parse_args(["test_driver"]).is_err());
+        let args = parse_args(["test_driver", "SpringQL"]).unwrap();
+        assert_eq!(
+            args.request_processing_model,
+            Some(RequestProcessingModel::SpringQL)
+        );
+    }
+
+    #[test]
+    fn request_processing_model_is_optional_with_status() {
+        let args = parse_args(["test_driver", "--status", "127.0.0.1:9000"]).unwrap();
+        assert_eq!(args.request_processing_model, None);
+        assert!(matches!(
+            check_arguments(&args),
+            Err(RunError::ArgumentConflict(_))
+        ));
+    }
+
+    #[test]
+    fn heat_and_power_thresholds_are_rejected_for_models_ignoring_them() {
+        for model in ["ReactiveStreaming", "ObjectOriented"] {
+            let args = parse_args([
+                "test_driver",
+                model,
+                "--rule-thresholds",
+                "8.6:1380:2000:9000:11000",
+            ])
+            .unwrap();
+            assert!(matches!(
+                check_arguments(&args),
+                Err(RunError::ArgumentConflict(_))
+            ));
+            let args = parse_args([
+                "test_driver",
+                model,
+                "--rule-thresholds",
+                "8.6:1380:3500:9000:5000",
+            ])
+            .unwrap();
+            assert!(check_arguments(&args).is_ok());
+        }
+        let args = parse_args([
+            "test_driver",
+            "ClientServer",
+            "--secondary-processing-model",
+            "ObjectOriented",
+            "--rule-thresholds",
+            "5:1380:3500:9000:11000",
+        ])
+        .unwrap();
+        assert!(matches!(
+            check_arguments(&args),
+            Err(RunError::ArgumentConflict(_))
+        ));
+        let args = parse_args([
+            "test_driver",
+            "ClientServer",
+            "--rule-thresholds",
+            "8.6:1380:2000:9000:11000",
+        ])
+        .unwrap();
+        assert!(check_arguments(&args).is_ok());
+    }
+
+    #[test]
+    fn random_failure_prob_is_rejected_for_spring_ql() {
+        let args = parse_args(["test_driver", "SpringQL", "--random-failure-prob", "0.1"]).unwrap();
+        assert!(matches!(
+            check_arguments(&args),
+            Err(RunError::ArgumentConflict(_))
+        ));
+        let args = parse_args([
+            "test_driver",
+            "ClientServer",
+            "--random-failure-prob",
+            "0.1",
+        ])
+        .unwrap();
+        assert!(check_arguments(&args).is_ok());
+    }
+}
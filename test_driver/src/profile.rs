@@ -0,0 +1,173 @@
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+
+use data_transfer_objects::RequestProcessingModel;
+
+use crate::{get_port_plan, Config, RunArgs};
+
+/// Built-in sets of parameter defaults, tuned per request processing model
+#[derive(ValueEnum, Debug, Copy, Clone)]
+pub enum Profile {
+    /// Small windows which are sampled often, detecting failures quickly
+    Latency,
+    /// High sensor rates, with windows being sampled rarely
+    Throughput,
+    /// Few readings per window and the smallest thread pools which do not stall
+    LowMemory,
+}
+
+struct ProfileDefaults {
+    window_size_ms: u64,
+    window_sampling_interval_ms: u32,
+    sensor_sampling_interval_ms: u32,
+    thread_pool_size: usize,
+}
+
+impl Profile {
+    fn get_defaults(
+        self,
+        request_processing_model: RequestProcessingModel,
+        motor_groups: u16,
+    ) -> ProfileDefaults {
+        let minimum_thread_pool_size =
+            get_minimum_thread_pool_size(request_processing_model, motor_groups);
+        match self {
+            Profile::Latency => ProfileDefaults {
+                window_size_ms: 1000,
+                window_sampling_interval_ms: 250,
+                sensor_sampling_interval_ms: 50,
+                thread_pool_size: get_headroom_thread_pool_size(
+                    request_processing_model,
+                    motor_groups,
+                ),
+            },
+            Profile::Throughput => ProfileDefaults {
+                window_size_ms: 5000,
+                window_sampling_interval_ms: 1000,
+                sensor_sampling_interval_ms: 10,
+                thread_pool_size: get_headroom_thread_pool_size(
+                    request_processing_model,
+                    motor_groups,
+                ),
+            },
+            Profile::LowMemory => ProfileDefaults {
+                window_size_ms: 2000,
+                window_sampling_interval_ms: 1000,
+                sensor_sampling_interval_ms: 500,
+                thread_pool_size: minimum_thread_pool_size,
+            },
+        }
+    }
+}
+
+/// Overrides the parameters which were not passed explicitly with the defaults of the profile
+pub fn apply_profile(args: &mut RunArgs, matches: &ArgMatches) {
+    let (Some(profile), Some(request_processing_model)) =
+        (args.profile, args.request_processing_model)
+    else {
+        return;
+    };
+    let defaults = profile.get_defaults(request_processing_model, get_motor_groups(args));
+    let is_default = |id: &str| matches.value_source(id) == Some(ValueSource::DefaultValue);
+    if is_default("window_size_ms") {
+        args.window_size_ms = defaults.window_size_ms;
+    }
+    if is_default("window_sampling_interval_ms") {
+        args.window_sampling_interval_ms = defaults.window_sampling_interval_ms;
+    }
+    if is_default("sensor_sampling_interval_ms") {
+        args.sensor_sampling_interval_ms = defaults.sensor_sampling_interval_ms;
+    }
+    if is_default("thread_pool_size") {
+        args.thread_pool_size = defaults.thread_pool_size;
+    }
+}
+
+/// Checks the parameters for combinations which are known to produce meaningless runs,
+/// returning a description of the consequence and the fix for each
+pub fn lint(args: &RunArgs, config: &Config) -> Vec<String> {
+    let mut warnings = vec![];
+    let motor_groups = get_motor_groups(args);
+    if args.sensor_sampling_interval_ms as u64 > args.window_size_ms {
+        warnings.push(format!(
+            "The sensor sampling interval ({} ms) exceeds the window size ({} ms), so most windows \
+            are empty and no alerts are produced; lower --sensor-sampling-interval-ms or raise --window-size-ms",
+            args.sensor_sampling_interval_ms, args.window_size_ms
+        ));
+    }
+    if let Err(e) = get_port_plan(args, config) {
+        warnings.push(format!(
+            "The ports of the run cannot be planned, as {e}, so sensors would connect to the wrong \
+            listener and the motor driver aborts the run; change motor_monitor.sensor_bind_port \
+            or the number of motor groups"
+        ));
+    }
+    let minimum_thread_pool_size =
+        get_minimum_thread_pool_size(args.request_processing_model(), motor_groups);
+    if args.thread_pool_size < minimum_thread_pool_size {
+        warnings.push(format!(
+            "The thread pool of {} threads is too small for {motor_groups} motor groups with the \
+            {:?} model, so sensors or motors are starved of threads and the run stalls; raise \
+            --thread-pool-size to at least {minimum_thread_pool_size}",
+            args.thread_pool_size,
+            args.request_processing_model()
+        ));
+    }
+    if !args
+        .window_size_ms
+        .is_multiple_of(args.window_sampling_interval_ms as u64)
+    {
+        warnings.push(format!(
+            "The window sampling interval ({} ms) does not divide the window size ({} ms), so \
+            consecutive windows overlap unevenly and are not comparable across models; choose a \
+            --window-sampling-interval-ms dividing --window-size-ms",
+            args.window_sampling_interval_ms, args.window_size_ms
+        ));
+    }
+    if args.duration * 1000 < args.window_size_ms * 2 {
+        warnings.push(format!(
+            "The run duration ({} s) is shorter than two full windows ({} ms each), so hardly any \
+            alerts can be produced; raise --duration to at least {} s",
+            args.duration,
+            args.window_size_ms,
+            (args.window_size_ms * 2).div_ceil(1000)
+        ));
+    }
+    warnings
+}
+
+fn get_motor_groups(args: &RunArgs) -> u16 {
+    args.motor_groups_tcp + args.motor_groups_i2c as u16
+}
+
+/// Number of threads the monitor blocks at most at the same time
+fn get_minimum_thread_pool_size(
+    request_processing_model: RequestProcessingModel,
+    motor_groups: u16,
+) -> usize {
+    let motor_groups = motor_groups as usize;
+    match request_processing_model {
+        // One thread per sensor connection plus the consumer
+        RequestProcessingModel::ClientServer => motor_groups * 4 + 1,
+        // One source worker per sensor, plus at least one generic worker
+        RequestProcessingModel::SpringQL => motor_groups * 4 + 1,
+        // One thread per sensor and one per motor monitor
+        RequestProcessingModel::ObjectOriented => motor_groups * 5,
+        // The windows of the motor groups are evaluated concurrently
+        RequestProcessingModel::ReactiveStreaming => motor_groups,
+    }
+}
+
+/// Thread pool sizes used by the bench executor
+fn get_headroom_thread_pool_size(
+    request_processing_model: RequestProcessingModel,
+    motor_groups: u16,
+) -> usize {
+    let motor_groups = motor_groups as usize;
+    match request_processing_model {
+        RequestProcessingModel::ReactiveStreaming => 10 * 40,
+        RequestProcessingModel::ClientServer => motor_groups * 4 + 1,
+        RequestProcessingModel::SpringQL => motor_groups * 12,
+        RequestProcessingModel::ObjectOriented => motor_groups * 5,
+    }
+}
@@ -0,0 +1,46 @@
+use data_transfer_objects::{Alert, MotorFailure, SampledMessageSend};
+
+/// Hops of a sampled message: from the sensor into the window of an alert, and with the alert
+/// to the cloud server. A message which contributed to no alert has no hops after its send.
+pub struct Provenance {
+    pub send: SampledMessageSend,
+    pub alert: Option<AlertHop>,
+}
+
+pub struct AlertHop {
+    pub motor_id: u16,
+    pub failure: MotorFailure,
+    /// Time of the windows the alert was evaluated on, i.e. of their latest reading
+    pub window_time: f64,
+    /// Time the alert arrived at the cloud server
+    pub arrival_time: f64,
+}
+
+/// Joins the send times of the sampled messages with the alerts listing them, one record
+/// per alert a message contributed to, ordered by the send time
+pub fn get_provenance(sends: &[SampledMessageSend], alerts: &[(Alert, f64)]) -> Vec<Provenance> {
+    let mut sends = sends.to_vec();
+    sends.sort_by(|send, other_send| send.send_time.total_cmp(&other_send.send_time));
+    let mut provenance = vec![];
+    for send in sends {
+        let records_before = provenance.len();
+        provenance.extend(
+            alerts
+                .iter()
+                .filter(|(alert, _)| alert.sampled_messages.contains(&send.id))
+                .map(|(alert, delay)| Provenance {
+                    send,
+                    alert: Some(AlertHop {
+                        motor_id: alert.motor_id,
+                        failure: alert.failure,
+                        window_time: alert.time,
+                        arrival_time: alert.time + delay,
+                    }),
+                }),
+        );
+        if provenance.len() == records_before {
+            provenance.push(Provenance { send, alert: None });
+        }
+    }
+    provenance
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use log::{info, warn};
+
+/// File in a finished run directory stating whether the run succeeded, and why it failed if not
+const RUN_STATUS_FILE: &str = "run_status.txt";
+const SUCCEEDED: &str = "succeeded";
+const ABORTED: &str = "aborted";
+
+/// What is removed of the run directories once a run finished.
+/// The directories of failed runs are kept for inspection with every policy.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RunDirectoryCleanup {
+    /// Keep the directories of all runs
+    Keep,
+    /// Remove the directory of a successful run right away
+    DeleteOnSuccess,
+    /// Keep the directories of this many most recently finished successful runs
+    KeepLast(usize),
+}
+
+impl FromStr for RunDirectoryCleanup {
+    type Err = String;
+
+    /// Parses `keep`, `delete-on-success` or `keep-last:<n>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(RunDirectoryCleanup::Keep),
+            "delete-on-success" => Ok(RunDirectoryCleanup::DeleteOnSuccess),
+            _ => match s.strip_prefix("keep-last:") {
+                Some(runs) => runs
+                    .parse()
+                    .map(RunDirectoryCleanup::KeepLast)
+                    .map_err(|e| format!("Could not parse number of runs to keep {runs}: {e}")),
+                None => Err(format!(
+                    "Unknown run directory cleanup {s}, expected keep, delete-on-success or keep-last:<n>"
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for RunDirectoryCleanup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RunDirectoryCleanup::Keep => write!(f, "keep"),
+            RunDirectoryCleanup::DeleteOnSuccess => write!(f, "delete-on-success"),
+            RunDirectoryCleanup::KeepLast(runs) => write!(f, "keep-last:{runs}"),
+        }
+    }
+}
+
+/// Directory of the run in the results directory, named by its run id
+pub fn get_path(results_dir: &Path, run_id: u64) -> PathBuf {
+    results_dir.join(format!("run_{run_id:016x}"))
+}
+
+/// Reason of the abort of a run, as recorded in its status
+pub fn get_abort_status(reason: &str) -> String {
+    format!("{ABORTED}: {reason}")
+}
+
+/// Records the abort of the run in the directory its results are persisted to,
+/// before it is finished, as the test driver may exit right after
+pub fn mark_aborted(directory: &Path, reason: &str) {
+    if let Err(e) = fs::write(directory.join(RUN_STATUS_FILE), get_abort_status(reason)) {
+        warn!(
+            "Could not mark the run in {} as aborted: {e}",
+            directory.display()
+        );
+    }
+}
+
+/// Removes the status of a previous run persisted to the same directory
+pub fn clear_status(directory: &Path) {
+    let _ = fs::remove_file(directory.join(RUN_STATUS_FILE));
+}
+
+/// Records how the run finished in its directory, and applies the cleanup policy to the
+/// directories of the successful runs in the results directory.
+/// Directories of runs which are still executing have no status yet, and are left alone.
+pub fn finish(
+    results_dir: &Path,
+    run_directory: &Path,
+    failure: Option<String>,
+    cleanup: RunDirectoryCleanup,
+) {
+    let status = failure.as_deref().unwrap_or(SUCCEEDED);
+    if let Err(e) = fs::write(run_directory.join(RUN_STATUS_FILE), status) {
+        warn!(
+            "Could not write the status of run directory {}: {e}",
+            run_directory.display()
+        );
+    }
+    if let Some(failure) = failure {
+        warn!(
+            "Keeping the directory {} of the failed run: {failure}",
+            run_directory.display()
+        );
+        return;
+    }
+    let obsolete_run_directories = match cleanup {
+        RunDirectoryCleanup::Keep => vec![],
+        RunDirectoryCleanup::DeleteOnSuccess => vec![run_directory.to_path_buf()],
+        RunDirectoryCleanup::KeepLast(runs) => get_successful_run_directories(results_dir)
+            .into_iter()
+            .skip(runs)
+            .collect(),
+    };
+    for obsolete_run_directory in obsolete_run_directories {
+        match fs::remove_dir_all(&obsolete_run_directory) {
+            Ok(()) => info!(
+                "Removed the directory {} of a successful run",
+                obsolete_run_directory.display()
+            ),
+            Err(e) => warn!(
+                "Could not remove run directory {}: {e}",
+                obsolete_run_directory.display()
+            ),
+        }
+    }
+}
+
+/// Directories of the successful runs in the results directory, the most recently finished first
+fn get_successful_run_directories(results_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(results_dir) else {
+        return vec![];
+    };
+    let mut run_directories: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join(RUN_STATUS_FILE))
+        .filter(|status_path| {
+            fs::read_to_string(status_path).is_ok_and(|status| status == SUCCEEDED)
+        })
+        .filter_map(|status_path| {
+            let finished = fs::metadata(&status_path).and_then(|metadata| metadata.modified());
+            Some((finished.ok()?, status_path.parent()?.to_path_buf()))
+        })
+        .collect();
+    run_directories.sort_by(|(finished, _), (other_finished, _)| other_finished.cmp(finished));
+    run_directories
+        .into_iter()
+        .map(|(_, run_directory)| run_directory)
+        .collect()
+}
@@ -0,0 +1,1047 @@
+use std::fs;
+use std::ops::Shl;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use log::{debug, info, warn};
+use rand::prelude::IteratorRandom;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use data_transfer_objects::{
+    Alert, AveragingStrategy, CompactSensorMessage, MotorFailure, RequestProcessingModel,
+    ScheduledFailure, SensorKind, SensorMessage, SyntheticSignal, WindowKind,
+    RANDOM_FAILURE_READING,
+};
+use utils::ewma::Ewma;
+use utils::units::MotorReadings;
+use utils::{ToolWear, SENSOR_CONNECTION_DELAY};
+
+use crate::{
+    get_run_duration, get_sensor_sampling_interval, get_time_scale, get_wear_model,
+    get_window_kind, get_window_sampling_interval, RunArgs,
+};
+
+const RESOURCE_PATH: &str = "resources";
+const FAILURES: [MotorFailure; 5] = [
+    MotorFailure::ToolWearFailure,
+    MotorFailure::HeatDissipationFailure,
+    MotorFailure::PowerFailure,
+    MotorFailure::OverstrainFailure,
+    MotorFailure::RandomFailure,
+];
+
+/// How the received alerts are matched with the expected ones
+#[derive(ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AlertMatching {
+    /// Matches as many alerts as possible, so that a missing or extra alert is one mismatch
+    Optimal,
+    /// Matches each expected alert with the first received one within the tolerance, which may
+    /// take the alert a later expected one needed and cascade into further mismatches
+    FirstFit,
+}
+
+/// Matches of the received alerts of a failure type with the expected ones
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Accuracy {
+    pub true_positives: usize,
+    /// Received alerts which were not expected
+    pub false_positives: usize,
+    /// Expected alerts which were not received
+    pub false_negatives: usize,
+    /// Mean of the time the matched alerts were received after the expected ones (in s),
+    /// negative if they are early, e.g. due to a clock offset
+    pub mean_offset: Option<f64>,
+    /// 95th percentile of the absolute time between the matched and the expected alerts (in s)
+    pub p95_offset: Option<f64>,
+}
+
+impl Accuracy {
+    pub fn get_mismatches(&self) -> usize {
+        self.false_positives + self.false_negatives
+    }
+
+    /// Share of the received alerts which were expected, if any were received
+    pub fn get_precision(&self) -> Option<f64> {
+        get_ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    /// Share of the expected alerts which were received, if any were expected
+    pub fn get_recall(&self) -> Option<f64> {
+        get_ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    pub fn get_f1_score(&self) -> Option<f64> {
+        get_ratio(
+            2 * self.true_positives,
+            2 * self.true_positives + self.get_mismatches(),
+        )
+    }
+}
+
+/// Detection of a scheduled failure by the alerts of a monitor
+#[derive(Debug, Copy, Clone)]
+pub struct Detection {
+    pub scheduled_failure: ScheduledFailure,
+    /// Time the failure began at
+    pub onset: f64,
+    /// Time (in s) from the onset until the time of the first alert of the failure,
+    /// if it was alerted
+    pub latency: Option<f64>,
+}
+
+/// Averages of the sensors of a motor the rules are evaluated on
+struct MotorAverages {
+    averages: [f64; 4],
+    number_of_values: usize,
+    time: f64,
+}
+
+/// Replays the readings the sensors sent during the run, evaluates them the same way
+/// the motor monitors do, and compares the resulting alerts to the received ones.
+/// Returns how many of the expected and received alerts of each failure type could be matched.
+/// If failures were scheduled, the alerts are compared to the schedule instead.
+pub fn validate_alerts(
+    args: &RunArgs,
+    request_processing_model: RequestProcessingModel,
+    start_time: Duration,
+    alerts: &[Alert],
+) -> Vec<(MotorFailure, Accuracy)> {
+    if !args.failure_schedule.is_empty() {
+        return validate_scheduled_alerts(args, start_time, alerts);
+    }
+    let expected_alerts = get_expected_alerts(
+        args,
+        request_processing_model,
+        start_time,
+        args.compact_wire,
+    );
+    let tolerance = get_window_sampling_interval(args).as_secs_f64();
+    if args.compact_wire {
+        // The fixed-point readings must lead to the same threshold decisions as the exact ones
+        let exact_alerts = get_expected_alerts(args, request_processing_model, start_time, false);
+        let changed_decisions = count_mismatches(
+            &exact_alerts,
+            &expected_alerts,
+            tolerance,
+            args.alert_matching,
+        );
+        if changed_decisions > 0 {
+            warn!("Rounding the readings to hundredths changed {changed_decisions} alerts");
+        }
+    }
+    let accuracies = get_accuracies(&expected_alerts, alerts, tolerance, args.alert_matching);
+    let failures: usize = accuracies
+        .iter()
+        .map(|(_, accuracy)| accuracy.get_mismatches())
+        .sum();
+    info!(
+        "Expected {} alerts, received {}, {failures} could not be matched",
+        expected_alerts.len(),
+        alerts.len()
+    );
+    accuracies
+}
+
+/// Compares the alerts to the scheduled failures, each of which counts as a true positive if it
+/// was alerted, with the time from its onset until its first alert as the offset, and as a false
+/// negative otherwise. The alerts which cannot be attributed to a scheduled failure are false positives.
+fn validate_scheduled_alerts(
+    args: &RunArgs,
+    start_time: Duration,
+    alerts: &[Alert],
+) -> Vec<(MotorFailure, Accuracy)> {
+    let (detections, attributed) = attribute_alerts(args, start_time, alerts);
+    let mut accuracies: Vec<(MotorFailure, Accuracy)> = FAILURES
+        .iter()
+        .map(|failure| (*failure, Accuracy::default()))
+        .collect();
+    for detection in &detections {
+        let accuracy = get_accuracy(&mut accuracies, detection.scheduled_failure.failure);
+        match detection.latency {
+            Some(_) => accuracy.true_positives += 1,
+            None => accuracy.false_negatives += 1,
+        }
+    }
+    for (alert, _) in alerts
+        .iter()
+        .zip(&attributed)
+        .filter(|(_, attributed)| !**attributed)
+    {
+        get_accuracy(&mut accuracies, alert.failure).false_positives += 1;
+    }
+    for (failure, accuracy) in &mut accuracies {
+        let latencies: Vec<f64> = detections
+            .iter()
+            .filter(|detection| detection.scheduled_failure.failure == *failure)
+            .filter_map(|detection| detection.latency)
+            .collect();
+        accuracy.mean_offset = get_mean(&latencies);
+        accuracy.p95_offset = get_percentile(latencies, 0.95);
+    }
+    info!(
+        "Detected {} of {} scheduled failures, {} of {} alerts could not be attributed to one",
+        detections
+            .iter()
+            .filter(|detection| detection.latency.is_some())
+            .count(),
+        detections.len(),
+        attributed.iter().filter(|attributed| !**attributed).count(),
+        alerts.len()
+    );
+    accuracies
+}
+
+/// Detections of the scheduled failures by the alerts
+pub fn get_detections(args: &RunArgs, start_time: Duration, alerts: &[Alert]) -> Vec<Detection> {
+    attribute_alerts(args, start_time, alerts).0
+}
+
+/// Time of the run this long after its start, compressed like the schedule the motor driver passes on
+pub fn get_schedule_time(args: &RunArgs, start_time: Duration, offset_s: f64) -> f64 {
+    start_time.as_secs_f64() + get_time_scale(args).compress_secs(offset_s)
+}
+
+/// Attributes the alerts of a motor and failure type to the scheduled failure of that type whose
+/// onset they follow, up to the onset of the next scheduled failure of the motor, as the windows
+/// keep holding the readings of a failure for a while after it ended.
+/// Returns the detection of each scheduled failure, and whether each alert was attributed to one.
+fn attribute_alerts(
+    args: &RunArgs,
+    start_time: Duration,
+    alerts: &[Alert],
+) -> (Vec<Detection>, Vec<bool>) {
+    let mut attributed = vec![false; alerts.len()];
+    let detections = args
+        .failure_schedule
+        .iter()
+        .map(|scheduled_failure| {
+            let onset = get_schedule_time(args, start_time, scheduled_failure.start_s);
+            let attribution_end = args
+                .failure_schedule
+                .iter()
+                .filter(|other_failure| {
+                    other_failure.motor_id == scheduled_failure.motor_id
+                        && other_failure.start_s > scheduled_failure.start_s
+                })
+                .map(|other_failure| get_schedule_time(args, start_time, other_failure.start_s))
+                .fold(f64::INFINITY, f64::min);
+            let mut first_alert: Option<f64> = None;
+            for (alert, attributed) in alerts.iter().zip(attributed.iter_mut()) {
+                if alert.motor_id == scheduled_failure.motor_id
+                    && alert.failure == scheduled_failure.failure
+                    && (onset..attribution_end).contains(&alert.time)
+                {
+                    *attributed = true;
+                    first_alert = Some(first_alert.map_or(alert.time, |time| time.min(alert.time)));
+                }
+            }
+            Detection {
+                scheduled_failure: *scheduled_failure,
+                onset,
+                latency: first_alert.map(|time| time - onset),
+            }
+        })
+        .collect();
+    (detections, attributed)
+}
+
+fn get_expected_alerts(
+    args: &RunArgs,
+    request_processing_model: RequestProcessingModel,
+    start_time: Duration,
+    compact_wire: bool,
+) -> Vec<Alert> {
+    let mut expected_alerts = vec![];
+    for motor_id in 0..args.motor_groups_tcp as u32 {
+        let motor_id = motor_id + args.motor_groups_i2c as u32;
+        let readings: Vec<Vec<SensorMessage>> = (0..4u32)
+            .map(|sensor_id| {
+                generate_sensor_readings(
+                    motor_id.shl(2) + sensor_id,
+                    args,
+                    start_time,
+                    compact_wire,
+                )
+            })
+            .collect();
+        expected_alerts.append(&mut evaluate_motor(
+            motor_id,
+            &readings,
+            args,
+            request_processing_model,
+            start_time,
+        ));
+    }
+    expected_alerts
+}
+
+fn generate_sensor_readings(
+    sensor_id: u32,
+    args: &RunArgs,
+    start_time: Duration,
+    compact_wire: bool,
+) -> Vec<SensorMessage> {
+    let synthetic_signal = get_synthetic_signal(sensor_id, args);
+    // Same readings as the sensor, which skips the implausible lines of its data file
+    let plausible_readings = match synthetic_signal {
+        Some(_) => vec![],
+        None => {
+            let sensor_kind = SensorKind::from_sensor_id(sensor_id);
+            let data = fs::read_to_string(format!("{RESOURCE_PATH}/{}.txt", sensor_kind.name()))
+                .expect("Failure reading sensor data");
+            utils::quarantine::parse_sensor_data(&data, sensor_kind, args.data_column)
+        }
+    };
+    let mut rng = SmallRng::seed_from_u64(sensor_id as u64);
+    let end_time = start_time + get_run_duration(args);
+    let sampling_interval = get_sensor_sampling_interval(args);
+    // A sensor connecting after the startup barrier because of its jitter samples right away
+    let mut time = start_time
+        + SENSOR_CONNECTION_DELAY
+        + Duration::max(
+            Duration::from_millis(args.startup_barrier_ms),
+            utils::get_connect_jitter(sensor_id, args.sensor_connect_jitter_ms),
+        );
+    let mut readings = vec![];
+    let mut number_of_samples = 0;
+    while time < end_time {
+        let reading = match synthetic_signal {
+            Some(signal) => {
+                signal.get_reading(number_of_samples as u64 * sampling_interval.as_millis() as u64)
+            }
+            None => *plausible_readings
+                .iter()
+                .choose_stable(&mut rng)
+                .expect("Data file iterator is empty"),
+        };
+        // Same draw as the sensor, which only advances its generator if failures are injected
+        let reading =
+            if args.random_failure_prob > 0.0 && rng.gen::<f32>() < args.random_failure_prob {
+                RANDOM_FAILURE_READING
+            } else {
+                reading
+            };
+        if number_of_samples >= args.warmup_samples {
+            let message = SensorMessage {
+                reading,
+                sensor_id,
+                timestamp: time.as_secs_f64(),
+                sample_id: None,
+            };
+            readings.push(match compact_wire {
+                true => CompactSensorMessage::from(message).into(),
+                false => message,
+            });
+        }
+        number_of_samples += 1;
+        time += sampling_interval;
+    }
+    readings
+}
+
+/// Signal the sensor emits instead of the readings of its data file, with its period compressed
+/// by the time scale like the one the motor driver passes on
+fn get_synthetic_signal(sensor_id: u32, args: &RunArgs) -> Option<SyntheticSignal> {
+    args.synthetic_signals
+        .iter()
+        .find(|signal| signal.sensor_kind == SensorKind::from_sensor_id(sensor_id))
+        .map(|signal| SyntheticSignal {
+            period_ms: get_time_scale(args).compress_ms(signal.period_ms),
+            ..*signal
+        })
+}
+
+fn evaluate_motor(
+    motor_id: u32,
+    readings: &[Vec<SensorMessage>],
+    args: &RunArgs,
+    request_processing_model: RequestProcessingModel,
+    start_time: Duration,
+) -> Vec<Alert> {
+    let end_time = start_time + get_run_duration(args);
+    let window_kind = get_window_kind(args);
+    let window_sampling_interval = get_window_sampling_interval(args);
+    let mut tool_wear =
+        ToolWear::new(get_time_scale(args).compress_wear_model(get_wear_model(args)));
+    let mut alerts = vec![];
+    // The moving averages of the sensors, and the index of the next reading to feed into each
+    let mut moving_averages = match args.averaging {
+        AveragingStrategy::Window => None,
+        AveragingStrategy::Ewma { alpha } => Some(([Ewma::new(alpha); 4], [0; 4])),
+    };
+    let mut window_end = start_time + window_sampling_interval;
+    while window_end <= end_time {
+        let motor_averages = match &mut moving_averages {
+            None => get_window_averages(readings, window_end, window_kind),
+            Some((ewmas, next_readings)) => {
+                get_moving_averages(readings, window_end, ewmas, next_readings)
+            }
+        };
+        if let Some(motor_averages) = motor_averages {
+            if let Some(alert) = evaluate_averages(
+                motor_id,
+                motor_averages,
+                args,
+                request_processing_model,
+                start_time,
+                &mut tool_wear,
+            ) {
+                debug!("Expecting {alert:?}");
+                alerts.push(alert);
+            }
+        }
+        window_end += window_sampling_interval;
+    }
+    alerts
+}
+
+/// Averages of the windows of the sensors ending at the given time, if none of them is empty.
+/// A count-based window holds the last readings up to that time, however long ago they were taken.
+fn get_window_averages(
+    readings: &[Vec<SensorMessage>],
+    window_end: Duration,
+    window_kind: WindowKind,
+) -> Option<MotorAverages> {
+    let windows: Vec<Vec<&SensorMessage>> = readings
+        .iter()
+        .map(|sensor_readings| {
+            let mut window: Vec<&SensorMessage> = sensor_readings
+                .iter()
+                .filter(|message| {
+                    let timestamp = Duration::from_secs_f64(message.timestamp);
+                    timestamp <= window_end
+                        && match window_kind {
+                            WindowKind::TimeMs(window_size_ms) => {
+                                timestamp + Duration::from_millis(window_size_ms) > window_end
+                            }
+                            WindowKind::Count(_) => true,
+                        }
+                })
+                .collect();
+            if let WindowKind::Count(window_count) = window_kind {
+                window.drain(..window.len().saturating_sub(window_count));
+            }
+            window
+        })
+        .collect();
+    if windows.iter().any(|window| window.is_empty()) {
+        return None;
+    }
+    Some(MotorAverages {
+        averages: [0, 1, 2, 3].map(|index| {
+            windows[index]
+                .iter()
+                .map(|message| message.reading as f64)
+                .sum::<f64>()
+                / windows[index].len() as f64
+        }),
+        number_of_values: windows.iter().map(|window| window.len()).sum::<usize>() / 4,
+        time: windows
+            .iter()
+            .flatten()
+            .map(|message| message.timestamp)
+            .reduce(f64::max)
+            .expect("Trying to get time from empty windows"),
+    })
+}
+
+/// Feeds the readings up to the given time into the moving averages of the sensors, with the
+/// same recursion as the monitors, returning them if every sensor sent a reading so far
+fn get_moving_averages(
+    readings: &[Vec<SensorMessage>],
+    window_end: Duration,
+    ewmas: &mut [Ewma; 4],
+    next_readings: &mut [usize; 4],
+) -> Option<MotorAverages> {
+    for ((sensor_readings, ewma), next_reading) in readings
+        .iter()
+        .zip(ewmas.iter_mut())
+        .zip(next_readings.iter_mut())
+    {
+        while let Some(message) = sensor_readings
+            .get(*next_reading)
+            .filter(|message| Duration::from_secs_f64(message.timestamp) <= window_end)
+        {
+            ewma.update(message.reading as f64, message.timestamp);
+            *next_reading += 1;
+        }
+    }
+    let [air_temperature, process_temperature, rotational_speed, torque] =
+        ewmas.map(|ewma| ewma.get_value());
+    Some(MotorAverages {
+        averages: [
+            air_temperature?,
+            process_temperature?,
+            rotational_speed?,
+            torque?,
+        ],
+        number_of_values: ewmas
+            .iter()
+            .map(Ewma::get_effective_sample_count)
+            .sum::<usize>()
+            / 4,
+        time: ewmas
+            .iter()
+            .filter_map(Ewma::get_latest_timestamp)
+            .reduce(f64::max)?,
+    })
+}
+
+fn evaluate_averages(
+    motor_id: u32,
+    motor_averages: MotorAverages,
+    args: &RunArgs,
+    request_processing_model: RequestProcessingModel,
+    start_time: Duration,
+    tool_wear: &mut ToolWear,
+) -> Option<Alert> {
+    let MotorAverages {
+        averages,
+        number_of_values,
+        time,
+    } = motor_averages;
+    let readings = MotorReadings::from_channels(averages);
+    let wear = tool_wear.update(readings.rotational_speed, Duration::from_secs_f64(time));
+    let wear = match args.use_cumulative_age {
+        true => utils::get_cumulative_age(start_time, Duration::from_secs_f64(time)),
+        false => wear,
+    };
+    let failure: Option<MotorFailure> = match request_processing_model {
+        RequestProcessingModel::ClientServer | RequestProcessingModel::SpringQL => {
+            utils::sensor_data_indicates_failure(readings, wear, &args.rule_thresholds)
+        }
+        RequestProcessingModel::ReactiveStreaming | RequestProcessingModel::ObjectOriented => {
+            utils::averages_indicate_failure(
+                readings,
+                number_of_values,
+                wear,
+                &args.rule_thresholds,
+            )
+        }
+    };
+    failure.map(|failure| {
+        tool_wear.register_failure(failure, Duration::from_secs_f64(time));
+        Alert {
+            time,
+            motor_id: motor_id as u16,
+            failure,
+            fingerprint: None,
+            cleared: false,
+            sampled_messages: vec![],
+        }
+    })
+}
+
+fn count_mismatches(
+    expected_alerts: &[Alert],
+    alerts: &[Alert],
+    tolerance: f64,
+    alert_matching: AlertMatching,
+) -> usize {
+    get_accuracies(expected_alerts, alerts, tolerance, alert_matching)
+        .iter()
+        .map(|(_, accuracy)| accuracy.get_mismatches())
+        .sum()
+}
+
+/// Matches the expected alerts with received ones of the same motor and failure type
+/// within the tolerance, counting the matches and mismatches per failure type
+fn get_accuracies(
+    expected_alerts: &[Alert],
+    alerts: &[Alert],
+    tolerance: f64,
+    alert_matching: AlertMatching,
+) -> Vec<(MotorFailure, Accuracy)> {
+    let mut accuracies: Vec<(MotorFailure, Accuracy)> = FAILURES
+        .iter()
+        .map(|failure| (*failure, Accuracy::default()))
+        .collect();
+    let matches = match alert_matching {
+        AlertMatching::Optimal => get_optimal_matches(expected_alerts, alerts, tolerance),
+        AlertMatching::FirstFit => get_first_fit_matches(expected_alerts, alerts, tolerance),
+    };
+    let mut matched = vec![false; alerts.len()];
+    let mut offsets: Vec<(MotorFailure, f64)> = vec![];
+    for (expected_alert, alert_match) in expected_alerts.iter().zip(matches) {
+        match alert_match {
+            Some(index) => {
+                matched[index] = true;
+                offsets.push((
+                    expected_alert.failure,
+                    alerts[index].time - expected_alert.time,
+                ));
+                get_accuracy(&mut accuracies, expected_alert.failure).true_positives += 1;
+            }
+            None => get_accuracy(&mut accuracies, expected_alert.failure).false_negatives += 1,
+        }
+    }
+    for (alert, _) in alerts.iter().zip(matched).filter(|(_, matched)| !matched) {
+        get_accuracy(&mut accuracies, alert.failure).false_positives += 1;
+    }
+    for (failure, accuracy) in &mut accuracies {
+        let failure_offsets: Vec<f64> = offsets
+            .iter()
+            .filter(|(offset_failure, _)| offset_failure == failure)
+            .map(|(_, offset)| *offset)
+            .collect();
+        accuracy.mean_offset = get_mean(&failure_offsets);
+        accuracy.p95_offset = get_percentile(
+            failure_offsets.iter().map(|offset| offset.abs()).collect(),
+            0.95,
+        );
+    }
+    accuracies
+}
+
+/// Index of the received alert matched with each expected one, matching as many as possible.
+/// As the tolerance is the same for all expected alerts, handing each of them, in the order of
+/// their time, the earliest unmatched received alert which is not too early for it yields a
+/// maximum matching: a received alert too early for an expected alert is also too early for all
+/// later ones, so a missing or extra alert cannot shift the matches of the following ones.
+fn get_optimal_matches(
+    expected_alerts: &[Alert],
+    alerts: &[Alert],
+    tolerance: f64,
+) -> Vec<Option<usize>> {
+    let mut matches = vec![None; expected_alerts.len()];
+    let mut unmatched = get_indices_by_time(alerts);
+    for expected_index in get_indices_by_time(expected_alerts) {
+        let expected_alert = &expected_alerts[expected_index];
+        let candidate = unmatched.iter().position(|index| {
+            let alert = &alerts[*index];
+            alert.motor_id == expected_alert.motor_id
+                && alert.failure == expected_alert.failure
+                && alert.time >= expected_alert.time - tolerance
+        });
+        if let Some(position) = candidate
+            .filter(|position| alerts[unmatched[*position]].time <= expected_alert.time + tolerance)
+        {
+            matches[expected_index] = Some(unmatched.remove(position));
+        }
+    }
+    matches
+}
+
+/// Index of the received alert matched with each expected one, taking the first received alert
+/// within the tolerance in the order they arrived
+fn get_first_fit_matches(
+    expected_alerts: &[Alert],
+    alerts: &[Alert],
+    tolerance: f64,
+) -> Vec<Option<usize>> {
+    let mut unmatched: Vec<usize> = (0..alerts.len()).collect();
+    expected_alerts
+        .iter()
+        .map(|expected_alert| {
+            unmatched
+                .iter()
+                .position(|index| {
+                    let alert = &alerts[*index];
+                    alert.motor_id == expected_alert.motor_id
+                        && alert.failure == expected_alert.failure
+                        && (alert.time - expected_alert.time).abs() <= tolerance
+                })
+                .map(|position| unmatched.remove(position))
+        })
+        .collect()
+}
+
+fn get_indices_by_time(alerts: &[Alert]) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..alerts.len()).collect();
+    indices.sort_by(|index, other_index| alerts[*index].time.total_cmp(&alerts[*other_index].time));
+    indices
+}
+
+fn get_mean(values: &[f64]) -> Option<f64> {
+    match values.len() {
+        0 => None,
+        len => Some(values.iter().sum::<f64>() / len as f64),
+    }
+}
+
+/// Nearest-rank percentile of the values, if there are any
+fn get_percentile(mut values: Vec<f64>, percentile: f64) -> Option<f64> {
+    values.sort_by(f64::total_cmp);
+    let rank = (percentile * values.len() as f64).ceil() as usize;
+    values.get(rank.saturating_sub(1)).copied()
+}
+
+fn get_accuracy(
+    accuracies: &mut [(MotorFailure, Accuracy)],
+    failure: MotorFailure,
+) -> &mut Accuracy {
+    &mut accuracies
+        .iter_mut()
+        .find(|(accuracy_failure, _)| *accuracy_failure == failure)
+        .expect("Every failure type has an accuracy")
+        .1
+}
+
+fn get_ratio(numerator: usize, denominator: usize) -> Option<f64> {
+    match denominator {
+        0 => None,
+        _ => Some(numerator as f64 / denominator as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_transfer_objects::DataColumn;
+
+    const TOLERANCE: f64 = 1.0;
+
+    fn get_alert(time: f64) -> Alert {
+        Alert {
+            time,
+            motor_id: 0,
+            failure: MotorFailure::PowerFailure,
+            fingerprint: None,
+            cleared: false,
+            sampled_messages: vec![],
+        }
+    }
+
+    /// Alerts of a burst in the order they arrive, the latest first
+    fn get_reversed_burst(times: impl Iterator<Item = f64>) -> Vec<Alert> {
+        let mut alerts: Vec<Alert> = times.map(get_alert).collect();
+        alerts.reverse();
+        alerts
+    }
+
+    fn get_power_failure_accuracy(
+        expected_alerts: &[Alert],
+        alerts: &[Alert],
+        alert_matching: AlertMatching,
+    ) -> Accuracy {
+        get_accuracies(expected_alerts, alerts, TOLERANCE, alert_matching)
+            .into_iter()
+            .find(|(failure, _)| *failure == MotorFailure::PowerFailure)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn rounding_keeps_every_dataset_reading() {
+        for sensor_id in 0..4u32 {
+            let sensor_kind = SensorKind::from_sensor_id(sensor_id);
+            let data =
+                fs::read_to_string(format!("{RESOURCE_PATH}/{}.txt", sensor_kind.name())).unwrap();
+            let readings =
+                utils::quarantine::parse_sensor_data(&data, sensor_kind, DataColumn::Last);
+            assert!(!readings.is_empty());
+            for reading in readings {
+                let message = SensorMessage {
+                    reading,
+                    sensor_id,
+                    timestamp: 0.0,
+                    sample_id: None,
+                };
+                let rounded = SensorMessage::from(CompactSensorMessage::from(message));
+                assert_eq!(rounded.reading, reading);
+            }
+        }
+    }
+
+    #[test]
+    fn rounding_does_not_change_threshold_decisions() {
+        let start_time = Duration::from_secs_f64(1_700_000_000.123_456);
+        for request_processing_model in ["ClientServer", "ReactiveStreaming"] {
+            let args = crate::parse_args([
+                "test_driver",
+                request_processing_model,
+                "--duration",
+                "60",
+                "--sensor-sampling-interval-ms",
+                "100",
+                "--window-size-ms",
+                "200",
+            ])
+            .unwrap();
+            let request_processing_model = args.request_processing_model.unwrap();
+            let exact_alerts =
+                get_expected_alerts(&args, request_processing_model, start_time, false);
+            let rounded_alerts =
+                get_expected_alerts(&args, request_processing_model, start_time, true);
+            assert!(!exact_alerts.is_empty());
+            assert_eq!(exact_alerts.len(), rounded_alerts.len());
+            assert_eq!(
+                count_mismatches(
+                    &exact_alerts,
+                    &rounded_alerts,
+                    get_window_sampling_interval(&args).as_secs_f64(),
+                    AlertMatching::Optimal,
+                ),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn doubled_time_scale_samples_the_nominal_duration_in_half_the_wall_time() {
+        let start_time = Duration::from_secs(1_700_000_000);
+        let get_readings = |time_scale: &str| {
+            let args = crate::parse_args([
+                "test_driver",
+                "ClientServer",
+                "--duration",
+                "60",
+                "--sensor-sampling-interval-ms",
+                "100",
+                "--time-scale",
+                time_scale,
+            ])
+            .unwrap();
+            generate_sensor_readings(0, &args, start_time, false)
+        };
+        let nominal_readings = get_readings("1");
+        let compressed_readings = get_readings("2");
+        // The sensors connect within the same wall time, after which the nominal 58 s are
+        // sampled every 100 ms, and the compressed 28 s every 50 ms
+        assert_eq!(nominal_readings.len(), 580);
+        assert_eq!(compressed_readings.len(), 560);
+        let end_time = start_time.as_secs_f64() + 30.0;
+        assert!(compressed_readings.last().unwrap().timestamp < end_time);
+        let interval = compressed_readings[1].timestamp - compressed_readings[0].timestamp;
+        assert!((interval - 0.05).abs() < 1e-6, "{interval}");
+    }
+
+    #[test]
+    fn missing_alert_of_burst_is_one_mismatch() {
+        let expected_alerts: Vec<Alert> = (0..10).map(|second| get_alert(second as f64)).collect();
+        let alerts = get_reversed_burst((1..10).map(|second| second as f64));
+        for alert_matching in [AlertMatching::Optimal, AlertMatching::FirstFit] {
+            let accuracy = get_power_failure_accuracy(&expected_alerts, &alerts, alert_matching);
+            assert_eq!(accuracy.true_positives, 9, "{alert_matching:?}");
+            assert_eq!(accuracy.get_mismatches(), 1, "{alert_matching:?}");
+        }
+    }
+
+    #[test]
+    fn early_alert_does_not_take_the_match_of_a_later_one() {
+        let expected_alerts = vec![get_alert(0.0), get_alert(1.0)];
+        // The first received alert is within the tolerance of both expected ones
+        let alerts = vec![get_alert(0.9), get_alert(-0.5)];
+        assert_eq!(
+            count_mismatches(&expected_alerts, &alerts, TOLERANCE, AlertMatching::Optimal),
+            0
+        );
+        assert_eq!(
+            count_mismatches(
+                &expected_alerts,
+                &alerts,
+                TOLERANCE,
+                AlertMatching::FirstFit
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn precision_and_recall_count_the_unmatched_alerts() {
+        let expected_alerts = vec![
+            get_alert(0.0),
+            get_alert(3.0),
+            get_alert(6.0),
+            get_alert(9.0),
+        ];
+        // The alert at 9 s is missed, the ones at 20 s and 30 s were not expected
+        let alerts = vec![
+            get_alert(0.0),
+            get_alert(3.0),
+            get_alert(6.0),
+            get_alert(20.0),
+            get_alert(30.0),
+        ];
+        for alert_matching in [AlertMatching::Optimal, AlertMatching::FirstFit] {
+            let accuracy = get_power_failure_accuracy(&expected_alerts, &alerts, alert_matching);
+            assert_eq!(
+                accuracy.get_precision(),
+                Some(3.0 / 5.0),
+                "{alert_matching:?}"
+            );
+            assert_eq!(accuracy.get_recall(), Some(3.0 / 4.0), "{alert_matching:?}");
+            assert_eq!(
+                accuracy.get_f1_score(),
+                Some(2.0 / 3.0),
+                "{alert_matching:?}"
+            );
+        }
+        let no_alerts = Accuracy::default();
+        assert_eq!(no_alerts.get_precision(), None);
+        assert_eq!(no_alerts.get_recall(), None);
+    }
+
+    #[test]
+    fn duplicated_alerts_are_false_positives() {
+        let expected_alerts = vec![get_alert(0.0), get_alert(5.0)];
+        let alerts = vec![
+            get_alert(0.0),
+            get_alert(0.0),
+            get_alert(5.0),
+            get_alert(5.0),
+        ];
+        for alert_matching in [AlertMatching::Optimal, AlertMatching::FirstFit] {
+            let accuracy = get_power_failure_accuracy(&expected_alerts, &alerts, alert_matching);
+            assert_eq!(accuracy.true_positives, 2, "{alert_matching:?}");
+            assert_eq!(accuracy.false_positives, 2, "{alert_matching:?}");
+            assert_eq!(accuracy.false_negatives, 0, "{alert_matching:?}");
+        }
+    }
+
+    #[test]
+    fn shifted_burst_is_matched_with_its_offset() {
+        let expected_alerts: Vec<Alert> =
+            (0..10).map(|index| get_alert(index as f64 * 0.5)).collect();
+        // Received 0.8 s early, e.g. due to a clock offset of the monitor
+        let alerts = get_reversed_burst((0..10).map(|index| index as f64 * 0.5 - 0.8));
+        let accuracy =
+            get_power_failure_accuracy(&expected_alerts, &alerts, AlertMatching::Optimal);
+        assert_eq!(accuracy.get_mismatches(), 0);
+        assert!((accuracy.mean_offset.unwrap() + 0.8).abs() < 1e-9);
+        assert!((accuracy.p95_offset.unwrap() - 0.8).abs() < 1e-9);
+        // The latest alerts, which arrive first, are taken by the earliest expected ones
+        assert_eq!(
+            count_mismatches(
+                &expected_alerts,
+                &alerts,
+                TOLERANCE,
+                AlertMatching::FirstFit
+            ),
+            6
+        );
+    }
+
+    #[test]
+    fn count_window_averages_the_latest_readings_however_old() {
+        let readings: Vec<Vec<SensorMessage>> = (0..4)
+            .map(|sensor_id| {
+                (0..30)
+                    .map(|index| SensorMessage {
+                        reading: (index * (sensor_id + 1)) as f32,
+                        sensor_id,
+                        timestamp: index as f64 / 10.0,
+                        sample_id: None,
+                    })
+                    .collect()
+            })
+            .collect();
+        // The sensors stopped sending 7 s before the end of the window
+        let window_end = Duration::from_secs(10);
+        let averages = get_window_averages(&readings, window_end, WindowKind::Count(20)).unwrap();
+        // The average of the readings 10 to 29, scaled per sensor
+        assert_eq!(averages.averages, [19.5, 39.0, 58.5, 78.0]);
+        assert_eq!(averages.number_of_values, 20);
+        assert_eq!(averages.time, 2.9);
+        assert!(get_window_averages(&readings, window_end, WindowKind::TimeMs(1000)).is_none());
+        // Readings after the end of the window are not part of it
+        let averages =
+            get_window_averages(&readings, Duration::from_secs(1), WindowKind::Count(20)).unwrap();
+        assert_eq!(averages.number_of_values, 11);
+    }
+
+    #[test]
+    fn overstrain_alerts_of_a_high_torque_series_follow_the_wear_model() {
+        let start_time = Duration::from_secs(1000);
+        // 10 s of constant readings every 100 ms, with a power within its limits and a torque
+        // for which a wear above 220 s overstrains the tool.
+        // The test `overstrain_alerts_of_a_high_torque_series_follow_the_wear_model` of the
+        // client server monitor expects the same alerts for the same series.
+        let readings: Vec<Vec<SensorMessage>> = [300.0, 290.0, 1500.0, 50.0]
+            .into_iter()
+            .enumerate()
+            .map(|(sensor_id, reading)| {
+                (1..=100)
+                    .map(|index| SensorMessage {
+                        reading,
+                        sensor_id: sensor_id as u32,
+                        timestamp: start_time.as_secs_f64() + index as f64 / 10.0,
+                        sample_id: None,
+                    })
+                    .collect()
+            })
+            .collect();
+        let args = crate::parse_args([
+            "test_driver",
+            "ClientServer",
+            "--duration",
+            "10",
+            "--window-size-ms",
+            "1000",
+            "--wear-rate",
+            "100",
+        ])
+        .unwrap();
+        let alerts = evaluate_motor(
+            0,
+            &readings,
+            &args,
+            RequestProcessingModel::ClientServer,
+            start_time,
+        );
+        // The wear starts with the first window, grows by 100 s per window and is reset by
+        // each overstrain alert
+        assert!(alerts
+            .iter()
+            .all(|alert| alert.failure == MotorFailure::OverstrainFailure));
+        assert_eq!(
+            alerts.iter().map(|alert| alert.time).collect::<Vec<f64>>(),
+            vec![1004.0, 1007.0, 1010.0]
+        );
+    }
+
+    #[test]
+    fn cumulative_age_is_counted_from_the_connection_of_the_sensors() {
+        let start_time = Duration::from_secs(1000);
+        // Readings every 100 ms once the sensors connected, with a power within its limits and a
+        // torque for which an age above 22 s overstrains the tool.
+        // The test `cumulative_age_is_counted_from_the_connection_of_the_sensors` of the
+        // client server monitor expects the same first alert for the same series.
+        let readings: Vec<Vec<SensorMessage>> = [300.0, 290.0, 150.0, 500.0]
+            .into_iter()
+            .enumerate()
+            .map(|(sensor_id, reading)| {
+                (21..=300)
+                    .map(|index| SensorMessage {
+                        reading,
+                        sensor_id: sensor_id as u32,
+                        timestamp: start_time.as_secs_f64() + index as f64 / 10.0,
+                        sample_id: None,
+                    })
+                    .collect()
+            })
+            .collect();
+        let args = crate::parse_args([
+            "test_driver",
+            "ClientServer",
+            "--duration",
+            "30",
+            "--window-size-ms",
+            "1000",
+            "--use-cumulative-age",
+        ])
+        .unwrap();
+        let alerts = evaluate_motor(
+            0,
+            &readings,
+            &args,
+            RequestProcessingModel::ClientServer,
+            start_time,
+        );
+        // The age is not reset by the alerts, so the motor keeps overstraining
+        assert!(alerts
+            .iter()
+            .all(|alert| alert.failure == MotorFailure::OverstrainFailure));
+        assert_eq!(
+            alerts.iter().map(|alert| alert.time).collect::<Vec<f64>>(),
+            vec![1025.0, 1026.0, 1027.0, 1028.0, 1029.0, 1030.0]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Measures the cost of the debug logging on the hot path of a monitor, which decodes and logs each
+//! sensor message, once with logging disabled and once with debug logging at several sample rates.
+//! Run with `cargo bench -p utils --bench log_sampling`.
+
+use std::hint::black_box;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use log::{debug, log_enabled, Level, LevelFilter, Log, Metadata, Record};
+
+use data_transfer_objects::SensorMessage;
+use utils::LogSampler;
+
+const MESSAGES: u32 = 2_000_000;
+const ROUNDS: u32 = 5;
+
+/// Formats the records like a logger writing to stderr would, but discards them
+struct DiscardingLogger;
+
+impl Log for DiscardingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        let _ = writeln!(io::sink(), "{} {}", record.level(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+fn get_frame() -> Vec<u8> {
+    utils::encode_object(&SensorMessage {
+        reading: 300.0,
+        sensor_id: 4,
+        timestamp: 1_700_000_000.0,
+        sample_id: None,
+    })
+    .expect("Could not encode sensor message")
+}
+
+/// The hot path before the logging was gated, logging every message
+fn handle_ungated(frame: &[u8]) {
+    let message: SensorMessage =
+        utils::decode_object(&mut frame.to_vec()).expect("Could not decode sensor message");
+    debug!("{message:?}");
+    black_box(message);
+}
+
+/// The hot path as the monitors run it, logging every `rate`th message
+fn handle_gated(frame: &[u8], log_sampler: &mut LogSampler) {
+    let message: SensorMessage =
+        utils::decode_object(&mut frame.to_vec()).expect("Could not decode sensor message");
+    if log_enabled!(Level::Debug) && log_sampler.sample() {
+        debug!("{message:?}");
+    }
+    black_box(message);
+}
+
+/// Fastest of the rounds, in ns per message
+fn measure(mut handle: impl FnMut()) -> f64 {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..MESSAGES {
+                handle();
+            }
+            start.elapsed().as_secs_f64() * 1e9 / MESSAGES as f64
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn report(name: &str, time: f64, baseline: f64) {
+    println!(
+        "{name:<32} {time:>8.1} ns/message {:>+7.1} %",
+        (time / baseline - 1.0) * 100.0
+    );
+}
+
+fn main() {
+    log::set_logger(&DiscardingLogger).expect("Could not set logger");
+    let frame = get_frame();
+
+    log::set_max_level(LevelFilter::Info);
+    // Warms up the caches and the clock frequency of the CPU
+    measure(|| handle_ungated(&frame));
+    let ungated = measure(|| handle_ungated(&frame));
+    let mut log_sampler = LogSampler::new(1);
+    let gated = measure(|| handle_gated(&frame, &mut log_sampler));
+    report("logging disabled, ungated", ungated, ungated);
+    report("logging disabled, gated", gated, ungated);
+
+    log::set_max_level(LevelFilter::Debug);
+    for rate in [1, 100, 1000] {
+        let mut log_sampler = LogSampler::new(rate);
+        let sampled = measure(|| handle_gated(&frame, &mut log_sampler));
+        report(
+            &format!("debug logging, sample rate {rate}"),
+            sampled,
+            ungated,
+        );
+    }
+}
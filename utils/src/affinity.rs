@@ -0,0 +1,87 @@
+use std::{io, mem};
+
+use log::info;
+
+use data_transfer_objects::MotorMonitorParameters;
+
+/// Pins the monitor to its CPU cores, if it has any, so that its threads do not migrate to other
+/// cores during the run. Threads inherit the affinity of the thread spawning them, so this has to
+/// be called on the main thread before any other thread is spawned, which then pins the worker
+/// threads of the pools and engines as well.
+pub fn pin_to_cpu_cores(motor_monitor_parameters: &MotorMonitorParameters) {
+    let cpu_cores = &motor_monitor_parameters.cpu_cores;
+    if cpu_cores.is_empty() {
+        return;
+    }
+    set_affinity(cpu_cores)
+        .unwrap_or_else(|e| panic!("Could not pin monitor to CPU cores {cpu_cores:?}: {e}"));
+    info!("Pinned monitor to CPU cores {cpu_cores:?}");
+}
+
+fn set_affinity(cpu_cores: &[usize]) -> io::Result<()> {
+    // Safety: the set is a plain bit mask, only accessed through the libc macros within its size
+    unsafe {
+        let mut cpu_set: libc::cpu_set_t = mem::zeroed();
+        for &cpu_core in cpu_cores {
+            if cpu_core >= libc::CPU_SETSIZE as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("core {cpu_core} exceeds the size of the CPU set"),
+                ));
+            }
+            libc::CPU_SET(cpu_core, &mut cpu_set);
+        }
+        if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &cpu_set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    /// Cores the calling thread may run on
+    fn get_affinity() -> Vec<usize> {
+        // Safety: see set_affinity
+        unsafe {
+            let mut cpu_set: libc::cpu_set_t = mem::zeroed();
+            assert_eq!(
+                libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut cpu_set),
+                0
+            );
+            (0..libc::CPU_SETSIZE as usize)
+                .filter(|&cpu_core| libc::CPU_ISSET(cpu_core, &cpu_set))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn worker_threads_report_the_configured_affinity() {
+        let cpu_cores = vec![*get_affinity().last().unwrap()];
+        let configured_cpu_cores = cpu_cores.clone();
+        // Pinned on a thread of its own, so that the threads of the other tests are not
+        let worker_affinities = thread::spawn(move || {
+            set_affinity(&configured_cpu_cores).unwrap();
+            let workers: Vec<_> = (0..4).map(|_| thread::spawn(get_affinity)).collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .join()
+        .unwrap();
+        for worker_affinity in worker_affinities {
+            assert_eq!(worker_affinity, cpu_cores);
+        }
+    }
+
+    #[test]
+    fn core_beyond_the_cpu_set_is_rejected() {
+        let error = set_affinity(&[libc::CPU_SETSIZE as usize]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
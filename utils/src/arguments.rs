@@ -0,0 +1,444 @@
+use std::str::FromStr;
+
+use data_transfer_objects::{
+    AlertWebhook, AveragingStrategy, BenchmarkOutput, MotorMonitorParameters,
+    RequestProcessingModel, RouteRule, RuleThresholds, ToolChange, Transport, WearModel,
+    WindowKind,
+};
+
+/// Only the client server monitor reads sensors over i2c, the other monitors would
+/// otherwise wait for tcp connections of the i2c sensors which never arrive
+pub fn reject_i2c_motor_groups(motor_monitor_parameters: &MotorMonitorParameters) {
+    assert_eq!(
+        motor_monitor_parameters.number_of_i2c_motor_groups, 0,
+        "The {:?} monitor does not support i2c motor groups",
+        motor_monitor_parameters.request_processing_model
+    );
+}
+
+pub fn get_motor_monitor_parameters(arguments: &[String]) -> MotorMonitorParameters {
+    let window_size_ms = arguments
+        .get(6)
+        .expect("Did not receive at least 6 arguments")
+        .parse()
+        .expect("Could not parse window_size successfully");
+    MotorMonitorParameters {
+        start_time: arguments
+            .get(1)
+            .expect("Did not receive at least 2 arguments")
+            .parse()
+            .expect("Could not parse start_time successfully"),
+        duration: arguments
+            .get(2)
+            .expect("Did not receive at least 3 arguments")
+            .parse()
+            .expect("Could not parse duration successfully"),
+        request_processing_model: RequestProcessingModel::from_str(
+            arguments
+                .get(3)
+                .expect("Did not receive at least 4 arguments"),
+        )
+        .expect("Could not parse Request Processing Model successfully"),
+        number_of_tcp_motor_groups: arguments
+            .get(4)
+            .expect("Did not receive at least 5 arguments")
+            .parse()
+            .expect("Could not parse number_of_motor_groups successfully"),
+        number_of_i2c_motor_groups: arguments
+            .get(5)
+            .expect("Did not receive at least 5 arguments")
+            .parse()
+            .expect("Could not parse number_of_motor_groups successfully"),
+        window_size_ms,
+        sensor_listen_address: arguments
+            .get(7)
+            .expect("Did not receive at least 7 arguments")
+            .parse()
+            .expect("Could not parse sensor listen address successfully"),
+        motor_monitor_listen_address: arguments
+            .get(8)
+            .expect("Did not receive at least 8 arguments")
+            .parse()
+            .expect("Could not parse motor monitor listen address successfully"),
+        window_sampling_interval: arguments
+            .get(9)
+            .expect("Did not receive at least 9 arguments")
+            .parse()
+            .expect("Could not parse sampling_interval successfully"),
+        sensor_sampling_interval: arguments
+            .get(10)
+            .expect("Did not receive at least 9 arguments")
+            .parse()
+            .expect("Could not parse sampling_interval successfully"),
+        thread_pool_size: arguments
+            .get(11)
+            .expect("Did not receive at least 10 arguments")
+            .parse()
+            .expect("Could not parse thread_pool_size successfully"),
+        wear_model: WearModel {
+            wear_rate: arguments
+                .get(12)
+                .expect("Did not receive at least 12 arguments")
+                .parse()
+                .expect("Could not parse wear_rate successfully"),
+            rotational_speed_threshold: arguments
+                .get(13)
+                .expect("Did not receive at least 13 arguments")
+                .parse()
+                .expect("Could not parse rotational_speed_threshold successfully"),
+            tool_change: ToolChange::from_interval_ms(
+                arguments
+                    .get(14)
+                    .expect("Did not receive at least 14 arguments")
+                    .parse()
+                    .expect("Could not parse tool_change_interval_ms successfully"),
+            ),
+        },
+        use_cumulative_age: arguments
+            .get(15)
+            .expect("Did not receive at least 15 arguments")
+            .parse()
+            .expect("Could not parse use_cumulative_age successfully"),
+        sensor_bind_port: arguments
+            .get(16)
+            .expect("Did not receive at least 16 arguments")
+            .parse()
+            .expect("Could not parse sensor_bind_port successfully"),
+        log_sample_rate: get_log_sample_rate(arguments),
+        buffer_alerts: arguments
+            .iter()
+            .any(|argument| argument == "--buffer-alerts"),
+        compress_alerts: arguments
+            .iter()
+            .any(|argument| argument == "--compress-alerts"),
+        detailed_alerts: arguments
+            .iter()
+            .any(|argument| argument == "--detailed-alerts"),
+        strict_deserialization: arguments
+            .iter()
+            .any(|argument| argument == "--strict-deserialization"),
+        alert_webhooks: get_alert_webhooks(arguments),
+        routing: get_routing(arguments),
+        benchmark_output: get_benchmark_output(arguments),
+        run_id: get_run_id(arguments),
+        compact_wire: arguments
+            .iter()
+            .any(|argument| argument == "--compact-wire"),
+        transport: get_transport(arguments),
+        forensic_alerts: arguments
+            .iter()
+            .any(|argument| argument == "--forensic-alerts"),
+        crash_file: get_crash_file(arguments),
+        memory_limit_mb: get_memory_limit(arguments),
+        max_quarantine_rate: get_max_quarantine_rate(arguments),
+        window_slide_ms: get_window_slide(arguments),
+        window_allowed_delay_ms: get_window_allowed_delay(arguments),
+        cpu_cores: get_cpu_cores(arguments),
+        startup_barrier_ms: get_startup_barrier(arguments),
+        averaging: get_averaging(arguments),
+        track_provenance: arguments
+            .iter()
+            .any(|argument| argument == "--track-provenance"),
+        max_sensor_connections: get_max_sensor_connections(arguments),
+        reject_excess_connections: arguments
+            .iter()
+            .any(|argument| argument == "--reject-excess-connections"),
+        expect_sensor_order: arguments
+            .iter()
+            .any(|argument| argument == "--expect-sensor-order"),
+        interpolate_gaps: arguments
+            .iter()
+            .any(|argument| argument == "--interpolate-gaps"),
+        otel_endpoint: get_otel_endpoint(arguments),
+        rule_thresholds: get_rule_thresholds(arguments),
+        report_post: arguments.iter().any(|argument| argument == "--report-post"),
+        no_cloud: arguments.iter().any(|argument| argument == "--no-cloud"),
+        window_export: get_window_export(arguments),
+        window_kind: get_window_kind(arguments, window_size_ms),
+    }
+}
+
+/// Reads the optional `--log-sample-rate <n>` argument, logging every message if it is absent
+fn get_log_sample_rate(arguments: &[String]) -> u64 {
+    arguments
+        .iter()
+        .position(|argument| argument == "--log-sample-rate")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a rate after --log-sample-rate")
+                .parse()
+                .expect("Could not parse log_sample_rate successfully")
+        })
+        .unwrap_or(1)
+}
+
+/// Reads the optional `--crash-file <path>` argument
+fn get_crash_file(arguments: &[String]) -> Option<String> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--crash-file")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a path after --crash-file")
+                .clone()
+        })
+}
+
+/// Reads the optional `--memory-limit-mb <mb>` argument
+fn get_memory_limit(arguments: &[String]) -> Option<u64> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--memory-limit-mb")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a limit after --memory-limit-mb")
+                .parse()
+                .expect("Could not parse memory limit successfully")
+        })
+}
+
+/// Reads the optional `--otel-endpoint <endpoint>` argument
+fn get_otel_endpoint(arguments: &[String]) -> Option<String> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--otel-endpoint")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive an endpoint after --otel-endpoint")
+                .clone()
+        })
+}
+
+/// Reads the optional `--window-export <path>` argument
+fn get_window_export(arguments: &[String]) -> Option<String> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--window-export")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a path after --window-export")
+                .clone()
+        })
+}
+
+/// Reads the optional `--rule-thresholds <thresholds>` argument, using the default ones if it is absent
+fn get_rule_thresholds(arguments: &[String]) -> RuleThresholds {
+    arguments
+        .iter()
+        .position(|argument| argument == "--rule-thresholds")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive thresholds after --rule-thresholds")
+                .parse()
+                .expect("Could not parse rule_thresholds successfully")
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the optional `--max-sensor-connections <n>` argument
+fn get_max_sensor_connections(arguments: &[String]) -> Option<usize> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--max-sensor-connections")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a number after --max-sensor-connections")
+                .parse()
+                .expect("Could not parse max_sensor_connections successfully")
+        })
+}
+
+/// Reads the optional `--max-quarantine-rate <rate>` argument
+fn get_max_quarantine_rate(arguments: &[String]) -> Option<f32> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--max-quarantine-rate")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a rate after --max-quarantine-rate")
+                .parse()
+                .expect("Could not parse max_quarantine_rate successfully")
+        })
+}
+
+/// Reads the optional `--window-count <n>` argument, bounding the windows by the window size
+/// if it is absent
+fn get_window_kind(arguments: &[String], window_size_ms: u64) -> WindowKind {
+    arguments
+        .iter()
+        .position(|argument| argument == "--window-count")
+        .map_or(WindowKind::TimeMs(window_size_ms), |index| {
+            WindowKind::Count(
+                arguments
+                    .get(index + 1)
+                    .expect("Did not receive a count after --window-count")
+                    .parse()
+                    .expect("Could not parse window_count successfully"),
+            )
+        })
+}
+
+/// Reads the optional `--window-slide-ms <ms>` argument
+fn get_window_slide(arguments: &[String]) -> Option<u64> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--window-slide-ms")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a slide after --window-slide-ms")
+                .parse()
+                .expect("Could not parse window_slide_ms successfully")
+        })
+}
+
+/// Reads the optional `--window-allowed-delay-ms <ms>` argument, defaulting to 0
+fn get_window_allowed_delay(arguments: &[String]) -> u64 {
+    arguments
+        .iter()
+        .position(|argument| argument == "--window-allowed-delay-ms")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a delay after --window-allowed-delay-ms")
+                .parse()
+                .expect("Could not parse window_allowed_delay_ms successfully")
+        })
+        .unwrap_or(0)
+}
+
+/// Reads the optional `--cpu-cores <core,...>` argument, defaulting to no cores
+fn get_cpu_cores(arguments: &[String]) -> Vec<usize> {
+    arguments
+        .iter()
+        .position(|argument| argument == "--cpu-cores")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive cores after --cpu-cores")
+                .split(',')
+                .map(|cpu_core| {
+                    cpu_core
+                        .parse()
+                        .expect("Could not parse cpu_cores successfully")
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the optional `--startup-barrier-ms <ms>` argument, defaulting to 0
+fn get_startup_barrier(arguments: &[String]) -> u64 {
+    arguments
+        .iter()
+        .position(|argument| argument == "--startup-barrier-ms")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a barrier after --startup-barrier-ms")
+                .parse()
+                .expect("Could not parse startup_barrier_ms successfully")
+        })
+        .unwrap_or(0)
+}
+
+/// Reads the optional `--averaging <window|ewma:alpha>` argument, averaging windows if it is absent
+fn get_averaging(arguments: &[String]) -> AveragingStrategy {
+    arguments
+        .iter()
+        .position(|argument| argument == "--averaging")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a strategy after --averaging")
+                .parse()
+                .expect("Could not parse averaging successfully")
+        })
+        .unwrap_or(AveragingStrategy::Window)
+}
+
+/// Reads the optional `--benchmark-output <stdout|path|fd:n|tcp:address>` argument,
+/// writing the benchmark data to stdout if it is absent
+fn get_benchmark_output(arguments: &[String]) -> BenchmarkOutput {
+    arguments
+        .iter()
+        .position(|argument| argument == "--benchmark-output")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive an output after --benchmark-output")
+                .parse()
+                .expect("Could not parse benchmark_output successfully")
+        })
+        .unwrap_or(BenchmarkOutput::Stdout)
+}
+
+/// Reads the optional `--run-id <id>` argument, defaulting to 0
+fn get_run_id(arguments: &[String]) -> u64 {
+    arguments
+        .iter()
+        .position(|argument| argument == "--run-id")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive an id after --run-id")
+                .parse()
+                .expect("Could not parse run_id successfully")
+        })
+        .unwrap_or(0)
+}
+
+/// Reads the optional `--transport <tcp|local|uds>` argument, defaulting to TCP
+fn get_transport(arguments: &[String]) -> Transport {
+    arguments
+        .iter()
+        .position(|argument| argument == "--transport")
+        .map(|index| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a transport after --transport")
+                .parse()
+                .expect("Could not parse transport successfully")
+        })
+        .unwrap_or(Transport::Tcp)
+}
+
+/// Reads the repeatable `--alert-webhook <motor_id>=<url>` argument
+fn get_alert_webhooks(arguments: &[String]) -> Vec<AlertWebhook> {
+    arguments
+        .iter()
+        .enumerate()
+        .filter(|(_, argument)| *argument == "--alert-webhook")
+        .map(|(index, _)| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a webhook after --alert-webhook")
+                .parse()
+                .expect("Could not parse alert webhook successfully")
+        })
+        .collect()
+}
+
+/// Reads the repeatable `--route <label>=<failure|*>:<cloud|drop>` argument, in the order of the rules
+fn get_routing(arguments: &[String]) -> Vec<RouteRule> {
+    arguments
+        .iter()
+        .enumerate()
+        .filter(|(_, argument)| *argument == "--route")
+        .map(|(index, _)| {
+            arguments
+                .get(index + 1)
+                .expect("Did not receive a route rule after --route")
+                .parse()
+                .expect("Could not parse route rule successfully")
+        })
+        .collect()
+}
@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::net::TcpStream;
+use std::num::NonZeroUsize;
+use std::os::fd::FromRawFd;
+use std::sync::Arc;
+use std::thread;
+
+use log::{error, info};
+use procfs::process::Process;
+use procfs::{LoadAverage, Uptime};
+
+use data_transfer_objects::{
+    BenchmarkData, BenchmarkDataType, BenchmarkOutput, MemorySample, MotorMonitorParameters,
+    NetworkUsage,
+};
+
+use crate::network_usage::ByteCounter;
+use crate::routing::Routing;
+use crate::{build_info, disk_writer, encode_object, profiling};
+
+/// Counts of a monitor run which are reported in its benchmark data.
+/// Clones share the counts, so the crash handler and the memory watchdog report the ones
+/// the threads of the monitor counted so far.
+#[derive(Debug, Clone)]
+pub struct RunStatistics {
+    /// Bytes exchanged with the sensors
+    pub sensor_bytes: Arc<ByteCounter>,
+    /// Bytes exchanged with the cloud server
+    pub cloud_server_bytes: Arc<ByteCounter>,
+    /// Routing of the alerts, which counts them
+    pub routing: Arc<Routing>,
+}
+
+impl RunStatistics {
+    pub fn new(motor_monitor_parameters: &MotorMonitorParameters) -> RunStatistics {
+        RunStatistics {
+            sensor_bytes: Arc::new(ByteCounter::new()),
+            cloud_server_bytes: Arc::new(ByteCounter::new()),
+            routing: Arc::new(Routing::new(motor_monitor_parameters)),
+        }
+    }
+}
+
+/// How the run of a monitor ended, which its benchmark data is flagged with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum RunEnd {
+    Completed,
+    Crashed,
+    ExceededMemoryLimit,
+}
+
+pub fn save_benchmark_readings(
+    id: u32,
+    benchmark_data_type: BenchmarkDataType,
+    benchmark_output: &BenchmarkOutput,
+    run_statistics: &RunStatistics,
+) {
+    write_benchmark_readings(
+        id,
+        benchmark_data_type,
+        benchmark_output,
+        run_statistics,
+        RunEnd::Completed,
+        0,
+        &[],
+    )
+}
+
+/// Saves the benchmark readings, with the number of motors which degraded during the run,
+/// followed by the memory samples of the run
+pub fn save_benchmark_readings_with_memory_breakdown(
+    id: u32,
+    benchmark_data_type: BenchmarkDataType,
+    benchmark_output: &BenchmarkOutput,
+    run_statistics: &RunStatistics,
+    degraded_motors: u32,
+    memory_breakdown: &[MemorySample],
+) {
+    write_benchmark_readings(
+        id,
+        benchmark_data_type,
+        benchmark_output,
+        run_statistics,
+        RunEnd::Completed,
+        degraded_motors,
+        memory_breakdown,
+    )
+}
+
+pub(crate) fn write_benchmark_readings(
+    id: u32,
+    benchmark_data_type: BenchmarkDataType,
+    benchmark_output: &BenchmarkOutput,
+    run_statistics: &RunStatistics,
+    run_end: RunEnd,
+    degraded_motors: u32,
+    memory_breakdown: &[MemorySample],
+) {
+    info!("Saving benchmark readings");
+    let load_average = LoadAverage::new().expect("Could not get load average").one;
+    let me = Process::myself().expect("Could not get process info handle");
+    let (cstime, cutime) = me
+        .tasks()
+        .unwrap()
+        .flatten()
+        .filter_map(|task| task.stat().ok())
+        .fold((0, 0), |(stime, utime), task_stat| {
+            (stime + task_stat.stime, utime + task_stat.utime)
+        });
+    let stat = me.stat().expect("Could not get /proc/[pid]/stat info");
+    let status = me.status().expect("Could not get /proc/[pid]/status info");
+    let benchmark_data = BenchmarkData {
+        id,
+        time_spent_in_user_mode: stat.utime,
+        time_spent_in_kernel_mode: stat.stime,
+        children_time_spent_in_user_mode: cutime,
+        children_time_spent_in_kernel_mode: cstime,
+        peak_resident_set_size: status.vmhwm.expect("Could not get vmhw"),
+        peak_virtual_memory_size: status.vmpeak.expect("Could not get vmrss"),
+        load_average,
+        cpu_utilization: get_cpu_utilization(stat.utime + stat.stime, stat.starttime),
+        benchmark_data_type,
+        crashed: run_end == RunEnd::Crashed,
+        exceeded_memory_limit: run_end == RunEnd::ExceededMemoryLimit,
+        degraded_motors,
+        sensor_network_usage: Some(run_statistics.sensor_bytes.get_usage())
+            .filter(|usage| *usage != NetworkUsage::default()),
+        cloud_server_network_usage: run_statistics.cloud_server_bytes.get_usage(),
+        sent_alerts: run_statistics.routing.get_sent_alerts(),
+        build_info: build_info::get().cloned(),
+        hot_path_profile: profiling::get_profile(),
+        route_counts: run_statistics.routing.get_route_counts(),
+    };
+    let mut vec: Vec<u8> =
+        encode_object(&benchmark_data).expect("Could not write benchmark data to Vec<u8>");
+    for memory_sample in memory_breakdown {
+        vec.extend(encode_object(memory_sample).expect("Could not write memory sample to Vec<u8>"));
+    }
+    match get_benchmark_writer(benchmark_output).write_all(&vec) {
+        Ok(()) => info!("Wrote benchmark data to {benchmark_output}"),
+        // The process is about to exit anyway, failing it would not save the benchmark data
+        Err(e) if disk_writer::is_disk_full(&e) => {
+            error!("DISK FULL: could not write benchmark data to {benchmark_output}: {e}")
+        }
+        Err(e) => panic!("Could not write benchmark data bytes: {e}"),
+    }
+}
+
+/// Relates the CPU time of the process (in clock ticks, covering all of its threads) to the wall time
+/// since it started, times the cores it may run on, which are fewer than the ones of the machine
+/// if it is pinned to some of them
+fn get_cpu_utilization(cpu_time: u64, start_time: u64) -> f32 {
+    let ticks_per_second = procfs::ticks_per_second() as f64;
+    let uptime = Uptime::new().expect("Could not get uptime").uptime;
+    let wall_time = uptime - start_time as f64 / ticks_per_second;
+    let cores = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    compute_cpu_utilization(cpu_time as f64 / ticks_per_second, wall_time, cores)
+}
+
+/// Share of the capacity of the cores used over the wall time, in seconds like the CPU time
+fn compute_cpu_utilization(cpu_time: f64, wall_time: f64, cores: usize) -> f32 {
+    (cpu_time / (wall_time * cores as f64)) as f32
+}
+
+fn get_benchmark_writer(benchmark_output: &BenchmarkOutput) -> Box<dyn Write> {
+    match benchmark_output {
+        BenchmarkOutput::Stdout => Box::new(io::stdout()),
+        BenchmarkOutput::Path(path) => {
+            Box::new(File::create(path).expect("Could not create benchmark output file"))
+        }
+        // The descriptor is handed to this process for exactly this purpose
+        BenchmarkOutput::Fd(fd) => Box::new(unsafe { File::from_raw_fd(*fd) }),
+        BenchmarkOutput::Tcp(address) => Box::new(
+            TcpStream::connect(address).expect("Could not connect to benchmark output address"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_utilization_relates_the_cpu_time_to_the_capacity_of_the_cores() {
+        // 2 s of CPU time in 10 s on 4 cores
+        assert_eq!(compute_cpu_utilization(2.0, 10.0, 4), 0.05);
+        // A busy single-threaded process on one core
+        assert_eq!(compute_cpu_utilization(10.0, 10.0, 1), 1.0);
+        // Threads busy on all 4 cores
+        assert_eq!(compute_cpu_utilization(40.0, 10.0, 4), 1.0);
+    }
+}
@@ -0,0 +1,28 @@
+use std::env;
+use std::process;
+use std::sync::OnceLock;
+
+use data_transfer_objects::BuildInfo;
+
+use crate::PROTOCOL_VERSION;
+
+static BUILD_INFO: OnceLock<BuildInfo> = OnceLock::new();
+
+/// Stores the build of the component, to be reported in its handshakes and benchmark data,
+/// printing it and exiting instead if the component was started with `--version-full`.
+/// To be called first thing in main, with `data_transfer_objects::build_info!()`.
+pub fn init(mut build_info: BuildInfo) {
+    build_info.protocol_version = Some(PROTOCOL_VERSION);
+    if env::args().any(|argument| argument == "--version-full") {
+        println!("{build_info}");
+        process::exit(0);
+    }
+    BUILD_INFO
+        .set(build_info)
+        .expect("Build info was already initialized");
+}
+
+/// Build of the component, absent if [init] was not called
+pub fn get() -> Option<&'static BuildInfo> {
+    BUILD_INFO.get()
+}
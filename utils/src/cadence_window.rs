@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Sliding windows over timestamped items, emitted every `sampling_interval` of the timestamps.
+/// The first window ends one sampling interval after the first item, and each window holds the
+/// items of (end - `window_size`, end].
+/// A window is emitted once an item later than its end arrives; an item arriving after the end of
+/// its window is only part of the later windows.
+pub struct CadenceWindows<T> {
+    sampling_interval: Duration,
+    window_size: Duration,
+    next_window_end: Option<Duration>,
+    items: VecDeque<(Duration, T)>,
+}
+
+impl<T: Clone> CadenceWindows<T> {
+    pub fn new(sampling_interval: Duration, window_size: Duration) -> CadenceWindows<T> {
+        assert!(
+            !sampling_interval.is_zero(),
+            "The window sampling interval has to be positive"
+        );
+        CadenceWindows {
+            sampling_interval,
+            window_size,
+            next_window_end: None,
+            items: VecDeque::new(),
+        }
+    }
+
+    /// Adds the item, returning the windows which ended before its timestamp, oldest first
+    pub fn push(&mut self, timestamp: Duration, item: T) -> Vec<Vec<T>> {
+        let mut window_end = *self
+            .next_window_end
+            .get_or_insert(timestamp + self.sampling_interval);
+        let mut windows = vec![];
+        while timestamp > window_end {
+            windows.push(self.get_window(window_end));
+            window_end += self.sampling_interval;
+        }
+        if !windows.is_empty() {
+            self.next_window_end = Some(window_end);
+            // Items at or before the start of the next window are part of no later window
+            if let Some(window_start) = window_end.checked_sub(self.window_size) {
+                self.items
+                    .retain(|(item_timestamp, _)| *item_timestamp > window_start);
+            }
+        }
+        self.items.push_back((timestamp, item));
+        windows
+    }
+
+    /// Ends the windows once no further item arrives, returning the one the latest items are
+    /// part of, or `None` if no item was added
+    pub fn finish(self) -> Option<Vec<T>> {
+        self.next_window_end
+            .map(|window_end| self.get_window(window_end))
+    }
+
+    fn get_window(&self, window_end: Duration) -> Vec<T> {
+        let window_start = window_end.checked_sub(self.window_size);
+        self.items
+            .iter()
+            .filter(|(timestamp, _)| {
+                window_start.is_none_or(|window_start| *timestamp > window_start)
+                    && *timestamp <= window_end
+            })
+            .map(|(_, item)| item.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn windows_are_emitted_every_sampling_interval() {
+        let duration = millis(10_000);
+        let sampling_interval = millis(1000);
+        let mut cadence_windows = CadenceWindows::new(sampling_interval, millis(3000));
+        let mut emitted_windows = 0;
+        for timestamp in (0..duration.as_millis() as u64).step_by(30) {
+            emitted_windows += cadence_windows.push(millis(timestamp), timestamp).len();
+        }
+        let expected_windows = (duration.as_millis() / sampling_interval.as_millis()) as usize;
+        assert!(
+            emitted_windows.abs_diff(expected_windows) <= 1,
+            "Emitted {emitted_windows} windows, expected {expected_windows} ± 1"
+        );
+    }
+
+    #[test]
+    fn windows_are_aligned_to_the_first_item() {
+        let mut cadence_windows = CadenceWindows::new(millis(1000), millis(1500));
+        let mut windows = vec![];
+        for timestamp in [200, 700, 1200, 1700, 2200, 2700, 3300] {
+            windows.extend(cadence_windows.push(millis(timestamp), timestamp));
+        }
+        // The windows end at 1200, 2200 and 3200
+        assert_eq!(
+            windows,
+            vec![
+                vec![200, 700, 1200],
+                vec![1200, 1700, 2200],
+                vec![2200, 2700]
+            ]
+        );
+    }
+
+    #[test]
+    fn late_item_is_only_part_of_later_windows() {
+        let mut cadence_windows = CadenceWindows::new(millis(1000), millis(2000));
+        cadence_windows.push(millis(0), 0);
+        assert_eq!(cadence_windows.push(millis(1100), 1100), vec![vec![0]]);
+        assert!(cadence_windows.push(millis(900), 900).is_empty());
+        assert_eq!(
+            cadence_windows.push(millis(2100), 2100),
+            vec![vec![1100, 900]]
+        );
+    }
+
+    #[test]
+    fn gap_emits_every_missed_window() {
+        let mut cadence_windows = CadenceWindows::new(millis(1000), millis(1500));
+        cadence_windows.push(millis(0), 0);
+        let windows = cadence_windows.push(millis(3500), 3500);
+        assert_eq!(windows, vec![vec![0], vec![], vec![]]);
+    }
+
+    #[test]
+    fn finish_emits_the_pending_window() {
+        let mut cadence_windows = CadenceWindows::new(millis(1000), millis(1500));
+        for timestamp in [0, 600, 1100, 1400] {
+            cadence_windows.push(millis(timestamp), timestamp);
+        }
+        assert_eq!(cadence_windows.finish(), Some(vec![600, 1100, 1400]));
+        assert_eq!(
+            CadenceWindows::<u64>::new(millis(1000), millis(1500)).finish(),
+            None
+        );
+    }
+}
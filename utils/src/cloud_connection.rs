@@ -0,0 +1,263 @@
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use log::{info, warn};
+
+use data_transfer_objects::{
+    Alert, AlertEncoding, AlertSource, CompactAlert, DetailedAlert, MotorMonitorParameters,
+};
+
+use crate::network_usage::{ByteCounter, CloudServerStream, CountingStream};
+use crate::{encode_object, read_object, MessageStream, ReadError};
+
+/// Opens the connection alerts are sent over, announces the run and model they belong to,
+/// and agrees on their encoding with the cloud server.
+/// Unless alerts should be buffered, Nagle's algorithm is disabled, as it would hold back
+/// a small alert until more data is written, inflating its measured delay
+/// (flushing does not help here, as it is a no-op for a `TcpStream`).
+/// Without a cloud server, the alerts are still encoded, so that only the network cost is left
+/// out, but written to a sink.
+pub fn connect_to_cloud_server(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    cloud_server_bytes: Arc<ByteCounter>,
+) -> (CloudServerStream, AlertCodec) {
+    if motor_monitor_parameters.detailed_alerts && !cfg!(feature = "detailed_alerts") {
+        warn!("Detailed alerts are requested, but the monitor is built without the detailed_alerts feature");
+    }
+    // The averages of the windows take precedence over a smaller alert frame
+    let requested_encoding =
+        if motor_monitor_parameters.detailed_alerts && cfg!(feature = "detailed_alerts") {
+            AlertEncoding::Detailed
+        } else if motor_monitor_parameters.compress_alerts {
+            get_supported_alert_encoding(AlertEncoding::Compact)
+        } else {
+            AlertEncoding::Plain
+        };
+    if motor_monitor_parameters.no_cloud {
+        info!("Running without a cloud server, only counting the alerts");
+        return (
+            CloudServerStream::Discarding,
+            AlertCodec::new(requested_encoding, motor_monitor_parameters.start_time),
+        );
+    }
+    let mut cloud_server =
+        TcpStream::connect(motor_monitor_parameters.motor_monitor_listen_address)
+            .expect("Could not open connection to cloud server");
+    cloud_server
+        .set_nodelay(!motor_monitor_parameters.buffer_alerts)
+        .expect("Could not configure TCP_NODELAY on connection to cloud server");
+    let alert_source = AlertSource {
+        run_id: motor_monitor_parameters.run_id,
+        request_processing_model: motor_monitor_parameters.request_processing_model,
+    };
+    cloud_server
+        .write_all(&encode_object(&alert_source).expect("Could not encode alert source"))
+        .expect("Could not send alert source to cloud server");
+    // Counted from here on like on the cloud server, which only knows the run from the alert source
+    let mut cloud_server =
+        CloudServerStream::Connected(CountingStream::new(cloud_server, cloud_server_bytes));
+    cloud_server
+        .write_all(&encode_object(&requested_encoding).expect("Could not encode alert encoding"))
+        .expect("Could not request alert encoding from cloud server");
+    let encoding = read_object::<AlertEncoding>(&mut cloud_server)
+        .expect("Could not get alert encoding from cloud server");
+    if encoding != requested_encoding {
+        warn!("Cloud server does not support {requested_encoding:?} alerts, sending {encoding:?} alerts");
+    }
+    let codec = AlertCodec::new(encoding, motor_monitor_parameters.start_time);
+    (cloud_server, codec)
+}
+
+/// Returns the requested encoding if this build supports it, and the plain one otherwise
+pub fn get_supported_alert_encoding(requested_encoding: AlertEncoding) -> AlertEncoding {
+    match requested_encoding {
+        AlertEncoding::Compact if cfg!(feature = "alert_compression") => AlertEncoding::Compact,
+        AlertEncoding::Detailed => AlertEncoding::Detailed,
+        _ => AlertEncoding::Plain,
+    }
+}
+
+/// Encodes and decodes the alerts sent over a connection to the cloud server
+#[derive(Debug, Copy, Clone)]
+pub struct AlertCodec {
+    encoding: AlertEncoding,
+    start_time: f64,
+}
+
+impl AlertCodec {
+    pub fn new(encoding: AlertEncoding, start_time: f64) -> AlertCodec {
+        AlertCodec {
+            encoding,
+            start_time,
+        }
+    }
+
+    /// Encodes the alert, without any averages of its windows in the detailed encoding
+    pub fn encode(&self, alert: &Alert) -> Vec<u8> {
+        match self.encoding {
+            AlertEncoding::Detailed => self.encode_detailed(&DetailedAlert::from(alert.clone())),
+            _ => self.encode_alert(alert),
+        }
+    }
+
+    /// Encodes the alert, with the averages of its windows only in the detailed encoding
+    pub fn encode_detailed(&self, detailed_alert: &DetailedAlert) -> Vec<u8> {
+        match self.encoding {
+            AlertEncoding::Detailed => encode_object(detailed_alert)
+                .expect("Could not write motor monitor alert to Vec<u8>"),
+            _ => self.encode_alert(&detailed_alert.alert),
+        }
+    }
+
+    fn encode_alert(&self, alert: &Alert) -> Vec<u8> {
+        match self.encoding {
+            AlertEncoding::Plain | AlertEncoding::Detailed => encode_object(alert),
+            AlertEncoding::Compact => {
+                encode_object(&CompactAlert::from_alert(alert, self.start_time))
+            }
+        }
+        .expect("Could not write motor monitor alert to Vec<u8>")
+    }
+
+    pub fn read_alert(&self, stream: &mut impl MessageStream) -> Result<Alert, ReadError> {
+        self.read_detailed_alert(stream)
+            .map(|detailed_alert| detailed_alert.alert)
+    }
+
+    /// Reads an alert, which only carries the averages of its windows in the detailed encoding
+    pub fn read_detailed_alert(
+        &self,
+        stream: &mut impl MessageStream,
+    ) -> Result<DetailedAlert, ReadError> {
+        match self.encoding {
+            AlertEncoding::Plain => read_object::<Alert>(stream).map(DetailedAlert::from),
+            AlertEncoding::Compact => read_object::<CompactAlert>(stream)
+                .map(|compact_alert| compact_alert.to_alert(self.start_time).into()),
+            AlertEncoding::Detailed => read_object::<DetailedAlert>(stream),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use data_transfer_objects::{ChannelFingerprint, MotorFailure, SampledMessageId};
+
+    use super::*;
+    use crate::get_now_secs;
+    use crate::tests::get_test_monitor_parameters;
+
+    #[test]
+    fn compact_alert_frames_decode_to_the_original_alerts() {
+        let start_time = 1_700_000_000.25;
+        let alerts = [
+            Alert {
+                time: start_time + 12.345,
+                motor_id: 3,
+                failure: MotorFailure::PowerFailure,
+                fingerprint: None,
+                cleared: false,
+                sampled_messages: vec![],
+            },
+            // Evaluated on readings from before the start of the run
+            Alert {
+                time: start_time - 0.5,
+                motor_id: 512,
+                failure: MotorFailure::OverstrainFailure,
+                fingerprint: Some(
+                    [ChannelFingerprint {
+                        count: 20,
+                        hash: 0xabcdef,
+                    }; 4],
+                ),
+                cleared: true,
+                sampled_messages: vec![SampledMessageId {
+                    sensor_id: 7,
+                    sample_id: 40,
+                }],
+            },
+        ];
+        let compact_codec = AlertCodec::new(AlertEncoding::Compact, start_time);
+        let plain_codec = AlertCodec::new(AlertEncoding::Plain, start_time);
+        for alert in alerts {
+            let frame = compact_codec.encode(&alert);
+            assert!(frame.len() < plain_codec.encode(&alert).len());
+            let decoded_alert = compact_codec.read_alert(&mut frame.as_slice()).unwrap();
+            assert_eq!(decoded_alert.time.to_bits(), alert.time.to_bits());
+            assert_eq!(format!("{decoded_alert:?}"), format!("{alert:?}"));
+        }
+    }
+
+    /// Stub cloud server, which agrees on the requested encoding and returns the times it
+    /// received the alerts at
+    fn spawn_cloud_server(alerts: usize) -> (SocketAddr, thread::JoinHandle<Vec<Instant>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            read_object::<AlertSource>(&mut stream).unwrap();
+            let encoding = read_object::<AlertEncoding>(&mut stream).unwrap();
+            stream
+                .write_all(&encode_object(&encoding).unwrap())
+                .unwrap();
+            (0..alerts)
+                .map(|_| {
+                    read_object::<Alert>(&mut stream).unwrap();
+                    Instant::now()
+                })
+                .collect()
+        });
+        (address, handle)
+    }
+
+    fn get_test_alert() -> Alert {
+        Alert {
+            time: get_now_secs(),
+            motor_id: 0,
+            failure: MotorFailure::PowerFailure,
+            fingerprint: None,
+            cleared: false,
+            sampled_messages: vec![],
+        }
+    }
+
+    #[test]
+    fn small_alert_is_received_promptly() {
+        let (cloud_server_address, cloud_server) = spawn_cloud_server(2);
+        let (mut stream, codec) = connect_to_cloud_server(
+            &get_test_monitor_parameters(cloud_server_address, &[]),
+            Arc::new(ByteCounter::new()),
+        );
+        let CloudServerStream::Connected(connection) = &stream else {
+            panic!("Expected a connection to the cloud server");
+        };
+        assert!(connection.get_ref().nodelay().unwrap());
+
+        // Nagle's algorithm would hold the second alert back until the first one is acknowledged
+        stream.write_all(&codec.encode(&get_test_alert())).unwrap();
+        let send_time = Instant::now();
+        stream.write_all(&codec.encode(&get_test_alert())).unwrap();
+
+        let receive_times = cloud_server.join().unwrap();
+        let delay = receive_times[1].saturating_duration_since(send_time);
+        assert!(delay < Duration::from_millis(20), "Alert took {delay:?}");
+    }
+
+    #[test]
+    fn buffered_alerts_keep_nagle() {
+        let (cloud_server_address, cloud_server) = spawn_cloud_server(0);
+        let (stream, _) = connect_to_cloud_server(
+            &get_test_monitor_parameters(cloud_server_address, &["--buffer-alerts"]),
+            Arc::new(ByteCounter::new()),
+        );
+        let CloudServerStream::Connected(connection) = &stream else {
+            panic!("Expected a connection to the cloud server");
+        };
+        assert!(!connection.get_ref().nodelay().unwrap());
+        cloud_server.join().unwrap();
+    }
+}
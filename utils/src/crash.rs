@@ -0,0 +1,126 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::{fs, panic, process, thread};
+
+use data_transfer_objects::{
+    BenchmarkDataType, BenchmarkOutput, CrashReport, MotorMonitorParameters,
+};
+
+use crate::benchmark::{RunEnd, RunStatistics};
+
+/// Exit code of a monitor which crashed, after writing its crash report
+pub const CRASH_EXIT_CODE: i32 = 70;
+
+/// Bytes of the panic message and backtrace kept in the crash report
+const MAX_MESSAGE_LENGTH: usize = 1024;
+const MAX_BACKTRACE_LENGTH: usize = 16 * 1024;
+
+thread_local! {
+    static REPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Exit of a monitor before the end of its run, by the crash handler or the memory watchdog,
+/// which writes its benchmark data with the counts of the run so far.
+/// Clones share whether the exit was claimed, so only the first of them exits the process.
+#[derive(Debug, Clone)]
+pub struct EarlyExit {
+    /// Set by the first thread reporting a crash or an exceeded memory limit,
+    /// which exits the process once it is done
+    exiting: Arc<AtomicBool>,
+    benchmark_output: BenchmarkOutput,
+    run_statistics: RunStatistics,
+}
+
+impl EarlyExit {
+    pub fn new(
+        motor_monitor_parameters: &MotorMonitorParameters,
+        run_statistics: &RunStatistics,
+    ) -> EarlyExit {
+        EarlyExit {
+            exiting: Arc::new(AtomicBool::new(false)),
+            benchmark_output: motor_monitor_parameters.benchmark_output.clone(),
+            run_statistics: run_statistics.clone(),
+        }
+    }
+
+    /// Returns whether the calling thread is the first one to exit the process early
+    pub(crate) fn claim(&self) -> bool {
+        !self.exiting.swap(true, Ordering::SeqCst)
+    }
+
+    /// Writes the benchmark data, flagged with why the run ended early
+    pub(crate) fn save_benchmark_readings(&self, run_end: RunEnd) {
+        crate::benchmark::write_benchmark_readings(
+            0,
+            BenchmarkDataType::MotorMonitor,
+            &self.benchmark_output,
+            &self.run_statistics,
+            run_end,
+            0,
+            &[],
+        );
+    }
+}
+
+/// Installs a panic hook which writes a `CrashReport` to the crash file of the monitor,
+/// attempts to write its benchmark data flagged as crashed, and exits with `CRASH_EXIT_CODE`.
+/// Panics of other threads meanwhile wait for the exit, while a panic while reporting
+/// aborts the process instead of recursing.
+pub fn install_crash_handler(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    early_exit: EarlyExit,
+) {
+    let crash_file = motor_monitor_parameters.crash_file.clone();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+        if REPORTING.with(|reporting| reporting.replace(true)) {
+            process::abort();
+        }
+        if !early_exit.claim() {
+            loop {
+                thread::park();
+            }
+        }
+        default_hook(panic_info);
+        let crash_report = CrashReport {
+            message: truncate(get_panic_message(panic_info.payload()), MAX_MESSAGE_LENGTH),
+            location: panic_info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_default(),
+            backtrace: truncate(Backtrace::force_capture().to_string(), MAX_BACKTRACE_LENGTH),
+        };
+        if let Some(crash_file) = &crash_file {
+            if let Ok(frame) = crate::encode_object(&crash_report) {
+                let _ = fs::write(crash_file, frame);
+            }
+        }
+        early_exit.save_benchmark_readings(RunEnd::Crashed);
+        process::exit(CRASH_EXIT_CODE);
+    }));
+}
+
+/// Message of the payload of a panic, if it is a string
+pub fn get_panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+fn truncate(mut text: String, max_length: usize) -> String {
+    if text.len() > max_length {
+        let mut end = max_length;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
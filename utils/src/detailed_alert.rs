@@ -0,0 +1,38 @@
+use data_transfer_objects::{Alert, DetailedAlert, WindowAverages};
+
+use crate::units::{Kelvin, MotorReadings, NewtonMeter, Rpm, Watt};
+
+/// Attaches the averages of the windows the alert was evaluated on, derived from the averages of
+/// the sensors of the motor the same way the rules derive them
+pub fn attach_sensor_averages(alert: Alert, readings: MotorReadings) -> DetailedAlert {
+    attach_averages(
+        alert,
+        readings.get_temperature_difference(),
+        readings.rotational_speed,
+        readings.get_power(),
+        readings.torque,
+    )
+}
+
+/// Attaches the averages the rules of the motor were evaluated on to the alert.
+/// Monitors built without the `detailed_alerts` feature attach none.
+pub fn attach_averages(
+    alert: Alert,
+    temperature_difference: Kelvin,
+    rotational_speed: Rpm,
+    power: Watt,
+    torque: NewtonMeter,
+) -> DetailedAlert {
+    if !cfg!(feature = "detailed_alerts") {
+        return DetailedAlert::from(alert);
+    }
+    DetailedAlert {
+        alert,
+        averages: Some(WindowAverages {
+            temperature_difference: temperature_difference.0,
+            rotational_speed: rotational_speed.0,
+            power: power.0,
+            torque: torque.0,
+        }),
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{error, warn};
+
+/// Time after which a writer dropping records because the disk is full tries writing again
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether the write failed because the disk, or the disk quota of the user, is full
+pub fn is_disk_full(error: &io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(libc::ENOSPC) | Some(libc::EDQUOT)
+    )
+}
+
+/// File of records (e.g. CSV lines) which does not fail once the disk is full, but drops the
+/// records written to it, counting them, and tries writing again every `PROBE_INTERVAL`.
+/// Once a write succeeds again, it is preceded by the gap marker, if any, stating how many
+/// records are missing before it.
+/// A record which was written only partially is cut off the file, so that the file only holds
+/// complete records.
+pub struct DiskWriter {
+    path: PathBuf,
+    file: File,
+    /// Length of the file up to the end of the last completely written record
+    written_length: u64,
+    gap_marker: Option<fn(u64) -> String>,
+    gap: Option<Gap>,
+    dropped_records: Arc<AtomicU64>,
+}
+
+/// Records dropped since the disk ran full
+struct Gap {
+    dropped_records: u64,
+    last_attempt: Instant,
+}
+
+impl DiskWriter {
+    /// Writes the records to the end of the file, adding the dropped ones to the counter,
+    /// which may be shared by several writers
+    pub fn new(path: &Path, file: File, dropped_records: Arc<AtomicU64>) -> DiskWriter {
+        let written_length = file
+            .metadata()
+            .unwrap_or_else(|e| panic!("Could not get length of {}: {e}", path.display()))
+            .len();
+        DiskWriter {
+            path: path.to_path_buf(),
+            file,
+            written_length,
+            gap_marker: None,
+            gap: None,
+            dropped_records,
+        }
+    }
+
+    /// Precedes the first record written after a gap with the record the marker returns
+    /// for the number of dropped records
+    pub fn with_gap_marker(self, gap_marker: fn(u64) -> String) -> DiskWriter {
+        DiskWriter {
+            gap_marker: Some(gap_marker),
+            ..self
+        }
+    }
+
+    /// Writes the record, returns false if it was dropped because the disk is full
+    pub fn write_record(&mut self, record: &str) -> io::Result<bool> {
+        let now = Instant::now();
+        let gap_marker = match &mut self.gap {
+            Some(gap) if now - gap.last_attempt < PROBE_INTERVAL => {
+                gap.dropped_records += 1;
+                self.dropped_records.fetch_add(1, Ordering::Relaxed);
+                return Ok(false);
+            }
+            Some(gap) => self
+                .gap_marker
+                .map(|gap_marker| gap_marker(gap.dropped_records)),
+            None => None,
+        };
+        let mut records = gap_marker.as_deref().into_iter().chain([record]);
+        let length: usize = records.clone().map(str::len).sum();
+        match records.try_for_each(|record| self.file.write_all(record.as_bytes())) {
+            Ok(()) => {
+                if let Some(gap) = self.gap.take() {
+                    warn!(
+                        "Resumed writing {} after dropping {} records",
+                        self.path.display(),
+                        gap.dropped_records
+                    );
+                }
+                self.written_length += length as u64;
+                Ok(true)
+            }
+            Err(e) if is_disk_full(&e) => {
+                self.cut_off_partial_record();
+                match &mut self.gap {
+                    Some(gap) => {
+                        gap.dropped_records += 1;
+                        gap.last_attempt = now;
+                    }
+                    None => {
+                        error!(
+                            "DISK FULL: could not write to {}, dropping its records until there is space again: {e}",
+                            self.path.display()
+                        );
+                        self.gap = Some(Gap {
+                            dropped_records: 1,
+                            last_attempt: now,
+                        });
+                    }
+                }
+                self.dropped_records.fetch_add(1, Ordering::Relaxed);
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Formats the arguments into a single record, so that `write!` and `writeln!` write
+    /// whole records
+    pub fn write_fmt(&mut self, arguments: fmt::Arguments) -> io::Result<bool> {
+        self.write_record(&arguments.to_string())
+    }
+
+    /// Records dropped by all writers sharing the counter of this one
+    pub fn get_dropped_records(&self) -> u64 {
+        self.dropped_records.load(Ordering::Relaxed)
+    }
+
+    fn cut_off_partial_record(&mut self) {
+        if let Err(e) = self
+            .file
+            .set_len(self.written_length)
+            .and_then(|_| self.file.seek(SeekFrom::Start(self.written_length)))
+        {
+            warn!(
+                "Could not cut off the partially written record of {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+/// Exponentially weighted moving average of the readings of a sensor, needing constant state
+/// instead of storing the readings of a window
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Ewma {
+    alpha: f64,
+    value: Option<f64>,
+    /// Sum of the squared weights of the readings, which is 1 after the first reading
+    squared_weight_sum: f64,
+    latest_timestamp: f64,
+}
+
+impl Ewma {
+    pub fn new(alpha: f64) -> Ewma {
+        Ewma {
+            alpha,
+            value: None,
+            squared_weight_sum: 0.0,
+            latest_timestamp: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, reading: f64, timestamp: f64) {
+        match self.value {
+            None => {
+                self.value = Some(reading);
+                self.squared_weight_sum = 1.0;
+            }
+            Some(value) => {
+                self.value = Some(self.alpha * reading + (1.0 - self.alpha) * value);
+                self.squared_weight_sum =
+                    (1.0 - self.alpha).powi(2) * self.squared_weight_sum + self.alpha.powi(2);
+            }
+        }
+        self.latest_timestamp = f64::max(self.latest_timestamp, timestamp);
+    }
+
+    pub fn get_value(&self) -> Option<f64> {
+        self.value
+    }
+
+    /// Number of equally weighted readings whose average has the same variance as this one
+    /// (Kish's effective sample size), which approaches `(2 - alpha) / alpha`.
+    /// It stands in for the window size in the confidence intervals of the rules.
+    pub fn get_effective_sample_count(&self) -> usize {
+        match self.value {
+            None => 0,
+            Some(_) => (1.0 / self.squared_weight_sum).round() as usize,
+        }
+    }
+
+    /// Timestamp of the latest reading, if there was one since the last reset
+    pub fn get_latest_timestamp(&self) -> Option<f64> {
+        self.value.map(|_| self.latest_timestamp)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Ewma::new(self.alpha);
+    }
+}
@@ -0,0 +1,183 @@
+use data_transfer_objects::{
+    MotorFailure, ReadingSchedule, RuleThresholds, ScheduledFailure, ScheduledReading, SensorKind,
+};
+
+use crate::units::Rpm;
+use crate::{CRITICAL_VALUE, POWER_MEAN, POWER_SD, TEMP_DIFF_MEAN};
+
+/// Air temperature (in K) of all readings, the process temperature setting the difference to it
+const AIR_TEMPERATURE: f64 = 300.0;
+/// Rotational speed (in rpm) of the readings without a heat dissipation failure, unless its rule
+/// needs a higher one
+const NORMAL_ROTATIONAL_SPEED: f64 = 1500.0;
+/// Factor by which the readings keep clear of the bounds of the rules
+const MARGIN: f64 = 1.25;
+
+/// Readings of the four sensors of a motor, in the order of their ids, which the rules of every
+/// monitor flag as the failure, or as none without a failure.
+/// Without a failure, the temperature difference and the power are the means of the statistical
+/// rules, and the rotational speed is above the one of the heat dissipation rule.
+/// A heat dissipation failure has no temperature difference and half that rotational speed,
+/// and a power failure a torque pushing the power above both the power band and the
+/// confidence interval of a single reading.
+/// Only heat dissipation and power failures can be scheduled, as the others depend on the tool
+/// wear or on random failure readings.
+pub fn get_target_readings(
+    failure: Option<MotorFailure>,
+    thresholds: &RuleThresholds,
+) -> Result<[f32; 4], String> {
+    let normal_rotational_speed = f64::max(
+        NORMAL_ROTATIONAL_SPEED,
+        MARGIN * thresholds.heat_dissipation_rotational_speed,
+    );
+    if !(thresholds.min_power..=thresholds.max_power).contains(&POWER_MEAN) {
+        return Err(format!(
+            "Failures cannot be scheduled with a power band of {}..={} W, \
+             which excludes the mean power {POWER_MEAN} W of the normal readings",
+            thresholds.min_power, thresholds.max_power
+        ));
+    }
+    let normal_torque = POWER_MEAN / Rpm(normal_rotational_speed).to_rad_per_second().0;
+    let readings = match failure {
+        None => [
+            AIR_TEMPERATURE,
+            AIR_TEMPERATURE + TEMP_DIFF_MEAN,
+            normal_rotational_speed,
+            normal_torque,
+        ],
+        Some(MotorFailure::HeatDissipationFailure) => {
+            if thresholds.heat_dissipation_temperature_difference <= 0.0
+                || thresholds.heat_dissipation_rotational_speed <= 0.0
+            {
+                return Err(format!(
+                    "Heat dissipation failures cannot be scheduled with the thresholds {thresholds}, \
+                     which no temperature difference and rotational speed fall below"
+                ));
+            }
+            [
+                AIR_TEMPERATURE,
+                AIR_TEMPERATURE,
+                thresholds.heat_dissipation_rotational_speed / 2.0,
+                normal_torque,
+            ]
+        }
+        Some(MotorFailure::PowerFailure) => {
+            let power =
+                MARGIN * f64::max(thresholds.max_power, POWER_MEAN + CRITICAL_VALUE * POWER_SD);
+            [
+                AIR_TEMPERATURE,
+                AIR_TEMPERATURE + TEMP_DIFF_MEAN,
+                normal_rotational_speed,
+                power / Rpm(normal_rotational_speed).to_rad_per_second().0,
+            ]
+        }
+        Some(failure) => {
+            return Err(format!(
+                "{failure} cannot be scheduled, only HeatDissipationFailure and PowerFailure"
+            ))
+        }
+    };
+    let readings = readings.map(|reading| reading as f32);
+    for (sensor_id, reading) in readings.iter().enumerate() {
+        let sensor_kind = SensorKind::from_sensor_id(sensor_id as u32);
+        if !sensor_kind.plausible_range().contains(reading) {
+            return Err(format!(
+                "The {} reading {reading} of {} is outside of {:?}, where the monitors would quarantine it",
+                sensor_kind.name(),
+                failure.map_or("the normal readings".to_string(), |failure| failure.to_string()),
+                sensor_kind.plausible_range()
+            ));
+        }
+    }
+    Ok(readings)
+}
+
+/// Readings the sensor emits during the scheduled failures of its motor and between them
+pub fn get_reading_schedule(
+    sensor_id: u32,
+    failure_schedule: &[ScheduledFailure],
+    thresholds: &RuleThresholds,
+) -> Result<ReadingSchedule, String> {
+    let motor_id = (sensor_id >> 2) as u16;
+    let channel = (sensor_id & 0x0003) as usize;
+    Ok(ReadingSchedule {
+        normal_reading: get_target_readings(None, thresholds)?[channel],
+        failures: failure_schedule
+            .iter()
+            .filter(|scheduled_failure| scheduled_failure.motor_id == motor_id)
+            .map(|scheduled_failure| {
+                Ok(ScheduledReading {
+                    start_s: scheduled_failure.start_s,
+                    end_s: scheduled_failure.end_s,
+                    reading: get_target_readings(Some(scheduled_failure.failure), thresholds)?
+                        [channel],
+                })
+            })
+            .collect::<Result<Vec<ScheduledReading>, String>>()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::units::{MotorReadings, Seconds};
+    use crate::{averages_indicate_failure, sensor_data_indicates_failure};
+
+    /// Numbers of readings the averages of the reactive streaming and object-oriented monitors
+    /// may be taken over
+    const WINDOW_SIZES: [usize; 5] = [1, 2, 10, 100, 1000];
+
+    fn get_motor_readings(
+        failure: Option<MotorFailure>,
+        thresholds: &RuleThresholds,
+    ) -> MotorReadings {
+        MotorReadings::from_channels(
+            get_target_readings(failure, thresholds)
+                .unwrap()
+                .map(|reading| reading as f64),
+        )
+    }
+
+    #[test]
+    fn target_readings_are_flagged_by_the_rules_of_every_model() {
+        for thresholds in [
+            RuleThresholds::default(),
+            RuleThresholds::from_str("5.0:2000.0:1000.0:12000.0:11000.0").unwrap(),
+        ] {
+            for failure in [
+                None,
+                Some(MotorFailure::HeatDissipationFailure),
+                Some(MotorFailure::PowerFailure),
+            ] {
+                let readings = get_motor_readings(failure, &thresholds);
+                // The rules of the client-server and SpringQL monitors
+                assert_eq!(
+                    sensor_data_indicates_failure(readings, Seconds(0.0), &thresholds),
+                    failure,
+                    "{thresholds}"
+                );
+                // The rules of the reactive streaming and object-oriented monitors
+                for window_size in WINDOW_SIZES {
+                    assert_eq!(
+                        averages_indicate_failure(readings, window_size, Seconds(0.0), &thresholds),
+                        failure,
+                        "{thresholds}, {window_size} readings"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn failures_depending_on_the_tool_wear_cannot_be_scheduled() {
+        for failure in [
+            MotorFailure::ToolWearFailure,
+            MotorFailure::OverstrainFailure,
+            MotorFailure::RandomFailure,
+        ] {
+            assert!(get_target_readings(Some(failure), &RuleThresholds::default()).is_err());
+        }
+    }
+}
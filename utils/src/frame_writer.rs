@@ -0,0 +1,90 @@
+use std::io;
+use std::io::Write;
+use std::sync::mpsc::{channel, sync_channel, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::error;
+
+use crate::network_usage::CloudServerStream;
+
+enum WriterMessage {
+    Frame(Vec<u8>),
+    Flush(SyncSender<()>),
+}
+
+/// Stream many threads write whole frames to, without the frames being interleaved.
+/// Either the frames are handed to a writer thread owning the stream, or they are written
+/// by the calling thread while holding a lock on the stream.
+#[derive(Clone)]
+pub struct SharedFrameWriter {
+    mode: WriterMode,
+}
+
+#[derive(Clone)]
+enum WriterMode {
+    Channel(Sender<WriterMessage>),
+    Locked(Arc<Mutex<CloudServerStream>>),
+}
+
+impl SharedFrameWriter {
+    /// Starts a writer thread which writes the frames in the order they are handed over
+    pub fn spawn(mut stream: CloudServerStream) -> SharedFrameWriter {
+        let (sender, receiver) = channel::<WriterMessage>();
+        thread::spawn(move || {
+            for message in receiver {
+                match message {
+                    WriterMessage::Frame(frame) => {
+                        if let Err(e) = stream.write_all(&frame) {
+                            // Dropping the receiver lets the following writes fail
+                            error!("Could not write frame, stopping frame writer: {e}");
+                            return;
+                        }
+                    }
+                    WriterMessage::Flush(flushed) => {
+                        let _ = flushed.send(());
+                    }
+                }
+            }
+        });
+        SharedFrameWriter {
+            mode: WriterMode::Channel(sender),
+        }
+    }
+
+    pub fn locked(stream: CloudServerStream) -> SharedFrameWriter {
+        SharedFrameWriter {
+            mode: WriterMode::Locked(Arc::new(Mutex::new(stream))),
+        }
+    }
+
+    pub fn write_frame(&self, frame: &[u8]) -> io::Result<()> {
+        match &self.mode {
+            WriterMode::Channel(sender) => sender
+                .send(WriterMessage::Frame(frame.to_vec()))
+                .map_err(|_| writer_stopped()),
+            WriterMode::Locked(stream) => stream
+                .lock()
+                .expect("Frame writer lock was poisoned")
+                .write_all(frame),
+        }
+    }
+
+    /// Blocks until all frames handed over before were written
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.mode {
+            WriterMode::Channel(sender) => {
+                let (flushed_sender, flushed_receiver) = sync_channel(1);
+                sender
+                    .send(WriterMessage::Flush(flushed_sender))
+                    .map_err(|_| writer_stopped())?;
+                flushed_receiver.recv().map_err(|_| writer_stopped())
+            }
+            WriterMode::Locked(_) => Ok(()),
+        }
+    }
+}
+
+fn writer_stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "Frame writer stopped")
+}
@@ -1,15 +1,78 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use core::f64::consts::PI;
+#[cfg(feature = "std")]
+pub mod affinity;
+#[cfg(feature = "std")]
+pub mod arguments;
+#[cfg(feature = "std")]
+pub mod benchmark;
+#[cfg(feature = "std")]
+pub mod build_info;
+#[cfg(feature = "std")]
+pub mod cadence_window;
+#[cfg(feature = "std")]
+pub mod cloud_connection;
+#[cfg(feature = "std")]
+pub mod crash;
+#[cfg(feature = "std")]
+pub mod detailed_alert;
+#[cfg(feature = "std")]
+pub mod disk_writer;
+#[cfg(feature = "std")]
+pub mod ewma;
+#[cfg(feature = "std")]
+pub mod failure_schedule;
+#[cfg(feature = "std")]
+pub mod frame_writer;
+#[cfg(feature = "std")]
+pub mod monitor_run;
+#[cfg(feature = "std")]
+pub mod network_usage;
+#[cfg(feature = "std")]
+pub mod profiling;
+#[cfg(feature = "std")]
+pub mod provenance;
+#[cfg(feature = "std")]
+pub mod quarantine;
+#[cfg(feature = "std")]
+pub mod recovery;
+#[cfg(feature = "std")]
+pub mod resolved_config;
+#[cfg(feature = "std")]
+pub mod retry;
+#[cfg(feature = "std")]
+pub mod routing;
+#[cfg(feature = "std")]
+pub mod signal;
+#[cfg(feature = "std")]
+pub mod telemetry;
+#[cfg(feature = "std")]
+pub mod transport;
+pub mod units;
+#[cfg(feature = "std")]
+pub mod watchdog;
+#[cfg(feature = "std")]
+pub mod webhook;
+#[cfg(feature = "std")]
+pub mod window_diagnostics;
+#[cfg(feature = "std")]
+pub mod window_export;
+
 use core::time::Duration;
 #[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
 use std::io::Read;
 #[cfg(feature = "std")]
 use std::io::Write;
 #[cfg(feature = "std")]
-use std::net::TcpStream;
+use std::net::{Shutdown, TcpStream};
 #[cfg(feature = "std")]
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 #[cfg(feature = "std")]
 use std::time::SystemTime;
 #[cfg(feature = "std")]
@@ -17,22 +80,22 @@ use std::time::UNIX_EPOCH;
 
 use log::info;
 #[cfg(feature = "std")]
-use log::{debug, error, trace, warn};
-use postcard::accumulator::{CobsAccumulator, FeedResult};
+use log::{debug, error, trace};
 #[cfg(feature = "std")]
 use postcard::to_allocvec_cobs;
 #[cfg(feature = "std")]
-use procfs::process::Process;
-use procfs::LoadAverage;
-#[cfg(feature = "std")]
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use data_transfer_objects::MotorFailure;
 use data_transfer_objects::MotorFailure::{HeatDissipationFailure, PowerFailure};
 #[cfg(feature = "std")]
-use data_transfer_objects::{BenchmarkData, BenchmarkDataType};
+use data_transfer_objects::{
+    ChannelFingerprint, CompactSensorMessage, HotPath, RuleThresholds, SensorMessage, ToolChange,
+    WearModel,
+};
 #[cfg(feature = "std")]
-use data_transfer_objects::{MotorMonitorParameters, RequestProcessingModel};
+use data_transfer_objects::{ClockOffset, ClockProbe, ClockReply};
+use units::{Kelvin, MotorReadings, NewtonMeter, NewtonMeterSeconds, Rpm, Seconds, Watt};
 
 //https://en.wikipedia.org/wiki/Algebra_of_random_variables
 
@@ -42,56 +105,291 @@ const TEMP_DIFF_SD: f64 = 2.49035776174829;
 const POWER_MEAN: f64 = 6443.50092908344;
 const POWER_SD: f64 = 1782.92606670628;
 
+/// Number of bytes a peer may send without a message delimiter before the connection is dropped
+#[cfg(feature = "std")]
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 2048;
+
+/// Version of the wire protocol, to be increased whenever a struct sent over the wire changes,
+/// as postcard is not self-describing and would silently misparse the fields of another version
+#[cfg(feature = "std")]
+pub const PROTOCOL_VERSION: u8 = 35;
+
+/// The sensors connect to the monitor this long after the start time of the run (plus their
+/// connect jitter)
+#[cfg(feature = "std")]
+pub const SENSOR_CONNECTION_DELAY: Duration = Duration::from_secs(2);
+
+/// Prefix of every message while the protocol guard is enabled: a magic byte and the version
+#[cfg(feature = "std")]
+const PROTOCOL_HEADER: [u8; 2] = [0xb5, PROTOCOL_VERSION];
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum ReadError {
+    /// The stream was closed before a complete object could be read
+    Closed,
+    /// More bytes than the maximum message size arrived without a delimiter
+    MessageTooLarge(usize),
+    /// A complete message arrived which could not be deserialized (only reported in strict mode)
+    Malformed,
+    /// A message with another protocol header arrived, holding the received header
+    ProtocolMismatch([u8; 2]),
+    Io(io::Error),
+}
+
 #[cfg(feature = "std")]
-//todo find way to return error object
-pub fn read_object<T>(stream: &mut TcpStream) -> Option<T>
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Closed => write!(f, "Stream closed before an object was read"),
+            ReadError::MessageTooLarge(max_message_size) => write!(
+                f,
+                "Received more than {max_message_size} bytes without a message delimiter"
+            ),
+            ReadError::Malformed => write!(f, "Received a malformed message"),
+            ReadError::ProtocolMismatch([magic, version]) => write!(
+                f,
+                "Protocol version mismatch: received magic {magic:#04x} and version {version}, \
+                 expected the header {:02x?} (is the peer built from another version?)",
+                PROTOCOL_HEADER
+            ),
+            ReadError::Io(e) => write!(f, "Could not read from stream: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReadError {}
+
+/// Stream objects are read from, which is shut down if its peer sends oversized messages
+#[cfg(feature = "std")]
+pub trait MessageStream: Read {
+    fn shutdown_connection(&self) -> io::Result<()>;
+}
+
+#[cfg(feature = "std")]
+impl MessageStream for TcpStream {
+    fn shutdown_connection(&self) -> io::Result<()> {
+        self.shutdown(Shutdown::Both)
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn read_object<T>(stream: &mut impl MessageStream) -> Result<T, ReadError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    read_object_with_limit(stream, DEFAULT_MAX_MESSAGE_SIZE)
+}
+
+/// Reads a single COBS encoded object from the stream, skipping malformed messages.
+/// If more than `max_message_size` bytes arrive without a message delimiter,
+/// the connection is shut down and an error is returned.
+#[cfg(feature = "std")]
+pub fn read_object_with_limit<T>(
+    stream: &mut impl MessageStream,
+    max_message_size: usize,
+) -> Result<T, ReadError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    read_object_with_options(stream, max_message_size, false)
+}
+
+/// Reads the next message of a sensor, decoding it from its compact wire form if `compact_wire` is set.
+/// In strict mode, a malformed message is returned as `ReadError::Malformed` instead of being
+/// skipped, for the monitor to exit with a non-zero code so that it is noticed
+#[cfg(feature = "std")]
+pub fn read_sensor_message(
+    stream: &mut impl MessageStream,
+    strict: bool,
+    compact_wire: bool,
+) -> Result<SensorMessage, ReadError> {
+    let sensor_message = match compact_wire {
+        true => read_object_with_options::<CompactSensorMessage>(
+            stream,
+            DEFAULT_MAX_MESSAGE_SIZE,
+            strict,
+        )
+        .map(SensorMessage::from),
+        false => read_object_with_options(stream, DEFAULT_MAX_MESSAGE_SIZE, strict),
+    };
+    if sensor_message.is_ok() {
+        profiling::count_message();
+    }
+    sensor_message
+}
+
+/// Whether the first message of a connection is from the sensor expected in its place, if the
+/// monitor expects the sensors to connect in the order of their ids, logging a mismatch,
+/// e.g. of a misconfigured sensor wiring
+#[cfg(feature = "std")]
+pub fn is_expected_sensor(expected_sensor_id: Option<u32>, sensor_id: u32) -> bool {
+    match expected_sensor_id {
+        Some(expected_sensor_id) if expected_sensor_id != sensor_id => {
+            error!(
+                "Expected sensor {expected_sensor_id} on its connection, but received the readings of sensor {sensor_id}, closing it"
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Reads a single COBS encoded object from the stream, like `read_object_with_limit`.
+/// In strict mode, a message which cannot be deserialized is returned as an error
+/// instead of being skipped.
+/// A message of another protocol version is never skipped, as all following ones would be too.
+#[cfg(feature = "std")]
+pub fn read_object_with_options<T>(
+    stream: &mut impl MessageStream,
+    max_message_size: usize,
+    strict: bool,
+) -> Result<T, ReadError>
 where
     T: for<'de> Deserialize<'de>,
 {
     let mut raw_buf = [0u8; 1];
-    let mut cobs_buf: CobsAccumulator<2048> = CobsAccumulator::new();
-    let mut return_object: Option<T> = None;
+    let mut frame: Vec<u8> = Vec::new();
     trace!("Reading from stream");
-    while let Ok(ct) = stream.read(&mut raw_buf) {
+    loop {
+        let ct = stream.read(&mut raw_buf).map_err(ReadError::Io)?;
         trace!("Read into buffer: {}", ct);
         // Finished reading input
         if ct == 0 {
-            break;
+            trace!("Read");
+            return Err(ReadError::Closed);
         }
-        let mut window = &raw_buf[..ct];
-        while return_object.is_none() && !window.is_empty() {
-            trace!("Reading into accumulator");
-            window = match cobs_buf.feed::<T>(window) {
-                FeedResult::Consumed => {
-                    trace!("Consumed buffer");
-                    break;
-                }
-                FeedResult::OverFull(new_wind) => {
-                    error!("Overfull");
-                    new_wind
-                }
-                FeedResult::DeserError(new_wind) => {
-                    error!("Deserialization error");
-                    new_wind
-                }
-                FeedResult::Success { data, remaining } => {
-                    trace!("Deserialized object");
-                    return_object = Some(data);
-                    if !remaining.is_empty() {
-                        warn!("Remaining size: {}", remaining.len());
-                    }
-                    remaining
-                }
-            };
-            trace!("Read into accumulator");
+        frame.push(raw_buf[0]);
+        if raw_buf[0] != 0 {
+            if frame.len() > max_message_size {
+                error!(
+                    "Received {} bytes without delimiter, closing connection",
+                    frame.len()
+                );
+                let _ = stream.shutdown_connection();
+                return Err(ReadError::MessageTooLarge(max_message_size));
+            }
+            continue;
         }
-        trace!("Read full window");
-        if return_object.is_some() {
-            return return_object;
+        match profiling::measure(HotPath::Decode, || decode_object::<T>(&mut frame)) {
+            Ok(data) => {
+                trace!("Deserialized object");
+                return Ok(data);
+            }
+            Err(ReadError::ProtocolMismatch(header)) => {
+                error!("{}", ReadError::ProtocolMismatch(header));
+                return Err(ReadError::ProtocolMismatch(header));
+            }
+            Err(_) if strict => {
+                error!("Deserialization error");
+                return Err(ReadError::Malformed);
+            }
+            Err(_) => {
+                error!("Deserialization error");
+                frame.clear();
+            }
         }
     }
-    trace!("Read");
-    return_object
+}
+
+/// Encodes the object into a COBS frame, prefixed with the protocol header if the protocol guard
+/// is enabled
+#[cfg(feature = "std")]
+pub fn encode_object<T: Serialize + ?Sized>(object: &T) -> postcard::Result<Vec<u8>> {
+    #[cfg(feature = "protocol_guard")]
+    let frame = to_allocvec_cobs(&(PROTOCOL_HEADER, object));
+    #[cfg(not(feature = "protocol_guard"))]
+    let frame = to_allocvec_cobs(object);
+    frame
+}
+
+/// Decodes an object from a COBS frame (in place), checking its protocol header first
+/// if the protocol guard is enabled
+#[cfg(feature = "std")]
+pub fn decode_object<T>(frame: &mut [u8]) -> Result<T, ReadError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    #[cfg(feature = "protocol_guard")]
+    check_protocol_header(frame)?;
+    #[cfg(feature = "protocol_guard")]
+    let object = postcard::from_bytes_cobs::<([u8; 2], T)>(frame).map(|(_, object)| object);
+    #[cfg(not(feature = "protocol_guard"))]
+    let object = postcard::from_bytes_cobs::<T>(frame);
+    object.map_err(|_| ReadError::Malformed)
+}
+
+#[cfg(feature = "protocol_guard")]
+fn check_protocol_header(frame: &[u8]) -> Result<(), ReadError> {
+    // Decoding works in place, so the header is decoded from a copy
+    let header = postcard::from_bytes_cobs::<[u8; 2]>(&mut frame.to_vec())
+        .map_err(|_| ReadError::Malformed)?;
+    match header == PROTOCOL_HEADER {
+        true => Ok(()),
+        false => Err(ReadError::ProtocolMismatch(header)),
+    }
+}
+
+/// Measures the offset of the clock of the peer to the local one, like a simplified NTP exchange,
+/// assuming the probe and its reply take equally long
+#[cfg(feature = "std")]
+pub fn measure_clock_offset(stream: &mut TcpStream) -> Result<ClockOffset, ReadError> {
+    let sent = get_now_secs();
+    write_object(stream, &ClockProbe { sent })?;
+    let reply = read_object::<ClockReply>(stream)?;
+    let received = get_now_secs();
+    let clock_offset = ClockOffset {
+        offset: reply.peer_time - (reply.sent + received) / 2.0,
+        round_trip_time: received - reply.sent,
+    };
+    write_object(stream, &clock_offset)?;
+    Ok(clock_offset)
+}
+
+/// Answers a clock probe of the peer, returning the offset the peer measured
+#[cfg(feature = "std")]
+pub fn answer_clock_probe(stream: &mut TcpStream) -> Result<ClockOffset, ReadError> {
+    let probe = read_object::<ClockProbe>(stream)?;
+    write_object(
+        stream,
+        &ClockReply {
+            sent: probe.sent,
+            peer_time: get_now_secs(),
+        },
+    )?;
+    let clock_offset = read_object::<ClockOffset>(stream)?;
+    info!(
+        "Clock is off by {:.1} ms (round trip time {:.1} ms)",
+        clock_offset.offset * 1000.0,
+        clock_offset.round_trip_time * 1000.0
+    );
+    Ok(clock_offset)
+}
+
+#[cfg(feature = "std")]
+fn write_object<T: Serialize>(stream: &mut TcpStream, object: &T) -> Result<(), ReadError> {
+    stream
+        .write_all(&encode_object(object).expect("Could not encode object"))
+        .map_err(ReadError::Io)
+}
+
+/// Creates the subdirectory of the component in the directory of the run, if the run has one,
+/// so that concurrent runs on one host do not overwrite or pick up each other's files.
+/// A relative run directory is resolved against the working directory, and the returned path is
+/// absolute so that it stays valid for child processes started elsewhere.
+#[cfg(feature = "std")]
+pub fn create_run_directory(
+    run_directory: Option<&str>,
+    component: &str,
+) -> io::Result<Option<PathBuf>> {
+    run_directory
+        .map(|run_directory| {
+            let path = Path::new(run_directory).join(component);
+            fs::create_dir_all(&path)?;
+            fs::canonicalize(path)
+        })
+        .transpose()
 }
 
 #[cfg(feature = "std")]
@@ -109,8 +407,13 @@ pub fn get_now_duration() -> Duration {
         .expect("Could not get epoch seconds")
 }
 
-pub fn rpm_to_rad(rpm: f64) -> f64 {
-    rpm / 60.0 * PI * 2.0
+/// Delay (up to `max_jitter_ms`) the sensor adds before connecting to the monitor.
+/// It only depends on the sensor id, so the validator can model when the sensor starts sending.
+#[cfg(feature = "std")]
+pub fn get_connect_jitter(sensor_id: u32, max_jitter_ms: u64) -> Duration {
+    // Fibonacci hashing, spreading consecutive ids across the jitter window
+    let hash = (sensor_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+    Duration::from_millis(hash % (max_jitter_ms + 1))
 }
 
 pub fn get_duration_to_end(start_time: Duration, duration: Duration) -> Duration {
@@ -124,102 +427,104 @@ pub fn get_duration_to_end(start_time: Duration, duration: Duration) -> Duration
     start_time - get_now_duration() + duration
 }
 
+/// Lets only every `rate`th message on a hot path be logged,
+/// so that debug logging neither drowns the log nor distorts the benchmark
 #[cfg(feature = "std")]
-pub fn save_benchmark_readings(id: u32, benchmark_data_type: BenchmarkDataType) {
-    info!("Saving benchmark readings");
-    let load_average = LoadAverage::new().expect("Could not get load average").one;
-    let me = Process::myself().expect("Could not get process info handle");
-    let (cstime, cutime) = me
-        .tasks()
-        .unwrap()
-        .flatten()
-        .filter_map(|task| task.stat().ok())
-        .fold((0, 0), |(stime, utime), task_stat| {
-            (stime + task_stat.stime, utime + task_stat.utime)
-        });
-    let stat = me.stat().expect("Could not get /proc/[pid]/stat info");
-    let status = me.status().expect("Could not get /proc/[pid]/status info");
-    let benchmark_data = BenchmarkData {
-        id,
-        time_spent_in_user_mode: stat.utime,
-        time_spent_in_kernel_mode: stat.stime,
-        children_time_spent_in_user_mode: cutime,
-        children_time_spent_in_kernel_mode: cstime,
-        peak_resident_set_size: status.vmhwm.expect("Could not get vmhw"),
-        peak_virtual_memory_size: status.vmpeak.expect("Could not get vmrss"),
-        load_average,
-        benchmark_data_type,
-    };
-    let vec: Vec<u8> =
-        to_allocvec_cobs(&benchmark_data).expect("Could not write benchmark data to Vec<u8>");
-    let _ = std::io::stdout()
-        .write(&vec)
-        .expect("Could not write benchmark data bytes to stdout");
-    info!("Wrote benchmark data");
-}
-
-#[cfg(feature = "std")]
-pub fn get_motor_monitor_parameters(arguments: &[String]) -> MotorMonitorParameters {
-    MotorMonitorParameters {
-        start_time: arguments
-            .get(1)
-            .expect("Did not receive at least 2 arguments")
-            .parse()
-            .expect("Could not parse start_time successfully"),
-        duration: arguments
-            .get(2)
-            .expect("Did not receive at least 3 arguments")
-            .parse()
-            .expect("Could not parse duration successfully"),
-        request_processing_model: RequestProcessingModel::from_str(
-            arguments
-                .get(3)
-                .expect("Did not receive at least 4 arguments"),
-        )
-        .expect("Could not parse Request Processing Model successfully"),
-        number_of_tcp_motor_groups: arguments
-            .get(4)
-            .expect("Did not receive at least 5 arguments")
-            .parse()
-            .expect("Could not parse number_of_motor_groups successfully"),
-        number_of_i2c_motor_groups: arguments
-            .get(5)
-            .expect("Did not receive at least 5 arguments")
-            .parse()
-            .expect("Could not parse number_of_motor_groups successfully"),
-        window_size_ms: arguments
-            .get(6)
-            .expect("Did not receive at least 6 arguments")
-            .parse()
-            .expect("Could not parse window_size successfully"),
-        sensor_listen_address: arguments
-            .get(7)
-            .expect("Did not receive at least 7 arguments")
-            .parse()
-            .expect("Could not parse sensor listen address successfully"),
-        motor_monitor_listen_address: arguments
-            .get(8)
-            .expect("Did not receive at least 8 arguments")
-            .parse()
-            .expect("Could not parse motor monitor listen address successfully"),
-        window_sampling_interval: arguments
-            .get(9)
-            .expect("Did not receive at least 9 arguments")
-            .parse()
-            .expect("Could not parse sampling_interval successfully"),
-        sensor_sampling_interval: arguments
-            .get(10)
-            .expect("Did not receive at least 9 arguments")
-            .parse()
-            .expect("Could not parse sampling_interval successfully"),
-        thread_pool_size: arguments
-            .get(11)
-            .expect("Did not receive at least 10 arguments")
-            .parse()
-            .expect("Could not parse thread_pool_size successfully"),
+#[derive(Debug, Copy, Clone)]
+pub struct LogSampler {
+    rate: u64,
+    calls: u64,
+}
+
+#[cfg(feature = "std")]
+impl LogSampler {
+    pub fn new(rate: u64) -> LogSampler {
+        LogSampler {
+            rate: rate.max(1),
+            calls: 0,
+        }
+    }
+
+    /// Returns whether the current message should be logged.
+    /// Callers check `log_enabled!` first, so nothing is counted if the level is disabled.
+    pub fn sample(&mut self) -> bool {
+        let sampled = self.calls == 0;
+        self.calls = (self.calls + 1) % self.rate;
+        sampled
+    }
+}
+
+/// Tracks the wear of a motor's tool according to a [`WearModel`].
+/// All times are the timestamps of the processed sensor data, so that every
+/// implementation tracks the same wear for the same input.
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct ToolWear {
+    wear_model: WearModel,
+    wear: f64,
+    last_update: Option<Duration>,
+    last_tool_change: Option<Duration>,
+}
+
+#[cfg(feature = "std")]
+impl ToolWear {
+    pub fn new(wear_model: WearModel) -> ToolWear {
+        ToolWear {
+            wear_model,
+            wear: 0f64,
+            last_update: None,
+            last_tool_change: None,
+        }
+    }
+
+    /// Accumulates the wear since the last update if the tool was rotating fast enough,
+    /// changing the tool first if it is due, and returns the current wear
+    pub fn update(&mut self, rotational_speed: Rpm, at_time: Duration) -> Seconds {
+        let last_tool_change = *self.last_tool_change.get_or_insert(at_time);
+        if let ToolChange::Periodic(interval) = self.wear_model.tool_change {
+            if at_time.saturating_sub(last_tool_change) >= interval {
+                self.change_tool(at_time);
+            }
+        }
+        let elapsed = at_time.saturating_sub(self.last_update.unwrap_or(at_time));
+        if rotational_speed > Rpm(self.wear_model.rotational_speed_threshold) {
+            self.wear += elapsed.as_secs_f64() * self.wear_model.wear_rate;
+        }
+        self.last_update = Some(at_time);
+        Seconds(self.wear)
+    }
+
+    /// Changes the tool if the failure was caused by overstrain and the model requires it
+    pub fn register_failure(&mut self, failure: MotorFailure, at_time: Duration) {
+        if failure == MotorFailure::OverstrainFailure
+            && self.wear_model.tool_change == ToolChange::OnOverstrainFailure
+        {
+            self.change_tool(at_time);
+        }
+    }
+
+    pub fn get_wear(&self) -> Seconds {
+        Seconds(self.wear)
+    }
+
+    fn change_tool(&mut self, at_time: Duration) {
+        debug!("Changing tool at {at_time:?} with wear {:5.2}", self.wear);
+        self.wear = 0f64;
+        self.last_tool_change = Some(at_time);
     }
 }
 
+/// Age of a motor at the given time, counted from the connection of its sensors after the start
+/// time of the run and never reset, so that a continuously failing motor does not look young
+#[cfg(feature = "std")]
+pub fn get_cumulative_age(start_time: Duration, at_time: Duration) -> Seconds {
+    Seconds(
+        at_time
+            .saturating_sub(start_time + SENSOR_CONNECTION_DELAY)
+            .as_secs_f64(),
+    )
+}
+
 /**
 1. heat dissipation failure (HDF) heat dissipation causes a process failure,
     if the difference between air- and process temperature is below 8.6 K and the tool’s rotational speed is below 1380 rpm
@@ -227,24 +532,73 @@ pub fn get_motor_monitor_parameters(arguments: &[String]) -> MotorMonitorParamet
     required for the process. If this power is below 3500 W or above 9000 W, the process fails.
 3. overstrain failure (OSF) if the product of tool wear and torque exceeds 11,000 minNm for the L
     product variant (12,000 for M, 13,000 for H), the process fails due to overstrain.
+
+These are the default `RuleThresholds`, which a run may override.
+The tool wear is tracked in seconds instead of minutes (see [`Seconds`]), so the overstrain limit is in Nms.
  **/
 #[cfg(feature = "std")]
 pub fn sensor_data_indicates_failure(
-    air_temperature: f64,
-    process_temperature: f64,
-    rotational_speed: f64,
-    torque: f64,
-    age: Duration,
+    readings: MotorReadings,
+    tool_wear: Seconds,
+    thresholds: &RuleThresholds,
 ) -> Option<MotorFailure> {
-    let rotational_speed_in_rad = rpm_to_rad(rotational_speed);
+    if readings_indicate_random_failure(readings) {
+        return Some(MotorFailure::RandomFailure);
+    }
     relevant_data_indicates_failure(
-        air_temperature - process_temperature,
-        rotational_speed,
-        torque * rotational_speed_in_rad,
-        age.as_secs_f64() * torque,
+        readings.get_temperature_difference(),
+        readings.rotational_speed,
+        readings.get_power(),
+        tool_wear * readings.torque,
+        thresholds,
     )
 }
 
+/// Seed of the FxHash the window fingerprints are computed with
+#[cfg(feature = "std")]
+const FINGERPRINT_SEED: u64 = 0x517c_c1b7_2722_0a95;
+
+/// Fingerprint of the window of a sensor, hashing its (timestamp, reading) pairs in sorted order
+/// over their canonicalized bit patterns, so that each model yields the same fingerprint for
+/// the same window contents, regardless of the order they were received or stored in
+#[cfg(feature = "std")]
+pub fn get_window_fingerprint<'a>(
+    messages: impl IntoIterator<Item = &'a SensorMessage>,
+) -> ChannelFingerprint {
+    let mut pairs: Vec<(u64, u64)> = messages
+        .into_iter()
+        .map(|message| {
+            (
+                canonicalize(message.timestamp),
+                canonicalize(message.reading as f64),
+            )
+        })
+        .collect();
+    pairs.sort_unstable();
+    let hash = pairs
+        .iter()
+        .flat_map(|(timestamp, reading)| [*timestamp, *reading])
+        .fold(0u64, |hash, word| {
+            (hash.rotate_left(5) ^ word).wrapping_mul(FINGERPRINT_SEED)
+        });
+    ChannelFingerprint {
+        count: pairs.len() as u32,
+        hash,
+    }
+}
+
+/// Bit pattern of the value, with all zeros and all NaNs mapped to a single one
+#[cfg(feature = "std")]
+fn canonicalize(value: f64) -> u64 {
+    if value == 0f64 {
+        0
+    } else if value.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
 /**
 1. air temperature [K] generated using a random walk process later normalized to a standard deviation of 2 K around 300 K
 2. process temperature [K] generated using a random walk process normalized to a standard deviation of 1 K, added to the air temperature plus 10 K
@@ -253,53 +607,258 @@ pub fn sensor_data_indicates_failure(
  **/
 #[cfg(feature = "std")]
 pub fn averages_indicate_failure(
-    air_temperature: f64,
-    process_temperature: f64,
-    rotational_speed: f64,
-    torque: f64,
+    readings: MotorReadings,
     window_size: usize,
+    tool_wear: Seconds,
+    thresholds: &RuleThresholds,
 ) -> Option<MotorFailure> {
-    let rotational_speed_in_rad = rpm_to_rad(rotational_speed);
+    if readings_indicate_random_failure(readings) {
+        return Some(MotorFailure::RandomFailure);
+    }
+    let MotorReadings {
+        air_temperature,
+        process_temperature,
+        rotational_speed,
+        torque,
+    } = readings;
+    let rotational_speed_in_rad = rotational_speed.to_rad_per_second();
     let sqrt_sample_size = f64::sqrt(window_size as f64);
     debug!(
         "TEMP_DIFF_MEAN: {:5.2}, TEMP_CI: {:5.2}, actual_diff: {:5.2}",
         TEMP_DIFF_MEAN,
         CRITICAL_VALUE * TEMP_DIFF_SD / sqrt_sample_size,
-        process_temperature - air_temperature
+        (process_temperature - air_temperature).0
     );
     debug!(
         "POWER_MEAN: {:5.2}, POWER_CI: {:5.2}, actual_power: {:5.2}",
         POWER_MEAN,
         CRITICAL_VALUE * POWER_SD / sqrt_sample_size,
-        torque * rotational_speed_in_rad
+        (torque * rotational_speed_in_rad).0
     );
-    if ((air_temperature - process_temperature).abs() - TEMP_DIFF_MEAN).abs()
+    if ((air_temperature - process_temperature).abs().0 - TEMP_DIFF_MEAN).abs()
         > CRITICAL_VALUE * TEMP_DIFF_SD / sqrt_sample_size
     {
         Some(HeatDissipationFailure)
-    } else if ((torque * rotational_speed_in_rad) - POWER_MEAN).abs()
+    } else if ((torque * rotational_speed_in_rad).0 - POWER_MEAN).abs()
         > CRITICAL_VALUE * POWER_SD / sqrt_sample_size
     {
         Some(PowerFailure)
+    } else if tool_wear * torque > NewtonMeterSeconds(thresholds.overstrain_limit) {
+        Some(MotorFailure::OverstrainFailure)
     } else {
         None
     }
 }
 
+/// None of the sensors can measure negative values, so a negative reading (or an average
+/// pulled below zero by one) can only stem from a sensor emitting the random failure reading
+#[cfg(feature = "std")]
+fn readings_indicate_random_failure(readings: MotorReadings) -> bool {
+    readings.air_temperature < Kelvin(0.0)
+        || readings.process_temperature < Kelvin(0.0)
+        || readings.rotational_speed < Rpm(0.0)
+        || readings.torque < NewtonMeter(0.0)
+}
+
 #[cfg(feature = "std")]
 pub fn relevant_data_indicates_failure(
-    temp_diff: f64,
-    rotational_speed: f64,
-    power: f64,
-    strain: f64,
+    temp_diff: Kelvin,
+    rotational_speed: Rpm,
+    power: Watt,
+    strain: NewtonMeterSeconds,
+    thresholds: &RuleThresholds,
 ) -> Option<MotorFailure> {
-    if temp_diff.abs() < 8.6 && rotational_speed < 1380.0 {
+    if temp_diff.abs() < Kelvin(thresholds.heat_dissipation_temperature_difference)
+        && rotational_speed < Rpm(thresholds.heat_dissipation_rotational_speed)
+    {
         Some(MotorFailure::HeatDissipationFailure)
-    } else if !(3500.0..=9000.0).contains(&power) {
+    } else if !(Watt(thresholds.min_power)..=Watt(thresholds.max_power)).contains(&power) {
         Some(MotorFailure::PowerFailure)
-    } else if strain > 11_000_f64 {
+    } else if strain > NewtonMeterSeconds(thresholds.overstrain_limit) {
         Some(MotorFailure::OverstrainFailure)
     } else {
         None
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use data_transfer_objects::MotorMonitorParameters;
+    use std::cell::Cell;
+    use std::net::SocketAddr;
+
+    /// Peer which sends bytes without ever sending a message delimiter
+    struct UndelimitedStream {
+        bytes_read: usize,
+        shut_down: Cell<bool>,
+    }
+
+    impl Read for UndelimitedStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            buf.fill(1);
+            self.bytes_read += buf.len();
+            Ok(buf.len())
+        }
+    }
+
+    impl MessageStream for UndelimitedStream {
+        fn shutdown_connection(&self) -> io::Result<()> {
+            self.shut_down.set(true);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn undelimited_stream_is_given_up() {
+        let mut stream = UndelimitedStream {
+            bytes_read: 0,
+            shut_down: Cell::new(false),
+        };
+
+        let result = read_object_with_limit::<u32>(&mut stream, 64);
+
+        assert!(matches!(result, Err(ReadError::MessageTooLarge(64))));
+        assert!(stream.shut_down.get());
+        assert_eq!(stream.bytes_read, 65);
+    }
+
+    impl MessageStream for &[u8] {
+        fn shutdown_connection(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn get_test_message() -> SensorMessage {
+        SensorMessage {
+            reading: 298.1,
+            sensor_id: 1,
+            timestamp: 1.5,
+            sample_id: None,
+        }
+    }
+
+    #[cfg(feature = "protocol_guard")]
+    #[test]
+    fn message_of_other_protocol_version_is_rejected() {
+        let other_header = [PROTOCOL_HEADER[0], PROTOCOL_VERSION - 1];
+        let mut frames = to_allocvec_cobs(&(other_header, get_test_message())).unwrap();
+        frames.extend(encode_object(&get_test_message()).unwrap());
+        let mut stream = frames.as_slice();
+
+        let result = read_object::<SensorMessage>(&mut stream);
+
+        assert!(
+            matches!(result, Err(ReadError::ProtocolMismatch(header)) if header == other_header)
+        );
+    }
+
+    #[test]
+    fn message_of_own_protocol_version_is_read() {
+        let frame = encode_object(&get_test_message()).unwrap();
+
+        let message = read_object::<SensorMessage>(&mut frame.as_slice()).unwrap();
+
+        assert_eq!(message.reading, 298.1);
+        assert_eq!(message.sensor_id, 1);
+    }
+
+    /// Readings of a motor at 1500 rpm whose power is the given one
+    fn get_readings_with_power(power: f64) -> MotorReadings {
+        let rotational_speed = Rpm(1500.0);
+        MotorReadings {
+            air_temperature: Kelvin(300.0),
+            process_temperature: Kelvin(310.0),
+            rotational_speed,
+            torque: NewtonMeter(power / rotational_speed.to_rad_per_second().0),
+        }
+    }
+
+    #[test]
+    fn overriding_the_power_band_changes_which_readings_fail() {
+        let default_thresholds = RuleThresholds::default();
+        let thresholds = RuleThresholds {
+            min_power: 2000.0,
+            max_power: 5000.0,
+            ..default_thresholds
+        };
+        for (power, default_failure, failure) in [
+            (1500.0, Some(PowerFailure), Some(PowerFailure)),
+            (3000.0, Some(PowerFailure), None),
+            (4000.0, None, None),
+            (7000.0, None, Some(PowerFailure)),
+            (9500.0, Some(PowerFailure), Some(PowerFailure)),
+        ] {
+            let readings = get_readings_with_power(power);
+            assert_eq!(
+                sensor_data_indicates_failure(readings, Seconds(0.0), &default_thresholds),
+                default_failure,
+                "{power} W"
+            );
+            assert_eq!(
+                sensor_data_indicates_failure(readings, Seconds(0.0), &thresholds),
+                failure,
+                "{power} W"
+            );
+        }
+    }
+
+    /// A frame with a valid protocol header whose message is cut short, followed by a valid one
+    fn get_corrupt_frames() -> Vec<u8> {
+        let mut frames = to_allocvec_cobs(&(PROTOCOL_HEADER, [0xffu8; 3])).unwrap();
+        frames.extend(encode_object(&get_test_message()).unwrap());
+        frames
+    }
+
+    #[test]
+    fn corrupt_frame_is_an_error_in_strict_mode() {
+        let frames = get_corrupt_frames();
+        let mut stream = frames.as_slice();
+
+        let result = read_sensor_message(&mut stream, true, false);
+
+        assert!(matches!(result, Err(ReadError::Malformed)));
+    }
+
+    #[test]
+    fn corrupt_frame_is_skipped_in_lenient_mode() {
+        let frames = get_corrupt_frames();
+        let mut stream = frames.as_slice();
+
+        let message = read_sensor_message(&mut stream, false, false).unwrap();
+
+        assert_eq!(message.reading, 298.1);
+    }
+
+    /// Parameters of a monitor sending its alerts to the cloud server at the address
+    pub(crate) fn get_test_monitor_parameters(
+        cloud_server_address: SocketAddr,
+        flags: &[&str],
+    ) -> MotorMonitorParameters {
+        let start_time = get_now_secs().to_string();
+        let cloud_server_address = cloud_server_address.to_string();
+        let mut arguments = vec![
+            "motor_monitor",
+            &start_time,
+            "60",
+            "ClientServer",
+            "1",
+            "0",
+            "1000",
+            "127.0.0.1:0",
+            &cloud_server_address,
+            "100",
+            "10",
+            "1",
+            "1.0",
+            "0",
+            "0",
+            "false",
+            "0",
+        ];
+        arguments.extend(flags);
+        arguments::get_motor_monitor_parameters(
+            &arguments.into_iter().map(String::from).collect::<Vec<_>>(),
+        )
+    }
+}
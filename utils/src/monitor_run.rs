@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use data_transfer_objects::MotorMonitorParameters;
+
+use crate::benchmark::RunStatistics;
+use crate::telemetry::Telemetry;
+use crate::window_export::WindowExport;
+
+/// State of the run of a monitor which its threads share, passed to them explicitly
+#[derive(Clone)]
+pub struct MonitorRun {
+    pub run_statistics: RunStatistics,
+    pub window_export: Arc<WindowExport>,
+    pub telemetry: Telemetry,
+}
+
+impl MonitorRun {
+    /// Creates the window export and starts the span export the monitor was passed, if any.
+    /// The statistics are created before, for the crash handler to report them.
+    pub fn new(
+        service_name: &'static str,
+        motor_monitor_parameters: &MotorMonitorParameters,
+        run_statistics: RunStatistics,
+    ) -> MonitorRun {
+        MonitorRun {
+            run_statistics,
+            window_export: Arc::new(WindowExport::new(motor_monitor_parameters)),
+            telemetry: Telemetry::new(
+                service_name,
+                motor_monitor_parameters.otel_endpoint.as_deref(),
+            ),
+        }
+    }
+}
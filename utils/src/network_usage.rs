@@ -0,0 +1,207 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use data_transfer_objects::NetworkUsage;
+
+use crate::MessageStream;
+
+/// Connection of a monitor to the cloud server, counting the bytes of the alerts sent over it,
+/// or a sink dropping the alerts if the monitor runs without a cloud server
+pub enum CloudServerStream {
+    Connected(CountingStream<TcpStream>),
+    Discarding,
+}
+
+/// Bytes written to and read from all streams counted by it, e.g. those to one class of peers
+#[derive(Debug, Default)]
+pub struct ByteCounter {
+    written: AtomicU64,
+    read: AtomicU64,
+}
+
+impl ByteCounter {
+    pub const fn new() -> ByteCounter {
+        ByteCounter {
+            written: AtomicU64::new(0),
+            read: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get_usage(&self) -> NetworkUsage {
+        NetworkUsage {
+            bytes_written: self.written.load(Ordering::Relaxed),
+            bytes_read: self.read.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Stream adding the bytes written to and read from it to a counter, which it shares with
+/// the other streams to the same class of peers
+pub struct CountingStream<S> {
+    stream: S,
+    counter: Arc<ByteCounter>,
+}
+
+impl<S> CountingStream<S> {
+    pub fn new(stream: S, counter: Arc<ByteCounter>) -> CountingStream<S> {
+        CountingStream { stream, counter }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn get_counter(&self) -> &Arc<ByteCounter> {
+        &self.counter
+    }
+}
+
+impl<S: Read> Read for CountingStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stream.read(buf)?;
+        self.counter.read.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+impl<S: Write> Write for CountingStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.stream.write(buf)?;
+        self.counter
+            .written
+            .fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S: MessageStream> MessageStream for CountingStream<S> {
+    fn shutdown_connection(&self) -> io::Result<()> {
+        self.stream.shutdown_connection()
+    }
+}
+
+impl Read for CloudServerStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            CloudServerStream::Connected(stream) => stream.read(buf),
+            CloudServerStream::Discarding => Ok(0),
+        }
+    }
+}
+
+impl Write for CloudServerStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CloudServerStream::Connected(stream) => stream.write(buf),
+            CloudServerStream::Discarding => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CloudServerStream::Connected(stream) => stream.flush(),
+            CloudServerStream::Discarding => Ok(()),
+        }
+    }
+}
+
+impl MessageStream for CloudServerStream {
+    fn shutdown_connection(&self) -> io::Result<()> {
+        match self {
+            CloudServerStream::Connected(stream) => stream.shutdown_connection(),
+            CloudServerStream::Discarding => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_sensor_message;
+    use data_transfer_objects::{CompactSensorMessage, SensorMessage};
+    use std::fs;
+
+    const RESOURCES: [&str; 4] = [
+        "air_temperature",
+        "process_temperature",
+        "rotational_speed",
+        "torque",
+    ];
+
+    /// Frames read from memory, like those a monitor reads from a sensor connection
+    struct FrameStream<'a> {
+        frames: &'a [u8],
+    }
+
+    impl Read for FrameStream<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.frames.read(buf)
+        }
+    }
+
+    impl MessageStream for FrameStream<'_> {
+        fn shutdown_connection(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Messages of the bundled datasets, taken every 100 ms from a current start time
+    fn get_dataset_messages() -> Vec<SensorMessage> {
+        let start_time = 1_700_000_000.123;
+        RESOURCES
+            .iter()
+            .enumerate()
+            .flat_map(|(sensor_id, resource)| {
+                fs::read_to_string(format!("../sensor/resources/{resource}.txt"))
+                    .unwrap()
+                    .lines()
+                    .filter_map(|line| line.trim().parse().ok())
+                    .enumerate()
+                    .map(|(index, reading)| SensorMessage {
+                        reading,
+                        sensor_id: sensor_id as u32,
+                        timestamp: start_time + index as f64 * 0.1,
+                        sample_id: None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Bytes the ingress counter of the monitor counts per message read
+    fn get_bytes_per_message(messages: &[SensorMessage], compact_wire: bool) -> f64 {
+        let frames: Vec<u8> = messages
+            .iter()
+            .flat_map(|message| match compact_wire {
+                true => crate::encode_object(&CompactSensorMessage::from(*message)).unwrap(),
+                false => crate::encode_object(message).unwrap(),
+            })
+            .collect();
+        let counter = Arc::new(ByteCounter::new());
+        let mut stream = CountingStream::new(FrameStream { frames: &frames }, counter.clone());
+        for message in messages {
+            let received = read_sensor_message(&mut stream, true, compact_wire).unwrap();
+            assert_eq!(received.sensor_id, message.sensor_id);
+        }
+        counter.get_usage().bytes_read as f64 / messages.len() as f64
+    }
+
+    #[test]
+    fn compact_wire_saves_a_fifth_of_the_bytes_per_message() {
+        let messages = get_dataset_messages();
+        let plain = get_bytes_per_message(&messages, false);
+        let compact = get_bytes_per_message(&messages, true);
+        let reduction = 1.0 - compact / plain;
+        assert!(
+            (0.15..0.25).contains(&reduction),
+            "Read {plain:.2} bytes per plain message and {compact:.2} per compact one"
+        );
+    }
+}
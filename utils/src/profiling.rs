@@ -0,0 +1,105 @@
+//! Time accounting of the hot paths of a monitor, for comparing where the monitors spend their
+//! cycles on the same input. Without the `profiling` feature, [measure] only calls its closure
+//! and the other functions do nothing, so the instrumentation is compiled out.
+
+use data_transfer_objects::{HotPath, HotPathProfile};
+
+#[cfg(feature = "profiling")]
+use data_transfer_objects::HotPathTime;
+#[cfg(feature = "profiling")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "profiling")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+
+/// Counters of a single thread, which only it writes to, registered globally to be summed up
+/// when the benchmark data is written
+#[cfg(feature = "profiling")]
+#[derive(Default)]
+struct ThreadCounters {
+    calls: [AtomicU64; 4],
+    nanoseconds: [AtomicU64; 4],
+    processed_messages: AtomicU64,
+}
+
+#[cfg(feature = "profiling")]
+static THREAD_COUNTERS: Mutex<Vec<Arc<ThreadCounters>>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "profiling")]
+thread_local! {
+    static COUNTERS: Arc<ThreadCounters> = {
+        let counters = Arc::new(ThreadCounters::default());
+        THREAD_COUNTERS
+            .lock()
+            .expect("Could not lock profiling counters")
+            .push(counters.clone());
+        counters
+    };
+}
+
+/// Runs the hot path, adding the time it took to the ones of its category
+#[cfg(feature = "profiling")]
+pub fn measure<T>(hot_path: HotPath, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed().as_nanos() as u64;
+    COUNTERS.with(|counters| {
+        let index = hot_path as usize;
+        counters.calls[index].fetch_add(1, Ordering::Relaxed);
+        counters.nanoseconds[index].fetch_add(elapsed, Ordering::Relaxed);
+    });
+    result
+}
+
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn measure<T>(_hot_path: HotPath, f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+/// Counts a decoded sensor message, which the times of the hot paths are normalized by
+#[cfg(feature = "profiling")]
+pub fn count_message() {
+    COUNTERS.with(|counters| counters.processed_messages.fetch_add(1, Ordering::Relaxed));
+}
+
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn count_message() {}
+
+/// Times of the hot paths summed over all threads, absent without the `profiling` feature
+#[cfg(feature = "profiling")]
+pub fn get_profile() -> Option<HotPathProfile> {
+    let thread_counters = THREAD_COUNTERS
+        .lock()
+        .expect("Could not lock profiling counters");
+    Some(HotPathProfile {
+        processed_messages: thread_counters
+            .iter()
+            .map(|counters| counters.processed_messages.load(Ordering::Relaxed))
+            .sum(),
+        hot_paths: HotPath::ALL
+            .iter()
+            .map(|hot_path| {
+                let index = *hot_path as usize;
+                HotPathTime {
+                    hot_path: *hot_path,
+                    calls: thread_counters
+                        .iter()
+                        .map(|counters| counters.calls[index].load(Ordering::Relaxed))
+                        .sum(),
+                    nanoseconds: thread_counters
+                        .iter()
+                        .map(|counters| counters.nanoseconds[index].load(Ordering::Relaxed))
+                        .sum(),
+                }
+            })
+            .collect(),
+    })
+}
+
+#[cfg(not(feature = "profiling"))]
+pub fn get_profile() -> Option<HotPathProfile> {
+    None
+}
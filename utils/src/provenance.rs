@@ -0,0 +1,70 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use log::warn;
+
+use data_transfer_objects::{SampledMessageId, SampledMessageSend, SensorMessage};
+
+/// Number of sampled messages an alert lists at most
+pub const MAX_SAMPLED_MESSAGES: usize = 16;
+
+/// File a sensor logs the send times of its sampled messages to,
+/// which its sensor driver collects once the sensor exited
+pub fn get_send_log_path(run_id: u64, sensor_id: u32) -> PathBuf {
+    env::temp_dir().join(format!("provenance_{run_id}_{sensor_id}.csv"))
+}
+
+/// Appends the send time of a sampled message to the send log, as `<sample id>,<send time>`
+pub fn log_send(send_log: &mut impl Write, message: &SensorMessage) {
+    if let Some(sample_id) = message.sample_id {
+        if let Err(e) = writeln!(send_log, "{sample_id},{}", message.timestamp) {
+            warn!("Could not log send of sampled message {sample_id}: {e}");
+        }
+    }
+}
+
+/// Reads the send log of a sensor, skipping the lines which could not be parsed,
+/// e.g. the last one if the sensor exited while writing it
+pub fn read_send_log(run_id: u64, sensor_id: u32) -> Vec<SampledMessageSend> {
+    let path = get_send_log_path(run_id, sensor_id);
+    let send_log = match File::open(&path) {
+        Ok(send_log) => send_log,
+        Err(e) => {
+            warn!("Could not open send log {}: {e}", path.display());
+            return vec![];
+        }
+    };
+    BufReader::new(send_log)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| {
+            let (sample_id, send_time) = line.split_once(',')?;
+            Some(SampledMessageSend {
+                id: SampledMessageId {
+                    sensor_id,
+                    sample_id: sample_id.parse().ok()?,
+                },
+                send_time: send_time.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Ids of the messages sampled for provenance tracking among the messages of a window,
+/// of which at most `MAX_SAMPLED_MESSAGES` are kept, as they are sent along with an alert
+pub fn get_sampled_messages<'a>(
+    messages: impl IntoIterator<Item = &'a SensorMessage>,
+) -> Vec<SampledMessageId> {
+    messages
+        .into_iter()
+        .filter_map(|message| {
+            message.sample_id.map(|sample_id| SampledMessageId {
+                sensor_id: message.sensor_id,
+                sample_id,
+            })
+        })
+        .take(MAX_SAMPLED_MESSAGES)
+        .collect()
+}
@@ -0,0 +1,152 @@
+use log::{log_enabled, warn, Level};
+
+use data_transfer_objects::{
+    DataColumn, MotorMonitorParameters, SensorKind, SensorMessage, RANDOM_FAILURE_READING,
+};
+
+use crate::LogSampler;
+
+/// Number of readings of a sensor below which its quarantine rate is not checked yet,
+/// so that a single bad reading at the start does not fail the run
+const MIN_READINGS_FOR_RATE: u64 = 100;
+
+/// Whether the reading lies in the plausible range of the kind of its sensor.
+/// The random failure reading is deliberately out of range, and passes to be detected as such.
+pub fn is_plausible_reading(message: &SensorMessage) -> bool {
+    message.reading == RANDOM_FAILURE_READING
+        || SensorKind::from_sensor_id(message.sensor_id)
+            .plausible_range()
+            .contains(&message.reading)
+}
+
+/// Parses the readings from the given column of the lines of a sensor data file, skipping those
+/// which are not a plausible reading of the given kind, like a header row or a truncated line
+pub fn parse_sensor_data(data: &str, sensor_kind: SensorKind, data_column: DataColumn) -> Vec<f32> {
+    let plausible_range = sensor_kind.plausible_range();
+    let mut readings = vec![];
+    let mut first_invalid_line = None;
+    let mut number_of_invalid_lines = 0;
+    for (index, line) in data.lines().enumerate() {
+        match data_column
+            .get_field(line)
+            .map(|field| field.trim().parse::<f32>())
+        {
+            Some(Ok(reading)) if plausible_range.contains(&reading) => readings.push(reading),
+            _ => {
+                first_invalid_line.get_or_insert(index + 1);
+                number_of_invalid_lines += 1;
+            }
+        }
+    }
+    if let Some(first_invalid_line) = first_invalid_line {
+        warn!(
+            "Skipped {number_of_invalid_lines} lines of {} data which are no reading in {plausible_range:?}, the first one being line {first_invalid_line}",
+            sensor_kind.name()
+        );
+    }
+    readings
+}
+
+/// Keeps the implausible readings of a sensor out of the windows, counting them, and failing
+/// the run if they exceed the maximum quarantine rate, as this indicates systematic corruption
+/// rather than noise
+pub struct ReadingQuarantine {
+    max_quarantine_rate: Option<f32>,
+    log_sampler: LogSampler,
+    sensor_id: Option<u32>,
+    number_of_readings: u64,
+    number_of_quarantined_readings: u64,
+}
+
+impl ReadingQuarantine {
+    pub fn new(motor_monitor_parameters: &MotorMonitorParameters) -> ReadingQuarantine {
+        ReadingQuarantine {
+            max_quarantine_rate: motor_monitor_parameters.max_quarantine_rate,
+            log_sampler: LogSampler::new(motor_monitor_parameters.log_sample_rate),
+            sensor_id: None,
+            number_of_readings: 0,
+            number_of_quarantined_readings: 0,
+        }
+    }
+
+    /// Returns whether the reading may be evaluated
+    pub fn admit(&mut self, message: &SensorMessage) -> bool {
+        self.sensor_id.get_or_insert(message.sensor_id);
+        self.number_of_readings += 1;
+        if is_plausible_reading(message) {
+            return true;
+        }
+        self.number_of_quarantined_readings += 1;
+        if log_enabled!(Level::Warn) && self.log_sampler.sample() {
+            warn!(
+                "Quarantining implausible reading {} of sensor {}",
+                message.reading, message.sensor_id
+            );
+        }
+        if let Some(max_quarantine_rate) = self.max_quarantine_rate {
+            let quarantine_rate =
+                self.number_of_quarantined_readings as f32 / self.number_of_readings as f32;
+            if self.number_of_readings >= MIN_READINGS_FOR_RATE
+                && quarantine_rate > max_quarantine_rate
+            {
+                panic!(
+                    "Quarantined {} of {} readings of sensor {}, exceeding the maximum quarantine rate of {max_quarantine_rate}",
+                    self.number_of_quarantined_readings, self.number_of_readings, message.sensor_id
+                );
+            }
+        }
+        false
+    }
+
+    /// Logs the number of quarantined readings once the sensor is done sending
+    pub fn log_counts(&self) {
+        if let Some(sensor_id) = self.sensor_id {
+            if self.number_of_quarantined_readings > 0 {
+                warn!(
+                    "Quarantined {} of {} readings of sensor {sensor_id}",
+                    self.number_of_quarantined_readings, self.number_of_readings
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTI_COLUMN_DATA: &str = "time,air_temperature,rotational_speed
+0.0,298.1,1551
+0.1,298.2,1408
+0.2,298.3
+";
+
+    #[test]
+    fn reading_is_parsed_from_the_configured_column() {
+        assert_eq!(
+            parse_sensor_data(
+                MULTI_COLUMN_DATA,
+                SensorKind::AirTemperature,
+                DataColumn::Index(1)
+            ),
+            [298.1, 298.2, 298.3]
+        );
+        // The truncated line has no third column
+        assert_eq!(
+            parse_sensor_data(
+                MULTI_COLUMN_DATA,
+                SensorKind::RotationalSpeed,
+                DataColumn::Index(2)
+            ),
+            [1551.0, 1408.0]
+        );
+    }
+
+    #[test]
+    fn last_column_reads_bare_and_timestamped_readings() {
+        assert_eq!(
+            parse_sensor_data("42.8\n0.1,46.3\n", SensorKind::Torque, DataColumn::Last),
+            [42.8, 46.3]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::mem;
+
+use data_transfer_objects::{Alert, MotorFailure};
+
+/// Remembers whether a motor failed with its last evaluated windows,
+/// to alert once when it is healthy again, so that e.g. a dashboard can clear its state
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RecoveryTracker {
+    failure: Option<MotorFailure>,
+}
+
+impl RecoveryTracker {
+    pub fn new() -> RecoveryTracker {
+        RecoveryTracker::default()
+    }
+
+    /// Registers the result of evaluating the rules on complete windows of the motor, returning
+    /// a recovery alert if it failed with the previous windows and is healthy with these.
+    /// Monitors built without the `recovery_alerts` feature never send one.
+    pub fn update(
+        &mut self,
+        motor_id: u16,
+        failure: Option<MotorFailure>,
+        time: f64,
+    ) -> Option<Alert> {
+        if !cfg!(feature = "recovery_alerts") {
+            return None;
+        }
+        match (mem::replace(&mut self.failure, failure), failure) {
+            (Some(previous_failure), None) => Some(Alert {
+                time,
+                motor_id,
+                failure: previous_failure,
+                fingerprint: None,
+                cleared: true,
+                sampled_messages: vec![],
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Alerts a monitor emits for the results of evaluating consecutive windows of a motor,
+    /// a failure alert for each failing window and a recovery alert if there is one
+    fn get_emitted_alerts(failures: &[Option<MotorFailure>]) -> Vec<Alert> {
+        let mut recovery_tracker = RecoveryTracker::new();
+        failures
+            .iter()
+            .enumerate()
+            .filter_map(|(index, failure)| {
+                let time = index as f64;
+                let recovery_alert = recovery_tracker.update(3, *failure, time);
+                failure
+                    .map(|failure| Alert {
+                        time,
+                        motor_id: 3,
+                        failure,
+                        fingerprint: None,
+                        cleared: false,
+                        sampled_messages: vec![],
+                    })
+                    .or(recovery_alert)
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "recovery_alerts")]
+    #[test]
+    fn fail_then_recover_emits_one_failure_and_one_recovery_alert() {
+        let alerts = get_emitted_alerts(&[None, Some(MotorFailure::PowerFailure), None, None]);
+        assert_eq!(alerts.len(), 2, "{alerts:?}");
+        assert!(!alerts[0].cleared);
+        assert_eq!(alerts[0].time, 1.0);
+        assert!(alerts[1].cleared);
+        assert_eq!(alerts[1].time, 2.0);
+        assert_eq!(alerts[1].motor_id, 3);
+        assert_eq!(alerts[1].failure, MotorFailure::PowerFailure);
+    }
+
+    #[cfg(not(feature = "recovery_alerts"))]
+    #[test]
+    fn recovery_is_not_alerted_without_the_feature() {
+        let alerts = get_emitted_alerts(&[Some(MotorFailure::PowerFailure), None]);
+        assert_eq!(alerts.len(), 1, "{alerts:?}");
+        assert!(!alerts[0].cleared);
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+use std::{env, fs};
+
+use log::{info, warn};
+use serde::Serialize;
+
+/// Environment variable naming the directory the binaries write their resolved configuration to
+pub const RESOLVED_CONFIG_DIR_VARIABLE: &str = "RESOLVED_CONFIG_DIR";
+
+/// Logs the fully resolved parameters a component runs with as JSON, to confirm what it actually
+/// received through the chain of arguments, and writes them to `<component>_resolved_config.json`
+/// in the directory named by `RESOLVED_CONFIG_DIR` if it is set
+pub fn log_resolved_config<T: Serialize>(component: &str, parameters: &T) {
+    let directory = env::var(RESOLVED_CONFIG_DIR_VARIABLE).ok();
+    dump_resolved_config(component, parameters, directory.as_deref().map(Path::new));
+}
+
+fn dump_resolved_config<T: Serialize>(component: &str, parameters: &T, directory: Option<&Path>) {
+    let resolved_config =
+        serde_json::to_string_pretty(parameters).expect("Could not serialize resolved config");
+    info!("Resolved config of {component}: {resolved_config}");
+    if let Some(directory) = directory {
+        let path = directory.join(format!("{component}_resolved_config.json"));
+        if let Err(e) = fs::write(&path, resolved_config) {
+            warn!("Could not write resolved config to {}: {e}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use data_transfer_objects::MotorMonitorParameters;
+
+    use super::*;
+
+    #[test]
+    fn dump_matches_the_parsed_parameters() {
+        let directory = env::temp_dir().join(format!("resolved_config_{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let motor_monitor_parameters = crate::tests::get_test_monitor_parameters(
+            "127.0.0.1:1".parse().unwrap(),
+            &["--cpu-cores", "0,1", "--startup-barrier-ms", "300"],
+        );
+
+        dump_resolved_config("motor_monitor", &motor_monitor_parameters, Some(&directory));
+
+        let dump =
+            fs::read_to_string(directory.join("motor_monitor_resolved_config.json")).unwrap();
+        let dumped_parameters: MotorMonitorParameters = serde_json::from_str(&dump).unwrap();
+        assert_eq!(dumped_parameters.cpu_cores, [0, 1]);
+        assert_eq!(dumped_parameters.startup_barrier_ms, 300);
+        assert_eq!(
+            serde_json::to_value(dumped_parameters).unwrap(),
+            serde_json::to_value(motor_monitor_parameters).unwrap()
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
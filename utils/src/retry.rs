@@ -0,0 +1,120 @@
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::thread;
+use std::time::Duration;
+
+use data_transfer_objects::RetryPolicy;
+use log::debug;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+/// Whether a failed attempt is worth repeating
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Retryable {
+    Retry,
+    GiveUp,
+}
+
+#[derive(Debug)]
+pub enum RetryError<E> {
+    /// Every attempt of the policy failed, the last one with the error
+    Exhausted { attempts: u32, error: E },
+    /// An attempt failed with an error classified as not worth repeating
+    GaveUp(E),
+}
+
+impl<E> RetryError<E> {
+    pub fn into_error(self) -> E {
+        match self {
+            RetryError::Exhausted { error, .. } => error,
+            RetryError::GaveUp(error) => error,
+        }
+    }
+}
+
+impl<E: Display> Display for RetryError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryError::Exhausted { attempts, error } => {
+                write!(f, "{error} (gave up after {attempts} attempts)")
+            }
+            RetryError::GaveUp(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl<E: Debug + Display> std::error::Error for RetryError<E> {}
+
+/// Delays between the attempts of a policy, one less than its attempts
+pub struct Backoff {
+    remaining: u32,
+    next_delay_ms: f64,
+    multiplier: f64,
+    max_delay_ms: f64,
+    jitter: f64,
+    rng: SmallRng,
+}
+
+impl Backoff {
+    pub fn new(policy: &RetryPolicy) -> Backoff {
+        Backoff {
+            remaining: policy.max_attempts.saturating_sub(1),
+            next_delay_ms: (policy.initial_delay_ms as f64).min(policy.max_delay_ms as f64),
+            multiplier: policy.multiplier,
+            max_delay_ms: policy.max_delay_ms as f64,
+            jitter: policy.jitter,
+            rng: SmallRng::from_entropy(),
+        }
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let delay_ms = self.next_delay_ms;
+        self.next_delay_ms = (delay_ms * self.multiplier).min(self.max_delay_ms);
+        let factor = match self.jitter > 0.0 {
+            true => 1.0 + self.rng.gen_range(-self.jitter..=self.jitter),
+            false => 1.0,
+        };
+        Some(Duration::from_secs_f64(
+            (delay_ms * factor).max(0.0) / 1000.0,
+        ))
+    }
+}
+
+/// Runs the operation until it succeeds, the attempts of the policy are exhausted,
+/// or it fails with an error `classify` deems not worth repeating, sleeping between the attempts
+pub fn retry<T, E>(
+    policy: &RetryPolicy,
+    mut operation: impl FnMut() -> Result<T, E>,
+    classify: impl Fn(&E) -> Retryable,
+) -> Result<T, RetryError<E>> {
+    let mut backoff = Backoff::new(policy);
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let error = match operation() {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        if classify(&error) == Retryable::GiveUp {
+            return Err(RetryError::GaveUp(error));
+        }
+        match backoff.next() {
+            Some(delay) => {
+                debug!(
+                    "Attempt {attempts} failed, retrying in {} ms",
+                    delay.as_millis()
+                );
+                thread::sleep(delay);
+            }
+            None => return Err(RetryError::Exhausted { attempts, error }),
+        }
+    }
+}
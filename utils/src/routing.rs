@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use data_transfer_objects::{
+    Alert, MotorMonitorParameters, RouteCount, RouteDestination, RouteRule, DEFAULT_ROUTE_LABEL,
+};
+use log::info;
+
+/// Routing of the alerts of a monitor by the rules it was passed with `--route`, counting the
+/// alerts routed by each of them, followed by the ones no rule matched.
+/// Without rules, every alert is sent to the cloud server.
+#[derive(Debug)]
+pub struct Routing {
+    rules: Vec<RouteRule>,
+    routed_alerts: Vec<AtomicU64>,
+}
+
+impl Routing {
+    pub fn new(motor_monitor_parameters: &MotorMonitorParameters) -> Routing {
+        for rule in &motor_monitor_parameters.routing {
+            info!("Routing alerts by {rule}");
+        }
+        Routing {
+            rules: motor_monitor_parameters.routing.clone(),
+            routed_alerts: (0..=motor_monitor_parameters.routing.len())
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+        }
+    }
+
+    /// Destination of the alert according to the first matching rule, counting it for that rule.
+    /// To be called once per alert the monitor raises, whether it is sent or dropped.
+    pub fn route(&self, alert: &Alert) -> RouteDestination {
+        let index = self
+            .rules
+            .iter()
+            .position(|rule| rule.matches(alert.failure))
+            .unwrap_or(self.rules.len());
+        self.routed_alerts[index].fetch_add(1, Ordering::Relaxed);
+        self.rules
+            .get(index)
+            .map_or(RouteDestination::CloudServer, |rule| rule.destination)
+    }
+
+    /// Alerts routed so far, i.e. all alerts the monitor raised
+    pub fn get_sent_alerts(&self) -> u64 {
+        self.routed_alerts
+            .iter()
+            .map(|routed_alerts| routed_alerts.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Alerts routed by each rule, and by none of them under `DEFAULT_ROUTE_LABEL`,
+    /// empty without any rules
+    pub fn get_route_counts(&self) -> Vec<RouteCount> {
+        if self.rules.is_empty() {
+            return vec![];
+        }
+        self.rules
+            .iter()
+            .map(|rule| (rule.label.as_str(), rule.destination))
+            .chain([(DEFAULT_ROUTE_LABEL, RouteDestination::CloudServer)])
+            .zip(&self.routed_alerts)
+            .map(|((label, destination), routed_alerts)| RouteCount {
+                label: label.to_string(),
+                destination,
+                alerts: routed_alerts.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,51 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+
+/// How often the handler thread checks whether the process was interrupted
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set by the signal handler, which may do nothing but that
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the process to terminate with SIGTERM
+pub fn terminate(pid: u32) -> io::Result<()> {
+    // Safety: kill only sends a signal, the pid is one of a child spawned by the caller
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Calls the handler on a thread of its own once the process receives SIGINT or SIGTERM.
+/// The default disposition is restored before, so a second Ctrl-C terminates the process
+/// if the handler hangs.
+pub fn on_interrupt(handler: impl FnOnce() + Send + 'static) {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // Safety: the installed handler only stores to an atomic, which is async-signal-safe
+        let signal_handler = set_interrupted as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(signal, signal_handler) } == libc::SIG_ERR {
+            warn!(
+                "Could not install handler of signal {signal}: {}",
+                io::Error::last_os_error()
+            );
+        }
+    }
+    thread::spawn(move || {
+        while !INTERRUPTED.load(Ordering::SeqCst) {
+            thread::sleep(INTERRUPT_POLL_INTERVAL);
+        }
+        for signal in [libc::SIGINT, libc::SIGTERM] {
+            // Safety: restores the default disposition
+            unsafe { libc::signal(signal, libc::SIG_DFL) };
+        }
+        handler();
+    });
+}
+
+extern "C" fn set_interrupted(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,211 @@
+#[cfg(feature = "otel")]
+use log::info;
+use log::warn;
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{Span as _, TraceContextExt, Tracer as _, TracerProvider as _};
+#[cfg(feature = "otel")]
+use opentelemetry::{Context, KeyValue, Value};
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider, Span};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::Resource;
+
+#[derive(Debug, Clone)]
+pub enum AttributeValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        AttributeValue::Bool(value)
+    }
+}
+
+impl From<u16> for AttributeValue {
+    fn from(value: u16) -> Self {
+        AttributeValue::Int(value as i64)
+    }
+}
+
+impl From<u32> for AttributeValue {
+    fn from(value: u32) -> Self {
+        AttributeValue::Int(value as i64)
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        AttributeValue::Float(value)
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue::Text(value)
+    }
+}
+
+pub type Attributes = Vec<(&'static str, AttributeValue)>;
+
+/// Exporter of the spans of the process, shared by its clones.
+/// Without the `otel` feature, or without an endpoint, it creates no spans, and their attributes
+/// are never computed.
+#[derive(Clone, Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    tracer: Option<(SdkTracerProvider, SdkTracer)>,
+}
+
+/// Span exported to the OpenTelemetry collector of the process, which ends when it is dropped
+pub struct TelemetrySpan {
+    #[cfg(feature = "otel")]
+    span: Option<Span>,
+}
+
+impl TelemetrySpan {
+    pub fn set_attribute(&mut self, key: &'static str, value: impl FnOnce() -> AttributeValue) {
+        #[cfg(feature = "otel")]
+        if let Some(span) = &mut self.span {
+            span.set_attribute(to_key_value((key, value())));
+        }
+        #[cfg(not(feature = "otel"))]
+        let _ = (key, value);
+    }
+}
+
+impl Telemetry {
+    /// Starts exporting the spans of the process to an OpenTelemetry collector via OTLP/HTTP
+    /// (`host:port` or a URL), in batches on a background thread, for the trace waterfall of a single run.
+    /// The spans of the components are not linked across processes, but carry the sensor ids,
+    /// motor ids, timestamps and alert times to correlate them by.
+    #[cfg(feature = "otel")]
+    pub fn new(service_name: &'static str, endpoint: Option<&str>) -> Telemetry {
+        let Some(endpoint) = endpoint else {
+            return Telemetry::default();
+        };
+        let traces_url = get_traces_url(endpoint);
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&traces_url)
+            .build()
+            .unwrap_or_else(|e| panic!("Could not create span exporter for {traces_url}: {e}"));
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(service_name).build())
+            .build();
+        let tracer = provider.tracer(service_name);
+        info!("Exporting spans to {traces_url}");
+        Telemetry {
+            tracer: Some((provider, tracer)),
+        }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    pub fn new(_service_name: &'static str, endpoint: Option<&str>) -> Telemetry {
+        if endpoint.is_some() {
+            warn!("An OpenTelemetry endpoint is configured, but the component is built without the otel feature");
+        }
+        Telemetry::default()
+    }
+
+    pub fn start_span(
+        &self,
+        name: &'static str,
+        attributes: impl FnOnce() -> Attributes,
+    ) -> TelemetrySpan {
+        #[cfg(feature = "otel")]
+        {
+            TelemetrySpan {
+                span: self.tracer.as_ref().map(|(_, tracer)| {
+                    tracer
+                        .span_builder(name)
+                        .with_attributes(to_key_values(attributes()))
+                        .start(tracer)
+                }),
+            }
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = (name, attributes);
+            TelemetrySpan {}
+        }
+    }
+
+    /// Starts a span within the parent one, or a root span if the parent is not exported
+    pub fn start_child_span(
+        &self,
+        parent: &TelemetrySpan,
+        name: &'static str,
+        attributes: impl FnOnce() -> Attributes,
+    ) -> TelemetrySpan {
+        #[cfg(feature = "otel")]
+        {
+            let Some(parent_span) = &parent.span else {
+                return self.start_span(name, attributes);
+            };
+            let context =
+                Context::new().with_remote_span_context(parent_span.span_context().clone());
+            TelemetrySpan {
+                span: self.tracer.as_ref().map(|(_, tracer)| {
+                    tracer
+                        .span_builder(name)
+                        .with_attributes(to_key_values(attributes()))
+                        .start_with_context(tracer, &context)
+                }),
+            }
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = (parent, name, attributes);
+            TelemetrySpan {}
+        }
+    }
+
+    /// Exports the spans which are still buffered, to be called before the process exits
+    pub fn shutdown(&self) {
+        #[cfg(feature = "otel")]
+        if let Some((provider, _)) = &self.tracer {
+            if let Err(e) = provider.shutdown() {
+                warn!("Could not export the remaining spans: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+fn get_traces_url(endpoint: &str) -> String {
+    let url = match endpoint.contains("://") {
+        true => endpoint.to_string(),
+        false => format!("http://{endpoint}"),
+    };
+    match url
+        .split_once("://")
+        .is_some_and(|(_, rest)| rest.contains('/'))
+    {
+        true => url,
+        false => format!("{url}/v1/traces"),
+    }
+}
+
+#[cfg(feature = "otel")]
+fn to_key_values(attributes: Attributes) -> Vec<KeyValue> {
+    attributes.into_iter().map(to_key_value).collect()
+}
+
+#[cfg(feature = "otel")]
+fn to_key_value((key, value): (&'static str, AttributeValue)) -> KeyValue {
+    KeyValue::new(
+        key,
+        match value {
+            AttributeValue::Int(value) => Value::I64(value),
+            AttributeValue::Float(value) => Value::F64(value),
+            AttributeValue::Bool(value) => Value::Bool(value),
+            AttributeValue::Text(value) => Value::from(value),
+        },
+    )
+}
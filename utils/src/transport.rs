@@ -0,0 +1,445 @@
+use std::env;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::{error, info, warn};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+
+use data_transfer_objects::{MotorMonitorParameters, Transport};
+
+use crate::MessageStream;
+
+type Connections = Arc<Mutex<Receiver<io::Result<SensorStream>>>>;
+
+/// Connections the kernel queues until the monitor accepts them, well above the default of 128,
+/// so that the sensors of a large run connecting at once are neither refused nor delayed
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Time between polls of the TCP listener for connections arriving before a deadline
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Path of the Unix domain socket a monitor accepts the sensors of a run on,
+/// next to its TCP listener on the port
+pub fn get_socket_path(run_id: u64, port: u16) -> PathBuf {
+    env::temp_dir().join(format!("motor_monitor_{run_id}_{port}.sock"))
+}
+
+/// Connection of a sensor to a monitor
+pub struct SensorStream {
+    connection: Connection,
+    /// Slot of the connection cap of the listener which accepted it, freed once it is dropped
+    _slot: Option<ConnectionSlot>,
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl From<Connection> for SensorStream {
+    fn from(connection: Connection) -> Self {
+        SensorStream {
+            connection,
+            _slot: None,
+        }
+    }
+}
+
+impl SensorStream {
+    /// Connects to the monitor at the address, over its Unix domain socket if the transport
+    /// allows it for the address
+    pub fn connect(
+        address: SocketAddr,
+        transport: Transport,
+        run_id: u64,
+        timeout: Duration,
+    ) -> io::Result<SensorStream> {
+        let use_socket = match transport {
+            Transport::Tcp => false,
+            Transport::Local => address.ip().is_loopback(),
+            Transport::Uds => true,
+        };
+        match use_socket {
+            true => UnixStream::connect(get_socket_path(run_id, address.port()))
+                .map(|stream| Connection::Unix(stream).into()),
+            false => TcpStream::connect_timeout(&address, timeout)
+                .map(|stream| Connection::Tcp(stream).into()),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match &self.connection {
+            Connection::Tcp(stream) => stream.set_read_timeout(timeout),
+            Connection::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for SensorStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.connection {
+            Connection::Tcp(stream) => stream.read(buf),
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for SensorStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.connection {
+            Connection::Tcp(stream) => stream.write(buf),
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.connection {
+            Connection::Tcp(stream) => stream.flush(),
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+impl MessageStream for SensorStream {
+    fn shutdown_connection(&self) -> io::Result<()> {
+        match &self.connection {
+            Connection::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            Connection::Unix(stream) => stream.shutdown(Shutdown::Both),
+        }
+    }
+}
+
+/// Accepts the connections of the sensors on the sensor bind port, and unless the transport
+/// is TCP, additionally on the Unix domain socket of the run
+pub struct SensorListener {
+    tcp_listener: TcpListener,
+    connections: Option<Connections>,
+    connection_cap: Option<Arc<ConnectionCap>>,
+}
+
+/// Maximum number of sensor connections a monitor handles at the same time.
+/// Further connections are either left queued in the accept backlog until a slot is freed,
+/// or accepted and closed right away.
+struct ConnectionCap {
+    max_connections: usize,
+    reject_excess: bool,
+    open_connections: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl ConnectionCap {
+    /// Takes a slot, waiting for one to be freed if all are taken
+    fn await_slot(self: &Arc<Self>) -> ConnectionSlot {
+        let mut open_connections = self.open_connections.lock().unwrap();
+        if *open_connections >= self.max_connections {
+            warn!(
+                "Reached the cap of {} concurrent sensor connections, queuing the next one",
+                self.max_connections
+            );
+        }
+        while *open_connections >= self.max_connections {
+            open_connections = self.freed.wait(open_connections).unwrap();
+        }
+        *open_connections += 1;
+        ConnectionSlot(Arc::clone(self))
+    }
+
+    /// Takes a slot, if one is free
+    fn try_take_slot(self: &Arc<Self>) -> Option<ConnectionSlot> {
+        let mut open_connections = self.open_connections.lock().unwrap();
+        if *open_connections >= self.max_connections {
+            return None;
+        }
+        *open_connections += 1;
+        Some(ConnectionSlot(Arc::clone(self)))
+    }
+}
+
+struct ConnectionSlot(Arc<ConnectionCap>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        *self.0.open_connections.lock().unwrap() -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
+impl SensorListener {
+    pub fn bind(motor_monitor_parameters: &MotorMonitorParameters) -> io::Result<SensorListener> {
+        let tcp_listener = bind_tcp_listener(motor_monitor_parameters.sensor_bind_port)?;
+        let connection_cap =
+            motor_monitor_parameters
+                .max_sensor_connections
+                .map(|max_connections| {
+                    Arc::new(ConnectionCap {
+                        max_connections,
+                        reject_excess: motor_monitor_parameters.reject_excess_connections,
+                        open_connections: Mutex::new(0),
+                        freed: Condvar::new(),
+                    })
+                });
+        if motor_monitor_parameters.transport == Transport::Tcp {
+            return Ok(SensorListener {
+                tcp_listener,
+                connections: None,
+                connection_cap,
+            });
+        }
+        let socket_path = get_socket_path(
+            motor_monitor_parameters.run_id,
+            motor_monitor_parameters.sensor_listen_address.port(),
+        );
+        // A socket file left behind by a crashed monitor would make the bind fail
+        let _ = std::fs::remove_file(&socket_path);
+        let unix_listener = bind_unix_listener(&socket_path)?;
+        info!("Bound listener on {}", socket_path.display());
+        let (sender, receiver) = channel();
+        let tcp_sender = sender.clone();
+        let accepting_tcp_listener = tcp_listener.try_clone()?;
+        thread::spawn(move || {
+            forward_connections(
+                accepting_tcp_listener
+                    .incoming()
+                    .map(|stream| stream.map(|stream| Connection::Tcp(stream).into())),
+                tcp_sender,
+            )
+        });
+        thread::spawn(move || {
+            forward_connections(
+                unix_listener
+                    .incoming()
+                    .map(|stream| stream.map(|stream| Connection::Unix(stream).into())),
+                sender,
+            )
+        });
+        Ok(SensorListener {
+            tcp_listener,
+            connections: Some(Arc::new(Mutex::new(receiver))),
+            connection_cap,
+        })
+    }
+
+    /// Accepts the next sensor connection within the connection cap, if there is one.
+    /// A connection beyond the cap is either only accepted once a slot is freed, or closed
+    /// right away and returned as a `ConnectionRefused` error.
+    pub fn accept(&self) -> io::Result<SensorStream> {
+        let Some(connection_cap) = &self.connection_cap else {
+            return self.accept_connection();
+        };
+        if !connection_cap.reject_excess {
+            let slot = connection_cap.await_slot();
+            return self.accept_connection().map(|stream| SensorStream {
+                _slot: Some(slot),
+                ..stream
+            });
+        }
+        let stream = self.accept_connection()?;
+        take_slot_or_reject(connection_cap, stream)
+    }
+
+    /// Accepts the next sensor connection arriving before the deadline (since the unix epoch),
+    /// e.g. of a sensor reconnecting mid-run, returning `None` once the deadline passed.
+    /// Instead of waiting for a free slot, a connection beyond the connection cap is closed
+    /// right away and returned as a `ConnectionRefused` error.
+    pub fn accept_before(&self, deadline: Duration) -> io::Result<Option<SensorStream>> {
+        let stream: SensorStream = match &self.connections {
+            None => {
+                self.tcp_listener.set_nonblocking(true)?;
+                loop {
+                    match self.tcp_listener.accept() {
+                        Ok((stream, _)) => {
+                            stream.set_nonblocking(false)?;
+                            break Connection::Tcp(stream).into();
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            if crate::get_now_duration() >= deadline {
+                                return Ok(None);
+                            }
+                            thread::sleep(ACCEPT_POLL_INTERVAL);
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+            Some(connections) => match connections
+                .lock()
+                .expect("Sensor connection lock was poisoned")
+                .recv_timeout(deadline.saturating_sub(crate::get_now_duration()))
+            {
+                Ok(stream) => stream?,
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(RecvTimeoutError::Disconnected) => {
+                    panic!("Sensor listeners stopped accepting connections")
+                }
+            },
+        };
+        match &self.connection_cap {
+            None => Ok(Some(stream)),
+            Some(connection_cap) => take_slot_or_reject(connection_cap, stream).map(Some),
+        }
+    }
+
+    fn accept_connection(&self) -> io::Result<SensorStream> {
+        match &self.connections {
+            None => self
+                .tcp_listener
+                .accept()
+                .map(|(stream, _)| Connection::Tcp(stream).into()),
+            Some(connections) => connections
+                .lock()
+                .expect("Sensor connection lock was poisoned")
+                .recv()
+                .expect("Sensor listeners stopped accepting connections"),
+        }
+    }
+
+    pub fn try_clone(&self) -> io::Result<SensorListener> {
+        Ok(SensorListener {
+            tcp_listener: self.tcp_listener.try_clone()?,
+            connections: self.connections.clone(),
+            connection_cap: self.connection_cap.clone(),
+        })
+    }
+}
+
+fn bind_tcp_listener(port: u16) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP))?;
+    // Like `TcpListener::bind`, so that lingering connections of a previous run do not block the port
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddr::from(([0, 0, 0, 0], port)).into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    Ok(socket.into())
+}
+
+fn bind_unix_listener(path: &Path) -> io::Result<UnixListener> {
+    let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+    socket.bind(&SockAddr::unix(path)?)?;
+    socket.listen(LISTEN_BACKLOG)?;
+    Ok(socket.into())
+}
+
+/// Hands the connection a slot of the connection cap, closing it if all slots are taken
+fn take_slot_or_reject(
+    connection_cap: &Arc<ConnectionCap>,
+    stream: SensorStream,
+) -> io::Result<SensorStream> {
+    match connection_cap.try_take_slot() {
+        Some(slot) => Ok(SensorStream {
+            _slot: Some(slot),
+            ..stream
+        }),
+        None => {
+            warn!(
+                "Reached the cap of {} concurrent sensor connections, rejecting the next one",
+                connection_cap.max_connections
+            );
+            let _ = stream.shutdown_connection();
+            Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!(
+                    "Rejected sensor connection beyond the cap of {}",
+                    connection_cap.max_connections
+                ),
+            ))
+        }
+    }
+}
+
+fn forward_connections(
+    incoming: impl Iterator<Item = io::Result<SensorStream>>,
+    sender: Sender<io::Result<SensorStream>>,
+) {
+    for stream in incoming {
+        if let Err(e) = sender.send(stream) {
+            error!("Could not forward sensor connection: {e}");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sensor listener on an ephemeral port, with its address
+    fn bind_capped_listener(flags: &[&str]) -> (SensorListener, SocketAddr) {
+        let parameters = crate::tests::get_test_monitor_parameters(
+            "127.0.0.1:1".parse().unwrap(),
+            &[&["--max-sensor-connections", "1"], flags].concat(),
+        );
+        let listener = SensorListener::bind(&parameters).unwrap();
+        let port = listener.tcp_listener.local_addr().unwrap().port();
+        (listener, SocketAddr::from(([127, 0, 0, 1], port)))
+    }
+
+    #[test]
+    fn connection_beyond_the_cap_is_queued_until_a_slot_is_freed() {
+        let (listener, address) = bind_capped_listener(&[]);
+        let _first_sensor = TcpStream::connect(address).unwrap();
+        let first_stream = listener.accept().unwrap();
+        let _second_sensor = TcpStream::connect(address).unwrap();
+        let (accepted_sender, accepted) = channel();
+        let queued_listener = listener.try_clone().unwrap();
+        thread::spawn(move || accepted_sender.send(queued_listener.accept().is_ok()));
+        assert!(accepted.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(first_stream);
+        assert_eq!(accepted.recv_timeout(Duration::from_secs(5)), Ok(true));
+    }
+
+    #[test]
+    fn connection_beyond_the_cap_is_rejected() {
+        let (listener, address) = bind_capped_listener(&["--reject-excess-connections"]);
+        let _first_sensor = TcpStream::connect(address).unwrap();
+        let first_stream = listener.accept().unwrap();
+        let mut second_sensor = TcpStream::connect(address).unwrap();
+        let error = listener.accept().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        // The rejected sensor sees its connection closed
+        second_sensor
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(second_sensor.read(&mut [0; 1]).unwrap(), 0);
+        drop(first_stream);
+        let _third_sensor = TcpStream::connect(address).unwrap();
+        assert!(listener.accept().is_ok());
+    }
+
+    #[test]
+    fn simultaneous_sensors_are_accepted_within_the_jitter_window() {
+        const SENSORS: u32 = 128;
+        const MAX_JITTER_MS: u64 = 500;
+        let parameters =
+            crate::tests::get_test_monitor_parameters("127.0.0.1:1".parse().unwrap(), &[]);
+        let listener = SensorListener::bind(&parameters).unwrap();
+        let port = listener.tcp_listener.local_addr().unwrap().port();
+        let start = std::time::Instant::now();
+        let sensors: Vec<_> = (0..SENSORS)
+            .map(|sensor_id| {
+                thread::spawn(move || {
+                    thread::sleep(crate::get_connect_jitter(sensor_id, MAX_JITTER_MS));
+                    TcpStream::connect_timeout(
+                        &SocketAddr::from(([127, 0, 0, 1], port)),
+                        Duration::from_secs(5),
+                    )
+                })
+            })
+            .collect();
+        let streams: Vec<_> = (0..SENSORS).map(|_| listener.accept().unwrap()).collect();
+        let elapsed = start.elapsed();
+        assert_eq!(streams.len(), SENSORS as usize);
+        assert!(
+            elapsed < Duration::from_millis(MAX_JITTER_MS + 500),
+            "Accepting took {elapsed:?}"
+        );
+        for sensor in sensors {
+            assert!(sensor.join().unwrap().is_ok());
+        }
+    }
+}
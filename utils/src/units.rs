@@ -0,0 +1,148 @@
+//! Units of the sensor readings and the quantities the rules derive from them.
+//! Readings are wrapped once their channel is known, so that passing e.g. a torque
+//! where a rotational speed is expected does not compile.
+//!
+//! ```
+//! use utils::units::{Kelvin, MotorReadings, NewtonMeter, Rpm};
+//!
+//! let readings = MotorReadings {
+//!     air_temperature: Kelvin(300.0),
+//!     process_temperature: Kelvin(310.0),
+//!     rotational_speed: Rpm(1500.0),
+//!     torque: NewtonMeter(40.0),
+//! };
+//! assert!((readings.get_power().0 - 6283.2).abs() < 0.1);
+//! ```
+//!
+//! A rotational speed read into the torque channel is rejected:
+//!
+//! ```compile_fail
+//! use utils::units::{Kelvin, MotorReadings, Rpm};
+//!
+//! let readings = MotorReadings {
+//!     air_temperature: Kelvin(300.0),
+//!     process_temperature: Kelvin(310.0),
+//!     rotational_speed: Rpm(1500.0),
+//!     torque: Rpm(1500.0),
+//! };
+//! ```
+
+use core::f64::consts::PI;
+use core::ops::{Mul, Sub};
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Kelvin(pub f64);
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Rpm(pub f64);
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct RadPerSecond(pub f64);
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct NewtonMeter(pub f64);
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Watt(pub f64);
+
+/// Tool wear, in seconds of use weighted by the wear rate.
+/// The dataset the rules stem from measures the wear in minutes, but the benchmarks run for
+/// minutes only, so the wear accrues in seconds and the overstrain limit is in [`NewtonMeterSeconds`].
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Seconds(pub f64);
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct NewtonMeterSeconds(pub f64);
+
+/// Readings of the four sensors of a motor
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MotorReadings {
+    pub air_temperature: Kelvin,
+    pub process_temperature: Kelvin,
+    pub rotational_speed: Rpm,
+    pub torque: NewtonMeter,
+}
+
+impl MotorReadings {
+    /// Wraps the readings of the channels, indexed by the lower two bits of their sensor ids
+    pub fn from_channels(readings: [f64; 4]) -> MotorReadings {
+        MotorReadings {
+            air_temperature: Kelvin(readings[0]),
+            process_temperature: Kelvin(readings[1]),
+            rotational_speed: Rpm(readings[2]),
+            torque: NewtonMeter(readings[3]),
+        }
+    }
+
+    pub fn get_temperature_difference(&self) -> Kelvin {
+        self.air_temperature - self.process_temperature
+    }
+
+    pub fn get_power(&self) -> Watt {
+        self.torque * self.rotational_speed.to_rad_per_second()
+    }
+}
+
+impl Kelvin {
+    pub fn abs(self) -> Kelvin {
+        if self.0 < 0.0 {
+            Kelvin(-self.0)
+        } else {
+            self
+        }
+    }
+}
+
+impl Sub for Kelvin {
+    type Output = Kelvin;
+
+    fn sub(self, rhs: Kelvin) -> Kelvin {
+        Kelvin(self.0 - rhs.0)
+    }
+}
+
+impl Rpm {
+    pub fn to_rad_per_second(self) -> RadPerSecond {
+        RadPerSecond(self.0 / 60.0 * PI * 2.0)
+    }
+}
+
+impl Mul<RadPerSecond> for NewtonMeter {
+    type Output = Watt;
+
+    fn mul(self, rhs: RadPerSecond) -> Watt {
+        Watt(self.0 * rhs.0)
+    }
+}
+
+impl Mul<NewtonMeter> for Seconds {
+    type Output = NewtonMeterSeconds;
+
+    fn mul(self, rhs: NewtonMeter) -> NewtonMeterSeconds {
+        NewtonMeterSeconds(self.0 * rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readings_are_wrapped_by_channel() {
+        let readings = MotorReadings::from_channels([300.0, 310.0, 1500.0, 40.0]);
+        assert_eq!(readings.air_temperature, Kelvin(300.0));
+        assert_eq!(readings.process_temperature, Kelvin(310.0));
+        assert_eq!(readings.rotational_speed, Rpm(1500.0));
+        assert_eq!(readings.torque, NewtonMeter(40.0));
+        assert_eq!(readings.get_temperature_difference(), Kelvin(-10.0));
+        assert!((readings.get_power().0 - 40.0 * 1500.0 / 60.0 * PI * 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn strain_is_wear_seconds_times_torque() {
+        assert_eq!(
+            Seconds(275.0) * NewtonMeter(40.0),
+            NewtonMeterSeconds(11000.0)
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+use std::{process, thread};
+
+use log::{error, warn};
+use procfs::process::Process;
+
+use data_transfer_objects::MotorMonitorParameters;
+
+use crate::benchmark::RunEnd;
+use crate::crash::EarlyExit;
+
+/// Exit code of a monitor which exceeded its memory limit, after writing its benchmark data
+pub const MEMORY_LIMIT_EXIT_CODE: i32 = 71;
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Starts a thread which checks the resident set size of the monitor against its memory limit,
+/// if it has one. Once it is exceeded, the benchmark data is written flagged as such and the
+/// monitor exits with `MEMORY_LIMIT_EXIT_CODE`, instead of being killed without any data.
+pub fn start_memory_watchdog(
+    motor_monitor_parameters: &MotorMonitorParameters,
+    early_exit: EarlyExit,
+) {
+    let Some(memory_limit_mb) = motor_monitor_parameters.memory_limit_mb else {
+        return;
+    };
+    watch_memory(memory_limit_mb, early_exit, |exit_code| {
+        process::exit(exit_code)
+    });
+}
+
+/// Starts the thread checking the resident set size, which calls `exit` with
+/// `MEMORY_LIMIT_EXIT_CODE` after writing the benchmark data once the limit is exceeded
+fn watch_memory(
+    memory_limit_mb: u64,
+    early_exit: EarlyExit,
+    exit: impl FnOnce(i32) + Send + 'static,
+) {
+    let memory_limit_kb = memory_limit_mb * 1024;
+    thread::spawn(move || {
+        let me = Process::myself().expect("Could not get process info handle");
+        loop {
+            match me.status().map(|status| status.vmrss) {
+                Ok(Some(resident_set_size_kb)) if resident_set_size_kb > memory_limit_kb => {
+                    if !early_exit.claim() {
+                        return;
+                    }
+                    error!(
+                        "Resident set size of {resident_set_size_kb} kB exceeds the memory limit of {memory_limit_mb} MB, exiting"
+                    );
+                    early_exit.save_benchmark_readings(RunEnd::ExceededMemoryLimit);
+                    exit(MEMORY_LIMIT_EXIT_CODE);
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Could not get resident set size: {e}"),
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::mpsc;
+    use std::{env, fs};
+
+    use data_transfer_objects::BenchmarkData;
+
+    use crate::benchmark::RunStatistics;
+
+    use super::*;
+
+    /// Resident set size of the test process (in MB)
+    fn get_resident_set_size_mb() -> u64 {
+        Process::myself().unwrap().status().unwrap().vmrss.unwrap() / 1024
+    }
+
+    #[test]
+    fn exceeding_the_memory_limit_writes_the_benchmark_data_and_exits() {
+        let benchmark_file = env::temp_dir().join(format!("utils_watchdog_{}", process::id()));
+        let memory_limit_mb = get_resident_set_size_mb() + 64;
+        let cloud_server_address: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let motor_monitor_parameters = crate::tests::get_test_monitor_parameters(
+            cloud_server_address,
+            &["--benchmark-output", &benchmark_file.to_string_lossy()],
+        );
+        let early_exit = EarlyExit::new(
+            &motor_monitor_parameters,
+            &RunStatistics::new(&motor_monitor_parameters),
+        );
+        let (exit_tx, exit_rx) = mpsc::channel();
+        watch_memory(memory_limit_mb, early_exit, move |exit_code| {
+            exit_tx.send(exit_code).unwrap()
+        });
+        // Below the limit, the watchdog keeps checking
+        assert!(exit_rx.recv_timeout(CHECK_INTERVAL * 3).is_err());
+        let allocation = vec![1u8; 256 * 1024 * 1024];
+        let exit_code = exit_rx.recv_timeout(Duration::from_secs(5));
+        drop(allocation);
+        assert_eq!(exit_code, Ok(MEMORY_LIMIT_EXIT_CODE));
+        let mut frame = fs::read(&benchmark_file).unwrap();
+        let benchmark_data: BenchmarkData = crate::decode_object(&mut frame).unwrap();
+        fs::remove_file(&benchmark_file).unwrap();
+        assert!(benchmark_data.exceeded_memory_limit);
+        assert!(!benchmark_data.crashed);
+    }
+}
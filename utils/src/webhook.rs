@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+#[cfg(feature = "alert_webhooks")]
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+#[cfg(feature = "alert_webhooks")]
+use std::thread;
+
+#[cfg(feature = "alert_webhooks")]
+use log::debug;
+use log::warn;
+
+use data_transfer_objects::{AlertWebhook, DetailedAlert};
+
+/// Posts the alerts of motors with a configured webhook as JSON to its URL.
+/// The requests are made on a separate thread, so that a slow endpoint does not hold back
+/// the processing of the monitor; alerts of motors without a webhook are ignored.
+#[derive(Clone)]
+pub struct AlertWebhooks {
+    urls: Arc<HashMap<u16, String>>,
+    sender: Option<Sender<(String, DetailedAlert)>>,
+}
+
+impl AlertWebhooks {
+    /// Starts posting the alerts, with an additional `averages` field holding the averages of
+    /// their windows if the monitor sends detailed alerts
+    pub fn start(alert_webhooks: &[AlertWebhook], detailed_alerts: bool) -> AlertWebhooks {
+        let urls: HashMap<u16, String> = alert_webhooks
+            .iter()
+            .map(|alert_webhook| (alert_webhook.motor_id, alert_webhook.url.clone()))
+            .collect();
+        let sender = match urls.is_empty() {
+            true => None,
+            false => start_poster(detailed_alerts),
+        };
+        AlertWebhooks {
+            urls: Arc::new(urls),
+            sender,
+        }
+    }
+
+    pub fn notify(&self, detailed_alert: &DetailedAlert) {
+        if let (Some(sender), Some(url)) =
+            (&self.sender, self.urls.get(&detailed_alert.alert.motor_id))
+        {
+            // The poster only stops once all senders are dropped
+            let _ = sender.send((url.clone(), detailed_alert.clone()));
+        }
+    }
+}
+
+#[cfg(feature = "alert_webhooks")]
+fn start_poster(detailed_alerts: bool) -> Option<Sender<(String, DetailedAlert)>> {
+    let (sender, receiver) = channel::<(String, DetailedAlert)>();
+    thread::spawn(move || {
+        for (url, detailed_alert) in receiver {
+            let alert = &detailed_alert.alert;
+            match ureq::post(&url).send_json(get_payload(&detailed_alert, detailed_alerts)) {
+                Ok(_) => debug!("Posted {alert:?} to {url}"),
+                Err(e) => warn!("Could not post {alert:?} to {url}: {e}"),
+            }
+        }
+    });
+    Some(sender)
+}
+
+/// The alert as JSON object, with the averages of its windows added if there are any to send
+#[cfg(feature = "alert_webhooks")]
+fn get_payload(detailed_alert: &DetailedAlert, detailed_alerts: bool) -> serde_json::Value {
+    let mut payload =
+        serde_json::to_value(&detailed_alert.alert).expect("Could not convert alert to JSON");
+    if let (true, Some(averages), Some(fields)) = (
+        detailed_alerts,
+        detailed_alert.averages,
+        payload.as_object_mut(),
+    ) {
+        fields.insert(
+            "averages".to_string(),
+            serde_json::to_value(averages).expect("Could not convert window averages to JSON"),
+        );
+    }
+    payload
+}
+
+#[cfg(not(feature = "alert_webhooks"))]
+fn start_poster(_detailed_alerts: bool) -> Option<Sender<(String, DetailedAlert)>> {
+    warn!("Alert webhooks are configured, but the monitor is built without the alert_webhooks feature");
+    None
+}
+
+#[cfg(all(test, feature = "alert_webhooks"))]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::mpsc::Receiver;
+    use std::time::Duration;
+
+    use data_transfer_objects::{Alert, MotorFailure};
+
+    use super::*;
+
+    /// Mock HTTP server, which answers every request with 200 and passes on its path and body
+    fn spawn_http_server() -> (SocketAddr, Receiver<(String, serde_json::Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut request_line = String::new();
+                stream.read_line(&mut request_line).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap().to_string();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    stream.read_line(&mut header).unwrap();
+                    if header == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                stream.read_exact(&mut body).unwrap();
+                stream
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                let _ = sender.send((path, serde_json::from_slice(&body).unwrap()));
+            }
+        });
+        (address, receiver)
+    }
+
+    fn get_alert(motor_id: u16) -> DetailedAlert {
+        DetailedAlert::from(Alert {
+            time: 12.5,
+            motor_id,
+            failure: MotorFailure::PowerFailure,
+            fingerprint: None,
+            cleared: false,
+            sampled_messages: vec![],
+        })
+    }
+
+    #[test]
+    fn webhook_fires_only_for_its_motor() {
+        let (address, requests) = spawn_http_server();
+        let alert_webhooks = AlertWebhooks::start(
+            &[AlertWebhook {
+                motor_id: 1,
+                url: format!("http://{address}/motor_1"),
+            }],
+            false,
+        );
+        for motor_id in [0, 1, 2] {
+            alert_webhooks.notify(&get_alert(motor_id));
+        }
+        let (path, payload) = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(path, "/motor_1");
+        assert_eq!(payload["motor_id"], 1);
+        assert_eq!(payload["failure"], "PowerFailure");
+        assert!(requests.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use log::{debug, log_enabled, Level};
+
+/// Whether the windows of the averages should be logged, which needs the monitor to be built with
+/// the `window_diagnostics` feature and debug logging to be enabled
+pub fn is_enabled() -> bool {
+    cfg!(feature = "window_diagnostics") && log_enabled!(Level::Debug)
+}
+
+/// Logs the bounds of the window (in s since the epoch) an average of a sensor was computed over,
+/// with the timestamps of the readings it included, in the same format for all monitors,
+/// so that the windowing of two monitors can be compared line by line
+pub fn log_window(
+    motor_id: u32,
+    sensor_id: u32,
+    bounds: (f64, f64),
+    average: f64,
+    timestamps: &[f64],
+) {
+    debug!(
+        "{}",
+        format_window(motor_id, sensor_id, bounds, average, timestamps)
+    );
+}
+
+pub fn format_window(
+    motor_id: u32,
+    sensor_id: u32,
+    (window_start, window_end): (f64, f64),
+    average: f64,
+    timestamps: &[f64],
+) -> String {
+    format!(
+        "Window of sensor {sensor_id} of motor {motor_id}: [{window_start:.3}, {window_end:.3}] ({:.3} s), average {average}, {} samples {timestamps:?}",
+        window_end - window_start,
+        timestamps.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_is_formatted_with_its_bounds_and_length() {
+        assert_eq!(
+            format_window(1, 2, (3.4, 4.9), 20.5, &[4.0, 4.5]),
+            "Window of sensor 2 of motor 1: [3.400, 4.900] (1.500 s), average 20.5, 2 samples [4.0, 4.5]"
+        );
+    }
+}
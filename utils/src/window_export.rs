@@ -0,0 +1,144 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+
+use data_transfer_objects::MotorMonitorParameters;
+use log::{error, info};
+
+use crate::disk_writer::DiskWriter;
+use crate::units::{Kelvin, MotorReadings, NewtonMeter, Rpm, Watt};
+
+const HEADER: &str = "motor_id,window_end,temperature_difference,rotational_speed,power,torque";
+
+/// File the aggregates of the evaluated windows are exported to, if the monitor was passed one
+/// with `--window-export`; without it, the exports do nothing
+pub struct WindowExport {
+    writer: Option<Mutex<DiskWriter>>,
+}
+
+impl WindowExport {
+    /// Creates (or truncates) the export file, if the monitor was passed one
+    pub fn new(motor_monitor_parameters: &MotorMonitorParameters) -> WindowExport {
+        let Some(path) = &motor_monitor_parameters.window_export else {
+            return WindowExport { writer: None };
+        };
+        let path = Path::new(path);
+        let file = File::create(path)
+            .unwrap_or_else(|e| panic!("Could not create window export {}: {e}", path.display()));
+        let mut writer = DiskWriter::new(path, file, Arc::new(AtomicU64::new(0)));
+        writeln!(writer, "{HEADER}").expect("Could not write window export header");
+        info!(
+            "Exporting the aggregates of the windows to {}",
+            path.display()
+        );
+        WindowExport {
+            writer: Some(Mutex::new(writer)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Exports the aggregates of a window, derived from the averages of the sensors of the motor
+    /// the same way the rules derive them
+    pub fn export_averages(&self, motor_id: u32, window_end: f64, readings: MotorReadings) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.export(
+            motor_id,
+            window_end,
+            readings.get_temperature_difference(),
+            readings.rotational_speed,
+            readings.get_power(),
+            readings.torque,
+        );
+    }
+
+    /// Exports the aggregates the rules of a motor were evaluated on, with the time of the latest
+    /// reading in the window (in s since the epoch)
+    pub fn export(
+        &self,
+        motor_id: u32,
+        window_end: f64,
+        temperature_difference: Kelvin,
+        rotational_speed: Rpm,
+        power: Watt,
+        torque: NewtonMeter,
+    ) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let mut writer = writer.lock().expect("Could not lock window export");
+        if let Err(e) = writeln!(
+            writer,
+            "{motor_id},{window_end},{},{},{},{}",
+            temperature_difference.0, rotational_speed.0, power.0, torque.0
+        ) {
+            error!("Could not export window of motor {motor_id}: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn windows_are_exported_as_aggregate_rows() {
+        let path = env::temp_dir().join(format!("window_export_{}.csv", process::id()));
+        let window_export_path = path.display().to_string();
+        let cloud_server_address: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let window_export = WindowExport::new(&crate::tests::get_test_monitor_parameters(
+            cloud_server_address,
+            &["--window-export", &window_export_path],
+        ));
+        assert!(window_export.is_enabled());
+        window_export.export_averages(
+            0,
+            10.5,
+            MotorReadings::from_channels([300.0, 310.0, 1000.0, 50.0]),
+        );
+        window_export.export_averages(
+            3,
+            11.0,
+            MotorReadings::from_channels([301.0, 309.5, 2000.0, 10.0]),
+        );
+        let rows = fs::read_to_string(&path).unwrap();
+        let rows: Vec<Vec<f64>> = rows
+            .lines()
+            .skip(1)
+            .map(|row| row.split(',').map(|value| value.parse().unwrap()).collect())
+            .collect();
+        let expected_rows = [
+            [
+                0.0,
+                10.5,
+                -10.0,
+                1000.0,
+                50.0 * 1000.0 * std::f64::consts::PI / 30.0,
+                50.0,
+            ],
+            [
+                3.0,
+                11.0,
+                -8.5,
+                2000.0,
+                10.0 * 2000.0 * std::f64::consts::PI / 30.0,
+                10.0,
+            ],
+        ];
+        assert_eq!(rows.len(), expected_rows.len());
+        for (row, expected_row) in rows.iter().zip(expected_rows) {
+            for (value, expected_value) in row.iter().zip(expected_row) {
+                assert!((value - expected_value).abs() < 1e-6, "{row:?}");
+            }
+        }
+        fs::remove_file(path).unwrap();
+    }
+}